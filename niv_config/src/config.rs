@@ -1,6 +1,7 @@
 use crate::error::{ConfigError, ConfigResult};
 use crate::extensions::ExtensionManagerConfig;
 use crate::keybindings::KeyBindingConfig;
+use crate::platform::{current_os, resolve_sections};
 use crate::settings::EditorSettings;
 use crate::toml_parser::{TomlParser, TomlValue};
 use crate::ui::UiSettings;
@@ -42,9 +43,16 @@ impl Config {
         Self::from_toml_str(&content)
     }
 
-    /// Load configuration from TOML string
+    /// Load configuration from TOML string, with the sections for other
+    /// operating systems left out
     pub fn from_toml_str(content: &str) -> ConfigResult<Self> {
-        let values = TomlParser::parse(content)?;
+        Self::from_toml_str_for_os(content, current_os())
+    }
+
+    /// Load configuration from TOML string as it applies on `os`
+    /// (`"windows"`, `"macos"` or `"linux"`)
+    pub fn from_toml_str_for_os(content: &str, os: &str) -> ConfigResult<Self> {
+        let values = resolve_sections(TomlParser::parse_sections(content)?, os)?;
 
         Ok(Self {
             editor: EditorSettings::from_toml(&values)?,
@@ -313,6 +313,8 @@ pub enum ExtensionHook {
     Startup,
     /// On editor shutdown
     Shutdown,
+    /// After no key was pressed for `updatetime`
+    CursorHold,
     /// On command execution
     Command(String),
 }
@@ -1,6 +1,7 @@
 use crate::error::ConfigResult;
 use crate::toml_parser::TomlValue;
 use std::collections::HashMap;
+use std::fmt;
 
 /// Key modifier flags
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -129,6 +130,52 @@ impl KeyBinding {
     }
 }
 
+/// Written the way config files spell it, e.g. `Ctrl+s` or `F1`
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let modifiers = [
+            (self.modifiers.ctrl, "Ctrl+"),
+            (self.modifiers.alt, "Alt+"),
+            (self.modifiers.shift, "Shift+"),
+            (self.modifiers.meta, "Meta+"),
+        ];
+        for (_, prefix) in modifiers.iter().filter(|(set, _)| *set) {
+            f.write_str(prefix)?;
+        }
+        let name = match &self.key {
+            KeyCode::Char(ch) => return write!(f, "{}", ch),
+            KeyCode::F1 => "F1",
+            KeyCode::F2 => "F2",
+            KeyCode::F3 => "F3",
+            KeyCode::F4 => "F4",
+            KeyCode::F5 => "F5",
+            KeyCode::F6 => "F6",
+            KeyCode::F7 => "F7",
+            KeyCode::F8 => "F8",
+            KeyCode::F9 => "F9",
+            KeyCode::F10 => "F10",
+            KeyCode::F11 => "F11",
+            KeyCode::F12 => "F12",
+            KeyCode::Escape => "Esc",
+            KeyCode::Enter => "Enter",
+            KeyCode::Tab => "Tab",
+            KeyCode::Backspace => "Backspace",
+            KeyCode::Delete => "Delete",
+            KeyCode::Insert => "Insert",
+            KeyCode::Home => "Home",
+            KeyCode::End => "End",
+            KeyCode::PageUp => "PageUp",
+            KeyCode::PageDown => "PageDown",
+            KeyCode::ArrowUp => "Up",
+            KeyCode::ArrowDown => "Down",
+            KeyCode::ArrowLeft => "Left",
+            KeyCode::ArrowRight => "Right",
+            KeyCode::Space => "Space",
+        };
+        f.write_str(name)
+    }
+}
+
 /// Editor command/action
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum EditorCommand {
@@ -190,6 +237,152 @@ pub enum EditorCommand {
     Custom(String),
 }
 
+impl EditorCommand {
+    /// Every command except `Custom`
+    pub const ALL: &[EditorCommand] = &[
+        EditorCommand::MoveUp,
+        EditorCommand::MoveDown,
+        EditorCommand::MoveLeft,
+        EditorCommand::MoveRight,
+        EditorCommand::MoveLineStart,
+        EditorCommand::MoveLineEnd,
+        EditorCommand::MovePageUp,
+        EditorCommand::MovePageDown,
+        EditorCommand::MoveWordNext,
+        EditorCommand::MoveWordPrev,
+        EditorCommand::MoveToLine,
+        EditorCommand::Insert,
+        EditorCommand::InsertLineAbove,
+        EditorCommand::InsertLineBelow,
+        EditorCommand::Delete,
+        EditorCommand::DeleteLine,
+        EditorCommand::DeleteWord,
+        EditorCommand::DeleteToEnd,
+        EditorCommand::Undo,
+        EditorCommand::Redo,
+        EditorCommand::Copy,
+        EditorCommand::Cut,
+        EditorCommand::Paste,
+        EditorCommand::Search,
+        EditorCommand::SearchNext,
+        EditorCommand::SearchPrev,
+        EditorCommand::Replace,
+        EditorCommand::Save,
+        EditorCommand::SaveAs,
+        EditorCommand::Open,
+        EditorCommand::New,
+        EditorCommand::Quit,
+        EditorCommand::ForceQuit,
+        EditorCommand::SplitVertical,
+        EditorCommand::SplitHorizontal,
+        EditorCommand::CloseSplit,
+        EditorCommand::NextSplit,
+        EditorCommand::PrevSplit,
+        EditorCommand::NormalMode,
+        EditorCommand::InsertMode,
+        EditorCommand::VisualMode,
+        EditorCommand::CommandMode,
+    ];
+
+    /// Name used in `[keybindings]`; for `Custom`, the ex command it runs
+    pub fn name(&self) -> &str {
+        match self {
+            EditorCommand::MoveUp => "move_up",
+            EditorCommand::MoveDown => "move_down",
+            EditorCommand::MoveLeft => "move_left",
+            EditorCommand::MoveRight => "move_right",
+            EditorCommand::MoveLineStart => "move_line_start",
+            EditorCommand::MoveLineEnd => "move_line_end",
+            EditorCommand::MovePageUp => "move_page_up",
+            EditorCommand::MovePageDown => "move_page_down",
+            EditorCommand::MoveWordNext => "move_word_next",
+            EditorCommand::MoveWordPrev => "move_word_prev",
+            EditorCommand::MoveToLine => "move_to_line",
+            EditorCommand::Insert => "insert",
+            EditorCommand::InsertLineAbove => "insert_line_above",
+            EditorCommand::InsertLineBelow => "insert_line_below",
+            EditorCommand::Delete => "delete",
+            EditorCommand::DeleteLine => "delete_line",
+            EditorCommand::DeleteWord => "delete_word",
+            EditorCommand::DeleteToEnd => "delete_to_end",
+            EditorCommand::Undo => "undo",
+            EditorCommand::Redo => "redo",
+            EditorCommand::Copy => "copy",
+            EditorCommand::Cut => "cut",
+            EditorCommand::Paste => "paste",
+            EditorCommand::Search => "search",
+            EditorCommand::SearchNext => "search_next",
+            EditorCommand::SearchPrev => "search_prev",
+            EditorCommand::Replace => "replace",
+            EditorCommand::Save => "save",
+            EditorCommand::SaveAs => "save_as",
+            EditorCommand::Open => "open",
+            EditorCommand::New => "new",
+            EditorCommand::Quit => "quit",
+            EditorCommand::ForceQuit => "force_quit",
+            EditorCommand::SplitVertical => "split_vertical",
+            EditorCommand::SplitHorizontal => "split_horizontal",
+            EditorCommand::CloseSplit => "close_split",
+            EditorCommand::NextSplit => "next_split",
+            EditorCommand::PrevSplit => "prev_split",
+            EditorCommand::NormalMode => "normal_mode",
+            EditorCommand::InsertMode => "insert_mode",
+            EditorCommand::VisualMode => "visual_mode",
+            EditorCommand::CommandMode => "command_mode",
+            EditorCommand::Custom(command) => command,
+        }
+    }
+
+    /// One-line description, for listings such as the command palette
+    pub fn description(&self) -> &'static str {
+        match self {
+            EditorCommand::MoveUp => "Move the cursor up a line",
+            EditorCommand::MoveDown => "Move the cursor down a line",
+            EditorCommand::MoveLeft => "Move the cursor left",
+            EditorCommand::MoveRight => "Move the cursor right",
+            EditorCommand::MoveLineStart => "Move to the start of the line",
+            EditorCommand::MoveLineEnd => "Move to the end of the line",
+            EditorCommand::MovePageUp => "Scroll up",
+            EditorCommand::MovePageDown => "Scroll down",
+            EditorCommand::MoveWordNext => "Move to the next word",
+            EditorCommand::MoveWordPrev => "Move to the previous word",
+            EditorCommand::MoveToLine => "Go to a line",
+            EditorCommand::Insert => "Insert before the cursor",
+            EditorCommand::InsertLineAbove => "Open a line above and insert",
+            EditorCommand::InsertLineBelow => "Open a line below and insert",
+            EditorCommand::Delete => "Delete the character under the cursor",
+            EditorCommand::DeleteLine => "Delete the current line",
+            EditorCommand::DeleteWord => "Delete to the next word",
+            EditorCommand::DeleteToEnd => "Delete to the end of the line",
+            EditorCommand::Undo => "Undo the last change",
+            EditorCommand::Redo => "Redo the last undone change",
+            EditorCommand::Copy => "Copy the selection",
+            EditorCommand::Cut => "Cut the selection",
+            EditorCommand::Paste => "Paste after the cursor",
+            EditorCommand::Search => "Search forward",
+            EditorCommand::SearchNext => "Go to the next match",
+            EditorCommand::SearchPrev => "Go to the previous match",
+            EditorCommand::Replace => "Search and replace",
+            EditorCommand::Save => "Write the current file",
+            EditorCommand::SaveAs => "Write to another file",
+            EditorCommand::Open => "Open a file",
+            EditorCommand::New => "Start a new buffer",
+            EditorCommand::Quit => "Quit",
+            EditorCommand::ForceQuit => "Quit, discarding changes",
+            EditorCommand::SplitVertical => "Split the window vertically",
+            EditorCommand::SplitHorizontal => "Split the window horizontally",
+            EditorCommand::CloseSplit => "Close the current split",
+            EditorCommand::NextSplit => "Go to the next split",
+            EditorCommand::PrevSplit => "Go to the previous split",
+            EditorCommand::NormalMode => "Return to normal mode",
+            EditorCommand::InsertMode => "Enter insert mode",
+            EditorCommand::VisualMode => "Enter visual mode",
+            EditorCommand::CommandMode => "Enter an ex command",
+            EditorCommand::Custom(_) => "Run an ex command",
+        }
+    }
+}
+
 /// Keybinding configuration
 #[derive(Debug, Clone)]
 pub struct KeyBindingConfig {
@@ -353,6 +546,19 @@ impl KeyBindingConfig {
         );
     }
 
+    /// Keys bound to `command` in normal mode or globally, sorted
+    pub fn normal_bindings_for(&self, command: &EditorCommand) -> Vec<&KeyBinding> {
+        let mut keys: Vec<&KeyBinding> = self
+            .normal
+            .iter()
+            .chain(&self.global)
+            .filter(|(_, bound)| *bound == command)
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort_by_key(|key| key.to_string());
+        keys
+    }
+
     /// Parse keybinding from string (e.g., "Ctrl+S", "F1", "g")
     pub fn parse_keybinding(key_str: &str) -> ConfigResult<KeyBinding> {
         let parts: Vec<&str> = key_str.split('+').collect();
@@ -431,11 +637,11 @@ impl KeyBindingConfig {
         let mut config = Self::default();
 
         // Load keybindings for each mode
-        Self::load_mode_bindings(&mut config.normal, values, "keybindings.normal")?;
-        Self::load_mode_bindings(&mut config.insert, values, "keybindings.insert")?;
-        Self::load_mode_bindings(&mut config.visual, values, "keybindings.visual")?;
-        Self::load_mode_bindings(&mut config.command, values, "keybindings.command")?;
-        Self::load_mode_bindings(&mut config.global, values, "keybindings.global")?;
+        Self::load_mode_bindings(&mut config.normal, values, "keybindings.normal.")?;
+        Self::load_mode_bindings(&mut config.insert, values, "keybindings.insert.")?;
+        Self::load_mode_bindings(&mut config.visual, values, "keybindings.visual.")?;
+        Self::load_mode_bindings(&mut config.command, values, "keybindings.command.")?;
+        Self::load_mode_bindings(&mut config.global, values, "keybindings.global.")?;
 
         Ok(config)
     }
@@ -5,6 +5,7 @@ pub mod error;
 pub mod extensions;
 pub mod keybindings;
 pub mod loader;
+pub mod platform;
 pub mod settings;
 pub mod toml_parser;
 pub mod ui;
@@ -14,6 +15,7 @@ pub use error::*;
 pub use extensions::*;
 pub use keybindings::*;
 pub use loader::*;
+pub use platform::*;
 pub use settings::*;
 pub use toml_parser::*;
 pub use ui::*;
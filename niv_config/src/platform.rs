@@ -0,0 +1,188 @@
+use crate::error::{ConfigError, ConfigResult};
+use crate::toml_parser::{TomlSection, TomlValue};
+use std::collections::HashMap;
+
+/// Operating systems that config sections can be limited to
+pub const OS_NAMES: [&str; 3] = ["windows", "macos", "linux"];
+
+/// Name of the operating system the editor runs on, as used in config
+/// sections and `when` guards
+pub fn current_os() -> &'static str {
+    std::env::consts::OS
+}
+
+/// Flatten config sections to dotted keys for the operating system `os`.
+///
+/// Sections under `[windows]`, `[macos]` or `[linux]` (such as
+/// `[macos.keybindings]`) apply only on that OS, with the OS name dropped
+/// from their keys. A section with a `when = "os == 'windows'"` (or `!=`)
+/// guard applies only when it holds, on top of any OS restriction from its
+/// name. Sections limited to an OS are merged after the others, so their
+/// values win wherever they appear in the file.
+pub fn resolve_sections(
+    sections: Vec<TomlSection>,
+    os: &str,
+) -> ConfigResult<HashMap<String, TomlValue>> {
+    let mut general = HashMap::new();
+    let mut specific = HashMap::new();
+    for mut section in sections {
+        let guard = section
+            .values
+            .iter()
+            .position(|(key, _)| key == "when")
+            .map(|index| section.values.remove(index).1);
+        let os_section = OS_NAMES.iter().find_map(|name| {
+            let rest = section.name.strip_prefix(name)?;
+            (rest.is_empty() || rest.starts_with('.'))
+                .then(|| (*name, rest.trim_start_matches('.').to_string()))
+        });
+
+        let guard_holds = match &guard {
+            Some(guard) => condition_holds(guard.as_string()?, os)?,
+            None => true,
+        };
+        let os_matches = os_section.as_ref().is_none_or(|(name, _)| *name == os);
+        if !guard_holds || !os_matches {
+            continue;
+        }
+        let limited = guard.is_some() || os_section.is_some();
+        if let Some((_, rest)) = os_section {
+            section.name = rest;
+        }
+        let target = if limited { &mut specific } else { &mut general };
+        target.extend(section.into_dotted());
+    }
+    general.extend(specific);
+    Ok(general)
+}
+
+/// Evaluate a `when` guard: `os == 'name'` or `os != 'name'`. Any name is
+/// accepted, so a guard on an OS without a section of its own (such as
+/// `freebsd`) simply never matches elsewhere.
+fn condition_holds(condition: &str, os: &str) -> ConfigResult<bool> {
+    let unsupported = || ConfigError::Validation(format!("Unsupported condition: '{}'", condition));
+    let (negated, value) = match condition.split_once("==") {
+        Some((variable, value)) if variable.trim() == "os" => (false, value),
+        _ => match condition.split_once("!=") {
+            Some((variable, value)) if variable.trim() == "os" => (true, value),
+            _ => return Err(unsupported()),
+        },
+    };
+    let name = value.trim().trim_matches(|ch| ch == '\'' || ch == '"');
+    if name.is_empty() {
+        return Err(unsupported());
+    }
+    Ok((name == os) != negated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::toml_parser::TomlParser;
+
+    fn resolve(content: &str, os: &str) -> ConfigResult<HashMap<String, String>> {
+        let values = resolve_sections(TomlParser::parse_sections(content)?, os)?;
+        values
+            .into_iter()
+            .map(|(key, value)| Ok((key, value.as_string()?.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn test_os_sections_apply_only_on_their_os() -> ConfigResult<()> {
+        let content = r#"
+[editor]
+shell = "sh"
+
+[windows.editor]
+shell = "cmd"
+
+[macos.keybindings]
+save = "Cmd+S"
+"#;
+        let linux = resolve(content, "linux")?;
+        assert_eq!(linux.get("editor.shell").map(String::as_str), Some("sh"));
+        assert!(!linux.contains_key("keybindings.save"));
+
+        let windows = resolve(content, "windows")?;
+        assert_eq!(windows.get("editor.shell").map(String::as_str), Some("cmd"));
+        assert!(!windows.keys().any(|key| key.starts_with("windows")));
+
+        let macos = resolve(content, "macos")?;
+        assert_eq!(
+            macos.get("keybindings.save").map(String::as_str),
+            Some("Cmd+S")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_os_values_win_regardless_of_order() -> ConfigResult<()> {
+        let content = r#"
+[linux.editor]
+shell = "bash"
+
+[editor]
+shell = "sh"
+"#;
+        let values = resolve(content, "linux")?;
+        assert_eq!(values.get("editor.shell").map(String::as_str), Some("bash"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_when_guards() -> ConfigResult<()> {
+        let content = r#"
+[editor]
+when = "os == 'macos'"
+shell = "zsh"
+
+[ui]
+when = "os != 'windows'"
+theme = "dark"
+"#;
+        let macos = resolve(content, "macos")?;
+        assert_eq!(macos.get("editor.shell").map(String::as_str), Some("zsh"));
+        assert_eq!(macos.get("ui.theme").map(String::as_str), Some("dark"));
+        assert!(!macos.contains_key("editor.when"));
+
+        let windows = resolve(content, "windows")?;
+        assert!(windows.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_when_guard_and_os_section_must_both_hold() -> ConfigResult<()> {
+        let content = r#"
+[linux.editor]
+when = "os != 'windows'"
+shell = "bash"
+"#;
+        assert!(resolve(content, "macos")?.is_empty());
+        assert_eq!(
+            resolve(content, "linux")?
+                .get("editor.shell")
+                .map(String::as_str),
+            Some("bash")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_when_guard_on_other_os_names() -> ConfigResult<()> {
+        let content = r#"
+[editor]
+when = "os == 'freebsd'"
+shell = "csh"
+"#;
+        assert!(resolve(content, "linux")?.is_empty());
+        assert_eq!(
+            resolve(content, "freebsd")?
+                .get("editor.shell")
+                .map(String::as_str),
+            Some("csh")
+        );
+        assert!(resolve("[editor]\nwhen = \"arch == 'x86'\"\n", "linux").is_err());
+        Ok(())
+    }
+}
@@ -11,6 +11,8 @@ pub struct EditorSettings {
     pub relative_numbers: bool,
     /// Tab width in spaces
     pub tab_width: u32,
+    /// Highlight text past this column and count overlong lines (0 disables)
+    pub text_width: u32,
     /// Use spaces instead of tabs
     pub expand_tab: bool,
     /// Auto indent new lines
@@ -35,18 +37,37 @@ pub struct EditorSettings {
     pub wrap: bool,
     /// Show line breaks
     pub line_break: bool,
+    /// Show BOM and zero-width characters as `<xxxx>`
+    pub show_invisible: bool,
+    /// Draw color codes such as `#RRGGBB` on the color they name
+    pub color_swatches: bool,
     /// Scroll offset from top/bottom
     pub scrolloff: u32,
     /// Side scroll offset
     pub sidescrolloff: u32,
+    /// Animate large scroll jumps (Ctrl+d, gg, ...)
+    pub smooth_scroll: bool,
+    /// Number of frames in a smooth scroll animation
+    pub smooth_scroll_frames: u32,
+    /// Milliseconds before info messages are cleared from the command line (0 keeps them)
+    pub message_timeout: u32,
+    /// Milliseconds without input before CursorHold fires
+    pub updatetime: u32,
     /// Enable mouse support
     pub mouse: bool,
+    /// Shift+Arrow keys start select mode, where typing replaces the
+    /// selection, and unshifted arrows leave it
+    pub shift_select: bool,
     /// Backup files before writing
     pub backup: bool,
     /// Write backup files
     pub writebackup: bool,
     /// Swap file for crash recovery
     pub swapfile: bool,
+    /// Encrypt swap files with a key from the passphrase in the
+    /// `NIV_SWAP_PASSPHRASE` environment variable; without one, no swap
+    /// files are written
+    pub swap_encrypt: bool,
     /// Undo levels
     pub undolevels: u32,
     /// Persistent undo
@@ -57,6 +78,28 @@ pub struct EditorSettings {
     pub autowrite: bool,
     /// Confirm before quitting with unsaved changes
     pub confirm: bool,
+    /// Change the working directory to the directory of the current file
+    pub autochdir: bool,
+    /// Save the open files per project on exit and offer to restore them
+    pub auto_session: bool,
+    /// Keep global marks, registers, histories and the last cursor position
+    /// in each file between runs, in the shada file of the state directory
+    pub shada: bool,
+    /// Keep the macros recorded with `q` in the shada file as well, to
+    /// replay them in later runs
+    pub shada_macros: bool,
+    /// Most keys a macro holds; recording stops there
+    pub macro_max_keys: u32,
+    /// Glob patterns for sensitive files that never get swap or undo files
+    pub sensitive_files: Vec<String>,
+    /// Record `:Rename`/`:Move` of tracked files in the version control system
+    /// (git, hg, svn or pijul) the file is in
+    pub git_stage_renames: bool,
+    /// Offer hidden files (dotfiles, and on Windows those with the hidden
+    /// attribute) in path completion and pickers without typing the `.`
+    pub show_hidden: bool,
+    /// Keep the Windows hidden attribute of files that saves overwrite
+    pub preserve_hidden: bool,
 }
 
 impl Default for EditorSettings {
@@ -65,6 +108,7 @@ impl Default for EditorSettings {
             line_numbers: true,
             relative_numbers: false,
             tab_width: 4,
+            text_width: 0,
             expand_tab: true,
             auto_indent: true,
             smart_indent: false,
@@ -77,73 +121,123 @@ impl Default for EditorSettings {
             smartcase: true,
             wrap: true,
             line_break: false,
+            show_invisible: false,
+            color_swatches: true,
             scrolloff: 5,
             sidescrolloff: 10,
+            smooth_scroll: false,
+            smooth_scroll_frames: 6,
+            message_timeout: 4000,
+            updatetime: 4000,
             mouse: false,
+            shift_select: false,
             backup: false,
             writebackup: true,
             swapfile: true,
+            swap_encrypt: false,
             undolevels: 1000,
             undofile: true,
             autoread: true,
             autowrite: false,
             confirm: true,
+            autochdir: false,
+            auto_session: false,
+            shada: true,
+            shada_macros: true,
+            macro_max_keys: 1000,
+            sensitive_files: vec![
+                "*.gpg".to_string(),
+                "*.asc".to_string(),
+                "*.pgp".to_string(),
+                "*.age".to_string(),
+            ],
+            git_stage_renames: false,
+            show_hidden: false,
+            preserve_hidden: true,
         }
     }
 }
 
+/// Access to one field of the settings
+type Field<T> = fn(&mut EditorSettings) -> &mut T;
+
+/// Boolean settings, by the key they are loaded from
+const BOOL_SETTINGS: &[(&str, Field<bool>)] = &[
+    ("editor.line_numbers", |s| &mut s.line_numbers),
+    ("editor.relative_numbers", |s| &mut s.relative_numbers),
+    ("editor.expand_tab", |s| &mut s.expand_tab),
+    ("editor.auto_indent", |s| &mut s.auto_indent),
+    ("editor.smart_indent", |s| &mut s.smart_indent),
+    ("editor.cursor_line", |s| &mut s.cursor_line),
+    ("editor.show_match", |s| &mut s.show_match),
+    ("editor.syntax", |s| &mut s.syntax),
+    ("editor.incsearch", |s| &mut s.incsearch),
+    ("editor.hlsearch", |s| &mut s.hlsearch),
+    ("editor.ignorecase", |s| &mut s.ignorecase),
+    ("editor.smartcase", |s| &mut s.smartcase),
+    ("editor.wrap", |s| &mut s.wrap),
+    ("editor.line_break", |s| &mut s.line_break),
+    ("editor.show_invisible", |s| &mut s.show_invisible),
+    ("editor.color_swatches", |s| &mut s.color_swatches),
+    ("editor.smooth_scroll", |s| &mut s.smooth_scroll),
+    ("editor.mouse", |s| &mut s.mouse),
+    ("editor.shift_select", |s| &mut s.shift_select),
+    ("editor.backup", |s| &mut s.backup),
+    ("editor.writebackup", |s| &mut s.writebackup),
+    ("editor.swapfile", |s| &mut s.swapfile),
+    ("editor.swap_encrypt", |s| &mut s.swap_encrypt),
+    ("editor.undofile", |s| &mut s.undofile),
+    ("editor.autoread", |s| &mut s.autoread),
+    ("editor.autowrite", |s| &mut s.autowrite),
+    ("editor.confirm", |s| &mut s.confirm),
+    ("editor.autochdir", |s| &mut s.autochdir),
+    ("editor.auto_session", |s| &mut s.auto_session),
+    ("editor.shada", |s| &mut s.shada),
+    ("editor.shada_macros", |s| &mut s.shada_macros),
+    ("editor.git_stage_renames", |s| &mut s.git_stage_renames),
+    ("editor.show_hidden", |s| &mut s.show_hidden),
+    ("editor.preserve_hidden", |s| &mut s.preserve_hidden),
+];
+
+/// Integer settings, by the key they are loaded from
+const INT_SETTINGS: &[(&str, Field<u32>)] = &[
+    ("editor.tab_width", |s| &mut s.tab_width),
+    ("editor.text_width", |s| &mut s.text_width),
+    ("editor.scrolloff", |s| &mut s.scrolloff),
+    ("editor.sidescrolloff", |s| &mut s.sidescrolloff),
+    ("editor.smooth_scroll_frames", |s| {
+        &mut s.smooth_scroll_frames
+    }),
+    ("editor.message_timeout", |s| &mut s.message_timeout),
+    ("editor.updatetime", |s| &mut s.updatetime),
+    ("editor.undolevels", |s| &mut s.undolevels),
+    ("editor.macro_max_keys", |s| &mut s.macro_max_keys),
+];
+
 impl EditorSettings {
     /// Load settings from TOML values
     pub fn from_toml(values: &HashMap<String, TomlValue>) -> ConfigResult<Self> {
         let mut settings = Self::default();
 
-        // Helper macro to load boolean settings
-        macro_rules! load_bool {
-            ($field:ident, $key:expr) => {
-                if let Some(value) = values.get($key) {
-                    settings.$field = value.as_bool()?;
-                }
-            };
+        for (key, field) in BOOL_SETTINGS {
+            if let Some(value) = values.get(*key) {
+                *field(&mut settings) = value.as_bool()?;
+            }
         }
-
-        // Helper macro to load integer settings
-        macro_rules! load_int {
-            ($field:ident, $key:expr) => {
-                if let Some(value) = values.get($key) {
-                    settings.$field = value.as_integer()? as u32;
-                }
-            };
+        for (key, field) in INT_SETTINGS {
+            if let Some(value) = values.get(*key) {
+                *field(&mut settings) = value.as_integer()? as u32;
+            }
         }
 
-        // Load boolean settings
-        load_bool!(line_numbers, "editor.line_numbers");
-        load_bool!(relative_numbers, "editor.relative_numbers");
-        load_bool!(expand_tab, "editor.expand_tab");
-        load_bool!(auto_indent, "editor.auto_indent");
-        load_bool!(smart_indent, "editor.smart_indent");
-        load_bool!(cursor_line, "editor.cursor_line");
-        load_bool!(show_match, "editor.show_match");
-        load_bool!(syntax, "editor.syntax");
-        load_bool!(incsearch, "editor.incsearch");
-        load_bool!(hlsearch, "editor.hlsearch");
-        load_bool!(ignorecase, "editor.ignorecase");
-        load_bool!(smartcase, "editor.smartcase");
-        load_bool!(wrap, "editor.wrap");
-        load_bool!(line_break, "editor.line_break");
-        load_bool!(mouse, "editor.mouse");
-        load_bool!(backup, "editor.backup");
-        load_bool!(writebackup, "editor.writebackup");
-        load_bool!(swapfile, "editor.swapfile");
-        load_bool!(undofile, "editor.undofile");
-        load_bool!(autoread, "editor.autoread");
-        load_bool!(autowrite, "editor.autowrite");
-        load_bool!(confirm, "editor.confirm");
-
-        // Load integer settings
-        load_int!(tab_width, "editor.tab_width");
-        load_int!(scrolloff, "editor.scrolloff");
-        load_int!(sidescrolloff, "editor.sidescrolloff");
-        load_int!(undolevels, "editor.undolevels");
+        // Load string list settings
+        if let Some(value) = values.get("editor.sensitive_files") {
+            settings.sensitive_files = value
+                .as_array()?
+                .iter()
+                .map(|item| item.as_string().map(str::to_string))
+                .collect::<ConfigResult<Vec<_>>>()?;
+        }
 
         Ok(settings)
     }
@@ -181,20 +275,49 @@ impl EditorSettings {
         export_bool!(smartcase, "editor.smartcase");
         export_bool!(wrap, "editor.wrap");
         export_bool!(line_break, "editor.line_break");
+        export_bool!(show_invisible, "editor.show_invisible");
+        export_bool!(color_swatches, "editor.color_swatches");
+        export_bool!(smooth_scroll, "editor.smooth_scroll");
         export_bool!(mouse, "editor.mouse");
+        export_bool!(shift_select, "editor.shift_select");
         export_bool!(backup, "editor.backup");
         export_bool!(writebackup, "editor.writebackup");
         export_bool!(swapfile, "editor.swapfile");
+        export_bool!(swap_encrypt, "editor.swap_encrypt");
         export_bool!(undofile, "editor.undofile");
         export_bool!(autoread, "editor.autoread");
         export_bool!(autowrite, "editor.autowrite");
         export_bool!(confirm, "editor.confirm");
+        export_bool!(autochdir, "editor.autochdir");
+        export_bool!(auto_session, "editor.auto_session");
+        export_bool!(shada, "editor.shada");
+        export_bool!(shada_macros, "editor.shada_macros");
+        export_bool!(git_stage_renames, "editor.git_stage_renames");
+        export_bool!(show_hidden, "editor.show_hidden");
+        export_bool!(preserve_hidden, "editor.preserve_hidden");
 
         // Export integer settings
         export_int!(tab_width, "editor.tab_width");
+        export_int!(text_width, "editor.text_width");
         export_int!(scrolloff, "editor.scrolloff");
         export_int!(sidescrolloff, "editor.sidescrolloff");
+        export_int!(smooth_scroll_frames, "editor.smooth_scroll_frames");
+        export_int!(message_timeout, "editor.message_timeout");
+        export_int!(updatetime, "editor.updatetime");
         export_int!(undolevels, "editor.undolevels");
+        export_int!(macro_max_keys, "editor.macro_max_keys");
+
+        // Export string list settings
+        values.insert(
+            "editor.sensitive_files".to_string(),
+            TomlValue::Array(
+                self.sensitive_files
+                    .iter()
+                    .cloned()
+                    .map(TomlValue::String)
+                    .collect(),
+            ),
+        );
 
         values
     }
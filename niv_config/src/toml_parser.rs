@@ -9,7 +9,16 @@ impl TomlParser {
     /// Parse a TOML string into a HashMap
     pub fn parse(content: &str) -> ConfigResult<HashMap<String, TomlValue>> {
         let mut result = HashMap::new();
-        let mut current_section = String::new();
+        for section in Self::parse_sections(content)? {
+            result.extend(section.into_dotted());
+        }
+        Ok(result)
+    }
+
+    /// Parse a TOML string into its sections, in file order. A section that
+    /// appears twice is returned twice.
+    pub fn parse_sections(content: &str) -> ConfigResult<Vec<TomlSection>> {
+        let mut sections = vec![TomlSection::default()];
 
         for (line_num, line) in content.lines().enumerate() {
             let line = line.trim();
@@ -21,20 +30,19 @@ impl TomlParser {
 
             // Handle section headers
             if line.starts_with('[') && line.ends_with(']') {
-                current_section = line[1..line.len() - 1].to_string();
+                sections.push(TomlSection {
+                    name: line[1..line.len() - 1].to_string(),
+                    values: Vec::new(),
+                });
                 continue;
             }
 
             // Parse key-value pairs
             if let Some((key, value)) = Self::parse_key_value(line) {
-                let full_key = if current_section.is_empty() {
-                    key.to_string()
-                } else {
-                    format!("{}.{}", current_section, key)
-                };
-
                 let toml_value = Self::parse_value(value)?;
-                result.insert(full_key, toml_value);
+                if let Some(section) = sections.last_mut() {
+                    section.values.push((key.to_string(), toml_value));
+                }
             } else if !line.is_empty() {
                 return Err(ConfigError::Toml(format!(
                     "Invalid line {}: '{}'",
@@ -44,7 +52,7 @@ impl TomlParser {
             }
         }
 
-        Ok(result)
+        Ok(sections)
     }
 
     fn parse_key_value(line: &str) -> Option<(&str, &str)> {
@@ -103,6 +111,29 @@ impl TomlParser {
     }
 }
 
+/// A `[section]` of a TOML file; the one before the first header has an
+/// empty name
+#[derive(Debug, Clone, Default)]
+pub struct TomlSection {
+    pub name: String,
+    /// Key-value pairs in file order
+    pub values: Vec<(String, TomlValue)>,
+}
+
+impl TomlSection {
+    /// The section's values with keys prefixed by the section name
+    pub fn into_dotted(self) -> impl Iterator<Item = (String, TomlValue)> {
+        let name = self.name;
+        self.values.into_iter().map(move |(key, value)| {
+            if name.is_empty() {
+                (key, value)
+            } else {
+                (format!("{}.{}", name, key), value)
+            }
+        })
+    }
+}
+
 /// TOML value types supported by our parser
 #[derive(Debug, Clone)]
 pub enum TomlValue {
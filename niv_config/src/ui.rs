@@ -11,7 +11,7 @@ pub struct Color {
 }
 
 impl Color {
-    pub fn new(r: u8, g: u8, b: u8) -> Self {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
         Self { r, g, b }
     }
 
@@ -66,6 +66,12 @@ pub struct ColorScheme {
     pub status_bg: Color,
     /// Status line foreground
     pub status_fg: Color,
+    /// Text color of windows without focus
+    pub inactive_fg: Color,
+    /// Status line background of windows without focus
+    pub status_inactive_bg: Color,
+    /// Status line foreground of windows without focus
+    pub status_inactive_fg: Color,
     /// Error color
     pub error: Color,
     /// Warning color
@@ -105,12 +111,24 @@ pub struct UiSettings {
     pub command_line: bool,
     /// Show tab bar
     pub tab_bar: bool,
+    /// Show a bar with the file path above each window
+    pub winbar: bool,
     /// Window transparency (0-100, for GUI)
     pub transparency: u8,
     /// Show minimap
     pub minimap: bool,
     /// Show file tree
     pub file_tree: bool,
+    /// Dim windows without focus, including the whole editor when the terminal loses focus
+    pub dim_inactive: bool,
+    /// Use the kitty keyboard protocol when the terminal supports it, so keys
+    /// like `Ctrl+i` and `Tab` can be told apart
+    pub extended_keys: bool,
+    /// Draw on the alternate screen; when off the editor runs inline below
+    /// the shell prompt and leaves the terminal scrollback alone
+    pub alternate_screen: bool,
+    /// Rows the editor takes when running inline
+    pub inline_height: u16,
     /// Split pane settings
     pub splits: SplitSettings,
 }
@@ -160,6 +178,9 @@ impl Default for ColorScheme {
             syntax: SyntaxColors::default(),
             status_bg: Color::from_hex("007ACC").unwrap(),
             status_fg: Color::from_hex("FFFFFF").unwrap(),
+            inactive_fg: Color::new(0x80, 0x80, 0x80),
+            status_inactive_bg: Color::new(0x3C, 0x3C, 0x3C),
+            status_inactive_fg: Color::new(0xA0, 0xA0, 0xA0),
             error: Color::from_hex("F44747").unwrap(),
             warning: Color::from_hex("FFA500").unwrap(),
             info: Color::from_hex("00BFFF").unwrap(),
@@ -193,9 +214,14 @@ impl Default for UiSettings {
             status_line: true,
             command_line: true,
             tab_bar: true,
+            winbar: false,
             transparency: 100,
             minimap: false,
             file_tree: false,
+            dim_inactive: true,
+            extended_keys: true,
+            alternate_screen: true,
+            inline_height: 15,
             splits: SplitSettings::default(),
         }
     }
@@ -240,13 +266,20 @@ impl UiSettings {
         load_bool!(status_line, "ui.status_line");
         load_bool!(command_line, "ui.command_line");
         load_bool!(tab_bar, "ui.tab_bar");
+        load_bool!(winbar, "ui.winbar");
         load_bool!(minimap, "ui.minimap");
         load_bool!(file_tree, "ui.file_tree");
+        load_bool!(dim_inactive, "ui.dim_inactive");
+        load_bool!(extended_keys, "ui.extended_keys");
+        load_bool!(alternate_screen, "ui.alternate_screen");
 
         // Load transparency
         if let Some(value) = values.get("ui.transparency") {
             settings.transparency = value.as_integer()?.clamp(0, 100) as u8;
         }
+        if let Some(value) = values.get("ui.inline_height") {
+            settings.inline_height = value.as_integer()?.clamp(3, u16::MAX as i64) as u16;
+        }
 
         Ok(settings)
     }
@@ -271,6 +304,10 @@ impl UiSettings {
             "ui.transparency".to_string(),
             TomlValue::Integer(self.transparency as i64),
         );
+        values.insert(
+            "ui.inline_height".to_string(),
+            TomlValue::Integer(self.inline_height as i64),
+        );
 
         // Export boolean settings
         macro_rules! export_bool {
@@ -282,8 +319,12 @@ impl UiSettings {
         export_bool!(status_line, "ui.status_line");
         export_bool!(command_line, "ui.command_line");
         export_bool!(tab_bar, "ui.tab_bar");
+        export_bool!(winbar, "ui.winbar");
         export_bool!(minimap, "ui.minimap");
         export_bool!(file_tree, "ui.file_tree");
+        export_bool!(dim_inactive, "ui.dim_inactive");
+        export_bool!(extended_keys, "ui.extended_keys");
+        export_bool!(alternate_screen, "ui.alternate_screen");
 
         values
     }
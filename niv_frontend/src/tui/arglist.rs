@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+/// The argument list: files given on the command line or with `:args`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArgList {
+    files: Vec<PathBuf>,
+    index: usize,
+}
+
+impl ArgList {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        Self { files, index: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Index of the current entry
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn current(&self) -> Option<&Path> {
+        self.files.get(self.index).map(PathBuf::as_path)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Path> {
+        self.files.get(index).map(PathBuf::as_path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(PathBuf::as_path)
+    }
+
+    /// Make entry `index` current; returns its path, or `None` if out of range
+    pub fn select(&mut self, index: usize) -> Option<&Path> {
+        if index >= self.files.len() {
+            return None;
+        }
+        self.index = index;
+        self.current()
+    }
+
+    /// Move `count` entries forward; `None` when that runs past the last file
+    pub fn next(&mut self, count: usize) -> Option<&Path> {
+        self.select(self.index + count.max(1))
+    }
+
+    /// Move `count` entries back; `None` when that runs past the first file
+    pub fn prev(&mut self, count: usize) -> Option<&Path> {
+        let index = self.index.checked_sub(count.max(1))?;
+        self.select(index)
+    }
+}
@@ -0,0 +1,52 @@
+/// Bytes scanned for a matching bracket before giving up, so the scan stays
+/// cheap on every cursor move in large files
+pub const BRACKET_SCAN_LIMIT: usize = 100_000;
+
+/// The bracket closing or opening `bracket`, and whether it comes after it
+fn bracket_pair(bracket: u8) -> Option<(u8, bool)> {
+    match bracket {
+        b'(' => Some((b')', true)),
+        b'[' => Some((b']', true)),
+        b'{' => Some((b'}', true)),
+        b')' => Some((b'(', false)),
+        b']' => Some((b'[', false)),
+        b'}' => Some((b'{', false)),
+        _ => None,
+    }
+}
+
+/// Byte offset of the bracket matching the one at `offset` in `text`,
+/// counting nested pairs and looking at most `limit` bytes away
+pub fn matching_bracket(text: &str, offset: usize, limit: usize) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let bracket = *bytes.get(offset)?;
+    let (pair, forward) = bracket_pair(bracket)?;
+    let mut depth = 0usize;
+    let mut step = |i: usize| {
+        if bytes[i] == bracket {
+            depth += 1;
+        } else if bytes[i] == pair {
+            if depth == 0 {
+                return true;
+            }
+            depth -= 1;
+        }
+        false
+    };
+    if forward {
+        let end = bytes.len().min(offset + 1 + limit);
+        (offset + 1..end).find(|&i| step(i))
+    } else {
+        let start = offset.saturating_sub(limit);
+        (start..offset).rev().find(|&i| step(i))
+    }
+}
+
+/// The bracket under the cursor at byte `cursor`, or else the one just before
+/// it, and the offset of its match
+pub fn bracket_at_cursor(text: &str, cursor: usize, limit: usize) -> Option<(usize, usize)> {
+    [Some(cursor), cursor.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .find_map(|offset| Some((offset, matching_bracket(text, offset, limit)?)))
+}
@@ -1,7 +1,15 @@
+use crate::tui::display::{
+    DisplayKind, DisplayOptions, DisplaySegment, display_width, render_line_highlighted,
+};
+use crate::tui::editorconfig::editorconfig_max_line_length;
+use crate::tui::interrupt::load_file_interruptible;
+use crate::tui::overlength::{OverlengthCount, overlength_start};
+use crate::tui::table::{adjacent_field, align_fields, aligned_col, column_widths, field_ranges};
+use crate::tui::wordindex::WordIndex;
 use niv_config::EditorSettings;
-use niv_fs::SaveContext;
+use niv_fs::{FileSaveConfig, SaveContext};
 use niv_rope::Rope;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Text buffer for TUI display
 #[derive(Debug, Clone)]
@@ -16,12 +24,41 @@ pub struct TextBuffer {
     pub save_context: SaveContext,
     /// Whether the buffer has unsaved changes
     pub modified: bool,
+    /// Length and hash of the content as last loaded or saved, telling
+    /// edits from edits undone again
+    saved_len: usize,
+    saved_hash: u64,
+    /// Never write this buffer's content to a swap file
+    pub no_swap: bool,
+    /// Never persist this buffer's undo history to disk. Reserved: undo
+    /// history isn't written anywhere yet, so nothing reads this until an
+    /// undo file is, which must then skip buffers that have it set.
+    pub no_undo_file: bool,
+    /// Keep the Windows hidden attribute of the file when saving
+    pub preserve_hidden: bool,
+    /// Tab width and invisible character display for this buffer
+    pub display: DisplayOptions,
+    /// Working directory set with `:lcd` (None uses the global one)
+    pub local_cwd: Option<PathBuf>,
+    /// `:tail`: load lines appended to the file and keep the end in view
+    pub follow: bool,
+    /// Bytes of the file read into the buffer; appended lines are read from here
+    pub loaded_len: u64,
+    /// Keywords in `content`, kept up to date by the editing methods
+    pub word_index: WordIndex,
+    /// Lines wider than `display.text_width`, kept up to date the same way
+    pub overlength: OverlengthCount,
     /// Current cursor position
     pub cursor_line: usize,
     pub cursor_col: usize,
     /// Scroll position
     pub scroll_line: usize,
     pub scroll_col: usize,
+    /// Bracket at the cursor and its match, as (line, byte column)
+    pub bracket_match: Option<[(usize, usize); 2]>,
+    /// Selected text from the first position up to the second, as (line,
+    /// byte column); a column past the end of its line takes the whole line
+    pub selection: Option<[(usize, usize); 2]>,
     /// Buffer dimensions
     pub width: u16,
     pub height: u16,
@@ -35,42 +72,61 @@ impl TextBuffer {
             file_path: None,
             save_context: SaveContext::new(),
             modified: false,
+            saved_len: 0,
+            saved_hash: content_hash(""),
+            no_swap: false,
+            no_undo_file: false,
+            preserve_hidden: true,
+            display: DisplayOptions::default(),
+            local_cwd: None,
+            follow: false,
+            loaded_len: 0,
+            word_index: WordIndex::default(),
             cursor_line: 0,
             cursor_col: 0,
             scroll_line: 0,
             scroll_col: 0,
+            bracket_match: None,
+            selection: None,
+            overlength: OverlengthCount::default(),
             width: 80,
             height: 24,
         }
     }
 
     pub fn from_rope(rope: Rope) -> Self {
-        // Read the rope content into a string
-        let mut content = String::new();
-        if rope.len() > 0 {
-            let mut buf = vec![0u8; rope.len()];
-            let _ = rope.read_bytes_global(0, &mut buf);
-            content = String::from_utf8_lossy(&buf).to_string();
-        }
+        let content = rope.to_string();
 
         Self {
             rope,
+            word_index: WordIndex::from_text(&content),
+            saved_len: content.len(),
+            saved_hash: content_hash(&content),
             content,
             file_path: None,
             save_context: SaveContext::new(),
             modified: false,
+            no_swap: false,
+            no_undo_file: false,
+            preserve_hidden: true,
+            display: DisplayOptions::default(),
+            local_cwd: None,
+            follow: false,
+            loaded_len: 0,
             cursor_line: 0,
             cursor_col: 0,
             scroll_line: 0,
             scroll_col: 0,
+            bracket_match: None,
+            selection: None,
+            overlength: OverlengthCount::default(),
             width: 80,
             height: 24,
         }
     }
 
     pub fn from_file(path: PathBuf, content: &str) -> Self {
-        let mut rope = Rope::new();
-        let _ = rope.build_from_bytes(content.as_bytes());
+        let rope = Rope::from(content);
 
         Self {
             rope,
@@ -78,32 +134,58 @@ impl TextBuffer {
             file_path: Some(path),
             save_context: SaveContext::new(),
             modified: false,
+            saved_len: content.len(),
+            saved_hash: content_hash(content),
+            no_swap: false,
+            no_undo_file: false,
+            preserve_hidden: true,
+            display: DisplayOptions::default(),
+            local_cwd: None,
+            follow: false,
+            loaded_len: 0,
+            word_index: WordIndex::from_text(content),
             cursor_line: 0,
             cursor_col: 0,
             scroll_line: 0,
             scroll_col: 0,
+            bracket_match: None,
+            selection: None,
+            overlength: OverlengthCount::default(),
             width: 80,
             height: 24,
         }
     }
 
     pub fn from_file_load_result(path: PathBuf, load_result: niv_fs::FileLoadResult) -> Self {
-        let mut rope = Rope::new();
-        let _ = rope.build_from_bytes(load_result.content.as_bytes());
+        let rope = Rope::from(load_result.content.as_str());
 
         // Create save context from load result to preserve original file properties
         let save_context = SaveContext::from_load_result(&load_result);
+        let loaded_len = load_result.identity.size;
 
         Self {
             rope,
+            word_index: WordIndex::from_text(&load_result.content),
+            saved_len: load_result.content.len(),
+            saved_hash: content_hash(&load_result.content),
             content: load_result.content,
             file_path: Some(path),
             save_context,
             modified: false,
+            no_swap: false,
+            no_undo_file: false,
+            preserve_hidden: true,
+            display: DisplayOptions::default(),
+            local_cwd: None,
+            follow: false,
+            loaded_len,
             cursor_line: 0,
             cursor_col: 0,
             scroll_line: 0,
             scroll_col: 0,
+            bracket_match: None,
+            selection: None,
+            overlength: OverlengthCount::default(),
             width: 80,
             height: 24,
         }
@@ -116,10 +198,23 @@ impl TextBuffer {
             file_path: Some(path),
             save_context: SaveContext::new(),
             modified: false,
+            saved_len: 0,
+            saved_hash: content_hash(""),
+            no_swap: false,
+            no_undo_file: false,
+            preserve_hidden: true,
+            display: DisplayOptions::default(),
+            local_cwd: None,
+            follow: false,
+            loaded_len: 0,
+            word_index: WordIndex::default(),
             cursor_line: 0,
             cursor_col: 0,
             scroll_line: 0,
             scroll_col: 0,
+            bracket_match: None,
+            selection: None,
+            overlength: OverlengthCount::default(),
             width: 80,
             height: 24,
         }
@@ -132,18 +227,151 @@ impl TextBuffer {
         self.adjust_scroll();
     }
 
+    /// Apply sensitive-file globs, the hidden-file save policy,
+    /// `.editorconfig` `max_line_length` and modeline options to the
+    /// buffer-local flags.
+    ///
+    /// Modeline options that are not understood are ignored.
+    pub fn apply_local_options(&mut self, settings: &EditorSettings) {
+        self.display.tab_width = settings.tab_width as usize;
+        self.display.show_invisible = settings.show_invisible;
+        self.display.color_swatches = settings.color_swatches;
+        self.preserve_hidden = settings.preserve_hidden;
+        self.display.text_width = self
+            .file_path
+            .as_deref()
+            .and_then(editorconfig_max_line_length)
+            .unwrap_or(settings.text_width as usize);
+        self.overlength = OverlengthCount::from_text(&self.content, &self.display);
+
+        let sensitive = self
+            .file_path
+            .as_ref()
+            .is_some_and(|path| niv_fs::path_matches_any(&settings.sensitive_files, path));
+        if sensitive {
+            self.no_swap = true;
+            self.no_undo_file = true;
+        }
+
+        for option in crate::tui::modeline::parse_modelines(&self.content) {
+            let _ = self.set_local_option(&option);
+        }
+    }
+
+    /// Set a buffer-local option (as used by `:setlocal` and modelines)
+    pub fn set_local_option(&mut self, option: &str) -> Result<(), String> {
+        match option {
+            "swapfile" | "swf" => self.no_swap = false,
+            "noswapfile" | "noswf" => self.no_swap = true,
+            "undofile" | "udf" => self.no_undo_file = false,
+            "noundofile" | "noudf" => self.no_undo_file = true,
+            "list" => self.display.show_invisible = true,
+            "nolist" => self.display.show_invisible = false,
+            "colorswatch" => self.display.color_swatches = true,
+            "nocolorswatch" => self.display.color_swatches = false,
+            _ => {
+                let (name, value) = option
+                    .split_once('=')
+                    .ok_or_else(|| format!("Unknown option: {}", option))?;
+                match name {
+                    "tabstop" | "ts" => {
+                        self.display.tab_width = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&width| width > 0)
+                            .ok_or_else(|| format!("Invalid value for {}: {}", name, value))?;
+                    }
+                    "textwidth" | "tw" => {
+                        self.display.text_width = value
+                            .parse::<usize>()
+                            .map_err(|_| format!("Invalid value for {}: {}", name, value))?;
+                    }
+                    _ => return Err(format!("Unknown option: {}", option)),
+                }
+                // Both change which lines are too wide
+                self.overlength = OverlengthCount::from_text(&self.content, &self.display);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the buffer content with the file on disk, discarding unsaved changes
+    pub fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(path) = &self.file_path else {
+            return Err("No file path set for buffer".into());
+        };
+        let load_result = load_file_interruptible(path)?;
+        self.replace_content(load_result);
+        Ok(())
+    }
+
+    /// Replace the buffer content with a new load of its file, discarding
+    /// unsaved changes
+    pub fn replace_content(&mut self, load_result: niv_fs::FileLoadResult) {
+        self.rope = Rope::from(load_result.content.as_str());
+        self.save_context = SaveContext::from_load_result(&load_result);
+        self.loaded_len = load_result.identity.size;
+        self.content = load_result.content;
+        self.word_index = WordIndex::from_text(&self.content);
+        self.overlength = OverlengthCount::from_text(&self.content, &self.display);
+        self.mark_saved();
+
+        // Keep the cursor where it was as far as the new content allows
+        let line_count = self.content.lines().count().max(1);
+        self.cursor_line = self.cursor_line.min(line_count - 1);
+        self.adjust_cursor_to_line_length();
+        self.adjust_scroll();
+    }
+
+    /// Whether the content differs from the file as last loaded or saved, so
+    /// edits undone again by hand don't count as changes
+    pub fn is_really_modified(&self) -> bool {
+        // Only content of the same length needs hashing
+        self.content.len() != self.saved_len || content_hash(&self.content) != self.saved_hash
+    }
+
+    /// Take the content as unmodified, as it is once saved
+    pub fn mark_saved(&mut self) {
+        self.saved_len = self.content.len();
+        self.saved_hash = content_hash(&self.content);
+        self.modified = false;
+    }
+
+    // Set `modified` after an edit, clearing it when the edit brought the
+    // content back to what the file holds
+    fn update_modified(&mut self) {
+        self.modified = self.is_really_modified();
+    }
+
     /// Save buffer to file using niv_fs
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(path) = &self.file_path {
-            niv_fs::save_file(path, &self.content, &self.save_context)?;
+            let config = self.save_config();
+            niv_fs::save_file_with_config(path, &self.content, &self.save_context, &config)?;
             Ok(())
         } else {
             Err("No file path set for buffer".into())
         }
     }
 
-    /// Get visible lines
+    /// How this buffer's options say to save its file
+    pub fn save_config(&self) -> FileSaveConfig {
+        FileSaveConfig {
+            preserve_hidden: self.preserve_hidden,
+            ..FileSaveConfig::default()
+        }
+    }
+
+    /// Get visible lines, laid out for the screen
     pub fn visible_lines(&self) -> Vec<String> {
+        self.visible_segments()
+            .into_iter()
+            .map(|segments| segments.into_iter().map(|segment| segment.text).collect())
+            .collect()
+    }
+
+    /// Get visible lines as styled segments (control characters, invalid bytes, etc.)
+    pub fn visible_segments(&self) -> Vec<Vec<DisplaySegment>> {
         let raw_lines: Vec<&str> = self.content.lines().collect();
         let has_any_content = !self.content.is_empty();
 
@@ -155,36 +383,121 @@ impl TextBuffer {
         let start_line = self.scroll_line;
         let end_line = (start_line + self.height as usize).min(lines.len().max(1));
 
+        let table = self.table_layout();
         let mut result_lines = Vec::new();
         for line_idx in start_line..end_line {
+            // A table's header stays in view as it scrolls
+            let line_idx = if line_idx == start_line && self.header_pinned() {
+                0
+            } else {
+                line_idx
+            };
             let line_str = if line_idx < lines.len() { lines[line_idx] } else { "" };
-            let start_col = self.scroll_col.min(line_str.len());
-            let end_col = (start_col + self.width as usize).min(line_str.len());
-            let visible_line = &line_str[start_col..end_col];
-            result_lines.push(visible_line.to_string());
+            let aligned = table.as_ref().map(|(delimiter, widths)| {
+                align_fields(line_str, *delimiter, widths, &self.display)
+            });
+            // Columns of aligned text don't match the buffer's
+            let brackets: Vec<_> = self
+                .bracket_match
+                .iter()
+                .flatten()
+                .filter(|&&(line, _)| line == line_idx && aligned.is_none())
+                .map(|&(_, col)| (col..col + 1, DisplayKind::MatchParen))
+                .collect();
+            let overlength = overlength_start(line_str, self.display.text_width, &self.display)
+                .filter(|_| aligned.is_none())
+                .map(|start| (start..line_str.len(), DisplayKind::Overlength));
+            let selected = self
+                .selection
+                .filter(|_| aligned.is_none())
+                .filter(|[start, end]| (start.0..=end.0).contains(&line_idx))
+                .map(|[start, end]| {
+                    let from = if line_idx == start.0 { start.1 } else { 0 };
+                    let to = if line_idx == end.0 { end.1 } else { usize::MAX };
+                    (from..to.min(line_str.len()), DisplayKind::Selection)
+                });
+            let highlights: Vec<_> = selected
+                .into_iter()
+                .chain(brackets)
+                .chain(overlength)
+                .collect();
+            result_lines.push(render_line_highlighted(
+                aligned.as_deref().unwrap_or(line_str),
+                self.scroll_col,
+                self.width as usize,
+                &self.display,
+                &highlights,
+            ));
         }
 
-        if result_lines.is_empty() { result_lines.push(String::new()); }
+        if result_lines.is_empty() { result_lines.push(Vec::new()); }
         result_lines
     }
 
-    /// Get line numbers for display
-    pub fn line_numbers(&self) -> Vec<String> {
+    /// Screen column of the cursor within its line (before horizontal scrolling)
+    pub fn cursor_display_col(&self) -> usize {
+        let line = self.current_line();
+        let col = self.cursor_col.min(line.len());
+        match self.table_layout() {
+            Some((delimiter, widths)) => aligned_col(line, col, delimiter, &widths, &self.display),
+            None => display_width(&line[..col], &self.display),
+        }
+    }
+
+    /// Delimiter and column widths while the buffer is shown as a table,
+    /// measured over the header and the lines in view
+    fn table_layout(&self) -> Option<(char, Vec<usize>)> {
+        let delimiter = self.display.table?;
+        let header = self.content.lines().take(1);
+        let visible = self
+            .content
+            .lines()
+            .skip(self.scroll_line)
+            .take(self.height as usize);
+        let widths = column_widths(header.chain(visible), delimiter, &self.display);
+        Some((delimiter, widths))
+    }
+
+    /// Whether the first line is drawn in place of the top line in view, as
+    /// the header of a table scrolled down
+    pub fn header_pinned(&self) -> bool {
+        self.display.table.is_some() && self.scroll_line > 0
+    }
+
+    /// Get line numbers for display, right-aligned in a column `width` wide
+    /// including the space after them. With `relative` numbers, lines other
+    /// than the cursor line show their distance from it, and the cursor line
+    /// shows 0 unless `numbers` are on too.
+    pub fn line_numbers(&self, width: u16, numbers: bool, relative: bool) -> Vec<String> {
+        if width == 0 {
+            return Vec::new();
+        }
         let raw_lines: Vec<&str> = self.content.lines().collect();
         let total_lines = if raw_lines.is_empty() { 1 } else { raw_lines.len() };
 
         let start_line = self.scroll_line;
         let visible_height = self.height as usize;
         let end_line = (start_line + visible_height).min(total_lines);
+        let digits = width as usize - 1;
+        let number = |line: usize| match (relative, numbers) {
+            (true, false) => line.abs_diff(self.cursor_line),
+            (true, true) if line != self.cursor_line => line.abs_diff(self.cursor_line),
+            _ => line + 1,
+        };
 
         let mut line_numbers = Vec::new();
         for i in start_line..end_line {
-            line_numbers.push(format!("{:>4} ", i + 1));
+            let line = if i == start_line && self.header_pinned() {
+                0
+            } else {
+                i
+            };
+            line_numbers.push(format!("{:>digits$} ", number(line)));
         }
 
         // Ensure we always show at least one line number for empty buffers
         if line_numbers.is_empty() {
-            line_numbers.push(format!("{:>4} ", 1));
+            line_numbers.push(format!("{:>digits$} ", number(0)));
         }
 
         line_numbers
@@ -215,7 +528,7 @@ impl TextBuffer {
     /// Move cursor left
     pub fn move_cursor_left(&mut self) {
         if self.cursor_col > 0 {
-            self.cursor_col -= 1;
+            self.cursor_col = prev_char_boundary(self.current_line(), self.cursor_col);
             self.adjust_scroll();
         }
     }
@@ -224,7 +537,7 @@ impl TextBuffer {
     pub fn move_cursor_right(&mut self) {
         let current_line_len = self.current_line_length();
         if self.cursor_col < current_line_len {
-            self.cursor_col += 1;
+            self.cursor_col = next_char_boundary(self.current_line(), self.cursor_col);
             self.adjust_scroll();
         }
     }
@@ -254,11 +567,13 @@ impl TextBuffer {
         if self.cursor_line >= lines.len() { self.cursor_line = lines.len() - 1; }
         let line = &mut lines[self.cursor_line];
         if self.cursor_col > line.len() { self.cursor_col = line.len(); }
+        let old_line = line.clone();
         line.insert(self.cursor_col, ch);
+        self.reindex(&old_line, line);
 
         self.content = lines.join("\n");
-        self.cursor_col += 1;
-        self.modified = true;
+        self.cursor_col += ch.len_utf8();
+        self.update_modified();
         self.adjust_scroll();
     }
 
@@ -274,19 +589,22 @@ impl TextBuffer {
         if self.cursor_line >= lines.len() { return; }
         let line_len = lines[self.cursor_line].len();
 
+        let old_line = lines[self.cursor_line].clone();
         if self.cursor_col < line_len {
             // Delete within the line
             lines[self.cursor_line].remove(self.cursor_col);
+            self.reindex(&old_line, &lines[self.cursor_line]);
         } else if self.cursor_line + 1 < lines.len() {
             // Join with next line
             let next = lines.remove(self.cursor_line + 1);
             lines[self.cursor_line].push_str(&next);
+            self.reindex(&format!("{}\n{}", old_line, next), &lines[self.cursor_line]);
         } else {
             return;
         }
 
         self.content = lines.join("\n");
-        self.modified = true;
+        self.update_modified();
     }
 
     /// Delete character before cursor
@@ -303,15 +621,20 @@ impl TextBuffer {
         if self.cursor_col > 0 {
             // Remove character before cursor
             if self.cursor_col <= lines[self.cursor_line].len() {
-                lines[self.cursor_line].remove(self.cursor_col - 1);
-                self.cursor_col -= 1;
+                let old_line = lines[self.cursor_line].clone();
+                let prev = prev_char_boundary(&lines[self.cursor_line], self.cursor_col);
+                lines[self.cursor_line].remove(prev);
+                self.reindex(&old_line, &lines[self.cursor_line]);
+                self.cursor_col = prev;
             }
         } else if self.cursor_line > 0 {
             // Merge with previous line
             let current = lines.remove(self.cursor_line);
             self.cursor_line -= 1;
             let prev_len = lines[self.cursor_line].len();
+            let old = format!("{}\n{}", lines[self.cursor_line], current);
             lines[self.cursor_line].push_str(&current);
+            self.reindex(&old, &lines[self.cursor_line]);
             self.cursor_col = prev_len;
         } else {
             // At start of first line: nothing to do
@@ -319,7 +642,7 @@ impl TextBuffer {
         }
 
         self.content = lines.join("\n");
-        self.modified = true;
+        self.update_modified();
         self.adjust_scroll();
     }
 
@@ -337,6 +660,7 @@ impl TextBuffer {
         let split_at = self.cursor_col.min(current.len());
         let before = current[..split_at].to_string();
         let after = current[split_at..].to_string();
+        self.reindex(&current, &format!("{}\n{}", before, after));
 
         lines[self.cursor_line] = before;
         lines.insert(self.cursor_line + 1, after);
@@ -344,10 +668,212 @@ impl TextBuffer {
         self.content = lines.join("\n");
         self.cursor_line += 1;
         self.cursor_col = 0;
-        self.modified = true;
+        self.update_modified();
         self.adjust_scroll();
     }
 
+    /// Insert `text` at the cursor as a single change, leaving the cursor
+    /// after it
+    pub fn insert_text(&mut self, text: &str) {
+        self.replace_before_cursor(self.cursor_col, text);
+        if let Some(last_newline) = text.rfind('\n') {
+            let line = self.cursor_line + text.matches('\n').count();
+            self.set_cursor(line, text.len() - last_newline - 1);
+        }
+    }
+
+    /// Replace the text between byte column `start` and the cursor with `text`,
+    /// leaving the cursor after it
+    pub fn replace_before_cursor(&mut self, start: usize, text: &str) {
+        let mut lines: Vec<String> = self
+            .content
+            .lines()
+            .map(|s| s.to_string())
+            .collect();
+        if lines.is_empty() { lines.push(String::new()); }
+
+        if self.cursor_line >= lines.len() { return; }
+        let line = &mut lines[self.cursor_line];
+        let end = self.cursor_col.min(line.len());
+        let start = start.min(end);
+        let old_line = line.clone();
+        line.replace_range(start..end, text);
+        self.reindex(&old_line, line);
+
+        self.content = lines.join("\n");
+        self.cursor_col = start + text.len();
+        self.update_modified();
+        self.adjust_scroll();
+    }
+
+    /// Replace lines `start..=end` with `text` as a single change
+    pub fn replace_lines(&mut self, start: usize, end: usize, text: &str) {
+        let mut lines: Vec<String> = self
+            .content
+            .lines()
+            .map(|s| s.to_string())
+            .collect();
+        if lines.is_empty() { lines.push(String::new()); }
+
+        let end = end.min(lines.len() - 1);
+        let start = start.min(end);
+        self.reindex(&lines[start..=end].join("\n"), text);
+        lines.splice(start..=end, text.lines().map(|s| s.to_string()));
+
+        self.content = lines.join("\n");
+        self.cursor_line = start.min(lines.len().saturating_sub(1));
+        self.cursor_col = 0;
+        self.update_modified();
+        self.adjust_scroll();
+    }
+
+    /// Replace bytes `start..end` of the content with `text`.
+    ///
+    /// The range must lie on character boundaries; the cursor stays where it
+    /// was, clamped to the new content.
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        let end = end.min(self.content.len());
+        let start = start.min(end);
+        // Re-index whole lines so words cut by the range are counted correctly
+        let line_start = self.content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = self.content[end..]
+            .find('\n')
+            .map_or(self.content.len(), |i| end + i);
+        let old_lines = self.content[line_start..line_end].to_string();
+
+        self.content.replace_range(start..end, text);
+        let new_end = line_end + text.len() - (end - start);
+        let new_lines = self.content[line_start..new_end].to_string();
+        self.reindex(&old_lines, &new_lines);
+        self.update_modified();
+        self.set_cursor(self.cursor_line, self.cursor_col);
+    }
+
+    /// Line span of the paragraph under the cursor (`ip`); with `around`,
+    /// the blank lines after it are included too (`ap`)
+    pub fn paragraph_range(&self, around: bool) -> (usize, usize) {
+        let lines: Vec<&str> = self.content.lines().collect();
+        if lines.is_empty() {
+            return (0, 0);
+        }
+        let line = self.cursor_line.min(lines.len() - 1);
+        let blank = |i: usize| lines[i].trim().is_empty();
+        let on_blank = blank(line);
+
+        let mut start = line;
+        while start > 0 && blank(start - 1) == on_blank {
+            start -= 1;
+        }
+        let mut end = line;
+        while end + 1 < lines.len() && blank(end + 1) == on_blank {
+            end += 1;
+        }
+        if around {
+            while end + 1 < lines.len() && blank(end + 1) != on_blank {
+                end += 1;
+            }
+        }
+        (start, end)
+    }
+
+    /// Byte offset in the content of a line and byte column, clamped to the
+    /// line
+    pub fn offset_at(&self, line: usize, col: usize) -> usize {
+        let mut offset = 0;
+        for (i, text) in self.content.split('\n').enumerate() {
+            if i == line {
+                return offset + col.min(text.len());
+            }
+            offset += text.len() + 1;
+        }
+        self.content.len()
+    }
+
+    /// Line and byte column of a byte offset in the content
+    pub fn position_at(&self, offset: usize) -> (usize, usize) {
+        let before = &self.content[..offset.min(self.content.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (before.matches('\n').count(), before.len() - line_start)
+    }
+
+    /// Move the cursor to a line and byte column, clamped to the buffer
+    pub fn set_cursor(&mut self, line: usize, col: usize) {
+        self.cursor_line = line.min(self.content.lines().count().saturating_sub(1));
+        self.cursor_col = col;
+        self.adjust_cursor_to_line_length();
+        self.adjust_scroll();
+    }
+
+    /// `Ctrl+d` / `Ctrl+u`: scroll the view and the cursor by half a screen
+    pub fn scroll_half_page(&mut self, down: bool) {
+        let half = (self.height as usize / 2).max(1);
+        let last_line = self.content.lines().count().saturating_sub(1);
+        if down {
+            self.scroll_line = (self.scroll_line + half).min(last_line);
+            self.cursor_line = (self.cursor_line + half).min(last_line);
+        } else {
+            self.scroll_line = self.scroll_line.saturating_sub(half);
+            self.cursor_line = self.cursor_line.saturating_sub(half);
+        }
+        self.adjust_cursor_to_line_length();
+        self.adjust_scroll();
+    }
+
+    /// Move the cursor to the start of the next field of a table, or of the
+    /// previous one when `backward`, going on to the next or previous line
+    /// at either end
+    pub fn move_to_field(&mut self, backward: bool) {
+        let Some(delimiter) = self.display.table else {
+            return;
+        };
+        let line = self.current_line();
+        if let Some(col) = adjacent_field(line, self.cursor_col, delimiter, backward) {
+            self.cursor_col = col;
+        } else if backward && self.cursor_line > 0 {
+            self.cursor_line -= 1;
+            let line = self.current_line();
+            self.cursor_col = field_ranges(line, delimiter)
+                .last()
+                .map_or(0, |range| range.start);
+        } else if !backward && self.cursor_line + 1 < self.content.lines().count() {
+            self.cursor_line += 1;
+            self.cursor_col = 0;
+        }
+        self.adjust_cursor_to_line_length();
+        self.adjust_scroll();
+    }
+
+    /// Put `line` at the top of the view with the cursor on it, stopping once
+    /// the last line is at the bottom
+    pub fn scroll_to(&mut self, line: usize) {
+        let last_top = self
+            .content
+            .lines()
+            .count()
+            .saturating_sub(self.height as usize);
+        self.scroll_line = line.min(last_top);
+        self.cursor_line = self.scroll_line;
+        self.cursor_col = 0;
+        self.adjust_scroll();
+    }
+
+    /// Add `text` at the end of the buffer, such as output arriving on a
+    /// stream. The cursor stays where it is and the buffer isn't marked modified.
+    pub fn append(&mut self, text: &str) {
+        let line_start = self.content.rfind('\n').map_or(0, |i| i + 1);
+        let old_line = self.content[line_start..].to_string();
+        self.content.push_str(text);
+        let new_line = format!("{}{}", old_line, text);
+        self.reindex(&old_line, &new_line);
+    }
+
+    /// Update the word index and the overlong line count for whole lines
+    /// `old` being replaced by `new`
+    fn reindex(&mut self, old: &str, new: &str) {
+        self.word_index.update(old, new);
+        self.overlength.update(old, new, &self.display);
+    }
+
     /// Get current line length
     fn current_line_length(&self) -> usize {
         let lines: Vec<&str> = self.content.lines().collect();
@@ -361,31 +887,68 @@ impl TextBuffer {
         }
     }
 
+    /// Get the text of the cursor line
+    pub fn current_line(&self) -> &str {
+        self.content.lines().nth(self.cursor_line).unwrap_or("")
+    }
+
+    /// Get the character under the cursor together with any combining marks after it
+    pub fn cursor_cluster(&self) -> Option<&str> {
+        let line = self.current_line();
+        let mut start = self.cursor_col.min(line.len());
+        while !line.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        let mut chars = line[start..].char_indices();
+        chars.next()?;
+        let end = chars
+            .find(|&(_, ch)| !crate::tui::charinfo::is_combining_mark(ch))
+            .map(|(i, _)| start + i)
+            .unwrap_or(line.len());
+        Some(&line[start..end])
+    }
+
     /// Adjust cursor position to fit within line
     fn adjust_cursor_to_line_length(&mut self) {
         let line_len = self.current_line_length();
         if self.cursor_col > line_len {
             self.cursor_col = line_len;
         }
+        let line = self.current_line();
+        let mut col = self.cursor_col;
+        while !line.is_char_boundary(col) {
+            col -= 1;
+        }
+        self.cursor_col = col;
     }
 
     /// Adjust scroll position to keep cursor visible
     fn adjust_scroll(&mut self) {
         let cursor_screen_line = self.cursor_line.saturating_sub(self.scroll_line);
-        let cursor_screen_col = self.cursor_col.saturating_sub(self.scroll_col);
 
         // Vertical scrolling
         if cursor_screen_line >= self.height as usize {
-            self.scroll_line = self.cursor_line.saturating_sub(self.height as usize - 1);
+            self.scroll_line = self
+                .cursor_line
+                .saturating_sub((self.height as usize).saturating_sub(1));
         } else if self.cursor_line < self.scroll_line {
             self.scroll_line = self.cursor_line;
         }
+        // The top row of a scrolled table shows its header instead
+        if self.header_pinned() && self.cursor_line == self.scroll_line {
+            self.scroll_line -= 1;
+        }
+
+        // Table columns are measured over the lines in view, so after scrolling
+        let cursor_col = self.cursor_display_col();
+        let cursor_screen_col = cursor_col.saturating_sub(self.scroll_col);
 
         // Horizontal scrolling
         if cursor_screen_col >= self.width as usize {
-            self.scroll_col = self.cursor_col.saturating_sub(self.width as usize - 1);
-        } else if self.cursor_col < self.scroll_col {
-            self.scroll_col = self.cursor_col;
+            self.scroll_col = cursor_col.saturating_sub((self.width as usize).saturating_sub(1));
+        } else if cursor_col < self.scroll_col {
+            self.scroll_col = cursor_col;
         }
     }
 
@@ -407,10 +970,44 @@ impl TextBuffer {
             if line_count == 0 { 1 } else { line_count }
         };
         
-        format!("{}{} - {}/{} lines", file_name, modified_indicator, line_info, lines_count)
+        let overlong = match self.overlength.get() {
+            0 => String::new(),
+            count => format!(" [{} overlong]", count),
+        };
+
+        format!(
+            "{}{} - {}/{} lines{}",
+            file_name, modified_indicator, line_info, lines_count, overlong
+        )
     }
 }
 
+/// Hash of a buffer's content, to tell whether it is back to what was saved
+fn content_hash(content: &str) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Byte offset of the character boundary before `col` in `line`
+fn prev_char_boundary(line: &str, col: usize) -> usize {
+    let mut prev = col.min(line.len()).saturating_sub(1);
+    while !line.is_char_boundary(prev) {
+        prev -= 1;
+    }
+    prev
+}
+
+/// Byte offset of the character boundary after `col` in `line`
+fn next_char_boundary(line: &str, col: usize) -> usize {
+    let mut next = (col + 1).min(line.len());
+    while !line.is_char_boundary(next) {
+        next += 1;
+    }
+    next
+}
+
 /// Buffer manager for multiple buffers
 pub struct BufferManager {
     buffers: Vec<TextBuffer>,
@@ -433,6 +1030,10 @@ impl BufferManager {
         self.buffers.get_mut(self.current_buffer)
     }
 
+    pub fn current_index(&self) -> usize {
+        self.current_buffer
+    }
+
     pub fn add_buffer(&mut self, buffer: TextBuffer) {
         self.buffers.push(buffer);
         self.current_buffer = self.buffers.len() - 1;
@@ -448,6 +1049,24 @@ impl BufferManager {
         self.buffers.len()
     }
 
+    /// Iterate over all buffers
+    pub fn iter(&self) -> impl Iterator<Item = &TextBuffer> {
+        self.buffers.iter()
+    }
+
+    /// Index of the buffer editing the given file. On file systems that
+    /// ignore case, `Foo.txt` finds the buffer of `foo.txt`.
+    pub fn position_by_path(&self, path: &Path) -> Option<usize> {
+        let key = niv_fs::path_key(path);
+        self.buffers.iter().position(|buffer| is_file(buffer, &key))
+    }
+
+    /// Find the buffer editing the given file
+    pub fn find_by_path_mut(&mut self, path: &Path) -> Option<&mut TextBuffer> {
+        let key = niv_fs::path_key(path);
+        self.buffers.iter_mut().find(|buffer| is_file(buffer, &key))
+    }
+
     pub fn close_current_buffer(&mut self) -> bool {
         if self.buffers.len() > 1 {
             self.buffers.remove(self.current_buffer);
@@ -461,6 +1080,14 @@ impl BufferManager {
     }
 }
 
+/// Check whether `buffer` edits the file with [`niv_fs::path_key`] `key`
+fn is_file(buffer: &TextBuffer, key: &Path) -> bool {
+    buffer
+        .file_path
+        .as_deref()
+        .is_some_and(|path| niv_fs::path_key(path) == key)
+}
+
 impl Default for BufferManager {
     fn default() -> Self {
         Self::new()
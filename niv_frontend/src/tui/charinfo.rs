@@ -0,0 +1,86 @@
+/// Names of the C0 control characters, indexed by code point
+const C0_NAMES: [&str; 32] = [
+    "NUL", "SOH", "STX", "ETX", "EOT", "ENQ", "ACK", "BEL", "BS", "HT", "LF", "VT", "FF", "CR",
+    "SO", "SI", "DLE", "DC1", "DC2", "DC3", "DC4", "NAK", "SYN", "ETB", "CAN", "EM", "SUB", "ESC",
+    "FS", "GS", "RS", "US",
+];
+
+/// Check whether a character is a combining mark that attaches to the previous character
+pub fn is_combining_mark(ch: char) -> bool {
+    matches!(ch as u32,
+        0x0300..=0x036F   // Combining Diacritical Marks
+        | 0x1AB0..=0x1AFF // Combining Diacritical Marks Extended
+        | 0x1DC0..=0x1DFF // Combining Diacritical Marks Supplement
+        | 0x20D0..=0x20FF // Combining Diacritical Marks for Symbols
+        | 0xFE20..=0xFE2F // Combining Half Marks
+        | 0x200D          // Zero width joiner
+        | 0xFE00..=0xFE0F // Variation selectors
+    )
+}
+
+/// Name of a character, for the characters niv knows about
+pub fn char_name(ch: char) -> Option<&'static str> {
+    let code = ch as u32;
+    if code < 0x20 {
+        return Some(C0_NAMES[code as usize]);
+    }
+    let name = match code {
+        0x20 => "SPACE",
+        0x7F => "DELETE",
+        0xA0 => "NO-BREAK SPACE",
+        0xAD => "SOFT HYPHEN",
+        0x200B => "ZERO WIDTH SPACE",
+        0x200C => "ZERO WIDTH NON-JOINER",
+        0x200D => "ZERO WIDTH JOINER",
+        0x200E => "LEFT-TO-RIGHT MARK",
+        0x200F => "RIGHT-TO-LEFT MARK",
+        0x2028 => "LINE SEPARATOR",
+        0x2029 => "PARAGRAPH SEPARATOR",
+        0x2060 => "WORD JOINER",
+        0xFEFF => "ZERO WIDTH NO-BREAK SPACE",
+        0xFFFD => "REPLACEMENT CHARACTER",
+        _ => return None,
+    };
+    Some(name)
+}
+
+/// Describe a character the way `ga` does: printable form, decimal, hex, octal and code point
+pub fn describe_char(ch: char) -> String {
+    let code = ch as u32;
+    let shown = if ch.is_control() {
+        match code {
+            0x00..=0x1F => format!("^{}", char::from_u32(code + 0x40).unwrap_or('?')),
+            0x7F => "^?".to_string(),
+            _ => format!("<{:x}>", code),
+        }
+    } else {
+        ch.to_string()
+    };
+
+    let mut text = format!(
+        "<{}> {}, Hex {:02x}, Oct {:03o}, U+{:04X}",
+        shown, code, code, code, code
+    );
+    if let Some(name) = char_name(ch) {
+        text.push_str(&format!(", {}", name));
+    }
+    text
+}
+
+/// Describe the UTF-8 encoding of a character cluster the way `g8` does.
+///
+/// Combining characters are separated from the base character with `+`.
+pub fn describe_utf8(cluster: &str) -> String {
+    cluster
+        .chars()
+        .map(|ch| {
+            let mut buf = [0u8; 4];
+            ch.encode_utf8(&mut buf)
+                .bytes()
+                .map(|b| format!("{:02x}", b))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
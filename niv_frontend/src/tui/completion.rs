@@ -0,0 +1,94 @@
+/// What an insert mode completion completes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    /// Words from the open buffers (`Ctrl+n` / `Ctrl+p`)
+    Keyword,
+    /// File system paths (`Ctrl+x Ctrl+f`)
+    FileName,
+}
+
+impl CompletionKind {
+    /// Mode text shown while completing, as in vim
+    pub fn mode_text(self) -> &'static str {
+        match self {
+            CompletionKind::Keyword => "-- Keyword completion (^N^P)",
+            CompletionKind::FileName => "-- File name completion (^F^N^P)",
+        }
+    }
+}
+
+/// Insert mode completion in progress: the candidates for the text between
+/// `start` and the cursor, and which one is currently inserted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub kind: CompletionKind,
+    /// Byte column where the completed text starts
+    pub start: usize,
+    /// Text that was typed before completion started
+    pub original: String,
+    pub candidates: Vec<String>,
+    /// Index of the inserted candidate; `None` shows the original text
+    pub selected: Option<usize>,
+}
+
+impl Completion {
+    pub fn new(
+        kind: CompletionKind,
+        start: usize,
+        original: String,
+        candidates: Vec<String>,
+    ) -> Self {
+        Self {
+            kind,
+            start,
+            original,
+            candidates,
+            selected: None,
+        }
+    }
+
+    /// Text to show for the current selection
+    pub fn current(&self) -> &str {
+        self.selected
+            .and_then(|i| self.candidates.get(i))
+            .unwrap_or(&self.original)
+    }
+
+    /// Select the next candidate, wrapping around through the original text
+    pub fn select_next(&mut self) {
+        self.selected = match self.selected {
+            None if !self.candidates.is_empty() => Some(0),
+            Some(i) if i + 1 < self.candidates.len() => Some(i + 1),
+            _ => None,
+        };
+    }
+
+    /// Select the previous candidate, wrapping around through the original text
+    pub fn select_prev(&mut self) {
+        self.selected = match self.selected {
+            None => self.candidates.len().checked_sub(1),
+            Some(0) => None,
+            Some(i) => Some(i - 1),
+        };
+    }
+}
+
+/// Byte column where the file path ending at `col` starts
+pub fn path_start(line: &str, col: usize) -> usize {
+    let is_path_char = |ch: char| !ch.is_whitespace() && !"\"'`()[]{}<>=,;|".contains(ch);
+    line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, ch)| is_path_char(ch))
+        .last()
+        .map_or(col, |(i, _)| i)
+}
+
+/// Split a partial path into its directory part (with the trailing separator)
+/// and the partial file name after it
+pub fn split_path_prefix(prefix: &str) -> (&str, &str) {
+    match prefix.rfind(['/', '\\']) {
+        Some(i) => prefix.split_at(i + 1),
+        None => ("", prefix),
+    }
+}
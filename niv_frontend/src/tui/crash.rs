@@ -0,0 +1,168 @@
+//! Crash reports written when the editor panics.
+//!
+//! The panic hook can't reach the editor, so the editor keeps a small
+//! [`CrashContext`] up to date: the last ex commands run and the paths of the
+//! open files. File contents are never included.
+
+use crossterm::{
+    event::{DisableFocusChange, DisableMouseCapture, PopKeyboardEnhancementFlags},
+    execute,
+    terminal::{LeaveAlternateScreen, disable_raw_mode},
+};
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, Once, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Ex commands kept for the report
+const MAX_RECENT_COMMANDS: usize = 20;
+
+/// What the editor was doing, for the crash report
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrashContext {
+    /// Most recent last
+    pub recent_commands: VecDeque<String>,
+    pub open_files: Vec<PathBuf>,
+}
+
+impl CrashContext {
+    pub const fn new() -> Self {
+        Self {
+            recent_commands: VecDeque::new(),
+            open_files: Vec::new(),
+        }
+    }
+
+    pub fn record_command(&mut self, command: &str) {
+        if self.recent_commands.len() == MAX_RECENT_COMMANDS {
+            self.recent_commands.pop_front();
+        }
+        self.recent_commands.push_back(command.to_string());
+    }
+}
+
+static CRASH_CONTEXT: Mutex<CrashContext> = Mutex::new(CrashContext::new());
+
+/// Remember an ex command for the crash report
+pub fn record_crash_command(command: &str) {
+    if let Ok(mut context) = CRASH_CONTEXT.lock() {
+        context.record_command(command);
+    }
+}
+
+/// Set the open files listed in the crash report
+pub fn set_crash_open_files<'a>(files: impl Iterator<Item = &'a Path>) {
+    if let Ok(mut context) = CRASH_CONTEXT.lock() {
+        context.open_files.clear();
+        context.open_files.extend(files.map(Path::to_path_buf));
+    }
+}
+
+/// Directory crash reports are written to: `crash` in the cache directory
+/// (e.g. `~/.cache/niv/crash`)
+pub fn crash_dir() -> PathBuf {
+    niv_fs::paths::cache_dir().join("crash")
+}
+
+/// Text of a crash report
+pub fn crash_report(
+    message: &str,
+    location: Option<String>,
+    backtrace: &str,
+    context: &CrashContext,
+    time: SystemTime,
+) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "niv {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "time: {}", niv_fs::format_timestamp(time));
+    let _ = writeln!(
+        report,
+        "os: {} {} ({})",
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        std::env::consts::FAMILY
+    );
+    let _ = writeln!(report, "panic: {}", message);
+    if let Some(location) = location {
+        let _ = writeln!(report, "location: {}", location);
+    }
+
+    let _ = writeln!(report, "\nrecent commands:");
+    for command in &context.recent_commands {
+        let _ = writeln!(report, "  :{}", command);
+    }
+    let _ = writeln!(report, "\nopen files:");
+    for file in &context.open_files {
+        let _ = writeln!(report, "  {}", file.display());
+    }
+    let _ = writeln!(report, "\nbacktrace:\n{}", backtrace);
+    report
+}
+
+/// Write a report for a panic, returning its path
+fn write_crash_report(info: &PanicHookInfo<'_>) -> io::Result<PathBuf> {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message.to_string(),
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.clone(),
+            None => "unknown panic".to_string(),
+        },
+    };
+    let location = info
+        .location()
+        .map(|location| format!("{}:{}", location.file(), location.line()));
+    // `try_lock`, as the panic may have happened while the context was locked
+    let context = match CRASH_CONTEXT.try_lock() {
+        Ok(context) => context.clone(),
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => CrashContext::default(),
+    };
+    let time = SystemTime::now();
+    let report = crash_report(
+        &message,
+        location,
+        &Backtrace::force_capture().to_string(),
+        &context,
+        time,
+    );
+
+    let dir = crash_dir();
+    fs::create_dir_all(&dir)?;
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = dir.join(format!("crash-{}-{}.txt", secs, std::process::id()));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+/// Install a panic hook that restores the terminal, writes a crash report and
+/// prints its path before the default panic message. Only the first call has
+/// an effect.
+pub fn install_crash_handler() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = execute!(
+                io::stdout(),
+                PopKeyboardEnhancementFlags,
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                DisableFocusChange
+            );
+            let _ = disable_raw_mode();
+            match write_crash_report(info) {
+                Ok(path) => eprintln!("niv crashed; report written to {}", path.display()),
+                Err(e) => eprintln!("niv crashed; could not write crash report: {}", e),
+            }
+            default_hook(info);
+        }));
+    });
+}
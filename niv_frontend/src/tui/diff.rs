@@ -0,0 +1,148 @@
+use niv_rope::Rope;
+
+pub use niv_rope::{LineDiff, diff_lines};
+
+/// How a line of the original text compares with the buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffRowKind {
+    Same,
+    /// Replaced by the buffer line next to it
+    Changed,
+    /// Not in the buffer any more
+    Removed,
+    /// Filler next to a line only the buffer has
+    Added,
+}
+
+/// A row of the original side of a diff, lined up with the buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRow {
+    /// Original line; empty for [`DiffRowKind::Added`] filler
+    pub text: String,
+    pub kind: DiffRowKind,
+    /// Buffer line the row is shown next to; removed lines go next to the
+    /// line after them
+    pub buffer_line: usize,
+}
+
+/// The original text of a buffer, such as its file on disk, lined up row by
+/// row with the buffer as it is now, using [`Rope::diff`]
+#[derive(Debug, Clone, Default)]
+pub struct DiffView {
+    original: String,
+    /// Buffer text the rows were computed for
+    text: String,
+    rows: Vec<DiffRow>,
+}
+
+impl DiffView {
+    pub fn new(original: String) -> Self {
+        let mut view = Self {
+            original,
+            text: String::new(),
+            rows: Vec::new(),
+        };
+        view.rows = view.compute_rows();
+        view
+    }
+
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Whether the rows are those of `text` already
+    pub fn is_current(&self, text: &str) -> bool {
+        text == self.text
+    }
+
+    /// Diff against `text`; nothing is done when it didn't change. Returns
+    /// whether the rows changed.
+    pub fn update(&mut self, text: &str) -> bool {
+        if text == self.text {
+            return false;
+        }
+        self.text = text.to_string();
+        let rows = self.compute_rows();
+        let changed = rows != self.rows;
+        self.rows = rows;
+        changed
+    }
+
+    pub fn rows(&self) -> &[DiffRow] {
+        &self.rows
+    }
+
+    /// Number of rows of `kind`
+    pub fn count(&self, kind: DiffRowKind) -> usize {
+        self.rows.iter().filter(|row| row.kind == kind).count()
+    }
+
+    /// Up to `rows` rows, starting with those next to buffer line `top`
+    pub fn visible(&self, top: usize, rows: usize) -> &[DiffRow] {
+        let start = self.rows.partition_point(|row| row.buffer_line < top);
+        &self.rows[start..(start + rows).min(self.rows.len())]
+    }
+
+    /// How buffer line `line` differs from the original: changed, added, or
+    /// removed when original lines were deleted right before it
+    pub fn line_change(&self, line: usize) -> Option<DiffRowKind> {
+        let start = self.rows.partition_point(|row| row.buffer_line < line);
+        let kinds: Vec<DiffRowKind> = self.rows[start..]
+            .iter()
+            .take_while(|row| row.buffer_line == line)
+            .map(|row| row.kind)
+            .collect();
+        [
+            DiffRowKind::Changed,
+            DiffRowKind::Added,
+            DiffRowKind::Removed,
+        ]
+        .into_iter()
+        .find(|kind| kinds.contains(kind))
+    }
+
+    fn compute_rows(&self) -> Vec<DiffRow> {
+        let old: Vec<&str> = self.original.lines().collect();
+        let hunks = line_rope(&self.original).diff(&line_rope(&self.text));
+        let mut rows = Vec::new();
+        // Lines of both texts before the next hunk are the same
+        let (mut old_line, mut new_line) = (0, 0);
+        for hunk in hunks {
+            for text in &old[old_line..hunk.old_lines.start] {
+                rows.push(row(text, DiffRowKind::Same, new_line));
+                new_line += 1;
+            }
+            let (removed, added) = (hunk.old_lines, hunk.new_lines);
+            let changed = removed.len().min(added.len());
+            for (line, buffer_line) in removed.clone().zip(added.clone()) {
+                rows.push(row(old[line], DiffRowKind::Changed, buffer_line));
+            }
+            for buffer_line in added.start + changed..added.end {
+                rows.push(row("", DiffRowKind::Added, buffer_line));
+            }
+            for text in &old[removed.start + changed..removed.end] {
+                rows.push(row(text, DiffRowKind::Removed, added.end));
+            }
+            (old_line, new_line) = (removed.end, added.end);
+        }
+        for text in &old[old_line..] {
+            rows.push(row(text, DiffRowKind::Same, new_line));
+            new_line += 1;
+        }
+        rows
+    }
+}
+
+/// `text` with every line ending in a newline, so that a missing one at the
+/// end or a CRLF doesn't count as a change
+fn line_rope(text: &str) -> Rope {
+    text.lines().flat_map(|line| [line, "\n"]).collect()
+}
+
+fn row(text: &str, kind: DiffRowKind, buffer_line: usize) -> DiffRow {
+    DiffRow {
+        text: text.to_string(),
+        kind,
+        buffer_line,
+    }
+}
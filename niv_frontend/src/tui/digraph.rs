@@ -0,0 +1,46 @@
+/// Built-in digraph table, a subset of RFC 1345 as used by vim
+#[rustfmt::skip]
+const DIGRAPHS: &[(&str, char)] = &[
+    // Latin-1 letters with diacritics
+    ("A!", 'À'), ("A'", 'Á'), ("A>", 'Â'), ("A?", 'Ã'), ("A:", 'Ä'), ("AA", 'Å'), ("AE", 'Æ'),
+    ("C,", 'Ç'), ("E!", 'È'), ("E'", 'É'), ("E>", 'Ê'), ("E:", 'Ë'), ("I!", 'Ì'), ("I'", 'Í'),
+    ("I>", 'Î'), ("I:", 'Ï'), ("D-", 'Ð'), ("N?", 'Ñ'), ("O!", 'Ò'), ("O'", 'Ó'), ("O>", 'Ô'),
+    ("O?", 'Õ'), ("O:", 'Ö'), ("O/", 'Ø'), ("U!", 'Ù'), ("U'", 'Ú'), ("U>", 'Û'), ("U:", 'Ü'),
+    ("Y'", 'Ý'), ("TH", 'Þ'), ("ss", 'ß'), ("a!", 'à'), ("a'", 'á'), ("a>", 'â'), ("a?", 'ã'),
+    ("a:", 'ä'), ("aa", 'å'), ("ae", 'æ'), ("c,", 'ç'), ("e!", 'è'), ("e'", 'é'), ("e>", 'ê'),
+    ("e:", 'ë'), ("i!", 'ì'), ("i'", 'í'), ("i>", 'î'), ("i:", 'ï'), ("d-", 'ð'), ("n?", 'ñ'),
+    ("o!", 'ò'), ("o'", 'ó'), ("o>", 'ô'), ("o?", 'õ'), ("o:", 'ö'), ("o/", 'ø'), ("u!", 'ù'),
+    ("u'", 'ú'), ("u>", 'û'), ("u:", 'ü'), ("y'", 'ý'), ("th", 'þ'), ("y:", 'ÿ'),
+    // Latin Extended-A
+    ("OE", 'Œ'), ("oe", 'œ'), ("S<", 'Š'), ("s<", 'š'), ("Z<", 'Ž'), ("z<", 'ž'), ("C<", 'Č'),
+    ("c<", 'č'), ("L/", 'Ł'), ("l/", 'ł'), ("g(", 'ğ'), ("G(", 'Ğ'), ("i.", 'ı'), ("I.", 'İ'),
+    // Punctuation and symbols
+    ("NS", '\u{a0}'), ("!I", '¡'), ("?I", '¿'), ("Ct", '¢'), ("Pd", '£'), ("Ye", '¥'), ("Eu", '€'),
+    ("SE", '§'), ("Co", '©'), ("Rg", '®'), ("<<", '«'), (">>", '»'), ("DG", '°'), ("+-", '±'),
+    ("2S", '²'), ("3S", '³'), ("My", 'µ'), ("PI", '¶'), (".M", '·'), ("14", '¼'), ("12", '½'),
+    ("34", '¾'), ("*X", '×'), ("-:", '÷'), ("-N", '–'), ("-M", '—'), ("'6", '‘'), ("'9", '’'),
+    ("\"6", '“'), ("\"9", '”'), (".9", '‚'), (":9", '„'), ("..", '‥'), (",.", '…'), ("oo", '•'),
+    ("TM", '™'), ("->", '→'), ("<-", '←'), ("-!", '↑'), ("-v", '↓'), ("=>", '⇒'), ("<=", '⇐'),
+    ("==", '⇔'), ("!=", '≠'), ("=<", '≤'), (">=", '≥'), ("?=", '≅'), ("?2", '≈'), ("00", '∞'),
+    ("FA", '∀'), ("dP", '∂'), ("TE", '∃'), ("(-", '∈'), ("RT", '√'), ("OK", '✓'), ("XX", '✗'),
+    // Greek
+    ("a*", 'α'), ("b*", 'β'), ("g*", 'γ'), ("d*", 'δ'), ("e*", 'ε'), ("z*", 'ζ'), ("y*", 'η'),
+    ("h*", 'θ'), ("i*", 'ι'), ("k*", 'κ'), ("l*", 'λ'), ("m*", 'μ'), ("n*", 'ν'), ("c*", 'ξ'),
+    ("o*", 'ο'), ("p*", 'π'), ("r*", 'ρ'), ("s*", 'σ'), ("t*", 'τ'), ("u*", 'υ'), ("f*", 'φ'),
+    ("x*", 'χ'), ("q*", 'ψ'), ("w*", 'ω'), ("A*", 'Α'), ("B*", 'Β'), ("G*", 'Γ'), ("D*", 'Δ'),
+    ("H*", 'Θ'), ("L*", 'Λ'), ("P*", 'Π'), ("S*", 'Σ'), ("F*", 'Φ'), ("Q*", 'Ψ'), ("W*", 'Ω'),
+];
+
+/// Look up the character for a digraph.
+///
+/// Like vim, the reversed pair is tried when the given order is not in the table,
+/// so both `a:` and `:a` produce `ä`.
+pub fn lookup_digraph(first: char, second: char) -> Option<char> {
+    let find = |a: char, b: char| {
+        DIGRAPHS.iter().find_map(|(pair, ch)| {
+            let mut chars = pair.chars();
+            (chars.next() == Some(a) && chars.next() == Some(b)).then_some(*ch)
+        })
+    };
+    find(first, second).or_else(|| find(second, first))
+}
@@ -0,0 +1,211 @@
+use crate::tui::charinfo::is_combining_mark;
+use crate::tui::swatch::color_codes;
+use std::ops::Range;
+
+/// Options controlling how buffer text is laid out on screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Width of a tab stop in cells
+    pub tab_width: usize,
+    /// Show BOM and zero-width characters as `<xxxx>` instead of hiding them
+    pub show_invisible: bool,
+    /// Delimiter of the fields to line up in columns (`:CsvAlign`)
+    pub table: Option<char>,
+    /// Draw color codes (`#RRGGBB`, `rgb()`, ANSI) on the color they name
+    pub color_swatches: bool,
+    /// Highlight text past this column (`textwidth`); 0 disables
+    pub text_width: usize,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            tab_width: 4,
+            show_invisible: false,
+            table: None,
+            color_swatches: false,
+            text_width: 0,
+        }
+    }
+}
+
+/// How a run of displayed text should be styled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayKind {
+    /// Ordinary text
+    Text,
+    /// Control character shown as `^X`
+    Control,
+    /// Undisplayable or undecodable character shown as `<xx>`
+    Invalid,
+    /// BOM or zero-width character shown as `<xxxx>`
+    Invisible,
+    /// Color code drawn on the color it names
+    Swatch([u8; 3]),
+    /// Bracket at the cursor or the one matching it
+    MatchParen,
+    /// Text past `textwidth`
+    Overlength,
+    /// Text selected in visual or select mode
+    Selection,
+}
+
+/// A run of screen text with a single style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplaySegment {
+    pub text: String,
+    pub kind: DisplayKind,
+}
+
+/// Check whether a character is invisible when printed (BOM, zero-width spaces, direction marks)
+pub fn is_invisible_char(ch: char) -> bool {
+    matches!(ch as u32,
+        0x200B..=0x200F   // Zero width space, joiners and direction marks
+        | 0x2028..=0x202E // Separators and embedding controls
+        | 0x2060..=0x2064 // Word joiner and invisible operators
+        | 0xFEFF          // BOM / zero width no-break space
+        | 0xAD            // Soft hyphen
+    )
+}
+
+/// Check whether a character occupies two terminal cells
+pub fn is_wide_char(ch: char) -> bool {
+    matches!(ch as u32,
+        0x1100..=0x115F     // Hangul Jamo
+        | 0x2E80..=0x303E   // CJK radicals and punctuation
+        | 0x3041..=0x33FF   // Kana and CJK compatibility
+        | 0x3400..=0x4DBF   // CJK Extension A
+        | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+        | 0xA000..=0xA4CF   // Yi
+        | 0xAC00..=0xD7A3   // Hangul syllables
+        | 0xF900..=0xFAFF   // CJK compatibility ideographs
+        | 0xFE30..=0xFE4F   // CJK compatibility forms
+        | 0xFF00..=0xFF60   // Fullwidth forms
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F // Pictographs and emoticons
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD // CJK Extensions B and later
+    )
+}
+
+/// Screen representation of a character that starts at display column `col`
+fn char_repr(ch: char, col: usize, options: &DisplayOptions) -> (String, DisplayKind) {
+    let code = ch as u32;
+    match ch {
+        '\t' => {
+            let width = options.tab_width.max(1);
+            (" ".repeat(width - col % width), DisplayKind::Text)
+        }
+        _ if code < 0x20 => (
+            format!("^{}", char::from_u32(code + 0x40).unwrap_or('?')),
+            DisplayKind::Control,
+        ),
+        '\u{7f}' => ("^?".to_string(), DisplayKind::Control),
+        // C1 controls are what stray bytes of a mis-detected 8-bit file decode to
+        _ if (0x80..=0x9F).contains(&code) => (format!("<{:02x}>", code), DisplayKind::Invalid),
+        // Bytes that were not valid in the file's encoding
+        '\u{fffd}' => ("<fffd>".to_string(), DisplayKind::Invalid),
+        _ if is_invisible_char(ch) => {
+            if options.show_invisible {
+                (format!("<{:04x}>", code), DisplayKind::Invisible)
+            } else {
+                (String::new(), DisplayKind::Invisible)
+            }
+        }
+        _ if ch.is_control() => (format!("<{:x}>", code), DisplayKind::Invalid),
+        _ => (ch.to_string(), DisplayKind::Text),
+    }
+}
+
+/// Number of cells a character takes when it starts at display column `col`
+pub fn char_width(ch: char, col: usize, options: &DisplayOptions) -> usize {
+    match ch {
+        _ if is_combining_mark(ch) && !is_invisible_char(ch) => 0,
+        _ if is_wide_char(ch) => 2,
+        _ => {
+            let (text, _) = char_repr(ch, col, options);
+            text.chars().count()
+        }
+    }
+}
+
+/// Display width of `text` when it starts at column 0
+pub fn display_width(text: &str, options: &DisplayOptions) -> usize {
+    text.chars()
+        .fold(0, |col, ch| col + char_width(ch, col, options))
+}
+
+/// Lay out a line for the screen, skipping the first `skip` display columns and
+/// keeping at most `max_width` columns.
+///
+/// Cells cut in half by either edge (tabs, `^X`, wide characters) are padded with spaces.
+pub fn render_line(
+    line: &str,
+    skip: usize,
+    max_width: usize,
+    options: &DisplayOptions,
+) -> Vec<DisplaySegment> {
+    render_line_highlighted(line, skip, max_width, options, &[])
+}
+
+/// [`render_line`], drawing the text in the byte ranges of `highlights` in
+/// their kind instead
+pub fn render_line_highlighted(
+    line: &str,
+    skip: usize,
+    max_width: usize,
+    options: &DisplayOptions,
+    highlights: &[(Range<usize>, DisplayKind)],
+) -> Vec<DisplaySegment> {
+    let end = skip + max_width;
+    let mut segments: Vec<DisplaySegment> = Vec::new();
+    let mut col = 0;
+    let swatches: Vec<(Range<usize>, DisplayKind)> = if options.color_swatches {
+        color_codes(line)
+            .into_iter()
+            .map(|code| (code.range, DisplayKind::Swatch(code.rgb)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    for (i, ch) in line.char_indices() {
+        if col >= end {
+            break;
+        }
+        let width = char_width(ch, col, options);
+        let (repr, mut kind) = char_repr(ch, col, options);
+        let highlight = highlights
+            .iter()
+            .chain(&swatches)
+            .find(|(range, _)| range.contains(&i));
+        if let (DisplayKind::Text, Some((_, highlight))) = (kind, highlight) {
+            kind = *highlight;
+        }
+        let start = col;
+        col += width;
+        if start < skip && col <= skip {
+            continue;
+        }
+
+        let (text, kind) = if start >= skip && col <= end {
+            (repr, kind)
+        } else {
+            // Partially visible: keep the layout by padding with spaces
+            (
+                " ".repeat(col.min(end) - start.max(skip)),
+                DisplayKind::Text,
+            )
+        };
+        if text.is_empty() {
+            continue;
+        }
+
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(&text),
+            _ => segments.push(DisplaySegment { text, kind }),
+        }
+    }
+
+    segments
+}
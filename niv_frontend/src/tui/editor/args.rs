@@ -0,0 +1,118 @@
+use super::{Editor, MessageType};
+use crate::tui::arglist::ArgList;
+use crate::tui::interrupt::interrupted;
+use std::path::{Path, PathBuf};
+
+impl Editor {
+    /// Open the files given on the command line and make them the argument list
+    pub fn open_args(&mut self, files: Vec<PathBuf>) {
+        self.open_paths(&files);
+        self.arg_list = ArgList::new(files);
+    }
+
+    /// `:args {files}`: replace the argument list with the expanded files and edit the first
+    pub(crate) fn set_arg_list(&mut self, args: &str) {
+        let mut paths = Vec::new();
+        for pattern in args.split_whitespace() {
+            match self.expand_file_pattern(pattern) {
+                Ok(expanded) => paths.extend(expanded),
+                Err(e) => {
+                    self.set_message(e, MessageType::Error);
+                    return;
+                }
+            }
+        }
+
+        self.open_args(paths);
+    }
+
+    /// `:args`: show the argument list with the current entry in brackets
+    pub(crate) fn show_arg_list(&mut self) {
+        if self.arg_list.is_empty() {
+            self.set_message("Argument list is empty".to_string(), MessageType::Info);
+            return;
+        }
+
+        let cwd = self.effective_cwd();
+        let listing = self
+            .arg_list
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let name = path.strip_prefix(&cwd).unwrap_or(path).display();
+                if i == self.arg_list.index() {
+                    format!("[{}]", name)
+                } else {
+                    name.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        self.set_message(listing, MessageType::Info);
+    }
+
+    /// `:next`: edit the next file in the argument list
+    pub(crate) fn next_arg(&mut self) {
+        let path = self.arg_list.next(1).map(Path::to_path_buf);
+        self.edit_arg(path, "Cannot go beyond last file");
+    }
+
+    /// `:prev`: edit the previous file in the argument list
+    pub(crate) fn prev_arg(&mut self) {
+        let path = self.arg_list.prev(1).map(Path::to_path_buf);
+        self.edit_arg(path, "Cannot go before first file");
+    }
+
+    /// `:first` / `:last`: edit the first or last file in the argument list
+    pub(crate) fn goto_arg(&mut self, last: bool) {
+        let index = if last {
+            self.arg_list.len().saturating_sub(1)
+        } else {
+            0
+        };
+        let path = self.arg_list.select(index).map(Path::to_path_buf);
+        self.edit_arg(path, "Argument list is empty");
+    }
+
+    fn edit_arg(&mut self, path: Option<PathBuf>, error: &str) {
+        if self.arg_list.is_empty() {
+            self.set_message("Argument list is empty".to_string(), MessageType::Error);
+            return;
+        }
+        match path {
+            Some(path) => self.open_paths(&[path]),
+            None => self.set_message(error.to_string(), MessageType::Error),
+        }
+    }
+
+    /// `:argdo {cmd}`: run a command line in every file of the argument list.
+    ///
+    /// Stops at the first file where the command reports an error.
+    pub(crate) fn arg_do(&mut self, command: &str) -> std::io::Result<()> {
+        if self.arg_list.is_empty() {
+            self.set_message("Argument list is empty".to_string(), MessageType::Error);
+            return Ok(());
+        }
+
+        self.clear_message();
+        for index in 0..self.arg_list.len() {
+            if interrupted() {
+                break;
+            }
+            let Some(path) = self.arg_list.select(index).map(Path::to_path_buf) else {
+                break;
+            };
+            if !self.open_path(&path) {
+                break;
+            }
+            self.run_command_line(command)?;
+            if self.message_type == MessageType::Error || !self.running {
+                break;
+            }
+        }
+
+        self.apply_autochdir();
+        self.render_state.mark_all_dirty();
+        Ok(())
+    }
+}
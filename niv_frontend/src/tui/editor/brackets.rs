@@ -0,0 +1,57 @@
+use super::Editor;
+use crate::tui::brackets::{BRACKET_SCAN_LIMIT, bracket_at_cursor};
+
+impl Editor {
+    /// Find the bracket matching the one at the cursor, only after the
+    /// cursor moved or the text changed, and redraw the lines of the pair
+    /// that was highlighted and the one that is now
+    pub(super) fn update_bracket_match(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let key = (
+            buffer.cursor_line,
+            buffer.cursor_col,
+            self.render_state.last_content_hash,
+        );
+        if self.render_state.bracket_key == Some(key) {
+            return;
+        }
+        self.render_state.bracket_key = Some(key);
+
+        let show_match = self.config_loader.get_copy().editor.show_match;
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        let pair = if show_match {
+            find_bracket_pair(&buffer.content, buffer.cursor_line, buffer.cursor_col)
+        } else {
+            None
+        };
+        if pair == buffer.bracket_match {
+            return;
+        }
+        let old = std::mem::replace(&mut buffer.bracket_match, pair);
+        let (top, height) = (buffer.scroll_line, buffer.height as usize);
+        for (line, _) in old.into_iter().chain(pair).flatten() {
+            if let Some(row) = line.checked_sub(top).filter(|&row| row < height) {
+                self.render_state.mark_line_dirty(row);
+            }
+        }
+    }
+}
+
+/// The bracket at or just before the cursor and its match, as (line, byte
+/// column)
+fn find_bracket_pair(text: &str, line: usize, col: usize) -> Option<[(usize, usize); 2]> {
+    let line_start: usize = text.split('\n').take(line).map(|line| line.len() + 1).sum();
+    let line_len = text.get(line_start..)?.split('\n').next()?.len();
+    let cursor = line_start + col.min(line_len);
+    let (bracket, matching) = bracket_at_cursor(text, cursor, BRACKET_SCAN_LIMIT)?;
+    let position = |offset: usize| {
+        let line = text[..offset].matches('\n').count();
+        let start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        (line, offset - start)
+    };
+    Some([position(bracket), position(matching)])
+}
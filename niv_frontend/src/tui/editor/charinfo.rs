@@ -0,0 +1,38 @@
+use super::{Editor, MessageType};
+use crate::tui::charinfo::{describe_char, describe_utf8};
+
+impl Editor {
+    /// `ga`: show code point information for the character under the cursor
+    pub(crate) fn show_char_info(&mut self) {
+        let info = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.cursor_cluster())
+            .map(|cluster| {
+                cluster
+                    .chars()
+                    .map(describe_char)
+                    .collect::<Vec<_>>()
+                    .join(" + ")
+            });
+
+        match info {
+            Some(info) => self.set_message(info, MessageType::Info),
+            None => self.set_message("NUL".to_string(), MessageType::Info),
+        }
+    }
+
+    /// `g8`: show the UTF-8 bytes of the character under the cursor
+    pub(crate) fn show_utf8_info(&mut self) {
+        let info = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.cursor_cluster())
+            .map(describe_utf8);
+
+        match info {
+            Some(info) => self.set_message(info, MessageType::Info),
+            None => self.set_message("NUL".to_string(), MessageType::Info),
+        }
+    }
+}
@@ -1,11 +1,65 @@
 use super::{Editor, MessageType};
+use crate::tui::crash;
+use crate::tui::excmd::{LineRange, WriteCommand, parse_range, parse_write_command, split_bar};
+use std::time::Instant;
 
 impl Editor {
     pub(crate) fn execute_command(&mut self) -> std::io::Result<()> {
         let command = self.command_line.trim().to_string();
         self.command_line.clear();
+        self.run_command_line(&command)
+    }
+
+    /// Run a `|`-separated command line
+    pub(crate) fn run_command_line(&mut self, line: &str) -> std::io::Result<()> {
+        let mut rest = Some(line);
+        while let Some(line) = rest {
+            let line = line.trim();
+            // `:argdo` takes the rest of the line, bars included
+            if let Some(command) = line.strip_prefix("argdo ") {
+                return self.arg_do(command.trim());
+            }
 
-        match command.as_str() {
+            let (command, tail) = split_bar(line);
+            crash::record_crash_command(command.trim());
+            let started = Instant::now();
+            self.run_command(command.trim())?;
+            self.profile_command_time(command.trim(), started);
+            if !self.running {
+                break;
+            }
+            rest = tail;
+        }
+        Ok(())
+    }
+
+    fn run_command(&mut self, command: &str) -> std::io::Result<()> {
+        let (current_line, line_count) = self
+            .buffer_manager
+            .current()
+            .map(|buffer| (buffer.cursor_line, buffer.content.lines().count().max(1)))
+            .unwrap_or((0, 1));
+        match parse_range(command, current_line, line_count, self.last_visual) {
+            Ok((range, rest)) => {
+                if let Some(write) = parse_write_command(range, rest) {
+                    self.write_command(write, line_count);
+                    return Ok(());
+                }
+                if let Some(filter) = rest.strip_prefix('!') {
+                    match range {
+                        Some(range) => self.filter_lines(range, filter.trim()),
+                        None => self.run_shell_command(filter.trim()),
+                    }
+                    return Ok(());
+                }
+            }
+            Err(e) => {
+                self.set_message(e, MessageType::Error);
+                return Ok(());
+            }
+        }
+
+        match command {
             "q" | "quit" => {
                 self.running = false;
             }
@@ -24,32 +78,138 @@ impl Editor {
                     self.set_message("No buffer to save".to_string(), MessageType::Warning);
                 }
             }
-            "w" => {
-                if let Some(buffer) = self.buffer_manager.current() {
-                    match buffer.save() {
-                        Ok(()) => {
-                            if let Some(buffer) = self.buffer_manager.current_mut() {
-                                buffer.modified = false;
-                            }
-                            self.set_message("File saved".to_string(), MessageType::Success);
-                            self.render_state.status_line_dirty = true;
-                        }
-                        Err(e) => {
-                            self.set_message(format!("Save failed: {}", e), MessageType::Error);
-                        }
-                    }
-                } else {
-                    self.set_message("No buffer to save".to_string(), MessageType::Warning);
-                }
-            }
             "q!" | "quit!" => {
                 self.running = false;
             }
-            cmd if cmd.starts_with("e ") => {
-                self.set_message("File opening not implemented yet".to_string(), MessageType::Info);
+            cmd if cmd.starts_with("setlocal ") || cmd.starts_with("setl ") => {
+                let options: Vec<String> =
+                    cmd.split_whitespace().skip(1).map(str::to_string).collect();
+                self.set_local_options(&options);
+            }
+            "e" | "edit" => {
+                self.reload_current_buffer(false);
+            }
+            "e!" | "edit!" => {
+                self.reload_current_buffer(true);
+            }
+            "checkt" | "checktime" => {
+                self.check_time();
+            }
+            "tail" => {
+                self.toggle_tail();
+            }
+            "CsvAlign" => {
+                self.toggle_table_view();
+            }
+            "MarkdownPreview" => {
+                self.toggle_markdown_preview();
+            }
+            "DiffOrig" => {
+                self.toggle_diff_orig();
+            }
+            "Blame" => {
+                self.blame_cursor_line();
+            }
+            "vs" | "vsplit" => {
+                self.toggle_split_window();
+            }
+            cmd if cmd.starts_with("Rename") || cmd.starts_with("Move") => {
+                let (name, arg) = cmd.split_once(' ').unwrap_or((cmd, ""));
+                let force = name.ends_with('!');
+                match name.trim_end_matches('!') {
+                    "Rename" => self.rename_current_file(arg.trim(), force, true),
+                    "Move" => self.rename_current_file(arg.trim(), force, false),
+                    _ => self.set_message(
+                        format!("Unknown command: {}", command),
+                        MessageType::Warning,
+                    ),
+                }
+            }
+            cmd if cmd.starts_with("e ") || cmd.starts_with("edit ") => {
+                let file = cmd.split_once(' ').map_or("", |(_, file)| file.trim());
+                self.edit_file(file);
+            }
+            "args" | "ar" => {
+                self.show_arg_list();
+            }
+            cmd if cmd.starts_with("args ") || cmd.starts_with("ar ") => {
+                let files = cmd.split_once(' ').map_or("", |(_, files)| files.trim());
+                self.set_arg_list(files);
+            }
+            "n" | "next" => {
+                self.next_arg();
+            }
+            "N" | "prev" | "previous" | "Next" => {
+                self.prev_arg();
+            }
+            "fir" | "first" | "rew" | "rewind" => {
+                self.goto_arg(false);
+            }
+            "la" | "last" => {
+                self.goto_arg(true);
+            }
+            "argdo" => {
+                self.set_message("Argument required".to_string(), MessageType::Error);
+            }
+            "bd" | "bdelete" => {
+                self.delete_current_buffer(false);
+            }
+            "bd!" | "bdelete!" => {
+                self.delete_current_buffer(true);
+            }
+            "reopen" => {
+                self.reopen_closed_buffer();
+            }
+            "palette" => {
+                self.open_palette();
+            }
+            "Yanks" => {
+                self.open_yank_picker();
+            }
+            "wa" | "wall" => {
+                self.write_all_buffers();
+            }
+            "wqa" | "wqall" | "xa" | "xall" => {
+                if self.write_all_buffers() {
+                    self.running = false;
+                }
+            }
+            "mes" | "messages" => {
+                self.show_messages();
+            }
+            "macros" => {
+                self.show_macros();
+            }
+            "up" | "update" => {
+                if self
+                    .buffer_manager
+                    .current()
+                    .is_some_and(|buffer| buffer.is_really_modified())
+                {
+                    self.write_current_buffer();
+                }
+            }
+            "pwd" => {
+                self.show_cwd();
+            }
+            "log" => {
+                self.show_log();
+            }
+            cmd if cmd == "profile" || cmd.starts_with("profile ") => {
+                self.profile_command(cmd[7..].trim());
+            }
+            #[cfg(feature = "lua")]
+            cmd if cmd.starts_with("lua ") => {
+                self.exec_lua(cmd[4..].trim());
+            }
+            cmd if cmd == "cd" || cmd.starts_with("cd ") => {
+                self.change_global_dir(cmd[2..].trim());
+            }
+            cmd if cmd == "lcd" || cmd.starts_with("lcd ") => {
+                self.change_local_dir(cmd[3..].trim());
             }
             _ => {
-                if !command.is_empty() {
+                if !command.is_empty() && !self.run_plugin_command(command) {
                     self.set_message(format!("Unknown command: {}", command), MessageType::Warning);
                 }
             }
@@ -57,4 +217,154 @@ impl Editor {
 
         Ok(())
     }
+
+    /// `:w`: save the current buffer to its own file
+    fn write_current_buffer(&mut self) {
+        if let Some(buffer) = self.buffer_manager.current() {
+            let result = self.save_suspended(buffer.file_path.as_deref(), || buffer.save());
+            match result {
+                Ok(()) => {
+                    if let Some(buffer) = self.buffer_manager.current_mut() {
+                        buffer.mark_saved();
+                        // A followed file grows from what was just written
+                        if let Some(metadata) = buffer
+                            .file_path
+                            .as_ref()
+                            .and_then(|path| std::fs::metadata(path).ok())
+                        {
+                            buffer.loaded_len = metadata.len();
+                        }
+                    }
+                    self.watch_current_file();
+                    self.set_message("File saved".to_string(), MessageType::Success);
+                    self.render_state.status_line_dirty = true;
+                }
+                Err(e) => {
+                    self.set_message(format!("Save failed: {}", e), MessageType::Error);
+                }
+            }
+        } else {
+            self.set_message("No buffer to save".to_string(), MessageType::Warning);
+        }
+    }
+
+    /// `:log`: open the log file at its end, reloading it if it is already open
+    fn show_log(&mut self) {
+        let Some(path) = niv_fs::log_file() else {
+            self.set_message(
+                "Logging is off (start with --log, --log-file or NIV_LOG)".to_string(),
+                MessageType::Warning,
+            );
+            return;
+        };
+        let already_open = self.buffer_manager.position_by_path(&path).is_some();
+        if !self.open_file_at(&path, None) {
+            return;
+        }
+        if already_open {
+            self.reload_current_buffer(false);
+        }
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_cursor(usize::MAX, 0);
+        }
+        self.render_state.mark_all_dirty();
+    }
+
+    /// `:[range]w[!] [>>] [file]`: write all or part of the buffer to a file
+    fn write_command(&mut self, write: WriteCommand, line_count: usize) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.set_message("No buffer to save".to_string(), MessageType::Warning);
+            return;
+        };
+        let Some(target) = write.path.clone().or_else(|| buffer.file_path.clone()) else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        let is_own_file = buffer.file_path.as_deref() == Some(target.as_path());
+        let range = write.range.unwrap_or(LineRange::whole(line_count));
+        let partial = !range.is_whole(line_count);
+
+        if !write.append {
+            if is_own_file && !partial {
+                self.write_current_buffer();
+                return;
+            }
+            if is_own_file && !write.force {
+                self.set_message(
+                    "Use ! to write partial buffer".to_string(),
+                    MessageType::Error,
+                );
+                return;
+            }
+            if !is_own_file && target.exists() && !write.force {
+                self.set_message(
+                    "File exists (add ! to override)".to_string(),
+                    MessageType::Error,
+                );
+                return;
+            }
+        }
+
+        let result = self.save_suspended(Some(&target), || {
+            if write.append {
+                let text = niv_fs::slice_lines(&buffer.content, range.start, range.end);
+                niv_fs::append_to_file(&target, text, &buffer.save_context)
+            } else {
+                niv_fs::save_line_range(
+                    &target,
+                    &buffer.content,
+                    range.start,
+                    range.end,
+                    &buffer.save_context,
+                )
+            }
+        });
+
+        match result {
+            Ok(_) => {
+                let lines = range.end - range.start + 1;
+                let verb = if write.append { "appended" } else { "written" };
+                self.set_message(
+                    format!("\"{}\" {}L {}", target.display(), lines, verb),
+                    MessageType::Success,
+                );
+            }
+            Err(e) => {
+                self.set_message(format!("Write failed: {}", e), MessageType::Error);
+            }
+        }
+    }
+
+    /// Apply `:setlocal` options to the current buffer
+    pub(super) fn set_local_options(&mut self, options: &[String]) {
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+
+        let mut scroll_bind = self.scroll_bind;
+        let errors: Vec<String> = options
+            .iter()
+            .filter_map(|option| match option.as_str() {
+                // Window options; the windows all show the current buffer
+                "scrollbind" | "scb" => {
+                    scroll_bind = true;
+                    None
+                }
+                "noscrollbind" | "noscb" => {
+                    scroll_bind = false;
+                    None
+                }
+                _ => buffer.set_local_option(option).err(),
+            })
+            .collect();
+        self.scroll_bind = scroll_bind;
+
+        self.refresh_current_swap_policy();
+        // Display options change how the text is drawn
+        self.render_state.mark_all_dirty();
+        if let Some(error) = errors.first() {
+            self.set_message(error.clone(), MessageType::Warning);
+        }
+    }
 }
@@ -0,0 +1,169 @@
+use super::cwd::expand_home;
+use super::{Editor, MessageType};
+use crate::tui::completion::{Completion, CompletionKind, path_start, split_path_prefix};
+use crate::tui::wordindex::keyword_start;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+impl Editor {
+    /// `Ctrl+x Ctrl+f`: complete the file path before the cursor.
+    ///
+    /// Relative paths are completed from the buffer's directory, or from the
+    /// working directory for buffers without a file.
+    pub(crate) fn start_path_completion(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let line = buffer.current_line();
+        let col = buffer.cursor_col.min(line.len());
+        let start = path_start(line, col);
+        let prefix = line[start..col].to_string();
+        let base = buffer
+            .file_path
+            .as_deref()
+            .and_then(Path::parent)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| self.effective_cwd());
+
+        let show_hidden = self.config_loader.get_copy().editor.show_hidden;
+        let candidates = path_candidates(&prefix, &base, show_hidden);
+        if candidates.is_empty() {
+            self.set_message("Pattern not found".to_string(), MessageType::Error);
+            return;
+        }
+        let mut completion = Completion::new(CompletionKind::FileName, start, prefix, candidates);
+        completion.select_next();
+        self.show_completion(completion);
+    }
+
+    /// `Ctrl+n` / `Ctrl+p`: complete the keyword before the cursor from the
+    /// words of the current buffer, then those of the other open buffers
+    pub(crate) fn start_keyword_completion(&mut self, backward: bool) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let line = buffer.current_line();
+        let col = buffer.cursor_col.min(line.len());
+        let start = keyword_start(line, col);
+        let prefix = line[start..col].to_string();
+
+        let mut seen = HashSet::new();
+        let others = self
+            .buffer_manager
+            .iter()
+            .filter(|other| !std::ptr::eq(*other, buffer));
+        let candidates: Vec<String> = std::iter::once(buffer)
+            .chain(others)
+            .flat_map(|buffer| buffer.word_index.with_prefix(&prefix))
+            // The word being typed is in the index too
+            .filter(|word| *word != prefix)
+            .filter(|word| seen.insert(*word))
+            .map(str::to_string)
+            .collect();
+
+        if candidates.is_empty() {
+            self.set_message("Pattern not found".to_string(), MessageType::Error);
+            return;
+        }
+        let mut completion = Completion::new(CompletionKind::Keyword, start, prefix, candidates);
+        if backward {
+            completion.select_prev();
+        } else {
+            completion.select_next();
+        }
+        self.show_completion(completion);
+    }
+
+    /// Keys typed while a completion is shown: `Ctrl+n` (or `Ctrl+f` for file
+    /// names) and `Ctrl+p` cycle, `Ctrl+y` accepts and `Ctrl+e` restores the typed text. Any other
+    /// key accepts the completion and is then handled as usual.
+    ///
+    /// Returns true when the key was consumed.
+    pub(crate) fn handle_completion_key(&mut self, key_event: KeyEvent) -> bool {
+        let Some(mut completion) = self.completion.take() else {
+            return false;
+        };
+        if !key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.clear_message();
+            return false;
+        }
+
+        match key_event.code {
+            KeyCode::Char('n') => {
+                completion.select_next();
+                self.show_completion(completion);
+            }
+            KeyCode::Char('f') if completion.kind == CompletionKind::FileName => {
+                completion.select_next();
+                self.show_completion(completion);
+            }
+            KeyCode::Char('p') => {
+                completion.select_prev();
+                self.show_completion(completion);
+            }
+            KeyCode::Char('y') => self.clear_message(),
+            KeyCode::Char('e') => {
+                completion.selected = None;
+                self.insert_completion(&completion);
+                self.clear_message();
+            }
+            _ => {
+                self.clear_message();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn show_completion(&mut self, completion: Completion) {
+        self.insert_completion(&completion);
+        let status = match completion.selected {
+            Some(i) => format!("match {} of {}", i + 1, completion.candidates.len()),
+            None => "Back at original".to_string(),
+        };
+        self.set_message(
+            format!("{} {}", completion.kind.mode_text(), status),
+            MessageType::Info,
+        );
+        self.completion = Some(completion);
+    }
+
+    fn insert_completion(&mut self, completion: &Completion) {
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.replace_before_cursor(completion.start, completion.current());
+            self.render_state.mark_text_dirty();
+        }
+    }
+}
+
+/// Paths completing `prefix`, keeping its directory part as typed.
+///
+/// Directories get a trailing `/` so completion can continue into them; hidden
+/// entries are only offered with `show_hidden` or when the typed name starts
+/// with `.`.
+fn path_candidates(prefix: &str, base: &Path, show_hidden: bool) -> Vec<String> {
+    let (dir_part, name_part) = split_path_prefix(prefix);
+    let dir = if dir_part.is_empty() {
+        base.to_path_buf()
+    } else {
+        let dir: PathBuf = expand_home(dir_part);
+        if dir.is_absolute() {
+            dir
+        } else {
+            base.join(dir)
+        }
+    };
+
+    niv_fs::list_dir(&dir)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|entry| entry.name.starts_with(name_part))
+        .filter(|entry| show_hidden || name_part.starts_with('.') || !entry.hidden)
+        .map(|entry| {
+            let suffix = if entry.is_dir { "/" } else { "" };
+            format!("{}{}{}", dir_part, entry.name, suffix)
+        })
+        .collect()
+}
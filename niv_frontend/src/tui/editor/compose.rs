@@ -0,0 +1,107 @@
+use super::{Editor, EditorMode};
+use crate::tui::interrupt;
+use crate::tui::keys::normalize_key_event;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use std::time::Duration;
+
+impl Editor {
+    /// Show `text` underlined at the cursor as what an input method is
+    /// composing; empty text ends the composition. Ignored outside insert
+    /// mode.
+    ///
+    /// Terminals draw the preedit of an IME themselves, so this is for front
+    /// ends that report it (the `input/preedit` RPC method).
+    pub fn set_preedit(&mut self, text: &str) {
+        let preedit =
+            (self.mode == EditorMode::Insert && !text.is_empty()).then(|| text.to_string());
+        if preedit != self.preedit {
+            self.preedit = preedit;
+            self.mark_cursor_line_dirty();
+        }
+    }
+
+    /// Text an input method is composing at the cursor
+    pub fn preedit(&self) -> Option<&str> {
+        self.preedit.as_deref()
+    }
+
+    /// Insert `text` committed by an input method as a single change, ending
+    /// any composition
+    pub fn commit_composition(&mut self, text: &str) {
+        self.set_preedit("");
+        if self.mode != EditorMode::Insert || text.is_empty() {
+            return;
+        }
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.insert_text(text);
+            self.render_state.mark_text_dirty();
+        }
+        self.clear_message();
+    }
+
+    /// Text typed by the key presses already queued behind `first`, when
+    /// there are several and they can go straight into the buffer.
+    ///
+    /// Terminals send text committed by an input method as one burst of key
+    /// presses, which is inserted as a single change rather than key by key.
+    pub(crate) fn read_composed_text(&mut self, first: KeyEvent) -> Option<String> {
+        if !self.accepts_composed_text() {
+            return None;
+        }
+        let mut text = String::from(text_key(&first)?);
+        loop {
+            let event = match interrupt::take_pending_event() {
+                Some(event) => event,
+                None if event::poll(Duration::ZERO).unwrap_or(false) => match event::read() {
+                    Ok(event) => event,
+                    Err(_) => break,
+                },
+                None => break,
+            };
+            let ch = match &event {
+                Event::Key(key) => normalize_key_event(*key).and_then(|key| text_key(&key)),
+                _ => None,
+            };
+            match ch {
+                Some(ch) => text.push(ch),
+                None => {
+                    interrupt::defer_event(event);
+                    break;
+                }
+            }
+        }
+        if text.chars().count() > 1 {
+            Some(text)
+        } else {
+            None
+        }
+    }
+
+    /// Whether typed text would be inserted as is: insert mode without a
+    /// prompt, palette, completion or pending Ctrl+k / Ctrl+v entry
+    fn accepts_composed_text(&self) -> bool {
+        self.mode == EditorMode::Insert
+            && self.file_change_prompts.is_empty()
+            && self.pending_session.is_none()
+            && self.palette.is_none()
+            && self.pager.is_none()
+            && self.completion.is_none()
+            && self.insert_pending.is_none()
+    }
+
+    /// Redraw the screen line of the cursor
+    fn mark_cursor_line_dirty(&mut self) {
+        if let Some(buffer) = self.buffer_manager.current() {
+            let row = buffer.cursor_line.saturating_sub(buffer.scroll_line);
+            self.render_state.mark_line_dirty(row);
+        }
+    }
+}
+
+/// Character a key press types, when it has no modifier but Shift
+fn text_key(key: &KeyEvent) -> Option<char> {
+    match key.code {
+        KeyCode::Char(ch) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => Some(ch),
+        _ => None,
+    }
+}
@@ -0,0 +1,249 @@
+use super::{Editor, MessageType};
+use crate::tui::buffer::TextBuffer;
+use crate::tui::interrupt::load_file_interruptible;
+use std::path::{Path, PathBuf};
+
+impl Editor {
+    /// Working directory in effect for the current buffer: its `:lcd` directory
+    /// if set, otherwise the global one
+    pub(crate) fn effective_cwd(&self) -> PathBuf {
+        self.buffer_manager
+            .current()
+            .and_then(|buffer| buffer.local_cwd.clone())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    /// Resolve a path typed in a command against the effective working directory
+    pub(crate) fn resolve_path(&self, path: &str) -> PathBuf {
+        let path = expand_home(path);
+        if path.is_absolute() {
+            path
+        } else {
+            self.effective_cwd().join(path)
+        }
+    }
+
+    /// `:cd [dir]`: change the global working directory.
+    ///
+    /// Without an argument goes to the home directory; `:cd -` returns to the previous one.
+    pub(crate) fn change_global_dir(&mut self, dir: &str) {
+        let Some(target) = self.cd_target(dir) else {
+            return;
+        };
+        let previous = std::env::current_dir().ok();
+        match std::env::set_current_dir(&target) {
+            Ok(()) => {
+                self.previous_cwd = previous;
+                // `:cd` overrides any window-local directory, as in vim
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.local_cwd = None;
+                }
+                self.show_cwd();
+            }
+            Err(e) => self.set_message(
+                format!("Can't change directory to {}: {}", target.display(), e),
+                MessageType::Error,
+            ),
+        }
+    }
+
+    /// `:lcd [dir]`: change the working directory of the current buffer only
+    pub(crate) fn change_local_dir(&mut self, dir: &str) {
+        let Some(target) = self.cd_target(dir) else {
+            return;
+        };
+        if !target.is_dir() {
+            self.set_message(
+                format!("Can't change directory to {}", target.display()),
+                MessageType::Error,
+            );
+            return;
+        }
+
+        self.previous_cwd = Some(self.effective_cwd());
+        let target = target.canonicalize().unwrap_or(target);
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.local_cwd = Some(target);
+        }
+        self.show_cwd();
+    }
+
+    /// `:pwd`: show the working directory in effect
+    pub(crate) fn show_cwd(&mut self) {
+        let cwd = self.effective_cwd();
+        self.set_message(cwd.display().to_string(), MessageType::Info);
+    }
+
+    fn cd_target(&mut self, dir: &str) -> Option<PathBuf> {
+        match dir {
+            "" => home_dir().or_else(|| {
+                self.set_message("No home directory".to_string(), MessageType::Error);
+                None
+            }),
+            "-" => self.previous_cwd.clone().or_else(|| {
+                self.set_message("No previous directory".to_string(), MessageType::Error);
+                None
+            }),
+            dir => Some(self.resolve_path(dir)),
+        }
+    }
+
+    /// With `autochdir`, follow the directory of the file in the current buffer
+    pub(crate) fn apply_autochdir(&mut self) {
+        if !self.config_loader.get_copy().editor.autochdir {
+            return;
+        }
+        let dir = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.file_path.as_deref())
+            .and_then(Path::parent)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf);
+        if let Some(dir) = dir {
+            let _ = std::env::set_current_dir(dir);
+        }
+    }
+
+    /// `:e {file}`: edit a file, switching to its buffer if it is already open.
+    ///
+    /// Glob patterns such as `src/**/*.rs` open every matching file.
+    pub(crate) fn edit_file(&mut self, file: &str) {
+        let paths = match self.expand_file_pattern(file) {
+            Ok(paths) => paths,
+            Err(e) => {
+                self.set_message(e, MessageType::Error);
+                return;
+            }
+        };
+        self.open_paths(&paths);
+    }
+
+    /// Open each path in a buffer and make the first one current
+    pub(crate) fn open_paths(&mut self, paths: &[PathBuf]) {
+        let opened = paths.iter().filter(|path| self.open_path(path)).count();
+        let Some(first) = paths
+            .iter()
+            .find_map(|path| self.buffer_manager.position_by_path(path))
+        else {
+            return;
+        };
+
+        self.buffer_manager.switch_buffer(first);
+        self.apply_autochdir();
+        self.render_state.mark_all_dirty();
+        if opened > 1 {
+            self.set_message(format!("{} files opened", opened), MessageType::Info);
+        } else {
+            self.show_file_info();
+        }
+    }
+
+    /// Edit `path`, moving the cursor to a 1-based `line` when given.
+    /// Returns false if the file could not be opened.
+    pub fn open_file_at(&mut self, path: &Path, line: Option<usize>) -> bool {
+        self.open_paths(&[path.to_path_buf()]);
+        if self.buffer_manager.position_by_path(path).is_none() {
+            return false;
+        }
+        if let Some(line) = line {
+            if let Some(buffer) = self.buffer_manager.current_mut() {
+                buffer.set_cursor(line.saturating_sub(1), 0);
+            }
+            self.render_state.mark_all_dirty();
+        }
+        true
+    }
+
+    /// Make `path` the current buffer, loading it first if it is not open yet
+    pub(crate) fn open_path(&mut self, path: &Path) -> bool {
+        if let Some(index) = self.buffer_manager.position_by_path(path) {
+            self.buffer_manager.switch_buffer(index);
+            return true;
+        }
+
+        // Load errors name the file and what failed on it
+        let result = if path.exists() {
+            load_file_interruptible(path)
+                .map_err(|e| e.to_string())
+                .and_then(|load_result| {
+                    self.open_buffer_from_content(path.to_path_buf(), load_result)
+                        .map_err(|e| format!("Can't open {}: {}", path.display(), e))
+                })
+        } else {
+            self.create_new_buffer(path.to_path_buf())
+                .map_err(|e| format!("Can't open {}: {}", path.display(), e))
+        };
+        if let Err(e) = result {
+            self.set_message(e, MessageType::Error);
+            return false;
+        }
+
+        let layout = self.layout_manager.get_layout();
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.restore_last_position(path);
+        true
+    }
+
+    /// Expand a file argument: globs and braces become the matching files,
+    /// anything else resolves to a single path
+    pub(crate) fn expand_file_pattern(&self, pattern: &str) -> Result<Vec<PathBuf>, String> {
+        let resolved = self.resolve_path(pattern);
+        if !niv_fs::has_glob_chars(pattern) {
+            return Ok(vec![resolved]);
+        }
+
+        let matches: Vec<PathBuf> =
+            niv_fs::expand_glob(&resolved.to_string_lossy(), &self.effective_cwd())
+                .map_err(|e| e.to_string())?
+                .into_iter()
+                .filter(|path| !path.is_dir())
+                .collect();
+        if matches.is_empty() {
+            Err(format!("No match: {}", pattern))
+        } else {
+            Ok(matches)
+        }
+    }
+
+    /// Show the name and size of the current buffer's file
+    pub(super) fn show_file_info(&mut self) {
+        let info = self.buffer_manager.current().map(describe_buffer);
+        if let Some(info) = info {
+            self.set_message(info, MessageType::Info);
+        }
+    }
+}
+
+fn describe_buffer(buffer: &TextBuffer) -> String {
+    let name = buffer
+        .file_path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|| "[No Name]".to_string());
+    if buffer.file_path.as_ref().is_some_and(|path| path.exists()) {
+        format!("\"{}\" {}L", name, buffer.content.lines().count())
+    } else {
+        format!("\"{}\" [New]", name)
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Expand a leading `~` to the home directory
+pub(super) fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => match home_dir() {
+            Some(home) => home.join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
+}
@@ -0,0 +1,80 @@
+use super::{Editor, MessageType};
+use crate::tui::diff::{DiffRowKind, DiffView};
+use crate::tui::interrupt::load_file_interruptible;
+
+impl Editor {
+    /// `:DiffOrig`: toggle a read-only split right of the text area showing
+    /// the current buffer's file as it is on disk, lined up with the buffer
+    /// and marked where unsaved changes differ from it
+    pub(super) fn toggle_diff_orig(&mut self) {
+        if self.diff_view.take().is_some() {
+            self.show_split(false);
+            return;
+        }
+        let Some(path) = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.file_path.clone())
+        else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        let original = match load_file_interruptible(&path) {
+            Ok(load_result) => load_result.content,
+            Err(e) => {
+                self.set_message(e.to_string(), MessageType::Error);
+                return;
+            }
+        };
+
+        let mut view = DiffView::new(original);
+        if let Some(buffer) = self.buffer_manager.current() {
+            view.update(&buffer.content);
+        }
+        let kinds = [
+            DiffRowKind::Changed,
+            DiffRowKind::Added,
+            DiffRowKind::Removed,
+        ];
+        let message = match kinds.map(|kind| view.count(kind)) {
+            [0, 0, 0] => "No changes since the file was saved".to_string(),
+            [changed, added, removed] => format!(
+                "{} changed, {} added, {} removed lines",
+                changed, added, removed
+            ),
+        };
+        // The split shows one of the preview, `:DiffOrig` and the window
+        self.markdown_preview = None;
+        self.split_window = None;
+        self.diff_view = Some((path, view));
+        self.show_split(true);
+        self.set_message(message, MessageType::Info);
+    }
+
+    /// Bring the diff up to date with the current buffer, closing it once
+    /// another buffer is current
+    pub(super) fn update_diff_view(&mut self) {
+        let Some((path, view)) = &mut self.diff_view else {
+            return;
+        };
+        match self.buffer_manager.current() {
+            Some(buffer) if buffer.file_path.as_ref() == Some(path) => {
+                view.update(&buffer.content);
+            }
+            _ => {
+                self.diff_view = None;
+                self.show_split(false);
+            }
+        }
+    }
+
+    /// Open or close the split right of the text area
+    pub(super) fn show_split(&mut self, show: bool) {
+        self.layout_manager.set_preview(show);
+        let layout = self.layout_manager.get_layout();
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.render_state.mark_all_dirty();
+    }
+}
@@ -0,0 +1,131 @@
+use super::{Editor, EditorMode, MessageType};
+use crate::tui::excmd::LineRange;
+use crate::tui::filter::run_filter;
+use crate::tui::interrupt::interrupted;
+use crossterm::event::{KeyCode, KeyEvent};
+
+impl Editor {
+    /// `:{range}!cmd`: replace the lines with the output of `cmd` run on them
+    pub(crate) fn filter_lines(&mut self, range: LineRange, command: &str) {
+        if command.is_empty() {
+            self.set_message("Argument required".to_string(), MessageType::Error);
+            return;
+        }
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let lines: Vec<&str> = buffer.content.lines().collect();
+        let end = range.end.min(lines.len().saturating_sub(1));
+        let mut input = lines.get(range.start..=end).unwrap_or_default().join("\n");
+        input.push('\n');
+
+        match run_filter(command, &input, &self.effective_cwd(), interrupted) {
+            Ok(output) => {
+                let count = range.end - range.start + 1;
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.replace_lines(range.start, range.end, &output);
+                }
+                self.render_state.mark_all_dirty();
+                self.set_message(format!("{} lines filtered", count), MessageType::Info);
+            }
+            Err(e) => {
+                self.set_message(format!("Filter failed: {}", e), MessageType::Error);
+            }
+        }
+    }
+
+    /// `:!cmd`: run a shell command and show its output
+    pub(crate) fn run_shell_command(&mut self, command: &str) {
+        if command.is_empty() {
+            self.set_message("Argument required".to_string(), MessageType::Error);
+            return;
+        }
+        match run_filter(command, "", &self.effective_cwd(), interrupted) {
+            Ok(output) => {
+                let output = output.lines().collect::<Vec<_>>().join(" | ");
+                self.set_message(output, MessageType::Info);
+            }
+            Err(e) => {
+                self.set_message(format!("Command failed: {}", e), MessageType::Error);
+            }
+        }
+    }
+
+    /// Feed a key to a pending `!{motion}` in normal mode.
+    ///
+    /// Supported motions are `!` (current line), `[count]j`, `[count]k`, `G`,
+    /// `gg`, `ip` and `ap`. Once complete, the command line is opened with the
+    /// range filled in, as in `:.,.+2!`.
+    pub(crate) fn handle_filter_motion(&mut self, mut keys: String, key_event: KeyEvent) {
+        let KeyCode::Char(ch) = key_event.code else {
+            return;
+        };
+        keys.push(ch);
+
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let cursor = buffer.cursor_line;
+        let last_line = buffer.content.lines().count().saturating_sub(1);
+
+        let digits = keys
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(keys.len());
+        let count: usize = keys[..digits].parse().unwrap_or(1);
+        let (start, end) = match &keys[digits..] {
+            // Still collecting a count or a multi-key motion
+            "" | "g" | "i" | "a" => {
+                self.filter_motion = Some(keys);
+                return;
+            }
+            "!" => (cursor, (cursor + count - 1).min(last_line)),
+            "j" => (cursor, (cursor + count).min(last_line)),
+            "k" => (cursor.saturating_sub(count), cursor),
+            "G" => (cursor, last_line),
+            "gg" => (0, cursor),
+            "ip" => buffer.paragraph_range(false),
+            "ap" => buffer.paragraph_range(true),
+            _ => return,
+        };
+
+        let address = |line: usize| match line.cmp(&cursor) {
+            std::cmp::Ordering::Equal => ".".to_string(),
+            std::cmp::Ordering::Greater => format!(".+{}", line - cursor),
+            std::cmp::Ordering::Less => format!(".-{}", cursor - line),
+        };
+        let range = if start == end {
+            address(start)
+        } else {
+            format!("{},{}", address(start), address(end))
+        };
+        self.open_filter_command_line(range);
+    }
+
+    /// Remember the visual selection's lines for `'<` and `'>`
+    pub(crate) fn remember_visual_selection(&mut self) {
+        let Some((anchor, _)) = self.visual_start.take() else {
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let cursor = buffer.cursor_line;
+        self.last_visual = Some(LineRange {
+            start: anchor.min(cursor),
+            end: anchor.max(cursor),
+        });
+    }
+
+    /// `!` in visual mode: filter the selected lines
+    pub(crate) fn filter_visual_selection(&mut self) {
+        self.remember_visual_selection();
+        self.open_filter_command_line("'<,'>".to_string());
+    }
+
+    fn open_filter_command_line(&mut self, range: String) {
+        self.mode = EditorMode::Command;
+        self.command_line = format!("{}!", range);
+        self.render_state.command_line_dirty = true;
+        self.render_state.status_line_dirty = true;
+    }
+}
@@ -0,0 +1,106 @@
+use super::Editor;
+use crate::tui::keys::{normalize_key_event, parse_key_notation};
+use crate::tui::screen::{Output, Screen};
+use crossterm::event::KeyEvent;
+use niv_config::{Config, ConfigLoader};
+use std::cell::{Ref, RefCell};
+use std::io;
+
+impl Editor {
+    /// Editor drawing into an in-memory [`Screen`] of `width` x `height` cells
+    /// instead of the terminal, for tests.
+    ///
+    /// It uses the default configuration, loads no plugins and keeps no swap
+    /// files, so nothing outside the test affects what it shows. Drive it with
+    /// [`feed_keys`](Self::feed_keys) and look at the result with
+    /// [`screen`](Self::screen).
+    pub fn headless(width: u16, height: u16) -> Self {
+        Self::headless_with_config(width, height, |_| {})
+    }
+
+    /// Headless editor whose configuration is changed by `configure` before
+    /// it starts, for tests of settings that are off by default. WebAssembly
+    /// extensions enabled there are loaded.
+    pub fn headless_with_config(
+        width: u16,
+        height: u16,
+        configure: impl FnOnce(&mut Config),
+    ) -> Self {
+        let config_loader = ConfigLoader::with_paths(Vec::new());
+        if let Ok(mut config) = config_loader.get().write() {
+            config.editor.swapfile = false;
+            configure(&mut config);
+        }
+        let output = Output::Screen(RefCell::new(Screen::new(width, height)));
+        let mut editor = Self::with_config_loader(config_loader, output);
+        editor.layout_manager.update_size(width, height);
+        #[cfg(feature = "wasm")]
+        editor.load_wasm_plugins();
+        editor.start();
+        // Nothing is drawn if the first frame fails; the next one redraws everything
+        let _ = editor.frame();
+        editor
+    }
+
+    /// Type `keys`, written in vim notation such as `ihello<Esc>:w<CR>`,
+    /// running a frame of the main loop after each key. Stops once the editor
+    /// quits.
+    pub fn feed_keys(&mut self, keys: &str) -> io::Result<()> {
+        for key in parse_key_notation(keys) {
+            if !self.running {
+                break;
+            }
+            self.restart_cursor_hold();
+            self.handle_key_event(key)?;
+            self.frame()?;
+        }
+        Ok(())
+    }
+
+    /// Press a key as the terminal reports it, for keys vim notation can't
+    /// write: key releases and what the kitty keyboard protocol sends.
+    /// Runs a frame of the main loop afterwards.
+    pub fn send_key(&mut self, event: KeyEvent) -> io::Result<()> {
+        if let Some(event) = normalize_key_event(event) {
+            self.restart_cursor_hold();
+            self.handle_key_event(event)?;
+        }
+        self.frame()
+    }
+
+    /// Run a frame of the main loop, drawing what changed since the last
+    /// one; for changes made through other methods than keys
+    pub fn run_frame(&mut self) -> io::Result<()> {
+        self.frame()
+    }
+
+    /// Resize the headless screen, without the debounce a terminal resize gets
+    pub fn resize_screen(&mut self, width: u16, height: u16) -> io::Result<()> {
+        if let Output::Screen(screen) = &self.output {
+            screen.borrow_mut().resize(width, height);
+        }
+        self.apply_resize(width, height);
+        self.frame()
+    }
+
+    /// Resize the headless screen the way a terminal does: the editor lays
+    /// itself out for the new size once no other resize follows within the
+    /// debounce delay
+    pub fn send_resize(&mut self, width: u16, height: u16) -> io::Result<()> {
+        if let Output::Screen(screen) = &self.output {
+            screen.borrow_mut().resize(width, height);
+        }
+        self.handle_resize(width, height);
+        self.frame()
+    }
+
+    /// What a headless editor shows; None when drawing to the terminal
+    pub fn screen(&self) -> Option<Ref<'_, Screen>> {
+        self.output.screen()
+    }
+
+    /// Whether the editor is still running, i.e. wasn't quit
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+}
@@ -0,0 +1,45 @@
+use super::{Editor, TimerCallback};
+use std::time::Duration;
+
+impl Editor {
+    /// Run `callback` on every CursorHold, after `updatetime` without input
+    pub fn on_cursor_hold(&mut self, callback: impl FnMut(&mut Editor) + 'static) {
+        self.cursor_hold_handlers.push(Box::new(callback));
+    }
+
+    /// Start waiting for CursorHold again; called for every key.
+    ///
+    /// CursorHold fires once per pause, not repeatedly while the editor is idle.
+    pub(crate) fn restart_cursor_hold(&mut self) {
+        if let Some(id) = self.cursor_hold_timer.take() {
+            self.timers.stop(id);
+        }
+        let updatetime = self.config_loader.get_copy().editor.updatetime;
+        if updatetime == 0 {
+            return;
+        }
+        let id = self.set_timeout(Duration::from_millis(updatetime as u64), |editor| {
+            editor.cursor_hold_timer = None;
+            editor.cursor_hold();
+        });
+        self.cursor_hold_timer = Some(id);
+    }
+
+    /// CursorHold: write the swap file, look for files changed on disk and
+    /// run the registered handlers
+    fn cursor_hold(&mut self) {
+        self.flush_current_swap();
+        // Never stack more prompts on an unanswered one
+        if self.file_change_prompts.is_empty() {
+            self.check_file_changes();
+        }
+
+        // Handlers may register further handlers while they run
+        let mut handlers: Vec<TimerCallback> = std::mem::take(&mut self.cursor_hold_handlers);
+        for handler in handlers.iter_mut() {
+            handler(self);
+        }
+        handlers.append(&mut self.cursor_hold_handlers);
+        self.cursor_hold_handlers = handlers;
+    }
+}
@@ -0,0 +1,47 @@
+use super::Editor;
+use crossterm::{cursor, execute, style::Print, terminal};
+use std::io;
+
+impl Editor {
+    /// Run inline below the shell prompt, in `ui.inline_height` rows, instead
+    /// of on the alternate screen. What the shell printed stays in the
+    /// scrollback and the editor's rows are cleared on exit, which suits quick
+    /// edits such as `git commit` messages.
+    pub fn set_inline(&mut self, inline: bool) {
+        let rows = inline.then(|| self.config_loader.get_copy().ui.inline_height);
+        self.layout_manager.set_inline_height(rows);
+    }
+
+    pub fn is_inline(&self) -> bool {
+        self.layout_manager.inline_height().is_some()
+    }
+
+    /// Make room for the editor from the cursor's line down. Near the bottom
+    /// of the terminal the newlines scroll the lines above into the scrollback.
+    pub(super) fn reserve_inline_rows(&mut self) -> io::Result<()> {
+        let Some(rows) = self.layout_manager.inline_height() else {
+            return Ok(());
+        };
+        let (_, height) = terminal::size()?;
+        let rows = rows.min(height);
+        execute!(
+            &self.output,
+            Print("\r\n".repeat(rows.saturating_sub(1) as usize))
+        )?;
+        let (_, row) = cursor::position()?;
+        self.layout_manager.set_top((row + 1).saturating_sub(rows));
+        Ok(())
+    }
+
+    /// Clear the editor's rows and leave the cursor on the first, where the
+    /// shell prompt continues
+    pub(super) fn release_inline_rows(&mut self) -> io::Result<()> {
+        let top = self.layout_manager.get_layout().top;
+        execute!(
+            &self.output,
+            cursor::MoveTo(0, top),
+            terminal::Clear(terminal::ClearType::FromCursorDown),
+            cursor::Show
+        )
+    }
+}
@@ -1,69 +1,236 @@
-use super::{Editor, EditorMode};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use super::{Editor, EditorMode, MessageType};
+use crate::tui::interrupt::{self, is_interrupt_key};
+use crate::tui::keys::{legacy_key, normalize_key_event};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{execute, terminal};
+use std::io;
+use std::time::Instant;
 
 impl Editor {
+    /// Switch to the kitty keyboard protocol if `extended_keys` is on and the
+    /// terminal answers the capability query; other terminals keep sending
+    /// legacy escape codes.
+    pub(crate) fn enable_extended_keys(&mut self) {
+        if !self.config_loader.get_copy().ui.extended_keys {
+            return;
+        }
+        if !terminal::supports_keyboard_enhancement().unwrap_or(false) {
+            niv_fs::log!(Debug, "input", "keyboard protocol not supported");
+            return;
+        }
+        let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+            | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS;
+        self.extended_keys = execute!(io::stdout(), PushKeyboardEnhancementFlags(flags)).is_ok();
+    }
+
+    /// Return the terminal to legacy key reporting
+    pub(crate) fn disable_extended_keys(&mut self) {
+        if std::mem::take(&mut self.extended_keys) {
+            let _ = execute!(io::stdout(), PopKeyboardEnhancementFlags);
+        }
+    }
+
+    /// Whether keys are reported through the kitty keyboard protocol
+    pub fn extended_keys(&self) -> bool {
+        self.extended_keys
+    }
+
     pub(crate) fn handle_events(&mut self) -> std::io::Result<()> {
-        // Use a longer timeout when no input is expected to reduce CPU usage
-        if event::poll(std::time::Duration::from_millis(50))? {
-            match event::read() {
-                Ok(Event::Key(key_event)) => {
-                    self.handle_key_event(key_event)?;
-                }
-                Ok(Event::Resize(width, height)) => {
-                    self.layout_manager.update_size(width, height);
-                    if let Some(buffer) = self.buffer_manager.current_mut() {
-                        buffer.set_size(
-                            self.layout_manager.get_layout().text_area_width,
-                            self.layout_manager.get_layout().text_area_height,
-                        );
+        // Events read while an operation looked for Ctrl+C come first. Then
+        // wait no longer than the next animation frame; otherwise use a longer
+        // timeout to reduce CPU usage
+        let event = match interrupt::take_pending_event() {
+            Some(event) => Ok(event),
+            None if event::poll(self.poll_timeout())? => event::read(),
+            None => return Ok(()),
+        };
+        let started = Instant::now();
+        match event {
+            Ok(Event::Key(key_event)) => {
+                if let Some(key_event) = normalize_key_event(key_event) {
+                    self.restart_cursor_hold();
+                    match self.read_composed_text(key_event) {
+                        Some(text) => self.commit_composition(&text),
+                        None => self.handle_key_event(key_event)?,
                     }
-                    self.render_state.mark_all_dirty();
                 }
-                Ok(_) => {}
-                Err(_) => {}
             }
+            Ok(Event::Resize(width, height)) => self.handle_resize(width, height),
+            Ok(Event::FocusGained) => {
+                self.terminal_focused = true;
+                // Commits and checkouts may have happened meanwhile
+                self.refresh_vcs_file();
+                self.render_state.mark_all_dirty();
+            }
+            Ok(Event::FocusLost) => {
+                self.terminal_focused = false;
+                self.render_state.mark_all_dirty();
+            }
+            Ok(_) => {}
+            Err(_) => {}
         }
+        self.profile_input(started);
         Ok(())
     }
 
     pub(crate) fn handle_key_event(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
+        self.finish_scroll_animation();
+        self.record_key(key_event);
+
+        // A file-changed prompt must be answered before anything else
+        if self.handle_file_change_prompt(key_event) {
+            return Ok(());
+        }
+        if self.handle_session_prompt(key_event) {
+            return Ok(());
+        }
+        if self.handle_palette_key(key_event)? {
+            return Ok(());
+        }
+        if self.handle_pager_key(key_event)? {
+            return Ok(());
+        }
+
+        // An active completion or a pending Ctrl+k / Ctrl+v / Ctrl+x insertion
+        // gets the key first, including ESC
+        if self.mode == EditorMode::Insert {
+            if self.handle_completion_key(key_event) {
+                return Ok(());
+            }
+            if let Some(pending) = self.insert_pending.take() {
+                return self.handle_insert_pending(pending, key_event);
+            }
+        }
+
         // Handle ESC globally for robustness
-        if matches!(key_event.code, KeyCode::Esc) {
-            match self.mode {
-                EditorMode::Normal => {
-                    // Already in normal mode, no change needed
-                }
-                EditorMode::Insert | EditorMode::Visual => {
-                    self.mode = EditorMode::Normal;
-                    self.render_state.status_line_dirty = true;
-                    self.render_state.command_line_dirty = true;
-                    self.clear_message();
-                }
-                EditorMode::Command => {
-                    self.mode = EditorMode::Normal;
-                    self.command_line.clear();
-                    self.render_state.command_line_dirty = true;
-                    self.render_state.status_line_dirty = true;
-                }
+        if legacy_key(key_event).code == KeyCode::Esc {
+            self.leave_to_normal_mode();
+            return Ok(());
+        }
+
+        // Ctrl+C cancels like ESC; it does not quit
+        if is_interrupt_key(&key_event) {
+            let idle = self.mode == EditorMode::Normal
+                && self.pending_key.is_none()
+                && self.filter_motion.is_none();
+            self.leave_to_normal_mode();
+            if idle {
+                self.set_message(
+                    "Type :q and press Enter to exit niv".to_string(),
+                    MessageType::Info,
+                );
             }
             return Ok(());
         }
 
+        if self.handle_shift_select(key_event) {
+            return Ok(());
+        }
+
         match self.mode {
             EditorMode::Normal => self.handle_normal_mode(key_event),
             EditorMode::Insert => self.handle_insert_mode(key_event),
             EditorMode::Visual => self.handle_visual_mode(key_event),
+            EditorMode::Select => self.handle_select_mode(key_event),
             EditorMode::Command => self.handle_command_mode(key_event),
         }
     }
 
+    /// Cancel whatever is in progress and return to normal mode
+    pub(crate) fn leave_to_normal_mode(&mut self) {
+        self.close_palette();
+        self.stop_following();
+        match self.mode {
+            EditorMode::Normal => {
+                // Already in normal mode; just cancel any pending key
+                self.pending_key = None;
+                self.filter_motion = None;
+            }
+            EditorMode::Insert | EditorMode::Visual | EditorMode::Select => {
+                self.set_preedit("");
+                self.remember_visual_selection();
+                self.select_from_insert = false;
+                self.mode = EditorMode::Normal;
+                self.render_state.status_line_dirty = true;
+                self.render_state.command_line_dirty = true;
+                self.clear_message();
+            }
+            EditorMode::Command => {
+                self.mode = EditorMode::Normal;
+                self.command_line.clear();
+                self.render_state.command_line_dirty = true;
+                self.render_state.status_line_dirty = true;
+            }
+        }
+    }
+
+    /// Return to normal mode once Ctrl+C or SIGINT stopped an operation
+    pub(crate) fn handle_interrupt(&mut self) {
+        if interrupt::clear_interrupt() {
+            self.leave_to_normal_mode();
+            self.set_message("Interrupted".to_string(), MessageType::Warning);
+        }
+    }
+
     fn handle_normal_mode(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
+        if let Some(prefix) = self.pending_key.take() {
+            return self.handle_pending_normal(prefix, key_event);
+        }
+        if let Some(keys) = self.filter_motion.take() {
+            self.handle_filter_motion(keys, key_event);
+            return Ok(());
+        }
+
+        let mapped = match key_event.code {
+            KeyCode::Char(key) => self.plugin_keymap(key),
+            _ => None,
+        };
+        if let Some(command) = mapped {
+            return self.run_command_line(&command);
+        }
+
         match key_event.code {
+            KeyCode::Char('g') => {
+                self.pending_key = Some('g');
+            }
+            KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.pending_key = Some('w');
+            }
+            KeyCode::Char(key @ ('m' | '\'' | '`')) => {
+                self.pending_key = Some(key);
+            }
             KeyCode::Char('i') => {
                 self.mode = EditorMode::Insert;
                 self.render_state.status_line_dirty = true;
             }
+            KeyCode::Char('!') => {
+                self.filter_motion = Some(String::new());
+            }
+            KeyCode::F(1) => self.open_palette(),
+            KeyCode::Char('*') => self.jump(|editor| editor.search_word_under_cursor(false)),
+            KeyCode::Char('#') => self.jump(|editor| editor.search_word_under_cursor(true)),
+            KeyCode::Char('G') => self.jump(|editor| {
+                if let Some(buffer) = editor.buffer_manager.current_mut() {
+                    buffer.set_cursor(usize::MAX, 0);
+                }
+            }),
+            KeyCode::Char(ch @ ('d' | 'u'))
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.jump(|editor| {
+                    if let Some(buffer) = editor.buffer_manager.current_mut() {
+                        buffer.scroll_half_page(ch == 'd');
+                    }
+                })
+            }
             KeyCode::Char('v') => {
+                self.visual_start = self
+                    .buffer_manager
+                    .current()
+                    .map(|buffer| (buffer.cursor_line, buffer.cursor_col));
                 self.mode = EditorMode::Visual;
                 self.render_state.status_line_dirty = true;
             }
@@ -97,6 +264,13 @@ impl Editor {
                     self.render_state.cursor_dirty = true;
                 }
             }
+            KeyCode::Tab | KeyCode::BackTab => {
+                // Cell to cell in a table; nothing otherwise
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.move_to_field(key_event.code == KeyCode::BackTab);
+                    self.render_state.cursor_dirty = true;
+                }
+            }
             KeyCode::Char('0') => {
                 if let Some(buffer) = self.buffer_manager.current_mut() {
                     buffer.move_cursor_line_start();
@@ -154,20 +328,71 @@ impl Editor {
                 self.render_state.mark_text_dirty();
                 self.render_state.status_line_dirty = true;
             }
+            KeyCode::Char('p') => self.paste_yank(0, true),
+            KeyCode::Char('P') => self.paste_yank(0, false),
+            KeyCode::Char('"') => self.open_yank_picker(),
             KeyCode::Char('u') => { /* TODO: undo */ }
-            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
-                self.running = false;
-            }
             KeyCode::Char('q') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
                 self.running = false;
             }
+            KeyCode::Char('q') if self.recording.is_some() => self.stop_recording(),
+            KeyCode::Char(key @ ('q' | '@')) => {
+                self.pending_key = Some(key);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Run a motion that may scroll far, animating the scroll if enabled
+    pub(crate) fn jump(&mut self, motion: impl FnOnce(&mut Self)) {
+        let from = self
+            .buffer_manager
+            .current()
+            .map(|buffer| buffer.scroll_line);
+        motion(self);
+        self.render_state.status_line_dirty = true;
+        if let Some(from) = from {
+            self.animate_scroll_from(from);
+        }
+    }
+
+    /// Handle the second key of a multi-key normal mode command
+    fn handle_pending_normal(&mut self, prefix: char, key_event: KeyEvent) -> std::io::Result<()> {
+        match (prefix, key_event.code) {
+            ('g', KeyCode::Char('g')) => self.jump(|editor| {
+                if let Some(buffer) = editor.buffer_manager.current_mut() {
+                    buffer.set_cursor(0, 0);
+                }
+            }),
+            ('g', KeyCode::Char('a')) => self.show_char_info(),
+            ('g', KeyCode::Char('h')) => self.start_select(false),
+            ('g', KeyCode::Char('8')) => self.show_utf8_info(),
+            // Ctrl+w w and Ctrl+w Ctrl+w
+            ('w', KeyCode::Char('w')) => self.switch_window(),
+            ('m', KeyCode::Char(name)) => self.set_global_mark(name),
+            ('q', KeyCode::Char(name)) => self.start_recording(name),
+            ('@', KeyCode::Char(name)) => return self.replay_macro(name),
+            ('\'' | '`', KeyCode::Char(name)) => {
+                self.jump(|editor| editor.jump_to_global_mark(name, prefix == '`'))
+            }
             _ => {}
         }
         Ok(())
     }
 
     fn handle_insert_mode(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
+        if self.start_insert_pending(key_event) {
+            return Ok(());
+        }
+        let key_event = legacy_key(key_event);
+
         match key_event.code {
+            KeyCode::Char(ch @ ('n' | 'p'))
+                if key_event.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                self.start_keyword_completion(ch == 'p');
+            }
             KeyCode::Char(ch) => {
                 if let Some(buffer) = self.buffer_manager.current_mut() {
                     buffer.insert_char(ch);
@@ -207,13 +432,25 @@ impl Editor {
 
     fn handle_visual_mode(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
         match key_event.code {
-            KeyCode::Char('y') => {
-                self.mode = EditorMode::Normal;
-                self.render_state.status_line_dirty = true;
+            KeyCode::Char('y') => self.yank_visual_lines(false),
+            KeyCode::Char('d') => self.yank_visual_lines(true),
+            KeyCode::Char('!') => {
+                self.filter_visual_selection();
             }
-            KeyCode::Char('d') => {
-                self.mode = EditorMode::Normal;
-                self.render_state.status_line_dirty = true;
+            KeyCode::Char('g') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.visual_to_select();
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.move_cursor_down();
+                    self.render_state.cursor_dirty = true;
+                }
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.move_cursor_up();
+                    self.render_state.cursor_dirty = true;
+                }
             }
             _ => {}
         }
@@ -221,7 +458,7 @@ impl Editor {
     }
 
     fn handle_command_mode(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
-        match key_event.code {
+        match legacy_key(key_event).code {
             KeyCode::Char(ch) => {
                 self.command_line.push(ch);
                 self.render_state.command_line_dirty = true;
@@ -0,0 +1,190 @@
+use super::Editor;
+use crate::tui::digraph::lookup_digraph;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Multi-key insert mode input in progress
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum InsertPending {
+    /// `Ctrl+k`, waiting for the first and then the second digraph character
+    Digraph(Option<char>),
+    /// `Ctrl+v`, waiting for the character to insert literally
+    LiteralNext,
+    /// `Ctrl+v u1234` and friends, collecting code point digits
+    CodePoint {
+        radix: u32,
+        max_digits: usize,
+        digits: String,
+    },
+    /// `Ctrl+x`, waiting for the kind of completion (`Ctrl+f` for file paths)
+    CtrlX,
+}
+
+impl Editor {
+    /// `Ctrl+k` / `Ctrl+v` / `Ctrl+x` in insert mode: start a multi-key insertion.
+    ///
+    /// Returns true when the key was consumed.
+    pub(crate) fn start_insert_pending(&mut self, key_event: KeyEvent) -> bool {
+        if !key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        let pending = match key_event.code {
+            KeyCode::Char('k') => InsertPending::Digraph(None),
+            KeyCode::Char('v') => InsertPending::LiteralNext,
+            KeyCode::Char('x') => {
+                self.set_message("-- ^X mode (^F)".to_string(), super::MessageType::Info);
+                InsertPending::CtrlX
+            }
+            _ => return false,
+        };
+        self.insert_pending = Some(pending);
+        true
+    }
+
+    /// Feed a key to the pending insertion started with `Ctrl+k` or `Ctrl+v`
+    pub(crate) fn handle_insert_pending(
+        &mut self,
+        pending: InsertPending,
+        key_event: KeyEvent,
+    ) -> std::io::Result<()> {
+        match pending {
+            InsertPending::Digraph(first) => self.handle_digraph_key(first, key_event),
+            InsertPending::LiteralNext => self.handle_literal_next(key_event),
+            InsertPending::CtrlX => {
+                self.clear_message();
+                match key_event.code {
+                    KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.start_path_completion();
+                        Ok(())
+                    }
+                    KeyCode::Esc => Ok(()),
+                    // Anything else leaves ^X mode and is typed normally
+                    _ => self.handle_key_event(key_event),
+                }
+            }
+            InsertPending::CodePoint {
+                radix,
+                max_digits,
+                mut digits,
+            } => {
+                let digit = match key_event.code {
+                    KeyCode::Char(ch) if ch.is_digit(radix) => Some(ch),
+                    _ => None,
+                };
+                match digit {
+                    Some(ch) => {
+                        digits.push(ch);
+                        if digits.len() == max_digits {
+                            self.insert_code_point(&digits, radix);
+                        } else {
+                            self.insert_pending = Some(InsertPending::CodePoint {
+                                radix,
+                                max_digits,
+                                digits,
+                            });
+                        }
+                    }
+                    None => {
+                        // A non-digit ends the code point early; the key itself is then typed normally
+                        self.insert_code_point(&digits, radix);
+                        return self.handle_key_event(key_event);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_digraph_key(
+        &mut self,
+        first: Option<char>,
+        key_event: KeyEvent,
+    ) -> std::io::Result<()> {
+        let KeyCode::Char(ch) = key_event.code else {
+            // Esc or any special key cancels the digraph
+            return Ok(());
+        };
+        match first {
+            None => self.insert_pending = Some(InsertPending::Digraph(Some(ch))),
+            // Unknown digraphs insert the second character, as in vim
+            Some(first) => self.insert_literal(lookup_digraph(first, ch).unwrap_or(ch)),
+        }
+        Ok(())
+    }
+
+    fn handle_literal_next(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
+        let code_point = |radix, max_digits| InsertPending::CodePoint {
+            radix,
+            max_digits,
+            digits: String::new(),
+        };
+
+        let ch = match key_event.code {
+            KeyCode::Char(ch) if key_event.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Ctrl+v Ctrl+a inserts the control character itself (^A);
+                // chars with no control character go in as they are
+                let upper = ch.to_ascii_uppercase();
+                if ('@'..='_').contains(&upper) {
+                    char::from(upper as u8 & 0x1f)
+                } else {
+                    ch
+                }
+            }
+            KeyCode::Char('u') => {
+                self.insert_pending = Some(code_point(16, 4));
+                return Ok(());
+            }
+            KeyCode::Char('U') => {
+                self.insert_pending = Some(code_point(16, 8));
+                return Ok(());
+            }
+            KeyCode::Char('x') | KeyCode::Char('X') => {
+                self.insert_pending = Some(code_point(16, 2));
+                return Ok(());
+            }
+            KeyCode::Char('o') | KeyCode::Char('O') => {
+                self.insert_pending = Some(code_point(8, 3));
+                return Ok(());
+            }
+            KeyCode::Char(ch) if ch.is_ascii_digit() => {
+                self.insert_pending = Some(InsertPending::CodePoint {
+                    radix: 10,
+                    max_digits: 3,
+                    digits: ch.to_string(),
+                });
+                return Ok(());
+            }
+            KeyCode::Char(ch) => ch,
+            KeyCode::Tab => '\t',
+            KeyCode::Enter => '\r',
+            KeyCode::Esc => '\x1b',
+            KeyCode::Backspace => '\x08',
+            _ => return Ok(()),
+        };
+        self.insert_literal(ch);
+        Ok(())
+    }
+
+    /// Insert the character for the collected code point digits, ignoring invalid values
+    fn insert_code_point(&mut self, digits: &str, radix: u32) {
+        let ch = u32::from_str_radix(digits, radix)
+            .ok()
+            // Decimal entry is limited to a single byte value
+            .filter(|&value| radix != 10 || value <= 255)
+            .and_then(char::from_u32);
+        match ch {
+            Some(ch) => self.insert_literal(ch),
+            None if !digits.is_empty() => self.set_message(
+                format!("Invalid character code: {}", digits),
+                super::MessageType::Error,
+            ),
+            None => {}
+        }
+    }
+
+    fn insert_literal(&mut self, ch: char) {
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.insert_char(ch);
+            self.render_state.mark_text_dirty();
+        }
+    }
+}
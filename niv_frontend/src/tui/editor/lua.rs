@@ -0,0 +1,210 @@
+//! Lua scripting (`lua` feature).
+//!
+//! `init.lua` next to the user's `config.toml` runs at startup, after the TOML
+//! configuration. Scripts reach the editor through the global `niv` table:
+//!
+//! - `niv.text()`: text of the current buffer
+//! - `niv.cursor()`: cursor line and byte column, both 0-based
+//! - `niv.args()`: arguments of the command being run
+//! - `niv.edit(start, end, text)`: replace bytes `start..end` of the buffer
+//! - `niv.command(name, fn)`: define `:name`
+//! - `niv.map(key, command)`: run an ex command when `key` is typed in normal mode
+//! - `niv.autocmd(event, fn)`: run `fn` on an event (`CursorHold`)
+//! - `niv.set(option)`: set a buffer-local option, as with `:setlocal`
+//! - `niv.cmd(command)`: run an ex command line
+//! - `niv.message(text)`: show a message
+//!
+//! Changes are applied when the script or callback returns. `:lua {chunk}`
+//! runs a chunk directly.
+
+use super::plugins::PluginCall;
+use super::{Editor, MessageType};
+use crate::tui::plugin::{PluginCapability, PluginContext, PluginRequest};
+use mlua::{Function, Lua, RegistryKey, Table};
+use std::path::PathBuf;
+
+/// Name shown in messages from Lua code
+const SCRIPT_NAME: &str = "lua";
+
+/// Functions handed to `niv.command` and `niv.autocmd`, indexed by `PluginCall::Lua`
+struct Callbacks(Vec<RegistryKey>);
+
+/// The Lua interpreter with the `niv` API installed
+pub(crate) struct LuaRuntime {
+    state: Lua,
+}
+
+impl Default for LuaRuntime {
+    fn default() -> Self {
+        let lua = Lua::new();
+        lua.set_app_data(PluginContext::new(PluginCapability::ALL.to_vec()));
+        lua.set_app_data(Callbacks(Vec::new()));
+        // Installing plain functions into a fresh state cannot fail short of
+        // running out of memory
+        let _ = install_api(&lua);
+        Self { state: lua }
+    }
+}
+
+impl Editor {
+    /// Run `init.lua` from the first configuration directory that has one
+    pub(crate) fn load_init_lua(&mut self) {
+        let Some(path) = init_lua_path(self.config_loader.paths()) else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(source) => {
+                let name = path.display().to_string();
+                self.run_lua(|lua| lua.load(source.as_str()).set_name(name).exec(), "");
+            }
+            Err(e) => self.set_message(
+                format!("Can't read {}: {}", path.display(), e),
+                MessageType::Error,
+            ),
+        }
+    }
+
+    /// `:lua {chunk}`
+    pub(crate) fn exec_lua(&mut self, chunk: &str) {
+        self.run_lua(|lua| lua.load(chunk).set_name(":lua").exec(), "");
+    }
+
+    /// Run a function registered with `niv.command` or `niv.autocmd`
+    pub(crate) fn call_lua(&mut self, callback: usize, args: &str) {
+        self.run_lua(
+            |lua| {
+                let function = lua
+                    .app_data_ref::<Callbacks>()
+                    .and_then(|callbacks| {
+                        let key = callbacks.0.get(callback)?;
+                        Some(lua.registry_value::<Function>(key))
+                    })
+                    .ok_or_else(|| mlua::Error::runtime("unknown callback"))?;
+                function?.call::<_, ()>(())
+            },
+            args,
+        );
+    }
+
+    /// Run Lua code with the current buffer visible to it, then apply what it asked for
+    fn run_lua(&mut self, run: impl FnOnce(&Lua) -> mlua::Result<()>, args: &str) {
+        let lua = &self.lua.state;
+        if let Some(mut context) = lua.app_data_mut::<PluginContext>() {
+            context.prepare(self.buffer_manager.current(), args);
+        }
+        let result = run(lua);
+        let requests = lua
+            .app_data_mut::<PluginContext>()
+            .map(|mut context| std::mem::take(&mut context.requests))
+            .unwrap_or_default();
+
+        self.apply_plugin_requests(SCRIPT_NAME, requests, |callback| PluginCall::Lua {
+            callback: callback.parse().unwrap_or(usize::MAX),
+        });
+        if let Err(e) = result {
+            self.set_message(format!("{}: {}", SCRIPT_NAME, e), MessageType::Error);
+        }
+    }
+}
+
+/// `init.lua` next to a `config.toml`; project-local `.niv.toml` files never
+/// run scripts
+fn init_lua_path(config_paths: &[PathBuf]) -> Option<PathBuf> {
+    config_paths
+        .iter()
+        .filter(|path| path.file_name().is_some_and(|name| name == "config.toml"))
+        .filter_map(|path| path.parent())
+        .map(|dir| dir.join("init.lua"))
+        .find(|path| path.is_file())
+}
+
+/// Queue a request from Lua code
+fn push_request(lua: &Lua, request: PluginRequest) -> mlua::Result<()> {
+    let mut context = lua
+        .app_data_mut::<PluginContext>()
+        .ok_or_else(|| mlua::Error::runtime("editor state unavailable"))?;
+    context.requests.push(request);
+    Ok(())
+}
+
+/// Keep a Lua function for later calls, returning its index as a string for
+/// the `export` of a request
+fn register_callback(lua: &Lua, function: Function) -> mlua::Result<String> {
+    let key = lua.create_registry_value(function)?;
+    let mut callbacks = lua
+        .app_data_mut::<Callbacks>()
+        .ok_or_else(|| mlua::Error::runtime("editor state unavailable"))?;
+    callbacks.0.push(key);
+    Ok((callbacks.0.len() - 1).to_string())
+}
+
+/// Read a value of the current call's context
+fn with_context<T>(lua: &Lua, read: impl FnOnce(&mut PluginContext) -> T) -> mlua::Result<T> {
+    let mut context = lua
+        .app_data_mut::<PluginContext>()
+        .ok_or_else(|| mlua::Error::runtime("editor state unavailable"))?;
+    Ok(read(&mut context))
+}
+
+fn install_api(lua: &Lua) -> mlua::Result<()> {
+    let niv: Table = lua.create_table()?;
+
+    niv.set(
+        "text",
+        lua.create_function(|lua, ()| with_context(lua, |context| context.text.clone()))?,
+    )?;
+    niv.set(
+        "cursor",
+        lua.create_function(|lua, ()| {
+            with_context(lua, |context| (context.cursor_line, context.cursor_col))
+        })?,
+    )?;
+    niv.set(
+        "args",
+        lua.create_function(|lua, ()| with_context(lua, |context| context.args.clone()))?,
+    )?;
+    niv.set(
+        "edit",
+        lua.create_function(|lua, (start, end, text): (usize, usize, String)| {
+            with_context(lua, |context| context.edit(start, end, text))
+        })?,
+    )?;
+    niv.set(
+        "command",
+        lua.create_function(|lua, (name, function): (String, Function)| {
+            let export = register_callback(lua, function)?;
+            push_request(lua, PluginRequest::RegisterCommand { name, export })
+        })?,
+    )?;
+    niv.set(
+        "map",
+        lua.create_function(|lua, (keys, command): (String, String)| {
+            push_request(lua, PluginRequest::MapKey { keys, command })
+        })?,
+    )?;
+    niv.set(
+        "autocmd",
+        lua.create_function(|lua, (event, function): (String, Function)| {
+            let export = register_callback(lua, function)?;
+            push_request(lua, PluginRequest::Subscribe { event, export })
+        })?,
+    )?;
+    niv.set(
+        "set",
+        lua.create_function(|lua, option: String| {
+            push_request(lua, PluginRequest::SetOption(option))
+        })?,
+    )?;
+    niv.set(
+        "cmd",
+        lua.create_function(|lua, command: String| {
+            push_request(lua, PluginRequest::Command(command))
+        })?,
+    )?;
+    niv.set(
+        "message",
+        lua.create_function(|lua, text: String| push_request(lua, PluginRequest::Message(text)))?,
+    )?;
+
+    lua.globals().set("niv", niv)
+}
@@ -0,0 +1,127 @@
+use super::{Editor, MessageType};
+use crate::tui::keys::{key_notation, parse_key_notation};
+use crossterm::event::KeyEvent;
+use std::fmt::Write as _;
+
+/// How deep a macro may replay macros that replay macros
+const MAX_MACRO_DEPTH: usize = 100;
+
+impl Editor {
+    /// `q{a-z}`: record the keys typed from now on into a register
+    pub(super) fn start_recording(&mut self, name: char) {
+        if !name.is_ascii_lowercase() {
+            self.set_message(
+                "Macros are recorded into the registers a-z".to_string(),
+                MessageType::Warning,
+            );
+            return;
+        }
+        self.recording = Some((name, Vec::new()));
+        self.set_message(format!("recording @{}", name), MessageType::Info);
+    }
+
+    /// `q` while recording: keep the keys typed since `q{a-z}`, and put
+    /// them in the shada file too with `shada_macros`
+    pub(super) fn stop_recording(&mut self) {
+        let Some((name, mut keys)) = self.recording.take() else {
+            return;
+        };
+        // The `q` that stopped the recording
+        keys.pop();
+        let text: String = keys.into_iter().filter_map(key_notation).collect();
+        if self.config().editor.shada_macros {
+            self.shared_data.set_register(name, &text, false);
+        }
+        self.macros.insert(name, text);
+        self.clear_message();
+    }
+
+    /// Add a key typed while recording to the macro, stopping at
+    /// `macro_max_keys`. Keys a macro replays are left out, as the `@` that
+    /// replays it is in already.
+    pub(super) fn record_key(&mut self, key_event: KeyEvent) {
+        if self.macro_depth > 0 {
+            return;
+        }
+        let max_keys = self.config().editor.macro_max_keys as usize;
+        let Some((_, keys)) = &mut self.recording else {
+            return;
+        };
+        if keys.len() < max_keys {
+            keys.push(key_event);
+            return;
+        }
+        // Keep the keys so far, as if `q` was typed now
+        keys.push(key_event);
+        self.stop_recording();
+        self.set_message(
+            format!("Macro too long; recording stopped at {} keys", max_keys),
+            MessageType::Warning,
+        );
+    }
+
+    /// `@{a-z}`: type the keys of a macro again; `@@` replays the one
+    /// replayed last
+    pub(super) fn replay_macro(&mut self, name: char) -> std::io::Result<()> {
+        let name = match name {
+            '@' => match self.last_macro {
+                Some(last) => last,
+                None => {
+                    self.set_message("No previous macro".to_string(), MessageType::Error);
+                    return Ok(());
+                }
+            },
+            name => name,
+        };
+        let Some(text) = self.macros.get(&name).cloned() else {
+            self.set_message(format!("Register {} is empty", name), MessageType::Error);
+            return Ok(());
+        };
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            self.set_message("Macros nested too deeply".to_string(), MessageType::Error);
+            return Ok(());
+        }
+        self.last_macro = Some(name);
+        self.macro_depth += 1;
+        let mut replayed = Ok(());
+        for key in parse_key_notation(&text) {
+            if !self.running {
+                break;
+            }
+            replayed = self.handle_key_event(key);
+            if replayed.is_err() {
+                break;
+            }
+        }
+        self.macro_depth -= 1;
+        replayed
+    }
+
+    /// Take the macros kept in the shada file, with `shada_macros`, leaving
+    /// out ones longer than `macro_max_keys`
+    pub(super) fn load_macros(&mut self) {
+        let editor = self.config().editor;
+        if !editor.shada_macros {
+            return;
+        }
+        for (name, register) in self.shared_data.registers() {
+            let fits = parse_key_notation(&register.text).len() <= editor.macro_max_keys as usize;
+            if name.is_ascii_lowercase() && fits {
+                self.macros.insert(name, register.text.clone());
+            }
+        }
+    }
+
+    /// `:macros`: list the recorded macros in a scratch buffer
+    pub(super) fn show_macros(&mut self) {
+        if self.macros.is_empty() {
+            self.set_message("No macros".to_string(), MessageType::Info);
+            return;
+        }
+        let mut report = String::new();
+        for (name, text) in &self.macros {
+            let _ = writeln!(report, "@{}  {}", name, text);
+        }
+        self.show_report(&report);
+    }
+}
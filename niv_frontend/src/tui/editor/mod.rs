@@ -1,18 +1,90 @@
-use crate::tui::{buffer::*, layout::*, theme::*};
+use crate::tui::{
+    arglist::ArgList,
+    buffer::*,
+    completion::Completion,
+    diff::DiffView,
+    crash,
+    interrupt,
+    excmd::LineRange,
+    killring::KillRing,
+    layout::*,
+    markdown::MarkdownPreview,
+    palette::Palette,
+    profile::{FramePhase, Profiler},
+    theme::*,
+    rpc::RpcServer,
+    screen::Output,
+    session::Session,
+    timer::{TimerId, TimerQueue},
+};
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture, KeyEvent,
+    },
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use niv_config::{Config, ConfigLoader};
+use niv_fs::{FileWatcher, SharedData, SwapManager, WatcherConfig};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io;
 use std::path::PathBuf;
+use std::time::Instant;
 
+mod args;
+mod brackets;
+mod charinfo;
 mod commands;
+mod complete;
+mod compose;
+mod cwd;
+mod diff;
+mod filter;
+mod headless;
+mod hold;
+mod inline;
 mod input;
+mod literal;
+#[cfg(feature = "lua")]
+mod lua;
+mod macros;
+mod pager;
+mod palette;
+mod plugins;
+mod preview;
+mod profile;
+mod reload;
+mod rename;
 mod render;
+mod reopen;
+mod resize;
+mod rpc;
+mod scroll;
+mod search;
+mod select;
+mod session;
+mod shada;
+mod swap;
+mod table;
+mod tail;
+mod timers;
+mod vcs;
+mod wall;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod window;
+mod yank;
 
+use literal::InsertPending;
+use pager::PagerState;
+use plugins::PluginCall;
 use render::RenderState;
+use reopen::ClosedBuffer;
+use scroll::ScrollAnimation;
+use vcs::VcsFile;
+use window::SplitWindow;
+
+pub use timers::TimerCallback;
 
 /// Main TUI editor
 pub struct Editor {
@@ -22,13 +94,114 @@ pub struct Editor {
     pub buffer_manager: BufferManager,
     command_line: String,
     mode: EditorMode,
+    /// First key of a multi-key normal mode command (e.g. `g` of `ga`)
+    pending_key: Option<char>,
+    /// Digraph or literal character being entered in insert mode
+    insert_pending: Option<InsertPending>,
+    /// Insert mode completion being cycled through
+    completion: Option<Completion>,
+    /// Text an input method is composing at the cursor, drawn underlined
+    preedit: Option<String>,
+    /// Command palette, while open
+    palette: Option<Palette>,
+    /// Pager state when running as `niv --view`
+    pager: Option<PagerState>,
+    /// Markdown preview of the current buffer, while shown with `:MarkdownPreview`
+    markdown_preview: Option<MarkdownPreview>,
+    /// File compared with the current buffer and its on-disk text, while
+    /// shown with `:DiffOrig`
+    diff_view: Option<(PathBuf, DiffView)>,
+    /// Repository of the current buffer's file, for the gutter, `:Blame`
+    /// and the branch in the status line
+    vcs_file: Option<VcsFile>,
+    /// Second window on the current buffer, while shown with `:vsplit`
+    split_window: Option<SplitWindow>,
+    /// Scroll the split window along with the text area
+    scroll_bind: bool,
+    /// Keys typed after `!` in normal mode while the motion is incomplete
+    filter_motion: Option<String>,
+    /// Line and byte column where the current visual or select mode
+    /// selection started
+    visual_start: Option<(usize, usize)>,
+    /// Select mode was entered from insert mode and goes back to it
+    select_from_insert: bool,
+    /// Lines of the last visual selection, for `'<` and `'>`
+    last_visual: Option<LineRange>,
+    /// Recent yanks and deletes, for `p`, `P` and the `:Yanks` picker
+    kill_ring: KillRing,
     running: bool,
     /// Rendering state for selective updates
     render_state: RenderState,
+    /// Where frames are drawn
+    output: Output,
+    /// Smooth scroll in progress
+    scroll_animation: Option<ScrollAnimation>,
+    /// Whether the kitty keyboard protocol was switched on
+    extended_keys: bool,
+    /// Whether the terminal window has focus; the editor is dimmed while it does not
+    terminal_focused: bool,
     /// Status/error message to display to user
     message: Option<String>,
     /// Message type for color coding
     message_type: MessageType,
+    /// Timer that clears the current message
+    message_timer: Option<TimerId>,
+    /// Messages shown so far, oldest first, for `:messages`
+    message_history: VecDeque<String>,
+    /// Timers started with `set_timeout`/`set_interval`
+    timers: TimerQueue<TimerCallback>,
+    /// Timer that applies the last of a burst of resize events
+    resize_timer: Option<TimerId>,
+    /// Timer that fires CursorHold after `updatetime` without input
+    cursor_hold_timer: Option<TimerId>,
+    /// Timer loading lines appended to followed files
+    tail_timer: Option<TimerId>,
+    /// Callbacks registered with `on_cursor_hold`
+    cursor_hold_handlers: Vec<TimerCallback>,
+    /// Crash-recovery swap files (None when `swapfile` is disabled)
+    swap_manager: Option<SwapManager>,
+    /// Content hash last pushed to the swap manager, per file
+    last_swap_hash: HashMap<PathBuf, u64>,
+    /// External change detection for open files
+    file_watcher: FileWatcher,
+    /// Files changed on disk while their buffer was modified, waiting for an answer
+    file_change_prompts: VecDeque<PathBuf>,
+    /// Session saved last time, while the prompt to restore it is up
+    pending_session: Option<Session>,
+    /// Where the project's session is saved on exit, with `auto_session`
+    session_file: Option<PathBuf>,
+    /// Global marks, histories and file positions, kept between runs in
+    /// `shada_file` when there is one
+    shared_data: SharedData,
+    shada_file: Option<PathBuf>,
+    /// Register and keys of the macro being recorded with `q`
+    recording: Option<(char, Vec<KeyEvent>)>,
+    /// Macros recorded with `q`, in key notation, by register
+    macros: BTreeMap<char, String>,
+    /// Register of the macro `@@` replays
+    last_macro: Option<char>,
+    /// How many macros replaying each other are being replayed
+    macro_depth: usize,
+    /// Working directory before the last `:cd`/`:lcd`, for `:cd -`
+    previous_cwd: Option<PathBuf>,
+    /// Files given on the command line or with `:args`
+    arg_list: ArgList,
+    /// Recently closed buffers, most recent last, for `:reopen`
+    closed_buffers: Vec<ClosedBuffer>,
+    /// Ex commands registered by plugins
+    plugin_commands: HashMap<String, PluginCall>,
+    /// Normal mode keys mapped to ex commands by plugins
+    plugin_keymaps: HashMap<char, String>,
+    /// Server for external tools, started with `listen`
+    rpc: Option<RpcServer>,
+    /// Frame and command timings, while profiling is on
+    profiler: Option<Profiler>,
+    /// Loaded WebAssembly plugins
+    #[cfg(feature = "wasm")]
+    wasm_plugins: wasm::WasmRuntime,
+    /// Interpreter running `init.lua`
+    #[cfg(feature = "lua")]
+    lua: lua::LuaRuntime,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +217,8 @@ pub enum EditorMode {
     Normal,
     Insert,
     Visual,
+    /// Visual mode where typed text replaces the selection
+    Select,
     Command,
 }
 
@@ -53,39 +228,163 @@ impl Editor {
         // Config loading is required at startup; if it fails we cannot proceed.
         config_loader.load().expect("Failed to load configuration");
 
+        #[cfg_attr(not(any(feature = "wasm", feature = "lua")), allow(unused_mut))]
+        let mut editor = Self::with_config_loader(config_loader, Output::Stdout);
+        #[cfg(feature = "wasm")]
+        editor.load_wasm_plugins();
+        #[cfg(feature = "lua")]
+        editor.load_init_lua();
+        editor
+    }
+
+    /// Editor with loaded configuration and no plugins
+    fn with_config_loader(config_loader: ConfigLoader, output: Output) -> Self {
         let config = config_loader.get_copy();
         let theme = TerminalTheme::from_config(&config.ui);
+        let swap_manager = Self::create_swap_manager(&config.editor);
+        let mut layout_manager = LayoutManager::new();
+        layout_manager.set_winbar(config.ui.winbar);
+        if !config.ui.alternate_screen {
+            layout_manager.set_inline_height(Some(config.ui.inline_height));
+        }
 
         Self {
             config_loader,
-            layout_manager: LayoutManager::new(),
+            layout_manager,
             theme,
             buffer_manager: BufferManager::new(),
             command_line: String::new(),
             mode: EditorMode::Normal,
+            pending_key: None,
+            insert_pending: None,
+            completion: None,
+            preedit: None,
+            palette: None,
+            pager: None,
+            markdown_preview: None,
+            diff_view: None,
+            vcs_file: None,
+            split_window: None,
+            scroll_bind: false,
+            filter_motion: None,
+            visual_start: None,
+            select_from_insert: false,
+            last_visual: None,
+            kill_ring: KillRing::default(),
             running: true,
             render_state: RenderState::default(),
+            output,
+            scroll_animation: None,
+            extended_keys: false,
+            terminal_focused: true,
             message: None,
             message_type: MessageType::Info,
+            message_timer: None,
+            message_history: VecDeque::new(),
+            timers: TimerQueue::new(),
+            resize_timer: None,
+            cursor_hold_timer: None,
+            tail_timer: None,
+            cursor_hold_handlers: Vec::new(),
+            swap_manager,
+            last_swap_hash: HashMap::new(),
+            file_watcher: FileWatcher::new(WatcherConfig::default()),
+            file_change_prompts: VecDeque::new(),
+            pending_session: None,
+            session_file: None,
+            shared_data: SharedData::new(),
+            shada_file: None,
+            recording: None,
+            macros: BTreeMap::new(),
+            last_macro: None,
+            macro_depth: 0,
+            previous_cwd: None,
+            arg_list: ArgList::default(),
+            closed_buffers: Vec::new(),
+            plugin_commands: HashMap::new(),
+            plugin_keymaps: HashMap::new(),
+            rpc: None,
+            profiler: None,
+            #[cfg(feature = "wasm")]
+            wasm_plugins: wasm::WasmRuntime::default(),
+            #[cfg(feature = "lua")]
+            lua: lua::LuaRuntime::default(),
         }
     }
 
     /// Main event/render loop
     pub fn run(&mut self) -> std::io::Result<()> {
+        // A panic from here on must give the terminal back
+        crash::install_crash_handler();
+        interrupt::install_interrupt_handler();
+
         // Setup terminal
         enable_raw_mode()?;
         let mut stdout = io::stdout();
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+        if self.is_inline() {
+            // No mouse capture either, so the wheel still scrolls the terminal
+            execute!(stdout, EnableFocusChange)?;
+        } else {
+            execute!(
+                stdout,
+                EnterAlternateScreen,
+                EnableMouseCapture,
+                EnableFocusChange
+            )?;
+        }
 
-        // Clear any previous output
-        execute!(
-            stdout,
-            crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
-        )?;
+        self.enable_extended_keys();
+        interrupt::set_interrupt_polling(true);
+
+        if self.is_inline() {
+            self.reserve_inline_rows()?;
+        } else {
+            // Clear any previous output
+            execute!(
+                stdout,
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+            )?;
+        }
 
         // Initialize layout
         self.layout_manager.update_from_terminal()?;
 
+        self.start();
+
+        // Main loop
+        while self.running {
+            // Handle events first to avoid lag
+            self.handle_events()?;
+            self.frame()?;
+        }
+
+        // Cleanup
+        if let Err(e) = self.save_auto_session() {
+            niv_fs::log!(Warn, "session", "saving the session failed: {}", e);
+        }
+        if let Err(e) = self.save_shada() {
+            niv_fs::log!(Warn, "shada", "saving the shada file failed: {}", e);
+        }
+        self.shutdown_swap();
+        interrupt::set_interrupt_polling(false);
+        self.disable_extended_keys();
+        if self.is_inline() {
+            self.release_inline_rows()?;
+            execute!(stdout, DisableFocusChange)?;
+        } else {
+            execute!(
+                stdout,
+                LeaveAlternateScreen,
+                DisableMouseCapture,
+                DisableFocusChange
+            )?;
+        }
+        disable_raw_mode()?;
+        Ok(())
+    }
+
+    /// Size the buffers for the layout and get ready for the first frame
+    fn start(&mut self) {
         // Create a default buffer only if no buffers exist
         if self.buffer_manager.buffer_count() == 0 {
             let mut buffer = TextBuffer::new();
@@ -109,22 +408,35 @@ impl Editor {
             self.render_state.init_from_buffer(buffer);
         }
 
-        // Main loop
-        while self.running {
-            // Handle events first to avoid lag
-            self.handle_events()?;
-            
-            // Only update render state and draw if something changed
-            self.update_render_state();
-            if self.needs_redraw() {
-                self.draw()?;
-                self.render_state.clear_dirty();
-            }
-        }
+        self.restart_cursor_hold();
+    }
 
-        // Cleanup
-        execute!(stdout, LeaveAlternateScreen, DisableMouseCapture)?;
-        disable_raw_mode()?;
+    /// Everything the main loop does after handling an event: run timers and
+    /// background work, then draw what changed
+    fn frame(&mut self) -> std::io::Result<()> {
+        let started = Instant::now();
+        self.run_due_timers();
+        self.process_rpc_requests();
+        self.read_pager_stream();
+        self.advance_scroll_animation();
+        self.sync_swap();
+        self.handle_interrupt();
+        crash::set_crash_open_files(
+            self.buffer_manager
+                .iter()
+                .filter_map(|buffer| buffer.file_path.as_deref()),
+        );
+        self.profile_phase(FramePhase::Events, started);
+
+        // Only update render state and draw if something changed
+        let started = Instant::now();
+        self.update_render_state();
+        self.profile_phase(FramePhase::Layout, started);
+        if self.needs_redraw() {
+            self.draw()?;
+            self.render_state.clear_dirty();
+        }
+        self.profile_end_frame();
         Ok(())
     }
 
@@ -134,15 +446,23 @@ impl Editor {
         path: PathBuf,
         load_result: niv_fs::FileLoadResult,
     ) -> std::io::Result<()> {
-        let buffer = TextBuffer::from_file_load_result(path, load_result);
+        let mut buffer = TextBuffer::from_file_load_result(path, load_result);
+        buffer.apply_local_options(&self.config_loader.get_copy().editor);
         self.buffer_manager.add_buffer(buffer);
+        self.register_current_swap();
+        self.watch_current_file();
+        self.apply_autochdir();
         Ok(())
     }
 
     /// Create a new empty buffer
     pub fn create_new_buffer(&mut self, path: PathBuf) -> std::io::Result<()> {
-        let buffer = TextBuffer::new_with_path(path);
+        let mut buffer = TextBuffer::new_with_path(path);
+        buffer.apply_local_options(&self.config_loader.get_copy().editor);
         self.buffer_manager.add_buffer(buffer);
+        self.register_current_swap();
+        self.watch_current_file();
+        self.apply_autochdir();
         Ok(())
     }
 
@@ -163,13 +483,24 @@ impl Editor {
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
         let config = self.config_loader.get_copy();
         self.theme = TerminalTheme::from_config(&config.ui);
+        self.layout_manager.set_winbar(config.ui.winbar);
+        let layout = self.layout_manager.get_layout();
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.render_state.mark_all_dirty();
         Ok(())
     }
 
     /// Set a message to display to the user
     pub fn set_message(&mut self, message: String, msg_type: MessageType) {
+        if msg_type == MessageType::Error {
+            niv_fs::log!(Warn, "editor", "{}", message);
+        }
+        self.record_message(&message);
         self.message = Some(message);
         self.message_type = msg_type;
+        self.schedule_message_dismiss(msg_type);
         self.render_state.status_line_dirty = true;
     }
 
@@ -183,8 +514,45 @@ impl Editor {
 
     // The following methods are implemented in submodules:
     // - update_render_state, needs_redraw, draw, position_cursor, clear/draw helpers (render)
+    // - highlighting the bracket matching the one at the cursor (brackets)
+    // - smooth scrolling animation and frame timing (scroll)
+    // - one-shot and repeating timers, message auto-dismiss (timers)
+    // - debounced terminal resizes (resize)
+    // - running inline below the shell prompt (inline)
+    // - drawing into an in-memory screen for tests (headless)
+    // - the CursorHold event after `updatetime` without input (hold)
+    // - the command palette (palette)
+    // - the read-only pager of `niv --view` (pager)
+    // - plugin commands, key mappings and host requests (plugins)
+    // - the WebAssembly plugin runtime, with the `wasm` feature (wasm)
+    // - `init.lua` and `:lua`, with the `lua` feature (lua)
+    // - the RPC server for external tools (rpc)
+    // - frame and command timing with `:profile` (profile)
     // - handle_events, handle_key_event, handle_*_mode (input)
     // - execute_command (commands)
+    // - working directories, path resolution and `:e {file}` (cwd)
+    // - the argument list and `:argdo` (args)
+    // - filtering lines through external commands (filter)
+    // - digraph and literal character entry in insert mode (literal)
+    // - insert mode completion of keywords and file paths (complete)
+    // - input method composition and committed text (compose)
+    // - searching for the word under the cursor (search)
+    // - select mode and Shift+Arrow selection (select)
+    // - yanking, deleting and pasting lines, and the kill ring picker (yank)
+    // - swap file registration and syncing (swap)
+    // - reloading from disk and external change checks (reload)
+    // - following files that grow with `:tail` (tail)
+    // - lining up CSV/TSV fields in columns with `:CsvAlign` (table)
+    // - the Markdown preview split of `:MarkdownPreview` (preview)
+    // - comparing the buffer with its file on disk with `:DiffOrig` (diff)
+    // - version control signs, `:Blame` and the branch of the current file (vcs)
+    // - a second window on the buffer with `:vsplit`, and `scrollbind` (window)
+    // - renaming and moving the current file (rename)
+    // - writing all modified buffers with `:wall`, and `:messages` (wall)
+    // - closing buffers and `:reopen` (reopen)
+    // - saving and restoring the session of a project (session)
+    // - global marks and the last cursor position in files, kept in the shada file (shada)
+    // - recording and replaying macros, and `:macros` (macros)
 }
 
 impl Default for Editor {
@@ -0,0 +1,318 @@
+use super::{Editor, MessageType};
+use crate::tui::buffer::TextBuffer;
+use crate::tui::interrupt::is_interrupt_key;
+use crate::tui::pager::{StreamDecoder, find_line, view_percent};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Most bytes read from a stream at once
+const STREAM_CHUNK: usize = 64 * 1024;
+
+/// Input read on a background thread, appended to the pager's buffer as it
+/// arrives
+struct PagerStream {
+    chunks: Receiver<Vec<u8>>,
+    decoder: StreamDecoder,
+}
+
+/// State of the read-only pager started with `niv --view`
+#[derive(Default)]
+pub(crate) struct PagerState {
+    /// Name shown for a buffer read from a stream
+    stream_name: Option<String>,
+    /// The stream, until it ends
+    stream: Option<PagerStream>,
+    /// `F`: keep the end of the input in view as it grows
+    following: bool,
+    /// Search being typed after `/` or `?`
+    prompt: Option<(char, String)>,
+    /// Last search pattern and whether it searched backward
+    last_search: Option<(String, bool)>,
+}
+
+impl Editor {
+    /// Turn the editor into a read-only pager like `less`: keys scroll and
+    /// search instead of editing, and no swap files are written. Call it
+    /// before opening files.
+    pub fn start_pager(&mut self) {
+        self.swap_manager = None;
+        self.pager = Some(PagerState::default());
+        self.render_state.mark_all_dirty();
+    }
+
+    pub fn is_pager(&self) -> bool {
+        self.pager.is_some()
+    }
+
+    /// Page through what `reader` produces, shown as `name`, reading it in
+    /// the background so the first screen shows before the input ends
+    pub fn view_stream(&mut self, name: &str, mut reader: impl Read + Send + 'static) {
+        let (sender, chunks) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut chunk = vec![0; STREAM_CHUNK];
+            loop {
+                match reader.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        if sender.send(chunk[..n].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        niv_fs::log!(Warn, "pager", "reading input failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+
+        let mut buffer = TextBuffer::new();
+        buffer.no_swap = true;
+        buffer.no_undo_file = true;
+        let layout = self.layout_manager.get_layout();
+        buffer.set_size(layout.text_area_width, layout.text_area_height);
+        self.buffer_manager.add_buffer(buffer);
+        let pager = self.pager.get_or_insert_with(PagerState::default);
+        pager.stream_name = Some(name.to_string());
+        pager.stream = Some(PagerStream {
+            chunks,
+            decoder: StreamDecoder::new(),
+        });
+        self.render_state.mark_all_dirty();
+    }
+
+    /// Append what arrived on the pager's stream since the last frame
+    pub(crate) fn read_pager_stream(&mut self) {
+        let Some(stream) = self.pager.as_mut().and_then(|pager| pager.stream.as_mut()) else {
+            return;
+        };
+        let mut text = String::new();
+        let ended = loop {
+            match stream.chunks.try_recv() {
+                Ok(chunk) => text.push_str(&stream.decoder.decode(&chunk)),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => {
+                    text.push_str(&stream.decoder.finish());
+                    break true;
+                }
+            }
+        };
+        if let (true, Some(pager)) = (ended, &mut self.pager) {
+            pager.stream = None;
+        }
+        if text.is_empty() {
+            return;
+        }
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.append(&text);
+        }
+        if self.pager.as_ref().is_some_and(|pager| pager.following) {
+            self.pager_scroll_to(usize::MAX);
+        }
+        self.render_state.status_line_dirty = true;
+    }
+
+    /// Handle a key while paging; false if the editor isn't a pager
+    pub(crate) fn handle_pager_key(&mut self, key_event: KeyEvent) -> std::io::Result<bool> {
+        let Some(pager) = &mut self.pager else {
+            return Ok(false);
+        };
+        if let Some((kind, mut query)) = pager.prompt.take() {
+            match key_event.code {
+                KeyCode::Enter => {
+                    if !query.is_empty() {
+                        pager.last_search = Some((query, kind == '?'));
+                    }
+                    self.repeat_search(false);
+                }
+                KeyCode::Esc => {}
+                _ if is_interrupt_key(&key_event) => {}
+                KeyCode::Backspace if query.is_empty() => {}
+                KeyCode::Backspace => {
+                    query.pop();
+                    pager.prompt = Some((kind, query));
+                }
+                KeyCode::Char(ch) => {
+                    query.push(ch);
+                    pager.prompt = Some((kind, query));
+                }
+                _ => pager.prompt = Some((kind, query)),
+            }
+            self.render_state.command_line_dirty = true;
+            return Ok(true);
+        }
+
+        // Any key stops following; Ctrl+C and Esc do nothing else
+        let following = pager.following;
+        self.clear_message();
+        if following {
+            self.stop_following();
+            if is_interrupt_key(&key_event) || key_event.code == KeyCode::Esc {
+                return Ok(true);
+            }
+        }
+
+        let height = self
+            .buffer_manager
+            .current()
+            .map_or(1, |buffer| buffer.height as isize);
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Char('e' | 'j' | 'n') if ctrl => self.pager_scroll(1),
+            KeyCode::Char('y' | 'k' | 'p') if ctrl => self.pager_scroll(-1),
+            KeyCode::Char('f') if ctrl => self.pager_scroll(height),
+            KeyCode::Char('b') if ctrl => self.pager_scroll(-height),
+            KeyCode::Char('d') if ctrl => self.pager_scroll(height / 2),
+            KeyCode::Char('u') if ctrl => self.pager_scroll(-height / 2),
+            _ if ctrl || is_interrupt_key(&key_event) => {}
+            KeyCode::Char('q' | 'Q') => self.running = false,
+            KeyCode::Char('j' | 'e') | KeyCode::Down | KeyCode::Enter => self.pager_scroll(1),
+            KeyCode::Char('k' | 'y') | KeyCode::Up => self.pager_scroll(-1),
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => self.pager_scroll(height),
+            KeyCode::Char('b') | KeyCode::PageUp => self.pager_scroll(-height),
+            KeyCode::Char('d') => self.pager_scroll(height / 2),
+            KeyCode::Char('u') => self.pager_scroll(-height / 2),
+            KeyCode::Char('g' | '<') | KeyCode::Home => {
+                self.jump(|editor| editor.pager_scroll_to(0))
+            }
+            KeyCode::Char('G' | '>') | KeyCode::End => {
+                self.jump(|editor| editor.pager_scroll_to(usize::MAX))
+            }
+            KeyCode::Char('F') => self.start_following(),
+            KeyCode::Char(kind @ ('/' | '?')) => {
+                if let Some(pager) = &mut self.pager {
+                    pager.prompt = Some((kind, String::new()));
+                }
+                self.render_state.command_line_dirty = true;
+            }
+            KeyCode::Char('n') => self.repeat_search(false),
+            KeyCode::Char('N') => self.repeat_search(true),
+            KeyCode::Char('h') | KeyCode::Left => {
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.move_cursor_left();
+                    self.render_state.cursor_dirty = true;
+                }
+            }
+            KeyCode::Char('l') | KeyCode::Right => {
+                if let Some(buffer) = self.buffer_manager.current_mut() {
+                    buffer.move_cursor_right();
+                    self.render_state.cursor_dirty = true;
+                }
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+
+    /// Scroll the view by `lines`, down when positive
+    fn pager_scroll(&mut self, lines: isize) {
+        let Some(top) = self
+            .buffer_manager
+            .current()
+            .map(|buffer| buffer.scroll_line)
+        else {
+            return;
+        };
+        self.jump(|editor| editor.pager_scroll_to(top.saturating_add_signed(lines)));
+    }
+
+    fn pager_scroll_to(&mut self, line: usize) {
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.scroll_to(line);
+        }
+        self.render_state.status_line_dirty = true;
+    }
+
+    /// `n` / `N`: search for the last pattern again, the other way with `reverse`
+    fn repeat_search(&mut self, reverse: bool) {
+        let Some((pattern, backward)) = self
+            .pager
+            .as_ref()
+            .and_then(|pager| pager.last_search.clone())
+        else {
+            self.set_message("No previous search pattern".to_string(), MessageType::Error);
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let backward = backward != reverse;
+        let Some((line, col)) = find_line(&buffer.content, &pattern, buffer.scroll_line, backward)
+        else {
+            self.set_message(
+                format!("Pattern not found: {}", pattern),
+                MessageType::Error,
+            );
+            return;
+        };
+        self.jump(|editor| {
+            editor.pager_scroll_to(line);
+            if let Some(buffer) = editor.buffer_manager.current_mut() {
+                buffer.set_cursor(line, col);
+            }
+        });
+    }
+
+    /// `F`: go to the end and stay there as the file or stream grows. Files
+    /// are followed like `:tail`.
+    fn start_following(&mut self) {
+        let Some(pager) = &mut self.pager else {
+            return;
+        };
+        pager.following = true;
+        self.set_follow(true);
+        self.jump(|editor| editor.pager_scroll_to(usize::MAX));
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Stop `F`; called for any key and on interrupts
+    pub(crate) fn stop_following(&mut self) {
+        let Some(pager) = &mut self.pager else {
+            return;
+        };
+        if !std::mem::take(&mut pager.following) {
+            return;
+        }
+        self.set_follow(false);
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Status line text while paging: the name, the lines in view and how far
+    /// through the input they are
+    pub(crate) fn pager_status(&self, buffer: &TextBuffer) -> Option<String> {
+        let pager = self.pager.as_ref()?;
+        let file_name = buffer.file_path.as_ref().and_then(|path| path.file_name());
+        let name = match (&pager.stream_name, file_name) {
+            (Some(name), _) => name.clone(),
+            (None, Some(file_name)) => file_name.to_string_lossy().to_string(),
+            (None, None) => "[No Name]".to_string(),
+        };
+        let line_count = buffer.content.lines().count();
+        let height = buffer.height as usize;
+        let first = (buffer.scroll_line + 1).min(line_count);
+        let last = (buffer.scroll_line + height).min(line_count);
+        let position = if last == line_count && pager.stream.is_none() {
+            "(END)".to_string()
+        } else {
+            format!("{}%", view_percent(buffer.scroll_line, height, line_count))
+        };
+        Some(format!(
+            "{} lines {}-{}/{} {}",
+            name, first, last, line_count, position
+        ))
+    }
+
+    /// Command line text while paging: the search being typed, or a note
+    /// while following
+    pub(crate) fn pager_prompt(&self) -> Option<String> {
+        let pager = self.pager.as_ref()?;
+        if let Some((kind, query)) = &pager.prompt {
+            return Some(format!("{}{}", kind, query));
+        }
+        pager
+            .following
+            .then(|| "Waiting for data... (interrupt to abort)".to_string())
+    }
+}
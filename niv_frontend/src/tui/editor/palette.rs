@@ -0,0 +1,171 @@
+use super::{Editor, EditorMode};
+use crate::tui::excmd::EX_COMMANDS;
+use crate::tui::interrupt::is_interrupt_key;
+use crate::tui::keys::parse_key_notation;
+use crate::tui::palette::{Palette, PaletteAction, PaletteItem};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use niv_config::EditorCommand;
+
+/// What running an editor command from the palette does, if niv implements it
+fn editor_command_action(command: &EditorCommand) -> Option<PaletteAction> {
+    let keys = match command {
+        EditorCommand::MoveUp => "k",
+        EditorCommand::MoveDown => "j",
+        EditorCommand::MoveLeft => "h",
+        EditorCommand::MoveRight => "l",
+        EditorCommand::MoveLineStart => "0",
+        EditorCommand::MoveLineEnd => "$",
+        EditorCommand::MovePageUp => "<C-u>",
+        EditorCommand::MovePageDown => "<C-d>",
+        EditorCommand::Insert | EditorCommand::InsertMode => "i",
+        EditorCommand::InsertLineAbove => "O",
+        EditorCommand::InsertLineBelow => "o",
+        EditorCommand::Delete => "x",
+        EditorCommand::VisualMode => "v",
+        EditorCommand::CommandMode => ":",
+        EditorCommand::Save => return Some(PaletteAction::Run("write".to_string())),
+        EditorCommand::SaveAs => return Some(PaletteAction::Edit("write ".to_string())),
+        EditorCommand::Open => return Some(PaletteAction::Edit("edit ".to_string())),
+        EditorCommand::Quit => return Some(PaletteAction::Run("quit".to_string())),
+        EditorCommand::ForceQuit => return Some(PaletteAction::Run("quit!".to_string())),
+        EditorCommand::Custom(command) => return Some(PaletteAction::Run(command.clone())),
+        _ => return None,
+    };
+    Some(PaletteAction::Keys(keys))
+}
+
+impl Editor {
+    /// Open the command palette listing ex commands, plugin commands and
+    /// editor commands with their key bindings
+    pub(crate) fn open_palette(&mut self) {
+        let keybindings = self.config_loader.get_copy().keybindings;
+        let mapped_keys = |command: &str| {
+            let mut keys: Vec<String> = self
+                .plugin_keymaps
+                .iter()
+                .filter(|(_, mapped)| mapped.as_str() == command)
+                .map(|(key, _)| key.to_string())
+                .collect();
+            keys.sort();
+            keys.join(", ")
+        };
+
+        let mut items: Vec<PaletteItem> = EX_COMMANDS
+            .iter()
+            .map(|command| {
+                let (label, action) = match command.args {
+                    "" => (
+                        format!(":{}", command.name),
+                        PaletteAction::Run(command.name.to_string()),
+                    ),
+                    args => {
+                        // `:!cmd` has no space after the name
+                        let separator = if command.name == "!" { "" } else { " " };
+                        (
+                            format!(":{} {}", command.name, args),
+                            PaletteAction::Edit(format!("{}{}", command.name, separator)),
+                        )
+                    }
+                };
+                PaletteItem {
+                    label,
+                    keys: mapped_keys(command.name),
+                    description: command.description.to_string(),
+                    action,
+                }
+            })
+            .collect();
+
+        let mut plugin_commands: Vec<&String> = self.plugin_commands.keys().collect();
+        plugin_commands.sort();
+        items.extend(plugin_commands.into_iter().map(|name| PaletteItem {
+            label: format!(":{}", name),
+            keys: mapped_keys(name),
+            description: "Plugin command".to_string(),
+            action: PaletteAction::Run(name.clone()),
+        }));
+
+        // Custom commands exist only as key bindings
+        let mut custom: Vec<&EditorCommand> = keybindings
+            .normal
+            .values()
+            .chain(keybindings.global.values())
+            .filter(|command| matches!(command, EditorCommand::Custom(_)))
+            .collect();
+        custom.sort_by_key(|command| command.name());
+        custom.dedup();
+        let editor_commands = EditorCommand::ALL.iter().chain(custom);
+        items.extend(editor_commands.filter_map(|command| {
+            let keys: Vec<String> = keybindings
+                .normal_bindings_for(command)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            Some(PaletteItem {
+                label: command.name().to_string(),
+                keys: keys.join(", "),
+                description: command.description().to_string(),
+                action: editor_command_action(command)?,
+            })
+        }));
+
+        self.palette = Some(Palette::new(items));
+        self.render_state.mark_all_dirty();
+    }
+
+    pub(crate) fn close_palette(&mut self) {
+        if self.palette.take().is_some() {
+            self.render_state.mark_all_dirty();
+        }
+    }
+
+    /// Handle a key while the palette is open; false if it isn't
+    pub(crate) fn handle_palette_key(&mut self, key_event: KeyEvent) -> std::io::Result<bool> {
+        let Some(palette) = &mut self.palette else {
+            return Ok(false);
+        };
+        let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            KeyCode::Esc => self.close_palette(),
+            _ if is_interrupt_key(&key_event) => self.close_palette(),
+            KeyCode::Enter => {
+                let action = palette.selected().map(|item| item.action.clone());
+                self.close_palette();
+                if let Some(action) = action {
+                    self.run_palette_action(action)?;
+                }
+            }
+            KeyCode::Up | KeyCode::BackTab => palette.select_prev(),
+            KeyCode::Down | KeyCode::Tab => palette.select_next(),
+            KeyCode::Char('p') if ctrl => palette.select_prev(),
+            KeyCode::Char('n') if ctrl => palette.select_next(),
+            KeyCode::Backspace if palette.query().is_empty() => self.close_palette(),
+            KeyCode::Backspace => palette.pop_char(),
+            KeyCode::Char(ch) if !ctrl && !key_event.modifiers.contains(KeyModifiers::ALT) => {
+                palette.push_char(ch)
+            }
+            _ => {}
+        }
+        self.render_state.mark_all_dirty();
+        Ok(true)
+    }
+
+    fn run_palette_action(&mut self, action: PaletteAction) -> std::io::Result<()> {
+        match action {
+            PaletteAction::Run(command) => self.run_command_line(&command)?,
+            PaletteAction::Edit(text) => {
+                self.mode = EditorMode::Command;
+                self.command_line = text;
+                self.render_state.command_line_dirty = true;
+                self.render_state.status_line_dirty = true;
+            }
+            PaletteAction::Keys(keys) => {
+                for key in parse_key_notation(keys) {
+                    self.handle_key_event(key)?;
+                }
+            }
+            PaletteAction::Paste(index) => self.paste_yank(index, true),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use super::Editor;
+#[cfg(any(feature = "wasm", feature = "lua"))]
+use {super::MessageType, crate::tui::plugin::PluginRequest};
+
+/// A plugin function bound to a command, key or event
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PluginCall {
+    /// Exported function of a loaded WebAssembly plugin
+    #[cfg(feature = "wasm")]
+    Wasm { plugin: usize, export: String },
+    /// Function registered by `init.lua`
+    #[cfg(feature = "lua")]
+    Lua { callback: usize },
+}
+
+impl Editor {
+    /// Run a plugin function, passing `args` as its command arguments
+    #[cfg_attr(not(any(feature = "wasm", feature = "lua")), allow(unused_variables))]
+    pub(crate) fn call_plugin(&mut self, call: &PluginCall, args: &str) {
+        match *call {
+            #[cfg(feature = "wasm")]
+            PluginCall::Wasm { plugin, ref export } => self.call_wasm(plugin, export, args),
+            #[cfg(feature = "lua")]
+            PluginCall::Lua { callback } => self.call_lua(callback, args),
+        }
+    }
+
+    /// Run `:name args` if a plugin registered the command
+    pub(crate) fn run_plugin_command(&mut self, command: &str) -> bool {
+        let (name, args) = command.split_once(' ').unwrap_or((command, ""));
+        let Some(call) = self.plugin_commands.get(name).cloned() else {
+            return false;
+        };
+        self.call_plugin(&call, args.trim());
+        true
+    }
+
+    /// Command mapped to a normal mode key by a plugin
+    pub(crate) fn plugin_keymap(&self, key: char) -> Option<String> {
+        self.plugin_keymaps.get(&key).cloned()
+    }
+
+    /// Apply what a plugin asked for during a call. `bind` turns one of the
+    /// plugin's function names into a call that can be stored.
+    #[cfg(any(feature = "wasm", feature = "lua"))]
+    pub(crate) fn apply_plugin_requests(
+        &mut self,
+        plugin: &str,
+        requests: Vec<PluginRequest>,
+        bind: impl Fn(&str) -> PluginCall,
+    ) {
+        let mut edited = false;
+        for request in requests {
+            match request {
+                PluginRequest::Edit { start, end, text } => {
+                    if let Some(buffer) = self.buffer_manager.current_mut() {
+                        buffer.replace_range(start, end, &text);
+                        edited = true;
+                    }
+                }
+                PluginRequest::RegisterCommand { name, export } => {
+                    // Same rule as vim: user commands start with an uppercase letter
+                    if !name.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        self.set_message(
+                            format!(
+                                "{}: command name must start with uppercase: {}",
+                                plugin, name
+                            ),
+                            MessageType::Error,
+                        );
+                        continue;
+                    }
+                    self.plugin_commands.insert(name, bind(&export));
+                }
+                PluginRequest::MapKey { keys, command } => {
+                    let mut chars = keys.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(key), None) => {
+                            self.plugin_keymaps.insert(key, command);
+                        }
+                        _ => self.set_message(
+                            format!("{}: only single keys can be mapped: {}", plugin, keys),
+                            MessageType::Error,
+                        ),
+                    }
+                }
+                PluginRequest::Subscribe { event, export } => match event.as_str() {
+                    "CursorHold" => {
+                        let call = bind(&export);
+                        self.on_cursor_hold(move |editor| editor.call_plugin(&call, ""));
+                    }
+                    _ => self.set_message(
+                        format!("{}: unknown event: {}", plugin, event),
+                        MessageType::Error,
+                    ),
+                },
+                PluginRequest::SetOption(option) => {
+                    self.set_local_options(&[option]);
+                }
+                PluginRequest::Command(command) => {
+                    if let Err(e) = self.run_command_line(&command) {
+                        self.set_message(format!("{}: {}", plugin, e), MessageType::Error);
+                    }
+                }
+                PluginRequest::Message(message) => {
+                    self.set_message(message, MessageType::Info);
+                }
+                PluginRequest::Denied(capability) => {
+                    self.set_message(
+                        format!("{}: not permitted: {}", plugin, capability.name()),
+                        MessageType::Warning,
+                    );
+                }
+            }
+        }
+        if edited {
+            self.render_state.mark_text_dirty();
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use super::Editor;
+use crate::tui::markdown::MarkdownPreview;
+
+impl Editor {
+    /// `:MarkdownPreview`: toggle a read-only split right of the text area
+    /// showing the current buffer rendered as Markdown. The preview scrolls
+    /// with the buffer, and only the blocks an edit touched are rendered again.
+    pub(super) fn toggle_markdown_preview(&mut self) {
+        let show = self.markdown_preview.is_none();
+        // The split shows one of the preview, `:DiffOrig` and the window
+        self.diff_view = None;
+        self.split_window = None;
+        self.layout_manager.set_preview(show);
+        let layout = self.layout_manager.get_layout();
+        self.markdown_preview = show.then(|| MarkdownPreview::new(layout.preview_width as usize));
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.render_state.mark_all_dirty();
+    }
+
+    /// Bring the preview up to date with the current buffer and pane width
+    pub(super) fn update_markdown_preview(&mut self) {
+        let width = self.layout_manager.get_layout().preview_width as usize;
+        let (Some(preview), Some(buffer)) =
+            (&mut self.markdown_preview, self.buffer_manager.current())
+        else {
+            return;
+        };
+        preview.set_width(width);
+        preview.update(&buffer.content);
+    }
+}
@@ -0,0 +1,79 @@
+use super::{Editor, MessageType};
+use crate::tui::buffer::TextBuffer;
+use crate::tui::profile::{FramePhase, Profiler};
+use std::time::Instant;
+
+impl Editor {
+    /// Start recording frame and command timings, discarding earlier ones
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// Report of the timings recorded so far, if profiling is on
+    pub fn profile_report(&self) -> Option<String> {
+        self.profiler.as_ref().map(Profiler::report)
+    }
+
+    /// `:profile start|stop|report`
+    pub(crate) fn profile_command(&mut self, args: &str) {
+        match args {
+            "start" => {
+                self.start_profiling();
+                self.set_message("Profiling started".to_string(), MessageType::Info);
+            }
+            "stop" | "report" => {
+                let Some(report) = self.profile_report() else {
+                    self.set_message("Not profiling".to_string(), MessageType::Warning);
+                    return;
+                };
+                if args == "stop" {
+                    self.profiler = None;
+                }
+                self.show_report(&report);
+            }
+            _ => self.set_message(
+                "Usage: :profile start|stop|report".to_string(),
+                MessageType::Error,
+            ),
+        }
+    }
+
+    /// Add the time since `started` to a phase of the current frame
+    pub(crate) fn profile_phase(&mut self, phase: FramePhase, started: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(phase, started.elapsed());
+        }
+    }
+
+    /// Add the time since `started` to the current frame as input handling
+    pub(crate) fn profile_input(&mut self, started: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_input(started.elapsed());
+        }
+    }
+
+    pub(crate) fn profile_end_frame(&mut self) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+        }
+    }
+
+    pub(crate) fn profile_command_time(&mut self, command: &str, started: Instant) {
+        if let Some(profiler) = &mut self.profiler {
+            let name = command.split_whitespace().next().unwrap_or(command);
+            profiler.record_command(name, started.elapsed());
+        }
+    }
+
+    /// Show a report in a new scratch buffer
+    pub(super) fn show_report(&mut self, report: &str) {
+        let mut buffer = TextBuffer::new();
+        buffer.replace_range(0, 0, report);
+        buffer.mark_saved();
+        buffer.no_swap = true;
+        let layout = self.layout_manager.get_layout();
+        buffer.set_size(layout.text_area_width, layout.text_area_height);
+        self.buffer_manager.add_buffer(buffer);
+        self.render_state.mark_all_dirty();
+    }
+}
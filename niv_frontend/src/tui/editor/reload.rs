@@ -0,0 +1,240 @@
+use super::{Editor, MessageType};
+use crate::tui::interrupt::reload_incremental_interruptible;
+use crossterm::event::{KeyCode, KeyEvent};
+use niv_fs::{ChangeType, FileIdentity, FileIdentityConfig, FileReloadResult, FsError};
+use std::path::{Path, PathBuf};
+
+impl Editor {
+    /// Start tracking the current buffer's file for external changes
+    pub(crate) fn watch_current_file(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            return;
+        };
+        let content = buffer.content.clone();
+        self.watch_file(&path, &content);
+    }
+
+    pub(super) fn watch_file(&mut self, path: &Path, content: &str) {
+        // New files have no identity until their first write
+        if let Ok(identity) = FileIdentity::from_path(path, &FileIdentityConfig::default()) {
+            let _ = self.file_watcher.watch_file(path, content, identity);
+        }
+    }
+
+    /// Run a save of `path` with external change detection paused, so the
+    /// watcher does not report the editor's own write
+    pub(crate) fn save_suspended<T>(&self, path: Option<&Path>, save: impl FnOnce() -> T) -> T {
+        let Some(path) = path else {
+            return save();
+        };
+        self.file_watcher.suspend(path);
+        let result = save();
+        let _ = self.file_watcher.resume(path);
+        result
+    }
+
+    /// `:e` / `:e!`: reload the current buffer from disk.
+    ///
+    /// Without `force`, a modified buffer is left alone.
+    pub(crate) fn reload_current_buffer(&mut self, force: bool) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        if buffer.modified && !force {
+            self.set_message(
+                "No write since last change (add ! to override)".to_string(),
+                MessageType::Error,
+            );
+            return;
+        }
+
+        self.reload_buffer(&path);
+    }
+
+    /// Reload the buffer editing `path` and report the result
+    fn reload_buffer(&mut self, path: &Path) {
+        let Some(buffer) = self.buffer_manager.find_by_path_mut(path) else {
+            return;
+        };
+        match buffer.reload() {
+            Ok(()) => {
+                let content = buffer.content.clone();
+                self.watch_file(path, &content);
+                self.render_state.mark_all_dirty();
+                self.set_message(
+                    format!("\"{}\" reloaded", path.display()),
+                    MessageType::Info,
+                );
+            }
+            Err(e) => {
+                self.set_message(format!("Reload failed: {}", e), MessageType::Error);
+            }
+        }
+    }
+
+    /// Bring the buffer editing `path` up to date with the file, reading only
+    /// the lines appended since it was loaded when the file just grew. Returns
+    /// whether the buffer changed.
+    pub(super) fn reload_buffer_incremental(&mut self, path: &Path) -> Result<bool, FsError> {
+        let Some(buffer) = self.buffer_manager.find_by_path_mut(path) else {
+            return Ok(false);
+        };
+        let result = reload_incremental_interruptible(
+            path,
+            &buffer.save_context.original_identity,
+            buffer.loaded_len,
+            buffer.save_context.original_encoding,
+        )?;
+        match result {
+            FileReloadResult::Unchanged => {
+                // Wait for the rest of a line being written
+                let _ = self.file_watcher.resume(path);
+                Ok(false)
+            }
+            FileReloadResult::Appended(appended) => {
+                buffer.append(&appended.content);
+                buffer.loaded_len = appended.end_offset;
+                let _ = self
+                    .file_watcher
+                    .record_append(path, &appended.content, appended.identity);
+                Ok(true)
+            }
+            FileReloadResult::Replaced(load_result) => {
+                buffer.replace_content(load_result);
+                let content = buffer.content.clone();
+                self.watch_file(path, &content);
+                Ok(true)
+            }
+        }
+    }
+
+    /// `autoread`: take in a change to the file of an unmodified buffer
+    fn autoread_buffer(&mut self, path: &Path) {
+        match self.reload_buffer_incremental(path) {
+            Ok(true) => {
+                self.render_state.mark_all_dirty();
+                self.set_message(
+                    format!("\"{}\" reloaded", path.display()),
+                    MessageType::Info,
+                );
+            }
+            Ok(false) => {}
+            Err(e) => {
+                self.set_message(format!("Reload failed: {}", e), MessageType::Error);
+            }
+        }
+    }
+
+    /// `:checktime`: check all buffers for changes made outside the editor
+    pub(crate) fn check_time(&mut self) {
+        if self.check_file_changes() == 0 {
+            self.set_message("No files changed on disk".to_string(), MessageType::Info);
+        }
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Check all buffers for changes made outside the editor, returning how
+    /// many files changed.
+    ///
+    /// Unmodified buffers are reloaded when `autoread` is set; modified buffers
+    /// queue a prompt asking whether to load the file or keep the buffer.
+    pub(crate) fn check_file_changes(&mut self) -> usize {
+        let autoread = self.config_loader.get_copy().editor.autoread;
+        let buffers: Vec<(PathBuf, bool)> = self
+            .buffer_manager
+            .iter()
+            .filter_map(|buffer| buffer.file_path.clone().map(|path| (path, buffer.modified)))
+            .collect();
+
+        let mut changed = 0;
+        for (path, modified) in buffers {
+            let change = match self.file_watcher.check_external_changes(&path) {
+                Ok(Some(change)) => change,
+                Ok(None) => continue,
+                Err(e) => {
+                    self.set_message(format!("Watcher error: {}", e), MessageType::Error);
+                    continue;
+                }
+            };
+            changed += 1;
+
+            match change.change_type {
+                ChangeType::Deleted => {
+                    self.set_message(
+                        format!("File \"{}\" no longer available", path.display()),
+                        MessageType::Warning,
+                    );
+                }
+                _ if modified => {
+                    if !self.file_change_prompts.contains(&path) {
+                        self.file_change_prompts.push_back(path);
+                    }
+                }
+                _ if autoread => self.autoread_buffer(&path),
+                _ => {
+                    self.set_message(
+                        format!(
+                            "File \"{}\" has changed since editing started",
+                            path.display()
+                        ),
+                        MessageType::Warning,
+                    );
+                    // Only warn once per external change
+                    if let Some(buffer) = self.buffer_manager.find_by_path_mut(&path) {
+                        let content = buffer.content.clone();
+                        self.watch_file(&path, &content);
+                    }
+                }
+            }
+        }
+        changed
+    }
+
+    /// Text of the file-changed prompt currently waiting for an answer
+    pub(crate) fn file_change_prompt(&self) -> Option<String> {
+        self.file_change_prompts.front().map(|path| {
+            format!(
+                "\"{}\" changed on disk and the buffer was modified: [L]oad file, [K]eep buffer",
+                path.display()
+            )
+        })
+    }
+
+    /// Answer the front file-changed prompt. Returns true when the key was consumed.
+    pub(crate) fn handle_file_change_prompt(&mut self, key_event: KeyEvent) -> bool {
+        let Some(path) = self.file_change_prompts.front().cloned() else {
+            return false;
+        };
+
+        match key_event.code {
+            KeyCode::Char('l') | KeyCode::Char('L') => {
+                self.file_change_prompts.pop_front();
+                self.reload_buffer(&path);
+            }
+            KeyCode::Char('k') | KeyCode::Char('K') | KeyCode::Esc => {
+                self.file_change_prompts.pop_front();
+                // Accept the disk version as the new base so we do not ask again
+                if let Some(buffer) = self.buffer_manager.find_by_path_mut(&path) {
+                    let content = buffer.content.clone();
+                    self.watch_file(&path, &content);
+                }
+                self.set_message(
+                    format!("Kept buffer for \"{}\"", path.display()),
+                    MessageType::Info,
+                );
+            }
+            // Any other key keeps the prompt up
+            _ => {}
+        }
+        self.render_state.command_line_dirty = true;
+        true
+    }
+}
@@ -0,0 +1,110 @@
+use super::{Editor, MessageType};
+use crate::tui::vcs::find_vcs;
+use std::path::Path;
+
+impl Editor {
+    /// `:Rename[!] {name}` / `:Move[!] {path}`: rename the current file on disk.
+    ///
+    /// `:Rename` resolves relative names against the file's own directory,
+    /// `:Move` against the working directory. The buffer path, swap file and
+    /// watcher registration follow the file.
+    pub(crate) fn rename_current_file(
+        &mut self,
+        target: &str,
+        force: bool,
+        relative_to_file: bool,
+    ) {
+        let Some(old_path) = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.file_path.clone())
+        else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        if target.is_empty() {
+            self.set_message("Argument required".to_string(), MessageType::Error);
+            return;
+        }
+
+        let target = match old_path.parent() {
+            Some(dir)
+                if relative_to_file
+                    && Path::new(target).is_relative()
+                    && !target.starts_with('~') =>
+            {
+                dir.join(target)
+            }
+            _ => self.resolve_path(target),
+        };
+        let new_path = niv_fs::resolve_move_target(&old_path, &target);
+        if new_path == old_path {
+            return;
+        }
+
+        // A buffer that was never written only needs its name changed
+        if old_path.exists() {
+            self.file_watcher.suspend(&old_path);
+            let result = niv_fs::rename_file(&old_path, &new_path, force);
+            let _ = self.file_watcher.resume(&old_path);
+            match result {
+                Ok(identity) => {
+                    if let Some(buffer) = self.buffer_manager.current_mut() {
+                        buffer.save_context.original_identity = identity;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    self.set_message(
+                        "File exists (add ! to override)".to_string(),
+                        MessageType::Error,
+                    );
+                    return;
+                }
+                Err(e) => {
+                    self.set_message(format!("Rename failed: {}", e), MessageType::Error);
+                    return;
+                }
+            }
+        }
+
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.file_path = Some(new_path.clone());
+        }
+        self.follow_renamed_file(&old_path, &new_path);
+
+        let mut message = format!("Renamed to \"{}\"", new_path.display());
+        let vcs = match self.config_loader.get_copy().editor.git_stage_renames {
+            true => find_vcs(&new_path),
+            false => None,
+        };
+        let recorded = vcs.map(|vcs| (vcs.name(), vcs.record_rename(&old_path, &new_path)));
+        if let Some((name, Err(e))) = recorded {
+            message = format!("{} ({}: {})", message, name, e);
+        }
+        self.set_message(message, MessageType::Success);
+        self.render_state.status_line_dirty = true;
+    }
+
+    /// Move swap and watcher state from the old path of a renamed file to the new one
+    fn follow_renamed_file(&mut self, old_path: &Path, new_path: &Path) {
+        let result = self
+            .swap_manager
+            .as_mut()
+            .map_or(Ok(()), |swap| swap.rename_file(old_path, new_path));
+        if let Err(e) = result {
+            self.set_message(format!("Swap error: {}", e), MessageType::Warning);
+        }
+        if let Some(hash) = self.last_swap_hash.remove(old_path) {
+            self.last_swap_hash.insert(new_path.to_path_buf(), hash);
+        }
+
+        let followed = self
+            .file_watcher
+            .follow_rename(old_path, new_path)
+            .unwrap_or(false);
+        if !followed {
+            // Cross-device moves create a new file; start watching it afresh
+            self.watch_current_file();
+        }
+    }
+}
@@ -1,8 +1,20 @@
 use super::{Editor, EditorMode};
 use crate::tui::buffer::*;
+use crate::tui::diff::DiffRowKind;
+use crate::tui::display::{
+    DisplayKind, DisplayOptions, DisplaySegment, char_width, display_width, render_line,
+};
+use crate::tui::layout::line_number_width;
+use crate::tui::markdown::MarkdownStyle;
+use crate::tui::palette::{Palette, PaletteItem};
+use crate::tui::profile::FramePhase;
+use crate::tui::screen::{clip_start_to_width, clip_to_width, fit_to_width};
+use crate::tui::swatch::is_light;
+use crate::tui::winbar::{join_crumbs, path_crumbs};
 use crossterm::{execute, style::Stylize};
 use niv_config::EditorSettings;
-use std::io::{self, Write};
+use std::io::Write;
+use std::time::Instant;
 
 /// Rendering state to track what needs to be redrawn
 #[derive(Debug, Clone)]
@@ -22,6 +34,10 @@ pub struct RenderState {
     pub last_content_hash: u64,
     pub last_cursor_line: usize,
     pub last_cursor_col: usize,
+    /// Cursor position and content hash the bracket match was found for
+    pub bracket_key: Option<(usize, usize, u64)>,
+    /// Overlong line count shown in the status line
+    pub last_overlength: usize,
 }
 
 impl Default for RenderState {
@@ -42,6 +58,8 @@ impl Default for RenderState {
             last_content_hash: 0,
             last_cursor_line: 0,
             last_cursor_col: 0,
+            bracket_key: None,
+            last_overlength: 0,
         }
     }
 }
@@ -75,6 +93,10 @@ impl RenderState {
     }
 
     pub fn mark_text_lines_dirty(&mut self, lines: std::collections::HashSet<usize>) {
+        // Already redrawing every line
+        if self.text_area_dirty && self.dirty_text_lines.is_none() {
+            return;
+        }
         self.text_area_dirty = true;
         if let Some(ref mut dirty_lines) = self.dirty_text_lines {
             dirty_lines.extend(lines);
@@ -125,13 +147,19 @@ impl Editor {
     }
 
     pub(crate) fn update_render_state(&mut self) {
+        let settings = self.config_loader.get_copy().editor;
         if let Some(buffer) = self.buffer_manager.current() {
             let current_hash = self.simple_hash(&buffer.content);
             if current_hash != self.render_state.last_content_hash {
                 self.render_state.mark_text_dirty();
                 self.render_state.last_content_hash = current_hash;
             }
+            if buffer.overlength.get() != self.render_state.last_overlength {
+                self.render_state.status_line_dirty = true;
+                self.render_state.last_overlength = buffer.overlength.get();
+            }
         }
+        self.update_line_number_width(&settings);
 
         if let Some(buffer) = self.buffer_manager.current() {
             let layout = self.layout_manager.get_layout();
@@ -147,6 +175,11 @@ impl Editor {
             if buffer.cursor_line != self.render_state.last_cursor_line {
                 self.render_state.cursor_dirty = true;
                 self.render_state.last_cursor_line = buffer.cursor_line;
+                // Relative numbers count from the cursor line
+                if settings.relative_numbers {
+                    self.render_state.line_numbers_dirty = true;
+                    self.render_state.dirty_line_numbers = None;
+                }
             }
             if buffer.cursor_col != self.render_state.last_cursor_col
                 && buffer.cursor_line == self.render_state.last_cursor_line
@@ -165,6 +198,32 @@ impl Editor {
                 self.render_state.last_scroll_col = buffer.scroll_col;
             }
         }
+
+        self.update_split_window();
+        self.update_selection();
+        self.update_bracket_match();
+    }
+
+    /// Fit the line number column to the largest line number of the current
+    /// buffer, laying the text area out again when its width changes
+    fn update_line_number_width(&mut self, settings: &EditorSettings) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let width = line_number_width(
+            buffer.content.lines().count().max(1),
+            settings.line_numbers,
+            settings.relative_numbers,
+        );
+        if width == self.layout_manager.get_layout().line_number_width {
+            return;
+        }
+        self.layout_manager.set_line_number_width(width);
+        let layout = self.layout_manager.get_layout();
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.render_state.mark_all_dirty();
     }
 
     pub(crate) fn needs_redraw(&self) -> bool {
@@ -177,26 +236,64 @@ impl Editor {
     }
 
     pub(crate) fn draw(&mut self) -> std::io::Result<()> {
+        // During a smooth scroll, draw the animation frame's scroll position
+        let animated = self.animated_scroll_line();
+        let scroll_line = self.buffer_manager.current_mut().and_then(|buffer| {
+            let line = animated?;
+            Some(std::mem::replace(&mut buffer.scroll_line, line))
+        });
+        let started = Instant::now();
+        let result = self.draw_frame();
+        if let (Some(line), Some(buffer)) = (scroll_line, self.buffer_manager.current_mut()) {
+            buffer.scroll_line = line;
+        }
+        niv_fs::log!(Trace, "render", "frame drawn in {:?}", started.elapsed());
+        result
+    }
+
+    fn draw_frame(&mut self) -> std::io::Result<()> {
+        let started = Instant::now();
+        if self.render_state.full_redraw || self.render_state.text_area_dirty {
+            self.update_markdown_preview();
+            self.update_diff_view();
+            self.update_vcs_file();
+        }
         let layout = self.layout_manager.get_layout();
         let config = self.config_loader.get_copy();
 
-        if self.render_state.full_redraw {
+        if layout.is_too_small() {
+            self.draw_too_small()?;
+        } else if self.render_state.full_redraw || self.palette.is_some() {
+            // Only the editor's own rows; inline, the rows above are the shell's
             execute!(
-                io::stdout(),
-                crossterm::terminal::Clear(crossterm::terminal::ClearType::All)
+                &self.output,
+                crossterm::cursor::MoveTo(0, layout.top),
+                crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown)
             )?;
             if let Some(buffer) = self.buffer_manager.current() {
                 self.draw_line_numbers(buffer, &config.editor)?;
                 self.draw_text_area(buffer)?;
+                self.draw_preedit(buffer)?;
+                self.draw_markdown_preview(buffer)?;
+                self.draw_diff_view(buffer)?;
+                self.draw_split_window(buffer)?;
             }
+            self.draw_winbar()?;
             self.draw_status_line(&config.editor)?;
             self.draw_command_line()?;
             self.position_cursor()?;
+            if let Some(palette) = &self.palette {
+                self.draw_palette(palette)?;
+            }
         } else {
             if self.render_state.text_area_dirty {
                 self.clear_text_area()?;
                 if let Some(buffer) = self.buffer_manager.current() {
                     self.draw_text_area(buffer)?;
+                    self.draw_preedit(buffer)?;
+                    self.draw_markdown_preview(buffer)?;
+                    self.draw_diff_view(buffer)?;
+                    self.draw_split_window(buffer)?;
                 }
             }
             if self.render_state.line_numbers_dirty {
@@ -205,6 +302,9 @@ impl Editor {
                     self.draw_line_numbers(buffer, &config.editor)?;
                 }
             }
+            if self.render_state.status_line_dirty || self.render_state.text_area_dirty {
+                self.draw_winbar()?;
+            }
             if self.render_state.status_line_dirty {
                 self.clear_status_line()?;
                 self.draw_status_line(&config.editor)?;
@@ -217,8 +317,38 @@ impl Editor {
                 self.position_cursor()?;
             }
         }
+        self.profile_phase(FramePhase::Draw, started);
+
+        let started = Instant::now();
+        (&self.output).flush()?;
+        self.profile_phase(FramePhase::Flush, started);
+        Ok(())
+    }
 
-        io::stdout().flush()?;
+    /// Placeholder drawn instead of the editor while the terminal is too small
+    fn draw_too_small(&self) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(0, layout.top),
+            crossterm::terminal::Clear(crossterm::terminal::ClearType::FromCursorDown)
+        )?;
+        if layout.width == 0 || layout.height == 0 {
+            return Ok(());
+        }
+        let text: String = "window too small"
+            .chars()
+            .take(layout.width as usize)
+            .collect();
+        let x = (layout.width as usize - text.chars().count()) / 2;
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(x as u16, layout.top + layout.height / 2),
+            crossterm::style::SetForegroundColor(self.theme.warning()),
+            crossterm::style::Print(text),
+            crossterm::style::ResetColor,
+            crossterm::cursor::MoveTo(0, layout.top)
+        )?;
         Ok(())
     }
 
@@ -226,10 +356,19 @@ impl Editor {
         let layout = self.layout_manager.get_layout();
         let height = layout.text_area_height;
         for y in 0..height {
+            // Only the lines about to be drawn again
+            let clean = self
+                .render_state
+                .dirty_text_lines
+                .as_ref()
+                .is_some_and(|dirty_lines| !dirty_lines.contains(&(y as usize)));
+            if clean {
+                continue;
+            }
             let screen_x = layout.line_number_width;
-            let screen_y = y;
+            let screen_y = layout.text_start_row + y;
             execute!(
-                io::stdout(),
+                &self.output,
                 crossterm::cursor::MoveTo(screen_x, screen_y),
                 crossterm::style::Print(" ".repeat(layout.text_area_width as usize))
             )?;
@@ -242,9 +381,18 @@ impl Editor {
         let height = layout.text_area_height;
         let width = layout.line_number_width;
         for y in 0..height {
+            // Only the lines about to be drawn again
+            let clean = self
+                .render_state
+                .dirty_line_numbers
+                .as_ref()
+                .is_some_and(|dirty_nums| !dirty_nums.contains(&(y as usize)));
+            if clean {
+                continue;
+            }
             execute!(
-                io::stdout(),
-                crossterm::cursor::MoveTo(0, y),
+                &self.output,
+                crossterm::cursor::MoveTo(0, layout.text_start_row + y),
                 crossterm::style::Print(" ".repeat(width as usize))
             )?;
         }
@@ -256,7 +404,7 @@ impl Editor {
         let width = layout.width;
         let y = layout.status_line_row;
         execute!(
-            io::stdout(),
+            &self.output,
             crossterm::cursor::MoveTo(0, y),
             crossterm::style::Print(" ".repeat(width as usize))
         )?;
@@ -266,9 +414,9 @@ impl Editor {
     fn clear_command_line(&self) -> std::io::Result<()> {
         let layout = self.layout_manager.get_layout();
         let width = layout.width;
-        let y = layout.height - 1;
+        let y = layout.command_line_row();
         execute!(
-            io::stdout(),
+            &self.output,
             crossterm::cursor::MoveTo(0, y),
             crossterm::style::Print(" ".repeat(width as usize))
         )?;
@@ -278,68 +426,346 @@ impl Editor {
     fn draw_line_numbers(
         &self,
         buffer: &TextBuffer,
-        _config: &EditorSettings,
+        config: &EditorSettings,
     ) -> std::io::Result<()> {
-        let line_numbers = buffer.line_numbers();
+        let layout = self.layout_manager.get_layout();
+        let row = layout.text_start_row;
+        let line_numbers = buffer.line_numbers(
+            layout.line_number_width,
+            config.line_numbers,
+            config.relative_numbers,
+        );
         if let Some(ref dirty_nums) = self.render_state.dirty_line_numbers {
             for &line_idx in dirty_nums {
                 if line_idx < line_numbers.len() {
                     let line_num = &line_numbers[line_idx];
                     execute!(
-                        io::stdout(),
-                        crossterm::cursor::MoveTo(0, line_idx as u16),
+                        &self.output,
+                        crossterm::cursor::MoveTo(0, row + line_idx as u16),
                         crossterm::style::Print(line_num.clone().with(self.theme.line_number()))
                     )?;
+                    self.draw_vcs_sign(buffer, line_idx)?;
                 }
             }
         } else {
             for (i, line_num) in line_numbers.iter().enumerate() {
                 execute!(
-                    io::stdout(),
-                    crossterm::cursor::MoveTo(0, i as u16),
+                    &self.output,
+                    crossterm::cursor::MoveTo(0, row + i as u16),
                     crossterm::style::Print(line_num.clone().with(self.theme.line_number()))
                 )?;
+                self.draw_vcs_sign(buffer, i)?;
             }
         }
         Ok(())
     }
 
+    /// Version control sign of screen line `line_idx`, in the column between
+    /// the line number and the text
+    fn draw_vcs_sign(&self, buffer: &TextBuffer, line_idx: usize) -> std::io::Result<()> {
+        let line = match line_idx {
+            0 if buffer.header_pinned() => 0,
+            _ => buffer.scroll_line + line_idx,
+        };
+        let Some((sign, kind)) = self.vcs_sign(line) else {
+            return Ok(());
+        };
+        let color = match kind {
+            DiffRowKind::Added => self.theme.info(),
+            DiffRowKind::Removed => self.theme.error(),
+            _ => self.theme.warning(),
+        };
+        let layout = self.layout_manager.get_layout();
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(
+                layout.line_number_width.saturating_sub(1),
+                layout.text_start_row + line_idx as u16
+            ),
+            crossterm::style::Print(sign.with(color))
+        )?;
+        Ok(())
+    }
+
     fn draw_text_area(&self, buffer: &TextBuffer) -> std::io::Result<()> {
         let layout = self.layout_manager.get_layout();
-        let lines = buffer.visible_lines();
+        let lines = buffer.visible_segments();
         if let Some(ref dirty_lines) = self.render_state.dirty_text_lines {
             for &line_idx in dirty_lines {
-                if line_idx < lines.len() {
-                    let line = &lines[line_idx];
+                if line_idx < lines.len() && line_idx < layout.text_area_height as usize {
                     let (screen_x, screen_y) = layout.buffer_to_screen(0, line_idx as u16);
-                    if screen_y < layout.text_area_height {
-                        execute!(
-                            io::stdout(),
-                            crossterm::cursor::MoveTo(screen_x, screen_y),
-                            crossterm::style::Print(line.clone().with(self.theme.fg()))
-                        )?;
-                    }
+                    self.draw_segments(screen_x, screen_y, &lines[line_idx])?;
                 }
             }
         } else {
-            for (i, line) in lines.iter().enumerate() {
+            for (i, segments) in lines.iter().enumerate() {
                 let (screen_x, screen_y) = layout.buffer_to_screen(0, i as u16);
-                if screen_y < layout.text_area_height {
-                    execute!(
-                        io::stdout(),
-                        crossterm::cursor::MoveTo(screen_x, screen_y),
-                        crossterm::style::Print(line.clone().with(self.theme.fg()))
-                    )?;
+                if i < layout.text_area_height as usize {
+                    self.draw_segments(screen_x, screen_y, segments)?;
                 }
             }
         }
         Ok(())
     }
 
+    /// Draw one screen line, styling control characters and invalid bytes distinctly
+    fn draw_segments(
+        &self,
+        screen_x: u16,
+        screen_y: u16,
+        segments: &[DisplaySegment],
+    ) -> std::io::Result<()> {
+        execute!(&self.output, crossterm::cursor::MoveTo(screen_x, screen_y))?;
+        for segment in segments {
+            let styled = match segment.kind {
+                DisplayKind::Text => segment
+                    .text
+                    .clone()
+                    .with(self.theme.text_fg(self.terminal_focused)),
+                DisplayKind::Control => segment.text.clone().with(self.theme.special_key()),
+                DisplayKind::Invalid => segment.text.clone().with(self.theme.error()),
+                DisplayKind::Invisible => segment.text.clone().with(self.theme.non_text()),
+                DisplayKind::Overlength => segment
+                    .text
+                    .clone()
+                    .with(self.theme.warning())
+                    .underlined(),
+                DisplayKind::MatchParen | DisplayKind::Selection => segment
+                    .text
+                    .clone()
+                    .with(self.theme.selection_fg())
+                    .on(self.theme.selection_bg()),
+                DisplayKind::Swatch([r, g, b]) => {
+                    let fg = if is_light([r, g, b]) {
+                        crossterm::style::Color::Black
+                    } else {
+                        crossterm::style::Color::White
+                    };
+                    segment
+                        .text
+                        .clone()
+                        .with(fg)
+                        .on(crossterm::style::Color::Rgb { r, g, b })
+                }
+            };
+            execute!(&self.output, crossterm::style::Print(styled))?;
+        }
+        Ok(())
+    }
+
+    /// Draw the text an input method is composing over the text at the
+    /// cursor, underlined and cut off at the edge of the text area
+    fn draw_preedit(&self, buffer: &TextBuffer) -> std::io::Result<()> {
+        let preedit = self.preedit.as_deref();
+        let Some(preedit) = preedit.filter(|_| self.mode == EditorMode::Insert) else {
+            return Ok(());
+        };
+        let layout = self.layout_manager.get_layout();
+        let relative_col = buffer.cursor_display_col().saturating_sub(buffer.scroll_col);
+        let relative_row = buffer.cursor_line.saturating_sub(buffer.scroll_line);
+        if relative_row >= layout.text_area_height as usize {
+            return Ok(());
+        }
+        let room = (layout.text_area_width as usize).saturating_sub(relative_col);
+        let mut width = 0;
+        let visible: String = preedit
+            .chars()
+            .take_while(|&ch| {
+                width += char_width(ch, relative_col + width, &buffer.display);
+                width <= room
+            })
+            .collect();
+        let (screen_x, screen_y) =
+            layout.buffer_to_screen(relative_col as u16, relative_row as u16);
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(screen_x, screen_y),
+            crossterm::style::Print(
+                visible
+                    .with(self.theme.text_fg(self.terminal_focused))
+                    .underlined()
+            )
+        )?;
+        Ok(())
+    }
+
+    /// Display width of the text an input method is composing
+    fn preedit_width(&self, buffer: &TextBuffer) -> usize {
+        match self.preedit.as_deref() {
+            Some(preedit) if self.mode == EditorMode::Insert => {
+                display_width(preedit, &buffer.display)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Draw the Markdown preview pane and its separator, starting from where
+    /// the buffer's top line is rendered
+    fn draw_markdown_preview(&self, buffer: &TextBuffer) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        let (Some(rect), Some(preview)) = (layout.preview_rect(), &self.markdown_preview) else {
+            return Ok(());
+        };
+        let lines = preview.visible(buffer.scroll_line, rect.height as usize);
+        let text_fg = self.theme.text_fg(self.terminal_focused);
+        for y in 0..rect.height {
+            execute!(
+                &self.output,
+                crossterm::cursor::MoveTo(rect.x - 1, rect.y + y),
+                crossterm::style::Print("│".with(self.theme.line_number()))
+            )?;
+            let mut width = 0;
+            for span in lines.get(y as usize).map_or(&[][..], |line| &line.spans) {
+                let text = clip_to_width(&span.text, rect.width as usize - width);
+                width += display_width(&text, &DisplayOptions::default());
+                let styled = match span.style {
+                    MarkdownStyle::Text => text.with(text_fg),
+                    MarkdownStyle::Heading => text.with(self.theme.info()).bold(),
+                    MarkdownStyle::Strong => text.with(text_fg).bold(),
+                    MarkdownStyle::Emphasis => text.with(text_fg).italic(),
+                    MarkdownStyle::Code => text.with(self.theme.special_key()),
+                    MarkdownStyle::Quote => text.with(self.theme.non_text()),
+                    MarkdownStyle::Marker => text.with(self.theme.line_number()),
+                };
+                execute!(&self.output, crossterm::style::Print(styled))?;
+            }
+            let padding = (rect.width as usize).saturating_sub(width);
+            execute!(&self.output, crossterm::style::Print(" ".repeat(padding)))?;
+        }
+        Ok(())
+    }
+
+    /// Draw the `:DiffOrig` split and its separator: the file on disk lined
+    /// up with the buffer, with a `~`, `-` or `+` before changed, removed
+    /// and added lines
+    fn draw_diff_view(&self, buffer: &TextBuffer) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        let (Some(rect), Some((_, view))) = (layout.preview_rect(), &self.diff_view) else {
+            return Ok(());
+        };
+        let rows = view.visible(buffer.scroll_line, rect.height as usize);
+        for y in 0..rect.height {
+            execute!(
+                &self.output,
+                crossterm::cursor::MoveTo(rect.x - 1, rect.y + y),
+                crossterm::style::Print("│".with(self.theme.line_number()))
+            )?;
+            let Some(row) = rows.get(y as usize) else {
+                execute!(
+                    &self.output,
+                    crossterm::style::Print(" ".repeat(rect.width as usize))
+                )?;
+                continue;
+            };
+            let (marker, color) = match row.kind {
+                DiffRowKind::Same => (' ', self.theme.text_fg(self.terminal_focused)),
+                DiffRowKind::Changed => ('~', self.theme.warning()),
+                DiffRowKind::Removed => ('-', self.theme.error()),
+                DiffRowKind::Added => ('+', self.theme.info()),
+            };
+            let width = (rect.width as usize).saturating_sub(1);
+            let text: String = render_line(&row.text, 0, width, &buffer.display)
+                .into_iter()
+                .map(|segment| segment.text)
+                .collect();
+            let padding = width.saturating_sub(display_width(&text, &buffer.display));
+            execute!(
+                &self.output,
+                crossterm::style::Print(marker.with(color)),
+                crossterm::style::Print(text.with(color)),
+                crossterm::style::Print(" ".repeat(padding))
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draw the `:vsplit` window and its separator: the buffer's lines from
+    /// the window's own top line, with line numbers
+    fn draw_split_window(&self, buffer: &TextBuffer) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        let (Some(rect), Some(split)) = (layout.preview_rect(), &self.split_window) else {
+            return Ok(());
+        };
+        let mut lines = buffer.content.lines().skip(split.scroll_line);
+        let number_width = (layout.line_number_width as usize).min(rect.width as usize);
+        let width = (rect.width as usize).saturating_sub(number_width);
+        for y in 0..rect.height {
+            execute!(
+                &self.output,
+                crossterm::cursor::MoveTo(rect.x - 1, rect.y + y),
+                crossterm::style::Print("│".with(self.theme.line_number()))
+            )?;
+            let Some(line) = lines.next() else {
+                execute!(
+                    &self.output,
+                    crossterm::style::Print(" ".repeat(rect.width as usize))
+                )?;
+                continue;
+            };
+            let number = format!(
+                "{:>width$} ",
+                split.scroll_line + y as usize + 1,
+                width = number_width.saturating_sub(1)
+            );
+            let number = clip_to_width(&number, number_width);
+            let text: String = render_line(line, split.scroll_col, width, &buffer.display)
+                .into_iter()
+                .map(|segment| segment.text)
+                .collect();
+            let padding = width.saturating_sub(display_width(&text, &buffer.display));
+            execute!(
+                &self.output,
+                crossterm::style::Print(number.with(self.theme.line_number())),
+                crossterm::style::Print(text.with(self.theme.text_fg(self.terminal_focused))),
+                crossterm::style::Print(" ".repeat(padding))
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Draw the winbar: the current file's path as breadcrumbs
+    fn draw_winbar(&self) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        let Some(rect) = layout.winbar_rect() else {
+            return Ok(());
+        };
+        let width = (rect.width as usize).saturating_sub(1);
+
+        let (mut text, modified) = match self.buffer_manager.current() {
+            Some(buffer) => {
+                let text = match &buffer.file_path {
+                    Some(path) => {
+                        let crumbs = path_crumbs(path, &self.effective_cwd());
+                        join_crumbs(&crumbs, width.saturating_sub(4))
+                    }
+                    None => "[No Name]".to_string(),
+                };
+                (text, buffer.modified)
+            }
+            None => ("[No Name]".to_string(), false),
+        };
+        if modified {
+            text.push_str(" [+]");
+        }
+
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(rect.x, rect.y),
+            crossterm::style::Print(
+                format!(" {}", fit_to_width(&text, width))
+                    .with(self.theme.text_fg(self.terminal_focused))
+                    .bold()
+            )
+        )?;
+        Ok(())
+    }
+
     fn draw_status_line(&self, config: &EditorSettings) -> std::io::Result<()> {
         let layout = self.layout_manager.get_layout();
         let status_rect = layout.status_line_rect();
         
+        let (status_fg, status_bg) = self.theme.status_colors(self.terminal_focused);
+
         // Show message if available, otherwise show buffer status
         let (status_text, text_color) = if let Some(ref message) = self.message {
             let color = match self.message_type {
@@ -350,20 +776,30 @@ impl Editor {
             };
             (message.clone(), color)
         } else if let Some(buffer) = self.buffer_manager.current() {
-            let mut text = buffer.status(config);
+            let mut text = self
+                .pager_status(buffer)
+                .unwrap_or_else(|| buffer.status(config));
             if text.is_empty() { text = String::from("[No Name]"); }
-            (text, self.theme.status_fg())
+            if let Some(branch) = self.vcs_status() {
+                // Right-aligned, when it fits
+                let width = status_rect.width as usize;
+                let used = text.chars().count() + 1;
+                if used + branch.chars().count() <= width {
+                    text = format!("{} {:>pad$}", text, branch, pad = width - used);
+                }
+            }
+            (text, status_fg)
         } else {
-            (String::from("[No Name]"), self.theme.status_fg())
+            (String::from("[No Name]"), status_fg)
         };
         
         execute!(
-            io::stdout(),
+            &self.output,
             crossterm::cursor::MoveTo(status_rect.x, status_rect.y),
             crossterm::style::Print(
-                format!("{:width$}", status_text, width = status_rect.width as usize)
+                fit_to_width(&status_text, status_rect.width as usize)
                     .with(text_color)
-                    .on(self.theme.status_bg())
+                    .on(status_bg)
             )
         )?;
         Ok(())
@@ -377,45 +813,117 @@ impl Editor {
             EditorMode::Normal => ("", self.theme.fg()),
             EditorMode::Insert => ("-- INSERT --", self.theme.info()),
             EditorMode::Visual => ("-- VISUAL --", self.theme.warning()),
+            EditorMode::Select if self.select_from_insert => {
+                ("-- (insert) SELECT --", self.theme.warning())
+            }
+            EditorMode::Select => ("-- SELECT --", self.theme.warning()),
             EditorMode::Command => (":", self.theme.fg()),
         };
         
-        let command_text = if self.mode == EditorMode::Command {
-            format!("{}{}", prompt, self.command_line)
+        let (command_text, prompt_color) = if let Some(question) = self.file_change_prompt() {
+            (question, self.theme.warning())
+        } else if let Some(question) = self.session_prompt() {
+            (question, self.theme.info())
+        } else if let Some(prompt) = self.pager_prompt() {
+            (prompt, self.theme.fg())
+        } else if self.mode == EditorMode::Command {
+            // Past the edge, the end being typed stays in view
+            let command_line = format!("{}{}", prompt, self.command_line);
+            let width = (command_rect.width as usize).saturating_sub(1);
+            (clip_start_to_width(&command_line, width), prompt_color)
         } else {
-            prompt.to_string()
+            (prompt.to_string(), prompt_color)
         };
         
         execute!(
-            io::stdout(),
+            &self.output,
             crossterm::cursor::MoveTo(command_rect.x, command_rect.y),
             crossterm::style::Print(
-                format!(
-                    "{:width$}",
-                    command_text,
-                    width = command_rect.width as usize
-                )
-                .with(prompt_color)
+                fit_to_width(&command_text, command_rect.width as usize).with(prompt_color)
             )
         )?;
         Ok(())
     }
 
+    /// Draw the command palette over the text area, its query on the command
+    /// line. The selected entry is kept in view.
+    fn draw_palette(&self, palette: &Palette) -> std::io::Result<()> {
+        let layout = self.layout_manager.get_layout();
+        let width = layout.width as usize;
+        let rows = layout.text_area_height as usize;
+        let column_width = |text: fn(&PaletteItem) -> &str, max: usize| {
+            palette
+                .matches()
+                .map(|item| text(item).chars().count())
+                .max()
+                .unwrap_or(0)
+                .min(max)
+        };
+        let label_width = column_width(|item| &item.label, width / 3);
+        let keys_width = column_width(|item| &item.keys, width / 4);
+        let first = palette
+            .selected_index()
+            .saturating_sub(rows.saturating_sub(1));
+
+        let mut entries = palette.matches().skip(first);
+        for row in 0..rows {
+            let (text, fg, bg) = match entries.next() {
+                Some(item) => {
+                    let text = format!(
+                        " {:label_width$}  {:keys_width$}  {}",
+                        item.label, item.keys, item.description
+                    );
+                    if first + row == palette.selected_index() {
+                        (text, self.theme.selection_fg(), self.theme.selection_bg())
+                    } else {
+                        (text, self.theme.fg(), self.theme.bg())
+                    }
+                }
+                None if row == 0 => (
+                    " no matching commands".to_string(),
+                    self.theme.non_text(),
+                    self.theme.bg(),
+                ),
+                None => (String::new(), self.theme.fg(), self.theme.bg()),
+            };
+            execute!(
+                &self.output,
+                crossterm::cursor::MoveTo(0, layout.text_start_row + row as u16),
+                crossterm::style::Print(fit_to_width(&text, width).with(fg).on(bg))
+            )?;
+        }
+
+        let prompt = clip_to_width(&format!("> {}", palette.query()), width);
+        let cursor_x = display_width(&prompt, &DisplayOptions::default())
+            .min(width.saturating_sub(1));
+        execute!(
+            &self.output,
+            crossterm::cursor::MoveTo(0, layout.command_line_row()),
+            crossterm::style::Print(fit_to_width(&prompt, width).with(self.theme.fg())),
+            crossterm::cursor::MoveTo(cursor_x as u16, layout.command_line_row())
+        )?;
+        Ok(())
+    }
+
     pub(crate) fn position_cursor(&self) -> std::io::Result<()> {
         if let Some(buffer) = self.buffer_manager.current() {
             let layout = self.layout_manager.get_layout();
             
-            // Calculate relative position within the visible area
-            let relative_col = buffer.cursor_col.saturating_sub(buffer.scroll_col);
+            // Calculate relative position within the visible area, after the
+            // text an input method is composing
+            let relative_col = buffer.cursor_display_col().saturating_sub(buffer.scroll_col)
+                + self.preedit_width(buffer);
             let relative_row = buffer.cursor_line.saturating_sub(buffer.scroll_line);
             
             // Convert to screen coordinates (accounting for line numbers)
             let screen_x = layout.line_number_width + relative_col as u16;
-            let screen_y = relative_row as u16;
+            let screen_y = layout.text_start_row + relative_row as u16;
             
             // Only position cursor if it's within the visible text area
-            if screen_y < layout.text_area_height && relative_col < layout.text_area_width as usize {
-                execute!(io::stdout(), crossterm::cursor::MoveTo(screen_x, screen_y))?;
+            if relative_row < layout.text_area_height as usize
+                && relative_col < layout.text_area_width as usize
+            {
+                execute!(&self.output, crossterm::cursor::MoveTo(screen_x, screen_y))?;
             }
         }
         Ok(())
@@ -0,0 +1,100 @@
+use super::{Editor, MessageType};
+use crate::tui::buffer::TextBuffer;
+use std::path::{Path, PathBuf};
+
+/// Number of closed buffers remembered for `:reopen`
+const CLOSED_HISTORY_SIZE: usize = 10;
+
+/// A buffer closed with `:bdelete`, remembered so `:reopen` can restore it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClosedBuffer {
+    pub path: PathBuf,
+    pub cursor_line: usize,
+    pub cursor_col: usize,
+    pub scroll_line: usize,
+}
+
+impl Editor {
+    /// `:bd[elete][!]`: close the current buffer.
+    ///
+    /// A modified buffer is only closed with `force`. Closing the last buffer
+    /// leaves an empty one in its place.
+    pub(crate) fn delete_current_buffer(&mut self, force: bool) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+        if buffer.modified && !force {
+            self.set_message(
+                "No write since last change (add ! to override)".to_string(),
+                MessageType::Error,
+            );
+            return;
+        }
+
+        let closed = buffer.file_path.clone().map(|path| ClosedBuffer {
+            path,
+            cursor_line: buffer.cursor_line,
+            cursor_col: buffer.cursor_col,
+            scroll_line: buffer.scroll_line,
+        });
+        if let Some(closed) = &closed {
+            self.forget_file(&closed.path);
+            self.shared_data
+                .set_position(&closed.path, closed.cursor_line, closed.cursor_col);
+        }
+
+        if self.buffer_manager.buffer_count() == 1 {
+            // Keep one buffer around: put an empty one in front and drop the old one
+            let mut empty = TextBuffer::new();
+            let layout = self.layout_manager.get_layout();
+            empty.set_size(layout.text_area_width, layout.text_area_height);
+            self.buffer_manager.add_buffer(empty);
+            self.buffer_manager.switch_buffer(0);
+        }
+        self.buffer_manager.close_current_buffer();
+
+        if let Some(closed) = closed {
+            self.set_message(
+                format!("\"{}\" closed", closed.path.display()),
+                MessageType::Info,
+            );
+            self.closed_buffers.push(closed);
+            if self.closed_buffers.len() > CLOSED_HISTORY_SIZE {
+                self.closed_buffers.remove(0);
+            }
+        }
+        self.apply_autochdir();
+        self.render_state.mark_all_dirty();
+    }
+
+    /// `:reopen`: open the most recently closed buffer again at its old position
+    pub(crate) fn reopen_closed_buffer(&mut self) {
+        let Some(closed) = self.closed_buffers.pop() else {
+            self.set_message("No closed buffers".to_string(), MessageType::Warning);
+            return;
+        };
+        if !self.open_path(&closed.path) {
+            return;
+        }
+
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            // set_cursor scrolls the view again if the file got shorter
+            buffer.scroll_line = closed.scroll_line;
+            buffer.set_cursor(closed.cursor_line, closed.cursor_col);
+        }
+        self.apply_autochdir();
+        self.render_state.mark_all_dirty();
+        self.show_file_info();
+    }
+
+    /// Drop the swap file and watcher registration of a file whose buffer is closed
+    fn forget_file(&mut self, path: &Path) {
+        if let Some(swap) = self.swap_manager.as_mut() {
+            let _ = swap.delete_swap(path);
+        }
+        self.last_swap_hash.remove(path);
+        self.file_watcher.unwatch_file(path);
+        self.file_change_prompts.retain(|prompt| prompt != path);
+    }
+}
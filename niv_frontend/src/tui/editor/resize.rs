@@ -0,0 +1,36 @@
+use super::Editor;
+use std::time::Duration;
+
+/// Resizes arriving closer together than this are applied once, after the last
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(30);
+
+impl Editor {
+    /// Handle a terminal resize. Dragging a window edge sends a burst of
+    /// events; only the last size is laid out and drawn.
+    pub(crate) fn handle_resize(&mut self, width: u16, height: u16) {
+        if let Some(id) = self.resize_timer.take() {
+            self.timers.stop(id);
+        }
+        let id = self.set_timeout(RESIZE_DEBOUNCE, move |editor| {
+            editor.resize_timer = None;
+            editor.apply_resize(width, height);
+        });
+        self.resize_timer = Some(id);
+    }
+
+    pub(crate) fn apply_resize(&mut self, width: u16, height: u16) {
+        self.layout_manager.update_size(width, height);
+        let layout = self.layout_manager.get_layout();
+        // The buffer keeps its last usable size behind the placeholder, so
+        // scrolling to keep the cursor in a zero-width window doesn't leave
+        // the text scrolled off once the terminal grows again
+        if layout.is_too_small() {
+            self.render_state.mark_all_dirty();
+            return;
+        }
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_size(layout.text_area_width, layout.text_area_height);
+        }
+        self.render_state.mark_all_dirty();
+    }
+}
@@ -0,0 +1,219 @@
+//! RPC server for external tools (`niv --listen`).
+//!
+//! Clients connect to a Unix socket and exchange JSON-RPC 2.0 messages, one
+//! per line. Methods:
+//!
+//! - `buffer/read`: `{path, text, line, col, modified}` of the current buffer
+//! - `buffer/edit {start, end, text}`: replace bytes `start..end` of the current buffer
+//! - `command {command}`: run an ex command line; returns the message it left
+//! - `open {path, line?}`: edit a file, optionally at a 1-based line
+//! - `input {keys}`: type keys written in vim notation (`ihello<Esc>`)
+//! - `input/preedit {text}`: show text an input method is composing; `""` ends it
+//! - `input/commit {text}`: insert text an input method composed as one change
+//! - `subscribe {event}`, `unsubscribe {event}`: `CursorHold` notifications
+//!
+//! Named pipes on Windows are not supported yet.
+
+use super::{Editor, MessageType};
+use crate::tui::json::JsonValue;
+use crate::tui::keys::parse_key_notation;
+use crate::tui::rpc::{RpcError, RpcRequest, RpcServer};
+use std::path::Path;
+
+/// Events clients can subscribe to
+const RPC_EVENTS: [&str; 1] = ["CursorHold"];
+
+impl Editor {
+    /// Start the RPC server on `path`
+    pub fn listen(&mut self, path: &Path) -> std::io::Result<()> {
+        let server = RpcServer::listen(path)?;
+        if self.rpc.is_none() {
+            self.on_cursor_hold(|editor| {
+                let params = editor.cursor_params();
+                if let Some(rpc) = &mut editor.rpc {
+                    rpc.notify("CursorHold", params);
+                }
+            });
+        }
+        self.rpc = Some(server);
+        Ok(())
+    }
+
+    /// Socket of the running RPC server
+    pub fn server_path(&self) -> Option<&Path> {
+        self.rpc.as_ref().map(RpcServer::path)
+    }
+
+    /// Handle the requests clients sent since the last call
+    pub(crate) fn process_rpc_requests(&mut self) {
+        while let Some(request) = self.rpc.as_ref().and_then(RpcServer::try_recv) {
+            let RpcRequest {
+                client,
+                id,
+                method,
+                params,
+            } = request;
+            niv_fs::log!(Debug, "rpc", "client {}: {}", client, method);
+            let result = match method.as_str() {
+                "buffer/read" => self.rpc_read_buffer(),
+                "buffer/edit" => self.rpc_edit_buffer(&params),
+                "command" => self.rpc_command(&params),
+                "open" => self.rpc_open(&params),
+                "input" => self.rpc_input(&params),
+                "input/preedit" => rpc_text(&params).map(|text| {
+                    self.set_preedit(text);
+                    JsonValue::Null
+                }),
+                "input/commit" => rpc_text(&params).map(|text| {
+                    self.commit_composition(text);
+                    JsonValue::Null
+                }),
+                "subscribe" | "unsubscribe" => rpc_event(&params).map(|event| {
+                    if let Some(rpc) = &mut self.rpc {
+                        if method == "subscribe" {
+                            rpc.subscribe(client, event);
+                        } else {
+                            rpc.unsubscribe(client, event);
+                        }
+                    }
+                    JsonValue::Null
+                }),
+                _ => Err(RpcError::new(
+                    RpcError::METHOD_NOT_FOUND,
+                    format!("unknown method: {}", method),
+                )),
+            };
+            if let Some(rpc) = &mut self.rpc {
+                rpc.respond(client, id, result);
+            }
+        }
+    }
+
+    /// Cursor position of the current buffer, as sent with events
+    fn cursor_params(&self) -> JsonValue {
+        let buffer = self.buffer_manager.current();
+        JsonValue::object([
+            (
+                "path",
+                buffer
+                    .and_then(|b| b.file_path.as_ref())
+                    .map(|p| p.display().to_string())
+                    .into(),
+            ),
+            ("line", buffer.map(|b| b.cursor_line).into()),
+            ("col", buffer.map(|b| b.cursor_col).into()),
+        ])
+    }
+
+    fn rpc_read_buffer(&self) -> Result<JsonValue, RpcError> {
+        let buffer = self
+            .buffer_manager
+            .current()
+            .ok_or_else(|| RpcError::new(RpcError::FAILED, "no buffer"))?;
+        let mut result = self.cursor_params();
+        if let JsonValue::Object(members) = &mut result {
+            members.push(("text".to_string(), buffer.content.as_str().into()));
+            members.push(("modified".to_string(), buffer.modified.into()));
+        }
+        Ok(result)
+    }
+
+    fn rpc_edit_buffer(&mut self, params: &JsonValue) -> Result<JsonValue, RpcError> {
+        let offset = |key| {
+            params
+                .get(key)
+                .and_then(JsonValue::as_usize)
+                .ok_or_else(|| invalid_params(&format!("`{}` must be a byte offset", key)))
+        };
+        let (start, end) = (offset("start")?, offset("end")?);
+        let text = params
+            .get("text")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| invalid_params("`text` must be a string"))?;
+
+        let buffer = self
+            .buffer_manager
+            .current_mut()
+            .ok_or_else(|| RpcError::new(RpcError::FAILED, "no buffer"))?;
+        let content = &buffer.content;
+        if start > end
+            || end > content.len()
+            || !content.is_char_boundary(start)
+            || !content.is_char_boundary(end)
+        {
+            return Err(invalid_params("range is not within the buffer"));
+        }
+        buffer.replace_range(start, end, text);
+        self.render_state.mark_text_dirty();
+        Ok(JsonValue::Null)
+    }
+
+    fn rpc_command(&mut self, params: &JsonValue) -> Result<JsonValue, RpcError> {
+        let command = params
+            .get("command")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| invalid_params("`command` must be a string"))?;
+        self.message = None;
+        self.run_command_line(command.trim_start_matches(':'))
+            .map_err(|e| RpcError::new(RpcError::FAILED, e.to_string()))?;
+        match &self.message {
+            Some(message) if self.message_type == MessageType::Error => {
+                Err(RpcError::new(RpcError::FAILED, message.clone()))
+            }
+            message => Ok(message.clone().into()),
+        }
+    }
+
+    fn rpc_open(&mut self, params: &JsonValue) -> Result<JsonValue, RpcError> {
+        let path = params
+            .get("path")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| invalid_params("`path` must be a string"))?;
+        let path = self.resolve_path(path);
+        let line = params.get("line").and_then(JsonValue::as_usize);
+        if !self.open_file_at(&path, line) {
+            let message = self.message.clone().unwrap_or_default();
+            return Err(RpcError::new(RpcError::FAILED, message));
+        }
+        Ok(JsonValue::Null)
+    }
+
+    fn rpc_input(&mut self, params: &JsonValue) -> Result<JsonValue, RpcError> {
+        let keys = params
+            .get("keys")
+            .and_then(JsonValue::as_str)
+            .ok_or_else(|| invalid_params("`keys` must be a string"))?;
+        for key in parse_key_notation(keys) {
+            self.handle_key_event(key)
+                .map_err(|e| RpcError::new(RpcError::FAILED, e.to_string()))?;
+            if !self.running {
+                break;
+            }
+        }
+        Ok(JsonValue::Null)
+    }
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError::new(RpcError::INVALID_PARAMS, message)
+}
+
+/// The `text` parameter of `input/preedit` and `input/commit`
+fn rpc_text(params: &JsonValue) -> Result<&str, RpcError> {
+    params
+        .get("text")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| invalid_params("`text` must be a string"))
+}
+
+/// The `event` parameter of `subscribe`/`unsubscribe`
+fn rpc_event(params: &JsonValue) -> Result<&str, RpcError> {
+    let event = params
+        .get("event")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| invalid_params("`event` must be a string"))?;
+    if !RPC_EVENTS.contains(&event) {
+        return Err(invalid_params(&format!("unknown event: {}", event)));
+    }
+    Ok(event)
+}
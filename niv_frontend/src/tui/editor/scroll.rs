@@ -0,0 +1,99 @@
+use super::Editor;
+use crossterm::execute;
+use std::time::{Duration, Instant};
+
+/// Time between animation frames
+const FRAME_INTERVAL: Duration = Duration::from_millis(12);
+/// Upper bound on `smooth_scroll_frames`, so an animation never takes long
+const MAX_FRAMES: u32 = 20;
+/// How long to wait for input when nothing is animating
+const IDLE_POLL: Duration = Duration::from_millis(50);
+
+/// A scroll jump being animated by drawing intermediate scroll positions
+#[derive(Debug, Clone)]
+pub(crate) struct ScrollAnimation {
+    from: usize,
+    to: usize,
+    frame: u32,
+    frames: u32,
+    next_frame: Instant,
+}
+
+impl ScrollAnimation {
+    /// Scroll line shown in the current frame, easing out towards the target
+    fn line(&self) -> usize {
+        let t = self.frame as f64 / self.frames as f64;
+        let eased = 1.0 - (1.0 - t) * (1.0 - t);
+        let distance = self.to as f64 - self.from as f64;
+        (self.from as f64 + distance * eased).round() as usize
+    }
+}
+
+impl Editor {
+    /// Animate the view from scroll line `from` to where the current buffer is
+    /// scrolled now, if `smooth_scroll` is on and the jump is more than a couple of lines
+    pub(crate) fn animate_scroll_from(&mut self, from: usize) {
+        let config = self.config_loader.get_copy();
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let to = buffer.scroll_line;
+        let frames = config.editor.smooth_scroll_frames.min(MAX_FRAMES);
+        if !config.editor.smooth_scroll || frames < 2 || from.abs_diff(to) <= 2 {
+            return;
+        }
+
+        self.scroll_animation = Some(ScrollAnimation {
+            from,
+            to,
+            frame: 1,
+            frames,
+            next_frame: Instant::now() + FRAME_INTERVAL,
+        });
+        let _ = execute!(&self.output, crossterm::cursor::Hide);
+        self.render_state.mark_text_dirty();
+    }
+
+    /// How long the event loop may wait for input before the next frame or
+    /// timer is due
+    pub(crate) fn poll_timeout(&self) -> Duration {
+        let frame = match &self.scroll_animation {
+            Some(animation) => animation
+                .next_frame
+                .saturating_duration_since(Instant::now()),
+            None => IDLE_POLL,
+        };
+        self.timer_timeout().map_or(frame, |timer| timer.min(frame))
+    }
+
+    /// Move the animation on to its next frame once that frame is due
+    pub(crate) fn advance_scroll_animation(&mut self) {
+        let Some(animation) = &mut self.scroll_animation else {
+            return;
+        };
+        if Instant::now() < animation.next_frame {
+            return;
+        }
+        animation.frame += 1;
+        animation.next_frame += FRAME_INTERVAL;
+        if animation.frame >= animation.frames {
+            self.finish_scroll_animation();
+        } else {
+            self.render_state.mark_text_dirty();
+        }
+    }
+
+    /// Jump straight to the end of a running animation; used when a key arrives
+    /// so animations never delay input
+    pub(crate) fn finish_scroll_animation(&mut self) {
+        if self.scroll_animation.take().is_some() {
+            let _ = execute!(&self.output, crossterm::cursor::Show);
+            self.render_state.mark_text_dirty();
+        }
+    }
+
+    /// Scroll line to draw this frame, if an animation is running
+    pub(crate) fn animated_scroll_line(&self) -> Option<usize> {
+        self.scroll_animation.as_ref().map(ScrollAnimation::line)
+    }
+}
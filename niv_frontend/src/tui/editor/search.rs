@@ -0,0 +1,74 @@
+use super::{Editor, MessageType};
+use crate::tui::wordindex::{find_word, keyword_at};
+
+impl Editor {
+    /// `*` / `#`: jump to the next or previous whole-word occurrence of the
+    /// keyword under the cursor, wrapping around the buffer
+    pub(crate) fn search_word_under_cursor(&mut self, backward: bool) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let line = buffer.current_line();
+        let Some((start, end)) = keyword_at(line, buffer.cursor_col) else {
+            self.set_message("No string under cursor".to_string(), MessageType::Error);
+            return;
+        };
+        let word = line[start..end].to_string();
+
+        // The index tells us when there is nothing else to find without a scan
+        if buffer.word_index.count(&word) <= 1 {
+            if let Some(buffer) = self.buffer_manager.current_mut() {
+                buffer.set_cursor(buffer.cursor_line, start);
+            }
+            self.render_state.cursor_dirty = true;
+            self.set_message(format!("Only match: {}", word), MessageType::Info);
+            return;
+        }
+
+        let lines: Vec<&str> = buffer.content.lines().collect();
+        let cursor_line = buffer.cursor_line.min(lines.len().saturating_sub(1));
+        let line_count = lines.len();
+        // Visit every line once starting at the cursor line, and the cursor line
+        // a second time to find matches on the other side of the cursor
+        let found = (0..=line_count).find_map(|step| {
+            let index = if backward {
+                (cursor_line + line_count - step % line_count) % line_count
+            } else {
+                (cursor_line + step) % line_count
+            };
+            let matches = find_word(lines[index], &word);
+            let col = match (step, backward) {
+                (0, false) => matches.into_iter().find(|&col| col > start),
+                (0, true) => matches.into_iter().rev().find(|&col| col < start),
+                (_, false) => matches.into_iter().next(),
+                (_, true) => matches.into_iter().next_back(),
+            };
+            col.map(|col| (index, col))
+        });
+
+        let Some((line, col)) = found else {
+            return;
+        };
+        let wrapped = if backward {
+            line > cursor_line || (line == cursor_line && col > start)
+        } else {
+            line < cursor_line || (line == cursor_line && col < start)
+        };
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_cursor(line, col);
+        }
+        self.render_state.mark_all_dirty();
+
+        let prefix = if backward { '?' } else { '/' };
+        if wrapped {
+            let message = if backward {
+                "search hit TOP, continuing at BOTTOM"
+            } else {
+                "search hit BOTTOM, continuing at TOP"
+            };
+            self.set_message(message.to_string(), MessageType::Warning);
+        } else {
+            self.set_message(format!("{}\\<{}\\>", prefix, word), MessageType::Info);
+        }
+    }
+}
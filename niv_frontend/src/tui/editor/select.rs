@@ -0,0 +1,185 @@
+use super::{Editor, EditorMode};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+impl Editor {
+    /// `gh`, or a Shift+Arrow with `shift_select`: start selecting text at
+    /// the cursor in select mode
+    pub(super) fn start_select(&mut self, from_insert: bool) {
+        self.visual_start = self
+            .buffer_manager
+            .current()
+            .map(|buffer| (buffer.cursor_line, buffer.cursor_col));
+        self.select_from_insert = from_insert;
+        self.mode = EditorMode::Select;
+        self.render_state.status_line_dirty = true;
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// `Ctrl+g` in visual mode: select the same lines in select mode
+    pub(super) fn visual_to_select(&mut self) {
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        let Some((anchor, _)) = self.visual_start else {
+            return;
+        };
+        let cursor = buffer.cursor_line;
+        let line_end = |line| buffer.content.split('\n').nth(line).map_or(0, str::len);
+        let (anchor_col, cursor_col) = if cursor >= anchor {
+            (0, line_end(cursor))
+        } else {
+            (line_end(anchor), 0)
+        };
+        self.visual_start = Some((anchor, anchor_col));
+        buffer.set_cursor(cursor, cursor_col);
+        self.select_from_insert = false;
+        self.mode = EditorMode::Select;
+        self.render_state.status_line_dirty = true;
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Shift+Arrow, Shift+Home and Shift+End start select mode from normal
+    /// and insert mode with `shift_select`, and extend the selection in
+    /// visual and select mode. Returns whether the key was used.
+    pub(super) fn handle_shift_select(&mut self, key_event: KeyEvent) -> bool {
+        if !key_event.modifiers.contains(KeyModifiers::SHIFT) || !is_motion(key_event.code) {
+            return false;
+        }
+        if !self.config_loader.get_copy().editor.shift_select {
+            return false;
+        }
+        match self.mode {
+            EditorMode::Normal | EditorMode::Insert => {
+                self.start_select(self.mode == EditorMode::Insert);
+            }
+            EditorMode::Visual | EditorMode::Select => {}
+            EditorMode::Command => return false,
+        }
+        self.move_cursor(key_event.code);
+        true
+    }
+
+    pub(super) fn handle_select_mode(&mut self, key_event: KeyEvent) -> std::io::Result<()> {
+        let control = key_event.modifiers.contains(KeyModifiers::CONTROL);
+        match key_event.code {
+            code if is_motion(code) => {
+                // Unshifted arrows end a selection Shift+Arrow can start
+                if self.config_loader.get_copy().editor.shift_select {
+                    self.stop_select();
+                }
+                self.move_cursor(code);
+            }
+            KeyCode::Char('g') if control => {
+                self.select_from_insert = false;
+                self.mode = EditorMode::Visual;
+                self.render_state.status_line_dirty = true;
+                self.render_state.command_line_dirty = true;
+            }
+            KeyCode::Char(ch) if !control => self.replace_selection(ch.encode_utf8(&mut [0; 4])),
+            KeyCode::Enter => self.replace_selection("\n"),
+            KeyCode::Tab => self.replace_selection("\t"),
+            KeyCode::Backspace | KeyCode::Delete => {
+                let text = self.delete_selection();
+                self.kill_text(text);
+                self.stop_select();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Show the visual or select mode selection in the current buffer,
+    /// redrawing the text when it changed
+    pub(super) fn update_selection(&mut self) {
+        let selection = match (self.mode, self.visual_start) {
+            (EditorMode::Visual, Some((anchor, _))) => {
+                self.buffer_manager.current().map(|buffer| {
+                    let cursor = buffer.cursor_line;
+                    [(anchor.min(cursor), 0), (anchor.max(cursor), usize::MAX)]
+                })
+            }
+            (EditorMode::Select, Some(anchor)) => self.buffer_manager.current().map(|buffer| {
+                let cursor = (buffer.cursor_line, buffer.cursor_col);
+                [anchor.min(cursor), anchor.max(cursor)]
+            }),
+            _ => None,
+        };
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        if buffer.selection != selection {
+            buffer.selection = selection;
+            self.render_state.mark_text_dirty();
+        }
+    }
+
+    /// Back to the mode select mode was entered from
+    fn stop_select(&mut self) {
+        self.remember_visual_selection();
+        self.mode = match std::mem::take(&mut self.select_from_insert) {
+            true => EditorMode::Insert,
+            false => EditorMode::Normal,
+        };
+        self.render_state.status_line_dirty = true;
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Delete the selected text, leaving the cursor where it started.
+    /// Returns the deleted text.
+    fn delete_selection(&mut self) -> String {
+        let Some(anchor) = self.visual_start else {
+            return String::new();
+        };
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return String::new();
+        };
+        let cursor = (buffer.cursor_line, buffer.cursor_col);
+        let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+        let start = buffer.offset_at(start.0, start.1);
+        let end = buffer.offset_at(end.0, end.1);
+        let text = buffer.content[start..end].to_string();
+        buffer.replace_range(start, end, "");
+        let (line, col) = buffer.position_at(start);
+        buffer.set_cursor(line, col);
+        self.render_state.mark_text_dirty();
+        text
+    }
+
+    /// Type `text` over the selection and continue in insert mode
+    fn replace_selection(&mut self, text: &str) {
+        self.delete_selection();
+        self.remember_visual_selection();
+        self.select_from_insert = false;
+        self.mode = EditorMode::Insert;
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.insert_text(text);
+        }
+        self.render_state.mark_text_dirty();
+        self.render_state.status_line_dirty = true;
+        self.render_state.command_line_dirty = true;
+    }
+
+    fn move_cursor(&mut self, code: KeyCode) {
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        match code {
+            KeyCode::Left => buffer.move_cursor_left(),
+            KeyCode::Right => buffer.move_cursor_right(),
+            KeyCode::Up => buffer.move_cursor_up(),
+            KeyCode::Down => buffer.move_cursor_down(),
+            KeyCode::Home => buffer.move_cursor_line_start(),
+            KeyCode::End => buffer.move_cursor_line_end(),
+            _ => {}
+        }
+        self.render_state.cursor_dirty = true;
+    }
+}
+
+/// Keys that move the cursor and, shifted, select
+fn is_motion(code: KeyCode) -> bool {
+    matches!(
+        code,
+        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End
+    )
+}
@@ -0,0 +1,118 @@
+use super::{Editor, MessageType};
+use crate::tui::session::{Session, SessionFile};
+use crossterm::event::{KeyCode, KeyEvent};
+use niv_fs::FsResult;
+use std::path::PathBuf;
+
+impl Editor {
+    /// Keep the session of a project in `path`: the open files and the kill
+    /// ring are saved there when the editor exits, and with `offer_restore` a prompt offers
+    /// to reopen the ones saved last time
+    pub fn start_auto_session(&mut self, path: PathBuf, offer_restore: bool) {
+        self.pending_session = offer_restore
+            .then(|| std::fs::read_to_string(&path).ok())
+            .flatten()
+            .and_then(|text| Session::parse(&text))
+            .filter(|session| !session.files.is_empty());
+        self.session_file = Some(path);
+        self.render_state.command_line_dirty = true;
+    }
+
+    /// Save the open files, their cursors and the kill ring to the
+    /// auto-session file, if there is one. `run` does this on exit. The file
+    /// is locked and replaced in one step, as other instances may be saving
+    /// the same project's session.
+    pub fn save_auto_session(&self) -> FsResult<()> {
+        let Some(path) = &self.session_file else {
+            return Ok(());
+        };
+        let files: Vec<SessionFile> = self
+            .buffer_manager
+            .iter()
+            .filter_map(|buffer| {
+                Some(SessionFile {
+                    path: buffer.file_path.clone()?,
+                    line: buffer.cursor_line,
+                    col: buffer.cursor_col,
+                })
+            })
+            .collect();
+        // A session without files would only hide the last useful one
+        if files.is_empty() {
+            return Ok(());
+        }
+        let current = self
+            .buffer_manager
+            .current()
+            .and_then(|buffer| buffer.file_path.as_ref())
+            .and_then(|current| files.iter().position(|file| &file.path == current))
+            .unwrap_or(0);
+        let session = Session {
+            files,
+            current,
+            yanks: self.kill_ring.iter().cloned().collect(),
+        };
+        niv_fs::write_locked(path, &session.to_text())
+    }
+
+    /// Text of the restore-session prompt waiting for an answer
+    pub(crate) fn session_prompt(&self) -> Option<String> {
+        self.pending_session.as_ref().map(|session| {
+            format!(
+                "Restore the last session of this project ({} files)? [Y]es, [N]o",
+                session.files.len()
+            )
+        })
+    }
+
+    /// Answer the restore-session prompt. Returns true when the key was consumed.
+    pub(crate) fn handle_session_prompt(&mut self, key_event: KeyEvent) -> bool {
+        if self.pending_session.is_none() {
+            return false;
+        }
+        match key_event.code {
+            KeyCode::Char('y') | KeyCode::Char('Y') | KeyCode::Enter => {
+                if let Some(session) = self.pending_session.take() {
+                    self.restore_session(&session);
+                }
+            }
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                self.pending_session = None;
+            }
+            // Any other key keeps the prompt up
+            _ => {}
+        }
+        self.render_state.command_line_dirty = true;
+        true
+    }
+
+    /// Reopen the files of `session` with their cursors where they were, and
+    /// bring back its kill ring.
+    /// Files that no longer exist are left out.
+    fn restore_session(&mut self, session: &Session) {
+        let mut restored = 0;
+        for file in &session.files {
+            if !file.path.exists() || !self.open_path(&file.path) {
+                continue;
+            }
+            if let Some(buffer) = self.buffer_manager.current_mut() {
+                buffer.set_cursor(file.line, file.col);
+            }
+            restored += 1;
+        }
+        let current = session
+            .files
+            .get(session.current)
+            .and_then(|file| self.buffer_manager.position_by_path(&file.path));
+        if let Some(index) = current {
+            self.buffer_manager.switch_buffer(index);
+        }
+        // Oldest first, so the newest ends up in front
+        for yank in session.yanks.iter().rev() {
+            self.kill_ring.push(yank.clone());
+        }
+        self.apply_autochdir();
+        self.render_state.mark_all_dirty();
+        self.set_message(format!("{} files restored", restored), MessageType::Info);
+    }
+}
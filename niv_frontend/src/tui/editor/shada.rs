@@ -0,0 +1,89 @@
+use super::{Editor, MessageType};
+use niv_fs::{FsResult, SharedData};
+use std::path::{Path, PathBuf};
+
+impl Editor {
+    /// Keep global marks and the last cursor position in each file in the
+    /// shada file at `path`, reading what is in it now. `run` writes it back
+    /// on exit, merged with what other instances wrote in the meantime.
+    pub fn start_shada(&mut self, path: PathBuf) {
+        match SharedData::load(&path) {
+            Ok(data) => self.shared_data.merge(data),
+            Err(e) => niv_fs::log!(Warn, "shada", "{}", e),
+        }
+        self.load_macros();
+        self.shada_file = Some(path);
+    }
+
+    /// Remember the cursor in each open file and write the shada file, if
+    /// there is one
+    pub fn save_shada(&mut self) -> FsResult<()> {
+        let Some(path) = self.shada_file.clone() else {
+            return Ok(());
+        };
+        for buffer in self.buffer_manager.iter() {
+            if let Some(file) = &buffer.file_path {
+                self.shared_data
+                    .set_position(file, buffer.cursor_line, buffer.cursor_col);
+            }
+        }
+        self.shared_data.write(&path)
+    }
+
+    /// Put the cursor in the just opened `path` where it was when the file
+    /// was last left
+    pub(super) fn restore_last_position(&mut self, path: &Path) {
+        let Some(position) = self.shared_data.position(path) else {
+            return;
+        };
+        let (line, col) = (position.line, position.col);
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            buffer.set_cursor(line, col);
+        }
+    }
+
+    /// `m{A-Z}`: set a global mark at the cursor
+    pub(super) fn set_global_mark(&mut self, name: char) {
+        if !name.is_ascii_uppercase() {
+            self.set_message(
+                "Only the global marks A-Z are supported".to_string(),
+                MessageType::Warning,
+            );
+            return;
+        }
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        let (line, col) = (buffer.cursor_line, buffer.cursor_col);
+        self.shared_data.set_mark(name, &path, line, col);
+    }
+
+    /// `'{A-Z}` and `` `{A-Z} ``: edit the file of a global mark and go to
+    /// its line, at the first non-blank or, with `exact`, at its column
+    pub(super) fn jump_to_global_mark(&mut self, name: char, exact: bool) {
+        let Some(mark) = self.shared_data.mark(name).cloned() else {
+            self.set_message("Mark not set".to_string(), MessageType::Error);
+            return;
+        };
+        if !self.open_file_at(&mark.path, None) {
+            return;
+        }
+        if let Some(buffer) = self.buffer_manager.current_mut() {
+            let col = if exact {
+                mark.col
+            } else {
+                buffer
+                    .content
+                    .lines()
+                    .nth(mark.line)
+                    .map_or(0, |text| text.len() - text.trim_start().len())
+            };
+            buffer.set_cursor(mark.line, col);
+        }
+        self.render_state.mark_all_dirty();
+    }
+}
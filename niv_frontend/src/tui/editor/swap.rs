@@ -0,0 +1,152 @@
+use super::Editor;
+use niv_config::EditorSettings;
+use niv_fs::{
+    CursorPosition, PassphraseKey, SWAP_PASSPHRASE_ENV, SwapConfig, SwapManager, ViewportState,
+};
+
+impl Editor {
+    /// Create the swap manager if swap files are enabled in the configuration
+    pub(crate) fn create_swap_manager(settings: &EditorSettings) -> Option<SwapManager> {
+        if !settings.swapfile {
+            return None;
+        }
+
+        let config = SwapConfig {
+            exclude_patterns: settings.sensitive_files.clone(),
+            ..SwapConfig::default()
+        };
+        let mut manager = SwapManager::new(config).ok()?;
+        if settings.swap_encrypt {
+            // Without a key, no swap at all rather than one anyone can read
+            let Ok(passphrase) = std::env::var(SWAP_PASSPHRASE_ENV) else {
+                niv_fs::log!(
+                    Warn,
+                    "swap",
+                    "swap_encrypt is set but {} is not; no swap files",
+                    SWAP_PASSPHRASE_ENV
+                );
+                return None;
+            };
+            if let Err(e) = manager.enable_encryption(&PassphraseKey::new(passphrase)) {
+                niv_fs::log!(Warn, "swap", "can't encrypt swap files: {}", e);
+                return None;
+            }
+        }
+        Some(manager)
+    }
+
+    /// Register the current buffer with the swap manager, respecting its `no_swap` flag
+    pub(crate) fn register_current_swap(&mut self) {
+        let Some(swap) = self.swap_manager.as_mut() else {
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            return;
+        };
+
+        let result = if buffer.no_swap {
+            swap.set_swap_disabled(&path, true)
+        } else {
+            swap.register_file(&path, &buffer.content)
+        };
+        self.last_swap_hash
+            .insert(path, Self::simple_hash_static(&buffer.content));
+
+        if let Err(e) = result {
+            self.set_message(format!("Swap error: {}", e), super::MessageType::Warning);
+        }
+    }
+
+    /// Apply the current buffer's `no_swap` flag after it changed (`:setlocal`)
+    pub(crate) fn refresh_current_swap_policy(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            return;
+        };
+        let no_swap = buffer.no_swap;
+
+        let result = self
+            .swap_manager
+            .as_mut()
+            .map_or(Ok(()), |swap| swap.set_swap_disabled(&path, no_swap));
+        if let Err(e) = result {
+            self.set_message(format!("Swap error: {}", e), super::MessageType::Warning);
+            return;
+        }
+        if !no_swap {
+            self.register_current_swap();
+        }
+    }
+
+    /// Push the current buffer content to the swap manager if it changed
+    pub(crate) fn sync_swap(&mut self) {
+        let Some(swap) = self.swap_manager.as_mut() else {
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.as_ref() else {
+            return;
+        };
+        if buffer.no_swap {
+            return;
+        }
+
+        let hash = Self::simple_hash_static(&buffer.content);
+        if self.last_swap_hash.get(path) == Some(&hash) {
+            return;
+        }
+        self.last_swap_hash.insert(path.clone(), hash);
+
+        let cursor = CursorPosition {
+            line: buffer.cursor_line,
+            column: buffer.cursor_col,
+            offset: 0,
+        };
+        let viewport = ViewportState {
+            top_line: buffer.scroll_line,
+            visible_lines: buffer.height as usize,
+            horizontal_offset: buffer.scroll_col,
+        };
+        if let Err(e) = swap.update_content(path, &buffer.content, Some(cursor), Some(viewport)) {
+            self.set_message(format!("Swap error: {}", e), super::MessageType::Warning);
+        }
+    }
+
+    /// Write the current buffer's swap file now, if the buffer has unsaved changes
+    pub(crate) fn flush_current_swap(&mut self) {
+        self.sync_swap();
+        let Some(swap) = self.swap_manager.as_mut() else {
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current() else {
+            return;
+        };
+        let Some(path) = buffer.file_path.as_ref() else {
+            return;
+        };
+        if !buffer.modified || buffer.no_swap {
+            return;
+        }
+        if let Err(e) = swap.save_swap(path) {
+            self.set_message(format!("Swap error: {}", e), super::MessageType::Warning);
+        }
+    }
+
+    /// Remove swap files of all buffers on a clean exit
+    pub(crate) fn shutdown_swap(&mut self) {
+        let Some(swap) = self.swap_manager.as_mut() else {
+            return;
+        };
+        for path in self.last_swap_hash.keys() {
+            let _ = swap.delete_swap(path);
+        }
+        swap.stop();
+    }
+}
@@ -0,0 +1,29 @@
+use super::{Editor, MessageType};
+use crate::tui::table::{guess_delimiter, table_delimiter};
+
+impl Editor {
+    /// `:CsvAlign`: toggle lining up the current buffer's fields in columns,
+    /// with the header kept in view. The delimiter comes from the file's
+    /// extension, or is guessed from the first line.
+    pub(super) fn toggle_table_view(&mut self) {
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+        if buffer.display.table.take().is_none() {
+            let delimiter = buffer
+                .file_path
+                .as_deref()
+                .and_then(table_delimiter)
+                .or_else(|| guess_delimiter(buffer.content.lines().next().unwrap_or("")));
+            let Some(delimiter) = delimiter else {
+                self.set_message("No delimiter found".to_string(), MessageType::Error);
+                return;
+            };
+            buffer.display.table = Some(delimiter);
+        }
+        // Columns move the cursor's screen position
+        buffer.set_cursor(buffer.cursor_line, buffer.cursor_col);
+        self.render_state.mark_all_dirty();
+    }
+}
@@ -0,0 +1,143 @@
+use super::{Editor, MessageType};
+use niv_fs::ChangeType;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How often followed files are checked for appended lines
+const TAIL_INTERVAL: Duration = Duration::from_millis(250);
+
+impl Editor {
+    /// `:tail`: toggle following the current buffer's file, like `tail -f`.
+    /// Lines appended to the file are loaded as they arrive, and the view
+    /// stays at the end while the cursor is on the last line.
+    pub(super) fn toggle_tail(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        let follow = !buffer.follow;
+        self.set_follow(follow);
+        let message = if follow {
+            let pager = self.pager.is_some();
+            self.jump(|editor| {
+                if let Some(buffer) = editor.buffer_manager.current_mut() {
+                    pin_to_end(buffer, pager);
+                }
+            });
+            format!("Following \"{}\"", path.display())
+        } else {
+            format!("Stopped following \"{}\"", path.display())
+        };
+        self.set_message(message, MessageType::Info);
+    }
+
+    /// Start or stop following the current buffer's file; buffers without a
+    /// file can't follow
+    pub(super) fn set_follow(&mut self, follow: bool) {
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        if buffer.file_path.is_some() {
+            buffer.follow = follow;
+        }
+        self.update_tail_timer();
+    }
+
+    /// Check for appended lines while any buffer follows its file
+    fn update_tail_timer(&mut self) {
+        let following = self.buffer_manager.iter().any(|buffer| buffer.follow);
+        match (following, self.tail_timer) {
+            (true, None) => {
+                let id = self.set_interval(TAIL_INTERVAL, |editor| editor.tail_files());
+                self.tail_timer = Some(id);
+            }
+            (false, Some(id)) => {
+                self.timers.stop(id);
+                self.tail_timer = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Load what was appended to each followed file
+    fn tail_files(&mut self) {
+        let paths: Vec<PathBuf> = self
+            .buffer_manager
+            .iter()
+            .filter(|buffer| buffer.follow)
+            .filter_map(|buffer| buffer.file_path.clone())
+            .collect();
+        for path in paths {
+            self.tail_file(&path);
+        }
+        // Followed buffers may have been closed
+        self.update_tail_timer();
+    }
+
+    /// Read the lines appended to `path` into its buffer if the watcher saw
+    /// the file change. A file that was rewritten is loaded again in full.
+    fn tail_file(&mut self, path: &Path) {
+        let change = match self.file_watcher.check_external_changes(path) {
+            Ok(Some(change)) => change,
+            Ok(None) => return,
+            Err(e) => {
+                self.set_message(format!("Watcher error: {}", e), MessageType::Error);
+                return;
+            }
+        };
+        if change.change_type == ChangeType::Deleted {
+            self.stop_tail(path);
+            self.set_message(
+                format!("File \"{}\" no longer available", path.display()),
+                MessageType::Warning,
+            );
+            return;
+        }
+
+        let pager = self.pager.is_some();
+        let Some(buffer) = self.buffer_manager.find_by_path_mut(path) else {
+            return;
+        };
+        // Unsaved changes are left alone, as with autoread
+        if buffer.modified {
+            return;
+        }
+        let pinned = pager || buffer.cursor_line + 1 >= buffer.content.lines().count();
+        match self.reload_buffer_incremental(path) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(e) => {
+                self.stop_tail(path);
+                self.set_message(format!("Reload failed: {}", e), MessageType::Error);
+                return;
+            }
+        }
+        if let (true, Some(buffer)) = (pinned, self.buffer_manager.find_by_path_mut(path)) {
+            pin_to_end(buffer, pager);
+        }
+        self.render_state.mark_all_dirty();
+    }
+
+    /// Stop following `path`, and the pager's `F` with it
+    fn stop_tail(&mut self, path: &Path) {
+        if let Some(buffer) = self.buffer_manager.find_by_path_mut(path) {
+            buffer.follow = false;
+        }
+        self.stop_following();
+        self.update_tail_timer();
+    }
+}
+
+/// Keep the end of the buffer in view: the last page in the pager, the last
+/// line with the cursor on it otherwise
+fn pin_to_end(buffer: &mut crate::tui::buffer::TextBuffer, pager: bool) {
+    if pager {
+        buffer.scroll_to(usize::MAX);
+    } else {
+        buffer.set_cursor(usize::MAX, 0);
+    }
+}
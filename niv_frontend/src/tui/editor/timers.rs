@@ -0,0 +1,64 @@
+use super::{Editor, MessageType};
+use crate::tui::timer::TimerId;
+use std::time::{Duration, Instant};
+
+/// Action run when an editor timer fires
+pub type TimerCallback = Box<dyn FnMut(&mut Editor)>;
+
+impl Editor {
+    /// Run `callback` once after `delay`
+    pub fn set_timeout(
+        &mut self,
+        delay: Duration,
+        callback: impl FnMut(&mut Editor) + 'static,
+    ) -> TimerId {
+        self.timers.start_once(delay, Box::new(callback))
+    }
+
+    /// Run `callback` every `interval` until the timer is cancelled
+    pub fn set_interval(
+        &mut self,
+        interval: Duration,
+        callback: impl FnMut(&mut Editor) + 'static,
+    ) -> TimerId {
+        self.timers.start_repeating(interval, Box::new(callback))
+    }
+
+    /// Cancel a timer. Returns false if it already fired or was cancelled.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.timers.stop(id)
+    }
+
+    /// Time until the next timer fires, if any are running
+    pub(crate) fn timer_timeout(&self) -> Option<Duration> {
+        self.timers
+            .next_deadline()
+            .map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// Run the callbacks of all timers that are due
+    pub(crate) fn run_due_timers(&mut self) {
+        let now = Instant::now();
+        while let Some(mut due) = self.timers.pop_due(now) {
+            (due.action)(self);
+            self.timers.finish(due);
+        }
+    }
+
+    /// Clear info messages after `message_timeout`; warnings and errors stay
+    /// until replaced
+    pub(crate) fn schedule_message_dismiss(&mut self, msg_type: MessageType) {
+        if let Some(id) = self.message_timer.take() {
+            self.timers.stop(id);
+        }
+        let timeout = self.config_loader.get_copy().editor.message_timeout;
+        if timeout == 0 || !matches!(msg_type, MessageType::Info | MessageType::Success) {
+            return;
+        }
+        let id = self.set_timeout(Duration::from_millis(timeout as u64), |editor| {
+            editor.message_timer = None;
+            editor.clear_message();
+        });
+        self.message_timer = Some(id);
+    }
+}
@@ -0,0 +1,164 @@
+use super::{Editor, MessageType};
+use crate::tui::diff::{DiffRowKind, DiffView};
+use crate::tui::timer::TimerId;
+use crate::tui::vcs::{Vcs, find_vcs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Edits closer together than this update the gutter signs once, after the
+/// last, so typing doesn't wait for a diff on every key
+const SIGNS_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The current buffer's file and the version control system it is in
+pub(super) struct VcsFile {
+    path: PathBuf,
+    vcs: Option<Box<dyn Vcs>>,
+    branch: Option<String>,
+    /// Buffer compared with the file as last committed, for the gutter
+    changes: Option<DiffView>,
+    /// Timer that diffs the buffer again once edits pause
+    refresh_timer: Option<TimerId>,
+}
+
+impl Editor {
+    /// Look up the repository of the current buffer's file when another file
+    /// became current, and bring the gutter up to date with the buffer: at
+    /// once for a file just looked up, otherwise once edits pause
+    pub(super) fn update_vcs_file(&mut self) {
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.vcs_file = None;
+            return;
+        };
+        let Some(path) = buffer.file_path.clone() else {
+            self.vcs_file = None;
+            return;
+        };
+        if self.vcs_file.as_ref().is_none_or(|file| file.path != path) {
+            let vcs = find_vcs(&path);
+            let branch = vcs.as_ref().and_then(|vcs| vcs.branch());
+            let changes = vcs
+                .as_ref()
+                .and_then(|vcs| vcs.base_text(&path))
+                .map(DiffView::new);
+            if let Some(id) = self
+                .vcs_file
+                .as_mut()
+                .and_then(|file| file.refresh_timer.take())
+            {
+                self.timers.stop(id);
+            }
+            self.vcs_file = Some(VcsFile {
+                path,
+                vcs,
+                branch,
+                changes,
+                refresh_timer: None,
+            });
+            self.render_state.mark_all_dirty();
+            self.update_vcs_signs();
+            return;
+        }
+
+        let Some(file) = &self.vcs_file else {
+            return;
+        };
+        let Some(changes) = &file.changes else {
+            return;
+        };
+        if file.refresh_timer.is_some() || changes.is_current(&buffer.content) {
+            return;
+        }
+        let id = self.set_timeout(SIGNS_DEBOUNCE, |editor| {
+            if let Some(file) = editor.vcs_file.as_mut() {
+                file.refresh_timer = None;
+            }
+            editor.update_vcs_signs();
+        });
+        if let Some(file) = self.vcs_file.as_mut() {
+            file.refresh_timer = Some(id);
+        }
+    }
+
+    /// Diff the buffer against the file as last committed, if it changed
+    /// since the last diff
+    fn update_vcs_signs(&mut self) {
+        let changes = self
+            .vcs_file
+            .as_mut()
+            .and_then(|file| file.changes.as_mut());
+        if let (Some(changes), Some(buffer)) = (changes, self.buffer_manager.current()) {
+            // Signs move on lines other than the edited ones
+            if changes.update(&buffer.content) {
+                self.render_state.line_numbers_dirty = true;
+                self.render_state.dirty_line_numbers = None;
+            }
+        }
+    }
+
+    /// Look the repository up again, after it may have changed outside the
+    /// editor
+    pub(super) fn refresh_vcs_file(&mut self) {
+        self.vcs_file = None;
+        self.update_vcs_file();
+    }
+
+    /// Gutter sign of buffer line `line`: `~` changed, `+` added and `-`
+    /// where lines were removed, since the last commit
+    pub(super) fn vcs_sign(&self, line: usize) -> Option<(char, DiffRowKind)> {
+        let changes = self.vcs_file.as_ref()?.changes.as_ref()?;
+        let kind = changes.line_change(line)?;
+        let sign = match kind {
+            DiffRowKind::Same => return None,
+            DiffRowKind::Changed => '~',
+            DiffRowKind::Added => '+',
+            DiffRowKind::Removed => '-',
+        };
+        Some((sign, kind))
+    }
+
+    /// Status line segment naming the version control system and branch,
+    /// e.g. `git:main`
+    pub(super) fn vcs_status(&self) -> Option<String> {
+        let file = self.vcs_file.as_ref()?;
+        let vcs = file.vcs.as_ref()?;
+        let branch = file.branch.as_ref()?;
+        Some(format!("{}:{}", vcs.name(), branch))
+    }
+
+    /// `:Blame`: show who last changed the cursor line, as the file is on disk
+    pub(super) fn blame_cursor_line(&mut self) {
+        self.update_vcs_file();
+        let Some(file) = &self.vcs_file else {
+            self.set_message("No file name".to_string(), MessageType::Error);
+            return;
+        };
+        let Some(vcs) = &file.vcs else {
+            self.set_message(
+                "Not in a version control repository".to_string(),
+                MessageType::Error,
+            );
+            return;
+        };
+        let line = self
+            .buffer_manager
+            .current()
+            .map_or(0, |buffer| buffer.cursor_line);
+        let (message, msg_type) = match vcs.blame(&file.path) {
+            Ok(lines) => match lines.get(line) {
+                Some(blame) if blame.author.is_empty() => {
+                    (blame.revision.clone(), MessageType::Info)
+                }
+                Some(blame) => (
+                    format!("{} {}", blame.revision, blame.author),
+                    MessageType::Info,
+                ),
+                None => (
+                    "Line not in the file on disk".to_string(),
+                    MessageType::Warning,
+                ),
+            },
+            Err(e) => (format!("{}: {}", vcs.name(), e), MessageType::Error),
+        };
+        self.set_message(message, msg_type);
+    }
+}
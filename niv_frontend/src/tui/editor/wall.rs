@@ -0,0 +1,117 @@
+use super::{Editor, MessageType};
+use niv_fs::SaveTransaction;
+use std::path::PathBuf;
+
+/// Most messages kept for `:messages`
+const MESSAGE_HISTORY: usize = 200;
+
+impl Editor {
+    /// `:wall`: write every modified buffer to its file. The files are
+    /// written together, so when one can't be written none of them is. Each
+    /// buffer that was not written gets a message of its own in the history
+    /// of `:messages`, under one that sums them up. Returns whether all the
+    /// modified buffers were written.
+    pub(super) fn write_all_buffers(&mut self) -> bool {
+        let mut transaction = SaveTransaction::new();
+        let mut paths = Vec::new();
+        let mut unnamed = Vec::new();
+        for (i, buffer) in self.buffer_manager.iter().enumerate() {
+            if !buffer.is_really_modified() {
+                continue;
+            }
+            match &buffer.file_path {
+                Some(path) => {
+                    let config = buffer.save_config();
+                    transaction.add(path, &buffer.content, &buffer.save_context, &config);
+                    paths.push(path.clone());
+                }
+                None => unnamed.push(i + 1),
+            }
+        }
+        if transaction.is_empty() && unnamed.is_empty() {
+            self.set_message("No modified buffers".to_string(), MessageType::Info);
+            return true;
+        }
+
+        // Our own writes are not external changes
+        for path in &paths {
+            self.file_watcher.suspend(path);
+        }
+        let result = transaction.commit();
+        for path in &paths {
+            let _ = self.file_watcher.resume(path);
+        }
+
+        let mut written: Vec<(PathBuf, String)> = Vec::new();
+        for saved in &result.saved {
+            if let Some(buffer) = self.buffer_manager.find_by_path_mut(&saved.path) {
+                buffer.mark_saved();
+                // A followed file grows from what was just written
+                if let Ok(metadata) = std::fs::metadata(&saved.path) {
+                    buffer.loaded_len = metadata.len();
+                }
+                written.push((saved.path.clone(), buffer.content.clone()));
+            }
+        }
+        for (path, content) in &written {
+            self.watch_file(path, content);
+        }
+
+        for number in &unnamed {
+            self.set_message(
+                format!("Buffer {} has no file name", number),
+                MessageType::Error,
+            );
+        }
+        for (_, e) in &result.failed {
+            self.set_message(e.to_string(), MessageType::Error);
+        }
+        for path in &result.rolled_back {
+            self.set_message(
+                format!("\"{}\" not written", path.display()),
+                MessageType::Warning,
+            );
+        }
+
+        let total = paths.len() + unnamed.len();
+        let not_written = total - result.saved.len();
+        if not_written == 0 {
+            self.set_message(format!("{} files written", total), MessageType::Success);
+            true
+        } else {
+            self.set_message(
+                format!(
+                    "{} of {} files not written (see :messages)",
+                    not_written, total
+                ),
+                MessageType::Error,
+            );
+            false
+        }
+    }
+
+    /// Keep `message` for `:messages`, dropping the oldest one once there
+    /// are `MESSAGE_HISTORY`
+    pub(super) fn record_message(&mut self, message: &str) {
+        if self.message_history.len() == MESSAGE_HISTORY {
+            self.message_history.pop_front();
+        }
+        self.message_history.push_back(message.to_string());
+    }
+
+    /// `:messages`: show the messages given so far in a scratch buffer
+    pub(super) fn show_messages(&mut self) {
+        if self.message_history.is_empty() {
+            self.set_message("No messages".to_string(), MessageType::Info);
+            return;
+        }
+        let mut report = self
+            .message_history
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+        report.push('\n');
+        self.show_report(&report);
+    }
+}
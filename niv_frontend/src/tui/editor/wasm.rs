@@ -0,0 +1,305 @@
+//! WebAssembly plugins (`wasm` feature).
+//!
+//! A plugin is a module importing host functions from the `niv` module. Strings
+//! are passed as pointer/length pairs into the plugin's exported `memory`.
+//! Functions return -1 when the plugin lacks the capability they need:
+//!
+//! - `buffer_read(ptr, len) -> total` (read): copy the current buffer; returns its full length
+//! - `cursor_line() -> line`, `cursor_col() -> col` (read)
+//! - `args_read(ptr, len) -> total`: arguments of the command being run
+//! - `edit(start, end, ptr, len) -> 0` (edit): replace a byte range of the buffer
+//! - `register_command(name_ptr, name_len, fn_ptr, fn_len) -> 0` (commands)
+//! - `map_key(key_ptr, key_len, cmd_ptr, cmd_len) -> 0` (keys)
+//! - `subscribe(event_ptr, event_len, fn_ptr, fn_len) -> 0` (events)
+//! - `message(ptr, len) -> 0`
+//!
+//! An exported `init` function is called once after loading. Registered
+//! functions take no parameters and return nothing.
+
+use super::plugins::PluginCall;
+use super::{Editor, MessageType};
+use crate::tui::plugin::{PluginCapability, PluginContext, PluginManifest, PluginRequest};
+use std::path::PathBuf;
+use wasmi::{Caller, Config, Engine, Extern, Instance, Linker, Module, Store};
+
+/// Instructions a single plugin call may run before it is stopped, so a
+/// looping plugin cannot hang the editor
+const FUEL_PER_CALL: u64 = 50_000_000;
+
+struct WasmPlugin {
+    manifest: PluginManifest,
+    store: Store<PluginContext>,
+    instance: Instance,
+}
+
+/// Loaded WebAssembly plugins
+pub(crate) struct WasmRuntime {
+    engine: Engine,
+    plugins: Vec<WasmPlugin>,
+}
+
+impl Default for WasmRuntime {
+    fn default() -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Self {
+            engine: Engine::new(&config),
+            plugins: Vec::new(),
+        }
+    }
+}
+
+impl Editor {
+    /// Load the enabled `.wasm` extensions and run their `init` functions
+    pub(crate) fn load_wasm_plugins(&mut self) {
+        let extensions = self.config_loader.get_copy().extensions;
+        for config in extensions.enabled_extensions() {
+            let manifest = match PluginManifest::from_extension(config, "wasm") {
+                Some(Ok(manifest)) => manifest,
+                Some(Err(e)) => {
+                    self.set_message(e, MessageType::Error);
+                    continue;
+                }
+                None => continue,
+            };
+            let path = self.find_plugin_module(&manifest, &extensions.directories);
+            match self.wasm_plugins.load(manifest, &path) {
+                Ok(index) => self.call_wasm(index, "init", ""),
+                Err(e) => self.set_message(e, MessageType::Error),
+            }
+        }
+    }
+
+    /// Resolve a module path: `~` is expanded and relative paths are looked up
+    /// in the extension directories
+    fn find_plugin_module(&self, manifest: &PluginManifest, directories: &[String]) -> PathBuf {
+        let module = super::cwd::expand_home(&manifest.module.to_string_lossy());
+        if module.is_absolute() {
+            return module;
+        }
+        directories
+            .iter()
+            .map(|dir| super::cwd::expand_home(dir).join(&module))
+            .find(|path| path.exists())
+            .unwrap_or(module)
+    }
+
+    /// Call an exported function of a loaded plugin with the current buffer
+    /// visible to it, then apply what it asked for
+    pub(crate) fn call_wasm(&mut self, plugin: usize, export: &str, args: &str) {
+        let Some(loaded) = self.wasm_plugins.plugins.get_mut(plugin) else {
+            return;
+        };
+        loaded
+            .store
+            .data_mut()
+            .prepare(self.buffer_manager.current(), args);
+
+        let result = match loaded
+            .instance
+            .get_typed_func::<(), ()>(&loaded.store, export)
+        {
+            Ok(func) => loaded
+                .store
+                .set_fuel(FUEL_PER_CALL)
+                .map_err(|e| e.to_string())
+                .and_then(|()| func.call(&mut loaded.store, ()).map_err(|e| e.to_string())),
+            // `init` is optional
+            Err(_) if export == "init" => Ok(()),
+            Err(_) => Err(format!("no function \"{}\"", export)),
+        };
+
+        let name = loaded.manifest.name.clone();
+        let requests = std::mem::take(&mut loaded.store.data_mut().requests);
+        // Changes made before a trap still apply, as with an ex command that fails halfway
+        self.apply_plugin_requests(&name, requests, |export| PluginCall::Wasm {
+            plugin,
+            export: export.to_string(),
+        });
+        if let Err(e) = result {
+            self.set_message(format!("{}: {}", name, e), MessageType::Error);
+        }
+    }
+}
+
+impl WasmRuntime {
+    /// Compile and instantiate a plugin, returning its index
+    fn load(&mut self, manifest: PluginManifest, path: &std::path::Path) -> Result<usize, String> {
+        let error = |e: &dyn std::fmt::Display| format!("{}: {}", manifest.name, e);
+        let bytes = std::fs::read(path).map_err(|e| error(&e))?;
+        let module = Module::new(&self.engine, &bytes).map_err(|e| error(&e))?;
+
+        let mut store = Store::new(
+            &self.engine,
+            PluginContext::new(manifest.capabilities.clone()),
+        );
+        let linker = host_functions(&self.engine).map_err(|e| error(&e))?;
+        store.set_fuel(FUEL_PER_CALL).map_err(|e| error(&e))?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|pre| pre.start(&mut store))
+            .map_err(|e| error(&e))?;
+
+        self.plugins.push(WasmPlugin {
+            manifest,
+            store,
+            instance,
+        });
+        Ok(self.plugins.len() - 1)
+    }
+}
+
+/// Read a UTF-8 string from the plugin's memory
+fn read_string(caller: &Caller<'_, PluginContext>, ptr: i32, len: i32) -> Option<String> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    let mut bytes = vec![0; usize::try_from(len).ok()?];
+    memory
+        .read(caller, usize::try_from(ptr).ok()?, &mut bytes)
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+/// Copy as much of `text` as fits into the plugin's buffer; returns the full length
+fn write_string(caller: &mut Caller<'_, PluginContext>, text: &str, ptr: i32, len: i32) -> i32 {
+    let Some(memory) = caller.get_export("memory").and_then(Extern::into_memory) else {
+        return -1;
+    };
+    let (Ok(ptr), Ok(len)) = (usize::try_from(ptr), usize::try_from(len)) else {
+        return -1;
+    };
+    let count = len.min(text.len());
+    if memory
+        .write(&mut *caller, ptr, &text.as_bytes()[..count])
+        .is_err()
+    {
+        return -1;
+    }
+    i32::try_from(text.len()).unwrap_or(i32::MAX)
+}
+
+/// Queue a request built from two strings, if `capability` was granted
+fn request_with_strings(
+    caller: &mut Caller<'_, PluginContext>,
+    capability: PluginCapability,
+    (a_ptr, a_len, b_ptr, b_len): (i32, i32, i32, i32),
+    build: impl FnOnce(String, String) -> PluginRequest,
+) -> i32 {
+    if !caller.data_mut().check(capability) {
+        return -1;
+    }
+    let (Some(a), Some(b)) = (
+        read_string(caller, a_ptr, a_len),
+        read_string(caller, b_ptr, b_len),
+    ) else {
+        return -1;
+    };
+    caller.data_mut().requests.push(build(a, b));
+    0
+}
+
+/// The `niv` host module imported by plugins
+fn host_functions(engine: &Engine) -> Result<Linker<PluginContext>, wasmi::errors::LinkerError> {
+    use PluginCapability::*;
+
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        "niv",
+        "buffer_read",
+        |mut caller: Caller<'_, PluginContext>, ptr: i32, len: i32| -> i32 {
+            if !caller.data_mut().check(ReadBuffer) {
+                return -1;
+            }
+            let text = caller.data().text.clone();
+            write_string(&mut caller, &text, ptr, len)
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "cursor_line",
+        |mut caller: Caller<'_, PluginContext>| -> i32 {
+            if !caller.data_mut().check(ReadBuffer) {
+                return -1;
+            }
+            i32::try_from(caller.data().cursor_line).unwrap_or(i32::MAX)
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "cursor_col",
+        |mut caller: Caller<'_, PluginContext>| -> i32 {
+            if !caller.data_mut().check(ReadBuffer) {
+                return -1;
+            }
+            i32::try_from(caller.data().cursor_col).unwrap_or(i32::MAX)
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "args_read",
+        |mut caller: Caller<'_, PluginContext>, ptr: i32, len: i32| -> i32 {
+            let args = caller.data().args.clone();
+            write_string(&mut caller, &args, ptr, len)
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "edit",
+        |mut caller: Caller<'_, PluginContext>, start: i32, end: i32, ptr: i32, len: i32| -> i32 {
+            if !caller.data_mut().check(EditBuffer) {
+                return -1;
+            }
+            let (Ok(start), Ok(end)) = (usize::try_from(start), usize::try_from(end)) else {
+                return -1;
+            };
+            let Some(text) = read_string(&caller, ptr, len) else {
+                return -1;
+            };
+            caller.data_mut().edit(start, end, text);
+            0
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "register_command",
+        |mut caller: Caller<'_, PluginContext>, a: i32, b: i32, c: i32, d: i32| -> i32 {
+            request_with_strings(&mut caller, Commands, (a, b, c, d), |name, export| {
+                PluginRequest::RegisterCommand { name, export }
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "map_key",
+        |mut caller: Caller<'_, PluginContext>, a: i32, b: i32, c: i32, d: i32| -> i32 {
+            request_with_strings(&mut caller, Keybindings, (a, b, c, d), |keys, command| {
+                PluginRequest::MapKey { keys, command }
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "subscribe",
+        |mut caller: Caller<'_, PluginContext>, a: i32, b: i32, c: i32, d: i32| -> i32 {
+            request_with_strings(&mut caller, Events, (a, b, c, d), |event, export| {
+                PluginRequest::Subscribe { event, export }
+            })
+        },
+    )?;
+    linker.func_wrap(
+        "niv",
+        "message",
+        |mut caller: Caller<'_, PluginContext>, ptr: i32, len: i32| -> i32 {
+            match read_string(&caller, ptr, len) {
+                Some(message) => {
+                    caller
+                        .data_mut()
+                        .requests
+                        .push(PluginRequest::Message(message));
+                    0
+                }
+                None => -1,
+            }
+        },
+    )?;
+    Ok(linker)
+}
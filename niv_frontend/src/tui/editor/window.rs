@@ -0,0 +1,98 @@
+use super::{Editor, MessageType};
+
+/// The second window of `:vsplit`: the current buffer once more, right of
+/// the text area, with a cursor and scroll position of its own
+pub(super) struct SplitWindow {
+    /// Index of the buffer both windows show
+    buffer: usize,
+    cursor_line: usize,
+    cursor_col: usize,
+    pub(super) scroll_line: usize,
+    pub(super) scroll_col: usize,
+    /// Top line of the window with the cursor when last looked at, to
+    /// scroll this one by as much with `scrollbind`
+    focused_scroll: usize,
+}
+
+impl Editor {
+    /// `:vsplit`: toggle a second window on the current buffer right of the
+    /// text area. `Ctrl+w w` moves the cursor to the other window, which
+    /// then shows on the left, as the text area is the window being edited.
+    pub(super) fn toggle_split_window(&mut self) {
+        if self.split_window.take().is_some() {
+            self.show_split(false);
+            return;
+        }
+        let Some(buffer) = self.buffer_manager.current() else {
+            self.set_message("No buffer".to_string(), MessageType::Warning);
+            return;
+        };
+        self.split_window = Some(SplitWindow {
+            buffer: self.buffer_manager.current_index(),
+            cursor_line: buffer.cursor_line,
+            cursor_col: buffer.cursor_col,
+            scroll_line: buffer.scroll_line,
+            scroll_col: buffer.scroll_col,
+            focused_scroll: buffer.scroll_line,
+        });
+        // The split shows one of the preview, `:DiffOrig` and the window
+        self.markdown_preview = None;
+        self.diff_view = None;
+        self.show_split(true);
+    }
+
+    /// `Ctrl+w w`: edit in the other window, its cursor and scroll position
+    /// trading places with the text area's
+    pub(super) fn switch_window(&mut self) {
+        let (Some(split), Some(buffer)) =
+            (&mut self.split_window, self.buffer_manager.current_mut())
+        else {
+            return;
+        };
+        let (line, col, scroll_line, scroll_col) = (
+            buffer.cursor_line,
+            buffer.cursor_col,
+            buffer.scroll_line,
+            buffer.scroll_col,
+        );
+        buffer.scroll_line = split.scroll_line;
+        buffer.scroll_col = split.scroll_col;
+        buffer.set_cursor(split.cursor_line, split.cursor_col);
+        split.cursor_line = line;
+        split.cursor_col = col;
+        split.scroll_line = scroll_line;
+        split.scroll_col = scroll_col;
+        split.focused_scroll = buffer.scroll_line;
+        self.render_state.mark_all_dirty();
+    }
+
+    /// Close the split window once another buffer is current, keep its
+    /// cursor in the buffer after edits, and with `scrollbind` scroll it by
+    /// as many lines as the text area scrolled
+    pub(super) fn update_split_window(&mut self) {
+        let Some(split) = &mut self.split_window else {
+            return;
+        };
+        let Some(buffer) = self
+            .buffer_manager
+            .current()
+            .filter(|_| self.buffer_manager.current_index() == split.buffer)
+        else {
+            self.split_window = None;
+            self.show_split(false);
+            return;
+        };
+        let last_line = buffer.content.lines().count().saturating_sub(1);
+        let height = self.layout_manager.get_layout().text_area_height as usize;
+        if self.scroll_bind && buffer.scroll_line != split.focused_scroll {
+            let scrolled = buffer.scroll_line as isize - split.focused_scroll as isize;
+            split.scroll_line = split.scroll_line.saturating_add_signed(scrolled);
+            // Keep the cursor in view, like scrolling with Ctrl+e and Ctrl+y
+            let bottom = split.scroll_line + height.saturating_sub(1);
+            split.cursor_line = split.cursor_line.clamp(split.scroll_line, bottom);
+        }
+        split.focused_scroll = buffer.scroll_line;
+        split.scroll_line = split.scroll_line.min(last_line);
+        split.cursor_line = split.cursor_line.min(last_line);
+    }
+}
@@ -0,0 +1,113 @@
+use super::{Editor, EditorMode, MessageType};
+use crate::tui::killring::Yank;
+use crate::tui::palette::{Palette, PaletteAction, PaletteItem};
+
+/// Line count from which yanking or deleting lines is reported, like vim's
+/// default `report`
+const REPORT_LINES: usize = 3;
+
+impl Editor {
+    /// `y` and `d` in visual mode: put the selected lines in the kill ring,
+    /// and with `delete` take them out of the buffer
+    pub(super) fn yank_visual_lines(&mut self, delete: bool) {
+        self.remember_visual_selection();
+        self.mode = EditorMode::Normal;
+        self.render_state.status_line_dirty = true;
+        let Some(range) = self.last_visual else {
+            return;
+        };
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        let lines: Vec<&str> = buffer
+            .content
+            .split('\n')
+            .skip(range.start)
+            .take(range.end - range.start + 1)
+            .collect();
+        let count = lines.len();
+        let text = lines.join("\n");
+        if delete {
+            buffer.replace_lines(range.start, range.end, "");
+            self.render_state.mark_text_dirty();
+        } else {
+            buffer.set_cursor(range.start, 0);
+            self.render_state.cursor_dirty = true;
+        }
+        self.kill_ring.push(Yank {
+            text,
+            linewise: true,
+        });
+        if count >= REPORT_LINES {
+            let message = match delete {
+                true => format!("{} fewer lines", count),
+                false => format!("{} lines yanked", count),
+            };
+            self.set_message(message, MessageType::Info);
+        }
+    }
+
+    /// Put text deleted by a select mode key in the kill ring
+    pub(super) fn kill_text(&mut self, text: String) {
+        self.kill_ring.push(Yank {
+            text,
+            linewise: false,
+        });
+    }
+
+    /// `p` and `P`: paste kill ring entry `index` after or before the
+    /// cursor, or below or above the cursor line for whole lines. The entry
+    /// becomes the newest.
+    pub(super) fn paste_yank(&mut self, index: usize, after: bool) {
+        let Some(yank) = self.kill_ring.get(index).cloned() else {
+            self.set_message("Nothing to paste".to_string(), MessageType::Error);
+            return;
+        };
+        self.kill_ring.promote(index);
+        let Some(buffer) = self.buffer_manager.current_mut() else {
+            return;
+        };
+        if yank.linewise {
+            let line = buffer.cursor_line;
+            let current = buffer.content.split('\n').nth(line).unwrap_or("");
+            // The trailing newline keeps a pasted empty last line
+            let text = match after {
+                true => format!("{}\n{}\n", current, yank.text),
+                false => format!("{}\n{}\n", yank.text, current),
+            };
+            buffer.replace_lines(line, line, &text);
+            buffer.set_cursor(if after { line + 1 } else { line }, 0);
+        } else {
+            if after {
+                buffer.move_cursor_right();
+            }
+            buffer.insert_text(&yank.text);
+            // On the last pasted character, as in vim
+            buffer.move_cursor_left();
+        }
+        self.render_state.mark_text_dirty();
+    }
+
+    /// `"` in normal mode and `:Yanks`: pick a recent yank or delete in the
+    /// palette and paste it after the cursor
+    pub(super) fn open_yank_picker(&mut self) {
+        if self.kill_ring.is_empty() {
+            self.set_message("Nothing yanked yet".to_string(), MessageType::Info);
+            return;
+        }
+        let max = self.layout_manager.get_layout().width as usize / 3;
+        let items = self
+            .kill_ring
+            .iter()
+            .enumerate()
+            .map(|(index, yank)| PaletteItem {
+                label: yank.preview(max),
+                keys: String::new(),
+                description: yank.summary(),
+                action: PaletteAction::Paste(index),
+            })
+            .collect();
+        self.palette = Some(Palette::new(items));
+        self.render_state.mark_all_dirty();
+    }
+}
@@ -0,0 +1,81 @@
+use niv_fs::{expand_braces, glob_match};
+use std::path::Path;
+
+/// Name of the files looked up in a file's directory and those above it
+const EDITORCONFIG: &str = ".editorconfig";
+
+/// Value of `property` for the file at `path` from the `.editorconfig` files
+/// in its directory and above, up to one marked `root = true`; closer files
+/// win over those further up
+pub fn editorconfig_property(path: &Path, property: &str) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    for dir in path.ancestors().skip(1) {
+        let Ok(text) = std::fs::read_to_string(dir.join(EDITORCONFIG)) else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let (value, root) = parse_editorconfig(&text, &relative, property);
+        if value.is_some() || root {
+            return value;
+        }
+    }
+    None
+}
+
+/// `max_line_length` of the file at `path`; `Some(0)` when it is `off`
+pub fn editorconfig_max_line_length(path: &Path) -> Option<usize> {
+    let value = editorconfig_property(path, "max_line_length")?;
+    match value.as_str() {
+        "off" => Some(0),
+        _ => value.parse().ok(),
+    }
+}
+
+/// Value of `property` in the sections of an `.editorconfig` file's `text`
+/// that match `relative`, the `/`-separated path of a file from the
+/// directory of the `.editorconfig`, with the last matching section
+/// winning; and whether the file is marked `root = true`
+pub fn parse_editorconfig(text: &str, relative: &str, property: &str) -> (Option<String>, bool) {
+    let mut value = None;
+    let mut root = false;
+    // Properties before the first section are the preamble, where only
+    // `root` means anything
+    let mut section: Option<bool> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = Some(section_matches(pattern, relative));
+            continue;
+        }
+        let Some((key, val)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let val = val.trim().to_ascii_lowercase();
+        match section {
+            None if key == "root" => root = val == "true",
+            Some(true) if key == property => value = Some(val),
+            _ => {}
+        }
+    }
+    (value, root)
+}
+
+/// Whether a section header's glob matches `relative`: patterns without a
+/// `/` match the file name in any directory, others the whole path
+fn section_matches(pattern: &str, relative: &str) -> bool {
+    let pattern = if pattern.contains('/') {
+        pattern.trim_start_matches('/').to_string()
+    } else {
+        format!("**/{}", pattern)
+    };
+    expand_braces(&pattern)
+        .iter()
+        .any(|pattern| glob_match(pattern, relative))
+}
@@ -0,0 +1,235 @@
+use std::path::PathBuf;
+
+/// A built-in ex command, for listings such as the command palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExCommand {
+    pub name: &'static str,
+    /// Shortest accepted abbreviation, if any
+    pub short: Option<&'static str>,
+    /// Arguments in usage notation (`{file}` required, `[dir]` optional);
+    /// empty when the command takes none
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+const fn ex(
+    name: &'static str,
+    short: Option<&'static str>,
+    args: &'static str,
+    description: &'static str,
+) -> ExCommand {
+    ExCommand {
+        name,
+        short,
+        args,
+        description,
+    }
+}
+
+/// Ex commands handled by the editor itself; keep in sync with `run_command`
+#[rustfmt::skip]
+pub const EX_COMMANDS: &[ExCommand] = &[
+    ex("write", Some("w"), "[file]", "Write the buffer to its file or another"),
+    ex("update", Some("up"), "", "Write the buffer if it was modified"),
+    ex("wq", Some("x"), "", "Write the buffer and quit"),
+    ex("quit", Some("q"), "", "Quit"),
+    ex("quit!", Some("q!"), "", "Quit, discarding changes"),
+    ex("edit", Some("e"), "[file]", "Reload the current file or open another"),
+    ex("edit!", Some("e!"), "", "Discard changes and reload the file"),
+    ex("checktime", Some("checkt"), "", "Check open files for changes on disk"),
+    ex("tail", None, "", "Follow lines appended to the file"),
+    ex("Rename", None, "{name}", "Rename the current file"),
+    ex("Move", None, "{path}", "Move the current file"),
+    ex("setlocal", Some("setl"), "{option}", "Set an option for the current buffer"),
+    ex("CsvAlign", None, "", "Line up the fields of a CSV or TSV file in columns"),
+    ex("MarkdownPreview", None, "", "Show the buffer rendered as Markdown in a split"),
+    ex("DiffOrig", None, "", "Compare the buffer with its file on disk in a split"),
+    ex("Blame", None, "", "Show who last changed the cursor line"),
+    ex("vsplit", Some("vs"), "", "Show the buffer in a second window beside the first"),
+    ex("args", Some("ar"), "[files]", "Show or set the argument list"),
+    ex("next", Some("n"), "", "Edit the next file in the argument list"),
+    ex("previous", Some("N"), "", "Edit the previous file in the argument list"),
+    ex("first", Some("fir"), "", "Edit the first file in the argument list"),
+    ex("last", Some("la"), "", "Edit the last file in the argument list"),
+    ex("argdo", None, "{command}", "Run a command in each file of the argument list"),
+    ex("bdelete", Some("bd"), "", "Close the current buffer"),
+    ex("bdelete!", Some("bd!"), "", "Close the current buffer, discarding changes"),
+    ex("reopen", None, "", "Reopen the last closed buffer"),
+    ex("palette", None, "", "Open the command palette (F1)"),
+    ex("Yanks", None, "", "Pick a recent yank or delete to paste (\")"),
+    ex("!", None, "{command}", "Run a shell command"),
+    ex("pwd", None, "", "Show the working directory"),
+    ex("cd", None, "[dir]", "Change the working directory"),
+    ex("lcd", None, "[dir]", "Change the working directory of the current buffer"),
+    ex("log", None, "", "Open the log file"),
+    ex("profile", None, "{start|stop|report}", "Time frames and commands"),
+    #[cfg(feature = "lua")]
+    ex("lua", None, "{code}", "Run Lua code"),
+];
+
+/// Inclusive range of buffer lines (0-based) given to an ex command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LineRange {
+    /// Range covering the whole buffer
+    pub fn whole(line_count: usize) -> Self {
+        Self {
+            start: 0,
+            end: line_count.saturating_sub(1),
+        }
+    }
+
+    pub fn is_whole(&self, line_count: usize) -> bool {
+        *self == Self::whole(line_count)
+    }
+}
+
+/// Split a leading line range off an ex command.
+///
+/// Supports `%`, `N`, `.`, `$`, `'<`/`'>`, `+N`/`-N` offsets and `a,b` pairs,
+/// with 1-based line numbers as typed by the user. `current_line` is 0-based;
+/// `visual` is the last visual selection, used by `'<` and `'>`.
+pub fn parse_range(
+    command: &str,
+    current_line: usize,
+    line_count: usize,
+    visual: Option<LineRange>,
+) -> Result<(Option<LineRange>, &str), String> {
+    if let Some(rest) = command.strip_prefix('%') {
+        return Ok((Some(LineRange::whole(line_count)), rest));
+    }
+
+    let (start, rest) = parse_address(command, current_line, line_count, visual)?;
+    let Some(start) = start else {
+        return Ok((None, command));
+    };
+    let (end, rest) = match rest.strip_prefix(',') {
+        Some(after_comma) => {
+            let (end, rest) = parse_address(after_comma, current_line, line_count, visual)?;
+            (end.unwrap_or(current_line), rest)
+        }
+        None => (start, rest),
+    };
+
+    if start > end {
+        return Err("Backwards range given".to_string());
+    }
+    if end >= line_count.max(1) {
+        return Err("Invalid range".to_string());
+    }
+    Ok((Some(LineRange { start, end }), rest))
+}
+
+/// Parse one line address, returning the 0-based line and the remaining text
+fn parse_address(
+    text: &str,
+    current_line: usize,
+    line_count: usize,
+    visual: Option<LineRange>,
+) -> Result<(Option<usize>, &str), String> {
+    let digits_end = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+
+    let (base, mut rest) = if let Some(rest) = text.strip_prefix('.') {
+        (Some(current_line as i64), rest)
+    } else if let Some(rest) = text.strip_prefix('$') {
+        (Some(line_count.saturating_sub(1) as i64), rest)
+    } else if let Some(rest) = text.strip_prefix("'<") {
+        let visual = visual.ok_or_else(|| "Mark not set".to_string())?;
+        (Some(visual.start as i64), rest)
+    } else if let Some(rest) = text.strip_prefix("'>") {
+        let visual = visual.ok_or_else(|| "Mark not set".to_string())?;
+        (Some(visual.end as i64), rest)
+    } else {
+        let end = digits_end(text);
+        if end > 0 {
+            let number: i64 = text[..end]
+                .parse()
+                .map_err(|_| "Invalid range".to_string())?;
+            // Line 0 means "before the first line"; clamp it to the first line
+            (Some((number - 1).max(0)), &text[end..])
+        } else if text.starts_with(['+', '-']) {
+            (None, text)
+        } else {
+            return Ok((None, text));
+        }
+    };
+
+    let mut line = base.unwrap_or(current_line as i64);
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let after = &rest[1..];
+        let end = digits_end(after);
+        let offset: i64 = if end == 0 {
+            1
+        } else {
+            after[..end].parse().unwrap_or(0)
+        };
+        line += if sign == '+' { offset } else { -offset };
+        rest = &after[end..];
+    }
+
+    if line < 0 {
+        return Err("Invalid range".to_string());
+    }
+    Ok((Some(line as usize), rest))
+}
+
+/// Parsed `:[range]w[rite][!] [>>] [file]` command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteCommand {
+    pub range: Option<LineRange>,
+    pub force: bool,
+    pub append: bool,
+    pub path: Option<PathBuf>,
+}
+
+/// Parse the part of a write command after its range; `None` if it is not a write
+pub fn parse_write_command(range: Option<LineRange>, command: &str) -> Option<WriteCommand> {
+    let rest = command
+        .strip_prefix("write")
+        .or_else(|| command.strip_prefix("w"))?;
+    if !(rest.is_empty() || rest.starts_with(['!', ' ', '>'])) {
+        return None;
+    }
+
+    let (force, rest) = match rest.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let rest = rest.trim_start();
+    let (append, rest) = match rest.strip_prefix(">>") {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, rest),
+    };
+    let path = (!rest.is_empty()).then(|| PathBuf::from(rest.trim_end()));
+
+    Some(WriteCommand {
+        range,
+        force,
+        append,
+        path,
+    })
+}
+
+/// Split the first command off a `|`-separated command line.
+///
+/// `\|` is kept as a literal bar. Returns the first command and, if there was a
+/// separator, the rest of the line.
+pub fn split_bar(line: &str) -> (String, Option<&str>) {
+    let mut command = String::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' if matches!(chars.peek(), Some((_, '|'))) => {
+                command.push('|');
+                chars.next();
+            }
+            '|' => return (command, Some(&line[i + 1..])),
+            _ => command.push(ch),
+        }
+    }
+    (command, None)
+}
@@ -0,0 +1,92 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often a running command checks whether it was interrupted
+const WAIT_STEP: Duration = Duration::from_millis(10);
+
+/// Run `command` through the shell with `input` on its stdin and return its stdout.
+///
+/// A non-zero exit status is reported as an error carrying the command's stderr.
+/// The command is killed, with an `Interrupted` error, once `interrupted`
+/// returns true.
+pub fn run_filter(
+    command: &str,
+    input: &str,
+    cwd: &Path,
+    interrupted: impl Fn() -> bool,
+) -> io::Result<String> {
+    let mut child = shell_command(command)
+        .current_dir(cwd)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from another thread so a command writing a lot of output
+    // before reading all of its input cannot deadlock us
+    let writer = child.stdin.take().map(|mut stdin| {
+        let input = input.to_string();
+        thread::spawn(move || stdin.write_all(input.as_bytes()))
+    });
+    let stdout = child.stdout.take().map(read_in_thread);
+    let stderr = child.stderr.take().map(read_in_thread);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if interrupted() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "Interrupted"));
+        }
+        thread::sleep(WAIT_STEP);
+    };
+    let stdout = join_output(stdout)?;
+    let stderr = join_output(stderr)?;
+    if let Some(Ok(Err(e))) = writer.map(|writer| writer.join()) {
+        // Commands that ignore their input close the pipe early
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            return Err(e);
+        }
+    }
+
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let reason = match stderr.trim() {
+            "" => status.to_string(),
+            stderr => stderr.to_string(),
+        };
+        return Err(io::Error::other(reason));
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Collect a pipe on another thread, so a full pipe never blocks the command
+fn read_in_thread(mut pipe: impl Read + Send + 'static) -> JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        pipe.read_to_end(&mut output).map(|_| output)
+    })
+}
+
+fn join_output(reader: Option<JoinHandle<io::Result<Vec<u8>>>>) -> io::Result<Vec<u8>> {
+    match reader.map(JoinHandle::join) {
+        Some(Ok(output)) => output,
+        Some(Err(_)) => Err(io::Error::other("output reader panicked")),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+
+    let mut cmd = Command::new(shell);
+    cmd.arg(flag).arg(command);
+    cmd
+}
@@ -0,0 +1,118 @@
+//! Ctrl+C interrupts for long-running operations.
+//!
+//! In raw mode Ctrl+C reaches the editor as a key rather than SIGINT, and
+//! keys are only read between operations. [`interrupted`] therefore also
+//! looks at pending terminal input, keeping any other events for the main
+//! loop to handle afterwards. SIGINT, e.g. from `kill -INT`, sets the same
+//! flag once [`install_interrupt_handler`] was called.
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use niv_fs::{Encoding, FileIdentity, FileLoadConfig, FileLoadResult, FileReloadResult, FsError};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+
+static INTERRUPTED: LazyLock<Arc<AtomicBool>> = LazyLock::new(Arc::default);
+/// Whether terminal input may be read while looking for Ctrl+C
+static POLL_INPUT: AtomicBool = AtomicBool::new(false);
+/// Events read while looking for Ctrl+C, oldest first
+static PENDING_EVENTS: Mutex<VecDeque<Event>> = Mutex::new(VecDeque::new());
+
+/// Set the interrupt flag on SIGINT instead of terminating. Only needed on
+/// Unix; elsewhere Ctrl+C is only seen as a key.
+pub fn install_interrupt_handler() {
+    #[cfg(unix)]
+    if let Err(e) =
+        signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&INTERRUPTED))
+    {
+        niv_fs::log!(Warn, "input", "can't handle SIGINT: {}", e);
+    }
+}
+
+/// Let [`interrupted`] read terminal input; only while the editor owns the
+/// terminal
+pub fn set_interrupt_polling(enabled: bool) {
+    POLL_INPUT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether `key` is Ctrl+C
+pub fn is_interrupt_key(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c')
+        && key.modifiers.contains(KeyModifiers::CONTROL)
+        && key.kind != KeyEventKind::Release
+}
+
+/// Ask running operations to stop
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether Ctrl+C was pressed or SIGINT received since the flag was last
+/// cleared. Long-running operations call this between steps and stop early
+/// when it returns true.
+pub fn interrupted() -> bool {
+    if POLL_INPUT.load(Ordering::Relaxed) {
+        while let Ok(true) = event::poll(Duration::ZERO) {
+            let Ok(event) = event::read() else {
+                break;
+            };
+            match &event {
+                Event::Key(key) if is_interrupt_key(key) => interrupt(),
+                _ => {
+                    if let Ok(mut pending) = PENDING_EVENTS.lock() {
+                        pending.push_back(event);
+                    }
+                }
+            }
+        }
+    }
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+/// Clear the interrupt flag, returning whether it was set
+pub fn clear_interrupt() -> bool {
+    INTERRUPTED.swap(false, Ordering::Relaxed)
+}
+
+/// Next event read by [`interrupted`] that the main loop hasn't handled yet
+pub fn take_pending_event() -> Option<Event> {
+    PENDING_EVENTS.lock().ok()?.pop_front()
+}
+
+/// Put `event` back to be handled before any other pending event
+pub fn defer_event(event: Event) {
+    if let Ok(mut pending) = PENDING_EVENTS.lock() {
+        pending.push_front(event);
+    }
+}
+
+/// Load a file, stopping early on Ctrl+C
+pub fn load_file_interruptible(path: &Path) -> Result<FileLoadResult, FsError> {
+    niv_fs::load_file_with_config(path, &interruptible_config())
+}
+
+/// Reload a file, reading only what was appended when it just grew. A full
+/// load stops early on Ctrl+C.
+pub fn reload_incremental_interruptible(
+    path: &Path,
+    identity: &FileIdentity,
+    prev_len: u64,
+    encoding: Encoding,
+) -> Result<FileReloadResult, FsError> {
+    niv_fs::reload_incremental_with_config(
+        path,
+        identity,
+        prev_len,
+        encoding,
+        &interruptible_config(),
+    )
+}
+
+fn interruptible_config() -> FileLoadConfig {
+    FileLoadConfig {
+        interrupt: Some(interrupted),
+        ..FileLoadConfig::default()
+    }
+}
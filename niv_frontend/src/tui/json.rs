@@ -0,0 +1,402 @@
+use std::fmt;
+
+/// A JSON value, as exchanged with RPC clients
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Members in the order they were written
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Build an object from `(key, value)` pairs
+    pub fn object<K: Into<String>>(members: impl IntoIterator<Item = (K, JsonValue)>) -> Self {
+        Self::Object(
+            members
+                .into_iter()
+                .map(|(key, value)| (key.into(), value))
+                .collect(),
+        )
+    }
+
+    /// Member of an object
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            Self::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    /// Non-negative integer value
+    pub fn as_usize(&self) -> Option<usize> {
+        match self {
+            Self::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n <= usize::MAX as f64 => {
+                Some(*n as usize)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse a complete JSON document
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: text.char_indices().peekable(),
+            depth: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some((i, _)) => Err(format!("trailing characters at {}", i)),
+        }
+    }
+}
+
+impl From<&str> for JsonValue {
+    fn from(s: &str) -> Self {
+        Self::String(s.to_string())
+    }
+}
+
+impl From<String> for JsonValue {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<usize> for JsonValue {
+    fn from(n: usize) -> Self {
+        Self::Number(n as f64)
+    }
+}
+
+impl From<bool> for JsonValue {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl<T: Into<JsonValue>> From<Option<T>> for JsonValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl fmt::Display for JsonValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Number(n) if n.is_finite() => write!(f, "{}", n),
+            // JSON has no NaN or infinity
+            Self::Number(_) => write!(f, "null"),
+            Self::String(s) => write_string(f, s),
+            Self::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Self::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            ch if (ch as u32) < 0x20 => write!(f, "\\u{:04x}", ch as u32)?,
+            ch => write!(f, "{}", ch)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Deepest nesting of arrays and objects parsed, so that a client can't
+/// overflow the stack of the thread reading it
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    /// Arrays and objects the parser is inside of
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .chars
+            .next_if(|(_, ch)| matches!(ch, ' ' | '\t' | '\n' | '\r'))
+            .is_some()
+        {}
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some((_, ch)) if ch == expected => Ok(()),
+            Some((i, ch)) => Err(format!("expected '{}' at {}, found '{}'", expected, i, ch)),
+            None => Err(format!("expected '{}' at end of input", expected)),
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: JsonValue) -> Result<JsonValue, String> {
+        for expected in word.chars() {
+            self.expect(expected)?;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+        let Some(&(i, ch)) = self.chars.peek() else {
+            return Err("unexpected end of input".to_string());
+        };
+        match ch {
+            'n' => self.literal("null", JsonValue::Null),
+            't' => self.literal("true", JsonValue::Bool(true)),
+            'f' => self.literal("false", JsonValue::Bool(false)),
+            '"' => self.string().map(JsonValue::String),
+            '[' => self.nested(Self::array),
+            '{' => self.nested(Self::object),
+            '-' | '0'..='9' => self.number(),
+            _ => Err(format!("unexpected '{}' at {}", ch, i)),
+        }
+    }
+
+    /// Parse an array or object one level deeper
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<JsonValue, String>,
+    ) -> Result<JsonValue, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("nested deeper than {}", MAX_DEPTH));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let mut text = String::new();
+        while let Some((_, ch)) = self
+            .chars
+            .next_if(|(_, ch)| matches!(ch, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+        {
+            text.push(ch);
+        }
+        text.parse()
+            .map(JsonValue::Number)
+            .map_err(|_| format!("invalid number: {}", text))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(s),
+                Some((_, '\\')) => s.push(self.escape()?),
+                Some((_, ch)) => s.push(ch),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, String> {
+        match self.chars.next() {
+            Some((_, '"')) => Ok('"'),
+            Some((_, '\\')) => Ok('\\'),
+            Some((_, '/')) => Ok('/'),
+            Some((_, 'b')) => Ok('\u{8}'),
+            Some((_, 'f')) => Ok('\u{c}'),
+            Some((_, 'n')) => Ok('\n'),
+            Some((_, 'r')) => Ok('\r'),
+            Some((_, 't')) => Ok('\t'),
+            Some((_, 'u')) => {
+                let high = self.hex4()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return Ok(char::from_u32(high).unwrap_or('\u{fffd}'));
+                }
+                // Characters outside the BMP come as a surrogate pair
+                self.expect('\\')?;
+                self.expect('u')?;
+                let low = self.hex4()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return Err("invalid surrogate pair".to_string());
+                }
+                let code = 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00);
+                Ok(char::from_u32(code).unwrap_or('\u{fffd}'))
+            }
+            Some((i, ch)) => Err(format!("invalid escape '\\{}' at {}", ch, i)),
+            None => Err("unterminated string".to_string()),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self
+                .chars
+                .next()
+                .and_then(|(_, ch)| ch.to_digit(16))
+                .ok_or_else(|| "invalid \\u escape".to_string())?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == ']').is_some() {
+            return Ok(JsonValue::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, ']')) => return Ok(JsonValue::Array(items)),
+                _ => return Err("expected ',' or ']'".to_string()),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.chars.next_if(|(_, ch)| *ch == '}').is_some() {
+            return Ok(JsonValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => {}
+                Some((_, '}')) => return Ok(JsonValue::Object(members)),
+                _ => return Err("expected ',' or '}'".to_string()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strings_round_trip_with_escapes() {
+        let parsed = JsonValue::parse(r#""a\"b\\c\/\n\t\u0001\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(parsed, JsonValue::from("a\"b\\c/\n\t\u{1}é😀"));
+        assert_eq!(parsed.to_string(), r#""a\"b\\c/\n\t\u0001é😀""#);
+        assert_eq!(JsonValue::parse(&parsed.to_string()).unwrap(), parsed);
+
+        // A high surrogate needs a low one after it
+        assert!(JsonValue::parse(r#""\ud83d""#).is_err());
+        assert!(JsonValue::parse(r#""\ud83d\u0041""#).is_err());
+        assert!(JsonValue::parse(r#""\x""#).is_err());
+        assert!(JsonValue::parse(r#""open"#).is_err());
+    }
+
+    #[test]
+    fn test_numbers_and_literals() {
+        let parsed = JsonValue::parse(" [0, -12, 3.5, 1e3, true, false, null] ").unwrap();
+        let expected = JsonValue::Array(vec![
+            JsonValue::Number(0.0),
+            JsonValue::Number(-12.0),
+            JsonValue::Number(3.5),
+            JsonValue::Number(1000.0),
+            JsonValue::Bool(true),
+            JsonValue::Bool(false),
+            JsonValue::Null,
+        ]);
+        assert_eq!(parsed, expected);
+        assert_eq!(parsed.to_string(), "[0,-12,3.5,1000,true,false,null]");
+        assert_eq!(JsonValue::Number(f64::NAN).to_string(), "null");
+        assert_eq!(JsonValue::Number(7.0).as_usize(), Some(7));
+        assert_eq!(JsonValue::Number(-1.0).as_usize(), None);
+        assert_eq!(JsonValue::Number(1.5).as_usize(), None);
+        assert!(JsonValue::parse("1.2.3").is_err());
+        assert!(JsonValue::parse("nul").is_err());
+    }
+
+    #[test]
+    fn test_objects_keep_their_order() {
+        let parsed = JsonValue::parse(r#"{"b": 1, "a": {"c": []}}"#).unwrap();
+        assert_eq!(parsed.get("b"), Some(&JsonValue::Number(1.0)));
+        assert_eq!(
+            parsed.get("a").and_then(|a| a.get("c")),
+            Some(&JsonValue::Array(Vec::new()))
+        );
+        assert_eq!(parsed.get("d"), None);
+        assert_eq!(parsed.to_string(), r#"{"b":1,"a":{"c":[]}}"#);
+        assert!(JsonValue::parse(r#"{"a" 1}"#).is_err());
+        assert!(JsonValue::parse(r#"{"a": 1,}"#).is_err());
+        assert!(JsonValue::parse("[1 2]").is_err());
+    }
+
+    #[test]
+    fn test_trailing_characters_are_refused() {
+        assert!(JsonValue::parse("{} x").is_err());
+        assert!(JsonValue::parse("1 2").is_err());
+        assert!(JsonValue::parse("").is_err());
+        assert!(JsonValue::parse("  true \n").is_ok());
+    }
+
+    #[test]
+    fn test_nesting_is_limited() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(JsonValue::parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(JsonValue::parse(&nested(MAX_DEPTH + 1)).is_err());
+        assert!(JsonValue::parse(&"[".repeat(200_000)).is_err());
+        assert!(JsonValue::parse(&r#"{"a":"#.repeat(200_000)).is_err());
+    }
+}
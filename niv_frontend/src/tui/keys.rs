@@ -0,0 +1,170 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+
+/// Parse keys written in vim notation, such as `ihello<Esc>:w<CR>` or `<C-d>`.
+///
+/// A `<` that does not start a known key name is taken literally; `<lt>`
+/// always means `<`.
+pub fn parse_key_notation(keys: &str) -> Vec<KeyEvent> {
+    let mut events = Vec::new();
+    let mut rest = keys;
+    while let Some(ch) = rest.chars().next() {
+        let special = match ch {
+            '<' => rest.find('>').and_then(|end| {
+                let event = parse_special_key(&rest[1..end])?;
+                Some((event, end + 1))
+            }),
+            _ => None,
+        };
+        if let Some((event, len)) = special {
+            events.push(event);
+            rest = &rest[len..];
+            continue;
+        }
+        events.push(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE));
+        rest = &rest[ch.len_utf8()..];
+    }
+    events
+}
+
+/// Write a key in the vim notation [`parse_key_notation`] reads back, as
+/// `x`, `<lt>` or `<C-d>`; None for keys that notation has no name for
+pub fn key_notation(event: KeyEvent) -> Option<String> {
+    let name = match event.code {
+        KeyCode::Esc => "Esc",
+        KeyCode::Enter => "CR",
+        KeyCode::Tab => "Tab",
+        KeyCode::Backspace => "BS",
+        KeyCode::Delete => "Del",
+        KeyCode::Up => "Up",
+        KeyCode::Down => "Down",
+        KeyCode::Left => "Left",
+        KeyCode::Right => "Right",
+        KeyCode::Home => "Home",
+        KeyCode::End => "End",
+        KeyCode::PageUp => "PageUp",
+        KeyCode::PageDown => "PageDown",
+        KeyCode::Insert => "Insert",
+        KeyCode::F(n @ 1..=12) => return Some(with_modifiers(&format!("F{}", n), event)),
+        KeyCode::Char('<') => "lt",
+        // Shift alone is in the character already, as in `A`
+        KeyCode::Char(ch) if (event.modifiers - KeyModifiers::SHIFT).is_empty() => {
+            return Some(ch.to_string());
+        }
+        KeyCode::Char(ch) => return Some(with_modifiers(&ch.to_string(), event)),
+        _ => return None,
+    };
+    Some(with_modifiers(name, event))
+}
+
+/// `<{modifiers}{name}>`
+fn with_modifiers(name: &str, event: KeyEvent) -> String {
+    let mut notation = String::from("<");
+    for (modifier, prefix) in [
+        (KeyModifiers::CONTROL, "C-"),
+        (KeyModifiers::ALT, "A-"),
+        (KeyModifiers::SHIFT, "S-"),
+        (KeyModifiers::SUPER, "D-"),
+    ] {
+        if event.modifiers.contains(modifier) {
+            notation.push_str(prefix);
+        }
+    }
+    notation.push_str(name);
+    notation.push('>');
+    notation
+}
+
+/// Bring a key event into the form bindings are matched against, whether it
+/// came from a legacy terminal or through the kitty keyboard protocol.
+///
+/// Key releases, which only the kitty protocol and Windows report, are
+/// dropped. Shifted letters are upper case with `SHIFT` set, as legacy
+/// terminals send them; the protocol reports `Ctrl+Shift+p` as a lower case
+/// `p`. Legacy terminals can't tell `Ctrl+Shift+p` from `Ctrl+p` at all.
+pub fn normalize_key_event(event: KeyEvent) -> Option<KeyEvent> {
+    match event.kind {
+        KeyEventKind::Release => None,
+        _ => Some(canonical_key(event)),
+    }
+}
+
+fn canonical_key(mut event: KeyEvent) -> KeyEvent {
+    match event.code {
+        KeyCode::Char(ch) if event.modifiers.contains(KeyModifiers::SHIFT) => {
+            event.code = KeyCode::Char(ch.to_ascii_uppercase());
+        }
+        _ => {}
+    }
+    event
+}
+
+/// The key a legacy terminal sends for `event`. The kitty keyboard protocol
+/// reports `Ctrl+i`, `Ctrl+m`, `Ctrl+h` and `Ctrl+[` as themselves; other
+/// terminals send Tab, Enter, Backspace and Esc, which is what they mean where
+/// nothing is bound to them, as while typing text.
+pub fn legacy_key(event: KeyEvent) -> KeyEvent {
+    if event.modifiers != KeyModifiers::CONTROL {
+        return event;
+    }
+    let code = match event.code {
+        KeyCode::Char('i') => KeyCode::Tab,
+        KeyCode::Char('m') => KeyCode::Enter,
+        KeyCode::Char('h') => KeyCode::Backspace,
+        KeyCode::Char('[') => KeyCode::Esc,
+        _ => return event,
+    };
+    KeyEvent::new(code, KeyModifiers::NONE)
+}
+
+/// The key inside `<...>`, with optional `C-`, `A-`/`M-`, `S-` and `D-`
+/// (Super) prefixes
+fn parse_special_key(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    while let Some((prefix, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        match prefix.to_ascii_lowercase().as_str() {
+            "c" => modifiers |= KeyModifiers::CONTROL,
+            "a" | "m" => modifiers |= KeyModifiers::ALT,
+            "s" => modifiers |= KeyModifiers::SHIFT,
+            "d" => modifiers |= KeyModifiers::SUPER,
+            _ => return None,
+        }
+        name = rest;
+    }
+
+    let code = match name.to_ascii_lowercase().as_str() {
+        "esc" => KeyCode::Esc,
+        "cr" | "enter" | "return" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "bs" => KeyCode::Backspace,
+        "del" => KeyCode::Delete,
+        "space" => KeyCode::Char(' '),
+        "lt" => KeyCode::Char('<'),
+        "bar" => KeyCode::Char('|'),
+        "bslash" => KeyCode::Char('\\'),
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        "insert" => KeyCode::Insert,
+        lower => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+            Some(n @ 1..=12) => KeyCode::F(n),
+            _ => {
+                let mut chars = name.chars();
+                match (chars.next(), chars.next()) {
+                    // A single character only makes sense with a modifier
+                    (Some(ch), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+                        KeyCode::Char(ch.to_ascii_lowercase())
+                    }
+                    (Some(ch), None) if !modifiers.is_empty() => KeyCode::Char(ch),
+                    _ => return None,
+                }
+            }
+        },
+    };
+    Some(canonical_key(KeyEvent::new(code, modifiers)))
+}
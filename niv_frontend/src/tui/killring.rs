@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+/// Entries the kill ring keeps before dropping the oldest
+pub const KILL_RING_SIZE: usize = 20;
+
+/// Text taken by a yank or delete
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Yank {
+    pub text: String,
+    /// Whole lines, pasted as lines below or above the cursor line rather
+    /// than at the cursor
+    pub linewise: bool,
+}
+
+impl Yank {
+    /// First line of the text, at most `max` characters, for the yank picker
+    pub fn preview(&self, max: usize) -> String {
+        let first = self.text.split('\n').next().unwrap_or("");
+        let mut preview: String = first.replace('\t', " ").chars().take(max).collect();
+        if first.chars().count() > max {
+            preview.pop();
+            preview.push('…');
+        }
+        preview
+    }
+
+    /// Short one-line description for the yank picker, e.g. `3 lines`
+    pub fn summary(&self) -> String {
+        match (self.linewise, self.text.split('\n').count()) {
+            (true, 1) => "1 line".to_string(),
+            (true, lines) => format!("{} lines", lines),
+            (false, _) => format!("{} chars", self.text.chars().count()),
+        }
+    }
+}
+
+/// Recent yanks and deletes, newest first. The newest one is the unnamed
+/// register `p` and `P` paste.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KillRing {
+    entries: VecDeque<Yank>,
+    capacity: usize,
+}
+
+impl Default for KillRing {
+    fn default() -> Self {
+        Self::new(KILL_RING_SIZE)
+    }
+}
+
+impl KillRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Make `yank` the newest entry. Taking the same text again moves it to
+    /// the front instead of keeping it twice.
+    pub fn push(&mut self, yank: Yank) {
+        // An empty line is still a line
+        if (yank.text.is_empty() && !yank.linewise) || self.capacity == 0 {
+            return;
+        }
+        self.entries.retain(|entry| *entry != yank);
+        self.entries.push_front(yank);
+        self.entries.truncate(self.capacity);
+    }
+
+    /// The unnamed register
+    pub fn newest(&self) -> Option<&Yank> {
+        self.entries.front()
+    }
+
+    /// Entry `index`, 0 being the newest
+    pub fn get(&self, index: usize) -> Option<&Yank> {
+        self.entries.get(index)
+    }
+
+    /// Make entry `index` the newest, after it was pasted from the picker
+    pub fn promote(&mut self, index: usize) {
+        if let Some(yank) = self.entries.remove(index) {
+            self.entries.push_front(yank);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Yank> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
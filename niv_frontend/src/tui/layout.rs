@@ -1,5 +1,27 @@
 use crossterm::terminal;
 
+/// Smallest usable text area; below it a placeholder is drawn instead
+pub const MIN_TEXT_WIDTH: u16 = 10;
+pub const MIN_TEXT_HEIGHT: u16 = 1;
+
+/// Digits the line number column has room for at least
+pub const MIN_LINE_NUMBER_DIGITS: usize = 4;
+
+/// Width of the line number column of a buffer of `line_count` lines: the
+/// digits of the largest number shown, and a column after them for the
+/// version control sign. With `relative` numbers only, the largest is the
+/// distance from the first line to the last; without numbers there is no
+/// column.
+pub fn line_number_width(line_count: usize, numbers: bool, relative: bool) -> u16 {
+    let largest = match (numbers, relative) {
+        (true, _) => line_count,
+        (false, true) => line_count.saturating_sub(1),
+        (false, false) => return 0,
+    };
+    let digits = largest.max(1).ilog10() as usize + 1;
+    (digits.max(MIN_LINE_NUMBER_DIGITS) + 1) as u16
+}
+
 /// Screen layout dimensions
 #[derive(Debug, Clone)]
 pub struct Layout {
@@ -10,15 +32,35 @@ pub struct Layout {
     pub status_line_row: u16,
     pub line_number_width: u16,
     pub text_start_col: u16,
+    /// First screen row of the text area (1 when the winbar is shown)
+    pub text_start_row: u16,
+    /// Whether a winbar row is shown above the text area
+    pub winbar: bool,
+    /// First terminal row the editor draws on; 0 unless running inline
+    pub top: u16,
+    /// Width of the preview pane right of the text area, 0 when not shown
+    pub preview_width: u16,
 }
 
 impl Layout {
     pub fn new(width: u16, height: u16) -> Self {
+        Self::with_winbar(width, height, false)
+    }
+
+    /// Layout with an optional winbar row at the top of the window
+    pub fn with_winbar(width: u16, height: u16, winbar: bool) -> Self {
+        Self::at_row(0, width, height, winbar)
+    }
+
+    /// Layout of `height` rows starting at terminal row `top`
+    pub fn at_row(top: u16, width: u16, height: u16, winbar: bool) -> Self {
         let line_number_width = 5; // " 123 "
         let text_start_col = line_number_width;
         let text_area_width = width.saturating_sub(text_start_col);
-        let text_area_height = height.saturating_sub(2); // -1 for status line, -1 for command line
-        let status_line_row = height.saturating_sub(2);
+        // -1 for status line, -1 for command line, -1 for the winbar if shown
+        let text_area_height = height.saturating_sub(2 + u16::from(winbar));
+        let text_start_row = top + u16::from(winbar);
+        let status_line_row = top + height.saturating_sub(2);
 
         Self {
             width,
@@ -28,34 +70,73 @@ impl Layout {
             status_line_row,
             line_number_width,
             text_start_col,
+            text_start_row,
+            winbar,
+            top,
+            preview_width: 0,
         }
     }
 
+    /// Give the line number column `width` columns, the text area the rest
+    pub fn with_line_numbers(mut self, width: u16) -> Self {
+        self.line_number_width = width;
+        self.text_start_col = width;
+        self.text_area_width = self.width.saturating_sub(width);
+        self
+    }
+
+    /// Split the text area in two, giving the right half and a separator
+    /// column to a preview pane
+    pub fn split_preview(mut self, preview: bool) -> Self {
+        if preview {
+            let pane = self.text_area_width / 2;
+            self.text_area_width -= pane;
+            self.preview_width = pane.saturating_sub(1);
+        }
+        self
+    }
+
+    /// Whether a preview pane is shown
+    pub fn has_preview(&self) -> bool {
+        self.preview_width > 0
+    }
+
     pub fn update_size(&mut self, width: u16, height: u16) {
-        *self = Self::new(width, height);
+        let preview = self.has_preview();
+        *self = Self::at_row(self.top, width, height, self.winbar)
+            .with_line_numbers(self.line_number_width)
+            .split_preview(preview);
+    }
+
+    /// Whether the terminal is too small to draw the editor
+    pub fn is_too_small(&self) -> bool {
+        self.text_area_width < MIN_TEXT_WIDTH || self.text_area_height < MIN_TEXT_HEIGHT
     }
 
     /// Get the row for command line
     pub fn command_line_row(&self) -> u16 {
-        self.height.saturating_sub(1)
+        self.top + self.height.saturating_sub(1)
     }
 
     /// Check if a position is within the text area
     pub fn is_in_text_area(&self, col: u16, row: u16) -> bool {
-        col >= self.text_start_col && col < self.width && row < self.text_area_height
+        col >= self.text_start_col
+            && col < self.text_start_col + self.text_area_width
+            && row >= self.text_start_row
+            && row < self.text_start_row + self.text_area_height
     }
 
     /// Convert screen coordinates to text buffer coordinates
     pub fn screen_to_buffer(&self, screen_col: u16, screen_row: u16) -> (u16, u16) {
         let buffer_col = screen_col.saturating_sub(self.text_start_col);
-        let buffer_row = screen_row;
+        let buffer_row = screen_row.saturating_sub(self.text_start_row);
         (buffer_col, buffer_row)
     }
 
     /// Convert text buffer coordinates to screen coordinates
     pub fn buffer_to_screen(&self, buffer_col: u16, buffer_row: u16) -> (u16, u16) {
         let screen_col = buffer_col + self.text_start_col;
-        let screen_row = buffer_row;
+        let screen_row = buffer_row + self.text_start_row;
         (screen_col, screen_row)
     }
 
@@ -63,22 +144,42 @@ impl Layout {
     pub fn text_area_rect(&self) -> Rect {
         Rect {
             x: self.text_start_col,
-            y: 0,
+            y: self.text_start_row,
             width: self.text_area_width,
             height: self.text_area_height,
         }
     }
 
+    /// Get the rectangle for the preview pane, if it is shown
+    pub fn preview_rect(&self) -> Option<Rect> {
+        self.has_preview().then_some(Rect {
+            x: self.text_start_col + self.text_area_width + 1,
+            y: self.text_start_row,
+            width: self.preview_width,
+            height: self.text_area_height,
+        })
+    }
+
     /// Get the rectangle for line numbers
     pub fn line_number_rect(&self) -> Rect {
         Rect {
             x: 0,
-            y: 0,
+            y: self.text_start_row,
             width: self.line_number_width,
             height: self.text_area_height,
         }
     }
 
+    /// Get the rectangle for the winbar, if it is shown
+    pub fn winbar_rect(&self) -> Option<Rect> {
+        self.winbar.then_some(Rect {
+            x: 0,
+            y: self.top,
+            width: self.width,
+            height: 1,
+        })
+    }
+
     /// Get the rectangle for status line
     pub fn status_line_rect(&self) -> Rect {
         Rect {
@@ -158,12 +259,18 @@ impl Position {
 /// Layout manager for the TUI
 pub struct LayoutManager {
     layout: Layout,
+    /// Rows used when running inline instead of on the whole terminal
+    inline_height: Option<u16>,
+    /// Whether the text area is split with a preview pane
+    preview: bool,
 }
 
 impl LayoutManager {
     pub fn new() -> Self {
         Self {
             layout: Layout::default(),
+            inline_height: None,
+            preview: false,
         }
     }
 
@@ -171,8 +278,63 @@ impl LayoutManager {
         &self.layout
     }
 
+    /// Lay out for a terminal of `width` x `height`. Inline, the editor keeps
+    /// its rows, moving up only as far as it must to stay on screen.
     pub fn update_size(&mut self, width: u16, height: u16) {
-        self.layout.update_size(width, height);
+        match self.inline_height {
+            Some(rows) => {
+                let rows = rows.min(height);
+                let top = self.layout.top.min(height - rows);
+                self.layout = self.lay_out(top, width, rows, self.layout.winbar);
+            }
+            None => {
+                self.layout = self.lay_out(0, width, height, self.layout.winbar);
+            }
+        }
+    }
+
+    /// Show or hide the winbar row above the text area
+    pub fn set_winbar(&mut self, winbar: bool) {
+        let layout = &self.layout;
+        self.layout = self.lay_out(layout.top, layout.width, layout.height, winbar);
+    }
+
+    /// Show or hide the preview pane right of the text area
+    pub fn set_preview(&mut self, preview: bool) {
+        self.preview = preview;
+        let layout = &self.layout;
+        self.layout = self.lay_out(layout.top, layout.width, layout.height, layout.winbar);
+    }
+
+    /// Make the line number column `width` columns wide
+    pub fn set_line_number_width(&mut self, width: u16) {
+        let layout = &self.layout;
+        self.layout = Layout::at_row(layout.top, layout.width, layout.height, layout.winbar)
+            .with_line_numbers(width)
+            .split_preview(self.preview);
+    }
+
+    /// Use only `rows` rows of the terminal, or all of it when None
+    pub fn set_inline_height(&mut self, rows: Option<u16>) {
+        self.inline_height = rows;
+    }
+
+    pub fn inline_height(&self) -> Option<u16> {
+        self.inline_height
+    }
+
+    /// Move the editor's first row to terminal row `top`
+    pub fn set_top(&mut self, top: u16) {
+        let layout = &self.layout;
+        self.layout = self.lay_out(top, layout.width, layout.height, layout.winbar);
+    }
+
+    /// Layout of `height` rows from terminal row `top`, keeping the line
+    /// number column and the preview pane as they are
+    fn lay_out(&self, top: u16, width: u16, height: u16, winbar: bool) -> Layout {
+        Layout::at_row(top, width, height, winbar)
+            .with_line_numbers(self.layout.line_number_width)
+            .split_preview(self.preview)
     }
 
     /// Get terminal size and update layout
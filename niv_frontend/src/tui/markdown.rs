@@ -0,0 +1,423 @@
+use crate::tui::display::{DisplayOptions, display_width};
+
+/// How a run of previewed Markdown is styled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkdownStyle {
+    Text,
+    Heading,
+    Strong,
+    Emphasis,
+    Code,
+    /// Text of a block quote
+    Quote,
+    /// List bullets, quote bars and rules
+    Marker,
+}
+
+/// A run of preview text with a single style
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkdownSpan {
+    pub text: String,
+    pub style: MarkdownStyle,
+}
+
+/// One line of the rendered preview
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreviewLine {
+    pub spans: Vec<MarkdownSpan>,
+    /// Line of the block it was rendered from
+    pub source_line: usize,
+}
+
+impl PreviewLine {
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+/// A block of the source (a paragraph, list, code block, ...) and its
+/// rendered lines
+#[derive(Debug, Clone)]
+struct RenderedBlock {
+    source: String,
+    line_count: usize,
+    lines: Vec<PreviewLine>,
+}
+
+/// Markdown rendered as styled text, block by block. Updates only render the
+/// blocks an edit touched again.
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownPreview {
+    width: usize,
+    blocks: Vec<RenderedBlock>,
+}
+
+impl MarkdownPreview {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Wrap at `width` columns, rendering everything again if it changed
+    pub fn set_width(&mut self, width: usize) {
+        if width != self.width {
+            self.width = width;
+            self.blocks.clear();
+        }
+    }
+
+    /// Bring the preview up to date with `text`. Blocks before and after the
+    /// edited part are kept; returns how many blocks were rendered.
+    pub fn update(&mut self, text: &str) -> usize {
+        let sources = split_blocks(text);
+        let prefix = self
+            .blocks
+            .iter()
+            .zip(&sources)
+            .take_while(|(block, source)| block.source == **source)
+            .count();
+        let max_suffix = self.blocks.len().min(sources.len()) - prefix;
+        let suffix = self
+            .blocks
+            .iter()
+            .rev()
+            .zip(sources.iter().rev())
+            .take(max_suffix)
+            .take_while(|(block, source)| block.source == **source)
+            .count();
+
+        let changed = &sources[prefix..sources.len() - suffix];
+        let rendered: Vec<RenderedBlock> = changed
+            .iter()
+            .map(|source| RenderedBlock {
+                line_count: source.lines().count(),
+                lines: render_block(source, self.width),
+                source: source.clone(),
+            })
+            .collect();
+        let count = rendered.len();
+        let old_end = self.blocks.len() - suffix;
+        self.blocks.splice(prefix..old_end, rendered);
+        count
+    }
+
+    /// Up to `rows` preview lines, starting where source line `top` is
+    /// rendered
+    pub fn visible(&self, top: usize, rows: usize) -> Vec<&PreviewLine> {
+        let mut start = 0;
+        let mut lines = Vec::new();
+        for block in &self.blocks {
+            let end = start + block.line_count;
+            if end > top || !lines.is_empty() {
+                let skip = if lines.is_empty() && top > start {
+                    let target = block
+                        .lines
+                        .iter()
+                        .map(|line| line.source_line)
+                        .filter(|&line| start + line <= top)
+                        .max();
+                    target
+                        .and_then(|target| {
+                            block
+                                .lines
+                                .iter()
+                                .position(|line| line.source_line == target)
+                        })
+                        .unwrap_or(0)
+                } else {
+                    0
+                };
+                lines.extend(block.lines.iter().skip(skip));
+                if lines.len() >= rows {
+                    lines.truncate(rows);
+                    break;
+                }
+            }
+            start = end;
+        }
+        lines
+    }
+}
+
+/// Split Markdown into blocks: runs of non-blank lines with the blank lines
+/// after them. Fenced code blocks stay whole, blank lines included.
+fn split_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut current = String::new();
+    let mut fenced = false;
+    let mut trailing_blank = false;
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if !blank && trailing_blank && !fenced {
+            blocks.push(std::mem::take(&mut current));
+        }
+        if is_fence(line) {
+            fenced = !fenced;
+        }
+        trailing_blank = blank;
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+/// Render one block to lines at most `width` columns wide
+fn render_block(source: &str, width: usize) -> Vec<PreviewLine> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut paragraph: Option<(usize, String)> = None;
+    let mut fenced = false;
+
+    for (index, line) in source.lines().enumerate() {
+        if is_fence(line) {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            fenced = !fenced;
+            continue;
+        }
+        if fenced {
+            let code: String = line.chars().take(width.saturating_sub(2)).collect();
+            lines.push(PreviewLine {
+                spans: vec![
+                    span("  ", MarkdownStyle::Code),
+                    span(&code, MarkdownStyle::Code),
+                ],
+                source_line: index,
+            });
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            continue;
+        }
+        if let Some((level, title)) = heading(trimmed) {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            let mut spans = inline_spans(title);
+            for span in &mut spans {
+                span.style = MarkdownStyle::Heading;
+            }
+            let start = lines.len();
+            wrap_spans(&spans, width, "", "", index, &mut lines);
+            if level <= 2 {
+                let underline = if level == 1 { "═" } else { "─" };
+                let title_width = lines[start..]
+                    .iter()
+                    .map(|line| display_width(&line.text(), &DisplayOptions::default()))
+                    .max()
+                    .unwrap_or(0);
+                lines.push(PreviewLine {
+                    spans: vec![span(&underline.repeat(title_width), MarkdownStyle::Marker)],
+                    source_line: index,
+                });
+            }
+        } else if is_rule(trimmed) {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            lines.push(PreviewLine {
+                spans: vec![span(&"─".repeat(width), MarkdownStyle::Marker)],
+                source_line: index,
+            });
+        } else if let Some(quote) = trimmed.strip_prefix('>') {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            let mut spans = inline_spans(quote.trim_start());
+            for span in &mut spans {
+                if span.style == MarkdownStyle::Text {
+                    span.style = MarkdownStyle::Quote;
+                }
+            }
+            wrap_spans(&spans, width, "│ ", "│ ", index, &mut lines);
+        } else if let Some((marker, item)) = list_item(trimmed) {
+            flush_paragraph(&mut paragraph, &mut lines, width);
+            let indent = " ".repeat(line.len() - trimmed.len());
+            let first = format!("{}{} ", indent, marker);
+            let rest = " ".repeat(first.chars().count());
+            wrap_spans(&inline_spans(item), width, &first, &rest, index, &mut lines);
+        } else {
+            // Lines of a paragraph flow together
+            match &mut paragraph {
+                Some((_, text)) => {
+                    text.push(' ');
+                    text.push_str(trimmed);
+                }
+                None => paragraph = Some((index, trimmed.to_string())),
+            }
+        }
+    }
+    flush_paragraph(&mut paragraph, &mut lines, width);
+
+    // One empty line between blocks, however many the source has
+    if source
+        .lines()
+        .last()
+        .is_some_and(|line| line.trim().is_empty())
+    {
+        lines.push(PreviewLine {
+            spans: Vec::new(),
+            source_line: source.lines().count() - 1,
+        });
+    }
+    lines
+}
+
+fn flush_paragraph(
+    paragraph: &mut Option<(usize, String)>,
+    lines: &mut Vec<PreviewLine>,
+    width: usize,
+) {
+    if let Some((index, text)) = paragraph.take() {
+        wrap_spans(&inline_spans(&text), width, "", "", index, lines);
+    }
+}
+
+fn span(text: &str, style: MarkdownStyle) -> MarkdownSpan {
+    MarkdownSpan {
+        text: text.to_string(),
+        style,
+    }
+}
+
+/// Level and text of an ATX heading (`## Title`)
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&ch| ch == '#').count();
+    let rest = &line[level..];
+    ((1..=6).contains(&level) && (rest.is_empty() || rest.starts_with(' ')))
+        .then(|| (level, rest.trim().trim_end_matches('#').trim_end()))
+}
+
+/// `---`, `***` or `___`, spaces allowed
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|ch| !ch.is_whitespace()).collect();
+    chars.len() >= 3
+        && matches!(chars[0], '-' | '*' | '_')
+        && chars.iter().all(|&ch| ch == chars[0])
+}
+
+/// Marker to show and text of a list item: `•` for bullets, the number for
+/// ordered items
+fn list_item(line: &str) -> Option<(String, &str)> {
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))
+    {
+        return Some(("•".to_string(), item));
+    }
+    let digits = line.chars().take_while(char::is_ascii_digit).count();
+    let rest = &line[digits..];
+    let item = rest
+        .strip_prefix(". ")
+        .or_else(|| rest.strip_prefix(") "))
+        .filter(|_| digits > 0)?;
+    Some((format!("{}.", &line[..digits]), item))
+}
+
+/// Spans of inline text: `**strong**`, `*emphasis*`, `` `code` `` and the
+/// text of `[links](url)`. Unclosed markers are shown as typed.
+fn inline_spans(text: &str) -> Vec<MarkdownSpan> {
+    let mut spans: Vec<MarkdownSpan> = Vec::new();
+    let mut push = |text: &str, style: MarkdownStyle| {
+        if text.is_empty() {
+            return;
+        }
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.text.push_str(text),
+            _ => spans.push(span(text, style)),
+        }
+    };
+
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let delimited = |open: &str, close: &str| {
+            let inner = rest.strip_prefix(open)?;
+            let end = inner.find(close).filter(|&end| end > 0)?;
+            Some((&inner[..end], open.len() + end + close.len()))
+        };
+        let found = match ch {
+            '`' => delimited("`", "`").map(|(inner, len)| (inner, len, MarkdownStyle::Code)),
+            '*' | '_' => {
+                let strong = if ch == '*' { "**" } else { "__" };
+                let marker = &strong[..1];
+                delimited(strong, strong)
+                    .map(|(inner, len)| (inner, len, MarkdownStyle::Strong))
+                    .or_else(|| {
+                        delimited(marker, marker)
+                            .map(|(inner, len)| (inner, len, MarkdownStyle::Emphasis))
+                    })
+            }
+            '[' => rest.find("](").and_then(|middle| {
+                let end = rest[middle..].find(')')? + middle;
+                Some((&rest[1..middle], end + 1, MarkdownStyle::Emphasis))
+            }),
+            _ => None,
+        };
+        match found {
+            Some((inner, len, style)) => {
+                push(inner, style);
+                rest = &rest[len..];
+            }
+            None => {
+                push(&rest[..ch.len_utf8()], MarkdownStyle::Text);
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+    spans
+}
+
+/// Word-wrap `spans` to `width` columns, starting the first line with
+/// `first_prefix` and the others with `rest_prefix`
+fn wrap_spans(
+    spans: &[MarkdownSpan],
+    width: usize,
+    first_prefix: &str,
+    rest_prefix: &str,
+    source_line: usize,
+    lines: &mut Vec<PreviewLine>,
+) {
+    let options = DisplayOptions::default();
+    let prefix_style = |prefix: &str| {
+        if prefix.trim().is_empty() {
+            MarkdownStyle::Text
+        } else {
+            MarkdownStyle::Marker
+        }
+    };
+    let new_line = |prefix: &str| PreviewLine {
+        spans: vec![span(prefix, prefix_style(prefix))],
+        source_line,
+    };
+    let mut line = new_line(first_prefix);
+    let mut col = display_width(first_prefix, &options);
+    let mut line_empty = true;
+
+    for span_in in spans {
+        for word in span_in.text.split_inclusive(' ') {
+            let word_width = display_width(word.trim_end(), &options);
+            if !line_empty && col + word_width > width {
+                lines.push(line);
+                line = new_line(rest_prefix);
+                col = display_width(rest_prefix, &options);
+            }
+            let word = if line_empty || col > display_width(rest_prefix, &options) {
+                word
+            } else {
+                word.trim_start()
+            };
+            match line.spans.last_mut() {
+                Some(last) if last.style == span_in.style => last.text.push_str(word),
+                _ => line.spans.push(span(word, span_in.style)),
+            }
+            col += display_width(word, &options);
+            line_empty = false;
+        }
+    }
+    lines.push(line);
+}
@@ -1,9 +1,71 @@
+pub mod arglist;
+pub mod brackets;
 pub mod buffer;
+pub mod charinfo;
+pub mod completion;
+pub mod crash;
+pub mod diff;
+pub mod digraph;
+pub mod display;
 pub mod editor;
+pub mod editorconfig;
+pub mod excmd;
+pub mod filter;
+pub mod interrupt;
+pub mod json;
+pub mod keys;
+pub mod killring;
 pub mod layout;
+pub mod markdown;
+pub mod modeline;
+pub mod overlength;
+pub mod pager;
+pub mod palette;
+pub mod plugin;
+pub mod profile;
+pub mod rpc;
+pub mod screen;
+pub mod session;
+pub mod swatch;
+pub mod table;
 pub mod theme;
+pub mod timer;
+pub mod vcs;
+pub mod winbar;
+pub mod wordindex;
 
+pub use arglist::*;
+pub use brackets::*;
 pub use buffer::*;
+pub use charinfo::*;
+pub use completion::*;
+pub use crash::*;
+pub use diff::*;
+pub use digraph::*;
+pub use display::*;
 pub use editor::*;
+pub use editorconfig::*;
+pub use excmd::*;
+pub use filter::*;
+pub use interrupt::*;
+pub use json::*;
+pub use keys::*;
+pub use killring::*;
 pub use layout::*;
+pub use markdown::*;
+pub use modeline::*;
+pub use overlength::*;
+pub use pager::*;
+pub use palette::*;
+pub use plugin::*;
+pub use profile::*;
+pub use rpc::*;
+pub use screen::*;
+pub use session::*;
+pub use swatch::*;
+pub use table::*;
 pub use theme::*;
+pub use timer::*;
+pub use vcs::*;
+pub use winbar::*;
+pub use wordindex::*;
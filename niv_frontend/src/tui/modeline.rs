@@ -0,0 +1,54 @@
+/// Number of lines scanned at the start and end of a buffer for modelines
+pub const MODELINE_SCAN_LINES: usize = 5;
+
+/// Extract buffer-local options from vim-style modelines.
+///
+/// Recognizes both forms used by vim, with `niv:`, `vim:`, `vi:` and `ex:` markers:
+/// - `// vim: set noswapfile noundofile:` (text after the closing `:` is ignored)
+/// - `# niv: noswapfile ts=4`
+pub fn parse_modelines(content: &str) -> Vec<String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let head = lines.len().min(MODELINE_SCAN_LINES);
+    let tail_start = lines.len().saturating_sub(MODELINE_SCAN_LINES).max(head);
+
+    lines[..head]
+        .iter()
+        .chain(lines[tail_start..].iter())
+        .filter_map(|line| parse_modeline(line))
+        .flatten()
+        .collect()
+}
+
+/// Parse a single line, returning its options if it contains a modeline.
+fn parse_modeline(line: &str) -> Option<Vec<String>> {
+    let rest = ["niv:", "vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        line.find(marker).and_then(|pos| {
+            // The marker must start the line or follow whitespace
+            let preceded_ok = pos == 0 || line[..pos].ends_with(char::is_whitespace);
+            preceded_ok.then(|| &line[pos + marker.len()..])
+        })
+    })?;
+
+    let rest = rest.trim_start();
+    let options = if let Some(set_args) = rest
+        .strip_prefix("set ")
+        .or_else(|| rest.strip_prefix("se "))
+    {
+        // `set` form ends at the next colon
+        set_args.split(':').next().unwrap_or("")
+    } else {
+        rest
+    };
+
+    let options: Vec<String> = options
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .filter(|opt| !opt.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    if options.is_empty() {
+        None
+    } else {
+        Some(options)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::tui::display::{DisplayOptions, char_width};
+
+/// Byte offset of the first character of `line` that goes past display
+/// column `limit`; None when the line fits or `limit` is 0
+pub fn overlength_start(line: &str, limit: usize, options: &DisplayOptions) -> Option<usize> {
+    if limit == 0 {
+        return None;
+    }
+    let mut col = 0;
+    for (i, ch) in line.char_indices() {
+        col += char_width(ch, col, options);
+        if col > limit {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Number of lines wider than [`DisplayOptions::text_width`], kept up to date
+/// from the lines each edit replaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OverlengthCount(usize);
+
+impl OverlengthCount {
+    /// Count the overlong lines of `text`
+    pub fn from_text(text: &str, options: &DisplayOptions) -> Self {
+        Self(count_overlong(text, options))
+    }
+
+    /// Account for whole lines `old` being replaced by `new`
+    pub fn update(&mut self, old: &str, new: &str, options: &DisplayOptions) {
+        self.0 =
+            (self.0 + count_overlong(new, options)).saturating_sub(count_overlong(old, options));
+    }
+
+    pub fn get(&self) -> usize {
+        self.0
+    }
+}
+
+fn count_overlong(text: &str, options: &DisplayOptions) -> usize {
+    if options.text_width == 0 {
+        return 0;
+    }
+    text.lines()
+        .filter(|line| overlength_start(line, options.text_width, options).is_some())
+        .count()
+}
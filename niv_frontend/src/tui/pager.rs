@@ -0,0 +1,74 @@
+/// How far through the text the bottom of the view is, as a percentage of the
+/// lines, the way `less` reports it
+pub fn view_percent(top_line: usize, height: usize, line_count: usize) -> usize {
+    if line_count == 0 {
+        return 100;
+    }
+    let bottom = (top_line + height).min(line_count);
+    bottom * 100 / line_count
+}
+
+/// Line and byte column of the first line after `from` containing `pattern`,
+/// or of the last one before it when `backward`. Searches don't wrap around.
+pub fn find_line(text: &str, pattern: &str, from: usize, backward: bool) -> Option<(usize, usize)> {
+    let found = |(index, line): (usize, &str)| line.find(pattern).map(|col| (index, col));
+    let lines = text.lines().enumerate();
+    if backward {
+        lines.take(from).filter_map(found).last()
+    } else {
+        lines.skip(from + 1).find_map(found)
+    }
+}
+
+/// Turns chunks of a byte stream into text, keeping a character cut at the
+/// end of a chunk until the rest of it arrives. Invalid bytes become U+FFFD.
+#[derive(Debug, Clone, Default)]
+pub struct StreamDecoder {
+    /// Start of a character whose remaining bytes haven't arrived yet
+    pending: Vec<u8>,
+}
+
+impl StreamDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Text of `bytes` as far as it is complete
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // An incomplete sequence at the end, rather than invalid bytes
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => incomplete_tail_start(&self.pending),
+        };
+        let text = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+        text
+    }
+
+    /// Whatever is left once the stream has ended
+    pub fn finish(&mut self) -> String {
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        text
+    }
+}
+
+/// Where an incomplete UTF-8 sequence at the end of `bytes` starts, or the
+/// length of `bytes` if it doesn't end in one
+fn incomplete_tail_start(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(3) {
+        let start = bytes.len() - back;
+        let needed = match bytes[start] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            // Continuation byte: the lead may be further back
+            0x80..=0xbf => continue,
+            _ => return bytes.len(),
+        };
+        return if needed > back { start } else { bytes.len() };
+    }
+    bytes.len()
+}
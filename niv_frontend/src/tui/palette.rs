@@ -0,0 +1,147 @@
+/// What choosing a palette entry does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PaletteAction {
+    /// Run an ex command line
+    Run(String),
+    /// Start an ex command line with this text, for commands taking arguments
+    Edit(String),
+    /// Type keys in normal mode, in vim notation
+    Keys(&'static str),
+    /// Paste entry N of the kill ring after the cursor
+    Paste(usize),
+}
+
+/// One entry of the command palette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteItem {
+    pub label: String,
+    /// Keys currently bound to the command, comma separated
+    pub keys: String,
+    pub description: String,
+    pub action: PaletteAction,
+}
+
+/// The command palette: entries filtered by a fuzzy query, one of them
+/// selected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    query: String,
+    items: Vec<PaletteItem>,
+    /// Indices of the items matching the query, best match first
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Palette {
+    pub fn new(items: Vec<PaletteItem>) -> Self {
+        let mut palette = Self {
+            query: String::new(),
+            items,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        palette.filter();
+        palette
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn push_char(&mut self, ch: char) {
+        self.query.push(ch);
+        self.filter();
+    }
+
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.filter();
+    }
+
+    /// Items matching the query, best first
+    pub fn matches(&self) -> impl Iterator<Item = &PaletteItem> {
+        self.matches.iter().map(|&i| &self.items[i])
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    /// Position of the selected item among the matches
+    pub fn selected_index(&self) -> usize {
+        self.selected
+    }
+
+    pub fn selected(&self) -> Option<&PaletteItem> {
+        self.matches.get(self.selected).map(|&i| &self.items[i])
+    }
+
+    /// Select the next match, wrapping around
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    /// Select the previous match, wrapping around
+    pub fn select_prev(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// Match the items against the query. Label matches rank above matches in
+    /// the description only; equal scores keep the items' order.
+    fn filter(&mut self) {
+        let mut scored: Vec<(i64, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let score = fuzzy_score(&self.query, &item.label)
+                    .or_else(|| Some(fuzzy_score(&self.query, &item.description)? - 1000))?;
+                Some((score, i))
+            })
+            .collect();
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
+
+/// How well `query` matches `text` as a case-insensitive subsequence, higher
+/// being better, or None if it doesn't match.
+///
+/// Consecutive characters and characters at the start of a word score extra,
+/// so `wq` prefers `:wq` over `:write [file]`. An empty query matches
+/// everything equally.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut score = 0;
+    let mut query_chars = query.chars().flat_map(char::to_lowercase).peekable();
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+    for ch in text.chars() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+        let word_start = previous.is_none_or(|p| !p.is_alphanumeric())
+            || (previous.is_some_and(char::is_lowercase) && ch.is_uppercase());
+        if ch.to_lowercase().eq(std::iter::once(wanted)) {
+            query_chars.next();
+            score += 1;
+            if previous_matched {
+                score += 5;
+            }
+            if word_start {
+                score += 8;
+            }
+            previous_matched = true;
+        } else {
+            // Every character skipped before the last match costs a point
+            score -= 1;
+            previous_matched = false;
+        }
+        previous = Some(ch);
+    }
+    query_chars.peek().is_none().then_some(score)
+}
@@ -0,0 +1,198 @@
+use crate::tui::buffer::TextBuffer;
+use niv_config::ExtensionConfig;
+use std::path::PathBuf;
+
+/// Something a plugin is allowed to do, granted in its `[extensions]` entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PluginCapability {
+    /// Read the current buffer and cursor position
+    ReadBuffer,
+    /// Edit the current buffer
+    EditBuffer,
+    /// Register ex commands
+    Commands,
+    /// Map normal mode keys to commands
+    Keybindings,
+    /// Subscribe to editor events such as CursorHold
+    Events,
+}
+
+impl PluginCapability {
+    /// Every capability; scripts from the user's own configuration get all of them
+    pub const ALL: [PluginCapability; 5] = [
+        Self::ReadBuffer,
+        Self::EditBuffer,
+        Self::Commands,
+        Self::Keybindings,
+        Self::Events,
+    ];
+
+    /// Parse a capability as written in the manifest (`read`, `edit`, ...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "read" => Some(Self::ReadBuffer),
+            "edit" => Some(Self::EditBuffer),
+            "commands" => Some(Self::Commands),
+            "keys" => Some(Self::Keybindings),
+            "events" => Some(Self::Events),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::ReadBuffer => "read",
+            Self::EditBuffer => "edit",
+            Self::Commands => "commands",
+            Self::Keybindings => "keys",
+            Self::Events => "events",
+        }
+    }
+}
+
+/// A plugin declared in the `[extensions]` section of the configuration.
+///
+/// ```toml
+/// [extensions]
+/// wordcount = ["path=~/.niv/extensions/wordcount.wasm", "capabilities=read,commands"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginManifest {
+    pub name: String,
+    /// Module file, as written in the configuration
+    pub module: PathBuf,
+    pub capabilities: Vec<PluginCapability>,
+}
+
+impl PluginManifest {
+    /// Read the manifest of an extension whose path has the given extension
+    /// (`wasm`, `lua`); `None` for other extensions
+    pub fn from_extension(
+        config: &ExtensionConfig,
+        module_extension: &str,
+    ) -> Option<Result<Self, String>> {
+        let module = PathBuf::from(config.path.as_ref()?);
+        if module.extension()? != module_extension {
+            return None;
+        }
+
+        let mut capabilities = Vec::new();
+        let granted = config
+            .settings
+            .get("capabilities")
+            .and_then(|value| value.as_string().ok())
+            .unwrap_or("");
+        for name in granted.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match PluginCapability::from_name(name) {
+                Some(capability) => capabilities.push(capability),
+                None => {
+                    return Some(Err(format!(
+                        "{}: unknown capability \"{}\"",
+                        config.name, name
+                    )));
+                }
+            }
+        }
+
+        Some(Ok(Self {
+            name: config.name.clone(),
+            module,
+            capabilities,
+        }))
+    }
+
+    pub fn allows(&self, capability: PluginCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+}
+
+/// A change a plugin asked for through the host API, applied by the editor
+/// once the plugin call returns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginRequest {
+    /// Replace bytes `start..end` of the current buffer
+    Edit {
+        start: usize,
+        end: usize,
+        text: String,
+    },
+    /// Run the plugin function `export` for `:name`
+    RegisterCommand { name: String, export: String },
+    /// Run the ex command `command` when `keys` is typed in normal mode
+    MapKey { keys: String, command: String },
+    /// Run the plugin function `export` on `event`
+    Subscribe { event: String, export: String },
+    /// Set a buffer-local option, as with `:setlocal`
+    SetOption(String),
+    /// Run an ex command line
+    Command(String),
+    /// Show a message
+    Message(String),
+    /// The plugin used a host function it was not granted
+    Denied(PluginCapability),
+}
+
+/// What a plugin call sees of the editor, and what it asked for
+#[derive(Debug, Clone, Default)]
+pub struct PluginContext {
+    pub capabilities: Vec<PluginCapability>,
+    /// Text of the current buffer, with the call's own edits applied
+    pub text: String,
+    pub cursor_line: usize,
+    pub cursor_col: usize,
+    /// Arguments of the command that started the call
+    pub args: String,
+    pub requests: Vec<PluginRequest>,
+}
+
+impl PluginContext {
+    pub fn new(capabilities: Vec<PluginCapability>) -> Self {
+        Self {
+            capabilities,
+            ..Self::default()
+        }
+    }
+
+    /// Start a call: show it `buffer` and `args`, and forget earlier requests
+    pub fn prepare(&mut self, buffer: Option<&TextBuffer>, args: &str) {
+        self.requests.clear();
+        self.args = args.to_string();
+        match buffer {
+            Some(buffer) => {
+                self.text = buffer.content.clone();
+                self.cursor_line = buffer.cursor_line;
+                self.cursor_col = buffer.cursor_col;
+            }
+            None => self.text.clear(),
+        }
+    }
+
+    /// Check a capability, recording a denial when it was not granted
+    pub fn check(&mut self, capability: PluginCapability) -> bool {
+        if self.capabilities.contains(&capability) {
+            return true;
+        }
+        if !self.requests.contains(&PluginRequest::Denied(capability)) {
+            self.requests.push(PluginRequest::Denied(capability));
+        }
+        false
+    }
+
+    /// Queue an edit, applying it to `text` so later reads in the same call
+    /// see it. Offsets are clamped and moved back to character boundaries.
+    pub fn edit(&mut self, start: usize, end: usize, text: String) {
+        let end = floor_char_boundary(&self.text, end);
+        let start = floor_char_boundary(&self.text, start.min(end));
+        self.text.replace_range(start..end, &text);
+        self.requests.push(PluginRequest::Edit { start, end, text });
+    }
+}
+
+/// Largest character boundary of `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
@@ -0,0 +1,181 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Frames kept for the report; older ones are dropped
+const MAX_FRAMES: usize = 10_000;
+
+/// Part of a main loop iteration that is timed separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePhase {
+    /// Handling input and timers, not counting the wait for input
+    Events,
+    /// Working out what changed on screen
+    Layout,
+    /// Writing the changed parts to the terminal
+    Draw,
+    /// Flushing the terminal output
+    Flush,
+}
+
+impl FramePhase {
+    pub const ALL: [FramePhase; 4] = [Self::Events, Self::Layout, Self::Draw, Self::Flush];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Events => "events",
+            Self::Layout => "layout",
+            Self::Draw => "draw",
+            Self::Flush => "flush",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+type FrameTimes = [Duration; 4];
+
+/// Per-frame and per-command timings, collected while profiling is on
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    started: Instant,
+    /// Phases of the frame in progress
+    current: FrameTimes,
+    /// Whether the frame in progress handled input
+    current_input: bool,
+    frames: VecDeque<FrameTimes>,
+    /// Execution times of each ex command, in the order first seen
+    commands: Vec<(String, Vec<Duration>)>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            current: FrameTimes::default(),
+            current_input: false,
+            frames: VecDeque::new(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// Add time spent in a phase of the current frame
+    pub fn record(&mut self, phase: FramePhase, duration: Duration) {
+        self.current[phase.index()] += duration;
+    }
+
+    /// Add time spent handling an input event
+    pub fn record_input(&mut self, duration: Duration) {
+        self.record(FramePhase::Events, duration);
+        self.current_input = true;
+    }
+
+    /// Finish the current frame. Idle iterations, which neither handled input
+    /// nor drew anything, are not counted.
+    pub fn end_frame(&mut self) {
+        let frame = std::mem::take(&mut self.current);
+        let input = std::mem::take(&mut self.current_input);
+        if !input && frame[FramePhase::Draw.index()].is_zero() {
+            return;
+        }
+        if self.frames.len() == MAX_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    pub fn record_command(&mut self, name: &str, duration: Duration) {
+        match self.commands.iter_mut().find(|(n, _)| n == name) {
+            Some((_, times)) => times.push(duration),
+            None => self.commands.push((name.to_string(), vec![duration])),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Summary of the timings, one table for frames and one for commands
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        let _ = writeln!(
+            report,
+            "Profile: {} frames over {:.1}s",
+            self.frames.len(),
+            self.started.elapsed().as_secs_f64()
+        );
+
+        let _ = writeln!(report);
+        let _ = writeln!(
+            report,
+            "{:<12} {:>10} {:>10} {:>10} {:>10}",
+            "phase", "avg", "p50", "p95", "max"
+        );
+        for phase in FramePhase::ALL {
+            let times: Vec<Duration> = self.frames.iter().map(|f| f[phase.index()]).collect();
+            write_stats_row(&mut report, phase.name(), &times);
+        }
+        let totals: Vec<Duration> = self.frames.iter().map(|f| f.iter().sum()).collect();
+        write_stats_row(&mut report, "total", &totals);
+
+        if !self.commands.is_empty() {
+            let _ = writeln!(report);
+            let _ = writeln!(
+                report,
+                "{:<12} {:>10} {:>10} {:>10} {:>10}",
+                "command", "count", "total", "avg", "max"
+            );
+            let mut commands: Vec<&(String, Vec<Duration>)> = self.commands.iter().collect();
+            commands.sort_by_key(|(_, times)| std::cmp::Reverse(times.iter().sum::<Duration>()));
+            for (name, times) in commands {
+                let total: Duration = times.iter().sum();
+                let _ = writeln!(
+                    report,
+                    "{:<12} {:>10} {:>10} {:>10} {:>10}",
+                    name,
+                    times.len(),
+                    format_ms(total),
+                    format_ms(total / times.len() as u32),
+                    format_ms(times.iter().max().copied().unwrap_or_default())
+                );
+            }
+        }
+        report
+    }
+}
+
+fn write_stats_row(report: &mut String, name: &str, times: &[Duration]) {
+    let mut sorted = times.to_vec();
+    sorted.sort();
+    let percentile = |p: usize| {
+        sorted
+            .get((sorted.len() * p / 100).min(sorted.len().saturating_sub(1)))
+            .copied()
+            .unwrap_or_default()
+    };
+    let avg = match sorted.len() {
+        0 => Duration::ZERO,
+        n => sorted.iter().sum::<Duration>() / n as u32,
+    };
+    let _ = writeln!(
+        report,
+        "{:<12} {:>10} {:>10} {:>10} {:>10}",
+        name,
+        format_ms(avg),
+        format_ms(percentile(50)),
+        format_ms(percentile(95)),
+        format_ms(sorted.last().copied().unwrap_or_default())
+    );
+}
+
+fn format_ms(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,572 @@
+use crate::tui::json::JsonValue;
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Connection of an RPC client
+pub type ClientId = u64;
+
+type Clients = Arc<Mutex<HashMap<ClientId, Box<dyn Write + Send>>>>;
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// The request was valid but the editor could not carry it out
+    pub const FAILED: i64 = -32000;
+
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        JsonValue::object([
+            ("code", JsonValue::Number(self.code as f64)),
+            ("message", JsonValue::from(self.message.as_str())),
+        ])
+    }
+}
+
+/// A request read from a client, waiting for the editor to handle it
+#[derive(Debug, Clone)]
+pub struct RpcRequest {
+    pub client: ClientId,
+    /// `None` for notifications, which get no response
+    pub id: Option<JsonValue>,
+    pub method: String,
+    pub params: JsonValue,
+}
+
+/// JSON-RPC 2.0 server on a Unix socket, one message per line.
+///
+/// Client connections are read on background threads; requests are handed to
+/// the editor's event loop, which answers them with [`respond`](Self::respond).
+pub struct RpcServer {
+    path: PathBuf,
+    requests: Receiver<RpcRequest>,
+    clients: Clients,
+    /// Clients subscribed to each event
+    subscriptions: HashMap<String, Vec<ClientId>>,
+}
+
+impl RpcServer {
+    /// Start listening on `path`. A stale socket left by a crashed instance is
+    /// replaced; one that still answers is an error. Only the user running
+    /// the editor may connect, as clients can type into it.
+    #[cfg(unix)]
+    pub fn listen(path: &Path) -> io::Result<Self> {
+        use std::fs::{DirBuilder, Permissions};
+        use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+        use std::os::unix::net::{UnixListener, UnixStream};
+
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} is in use by another server", path.display()),
+                ));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(parent)?;
+            // `create` leaves a directory that was already there as it is
+            check_private_dir(parent)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, Permissions::from_mode(0o600))?;
+
+        let (sender, requests) = mpsc::channel();
+        let clients: Clients = Arc::default();
+        let accept_clients = Arc::clone(&clients);
+        std::thread::spawn(move || {
+            for (id, stream) in (0..).zip(listener.incoming()) {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let Ok(writer) = stream.try_clone() else {
+                    continue;
+                };
+                if let Ok(mut clients) = accept_clients.lock() {
+                    clients.insert(id, Box::new(writer));
+                }
+                let sender = sender.clone();
+                let clients = Arc::clone(&accept_clients);
+                std::thread::spawn(move || read_client(id, stream, sender, clients));
+            }
+        });
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            requests,
+            clients,
+            subscriptions: HashMap::new(),
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn listen(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the RPC server needs Unix domain sockets",
+        ))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Next request waiting to be handled, without blocking
+    pub fn try_recv(&self) -> Option<RpcRequest> {
+        self.requests.try_recv().ok()
+    }
+
+    /// Send the result of a request; notifications get no response
+    pub fn respond(
+        &mut self,
+        client: ClientId,
+        id: Option<JsonValue>,
+        result: Result<JsonValue, RpcError>,
+    ) {
+        let Some(id) = id else {
+            return;
+        };
+        let outcome = match result {
+            Ok(value) => ("result", value),
+            Err(error) => ("error", error.to_json()),
+        };
+        let message = JsonValue::object([("jsonrpc", JsonValue::from("2.0")), ("id", id), outcome]);
+        send(&self.clients, client, &message);
+    }
+
+    pub fn subscribe(&mut self, client: ClientId, event: &str) {
+        let clients = self.subscriptions.entry(event.to_string()).or_default();
+        if !clients.contains(&client) {
+            clients.push(client);
+        }
+    }
+
+    pub fn unsubscribe(&mut self, client: ClientId, event: &str) {
+        if let Some(clients) = self.subscriptions.get_mut(event) {
+            clients.retain(|&c| c != client);
+        }
+    }
+
+    /// Send an `event` notification to the clients subscribed to it
+    pub fn notify(&mut self, event: &str, params: JsonValue) {
+        let Some(subscribers) = self.subscriptions.get_mut(event) else {
+            return;
+        };
+        let message = JsonValue::object([
+            ("jsonrpc", JsonValue::from("2.0")),
+            ("method", JsonValue::from(event)),
+            ("params", params),
+        ]);
+        // Clients that went away are dropped from the subscription
+        subscribers.retain(|&client| send(&self.clients, client, &message));
+    }
+}
+
+impl Drop for RpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Files whose presence marks the root of a workspace
+const WORKSPACE_MARKERS: [&str; 2] = [".git", ".niv.toml"];
+
+/// Root of the workspace containing `dir`: the nearest ancestor with a `.git`
+/// or `.niv.toml`, or `dir` itself when there is none
+pub fn workspace_root(dir: &Path) -> PathBuf {
+    find_workspace_root(dir).unwrap_or_else(|| dir.to_path_buf())
+}
+
+/// The nearest ancestor of `dir` with a `.git` or `.niv.toml`, if any
+pub fn find_workspace_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| {
+            WORKSPACE_MARKERS
+                .iter()
+                .any(|marker| ancestor.join(marker).exists())
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Hash of a workspace root that names its files, the same across builds
+pub fn workspace_hash(root: &Path) -> u64 {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    // FNV-1a, which unlike `DefaultHasher` is the same across builds
+    root.to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// Socket of the server for a workspace, so `--remote` finds the instance
+/// started in the same project. Lives in the runtime directory, or without
+/// one in a directory of the user's own under the temporary directory,
+/// which `RpcServer::listen` creates private. Both ends refuse the
+/// directory if it isn't.
+pub fn workspace_socket_path(root: &Path) -> PathBuf {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            let user = std::env::var("USER").unwrap_or_default();
+            std::env::temp_dir().join(format!("niv-{}", user))
+        });
+    dir.join(format!("niv-{:016x}.sock", workspace_hash(root)))
+}
+
+/// Fail unless `dir` is a directory of the user's own that nobody else can
+/// write to. Otherwise another user could have made it first and put a
+/// socket there that receives the paths and keys meant for the editor.
+#[cfg(unix)]
+fn check_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = std::fs::symlink_metadata(dir)?;
+    // SAFETY: getuid has no preconditions and always succeeds
+    let uid = unsafe { libc::getuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "{} must be a directory only its owner can use",
+                dir.display()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Connection to a running server, for `niv --remote`
+pub struct RpcClient {
+    writer: Box<dyn Write>,
+    reader: Box<dyn BufRead>,
+    next_id: usize,
+}
+
+impl RpcClient {
+    /// Connect to the server at `path`, unless its directory could belong
+    /// to another user's server
+    #[cfg(unix)]
+    pub fn connect(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            check_private_dir(parent)?;
+        }
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self {
+            writer: Box::new(stream),
+            reader: Box::new(reader),
+            next_id: 0,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn connect(_path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the RPC client needs Unix domain sockets",
+        ))
+    }
+
+    /// Call a method and wait for its result; notifications arriving in the
+    /// meantime are skipped
+    pub fn call(&mut self, method: &str, params: JsonValue) -> io::Result<JsonValue> {
+        self.next_id += 1;
+        let id = JsonValue::from(self.next_id);
+        let request = JsonValue::object([
+            ("jsonrpc", JsonValue::from("2.0")),
+            ("id", id.clone()),
+            ("method", JsonValue::from(method)),
+            ("params", params),
+        ]);
+        writeln!(self.writer, "{}", request)?;
+        self.writer.flush()?;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "server closed the connection",
+                ));
+            }
+            let response = JsonValue::parse(&line)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if response.get("id") != Some(&id) {
+                continue;
+            }
+            if let Some(error) = response.get("error") {
+                let message = error.get("message").and_then(JsonValue::as_str);
+                return Err(io::Error::other(
+                    message.unwrap_or("request failed").to_string(),
+                ));
+            }
+            return Ok(response.get("result").cloned().unwrap_or(JsonValue::Null));
+        }
+    }
+}
+
+/// Write one message to a client; false if the client is gone
+fn send(clients: &Clients, client: ClientId, message: &JsonValue) -> bool {
+    let Ok(mut clients) = clients.lock() else {
+        return false;
+    };
+    let Some(writer) = clients.get_mut(&client) else {
+        return false;
+    };
+    if writeln!(writer, "{}", message)
+        .and_then(|()| writer.flush())
+        .is_err()
+    {
+        clients.remove(&client);
+        return false;
+    }
+    true
+}
+
+/// Longest request line read from a client; a client sending a longer one
+/// is answered with an error and disconnected
+const MAX_REQUEST_LEN: usize = 16 << 20;
+
+/// Read requests from one client until it disconnects
+fn read_client(
+    client: ClientId,
+    stream: impl io::Read,
+    sender: Sender<RpcRequest>,
+    clients: Clients,
+) {
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        let limit = MAX_REQUEST_LEN as u64 + 1;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        if line.len() > MAX_REQUEST_LEN {
+            let error = RpcError::new(RpcError::INVALID_REQUEST, "request too long");
+            send_error(&clients, client, JsonValue::Null, error);
+            break;
+        }
+        let Ok(line) = std::str::from_utf8(&line) else {
+            let error = RpcError::new(RpcError::PARSE_ERROR, "request is not UTF-8");
+            send_error(&clients, client, JsonValue::Null, error);
+            continue;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_request(client, line) {
+            Ok(request) => {
+                if sender.send(request).is_err() {
+                    break;
+                }
+            }
+            Err((id, error)) => {
+                send_error(&clients, client, id, error);
+            }
+        }
+    }
+    if let Ok(mut clients) = clients.lock() {
+        clients.remove(&client);
+    }
+}
+
+/// Answer a request that couldn't be read with `error`
+fn send_error(clients: &Clients, client: ClientId, id: JsonValue, error: RpcError) {
+    let message = JsonValue::object([
+        ("jsonrpc", JsonValue::from("2.0")),
+        ("id", id),
+        ("error", error.to_json()),
+    ]);
+    send(clients, client, &message);
+}
+
+/// Parse one line into a request, or the error to answer it with
+fn parse_request(client: ClientId, line: &str) -> Result<RpcRequest, (JsonValue, RpcError)> {
+    let message = JsonValue::parse(line)
+        .map_err(|e| (JsonValue::Null, RpcError::new(RpcError::PARSE_ERROR, e)))?;
+    let id = message.get("id").cloned();
+    let Some(method) = message.get("method").and_then(JsonValue::as_str) else {
+        return Err((
+            id.unwrap_or(JsonValue::Null),
+            RpcError::new(RpcError::INVALID_REQUEST, "missing method"),
+        ));
+    };
+    Ok(RpcRequest {
+        client,
+        id,
+        method: method.to_string(),
+        params: message
+            .get("params")
+            .cloned()
+            .unwrap_or(JsonValue::Object(Vec::new())),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use niv_fs::testing::TempDir;
+
+    /// A client writing into a shared buffer, to read back what it was sent
+    #[derive(Clone, Default)]
+    struct Recorder(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Recorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Recorder {
+        fn messages(&self) -> Vec<JsonValue> {
+            let sent = String::from_utf8(self.0.lock().unwrap().clone()).unwrap();
+            sent.lines()
+                .map(|line| JsonValue::parse(line).unwrap())
+                .collect()
+        }
+    }
+
+    /// Feed `input` to `read_client`, returning the requests it passed on
+    /// and the replies it sent itself
+    fn read_input(input: &[u8]) -> (Vec<RpcRequest>, Vec<JsonValue>) {
+        let recorder = Recorder::default();
+        let clients: Clients = Arc::default();
+        clients
+            .lock()
+            .unwrap()
+            .insert(1, Box::new(recorder.clone()));
+        let (sender, requests) = mpsc::channel();
+        read_client(1, input, sender, clients);
+        (requests.try_iter().collect(), recorder.messages())
+    }
+
+    fn error_code(reply: &JsonValue) -> Option<usize> {
+        let code = reply.get("error")?.get("code")?;
+        match code {
+            JsonValue::Number(n) => Some(-*n as usize),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_parse_request_reads_id_method_and_params() {
+        let line = r#"{"jsonrpc":"2.0","id":7,"method":"open","params":{"path":"a"}}"#;
+        let request = parse_request(3, line).unwrap();
+        assert_eq!(request.client, 3);
+        assert_eq!(request.id, Some(JsonValue::Number(7.0)));
+        assert_eq!(request.method, "open");
+        assert_eq!(request.params.get("path"), Some(&JsonValue::from("a")));
+
+        // Notifications have no id, and params default to an empty object
+        let request = parse_request(3, r#"{"method":"ping"}"#).unwrap();
+        assert_eq!(request.id, None);
+        assert_eq!(request.params, JsonValue::Object(Vec::new()));
+    }
+
+    #[test]
+    fn test_parse_request_errors() {
+        let (id, error) = parse_request(1, "{not json").unwrap_err();
+        assert_eq!(id, JsonValue::Null);
+        assert_eq!(error.code, RpcError::PARSE_ERROR);
+
+        let (id, error) = parse_request(1, r#"{"id":"x","params":[]}"#).unwrap_err();
+        assert_eq!(id, JsonValue::from("x"));
+        assert_eq!(error.code, RpcError::INVALID_REQUEST);
+
+        let (id, error) = parse_request(1, r#"{"method":5}"#).unwrap_err();
+        assert_eq!(id, JsonValue::Null);
+        assert_eq!(error.code, RpcError::INVALID_REQUEST);
+    }
+
+    #[test]
+    fn test_read_client_passes_requests_and_answers_bad_ones() {
+        let input = b"{\"id\":1,\"method\":\"a\"}\n\n[[[\n\xff\n{\"id\":2}\n{\"method\":\"b\"}";
+        let (requests, replies) = read_input(input);
+        let methods: Vec<_> = requests.iter().map(|r| r.method.as_str()).collect();
+        assert_eq!(methods, ["a", "b"]);
+        let codes: Vec<_> = replies.iter().map(error_code).collect();
+        assert_eq!(codes, [Some(32700), Some(32700), Some(32600)]);
+        assert_eq!(replies[2].get("id"), Some(&JsonValue::Number(2.0)));
+    }
+
+    #[test]
+    fn test_read_client_drops_overlong_requests() {
+        let mut input = vec![b' '; MAX_REQUEST_LEN + 10];
+        input.extend_from_slice(b"\n{\"method\":\"after\"}\n");
+        let (requests, replies) = read_input(&input);
+        assert!(requests.is_empty());
+        assert_eq!(replies.len(), 1);
+        assert_eq!(error_code(&replies[0]), Some(32600));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_listen_keeps_the_socket_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("rpc");
+        let path = dir.join("run").join("test.sock");
+        let server = RpcServer::listen(&path).unwrap();
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert!(RpcClient::connect(&path).is_ok());
+        drop(server);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sockets_in_directories_others_can_use_are_refused() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("rpc_shared");
+        let shared = dir.join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o777)).unwrap();
+        let error = RpcServer::listen(&shared.join("test.sock")).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        let error = RpcClient::connect(&shared.join("test.sock")).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+        // A link to a private directory could be swapped for another one
+        let private = dir.join("private");
+        std::fs::create_dir(&private).unwrap();
+        std::fs::set_permissions(&private, std::fs::Permissions::from_mode(0o700)).unwrap();
+        std::os::unix::fs::symlink(&private, dir.join("link")).unwrap();
+        let error = RpcServer::listen(&dir.join("link").join("test.sock"))
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        assert!(RpcServer::listen(&private.join("test.sock")).is_ok());
+    }
+}
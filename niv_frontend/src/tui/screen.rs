@@ -0,0 +1,487 @@
+//! In-memory terminal for testing the renderer without a real terminal.
+//!
+//! [`Screen`] reads the escape sequences crossterm writes and keeps the
+//! resulting cell grid, so tests can assert what a terminal would show.
+//! [`Output`] is where the editor draws to: stdout, or a `Screen`.
+//! [`clip_to_width`] and [`fit_to_width`] keep text drawn into a rect from
+//! running past its edge.
+
+use crate::tui::charinfo::is_combining_mark;
+use crate::tui::display::is_wide_char;
+use crossterm::style::{Color, Colored};
+use std::cell::{Ref, RefCell};
+use std::io::{self, Write};
+
+/// One character cell
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cell {
+    /// Character drawn in the cell with any combining marks; empty for the
+    /// second cell of a wide character
+    pub symbol: String,
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub underline: bool,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            symbol: " ".to_string(),
+            fg: Color::Reset,
+            bg: Color::Reset,
+            bold: false,
+            underline: false,
+        }
+    }
+}
+
+/// Style set by the last SGR sequences
+#[derive(Debug, Clone, Copy)]
+struct Pen {
+    fg: Color,
+    bg: Color,
+    bold: bool,
+    underline: bool,
+}
+
+impl Default for Pen {
+    fn default() -> Self {
+        Self {
+            fg: Color::Reset,
+            bg: Color::Reset,
+            bold: false,
+            underline: false,
+        }
+    }
+}
+
+/// A grid of cells updated by the ANSI output written to it.
+///
+/// Understands cursor movement, clearing the screen or a line, colors, bold,
+/// underline and cursor visibility; other sequences are ignored. Writes may
+/// split sequences and characters anywhere.
+#[derive(Debug, Clone)]
+pub struct Screen {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    cursor: (u16, u16),
+    cursor_visible: bool,
+    pen: Pen,
+    /// Bytes of an incomplete escape sequence or character
+    pending: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(width: u16, height: u16) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width as usize * height as usize],
+            cursor: (0, 0),
+            cursor_visible: true,
+            pen: Pen::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Change the size, keeping the cells that still fit
+    pub fn resize(&mut self, width: u16, height: u16) {
+        let mut cells = vec![Cell::default(); width as usize * height as usize];
+        for y in 0..height.min(self.height) {
+            for x in 0..width.min(self.width) {
+                cells[y as usize * width as usize + x as usize] = self.cell(x, y).clone();
+            }
+        }
+        self.width = width;
+        self.height = height;
+        self.cells = cells;
+        self.cursor = (
+            self.cursor.0.min(width.saturating_sub(1)),
+            self.cursor.1.min(height.saturating_sub(1)),
+        );
+    }
+
+    /// Cell at column `x` of row `y`
+    ///
+    /// # Panics
+    ///
+    /// If the position is outside the screen.
+    pub fn cell(&self, x: u16, y: u16) -> &Cell {
+        assert!(
+            x < self.width && y < self.height,
+            "({}, {}) is off screen",
+            x,
+            y
+        );
+        &self.cells[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Text of row `y` without trailing spaces
+    pub fn row_text(&self, y: u16) -> String {
+        let start = y as usize * self.width as usize;
+        let row: String = self.cells[start..start + self.width as usize]
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect();
+        row.trim_end().to_string()
+    }
+
+    /// Text of all rows, one per line
+    pub fn text(&self) -> String {
+        (0..self.height)
+            .map(|y| self.row_text(y))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Column and row where `needle` first appears on a single row
+    pub fn find(&self, needle: &str) -> Option<(u16, u16)> {
+        (0..self.height).find_map(|y| {
+            let start = y as usize * self.width as usize;
+            let row = &self.cells[start..start + self.width as usize];
+            // The second cell of a wide character can't start a match
+            (0..row.len())
+                .filter(|&x| !row[x].symbol.is_empty())
+                .find_map(|x| {
+                    let mut text = String::new();
+                    for cell in &row[x..] {
+                        if text.len() >= needle.len() {
+                            break;
+                        }
+                        text.push_str(&cell.symbol);
+                    }
+                    text.starts_with(needle).then_some((x as u16, y))
+                })
+        })
+    }
+
+    /// Cursor position as column and row
+    pub fn cursor(&self) -> (u16, u16) {
+        self.cursor
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    fn clear_cells(&mut self, from: usize, to: usize) {
+        let to = to.min(self.cells.len());
+        for cell in self.cells.iter_mut().take(to).skip(from) {
+            *cell = Cell::default();
+        }
+    }
+
+    fn cursor_index(&self) -> usize {
+        self.cursor.1 as usize * self.width as usize + self.cursor.0 as usize
+    }
+
+    fn put_char(&mut self, ch: char) {
+        if self.width == 0 || self.height == 0 {
+            return;
+        }
+        if is_combining_mark(ch) {
+            if self.cursor.0 > 0 {
+                let index = self.cursor_index() - 1;
+                self.cells[index].symbol.push(ch);
+            }
+            return;
+        }
+        let width = cell_width(ch) as u16;
+        // Like a terminal, the cursor stays on the last column and a later
+        // character overwrites it
+        let (x, y) = self.cursor;
+        if x + width > self.width {
+            return;
+        }
+        let index = self.cursor_index();
+        self.cells[index] = Cell {
+            symbol: ch.to_string(),
+            fg: self.pen.fg,
+            bg: self.pen.bg,
+            bold: self.pen.bold,
+            underline: self.pen.underline,
+        };
+        if width == 2 {
+            self.cells[index + 1] = Cell {
+                symbol: String::new(),
+                ..self.cells[index].clone()
+            };
+        }
+        self.cursor = ((x + width).min(self.width - 1), y);
+    }
+
+    fn apply_csi(&mut self, params: &str, command: u8) {
+        let numbers: Vec<u16> = params
+            .trim_start_matches(['?', '>', '<', '='])
+            .split(';')
+            .map(|n| n.parse().unwrap_or(0))
+            .collect();
+        let n = |i: usize| numbers.get(i).copied().unwrap_or(0);
+        let count = n(0).max(1);
+        let (x, y) = self.cursor;
+        let max_x = self.width.saturating_sub(1);
+        let max_y = self.height.saturating_sub(1);
+        match command {
+            b'H' | b'f' => {
+                self.cursor = (
+                    n(1).saturating_sub(1).min(max_x),
+                    n(0).saturating_sub(1).min(max_y),
+                )
+            }
+            b'A' => self.cursor = (x, y.saturating_sub(count)),
+            b'B' => self.cursor = (x, y.saturating_add(count).min(max_y)),
+            b'C' => self.cursor = (x.saturating_add(count).min(max_x), y),
+            b'D' => self.cursor = (x.saturating_sub(count), y),
+            b'G' => self.cursor = (n(0).saturating_sub(1).min(max_x), y),
+            b'J' => match n(0) {
+                0 => self.clear_cells(self.cursor_index(), self.cells.len()),
+                1 => self.clear_cells(0, self.cursor_index() + 1),
+                _ => self.clear_cells(0, self.cells.len()),
+            },
+            b'K' => {
+                let row = y as usize * self.width as usize;
+                let (from, to) = match n(0) {
+                    0 => (self.cursor_index(), row + self.width as usize),
+                    1 => (row, self.cursor_index() + 1),
+                    _ => (row, row + self.width as usize),
+                };
+                self.clear_cells(from, to);
+            }
+            b'h' | b'l' if params == "?25" => self.cursor_visible = command == b'h',
+            b'm' => self.apply_sgr(params),
+            _ => {}
+        }
+    }
+
+    /// Apply a "select graphic rendition" sequence; crossterm writes one
+    /// attribute or color per sequence
+    fn apply_sgr(&mut self, params: &str) {
+        match params {
+            "" | "0" => self.pen = Pen::default(),
+            "1" => self.pen.bold = true,
+            "22" => self.pen.bold = false,
+            "4" => self.pen.underline = true,
+            "24" => self.pen.underline = false,
+            _ => match Colored::parse_ansi(params) {
+                Some(Colored::ForegroundColor(color)) => self.pen.fg = color,
+                Some(Colored::BackgroundColor(color)) => self.pen.bg = color,
+                _ => {}
+            },
+        }
+    }
+
+    /// Interpret as much of the pending output as is complete
+    fn process(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let mut pos = 0;
+        while pos < pending.len() {
+            let rest = &pending[pos..];
+            match rest[0] {
+                0x1b => {
+                    let Some(&kind) = rest.get(1) else {
+                        break;
+                    };
+                    if kind != b'[' {
+                        pos += 2;
+                        continue;
+                    }
+                    let Some(end) = rest[2..].iter().position(|b| (0x40..=0x7e).contains(b)) else {
+                        break;
+                    };
+                    let params = String::from_utf8_lossy(&rest[2..2 + end]).into_owned();
+                    self.apply_csi(&params, rest[2 + end]);
+                    pos += end + 3;
+                }
+                b'\r' => {
+                    self.cursor.0 = 0;
+                    pos += 1;
+                }
+                b'\n' => {
+                    self.cursor.1 = (self.cursor.1 + 1).min(self.height.saturating_sub(1));
+                    pos += 1;
+                }
+                lead => {
+                    let len = match lead {
+                        0xf0..=0xf7 => 4,
+                        0xe0..=0xef => 3,
+                        0xc0..=0xdf => 2,
+                        _ => 1,
+                    };
+                    if rest.len() < len {
+                        break;
+                    }
+                    let ch = std::str::from_utf8(&rest[..len])
+                        .ok()
+                        .and_then(|s| s.chars().next())
+                        .unwrap_or(char::REPLACEMENT_CHARACTER);
+                    if !ch.is_control() {
+                        self.put_char(ch);
+                    }
+                    pos += len;
+                }
+            }
+        }
+        self.pending = pending[pos..].to_vec();
+    }
+}
+
+impl Write for Screen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        self.process();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Columns a character takes on the terminal
+fn cell_width(ch: char) -> usize {
+    match ch {
+        _ if is_combining_mark(ch) => 0,
+        _ if is_wide_char(ch) => 2,
+        _ => 1,
+    }
+}
+
+/// Character printed for `ch`: tabs become spaces and other control
+/// characters, which could start escape sequences, become `?`
+fn printable(ch: char) -> char {
+    match ch {
+        '\t' => ' ',
+        _ if ch.is_control() => '?',
+        _ => ch,
+    }
+}
+
+/// `text` cut to at most `width` terminal columns, and the columns it takes.
+/// A wide character cut in half by the edge becomes a space.
+fn clip(text: &str, width: usize) -> (String, usize) {
+    let mut clipped = String::new();
+    let mut used = 0;
+    for ch in text.chars().map(printable) {
+        let ch_width = cell_width(ch);
+        if used + ch_width > width {
+            if used < width {
+                clipped.push(' ');
+                used += 1;
+            }
+            break;
+        }
+        clipped.push(ch);
+        used += ch_width;
+    }
+    (clipped, used)
+}
+
+/// `text` cut to at most `width` terminal columns, so that printing it
+/// stays inside a rect rather than running past the edge of the terminal
+/// and wrapping. See [`fit_to_width`] for filling the rect too.
+pub fn clip_to_width(text: &str, width: usize) -> String {
+    clip(text, width).0
+}
+
+/// `text` cut or padded with spaces to exactly `width` terminal columns
+pub fn fit_to_width(text: &str, width: usize) -> String {
+    let (mut fitted, used) = clip(text, width);
+    fitted.extend(std::iter::repeat_n(' ', width - used));
+    fitted
+}
+
+/// The end of `text` that fits in `width` terminal columns, for text whose
+/// end matters most, like a command line being typed
+pub fn clip_start_to_width(text: &str, width: usize) -> String {
+    let mut kept = Vec::new();
+    let mut used = 0;
+    for ch in text.chars().rev().map(printable) {
+        used += cell_width(ch);
+        if used > width {
+            // Marks whose base character didn't fit go too
+            while kept.last().is_some_and(|&mark| is_combining_mark(mark)) {
+                kept.pop();
+            }
+            break;
+        }
+        kept.push(ch);
+    }
+    kept.into_iter().rev().collect()
+}
+
+/// Where the editor draws
+#[derive(Debug)]
+pub enum Output {
+    Stdout,
+    /// Headless: an in-memory screen
+    Screen(RefCell<Screen>),
+}
+
+impl Output {
+    /// The in-memory screen, when drawing headless
+    pub fn screen(&self) -> Option<Ref<'_, Screen>> {
+        match self {
+            Output::Stdout => None,
+            Output::Screen(screen) => Some(screen.borrow()),
+        }
+    }
+}
+
+impl Write for &Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout => io::stdout().write(buf),
+            Output::Screen(screen) => screen.borrow_mut().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout => io::stdout().flush(),
+            Output::Screen(_) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::{cursor::MoveTo, queue, style::Print, style::Stylize};
+
+    #[test]
+    fn test_screen_draws_styled_text() {
+        let mut screen = Screen::new(10, 3);
+        queue!(
+            screen,
+            MoveTo(2, 1),
+            Print("ab".with(Color::Red).on(Color::Blue)),
+            Print("漢c")
+        )
+        .unwrap();
+        assert_eq!(screen.row_text(1), "  ab漢c");
+        assert_eq!(screen.cell(2, 1).fg, Color::Red);
+        assert_eq!(screen.cell(2, 1).bg, Color::Blue);
+        assert_eq!(screen.cell(4, 1).fg, Color::Reset);
+        assert_eq!(screen.cell(5, 1).symbol, "");
+        assert_eq!(screen.find("c"), Some((6, 1)));
+        assert_eq!(screen.cursor(), (7, 1));
+
+        // Sequences split across writes
+        screen.write_all(b"\x1b[1;").unwrap();
+        screen.write_all(b"1H\xe2\x82").unwrap();
+        screen.write_all(b"\xac\x1b[2K").unwrap();
+        assert_eq!(screen.row_text(0), "");
+        screen.write_all(b"\x1b[2J").unwrap();
+        assert_eq!(screen.text(), "\n\n");
+    }
+}
@@ -0,0 +1,126 @@
+use crate::tui::killring::Yank;
+use crate::tui::rpc::workspace_hash;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// First line of a session file
+const SESSION_HEADER: &str = "niv session";
+
+/// A file open in a session and where its cursor was
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionFile {
+    pub path: PathBuf,
+    /// 0-based cursor line
+    pub line: usize,
+    /// Byte column of the cursor
+    pub col: usize,
+}
+
+/// The files open when a session was saved, and the kill ring
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub files: Vec<SessionFile>,
+    /// Index in `files` of the current buffer
+    pub current: usize,
+    /// Kill ring entries, newest first
+    pub yanks: Vec<Yank>,
+}
+
+impl Session {
+    /// Text of the session file: a header, the current file, a
+    /// `file {line} {col} {path}` line per file and a `yank {line|char} {text}`
+    /// line per kill ring entry, with backslashes and newlines escaped
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\ncurrent {}\n", SESSION_HEADER, self.current);
+        for file in &self.files {
+            let _ = writeln!(
+                text,
+                "file {} {} {}",
+                file.line,
+                file.col,
+                file.path.display()
+            );
+        }
+        for yank in &self.yanks {
+            let kind = if yank.linewise { "line" } else { "char" };
+            let _ = writeln!(text, "yank {} {}", kind, escape(&yank.text));
+        }
+        text
+    }
+
+    /// Read a session file's text; None when it isn't one. Lines that are
+    /// not understood are skipped.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != SESSION_HEADER {
+            return None;
+        }
+        let mut session = Session::default();
+        for line in lines {
+            if let Some(current) = line.strip_prefix("current ") {
+                session.current = current.parse().unwrap_or(0);
+            } else if let Some(file) = line.strip_prefix("file ") {
+                let mut fields = file.splitn(3, ' ');
+                let (Some(line), Some(col), Some(path)) =
+                    (fields.next(), fields.next(), fields.next())
+                else {
+                    continue;
+                };
+                let (Ok(line), Ok(col)) = (line.parse(), col.parse()) else {
+                    continue;
+                };
+                session.files.push(SessionFile {
+                    path: PathBuf::from(path),
+                    line,
+                    col,
+                });
+            } else if let Some(yank) = line.strip_prefix("yank ") {
+                let Some((kind, text)) = yank.split_once(' ') else {
+                    continue;
+                };
+                let linewise = match kind {
+                    "line" => true,
+                    "char" => false,
+                    _ => continue,
+                };
+                session.yanks.push(Yank {
+                    text: unescape(text),
+                    linewise,
+                });
+            }
+        }
+        Some(session)
+    }
+}
+
+/// Keep `text` on one line of the session file
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            next => unescaped.extend(next),
+        }
+    }
+    unescaped
+}
+
+/// File the auto-session of the project at `root` is kept in, under
+/// `sessions` in the state directory (e.g. `~/.local/state/niv/sessions`)
+pub fn session_path(root: &Path) -> PathBuf {
+    niv_fs::paths::state_dir()
+        .join("sessions")
+        .join(format!("{:016x}.session", workspace_hash(root)))
+}
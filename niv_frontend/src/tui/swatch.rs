@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+/// Escape sequence openers, raw and as written in source code
+const ANSI_PREFIXES: [&str; 7] = [
+    "\x1b[", "\\x1b[", "\\x1B[", "\\033[", "\\e[", "\\u001b[", "\\u001B[",
+];
+
+/// Colors of the 16 ANSI colors, as xterm draws them
+const ANSI_COLORS: [[u8; 3]; 16] = [
+    [0, 0, 0],
+    [205, 0, 0],
+    [0, 205, 0],
+    [205, 205, 0],
+    [0, 0, 238],
+    [205, 0, 205],
+    [0, 205, 205],
+    [229, 229, 229],
+    [127, 127, 127],
+    [255, 0, 0],
+    [0, 255, 0],
+    [255, 255, 0],
+    [92, 92, 255],
+    [255, 0, 255],
+    [0, 255, 255],
+    [255, 255, 255],
+];
+
+/// A color code in a line of text and the color it names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColorCode {
+    /// Byte range of the code
+    pub range: Range<usize>,
+    pub rgb: [u8; 3],
+}
+
+/// Color codes in `line`: `#RRGGBB` (with optional alpha), CSS `rgb()` and
+/// `rgba()`, and ANSI SGR sequences that set a color, raw or escaped as
+/// `\x1b[`, `\033[` or `\e[`
+pub fn color_codes(line: &str) -> Vec<ColorCode> {
+    let mut codes = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        let rest = &line[i..];
+        let word_start = !line[..i].ends_with(|ch: char| ch.is_alphanumeric() || ch == '_');
+        let found = hex_color(rest)
+            .or_else(|| word_start.then(|| css_rgb(rest)).flatten())
+            .or_else(|| ansi_color(rest));
+        match found {
+            Some((len, rgb)) => {
+                codes.push(ColorCode {
+                    range: i..i + len,
+                    rgb,
+                });
+                i += len;
+            }
+            None => i += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    codes
+}
+
+/// Whether text on `rgb` reads better in black than in white
+pub fn is_light(rgb: [u8; 3]) -> bool {
+    let [r, g, b] = rgb.map(u32::from);
+    r * 299 + g * 587 + b * 114 > 128_000
+}
+
+/// `#RRGGBB` or `#RRGGBBAA` at the start of `text`
+fn hex_color(text: &str) -> Option<(usize, [u8; 3])> {
+    let digits = text.strip_prefix('#')?;
+    let len = digits.bytes().take_while(u8::is_ascii_hexdigit).count();
+    let followed_by_word = digits[len..].starts_with(|ch: char| ch.is_alphanumeric() || ch == '_');
+    if !(len == 6 || len == 8) || followed_by_word {
+        return None;
+    }
+    let channel = |at: usize| u8::from_str_radix(&digits[at..at + 2], 16).ok();
+    Some((1 + len, [channel(0)?, channel(2)?, channel(4)?]))
+}
+
+/// CSS `rgb(r, g, b)` or `rgba(r, g, b, a)` at the start of `text`, with
+/// channels as numbers up to 255 or percentages
+fn css_rgb(text: &str) -> Option<(usize, [u8; 3])> {
+    let lower = text.get(..5)?.to_ascii_lowercase();
+    let open = if lower.starts_with("rgba(") {
+        5
+    } else if lower.starts_with("rgb(") {
+        4
+    } else {
+        return None;
+    };
+    let close = open + text[open..].find(')')?;
+    let mut channels = text[open..close]
+        .split(|ch: char| ch == ',' || ch == '/' || ch.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(|part| match part.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok().map(|p| p * 2.55),
+            None => part.parse::<f32>().ok(),
+        });
+    let mut channel = || {
+        let value = channels.next()??;
+        (0.0..=255.0).contains(&value).then(|| value.round() as u8)
+    };
+    Some((close + 1, [channel()?, channel()?, channel()?]))
+}
+
+/// An SGR sequence setting a foreground or background color at the start of
+/// `text`; the first color it sets
+fn ansi_color(text: &str) -> Option<(usize, [u8; 3])> {
+    let prefix = ANSI_PREFIXES
+        .iter()
+        .find(|prefix| text.starts_with(**prefix))?;
+    let params_len = text[prefix.len()..]
+        .bytes()
+        .take_while(|&byte| byte.is_ascii_digit() || byte == b';')
+        .count();
+    let end = prefix.len() + params_len;
+    if !text[end..].starts_with('m') {
+        return None;
+    }
+    let params: Vec<u16> = text[prefix.len()..end]
+        .split(';')
+        .map(|param| param.parse().unwrap_or(0))
+        .collect();
+
+    let mut i = 0;
+    while i < params.len() {
+        let rgb = match params[i] {
+            code @ (30..=37 | 40..=47) => Some(ANSI_COLORS[usize::from(code % 10)]),
+            code @ (90..=97 | 100..=107) => Some(ANSI_COLORS[usize::from(code % 10) + 8]),
+            38 | 48 => match params.get(i + 1) {
+                Some(5) => params.get(i + 2).map(|&n| xterm_color(n)),
+                Some(2) => match params.get(i + 2..i + 5) {
+                    Some(&[r, g, b]) => Some([r, g, b].map(|c| c.min(255) as u8)),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(rgb) = rgb {
+            return Some((end + 1, rgb));
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Color `n` of the xterm 256-color palette
+fn xterm_color(n: u16) -> [u8; 3] {
+    match n {
+        0..=15 => ANSI_COLORS[usize::from(n)],
+        16..=231 => {
+            let level = |c: u16| if c == 0 { 0 } else { (55 + c * 40) as u8 };
+            let n = n - 16;
+            [level(n / 36), level(n / 6 % 6), level(n % 6)]
+        }
+        _ => {
+            let gray = (8 + (n.min(255) - 232) * 10) as u8;
+            [gray; 3]
+        }
+    }
+}
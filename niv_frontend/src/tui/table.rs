@@ -0,0 +1,128 @@
+use crate::tui::display::{DisplayOptions, display_width};
+use std::ops::Range;
+use std::path::Path;
+
+/// Drawn between the columns of an aligned table
+pub const COLUMN_SEPARATOR: &str = " │ ";
+
+/// Delimiters tried when a file's extension doesn't tell
+const GUESSED_DELIMITERS: [char; 4] = ['\t', ',', ';', '|'];
+
+/// Delimiter of a file shown as a table, by its extension: `,` for `.csv`
+/// and a tab for `.tsv` and `.tab`
+pub fn table_delimiter(path: &Path) -> Option<char> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "csv" => Some(','),
+        "tsv" | "tab" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Guess the delimiter of a header line: whichever of tab, comma, semicolon
+/// and `|` splits it into the most fields
+pub fn guess_delimiter(line: &str) -> Option<char> {
+    GUESSED_DELIMITERS
+        .into_iter()
+        .map(|delimiter| (field_ranges(line, delimiter).len(), delimiter))
+        .filter(|&(fields, _)| fields > 1)
+        .max_by_key(|&(fields, _)| fields)
+        .map(|(_, delimiter)| delimiter)
+}
+
+/// Byte ranges of the fields of a line, without the delimiters. Delimiters
+/// inside double quotes don't split fields.
+pub fn field_ranges(line: &str, delimiter: char) -> Vec<Range<usize>> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, ch) in line.char_indices() {
+        if ch == '"' {
+            // A doubled quote inside quotes toggles twice and stays quoted
+            quoted = !quoted;
+        } else if ch == delimiter && !quoted {
+            fields.push(start..i);
+            start = i + ch.len_utf8();
+        }
+    }
+    fields.push(start..line.len());
+    fields
+}
+
+/// Width of each column when `lines` are laid out as a table
+pub fn column_widths<'a>(
+    lines: impl IntoIterator<Item = &'a str>,
+    delimiter: char,
+    options: &DisplayOptions,
+) -> Vec<usize> {
+    let mut widths: Vec<usize> = Vec::new();
+    for line in lines {
+        for (column, range) in field_ranges(line, delimiter).into_iter().enumerate() {
+            let width = display_width(&line[range], options);
+            match widths.get_mut(column) {
+                Some(max) => *max = (*max).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+    widths
+}
+
+/// `line` with its fields padded to the column widths and separated by
+/// [`COLUMN_SEPARATOR`]
+pub fn align_fields(
+    line: &str,
+    delimiter: char,
+    widths: &[usize],
+    options: &DisplayOptions,
+) -> String {
+    let fields = field_ranges(line, delimiter);
+    let last = fields.len() - 1;
+    let mut aligned = String::with_capacity(line.len());
+    for (column, range) in fields.into_iter().enumerate() {
+        let field = &line[range];
+        aligned.push_str(field);
+        if column < last {
+            let width = widths.get(column).copied().unwrap_or(0);
+            let padding = width.saturating_sub(display_width(field, options));
+            aligned.extend(std::iter::repeat_n(' ', padding));
+            aligned.push_str(COLUMN_SEPARATOR);
+        }
+    }
+    aligned
+}
+
+/// Display column of byte `col` of `line` once it is aligned with
+/// [`align_fields`]
+pub fn aligned_col(
+    line: &str,
+    col: usize,
+    delimiter: char,
+    widths: &[usize],
+    options: &DisplayOptions,
+) -> usize {
+    let separator = COLUMN_SEPARATOR.chars().count();
+    let mut start_col = 0;
+    for (column, range) in field_ranges(line, delimiter).into_iter().enumerate() {
+        if col <= range.end {
+            let offset = col.clamp(range.start, range.end);
+            return start_col + display_width(&line[range.start..offset], options);
+        }
+        let width = display_width(&line[range], options);
+        start_col += widths.get(column).copied().unwrap_or(0).max(width) + separator;
+    }
+    start_col
+}
+
+/// Byte offset of the start of the field after the one containing `col`,
+/// or before it when `backward`; None at the first or last field
+pub fn adjacent_field(line: &str, col: usize, delimiter: char, backward: bool) -> Option<usize> {
+    let fields = field_ranges(line, delimiter);
+    let current = fields.iter().position(|range| col <= range.end)?;
+    let target = if backward {
+        current.checked_sub(1)?
+    } else {
+        current + 1
+    };
+    fields.get(target).map(|range| range.start)
+}
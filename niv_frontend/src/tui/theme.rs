@@ -6,6 +6,8 @@ use niv_config::{Color as ConfigColor, ColorScheme, SyntaxColors, UiSettings};
 pub struct TerminalTheme {
     pub colors: ColorScheme,
     pub syntax: SyntaxColors,
+    /// Use the inactive colors for windows without focus
+    pub dim_inactive: bool,
 }
 
 impl Default for TerminalTheme {
@@ -13,6 +15,7 @@ impl Default for TerminalTheme {
         Self {
             colors: ColorScheme::default(),
             syntax: SyntaxColors::default(),
+            dim_inactive: true,
         }
     }
 }
@@ -23,6 +26,7 @@ impl TerminalTheme {
         Self {
             colors: ColorScheme::default(),
             syntax: SyntaxColors::default(),
+            dim_inactive: ui_settings.dim_inactive,
         }
     }
 
@@ -73,6 +77,27 @@ impl TerminalTheme {
         Self::hex_to_color(self.colors.status_fg)
     }
 
+    /// Get the text color of a window, dimmed when it does not have focus
+    pub fn text_fg(&self, focused: bool) -> Color {
+        if focused || !self.dim_inactive {
+            self.fg()
+        } else {
+            Self::hex_to_color(self.colors.inactive_fg)
+        }
+    }
+
+    /// Get status bar (foreground, background) of a window with or without focus
+    pub fn status_colors(&self, focused: bool) -> (Color, Color) {
+        if focused || !self.dim_inactive {
+            (self.status_fg(), self.status_bg())
+        } else {
+            (
+                Self::hex_to_color(self.colors.status_inactive_fg),
+                Self::hex_to_color(self.colors.status_inactive_bg),
+            )
+        }
+    }
+
     /// Get syntax colors
     pub fn keyword(&self) -> Color {
         Self::hex_to_color(self.syntax.keyword)
@@ -110,6 +135,15 @@ impl TerminalTheme {
     pub fn info(&self) -> Color {
         Self::hex_to_color(self.colors.info)
     }
+
+    /// Get colors for control characters (`^X`) and hidden characters (`<200b>`)
+    pub fn special_key(&self) -> Color {
+        Self::hex_to_color(self.colors.info)
+    }
+
+    pub fn non_text(&self) -> Color {
+        Self::hex_to_color(self.colors.line_numbers)
+    }
 }
 
 /// Styled text with color information
@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+
+/// Handle of a timer started on a [`TimerQueue`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimerId(u64);
+
+/// A timer whose deadline has passed, taken off the queue to be run
+#[derive(Debug)]
+pub struct DueTimer<T> {
+    pub id: TimerId,
+    pub action: T,
+    interval: Option<Duration>,
+    deadline: Instant,
+}
+
+#[derive(Debug)]
+struct Timer<T> {
+    id: TimerId,
+    deadline: Instant,
+    /// Set for repeating timers
+    interval: Option<Duration>,
+    action: T,
+}
+
+/// One-shot and repeating timers, run from the editor's event loop.
+///
+/// Timers are kept sorted by deadline. A due timer is taken off the queue with
+/// [`pop_due`](Self::pop_due) and handed back with [`finish`](Self::finish)
+/// once its action has run, so the action may start or stop timers itself.
+#[derive(Debug)]
+pub struct TimerQueue<T> {
+    timers: Vec<Timer<T>>,
+    next_id: u64,
+    /// Timer currently being run, and whether it was stopped while running
+    running: Option<(TimerId, bool)>,
+}
+
+impl<T> TimerQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            timers: Vec::new(),
+            next_id: 0,
+            running: None,
+        }
+    }
+
+    /// Run `action` once after `delay`
+    pub fn start_once(&mut self, delay: Duration, action: T) -> TimerId {
+        self.start(Instant::now() + delay, None, action)
+    }
+
+    /// Run `action` every `interval` until the timer is stopped
+    pub fn start_repeating(&mut self, interval: Duration, action: T) -> TimerId {
+        // A zero interval would keep the event loop from ever waiting for input
+        let interval = interval.max(Duration::from_millis(1));
+        self.start(Instant::now() + interval, Some(interval), action)
+    }
+
+    fn start(&mut self, deadline: Instant, interval: Option<Duration>, action: T) -> TimerId {
+        let id = TimerId(self.next_id);
+        self.next_id += 1;
+        self.insert(Timer {
+            id,
+            deadline,
+            interval,
+            action,
+        });
+        id
+    }
+
+    fn insert(&mut self, timer: Timer<T>) {
+        // Timers with the same deadline run in the order they were started
+        let index = self
+            .timers
+            .partition_point(|other| other.deadline <= timer.deadline);
+        self.timers.insert(index, timer);
+    }
+
+    /// Stop a timer. Returns false if it already fired or was stopped.
+    pub fn stop(&mut self, id: TimerId) -> bool {
+        if let Some((_, stopped)) = self
+            .running
+            .as_mut()
+            .filter(|(running, stopped)| *running == id && !*stopped)
+        {
+            *stopped = true;
+            return true;
+        }
+        match self.timers.iter().position(|timer| timer.id == id) {
+            Some(index) => {
+                self.timers.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Check whether a timer is still waiting to fire
+    pub fn is_active(&self, id: TimerId) -> bool {
+        self.timers.iter().any(|timer| timer.id == id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+
+    /// Deadline of the timer that fires first
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.timers.first().map(|timer| timer.deadline)
+    }
+
+    /// Take the first timer whose deadline is at or before `now`
+    pub fn pop_due(&mut self, now: Instant) -> Option<DueTimer<T>> {
+        if self.timers.first()?.deadline > now {
+            return None;
+        }
+        let timer = self.timers.remove(0);
+        self.running = Some((timer.id, false));
+        Some(DueTimer {
+            id: timer.id,
+            action: timer.action,
+            interval: timer.interval,
+            deadline: timer.deadline,
+        })
+    }
+
+    /// Hand back a timer after running it; repeating timers are scheduled again
+    /// unless they were stopped in the meantime
+    pub fn finish(&mut self, due: DueTimer<T>) {
+        let stopped = matches!(self.running.take(), Some((id, true)) if id == due.id);
+        let Some(interval) = due.interval.filter(|_| !stopped) else {
+            return;
+        };
+        // Skip missed ticks instead of firing them all at once after a stall
+        let now = Instant::now();
+        let mut deadline = due.deadline + interval;
+        if deadline <= now {
+            deadline = now + interval;
+        }
+        self.insert(Timer {
+            id: due.id,
+            deadline,
+            interval: Some(interval),
+            action: due.action,
+        });
+    }
+}
+
+impl<T> Default for TimerQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
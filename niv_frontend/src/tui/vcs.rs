@@ -0,0 +1,395 @@
+//! Version control systems behind one interface.
+//!
+//! The repository of a file is the nearest ancestor with a `.git`, `.hg`,
+//! `.svn` or `.pijul` marker, and is driven through its command line tool.
+//! Git and Mercurial branches are read from the repository's files, so
+//! looking them up doesn't start a process.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Who last changed a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// Commit, changeset or revision of the change, shortened
+    pub revision: String,
+    /// Empty where the tool doesn't tell
+    pub author: String,
+}
+
+/// A version control system and the repository a file is in
+pub trait Vcs {
+    /// Name of the tool (`git`, `hg`, `svn`, `pijul`)
+    fn name(&self) -> &'static str;
+
+    /// Root directory of the repository
+    fn root(&self) -> &Path;
+
+    /// Current branch; the channel in Pijul
+    fn branch(&self) -> Option<String>;
+
+    /// Text of `path` as last committed; None when it isn't tracked
+    fn base_text(&self, path: &Path) -> Option<String>;
+
+    /// Who last changed each line of `path` as it is on disk
+    fn blame(&self, path: &Path) -> Result<Vec<BlameLine>, String>;
+
+    /// Record that `old` was renamed to `new` on disk; Ok(false) when `old`
+    /// isn't tracked
+    fn record_rename(&self, old: &Path, new: &Path) -> Result<bool, String>;
+}
+
+/// The repository containing `path`, by the marker of the nearest ancestor
+pub fn find_vcs(path: &Path) -> Option<Box<dyn Vcs>> {
+    let path = std::path::absolute(path).ok()?;
+    for dir in path.ancestors().skip(1) {
+        let root = dir.to_path_buf();
+        if dir.join(".git").exists() {
+            return Some(Box::new(Git { root }));
+        }
+        if dir.join(".hg").is_dir() {
+            return Some(Box::new(Mercurial { root }));
+        }
+        if dir.join(".svn").is_dir() {
+            return Some(Box::new(Subversion { root }));
+        }
+        if dir.join(".pijul").is_dir() {
+            return Some(Box::new(Pijul { root }));
+        }
+    }
+    None
+}
+
+struct Git {
+    root: PathBuf,
+}
+
+impl Vcs for Git {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn branch(&self) -> Option<String> {
+        // In worktrees and submodules `.git` is a file naming the real one
+        let marker = self.root.join(".git");
+        let git_dir = match std::fs::read_to_string(&marker) {
+            Ok(link) => {
+                let dir = PathBuf::from(link.strip_prefix("gitdir:")?.trim());
+                self.root.join(dir)
+            }
+            Err(_) => marker,
+        };
+        let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        git_head_branch(&head)
+    }
+
+    fn base_text(&self, path: &Path) -> Option<String> {
+        let spec = format!("HEAD:{}", relative_path(&self.root, path)?);
+        run(&self.root, "git", &["show", &spec]).ok()
+    }
+
+    fn blame(&self, path: &Path) -> Result<Vec<BlameLine>, String> {
+        let relative = relative_path(&self.root, path).ok_or("not in the repository")?;
+        let output = run(
+            &self.root,
+            "git",
+            &["blame", "--porcelain", "--", &relative],
+        )?;
+        Ok(parse_git_blame(&output))
+    }
+
+    fn record_rename(&self, old: &Path, new: &Path) -> Result<bool, String> {
+        let (Some(old), Some(new)) = (
+            relative_path(&self.root, old),
+            relative_path(&self.root, new),
+        ) else {
+            return Ok(false);
+        };
+        if run(
+            &self.root,
+            "git",
+            &["ls-files", "--error-unmatch", "--", &old],
+        )
+        .is_err()
+        {
+            return Ok(false);
+        }
+        run(
+            &self.root,
+            "git",
+            &["rm", "--cached", "--quiet", "--", &old],
+        )?;
+        run(&self.root, "git", &["add", "--", &new])?;
+        Ok(true)
+    }
+}
+
+struct Mercurial {
+    root: PathBuf,
+}
+
+impl Vcs for Mercurial {
+    fn name(&self) -> &'static str {
+        "hg"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn branch(&self) -> Option<String> {
+        // The file is only written once a branch other than the default is used
+        let branch = std::fs::read_to_string(self.root.join(".hg").join("branch"))
+            .unwrap_or_else(|_| "default".to_string());
+        Some(branch.trim().to_string())
+    }
+
+    fn base_text(&self, path: &Path) -> Option<String> {
+        let relative = relative_path(&self.root, path)?;
+        run(&self.root, "hg", &["cat", "-r", ".", "--", &relative]).ok()
+    }
+
+    fn blame(&self, path: &Path) -> Result<Vec<BlameLine>, String> {
+        let relative = relative_path(&self.root, path).ok_or("not in the repository")?;
+        let output = run(&self.root, "hg", &["annotate", "-u", "-c", "--", &relative])?;
+        Ok(parse_hg_annotate(&output))
+    }
+
+    fn record_rename(&self, old: &Path, new: &Path) -> Result<bool, String> {
+        let (Some(old), Some(new)) = (
+            relative_path(&self.root, old),
+            relative_path(&self.root, new),
+        ) else {
+            return Ok(false);
+        };
+        if run(&self.root, "hg", &["files", "--", &old]).is_err() {
+            return Ok(false);
+        }
+        run(&self.root, "hg", &["rename", "--after", "--", &old, &new])?;
+        Ok(true)
+    }
+}
+
+struct Subversion {
+    root: PathBuf,
+}
+
+impl Vcs for Subversion {
+    fn name(&self) -> &'static str {
+        "svn"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn branch(&self) -> Option<String> {
+        let url = run(&self.root, "svn", &["info", "--show-item", "relative-url"]).ok()?;
+        svn_branch(url.trim())
+    }
+
+    fn base_text(&self, path: &Path) -> Option<String> {
+        let relative = relative_path(&self.root, path)?;
+        run(&self.root, "svn", &["cat", "-r", "BASE", "--", &relative]).ok()
+    }
+
+    fn blame(&self, path: &Path) -> Result<Vec<BlameLine>, String> {
+        let relative = relative_path(&self.root, path).ok_or("not in the repository")?;
+        let output = run(&self.root, "svn", &["blame", "--", &relative])?;
+        Ok(parse_svn_blame(&output))
+    }
+
+    fn record_rename(&self, old: &Path, new: &Path) -> Result<bool, String> {
+        let (Some(old), Some(new)) = (
+            relative_path(&self.root, old),
+            relative_path(&self.root, new),
+        ) else {
+            return Ok(false);
+        };
+        if run(&self.root, "svn", &["info", "--", &old]).is_err() {
+            return Ok(false);
+        }
+        run(&self.root, "svn", &["rm", "--keep-local", "--", &old])?;
+        run(&self.root, "svn", &["add", "--", &new])?;
+        Ok(true)
+    }
+}
+
+struct Pijul {
+    root: PathBuf,
+}
+
+impl Vcs for Pijul {
+    fn name(&self) -> &'static str {
+        "pijul"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn branch(&self) -> Option<String> {
+        let channels = run(&self.root, "pijul", &["channel"]).ok()?;
+        channels
+            .lines()
+            .find_map(|line| line.strip_prefix("* "))
+            .map(|channel| channel.trim().to_string())
+    }
+
+    fn base_text(&self, path: &Path) -> Option<String> {
+        // Prints the recorded file instead of restoring it
+        let relative = relative_path(&self.root, path)?;
+        run(&self.root, "pijul", &["reset", "--dry-run", &relative]).ok()
+    }
+
+    fn blame(&self, path: &Path) -> Result<Vec<BlameLine>, String> {
+        let relative = relative_path(&self.root, path).ok_or("not in the repository")?;
+        let output = run(&self.root, "pijul", &["credit", &relative])?;
+        Ok(parse_pijul_credit(&output))
+    }
+
+    fn record_rename(&self, old: &Path, new: &Path) -> Result<bool, String> {
+        let (Some(old), Some(new)) = (
+            relative_path(&self.root, old),
+            relative_path(&self.root, new),
+        ) else {
+            return Ok(false);
+        };
+        let tracked = run(&self.root, "pijul", &["ls"])?;
+        if !tracked.lines().any(|line| line == old) {
+            return Ok(false);
+        }
+        run(&self.root, "pijul", &["remove", &old])?;
+        run(&self.root, "pijul", &["add", &new])?;
+        Ok(true)
+    }
+}
+
+/// Branch checked out according to a `.git/HEAD` file, or the start of the
+/// commit when it is detached
+pub fn git_head_branch(head: &str) -> Option<String> {
+    let head = head.trim();
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        ),
+        None => head.get(..7).map(str::to_string),
+    }
+}
+
+/// Branch of a Subversion working copy from its `^/`-relative URL, by the
+/// usual `trunk`, `branches/NAME` and `tags/NAME` layout
+pub fn svn_branch(relative_url: &str) -> Option<String> {
+    let mut parts = relative_url.strip_prefix("^/")?.split('/');
+    let (first, second) = (parts.next()?, parts.next());
+    match (first, second) {
+        ("trunk", _) => Some("trunk".to_string()),
+        ("branches" | "tags", Some(name)) if !name.is_empty() => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// Lines of `git blame --porcelain` output. A commit's author is only given
+/// the first time the commit appears.
+pub fn parse_git_blame(output: &str) -> Vec<BlameLine> {
+    let mut authors: HashMap<&str, &str> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut commit = "";
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            lines.push(BlameLine {
+                revision: commit.chars().take(8).collect(),
+                author: authors.get(commit).copied().unwrap_or_default().to_string(),
+            });
+        } else if let Some(author) = line.strip_prefix("author ") {
+            authors.insert(commit, author);
+        } else {
+            let mut fields = line.split(' ');
+            let header = fields.next().filter(|hash| {
+                hash.len() == 40 && hash.bytes().all(|byte| byte.is_ascii_hexdigit())
+            });
+            if let Some(hash) = header {
+                commit = hash;
+            }
+        }
+    }
+    lines
+}
+
+/// Lines of `hg annotate -u -c` output: `user changeset: text`
+pub fn parse_hg_annotate(output: &str) -> Vec<BlameLine> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (annotation, _) = line.split_once(": ")?;
+            let (author, revision) = annotation.trim().rsplit_once(' ')?;
+            Some(BlameLine {
+                revision: revision.to_string(),
+                author: author.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Lines of `svn blame` output: revision, author and the text
+pub fn parse_svn_blame(output: &str) -> Vec<BlameLine> {
+    output
+        .lines()
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            BlameLine {
+                revision: fields.next().unwrap_or_default().to_string(),
+                author: fields.next().unwrap_or_default().to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Lines of `pijul credit` output: each change's hash, followed by the lines
+/// it introduced prefixed with `> `
+pub fn parse_pijul_credit(output: &str) -> Vec<BlameLine> {
+    let mut change = "";
+    let mut lines = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("> ") || line == ">" {
+            lines.push(BlameLine {
+                revision: change.chars().take(8).collect(),
+                author: String::new(),
+            });
+        } else if !line.trim().is_empty() {
+            change = line.trim().trim_end_matches(',');
+        }
+    }
+    lines
+}
+
+/// `path` relative to `root`, with `/` separators
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let path = std::path::absolute(path).ok()?;
+    let relative = path.strip_prefix(root).ok()?;
+    Some(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Run a version control tool in `root`, returning its output or why it
+/// failed
+fn run(root: &Path, tool: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(tool)
+        .current_dir(root)
+        .args(args)
+        .output()
+        .map_err(|e| format!("{}: {}", tool, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        let error = String::from_utf8_lossy(&output.stderr);
+        Err(error.lines().next().unwrap_or("failed").to_string())
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::{Component, Path};
+
+/// Separator between winbar breadcrumbs
+pub const CRUMB_SEPARATOR: &str = " > ";
+
+/// Breadcrumbs for a file: the components of its path relative to `cwd`
+pub fn path_crumbs(path: &Path, cwd: &Path) -> Vec<String> {
+    path.strip_prefix(cwd)
+        .unwrap_or(path)
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            Component::Prefix(prefix) => Some(prefix.as_os_str().to_string_lossy().into_owned()),
+            Component::ParentDir => Some("..".to_string()),
+            Component::RootDir | Component::CurDir => None,
+        })
+        .collect()
+}
+
+/// Join breadcrumbs for a bar `width` columns wide, replacing leading crumbs
+/// with `…` until the rest fits. The last crumb is always kept.
+pub fn join_crumbs(crumbs: &[String], width: usize) -> String {
+    let mut skip = 0;
+    loop {
+        let mut text = crumbs[skip..].join(CRUMB_SEPARATOR);
+        if skip > 0 {
+            text = format!("…{}{}", CRUMB_SEPARATOR, text);
+        }
+        if text.chars().count() <= width || skip + 1 >= crumbs.len() {
+            return text;
+        }
+        skip += 1;
+    }
+}
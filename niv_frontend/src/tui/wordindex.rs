@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+/// Characters that make up a keyword for completion and `*` search
+pub fn is_keyword_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// The keywords in `text`, in order
+pub fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|ch: char| !is_keyword_char(ch))
+        .filter(|word| !word.is_empty())
+}
+
+/// Byte column where the keyword ending at `col` starts
+pub fn keyword_start(line: &str, col: usize) -> usize {
+    line[..col]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, ch)| is_keyword_char(ch))
+        .last()
+        .map_or(col, |(i, _)| i)
+}
+
+/// Byte span of the keyword under `col`, or of the next keyword after it on the line
+pub fn keyword_at(line: &str, col: usize) -> Option<(usize, usize)> {
+    let col = col.min(line.len());
+    let start = match line[col..]
+        .char_indices()
+        .find(|&(_, ch)| is_keyword_char(ch))
+    {
+        Some((0, _)) => keyword_start(line, col),
+        Some((offset, _)) => col + offset,
+        None => return None,
+    };
+    let end = line[start..]
+        .find(|ch: char| !is_keyword_char(ch))
+        .map_or(line.len(), |offset| start + offset);
+    Some((start, end))
+}
+
+/// Byte columns of the whole-word occurrences of `word` in `line`
+pub fn find_word(line: &str, word: &str) -> Vec<usize> {
+    let mut found = Vec::new();
+    if word.is_empty() {
+        return found;
+    }
+    for (i, _) in line.match_indices(word) {
+        let before = line[..i].chars().next_back();
+        let after = line[i + word.len()..].chars().next();
+        if !before.is_some_and(is_keyword_char) && !after.is_some_and(is_keyword_char) {
+            found.push(i);
+        }
+    }
+    found
+}
+
+/// Occurrence counts of the keywords in a buffer, kept sorted so that
+/// prefix lookups do not have to rescan the text
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WordIndex {
+    counts: BTreeMap<String, usize>,
+}
+
+impl WordIndex {
+    pub fn from_text(text: &str) -> Self {
+        let mut index = Self::default();
+        index.add(text);
+        index
+    }
+
+    pub fn add(&mut self, text: &str) {
+        for word in words(text) {
+            *self.counts.entry(word.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    pub fn remove(&mut self, text: &str) {
+        for word in words(text) {
+            if let Some(count) = self.counts.get_mut(word) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(word);
+                }
+            }
+        }
+    }
+
+    /// Account for an edit that replaced `old` text with `new`
+    pub fn update(&mut self, old: &str, new: &str) {
+        self.remove(old);
+        self.add(new);
+    }
+
+    /// Number of times `word` occurs
+    pub fn count(&self, word: &str) -> usize {
+        self.counts.get(word).copied().unwrap_or(0)
+    }
+
+    /// Number of distinct words
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Words starting with `prefix`, in sorted order
+    pub fn with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.counts
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .map(|(word, _)| word.as_str())
+            .take_while(move |word| word.starts_with(prefix))
+    }
+}
@@ -0,0 +1,51 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+fn current_path(editor: &Editor) -> std::path::PathBuf {
+    let buffer = editor.buffer_manager.current().unwrap();
+    buffer.file_path.clone().unwrap()
+}
+
+#[test]
+fn test_argdo_edits_every_file_of_the_argument_list() {
+    let dir = TempDir::new("args");
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    std::fs::write(&a, "foo one").unwrap();
+    std::fs::write(&b, "two").unwrap();
+    std::fs::write(dir.join("c.md"), "foo three").unwrap();
+
+    let mut editor = Editor::headless(120, 8);
+    editor.feed_keys(":args<CR>").unwrap();
+    assert_eq!(message(&editor), "Argument list is empty");
+
+    editor
+        .feed_keys(&format!(":args {}<CR>", dir.join("*.txt").display()))
+        .unwrap();
+    assert_eq!(current_path(&editor), a);
+    editor.feed_keys(":args<CR>").unwrap();
+    assert_eq!(
+        message(&editor),
+        format!("[{}] {}", a.display(), b.display())
+    );
+
+    editor.feed_keys(":next<CR>").unwrap();
+    assert_eq!(current_path(&editor), b);
+    editor.feed_keys(":next<CR>").unwrap();
+    assert_eq!(message(&editor), "Cannot go beyond last file");
+    editor.feed_keys(":prev<CR>").unwrap();
+    assert_eq!(current_path(&editor), a);
+
+    // The rest of the line, bars included, is run in each file
+    editor.feed_keys(":argdo %!tr o 0 | update<CR>").unwrap();
+    assert_eq!(niv_fs::load_file(&a).unwrap().content, "f00 0ne");
+    assert_eq!(niv_fs::load_file(&b).unwrap().content, "tw0");
+    assert_eq!(
+        std::fs::read_to_string(dir.join("c.md")).unwrap(),
+        "foo three"
+    );
+    assert_eq!(current_path(&editor), b);
+}
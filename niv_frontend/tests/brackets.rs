@@ -0,0 +1,46 @@
+use crossterm::style::Color;
+use niv_frontend::{Editor, bracket_at_cursor, matching_bracket};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_matching_bracket_counts_nesting() {
+    let text = "f(a, (b), [c]) { x }";
+    assert_eq!(matching_bracket(text, 1, 100), Some(13));
+    assert_eq!(matching_bracket(text, 13, 100), Some(1));
+    assert_eq!(matching_bracket(text, 15, 100), Some(19));
+    // Not a bracket, or the match is too far away
+    assert_eq!(matching_bracket(text, 0, 100), None);
+    assert_eq!(matching_bracket(text, 1, 5), None);
+    assert_eq!(matching_bracket("(()", 0, 100), None);
+
+    // On a bracket, or just after one
+    assert_eq!(bracket_at_cursor(text, 5, 100), Some((5, 7)));
+    assert_eq!(bracket_at_cursor(text, 8, 100), Some((7, 5)));
+    assert_eq!(bracket_at_cursor(text, 3, 100), None);
+}
+
+#[test]
+fn test_matching_bracket_is_highlighted() {
+    let dir = TempDir::new("brackets");
+    let path = dir.join("brackets.rs");
+    std::fs::write(&path, "fn main() {\n    let v = [1, (2)];\n}\n").unwrap();
+
+    let mut editor = Editor::headless(40, 6);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("$").unwrap();
+    let screen = editor.screen().unwrap();
+    let highlight = screen.cell(15, 0).bg;
+    assert_ne!(highlight, Color::Reset);
+    assert_eq!(screen.cell(5, 2).bg, highlight);
+    assert_ne!(screen.cell(14, 0).bg, highlight);
+    drop(screen);
+
+    // Moving off the bracket clears the pair without blanking other lines
+    editor.feed_keys("j0").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_ne!(screen.cell(15, 0).bg, highlight);
+    assert_ne!(screen.cell(5, 2).bg, highlight);
+    assert_eq!(screen.row_text(0), "   1 fn main() {");
+    assert_eq!(screen.row_text(1), "   2     let v = [1, (2)];");
+    assert_eq!(screen.row_text(2), "   3 }");
+}
@@ -0,0 +1,36 @@
+use niv_frontend::Editor;
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+#[test]
+fn test_ga_and_g8_describe_the_cursor_character() {
+    let mut editor = Editor::headless(80, 8);
+    editor.feed_keys("iAe\u{301}<Esc>").unwrap();
+
+    // A combining character is reported with its base character
+    editor.feed_keys("0lga").unwrap();
+    assert_eq!(
+        message(&editor),
+        "<e> 101, Hex 65, Oct 145, U+0065 + <\u{301}> 769, Hex 301, Oct 1401, U+0301"
+    );
+    editor.feed_keys("g8").unwrap();
+    assert_eq!(message(&editor), "65 + cc 81");
+
+    editor.feed_keys("0ga").unwrap();
+    assert_eq!(message(&editor), "<A> 65, Hex 41, Oct 101, U+0041");
+
+    // Control characters are named and shown in caret notation
+    editor.feed_keys("o<C-v><C-a><Esc>0ga").unwrap();
+    assert_eq!(message(&editor), "<^A> 1, Hex 01, Oct 001, U+0001, SOH");
+    editor.feed_keys("g8").unwrap();
+    assert_eq!(message(&editor), "01");
+}
+
+#[test]
+fn test_ga_on_an_empty_line_reports_nul() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("ga").unwrap();
+    assert_eq!(message(&editor), "NUL");
+}
@@ -0,0 +1,36 @@
+use niv_frontend::{Editor, clip_start_to_width, clip_to_width, fit_to_width};
+
+#[test]
+fn test_text_is_clipped_to_terminal_columns() {
+    assert_eq!(clip_to_width("status", 4), "stat");
+    assert_eq!(clip_to_width("short", 10), "short");
+    // A wide character cut by the edge leaves a space
+    assert_eq!(clip_to_width("ab漢字", 5), "ab漢 ");
+    assert_eq!(clip_to_width("e\u{301}x", 1), "e\u{301}");
+    // Control characters can't reach the terminal
+    assert_eq!(clip_to_width("a\x1b[2Jb\tc\n", 10), "a?[2Jb c?");
+
+    assert_eq!(fit_to_width("ab", 4), "ab  ");
+    assert_eq!(fit_to_width("漢字", 3), "漢 ");
+
+    assert_eq!(clip_start_to_width(":edit file.txt", 8), "file.txt");
+    assert_eq!(clip_start_to_width("漢字x", 4), "字x");
+    assert_eq!(clip_start_to_width("e\u{301}x", 1), "x");
+}
+
+#[test]
+fn test_narrow_terminal_clips_status_and_command_line() {
+    let mut editor = Editor::headless(24, 6);
+    editor.feed_keys(":nosuchcommand<CR>").unwrap();
+    assert_eq!(
+        editor.screen().unwrap().row_text(4),
+        "Unknown command: nosuchc"
+    );
+
+    // The end of a long command line stays in view, a column left for the cursor
+    editor.feed_keys(":w some/rather/long/path.txt").unwrap();
+    assert_eq!(
+        editor.screen().unwrap().row_text(5),
+        "me/rather/long/path.txt"
+    );
+}
@@ -0,0 +1,72 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn first_line(editor: &Editor) -> String {
+    let content = editor.buffer_manager.current().unwrap().content.clone();
+    content.lines().next().unwrap_or_default().to_string()
+}
+
+#[test]
+fn test_keyword_completion_follows_edits_and_other_buffers() {
+    let dir = TempDir::new("complete");
+    std::fs::write(dir.join("other.txt"), "alps").unwrap();
+    std::fs::write(dir.join("main.txt"), "alphabet alpha beta").unwrap();
+    let mut editor = Editor::headless(60, 8);
+    assert!(editor.open_file_at(&dir.join("other.txt"), None));
+    assert!(editor.open_file_at(&dir.join("main.txt"), None));
+
+    // Words of the current buffer come before those of the others
+    editor.feed_keys("A al<C-n>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta alpha");
+    editor.feed_keys("<C-n>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta alphabet");
+    editor.feed_keys("<C-n>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta alps");
+    editor.feed_keys("<C-e>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta al");
+
+    // Typed words are found at once, and removed ones no longer
+    editor.feed_keys("<BS><BS>betamax be<C-p>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta betamax betamax");
+    editor.feed_keys(&"<BS>".repeat(16)).unwrap();
+    editor.feed_keys(" betam<C-n>").unwrap();
+    assert_eq!(first_line(&editor), "alphabet alpha beta betam");
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .row_text(6)
+            .contains("Pattern not found")
+    );
+}
+
+#[test]
+fn test_star_jumps_to_the_next_whole_word() {
+    let dir = TempDir::new("complete_star");
+    let path = dir.join("star.txt");
+    std::fs::write(&path, "foo food\nbar foo\nfoo").unwrap();
+    let mut editor = Editor::headless(40, 8);
+    assert!(editor.open_file_at(&path, None));
+    let cursor = |editor: &Editor| {
+        let buffer = editor.buffer_manager.current().unwrap();
+        (buffer.cursor_line, buffer.cursor_col)
+    };
+
+    editor.feed_keys("*").unwrap();
+    assert_eq!(cursor(&editor), (1, 4));
+    editor.feed_keys("*").unwrap();
+    assert_eq!(cursor(&editor), (2, 0));
+    editor.feed_keys("#").unwrap();
+    assert_eq!(cursor(&editor), (1, 4));
+
+    // A word found once is reported without moving on
+    editor.feed_keys("k0lllll*").unwrap();
+    assert_eq!(cursor(&editor), (0, 4));
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .row_text(6)
+            .contains("Only match: food")
+    );
+}
@@ -0,0 +1,55 @@
+use niv_frontend::Editor;
+
+#[test]
+fn test_preedit_is_drawn_underlined_at_cursor() {
+    let mut editor = Editor::headless(40, 6);
+    editor.feed_keys("iab").unwrap();
+    editor.set_preedit("日本");
+    editor.run_frame().unwrap();
+
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 ab日本");
+    assert!(screen.cell(7, 0).underline);
+    assert!(screen.cell(9, 0).underline);
+    assert!(!screen.cell(6, 0).underline);
+    assert_eq!(screen.cursor(), (11, 0));
+    drop(screen);
+
+    // The buffer is untouched until the text is committed
+    assert_eq!(editor.buffer_manager.current().unwrap().content, "ab");
+    editor.set_preedit("");
+    editor.run_frame().unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 ab");
+    assert!(!screen.cell(7, 0).underline);
+}
+
+#[test]
+fn test_commit_inserts_composed_text_at_cursor() {
+    let mut editor = Editor::headless(40, 6);
+    editor.feed_keys("iab").unwrap();
+    editor.set_preedit("にほ");
+    editor.commit_composition("日本語");
+    editor.feed_keys("!").unwrap();
+
+    let buffer = editor.buffer_manager.current().unwrap();
+    assert_eq!(buffer.content, "ab日本語!");
+    assert_eq!(editor.preedit(), None);
+    assert_eq!(editor.screen().unwrap().row_text(0), "   1 ab日本語!");
+}
+
+#[test]
+fn test_composition_only_happens_in_insert_mode() {
+    let mut editor = Editor::headless(40, 6);
+    editor.set_preedit("日本");
+    assert_eq!(editor.preedit(), None);
+    editor.commit_composition("日本");
+    assert_eq!(editor.buffer_manager.current().unwrap().content, "");
+
+    // Leaving insert mode drops the composition
+    editor.feed_keys("i").unwrap();
+    editor.set_preedit("日本");
+    editor.feed_keys("<Esc>").unwrap();
+    assert_eq!(editor.preedit(), None);
+    assert_eq!(editor.screen().unwrap().row_text(0), "   1");
+}
@@ -0,0 +1,34 @@
+#![cfg(unix)]
+
+use niv_frontend::{Editor, crash_dir, install_crash_handler};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_panics_leave_a_crash_report() {
+    let dir = TempDir::new("crash");
+    // SAFETY: the only test in this binary, set before any other thread starts
+    unsafe { std::env::set_var("XDG_CACHE_HOME", dir.join("cache")) };
+    let path = dir.join("secret.txt");
+    std::fs::write(&path, "password=hunter2").unwrap();
+
+    let mut editor = Editor::headless(40, 8);
+    editor
+        .feed_keys(&format!(":e {}<CR>:pwd<CR>Ahunter3<Esc>", path.display()))
+        .unwrap();
+    install_crash_handler();
+    let panic = std::panic::catch_unwind(|| panic!("something broke"));
+    assert!(panic.is_err());
+
+    let reports: Vec<_> = std::fs::read_dir(crash_dir()).unwrap().collect();
+    assert_eq!(reports.len(), 1);
+    let report = std::fs::read_to_string(reports[0].as_ref().unwrap().path()).unwrap();
+    assert!(report.starts_with("niv "), "{}", report);
+    assert!(report.contains("panic: something broke"));
+    assert!(report.contains("tests/crash.rs"));
+    assert!(report.contains("\n  :pwd\n"));
+    assert!(report.contains(&format!("\n  {}\n", path.display())));
+    assert!(report.contains("backtrace:"));
+
+    // File contents stay out of it
+    assert!(!report.contains("hunter"));
+}
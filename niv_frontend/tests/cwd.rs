@@ -0,0 +1,46 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+fn current_path(editor: &Editor) -> std::path::PathBuf {
+    let buffer = editor.buffer_manager.current().unwrap();
+    buffer.file_path.clone().unwrap()
+}
+
+// The only test here, as `:cd` changes the directory of the whole process
+#[test]
+fn test_relative_paths_follow_cd_and_lcd() {
+    let dir = TempDir::new("cwd");
+    let root = dir.path().canonicalize().unwrap();
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("top.txt"), "top").unwrap();
+    std::fs::write(root.join("sub/inner.txt"), "inner").unwrap();
+    let start = std::env::current_dir().unwrap();
+
+    let mut editor = Editor::headless(100, 8);
+    editor
+        .feed_keys(&format!(":cd {}<CR>", root.display()))
+        .unwrap();
+    assert_eq!(message(&editor), root.display().to_string());
+    editor.feed_keys(":e top.txt<CR>").unwrap();
+    assert_eq!(current_path(&editor), root.join("top.txt"));
+    editor.feed_keys(":cd -<CR>").unwrap();
+    assert_eq!(std::env::current_dir().unwrap(), start);
+    editor.feed_keys(":cd -<CR>").unwrap();
+    assert_eq!(std::env::current_dir().unwrap(), root);
+
+    // `:lcd` only applies to the buffer it was given in
+    editor.feed_keys(":lcd sub<CR>:pwd<CR>").unwrap();
+    assert_eq!(message(&editor), root.join("sub").display().to_string());
+    editor.feed_keys(":e inner.txt<CR>").unwrap();
+    assert_eq!(current_path(&editor), root.join("sub/inner.txt"));
+    editor.feed_keys(":pwd<CR>").unwrap();
+    assert_eq!(message(&editor), root.display().to_string());
+    editor.feed_keys(":lcd nowhere<CR>").unwrap();
+    assert!(message(&editor).starts_with("Can't change directory"));
+
+    std::env::set_current_dir(start).unwrap();
+}
@@ -0,0 +1,125 @@
+use niv_frontend::{DiffRowKind, DiffView, Editor, LineDiff, diff_lines};
+use niv_fs::testing::TempDir;
+
+/// The split of a screen row, right of the separator
+fn split_text(editor: &Editor, y: u16) -> String {
+    let row = editor.screen().unwrap().row_text(y);
+    row.split_once('│')
+        .map(|(_, split)| split.to_string())
+        .unwrap_or_default()
+}
+
+/// Apply `diff` to `old`, checking it against `new` along the way
+fn apply(old: &[&str], new: &[&str], diff: &[LineDiff]) -> Vec<String> {
+    let mut result = Vec::new();
+    let (mut next_old, mut next_new) = (0, 0);
+    for line in diff {
+        match *line {
+            LineDiff::Same { old: o, new: n } => {
+                assert_eq!((o, n), (next_old, next_new));
+                assert_eq!(old[o], new[n]);
+                result.push(old[o].to_string());
+                next_old += 1;
+                next_new += 1;
+            }
+            LineDiff::Removed(o) => {
+                assert_eq!(o, next_old);
+                next_old += 1;
+            }
+            LineDiff::Added(n) => {
+                assert_eq!(n, next_new);
+                result.push(new[n].to_string());
+                next_new += 1;
+            }
+        }
+    }
+    assert_eq!((next_old, next_new), (old.len(), new.len()));
+    result
+}
+
+#[test]
+fn test_diff_lines_is_shortest_edit() {
+    let cases: [(&[&str], &[&str], usize); 6] = [
+        (&[], &[], 0),
+        (&["a"], &[], 1),
+        (&[], &["a", "b"], 2),
+        (&["a", "b", "c"], &["a", "x", "c"], 2),
+        (
+            &["a", "b", "c", "a", "b", "b", "a"],
+            &["c", "b", "a", "b", "a", "c"],
+            5,
+        ),
+        (&["x", "a", "y", "b"], &["a", "z", "b", "w"], 4),
+    ];
+    for (old, new, edits) in cases {
+        let diff = diff_lines(old, new);
+        assert_eq!(apply(old, new, &diff), new);
+        let count = diff
+            .iter()
+            .filter(|line| !matches!(line, LineDiff::Same { .. }))
+            .count();
+        assert_eq!(count, edits, "{:?} -> {:?}", old, new);
+    }
+}
+
+#[test]
+fn test_diff_view_lines_up_with_buffer() {
+    let mut view = DiffView::new("one\ntwo\nthree\nfour\n".to_string());
+    view.update("one\n2\nthree\nnew\nfour\n");
+    let rows: Vec<(&str, DiffRowKind, usize)> = view
+        .rows()
+        .iter()
+        .map(|row| (row.text.as_str(), row.kind, row.buffer_line))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ("one", DiffRowKind::Same, 0),
+            ("two", DiffRowKind::Changed, 1),
+            ("three", DiffRowKind::Same, 2),
+            ("", DiffRowKind::Added, 3),
+            ("four", DiffRowKind::Same, 4),
+        ]
+    );
+
+    // Removed lines sit next to the line after them
+    view.update("one\nfour\n");
+    let visible: Vec<_> = view.visible(1, 10).iter().map(|row| &row.text).collect();
+    assert_eq!(visible, ["two", "three", "four"]);
+    assert_eq!(view.count(DiffRowKind::Removed), 2);
+    assert_eq!(view.line_change(1), Some(DiffRowKind::Removed));
+    assert_eq!(view.line_change(0), None);
+}
+
+#[test]
+fn test_diff_orig_shows_file_on_disk() {
+    let dir = TempDir::new("difforig");
+    let path = dir.join("difforig.txt");
+    std::fs::write(&path, "alpha\nbeta\ngamma\n").unwrap();
+
+    let mut editor = Editor::headless(60, 8);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("jA!<Esc>").unwrap();
+    editor.feed_keys(":DiffOrig<CR>").unwrap();
+    assert_eq!(split_text(&editor, 0), " alpha");
+    assert_eq!(split_text(&editor, 1), "~beta");
+    assert_eq!(split_text(&editor, 2), " gamma");
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .text()
+            .contains("1 changed, 0 added, 0 removed lines")
+    );
+
+    // The diff follows edits
+    editor.feed_keys("o<Esc>").unwrap();
+    assert_eq!(split_text(&editor, 2), "+");
+    assert_eq!(split_text(&editor, 3), " gamma");
+
+    // The Markdown preview takes the split over, and toggling closes it
+    editor.feed_keys(":MarkdownPreview<CR>").unwrap();
+    assert_eq!(split_text(&editor, 0), "alpha beta!");
+    editor.feed_keys(":DiffOrig<CR>:DiffOrig<CR>").unwrap();
+    assert!(!editor.screen().unwrap().row_text(0).contains('│'));
+}
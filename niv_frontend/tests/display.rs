@@ -0,0 +1,34 @@
+use niv_frontend::Editor;
+
+fn row(editor: &Editor, y: u16) -> String {
+    editor.screen().unwrap().row_text(y).trim_end().to_string()
+}
+
+#[test]
+fn test_odd_characters_are_drawn_visibly() {
+    let mut editor = Editor::headless(40, 6);
+    editor
+        .feed_keys("ia<C-v><C-a>b\u{200b}c\u{fffd}<Esc>")
+        .unwrap();
+    assert_eq!(row(&editor, 0), "   1 a^Abc<fffd>");
+
+    // Control characters and invalid bytes are styled apart from the text
+    let screen = editor.screen().unwrap();
+    let text = screen.cell(5, 0).fg;
+    assert_ne!(screen.cell(6, 0).fg, text);
+    assert_eq!(screen.cell(6, 0).fg, screen.cell(7, 0).fg);
+    assert_ne!(screen.cell(10, 0).fg, text);
+    drop(screen);
+
+    // A control character is one character to move over
+    editor.feed_keys("0l").unwrap();
+    assert_eq!(editor.screen().unwrap().cursor(), (6, 0));
+    editor.feed_keys("l").unwrap();
+    assert_eq!(editor.screen().unwrap().cursor(), (8, 0));
+
+    // Zero-width characters are shown only with `list`
+    editor.feed_keys(":setlocal list<CR>").unwrap();
+    assert_eq!(row(&editor, 0), "   1 a^Ab<200b>c<fffd>");
+    editor.feed_keys(":setlocal nolist<CR>").unwrap();
+    assert_eq!(row(&editor, 0), "   1 a^Abc<fffd>");
+}
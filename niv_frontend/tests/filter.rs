@@ -0,0 +1,56 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn open(dir: &TempDir, text: &str) -> Editor {
+    let path = dir.join("filter.txt");
+    std::fs::write(&path, text).unwrap();
+    let mut editor = Editor::headless(60, 8);
+    assert!(editor.open_file_at(&path, None));
+    editor
+}
+
+fn content(editor: &Editor) -> String {
+    editor.buffer_manager.current().unwrap().content.clone()
+}
+
+fn command_line(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(7).trim_end().to_string()
+}
+
+#[test]
+fn test_motion_filter_replaces_the_lines_with_the_output() {
+    let dir = TempDir::new("filter_motion");
+    let mut editor = open(&dir, "c\na\nb\n\nz\ny");
+
+    editor.feed_keys("!ip").unwrap();
+    assert_eq!(command_line(&editor), ":.,.+2!");
+    editor.feed_keys("sort<CR>").unwrap();
+    assert_eq!(content(&editor), "a\nb\nc\n\nz\ny");
+
+    editor.feed_keys("!j").unwrap();
+    assert_eq!(command_line(&editor), ":.,.+1!");
+    editor.feed_keys("tr a-z A-Z<CR>").unwrap();
+    assert_eq!(content(&editor), "A\nB\nc\n\nz\ny");
+}
+
+#[test]
+fn test_visual_filter_and_failing_commands() {
+    let dir = TempDir::new("filter_visual");
+    let mut editor = open(&dir, "one\ntwo\nthree");
+
+    editor.feed_keys("jvj!").unwrap();
+    assert_eq!(command_line(&editor), ":'<,'>!");
+    editor.feed_keys("rev<CR>").unwrap();
+    assert_eq!(content(&editor), "one\nowt\neerht");
+
+    // A failing command leaves the text alone
+    editor.feed_keys(":1!exit 3<CR>").unwrap();
+    assert_eq!(content(&editor), "one\nowt\neerht");
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .row_text(6)
+            .starts_with("Filter failed")
+    );
+}
@@ -0,0 +1,59 @@
+use niv_frontend::Editor;
+
+#[test]
+fn test_headless_typing() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("ihello world").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 hello world");
+    assert_eq!(screen.cursor(), (16, 0));
+    drop(screen);
+
+    editor.feed_keys("<Esc>").unwrap();
+    assert!(editor.screen().unwrap().row_text(6).contains("[+]"));
+}
+
+#[test]
+fn test_headless_command_line() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys(":nosuchcommand").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(7), ":nosuchcommand");
+
+    editor.feed_keys("<CR>").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(6), "Unknown command: nosuchcommand");
+    assert_eq!(screen.row_text(7), "");
+    drop(screen);
+
+    editor.feed_keys(":q!<CR>ignored").unwrap();
+    assert!(!editor.is_running());
+}
+
+#[test]
+fn test_headless_resize() {
+    let mut editor = Editor::headless(40, 8);
+    editor.resize_screen(8, 1).unwrap();
+    assert_eq!(editor.screen().unwrap().text(), "window t");
+
+    editor.resize_screen(20, 4).unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.width(), 20);
+    assert_eq!(screen.find("[No Name]"), Some((0, 2)));
+}
+
+#[test]
+fn test_headless_inline() {
+    let mut editor = Editor::headless(40, 20);
+    editor.set_inline(true);
+    editor.resize_screen(40, 20).unwrap();
+    editor.feed_keys(":abc").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.find("[No Name]"), Some((0, 13)));
+    assert_eq!(screen.row_text(14), ":abc");
+    assert_eq!(screen.text().lines().skip(15).collect::<String>(), "");
+    drop(screen);
+
+    // Shorter than `inline_height`: the whole terminal
+    editor.resize_screen(40, 6).unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(5), ":abc");
+}
@@ -0,0 +1,56 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+fn hold_editor() -> Editor {
+    Editor::headless_with_config(60, 8, |config| config.editor.updatetime = 30)
+}
+
+/// Run frames for `duration`
+fn idle(editor: &mut Editor, duration: Duration) {
+    let until = Instant::now() + duration;
+    while Instant::now() < until {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+    }
+}
+
+#[test]
+fn test_cursor_hold_fires_once_per_pause() {
+    let mut editor = hold_editor();
+    let holds = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&holds);
+    editor.on_cursor_hold(move |_| counter.set(counter.get() + 1));
+
+    // Typing keeps putting it off
+    for key in ["i", "a", "b", "c", "<Esc>"] {
+        editor.feed_keys(key).unwrap();
+        idle(&mut editor, Duration::from_millis(10));
+    }
+    assert_eq!(holds.get(), 0);
+
+    idle(&mut editor, Duration::from_millis(150));
+    assert_eq!(holds.get(), 1);
+
+    editor.feed_keys("h").unwrap();
+    idle(&mut editor, Duration::from_millis(150));
+    assert_eq!(holds.get(), 2);
+}
+
+#[test]
+fn test_cursor_hold_notices_changed_files() {
+    let dir = TempDir::new("hold");
+    let path = dir.join("hold.txt");
+    std::fs::write(&path, "one").unwrap();
+    let mut editor = hold_editor();
+    assert!(editor.open_file_at(&path, None));
+
+    // With `autoread` the file is taken in without a key being pressed
+    std::fs::write(&path, "changed outside").unwrap();
+    idle(&mut editor, Duration::from_millis(150));
+    let content = &editor.buffer_manager.current().unwrap().content;
+    assert_eq!(content, "changed outside");
+    assert!(editor.screen().unwrap().row_text(6).contains("reloaded"));
+}
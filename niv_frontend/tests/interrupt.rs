@@ -0,0 +1,83 @@
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use niv_frontend::{
+    clear_interrupt, defer_event, interrupt, interrupted, is_interrupt_key, run_filter,
+    take_pending_event,
+};
+use std::io::ErrorKind;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The flag and the pending events are shared by the whole process
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn serial() -> std::sync::MutexGuard<'static, ()> {
+    SERIAL.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn key(ch: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(ch), KeyModifiers::NONE))
+}
+
+#[test]
+fn test_interrupt_flag_is_set_until_cleared() {
+    let _serial = serial();
+    clear_interrupt();
+    assert!(!interrupted());
+
+    interrupt();
+    assert!(interrupted());
+    assert!(interrupted());
+    assert!(clear_interrupt());
+    assert!(!interrupted());
+    assert!(!clear_interrupt());
+}
+
+#[test]
+fn test_ctrl_c_is_the_interrupt_key() {
+    let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert!(is_interrupt_key(&ctrl_c));
+    assert!(!is_interrupt_key(&KeyEvent::new(
+        KeyCode::Char('c'),
+        KeyModifiers::NONE
+    )));
+    let mut release = ctrl_c;
+    release.kind = KeyEventKind::Release;
+    assert!(!is_interrupt_key(&release));
+}
+
+#[test]
+fn test_deferred_events_come_back_first_in_last_out() {
+    let _serial = serial();
+    while take_pending_event().is_some() {}
+
+    defer_event(key('a'));
+    defer_event(key('b'));
+    assert_eq!(take_pending_event(), Some(key('b')));
+    defer_event(key('c'));
+    assert_eq!(take_pending_event(), Some(key('c')));
+    assert_eq!(take_pending_event(), Some(key('a')));
+    assert_eq!(take_pending_event(), None);
+}
+
+#[test]
+fn test_interrupt_kills_a_running_filter() {
+    let _serial = serial();
+    clear_interrupt();
+    let cwd = std::env::temp_dir();
+    assert_eq!(
+        run_filter("cat", "kept\n", &cwd, interrupted).unwrap(),
+        "kept\n"
+    );
+
+    let started = Instant::now();
+    let interrupter = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(100));
+        interrupt();
+    });
+    let error = run_filter("sleep 5", "", &cwd, interrupted).unwrap_err();
+    interrupter.join().unwrap();
+    assert_eq!(error.kind(), ErrorKind::Interrupted);
+    assert!(started.elapsed() < Duration::from_secs(4));
+    assert!(clear_interrupt());
+}
@@ -0,0 +1,65 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use niv_frontend::{Editor, EditorMode};
+
+fn content(editor: &Editor) -> String {
+    editor.buffer_manager.current().unwrap().content.clone()
+}
+
+fn press(editor: &mut Editor, code: KeyCode, modifiers: KeyModifiers) {
+    editor.send_key(KeyEvent::new(code, modifiers)).unwrap();
+}
+
+fn ctrl(editor: &mut Editor, ch: char) {
+    press(editor, KeyCode::Char(ch), KeyModifiers::CONTROL);
+}
+
+#[test]
+fn test_key_releases_are_ignored() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("i").unwrap();
+    for kind in [
+        KeyEventKind::Press,
+        KeyEventKind::Repeat,
+        KeyEventKind::Release,
+    ] {
+        let event = KeyEvent::new_with_kind(KeyCode::Char('x'), KeyModifiers::NONE, kind);
+        editor.send_key(event).unwrap();
+    }
+    assert_eq!(content(&editor), "xx");
+}
+
+#[test]
+fn test_shifted_letters_match_their_upper_case_bindings() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("iabc<Esc>0").unwrap();
+    // The protocol reports the key, which is lower case, with Shift held
+    press(&mut editor, KeyCode::Char('a'), KeyModifiers::SHIFT);
+    editor.feed_keys("!").unwrap();
+    assert_eq!(content(&editor), "abc!");
+}
+
+#[test]
+fn test_control_keys_type_as_in_legacy_terminals() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("i").unwrap();
+
+    // Told apart from Tab, but with nothing bound to it still a tab
+    ctrl(&mut editor, 'i');
+    press(&mut editor, KeyCode::Tab, KeyModifiers::NONE);
+    editor.feed_keys("ab").unwrap();
+    ctrl(&mut editor, 'h');
+    assert_eq!(content(&editor), "\t\ta");
+
+    ctrl(&mut editor, '[');
+    assert_eq!(editor.mode(), EditorMode::Normal);
+
+    editor.feed_keys(":pwx").unwrap();
+    ctrl(&mut editor, 'h');
+    editor.feed_keys("d").unwrap();
+    ctrl(&mut editor, 'm');
+    assert_eq!(editor.mode(), EditorMode::Normal);
+    let message = editor.screen().unwrap().row_text(6);
+    editor.feed_keys(":pwd<CR>").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(6), message);
+    assert!(!message.contains("Unknown command"), "{}", message);
+}
@@ -0,0 +1,11 @@
+use niv_frontend::Editor;
+
+#[test]
+fn test_ctrl_v_inserts_control_characters() {
+    let mut editor = Editor::headless(40, 8);
+    editor
+        .feed_keys("i<C-v><C-a><C-v><C-[><C-v><C-é><C-v><C-1><Esc>")
+        .unwrap();
+    let content = &editor.buffer_manager.current().unwrap().content;
+    assert_eq!(content, "\u{1}\u{1b}é1");
+}
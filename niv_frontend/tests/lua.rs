@@ -0,0 +1,71 @@
+#![cfg(feature = "lua")]
+
+use niv_frontend::Editor;
+use std::time::{Duration, Instant};
+
+fn content(editor: &Editor) -> String {
+    editor.buffer_manager.current().unwrap().content.clone()
+}
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+fn row(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(0).trim_end().to_string()
+}
+
+#[test]
+fn test_lua_commands_and_mappings() {
+    let mut editor = Editor::headless(60, 8);
+    editor
+        .feed_keys(":lua niv.command('Shout', function() niv.edit(0, #niv.text(), niv.text():upper() .. niv.args()) end)<CR>")
+        .unwrap();
+    editor
+        .feed_keys(":lua niv.map('Q', 'Shout !')<CR>")
+        .unwrap();
+
+    editor.feed_keys("ihello<Esc>:Shout ?<CR>").unwrap();
+    assert_eq!(content(&editor), "HELLO?");
+    editor.feed_keys("Q").unwrap();
+    assert_eq!(content(&editor), "HELLO?!");
+}
+
+#[test]
+fn test_lua_reads_the_buffer_and_runs_ex_commands() {
+    let mut editor = Editor::headless(60, 8);
+    editor.feed_keys("iabc<Esc>0l").unwrap();
+    editor
+        .feed_keys(":lua local l, c = niv.cursor(); niv.message(niv.text() .. ' at ' .. l .. ':' .. c)<CR>")
+        .unwrap();
+    assert_eq!(message(&editor), "abc at 0:1");
+
+    editor
+        .feed_keys(":lua niv.edit(0, 0, '\\t'); niv.set('tabstop=2')<CR>")
+        .unwrap();
+    assert_eq!(row(&editor), "   1   abc");
+    editor
+        .feed_keys(":lua niv.cmd('setlocal ts=6')<CR>")
+        .unwrap();
+    assert_eq!(row(&editor), "   1       abc");
+
+    // Errors are reported rather than raised
+    editor.feed_keys(":lua error('boom')<CR>").unwrap();
+    assert!(message(&editor).contains("boom"), "{}", message(&editor));
+    assert_eq!(content(&editor), "\tabc");
+}
+
+#[test]
+fn test_lua_autocmd_runs_on_cursor_hold() {
+    let mut editor = Editor::headless_with_config(60, 8, |config| config.editor.updatetime = 30);
+    editor
+        .feed_keys(":lua niv.autocmd('CursorHold', function() niv.edit(0, 0, '.') end)<CR>")
+        .unwrap();
+    editor.feed_keys("ix<Esc>").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while content(&editor) == "x" && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+    }
+    assert_eq!(content(&editor), ".x");
+}
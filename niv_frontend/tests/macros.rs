@@ -0,0 +1,34 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_macros_are_replayed_and_kept_between_runs() {
+    let dir = TempDir::new("macros");
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    std::fs::write(&a, "one\ntwo\nthree\n").unwrap();
+    std::fs::write(&b, "four\nfive\n").unwrap();
+    let shada = dir.join("shada");
+    let content = |editor: &Editor| editor.buffer_manager.current().unwrap().content.clone();
+
+    let mut editor = Editor::headless(40, 8);
+    editor.start_shada(shada.clone());
+    assert!(editor.open_file_at(&a, None));
+    editor.feed_keys("qaA!<Esc>jq").unwrap();
+    assert_eq!(content(&editor), "one!\ntwo\nthree");
+    editor.feed_keys("@a@@").unwrap();
+    assert_eq!(content(&editor), "one!\ntwo!\nthree!");
+    editor.feed_keys("@b").unwrap();
+    let message = editor.screen().unwrap().row_text(6);
+    assert!(message.contains("Register b is empty"));
+    editor.save_shada().unwrap();
+
+    // The next run replays the macro recorded in this one
+    let mut editor = Editor::headless(40, 8);
+    editor.start_shada(shada);
+    assert!(editor.open_file_at(&b, None));
+    editor.feed_keys("@a").unwrap();
+    assert_eq!(content(&editor), "four!\nfive");
+    editor.feed_keys(":macros<CR>").unwrap();
+    let listing = editor.screen().unwrap().row_text(0);
+    assert!(listing.contains("@a  A!<Esc>j"));
+}
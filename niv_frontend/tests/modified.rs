@@ -0,0 +1,30 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_undone_edits_leave_the_buffer_unmodified() {
+    let dir = TempDir::new("modified");
+    let path = dir.join("modified.txt");
+    std::fs::write(&path, "one\ntwo").unwrap();
+
+    let mut editor = Editor::headless(40, 8);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("ix<Esc>").unwrap();
+    assert!(editor.screen().unwrap().row_text(6).contains("[+]"));
+
+    // Taking the typed text out again brings the buffer back to the file
+    editor.feed_keys("i<BS><Esc>").unwrap();
+    assert!(!editor.screen().unwrap().row_text(6).contains("[+]"));
+
+    // `:update` leaves an unchanged file alone
+    let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86_400);
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(old).unwrap();
+    drop(file);
+    editor.feed_keys(":update<CR>").unwrap();
+    assert_eq!(std::fs::metadata(&path).unwrap().modified().unwrap(), old);
+
+    editor.feed_keys("iy<Esc>:update<CR>").unwrap();
+    assert_eq!(niv_fs::load_file(&path).unwrap().content, "yone\ntwo");
+    assert!(!editor.screen().unwrap().row_text(6).contains("[+]"));
+}
@@ -0,0 +1,38 @@
+use niv_frontend::{Editor, line_number_width};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_line_number_width_fits_the_largest_number() {
+    assert_eq!(line_number_width(1, true, false), 5);
+    assert_eq!(line_number_width(9999, true, false), 5);
+    assert_eq!(line_number_width(10000, true, false), 6);
+    assert_eq!(line_number_width(123456, true, true), 7);
+    // The largest relative number is one less than the line count
+    assert_eq!(line_number_width(10000, false, true), 5);
+    assert_eq!(line_number_width(10001, false, true), 6);
+    assert_eq!(line_number_width(10000, false, false), 0);
+}
+
+#[test]
+fn test_gutter_widens_for_large_files() {
+    let dir = TempDir::new("numbers");
+    let path = dir.join("numbers");
+    let text: String = (1..=10000).map(|n| format!("line {}\n", n)).collect();
+    std::fs::write(&path, text).unwrap();
+    let mut editor = Editor::headless(40, 6);
+    assert!(editor.open_file_at(&path, None));
+
+    editor.feed_keys("G").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(3), "10000 line 10000");
+    assert_eq!(screen.row_text(0), " 9997 line 9997");
+    assert_eq!(screen.cursor(), (6, 3));
+    drop(screen);
+
+    // Back under 10000 lines the column narrows again
+    editor.feed_keys("vd").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(2), "9999 line 9999");
+    assert_eq!(screen.cursor(), (5, 2));
+    drop(screen);
+}
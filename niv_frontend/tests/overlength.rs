@@ -0,0 +1,69 @@
+use niv_frontend::{DisplayOptions, Editor, overlength_start, parse_editorconfig};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_overlength_start_counts_display_columns() {
+    let options = DisplayOptions::default();
+    assert_eq!(overlength_start("abcdef", 4, &options), Some(4));
+    assert_eq!(overlength_start("abcd", 4, &options), None);
+    // A tab reaches the next stop and a wide character takes two columns
+    assert_eq!(overlength_start("\tab", 5, &options), Some(2));
+    assert_eq!(overlength_start("a日本", 4, &options), Some(4));
+    // Zero turns the limit off
+    assert_eq!(overlength_start("abcdef", 0, &options), None);
+}
+
+#[test]
+fn test_editorconfig_sections_match_by_glob() {
+    let text = "root = true\n\n[*]\nmax_line_length = 100\n\n[*.{rs,toml}]\nmax_line_length = 80\n\n[docs/**.md]\nmax_line_length = off\n";
+    let value = |path| parse_editorconfig(text, path, "max_line_length").0;
+    assert_eq!(value("src/main.rs").as_deref(), Some("80"));
+    assert_eq!(value("Cargo.toml").as_deref(), Some("80"));
+    assert_eq!(value("README.md").as_deref(), Some("100"));
+    assert_eq!(value("docs/guide/intro.md").as_deref(), Some("off"));
+    assert!(parse_editorconfig(text, "a.rs", "indent_size").0.is_none());
+    assert!(parse_editorconfig(text, "a.rs", "max_line_length").1);
+    assert!(!parse_editorconfig("[*]\nroot = true\n", "a.rs", "max_line_length").1);
+}
+
+#[test]
+fn test_overlong_text_is_highlighted_and_counted() {
+    let dir = TempDir::new("overlength");
+    let path = dir.join("overlength.txt");
+    std::fs::write(&path, "short\nthis line is long\n").unwrap();
+
+    let mut editor = Editor::headless(60, 6);
+    assert!(editor.open_file_at(&path, None));
+    // Leaving insert mode clears the message that covers the status line
+    editor.feed_keys(":setlocal tw=10<CR>i<Esc>").unwrap();
+    let screen = editor.screen().unwrap();
+    // Columns past the tenth, after the 5 columns of line numbers
+    assert!(!screen.cell(14, 1).underline);
+    assert!(screen.cell(15, 1).underline);
+    assert!(!screen.cell(5, 0).underline);
+    assert!(screen.text().contains("[1 overlong]"));
+    drop(screen);
+
+    // The count follows edits
+    editor.feed_keys("A and more<Esc>").unwrap();
+    assert!(editor.screen().unwrap().text().contains("[2 overlong]"));
+    editor.feed_keys(":setlocal tw=0<CR>i<Esc>").unwrap();
+    assert!(!editor.screen().unwrap().text().contains("overlong"));
+}
+
+#[test]
+fn test_editorconfig_max_line_length_applies() {
+    let dir = TempDir::new("editorconfig");
+    std::fs::write(
+        dir.join(".editorconfig"),
+        "root = true\n[*.txt]\nmax_line_length = 4\n",
+    )
+    .unwrap();
+    let path = dir.join("notes.txt");
+    std::fs::write(&path, "one\nthree\nfive!\n").unwrap();
+
+    let mut editor = Editor::headless(60, 6);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("i<Esc>").unwrap();
+    assert!(editor.screen().unwrap().text().contains("[2 overlong]"));
+}
@@ -0,0 +1,63 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+use std::io::Cursor;
+use std::time::Duration;
+
+#[test]
+fn test_pager_scrolls_and_searches() {
+    let text: String = (1..=100).map(|i| format!("line {}\n", i)).collect();
+    let dir = TempDir::new("pager");
+    let path = dir.join("pager.txt");
+    std::fs::write(&path, text).unwrap();
+
+    let mut editor = Editor::headless(50, 12);
+    editor.start_pager();
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("j").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   2 line 2");
+    assert!(screen.row_text(10).ends_with("lines 2-11/100 11%"));
+    drop(screen);
+
+    // Editing keys do nothing; `d` scrolls half a page
+    editor.feed_keys("ixd").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(0), "   7 line 7");
+
+    editor.feed_keys("/line 5<CR>").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(0), "  50 line 50");
+    editor.feed_keys("n").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(0), "  51 line 51");
+    editor.feed_keys("N").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(0), "  50 line 50");
+    editor.feed_keys("/nothing<CR>").unwrap();
+    assert_eq!(
+        editor.screen().unwrap().row_text(10),
+        "Pattern not found: nothing"
+    );
+
+    editor.feed_keys("G").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(9), " 100 line 100");
+    assert!(screen.row_text(10).ends_with("lines 91-100/100 (END)"));
+    drop(screen);
+
+    editor.feed_keys("q").unwrap();
+    assert!(!editor.is_running());
+}
+
+#[test]
+fn test_pager_reads_stream() {
+    let mut editor = Editor::headless(40, 6);
+    editor.start_pager();
+    editor.view_stream("[test]", Cursor::new("first\nsecond\n".as_bytes().to_vec()));
+    for _ in 0..200 {
+        editor.resize_screen(40, 6).unwrap();
+        if editor.screen().unwrap().row_text(4).ends_with("(END)") {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(1), "   2 second");
+    assert_eq!(screen.row_text(4), "[test] lines 1-2/2 (END)");
+}
@@ -0,0 +1,26 @@
+use niv_frontend::Editor;
+
+#[test]
+fn test_palette_filters_and_runs_commands() {
+    let mut editor = Editor::headless(60, 10);
+    editor.feed_keys("<F1>").unwrap();
+    let screen = editor.screen().unwrap();
+    assert!(screen.row_text(0).starts_with(" :write [file]"));
+    assert_eq!(screen.row_text(9), ">");
+    drop(screen);
+
+    editor.feed_keys("movdown").unwrap();
+    let screen = editor.screen().unwrap();
+    assert!(screen.row_text(0).starts_with(" move_down       j "));
+    assert_eq!(screen.row_text(9), "> movdown");
+    drop(screen);
+
+    editor.feed_keys("<Esc><F1>xyzzy").unwrap();
+    assert_eq!(
+        editor.screen().unwrap().row_text(0),
+        " no matching commands"
+    );
+
+    editor.feed_keys("<Esc><F1>reop<CR>").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(8), "No closed buffers");
+}
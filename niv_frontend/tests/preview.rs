@@ -0,0 +1,73 @@
+use niv_frontend::{Editor, MarkdownPreview};
+use niv_fs::testing::TempDir;
+
+/// The preview pane of a screen row, right of the separator
+fn preview_text(editor: &Editor, y: u16) -> String {
+    let row = editor.screen().unwrap().row_text(y);
+    row.split_once('│')
+        .map(|(_, preview)| preview.to_string())
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_markdown_preview_renders_and_follows_edits() {
+    let text = "# Title\n\nSome *emphasis* and `code`.\n\n- one\n- two\n\n```\nlet x = 1;\n```\n";
+    let dir = TempDir::new("preview");
+    let path = dir.join("preview.md");
+    std::fs::write(&path, text).unwrap();
+
+    let mut editor = Editor::headless(60, 12);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys(":MarkdownPreview<CR>").unwrap();
+    assert_eq!(preview_text(&editor, 0), "Title");
+    assert_eq!(preview_text(&editor, 1), "═════");
+    assert_eq!(preview_text(&editor, 3), "Some emphasis and code.");
+    assert_eq!(preview_text(&editor, 5), "• one");
+    assert_eq!(preview_text(&editor, 8), "  let x = 1;");
+
+    // Edits show up in the preview
+    editor.feed_keys("ggA again<Esc>").unwrap();
+    assert_eq!(preview_text(&editor, 0), "Title again");
+    assert_eq!(preview_text(&editor, 1), "═══════════");
+    editor.feed_keys("jjjjjo- three<Esc>").unwrap();
+    assert_eq!(preview_text(&editor, 7), "• three");
+
+    // Toggled off, the buffer has the whole width again
+    editor.feed_keys(":MarkdownPreview<CR>").unwrap();
+    assert!(!editor.screen().unwrap().row_text(0).contains('│'));
+}
+
+#[test]
+fn test_markdown_preview_scrolls_with_buffer() {
+    let text: String = (1..=30).map(|i| format!("Paragraph {}\n\n", i)).collect();
+    let dir = TempDir::new("preview_scroll");
+    let path = dir.join("preview_scroll.md");
+    std::fs::write(&path, text).unwrap();
+
+    let mut editor = Editor::headless(60, 12);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys(":MarkdownPreview<CR>G").unwrap();
+    // The buffer's top line is the 51st, the first of paragraph 26
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .row_text(0)
+            .starts_with("  51 Paragraph 26")
+    );
+    assert_eq!(preview_text(&editor, 0), "Paragraph 26");
+}
+
+#[test]
+fn test_markdown_preview_renders_only_edited_blocks() {
+    let mut preview = MarkdownPreview::new(40);
+    assert_eq!(preview.update("# One\n\ntext\n\n- item\n"), 3);
+    assert_eq!(preview.update("# One\n\nmore text\n\n- item\n"), 1);
+    assert_eq!(preview.update("# One\n\nmore text\n\n- item\n"), 0);
+    let lines: Vec<String> = preview
+        .visible(0, 10)
+        .iter()
+        .map(|line| line.text())
+        .collect();
+    assert_eq!(lines, ["One", "═══", "", "more text", "", "• item"]);
+}
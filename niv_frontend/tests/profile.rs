@@ -0,0 +1,59 @@
+use niv_frontend::Editor;
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+/// The row of a report table starting with `name`, split into columns
+fn report_row(report: &str, name: &str) -> Vec<String> {
+    let line = report
+        .lines()
+        .find(|line| line.split_whitespace().next() == Some(name))
+        .unwrap_or_else(|| panic!("no `{}` row in:\n{}", name, report));
+    line.split_whitespace().map(str::to_string).collect()
+}
+
+#[test]
+fn test_profile_records_frames_and_commands() {
+    let mut editor = Editor::headless(60, 8);
+    editor.feed_keys(":profile report<CR>").unwrap();
+    assert_eq!(message(&editor), "Not profiling");
+
+    editor.feed_keys(":profile start<CR>").unwrap();
+    assert_eq!(message(&editor), "Profiling started");
+    editor.feed_keys("ihello<Esc>:pwd<CR>:pwd<CR>").unwrap();
+    editor.feed_keys(":profile stop<CR>").unwrap();
+
+    // The report opens in a buffer of its own
+    let buffer = editor.buffer_manager.current().unwrap();
+    let report = buffer.content.clone();
+    assert!(buffer.file_path.is_none());
+    assert!(!buffer.modified);
+    let frames: usize = report_row(&report, "Profile:")[1].parse().unwrap();
+    assert!(frames >= 9, "{}", report);
+    for phase in ["events", "layout", "draw", "flush", "total"] {
+        assert_eq!(report_row(&report, phase).len(), 5);
+    }
+    assert_eq!(report_row(&report, "pwd")[1], "2");
+    assert_eq!(report_row(&report, "profile")[1], "1");
+    assert!(editor.screen().unwrap().row_text(0).contains("Profile:"));
+
+    // Stopping ends the recording
+    editor.feed_keys(":profile report<CR>").unwrap();
+    assert_eq!(message(&editor), "Not profiling");
+    editor.feed_keys(":profile restart<CR>").unwrap();
+    assert_eq!(message(&editor), "Usage: :profile start|stop|report");
+}
+
+#[test]
+fn test_profile_report_keeps_recording() {
+    let mut editor = Editor::headless(60, 8);
+    editor.start_profiling();
+    editor.feed_keys("ix<Esc>").unwrap();
+    let first = editor.profile_report().unwrap();
+    editor.feed_keys(":profile report<CR>").unwrap();
+    let second = editor.buffer_manager.current().unwrap().content.clone();
+    let frames = |report: &str| -> usize { report_row(report, "Profile:")[1].parse().unwrap() };
+    assert!(frames(&second) > frames(&first));
+    assert!(editor.profile_report().is_some());
+}
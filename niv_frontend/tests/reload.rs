@@ -0,0 +1,59 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn content(editor: &Editor) -> String {
+    editor.buffer_manager.current().unwrap().content.clone()
+}
+
+fn shows(editor: &Editor, text: &str) -> bool {
+    editor.screen().unwrap().text().contains(text)
+}
+
+#[test]
+fn test_reload_discards_changes_only_when_forced() {
+    let dir = TempDir::new("reload");
+    let path = dir.join("reload.txt");
+    std::fs::write(&path, "one\ntwo").unwrap();
+    let mut editor = Editor::headless(100, 8);
+    assert!(editor.open_file_at(&path, None));
+
+    editor.feed_keys("ix<Esc>:e<CR>").unwrap();
+    assert_eq!(content(&editor), "xone\ntwo");
+    assert!(shows(&editor, "No write since last change"));
+
+    editor.feed_keys(":e!<CR>").unwrap();
+    assert_eq!(content(&editor), "one\ntwo");
+    assert!(shows(&editor, "reloaded"));
+    assert!(!editor.screen().unwrap().row_text(6).contains("[+]"));
+}
+
+#[test]
+fn test_checktime_asks_before_replacing_a_modified_buffer() {
+    let dir = TempDir::new("checktime");
+    let path = dir.join("checktime.txt");
+    std::fs::write(&path, "one").unwrap();
+    let mut editor = Editor::headless(100, 8);
+    assert!(editor.open_file_at(&path, None));
+
+    editor.feed_keys(":checktime<CR>").unwrap();
+    assert!(shows(&editor, "No files changed on disk"));
+
+    // A modified buffer waits for an answer; other keys leave it up
+    editor.feed_keys("ix<Esc>").unwrap();
+    std::fs::write(&path, "changed outside").unwrap();
+    editor.feed_keys(":checktime<CR>").unwrap();
+    assert!(shows(&editor, "changed on disk"));
+    editor.feed_keys("j").unwrap();
+    assert!(shows(&editor, "changed on disk"));
+    editor.feed_keys("l").unwrap();
+    assert_eq!(content(&editor), "changed outside");
+    assert!(!shows(&editor, "changed on disk"));
+
+    // Keeping the buffer takes the file as seen, so it is not asked again
+    editor.feed_keys("0iy<Esc>").unwrap();
+    std::fs::write(&path, "changed again").unwrap();
+    editor.feed_keys(":checktime<CR>k").unwrap();
+    assert_eq!(content(&editor), "ychanged outside");
+    editor.feed_keys(":checktime<CR>").unwrap();
+    assert!(shows(&editor, "No files changed on disk"));
+}
@@ -0,0 +1,71 @@
+#![cfg(unix)]
+
+use niv_frontend::{Editor, JsonValue, RpcClient, workspace_root, workspace_socket_path};
+use niv_fs::testing::TempDir;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Run frames, which answer the client, until the client thread is done
+fn serve<T>(editor: &mut Editor, client: JoinHandle<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !client.is_finished() && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+    }
+    client.join().unwrap()
+}
+
+#[test]
+fn test_remote_opens_files_and_sends_keys_to_the_workspace_instance() {
+    let dir = TempDir::new("remote");
+    std::fs::create_dir_all(dir.join("project/.git")).unwrap();
+    std::fs::create_dir_all(dir.join("project/src")).unwrap();
+    let path = dir.join("project/src/notes.txt");
+    std::fs::write(&path, "one\ntwo\nthree").unwrap();
+
+    // `niv --remote` from a subdirectory finds the instance started at the root
+    let root = workspace_root(&dir.join("project/src"));
+    assert_eq!(root, dir.join("project"));
+    let socket = workspace_socket_path(&root);
+    assert_eq!(
+        socket,
+        workspace_socket_path(&workspace_root(&dir.join("project")))
+    );
+    assert_ne!(socket, workspace_socket_path(dir.path()));
+
+    let mut editor = Editor::headless(40, 8);
+    editor.listen(&socket).unwrap();
+    editor.feed_keys("ihello<Esc>").unwrap();
+
+    // `niv --remote +2 src/notes.txt`
+    let remote = socket.clone();
+    let file = path.display().to_string();
+    let client = std::thread::spawn(move || {
+        let mut client = RpcClient::connect(&remote).unwrap();
+        let params = JsonValue::object([("path", file.into()), ("line", 2.into())]);
+        client.call("open", params)
+    });
+    serve(&mut editor, client).unwrap();
+    let buffer = editor.buffer_manager.current().unwrap();
+    assert_eq!(buffer.file_path.as_deref(), Some(path.as_path()));
+    assert_eq!(buffer.cursor_line, 1);
+
+    // `niv --remote-send 'A!<Esc>'`
+    let remote = socket.clone();
+    let client = std::thread::spawn(move || {
+        let mut client = RpcClient::connect(&remote).unwrap();
+        client.call("input", JsonValue::object([("keys", "A!<Esc>".into())]))
+    });
+    serve(&mut editor, client).unwrap();
+    let content = &editor.buffer_manager.current().unwrap().content;
+    assert_eq!(content, "one\ntwo!\nthree");
+    assert_eq!(editor.screen().unwrap().row_text(1).trim_end(), "   2 two!");
+
+    // Failures are reported back to the client rather than dropped
+    let client = std::thread::spawn(move || {
+        let mut client = RpcClient::connect(&socket).unwrap();
+        client.call("input", JsonValue::Object(Vec::new()))
+    });
+    let error = serve(&mut editor, client).unwrap_err();
+    assert!(error.to_string().contains("keys"), "{}", error);
+}
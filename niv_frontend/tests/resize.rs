@@ -0,0 +1,54 @@
+use niv_frontend::Editor;
+use std::time::{Duration, Instant};
+
+fn rows(editor: &Editor) -> Vec<String> {
+    let screen = editor.screen().unwrap();
+    let height = screen.text().lines().count() as u16;
+    (0..height)
+        .map(|y| screen.row_text(y).trim_end().to_string())
+        .collect()
+}
+
+/// Run frames until `done` holds, for at most two seconds
+fn run_until(editor: &mut Editor, done: impl Fn(&Editor) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while !done(editor) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+    }
+}
+
+#[test]
+fn test_a_burst_of_resizes_is_laid_out_once() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("ihello<Esc>").unwrap();
+    let status = rows(&editor)[6].clone();
+    assert!(status.starts_with("[No Name]"), "{}", status);
+
+    // Dragging the window edge; the layout keeps the old size meanwhile
+    for height in [9, 10, 11, 12] {
+        editor.send_resize(40, height).unwrap();
+        assert_eq!(rows(&editor)[6], status);
+    }
+    run_until(&mut editor, |editor| rows(editor)[10] == status);
+    assert_eq!(rows(&editor)[10], status);
+    assert_eq!(rows(&editor)[0], "   1 hello");
+}
+
+#[test]
+fn test_too_small_screens_show_a_placeholder() {
+    let mut editor = Editor::headless(40, 8);
+    editor.feed_keys("ihello<Esc>").unwrap();
+
+    editor.resize_screen(12, 8).unwrap();
+    let placeholder = rows(&editor);
+    assert_eq!(placeholder[4], "window too s");
+    assert!(placeholder.iter().all(|row| !row.contains("hello")));
+
+    // Keys still work, and everything comes back at a usable size
+    editor.feed_keys("A!<Esc>").unwrap();
+    editor.resize_screen(40, 1).unwrap();
+    editor.resize_screen(1, 1).unwrap();
+    editor.resize_screen(40, 8).unwrap();
+    assert_eq!(rows(&editor)[0], "   1 hello!");
+}
@@ -0,0 +1,54 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+use std::time::{Duration, Instant};
+
+fn open(dir: &TempDir, smooth: bool) -> Editor {
+    let path = dir.join("scroll.txt");
+    let text: Vec<String> = (1..=100).map(|n| format!("line {}", n)).collect();
+    std::fs::write(&path, text.join("\n")).unwrap();
+    let mut editor = Editor::headless_with_config(40, 8, |config| {
+        config.editor.smooth_scroll = smooth;
+    });
+    assert!(editor.open_file_at(&path, None));
+    editor
+}
+
+/// Number of the line drawn at the top of the window
+fn top_line(editor: &Editor) -> usize {
+    let row = editor.screen().unwrap().row_text(0);
+    row.split_whitespace().next().unwrap().parse().unwrap()
+}
+
+#[test]
+fn test_smooth_scroll_draws_frames_on_the_way() {
+    let dir = TempDir::new("scroll_smooth");
+    let mut editor = open(&dir, true);
+
+    editor.feed_keys("G").unwrap();
+    let mut seen = vec![top_line(&editor)];
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while Instant::now() < deadline && seen.last() != Some(&95) {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+        if seen.last() != Some(&top_line(&editor)) {
+            seen.push(top_line(&editor));
+        }
+    }
+    assert_eq!(seen.last(), Some(&95));
+    assert!(seen.len() > 2, "no frames in between: {:?}", seen);
+    assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // A key ends the animation at once
+    editor.feed_keys("gg").unwrap();
+    assert!(top_line(&editor) > 1);
+    editor.feed_keys("j").unwrap();
+    assert_eq!(top_line(&editor), 1);
+}
+
+#[test]
+fn test_scroll_jumps_without_smooth_scroll() {
+    let dir = TempDir::new("scroll_jump");
+    let mut editor = open(&dir, false);
+    editor.feed_keys("G").unwrap();
+    assert_eq!(top_line(&editor), 95);
+}
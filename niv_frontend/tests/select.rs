@@ -0,0 +1,57 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn open(text: &str, name: &str) -> (Editor, TempDir) {
+    let dir = TempDir::new("select");
+    let path = dir.join(format!("{}.txt", name));
+    std::fs::write(&path, text).unwrap();
+    let mut editor = Editor::headless(40, 6);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("i<Esc>").unwrap();
+    (editor, dir)
+}
+
+fn row(editor: &Editor, y: u16) -> String {
+    editor.screen().unwrap().row_text(y).trim_end().to_string()
+}
+
+#[test]
+fn test_typing_replaces_the_selection() {
+    let (mut editor, _dir) = open("hello world\nsecond\n", "replace");
+    editor.feed_keys("gh").unwrap();
+    assert_eq!(row(&editor, 5), "-- SELECT --");
+
+    // Unshifted arrows extend the selection while `shift_select` is off
+    editor
+        .feed_keys("<Right><S-Right><Right><Right><Right>")
+        .unwrap();
+    let screen = editor.screen().unwrap();
+    let highlight = screen.cell(5, 0).bg;
+    assert_eq!(screen.cell(9, 0).bg, highlight);
+    assert_ne!(screen.cell(10, 0).bg, highlight);
+    drop(screen);
+
+    editor.feed_keys("Hi").unwrap();
+    assert_eq!(row(&editor, 0), "   1 Hi world");
+    assert_eq!(row(&editor, 5), "-- INSERT --");
+    assert_ne!(editor.screen().unwrap().cell(7, 0).bg, highlight);
+
+    // Backspace deletes the selection across lines
+    editor.feed_keys("<Esc>gh<Down><BS>").unwrap();
+    assert_eq!(row(&editor, 0), "   1 Hicond");
+    assert_eq!(row(&editor, 5), "");
+}
+
+#[test]
+fn test_visual_lines_switch_to_select_mode() {
+    let (mut editor, _dir) = open("one\ntwo\nthree\n", "visual");
+    editor.feed_keys("vj<C-g>").unwrap();
+    assert_eq!(row(&editor, 5), "-- SELECT --");
+    editor.feed_keys("x").unwrap();
+    assert_eq!(row(&editor, 0), "   1 x");
+    assert_eq!(row(&editor, 1), "   2 three");
+
+    // Without `shift_select` Shift+Arrow only moves the cursor
+    editor.feed_keys("<Esc><S-Right>").unwrap();
+    assert_eq!(row(&editor, 5), "");
+}
@@ -0,0 +1,41 @@
+use niv_frontend::{Editor, find_workspace_root};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_auto_session_is_saved_and_restored() {
+    let project = TempDir::new("session");
+    let src = project.join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(project.join(".niv.toml"), "").unwrap();
+    let first = src.join("first.txt");
+    let second = src.join("second.txt");
+    std::fs::write(&first, "one\ntwo\nthree\n").unwrap();
+    std::fs::write(&second, "alpha\nbeta\n").unwrap();
+    assert_eq!(find_workspace_root(&src), Some(project.path().to_path_buf()));
+    let session_file = project.join("session");
+
+    let mut editor = Editor::headless(70, 6);
+    editor.start_auto_session(session_file.clone(), true);
+    assert!(editor.open_file_at(&second, Some(2)));
+    assert!(editor.open_file_at(&first, Some(3)));
+    editor.feed_keys("ll").unwrap();
+    editor.save_auto_session().unwrap();
+
+    // Declining leaves the editor as it was
+    let mut editor = Editor::headless(70, 6);
+    editor.start_auto_session(session_file.clone(), true);
+    editor.feed_keys("x").unwrap();
+    let prompt = "Restore the last session of this project (2 files)? [Y]es, [N]o";
+    assert_eq!(editor.screen().unwrap().row_text(5), prompt);
+    editor.feed_keys("n").unwrap();
+    let status = editor.screen().unwrap().row_text(4);
+    assert!(status.starts_with("[No Name]"));
+
+    let mut editor = Editor::headless(70, 6);
+    editor.start_auto_session(session_file.clone(), true);
+    editor.feed_keys("y").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(2), "   3 three");
+    assert_eq!(screen.cursor(), (7, 2));
+    drop(screen);
+}
@@ -0,0 +1,42 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_marks_and_positions_are_kept_between_runs() {
+    let dir = TempDir::new("shada");
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    std::fs::write(&a, "one\ntwo\n  three\nfour\n").unwrap();
+    std::fs::write(&b, "other\n").unwrap();
+    let shada = dir.join("state").join("shada");
+    let cursor = |editor: &Editor| {
+        let buffer = editor.buffer_manager.current().unwrap();
+        (
+            buffer.file_path.clone().unwrap(),
+            buffer.cursor_line,
+            buffer.cursor_col,
+        )
+    };
+
+    let mut editor = Editor::headless(40, 8);
+    editor.start_shada(shada.clone());
+    assert!(editor.open_file_at(&a, None));
+    editor.feed_keys("jjlllmAj").unwrap();
+    assert!(editor.open_file_at(&b, None));
+    editor.save_shada().unwrap();
+
+    // The next run opens `a` where its cursor was left
+    let mut editor = Editor::headless(40, 8);
+    editor.start_shada(shada);
+    assert!(editor.open_file_at(&a, None));
+    assert_eq!(cursor(&editor).1, 3);
+
+    // and jumps back to the mark from another file
+    assert!(editor.open_file_at(&b, None));
+    editor.feed_keys("`A").unwrap();
+    assert_eq!(cursor(&editor), (a.clone(), 2, 3));
+    editor.feed_keys("gg'A").unwrap();
+    assert_eq!(cursor(&editor), (a, 2, 2));
+    editor.feed_keys("'B").unwrap();
+    let message = editor.screen().unwrap().row_text(6);
+    assert!(message.contains("Mark not set"));
+}
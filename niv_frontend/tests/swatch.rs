@@ -0,0 +1,52 @@
+use crossterm::style::Color;
+use niv_frontend::{Editor, color_codes};
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_color_codes_are_found() {
+    let rgb = |line: &str| -> Vec<[u8; 3]> {
+        color_codes(line).into_iter().map(|code| code.rgb).collect()
+    };
+    assert_eq!(
+        rgb("color: #FF8000; border: #12345678"),
+        [[255, 128, 0], [0x12, 0x34, 0x56]]
+    );
+    // Not six or eight digits, or part of a longer word
+    assert!(color_codes("#fff #12345 #1234567 #abcdefgh").is_empty());
+    assert_eq!(
+        rgb("rgb(10, 20, 30) RGBA(100% 0% 50% / 0.5)"),
+        [[10, 20, 30], [255, 0, 128]]
+    );
+    assert!(color_codes("rgb(300, 0, 0) myrgb(1, 2, 3)").is_empty());
+    assert_eq!(
+        rgb("\\x1b[1;31m \\e[0m \\033[38;5;208m \x1b[48;2;1;2;3m"),
+        [[205, 0, 0], [255, 135, 0], [1, 2, 3]]
+    );
+
+    let codes = color_codes("a \\e[92mb");
+    assert_eq!(codes[0].range, 2..8);
+}
+
+#[test]
+fn test_color_swatches_are_drawn() {
+    let dir = TempDir::new("swatch");
+    let path = dir.join("swatch.css");
+    std::fs::write(&path, "a { color: #ff0000; }\n").unwrap();
+
+    let mut editor = Editor::headless(40, 6);
+    assert!(editor.open_file_at(&path, None));
+    editor.resize_screen(40, 6).unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 a { color: #ff0000; }");
+    // The code is drawn on its color, and the text around it is not
+    let red = Color::Rgb { r: 255, g: 0, b: 0 };
+    for x in 16..23 {
+        assert_eq!(screen.cell(x, 0).bg, red);
+    }
+    assert_ne!(screen.cell(15, 0).bg, red);
+    assert_ne!(screen.cell(23, 0).bg, red);
+    drop(screen);
+
+    editor.feed_keys(":setlocal nocolorswatch<CR>").unwrap();
+    assert_ne!(editor.screen().unwrap().cell(16, 0).bg, red);
+}
@@ -0,0 +1,38 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_csv_align_lines_up_columns() {
+    let mut text = String::from("name,qty,note\n");
+    text.push_str("apple,3,\"red, sweet\"\n");
+    for i in 1..=20 {
+        text.push_str(&format!("item{},{},x\n", i, i * 10));
+    }
+    let dir = TempDir::new("table");
+    let path = dir.join("table.csv");
+    std::fs::write(&path, text).unwrap();
+
+    let mut editor = Editor::headless(50, 8);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys(":CsvAlign<CR>").unwrap();
+    // Columns are as wide as their widest field in view
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 name  │ qty │ note");
+    // Quoted delimiters don't split fields
+    assert_eq!(screen.row_text(1), "   2 apple │ 3   │ \"red, sweet\"");
+    drop(screen);
+
+    // Tab moves cell by cell, on to the next line after the last
+    editor.feed_keys("<Tab><Tab><Tab>").unwrap();
+    assert_eq!(editor.screen().unwrap().cursor(), (5, 1));
+
+    // Scrolled down, the header stays on the top row
+    editor.feed_keys("G").unwrap();
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(0), "   1 name   │ qty │ note");
+    assert_eq!(screen.row_text(5), "  22 item20 │ 200 │ x");
+    drop(screen);
+
+    editor.feed_keys(":CsvAlign<CR>").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(5), "  22 item20,200,x");
+}
@@ -0,0 +1,45 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+use std::io::Write;
+use std::time::Duration;
+
+#[test]
+fn test_tail_follows_appended_lines() {
+    let text: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+    let dir = TempDir::new("tail");
+    let path = dir.join("tail.log");
+    std::fs::write(&path, text).unwrap();
+
+    let mut editor = Editor::headless(40, 8);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys(":tail<CR>").unwrap();
+    assert_eq!(editor.screen().unwrap().row_text(5), "  20 line 20");
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap();
+    file.write_all(b"line 21\nline 22\nline 2").unwrap();
+    for _ in 0..200 {
+        editor.resize_screen(40, 8).unwrap();
+        if editor.screen().unwrap().row_text(5) == "  22 line 22" {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    // The line still being written waits for its newline
+    let screen = editor.screen().unwrap();
+    assert_eq!(screen.row_text(4), "  21 line 21");
+    assert_eq!(screen.row_text(5), "  22 line 22");
+    drop(screen);
+
+    file.write_all(b"3\n").unwrap();
+    for _ in 0..200 {
+        editor.resize_screen(40, 8).unwrap();
+        if editor.screen().unwrap().row_text(5) == "  23 line 23" {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(editor.screen().unwrap().row_text(5), "  23 line 23");
+}
@@ -0,0 +1,66 @@
+use niv_frontend::{Editor, MessageType};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+fn message(editor: &Editor) -> String {
+    editor.screen().unwrap().row_text(6).trim_end().to_string()
+}
+
+/// Run frames until `done` holds, for at most two seconds
+fn run_until(editor: &mut Editor, done: impl Fn(&Editor) -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(2);
+    while !done(editor) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(5));
+        editor.run_frame().unwrap();
+    }
+}
+
+#[test]
+fn test_info_messages_are_dismissed_after_the_timeout() {
+    let mut editor = Editor::headless_with_config(40, 8, |config| {
+        config.editor.message_timeout = 50;
+    });
+    let status = message(&editor);
+    editor.feed_keys(":pwd<CR>").unwrap();
+    assert_ne!(message(&editor), status);
+    run_until(&mut editor, |editor| message(editor) == status);
+    assert_eq!(message(&editor), status);
+
+    // Errors stay until replaced
+    editor.feed_keys(":nosuchcommand<CR>").unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    editor.run_frame().unwrap();
+    assert_eq!(message(&editor), "Unknown command: nosuchcommand");
+}
+
+#[test]
+fn test_timers_fire_from_the_event_loop() {
+    let mut editor = Editor::headless(40, 8);
+    editor.set_timeout(Duration::from_millis(20), |editor| {
+        editor.set_message("timeout".to_string(), MessageType::Warning);
+    });
+    let ticks = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&ticks);
+    let interval = editor.set_interval(Duration::from_millis(10), move |_| {
+        counter.set(counter.get() + 1);
+    });
+    let cancelled = editor.set_timeout(Duration::from_millis(10), |editor| {
+        editor.set_message("cancelled".to_string(), MessageType::Warning);
+    });
+    assert!(editor.cancel_timer(cancelled));
+    assert!(!editor.cancel_timer(cancelled));
+
+    // Nothing runs before it is due, and keys keep working meanwhile
+    editor.feed_keys("ihi<Esc>").unwrap();
+    assert_eq!(message(&editor), "[No Name] [+] - 1:3/1 lines");
+    run_until(&mut editor, |editor| message(editor) == "timeout");
+    assert_eq!(message(&editor), "timeout");
+
+    run_until(&mut editor, |_| ticks.get() >= 3);
+    assert!(editor.cancel_timer(interval));
+    let count = ticks.get();
+    std::thread::sleep(Duration::from_millis(30));
+    editor.run_frame().unwrap();
+    assert_eq!(ticks.get(), count);
+}
@@ -0,0 +1,150 @@
+use niv_frontend::{
+    BlameLine, Editor, find_vcs, git_head_branch, parse_git_blame, parse_hg_annotate,
+    parse_pijul_credit, parse_svn_blame, svn_branch,
+};
+use niv_fs::testing::TempDir;
+use std::path::Path;
+use std::process::Command;
+
+fn blame(revision: &str, author: &str) -> BlameLine {
+    BlameLine {
+        revision: revision.to_string(),
+        author: author.to_string(),
+    }
+}
+
+#[test]
+fn test_nearest_repository_marker_wins() {
+    let dir = TempDir::new("vcs_markers");
+    std::fs::create_dir_all(dir.join(".hg")).unwrap();
+    std::fs::write(dir.join(".hg/branch"), "stable\n").unwrap();
+    std::fs::create_dir_all(dir.join("sub/.git")).unwrap();
+    std::fs::write(dir.join("sub/.git/HEAD"), "ref: refs/heads/topic\n").unwrap();
+
+    let hg = find_vcs(&dir.join("a.txt")).unwrap();
+    assert_eq!((hg.name(), hg.root()), ("hg", dir.path()));
+    assert_eq!(hg.branch().as_deref(), Some("stable"));
+
+    let git = find_vcs(&dir.join("sub/deep/b.txt")).unwrap();
+    assert_eq!((git.name(), git.root()), ("git", dir.join("sub").as_path()));
+    assert_eq!(git.branch().as_deref(), Some("topic"));
+
+    // A worktree's `.git` file points at the real directory
+    std::fs::create_dir_all(dir.join("tree")).unwrap();
+    std::fs::write(dir.join("tree/.git"), "gitdir: ../sub/.git\n").unwrap();
+    let worktree = find_vcs(&dir.join("tree/c.txt")).unwrap();
+    assert_eq!(worktree.branch().as_deref(), Some("topic"));
+
+    assert!(find_vcs(Path::new("/")).is_none());
+}
+
+#[test]
+fn test_branch_names() {
+    assert_eq!(
+        git_head_branch("ref: refs/heads/main\n").as_deref(),
+        Some("main")
+    );
+    assert_eq!(
+        git_head_branch("3f1c2a9e5b7d4c6a8e0f1b2c3d4e5f6a7b8c9d0e\n").as_deref(),
+        Some("3f1c2a9")
+    );
+    assert_eq!(svn_branch("^/trunk/src").as_deref(), Some("trunk"));
+    assert_eq!(
+        svn_branch("^/branches/release-1.2").as_deref(),
+        Some("release-1.2")
+    );
+    assert_eq!(svn_branch("^/tags/v1/docs").as_deref(), Some("v1"));
+    assert_eq!(svn_branch("^/project"), None);
+}
+
+#[test]
+fn test_blame_output_is_parsed() {
+    let git = "\
+3f1c2a9e5b7d4c6a8e0f1b2c3d4e5f6a7b8c9d0e 1 1 2
+author Ada Lovelace
+author-mail <ada@example.com>
+summary first
+filename a.txt
+\tone
+3f1c2a9e5b7d4c6a8e0f1b2c3d4e5f6a7b8c9d0e 2 2
+\ttwo
+0000000000000000000000000000000000000000 3 3 1
+author Not Committed Yet
+filename a.txt
+\tthree
+";
+    assert_eq!(
+        parse_git_blame(git),
+        [
+            blame("3f1c2a9e", "Ada Lovelace"),
+            blame("3f1c2a9e", "Ada Lovelace"),
+            blame("00000000", "Not Committed Yet"),
+        ]
+    );
+
+    let hg = "   alice 1a2b3c4d5e6f: one\nbob smith 6f5e4d3c2b1a: two: with colon\n";
+    assert_eq!(
+        parse_hg_annotate(hg),
+        [
+            blame("1a2b3c4d5e6f", "alice"),
+            blame("6f5e4d3c2b1a", "bob smith")
+        ]
+    );
+
+    let svn = "    12      carol one\n     7        dan   two\n";
+    assert_eq!(
+        parse_svn_blame(svn),
+        [blame("12", "carol"), blame("7", "dan")]
+    );
+
+    let pijul = "ABCDEFGHIJKL\n> one\n> two\n\nMNOPQRSTUVWX, YZ\n> three\n";
+    assert_eq!(
+        parse_pijul_credit(pijul),
+        [
+            blame("ABCDEFGH", ""),
+            blame("ABCDEFGH", ""),
+            blame("MNOPQRST", "")
+        ]
+    );
+}
+
+#[test]
+fn test_git_changes_show_in_gutter_and_status_line() {
+    let dir = TempDir::new("vcs_git");
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .current_dir(&dir)
+            .args(["-c", "user.name=Ada", "-c", "user.email=ada@example.com"])
+            .args(args)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    if !git(&["init", "-q", "-b", "main"]) {
+        // git isn't installed
+        return;
+    }
+    let path = dir.join("a.txt");
+    std::fs::write(&path, "one\ntwo\nthree\nfour\n").unwrap();
+    assert!(git(&["add", "a.txt"]));
+    assert!(git(&["commit", "-q", "-m", "first"]));
+
+    let mut editor = Editor::headless(60, 8);
+    let row = |editor: &Editor, y| editor.screen().unwrap().row_text(y);
+    assert!(editor.open_file_at(&path, None));
+    editor.feed_keys("i<Esc>").unwrap();
+    assert!(row(&editor, 6).trim_end().ends_with("git:main"));
+    assert_eq!(row(&editor, 0).trim_end(), "   1 one");
+
+    // Change the second line and add one after it; the signs follow once
+    // the edits pause
+    editor.feed_keys("jA!<Esc>o2b<Esc>").unwrap();
+    assert_eq!(row(&editor, 1).trim_end(), "   2 two!");
+    std::thread::sleep(std::time::Duration::from_millis(250));
+    editor.run_frame().unwrap();
+    assert_eq!(row(&editor, 1).trim_end(), "   2~two!");
+    assert_eq!(row(&editor, 2).trim_end(), "   3+2b");
+    assert_eq!(row(&editor, 3).trim_end(), "   4 three");
+
+    editor.feed_keys("gg:Blame<CR>").unwrap();
+    assert!(row(&editor, 6).contains(" Ada"));
+}
@@ -0,0 +1,43 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_wall_writes_all_modified_buffers_or_none() {
+    let dir = TempDir::new("wall");
+    let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+    std::fs::write(&a, "a").unwrap();
+    std::fs::write(&b, "b").unwrap();
+    let content = |path| niv_fs::load_file(path).unwrap().content;
+
+    let mut editor = Editor::headless(80, 12);
+    assert!(editor.open_file_at(&a, None));
+    editor.feed_keys("ix<Esc>").unwrap();
+    assert!(editor.open_file_at(&b, None));
+    editor.feed_keys("iy<Esc>:wall<CR>").unwrap();
+    assert_eq!(content(&a), "xa");
+    assert_eq!(content(&b), "yb");
+    assert!(editor.buffer_manager.iter().all(|buffer| !buffer.modified));
+
+    // A file in a missing directory can't be written, so neither is `a`
+    let missing = dir.join("missing").join("c.txt");
+    assert!(editor.open_file_at(&missing, None));
+    editor.feed_keys("iz<Esc>").unwrap();
+    assert!(editor.open_file_at(&a, None));
+    editor.feed_keys("iw<Esc>:wqa<CR>").unwrap();
+    assert!(editor.is_running());
+    assert_eq!(content(&a), "xa");
+    assert!(
+        editor
+            .screen()
+            .unwrap()
+            .row_text(10)
+            .contains("2 of 2 files not written")
+    );
+
+    editor.feed_keys(":messages<CR>").unwrap();
+    let screen = editor.screen().unwrap();
+    let rows: Vec<String> = (0..10).map(|row| screen.row_text(row)).collect();
+    drop(screen);
+    assert!(rows.iter().any(|row| row.contains("c.txt")));
+    assert!(rows.iter().any(|row| row.contains("a.txt\" not written")));
+}
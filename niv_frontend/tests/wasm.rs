@@ -0,0 +1,78 @@
+#![cfg(feature = "wasm")]
+
+use niv_config::{ExtensionConfig, TomlValue};
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+use std::collections::HashMap;
+
+/// A plugin whose `init` adds `:Greet` and maps `Q` to it. `greet` puts
+/// "hello " in front of the buffer.
+const GREETER: &str = r#"
+(module
+  (import "niv" "register_command" (func $command (param i32 i32 i32 i32) (result i32)))
+  (import "niv" "map_key" (func $map (param i32 i32 i32 i32) (result i32)))
+  (import "niv" "edit" (func $edit (param i32 i32 i32 i32) (result i32)))
+  (import "niv" "message" (func $message (param i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "Greet")
+  (data (i32.const 8) "greet")
+  (data (i32.const 16) "hello ")
+  (data (i32.const 24) "Q")
+  (data (i32.const 32) "can't edit")
+  (func (export "init")
+    (drop (call $command (i32.const 0) (i32.const 5) (i32.const 8) (i32.const 5)))
+    (drop (call $map (i32.const 24) (i32.const 1) (i32.const 0) (i32.const 5))))
+  (func (export "greet")
+    (if (i32.lt_s (call $edit (i32.const 0) (i32.const 0) (i32.const 16) (i32.const 6))
+                  (i32.const 0))
+      (then (drop (call $message (i32.const 32) (i32.const 10)))))))
+"#;
+
+fn greeter(dir: &TempDir, capabilities: &str) -> Editor {
+    let module = dir.join("greeter.wasm");
+    std::fs::write(&module, wat::parse_str(GREETER).unwrap()).unwrap();
+    let extension = ExtensionConfig {
+        name: "greeter".to_string(),
+        version: None,
+        enabled: true,
+        settings: HashMap::from([(
+            "capabilities".to_string(),
+            TomlValue::String(capabilities.to_string()),
+        )]),
+        path: Some(module.display().to_string()),
+        repository: None,
+    };
+    Editor::headless_with_config(40, 8, |config| {
+        config
+            .extensions
+            .extensions
+            .insert("greeter".to_string(), extension);
+    })
+}
+
+fn content(editor: &Editor) -> String {
+    editor.buffer_manager.current().unwrap().content.clone()
+}
+
+#[test]
+fn test_plugin_commands_and_keys_edit_the_buffer() {
+    let dir = TempDir::new("wasm_granted");
+    let mut editor = greeter(&dir, "commands, keys, edit");
+    editor.feed_keys("iworld<Esc>:Greet<CR>").unwrap();
+    assert_eq!(content(&editor), "hello world");
+    editor.feed_keys("Q").unwrap();
+    assert_eq!(content(&editor), "hello hello world");
+}
+
+#[test]
+fn test_plugins_only_get_the_capabilities_granted() {
+    let dir = TempDir::new("wasm_denied");
+    let mut editor = greeter(&dir, "commands");
+    editor.feed_keys("iworld<Esc>:Greet<CR>").unwrap();
+    assert_eq!(content(&editor), "world");
+    assert!(editor.screen().unwrap().row_text(6).contains("can't edit"));
+
+    // Without `keys` the mapping was never made
+    editor.feed_keys("Q").unwrap();
+    assert_eq!(content(&editor), "world");
+}
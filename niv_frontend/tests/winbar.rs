@@ -0,0 +1,37 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+fn row(editor: &Editor, y: u16) -> String {
+    editor.screen().unwrap().row_text(y).trim_end().to_string()
+}
+
+#[test]
+fn test_winbar_shows_the_path_as_breadcrumbs() {
+    let dir = TempDir::new("winbar");
+    std::fs::create_dir_all(dir.join("src/tui")).unwrap();
+    let path = dir.join("src/tui/main.rs");
+    std::fs::write(&path, "fn main() {}").unwrap();
+
+    let mut editor = Editor::headless_with_config(40, 8, |config| config.ui.winbar = true);
+    assert!(editor.open_file_at(&path, None));
+    editor
+        .feed_keys(&format!(":lcd {}<CR>", dir.path().display()))
+        .unwrap();
+    assert_eq!(row(&editor, 0), " src > tui > main.rs");
+    assert_eq!(row(&editor, 1), "   1 fn main() {}");
+
+    editor.feed_keys("ix<Esc>").unwrap();
+    assert_eq!(row(&editor, 0), " src > tui > main.rs [+]");
+
+    // Leading crumbs give way on narrow screens
+    editor.resize_screen(20, 8).unwrap();
+    assert_eq!(row(&editor, 0), " … > main.rs [+]");
+}
+
+#[test]
+fn test_no_winbar_by_default() {
+    let mut editor = Editor::headless(40, 8);
+    assert_eq!(row(&editor, 0), "   1");
+    editor.feed_keys("ihi<Esc>").unwrap();
+    assert_eq!(row(&editor, 0), "   1 hi");
+}
@@ -0,0 +1,44 @@
+use niv_frontend::Editor;
+use niv_fs::testing::TempDir;
+
+#[test]
+fn test_split_windows_scroll_independently_unless_bound() {
+    let dir = TempDir::new("window");
+    let path = dir.join("window");
+    let text: String = (1..=30).map(|n| format!("line {}\n", n)).collect();
+    std::fs::write(&path, text).unwrap();
+    let mut editor = Editor::headless(60, 8);
+    let row = |editor: &Editor, y| editor.screen().unwrap().row_text(y);
+    assert!(editor.open_file_at(&path, None));
+
+    editor.feed_keys(":vs<CR>").unwrap();
+    assert_eq!(
+        row(&editor, 0),
+        "   1 line 1                      │   1 line 1"
+    );
+
+    // Only the window with the cursor scrolls
+    editor.feed_keys("G").unwrap();
+    assert_eq!(
+        row(&editor, 5),
+        "  30 line 30                     │   6 line 6"
+    );
+
+    // The other window kept its cursor and scroll position
+    editor.feed_keys("<C-w>w").unwrap();
+    assert_eq!(
+        row(&editor, 0),
+        "   1 line 1                      │  25 line 25"
+    );
+    assert_eq!(editor.screen().unwrap().cursor(), (5, 0));
+
+    // Bound, both scroll by half a screen
+    editor.feed_keys(":setlocal scrollbind<CR><C-d>").unwrap();
+    assert_eq!(
+        row(&editor, 0),
+        "   4 line 4                      │  28 line 28"
+    );
+
+    editor.feed_keys(":vs<CR>").unwrap();
+    assert_eq!(row(&editor, 0), "   4 line 4");
+}
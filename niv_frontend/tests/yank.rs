@@ -0,0 +1,92 @@
+use niv_frontend::{Editor, KillRing, Session, Yank};
+use niv_fs::testing::TempDir;
+
+fn yank(text: &str, linewise: bool) -> Yank {
+    Yank {
+        text: text.to_string(),
+        linewise,
+    }
+}
+
+fn temp_file(dir: &TempDir, name: &str, text: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn test_kill_ring_is_bounded_and_newest_first() {
+    let mut ring = KillRing::new(3);
+    for text in ["a", "b", "c", "d"] {
+        ring.push(yank(text, false));
+    }
+    let texts: Vec<&str> = ring.iter().map(|yank| yank.text.as_str()).collect();
+    assert_eq!(texts, ["d", "c", "b"]);
+
+    // Taking the same text again moves it to the front
+    ring.push(yank("b", false));
+    assert_eq!(ring.len(), 3);
+    assert_eq!(ring.newest(), Some(&yank("b", false)));
+
+    ring.promote(2);
+    assert_eq!(ring.newest(), Some(&yank("c", false)));
+    assert_eq!(ring.get(1), Some(&yank("b", false)));
+
+    // An empty line can be pasted, empty text can't
+    ring.push(yank("", false));
+    ring.push(yank("", true));
+    assert_eq!(ring.newest(), Some(&yank("", true)));
+}
+
+#[test]
+fn test_yanks_are_kept_in_the_session() {
+    let session = Session {
+        yanks: vec![yank("one\\two\nthree", true), yank("x\r", false)],
+        ..Session::default()
+    };
+    let text = session.to_text();
+    assert!(text.contains("yank line one\\\\two\\nthree\n"));
+    assert_eq!(Session::parse(&text), Some(session));
+}
+
+#[test]
+fn test_visual_yank_delete_and_paste() {
+    let dir = TempDir::new("yank");
+    let path = temp_file(&dir, "paste", "one\ntwo\nthree\nfour\n");
+    let mut editor = Editor::headless(40, 8);
+    let row = |editor: &Editor, y| editor.screen().unwrap().row_text(y).trim_end().to_string();
+    assert!(editor.open_file_at(&path, None));
+
+    // Yank the first two lines and paste them below the last
+    editor.feed_keys("vjyGp").unwrap();
+    assert_eq!(row(&editor, 4), "   5 one");
+    assert_eq!(row(&editor, 5), "   6 two");
+    assert_eq!(editor.screen().unwrap().cursor(), (5, 4));
+
+    // Delete the third line and paste it above the first
+    editor.feed_keys("ggjjvdggP").unwrap();
+    assert_eq!(row(&editor, 0), "   1 three");
+    assert_eq!(row(&editor, 1), "   2 one");
+    assert_eq!(row(&editor, 3), "   4 four");
+}
+
+#[test]
+fn test_picker_pastes_an_older_yank() {
+    let dir = TempDir::new("yank");
+    let path = temp_file(&dir, "picker", "alpha\nbeta\n");
+    let mut editor = Editor::headless(40, 8);
+    let row = |editor: &Editor, y| editor.screen().unwrap().row_text(y).trim_end().to_string();
+    assert!(editor.open_file_at(&path, None));
+
+    editor.feed_keys("\"").unwrap();
+    assert_eq!(row(&editor, 6), "Nothing yanked yet");
+
+    editor.feed_keys("vyjvy:Yanks<CR>").unwrap();
+    assert_eq!(row(&editor, 0), " beta     1 line");
+    assert_eq!(row(&editor, 1), " alpha    1 line");
+
+    // The older yank is pasted and becomes the one `p` pastes
+    editor.feed_keys("<Down><CR>p").unwrap();
+    assert_eq!(row(&editor, 2), "   3 alpha");
+    assert_eq!(row(&editor, 3), "   4 alpha");
+}
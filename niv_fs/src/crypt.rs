@@ -0,0 +1,224 @@
+//! Encryption of swap and draft files at rest, so the unsaved text of a
+//! crash-recovery file can't be read by others sharing the machine.
+//!
+//! A session key comes from a [`KeyProvider`]: a passphrase stretched with
+//! PBKDF2-HMAC-SHA256, or a key kept elsewhere such as the OS keyring. Files
+//! are sealed with ChaCha20-Poly1305 under a random nonce from the OS, so a
+//! wrong key or a damaged file is found instead of giving garbage.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::{IoContext, IoOp};
+
+/// Environment variable holding the passphrase swap files are encrypted
+/// with, when encrypting them is enabled
+pub const SWAP_PASSPHRASE_ENV: &str = "NIV_SWAP_PASSPHRASE";
+
+/// PBKDF2 rounds stretching a passphrase into a key
+pub const PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// First bytes of an encrypted file
+const MAGIC: &[u8; 8] = b"niv-enc1";
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+
+/// Gives the key swap and draft files are encrypted with in a session
+pub trait KeyProvider {
+    /// The key for the files kept with `salt`, random bytes stored next to
+    /// them. A provider deriving the key from a secret mixes the salt in;
+    /// one holding a key of its own, as a keyring does, may ignore it.
+    fn session_key(&self, salt: &[u8]) -> io::Result<SessionKey>;
+}
+
+/// A passphrase the user gave, stretched into a key with PBKDF2
+pub struct PassphraseKey {
+    passphrase: String,
+    iterations: u32,
+}
+
+impl PassphraseKey {
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+            iterations: PBKDF2_ITERATIONS,
+        }
+    }
+
+    /// Use `iterations` rounds of PBKDF2 instead of [`PBKDF2_ITERATIONS`]
+    pub fn with_iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations.max(1);
+        self
+    }
+}
+
+impl KeyProvider for PassphraseKey {
+    fn session_key(&self, salt: &[u8]) -> io::Result<SessionKey> {
+        let mut secret = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(
+            self.passphrase.as_bytes(),
+            salt,
+            self.iterations,
+            &mut secret,
+        );
+        let key = SessionKey::from_secret(&secret);
+        secret.fill(0);
+        Ok(key)
+    }
+}
+
+impl fmt::Debug for PassphraseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassphraseKey")
+            .field("iterations", &self.iterations)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The key files are sealed with in a session. Its bytes are never shown
+/// and are wiped when it is dropped.
+#[derive(Clone)]
+pub struct SessionKey {
+    cipher: [u8; 32],
+}
+
+impl SessionKey {
+    /// Key made from a secret of any length, such as a key read from a
+    /// keyring
+    pub fn from_secret(secret: &[u8]) -> Self {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC takes keys of any length");
+        mac.update(b"niv swap cipher");
+        Self {
+            cipher: mac.finalize().into_bytes().into(),
+        }
+    }
+}
+
+impl fmt::Debug for SessionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SessionKey(..)")
+    }
+}
+
+impl Drop for SessionKey {
+    fn drop(&mut self) {
+        self.cipher.fill(0);
+    }
+}
+
+/// The session key from `provider` for the files whose salt is kept in
+/// `salt_file`, which is made on first use
+pub(crate) fn load_session_key(
+    salt_file: &Path,
+    provider: &dyn KeyProvider,
+) -> io::Result<SessionKey> {
+    let salt = match fs::read(salt_file) {
+        Ok(salt) if salt.len() == SALT_LEN => salt,
+        Ok(_) => {
+            let damaged = io::Error::new(io::ErrorKind::InvalidData, "damaged salt file");
+            return Err(damaged).io_context(IoOp::Read, salt_file);
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let salt = random_bytes::<SALT_LEN>()
+                .io_context(IoOp::Create, salt_file)?
+                .to_vec();
+            fs::write(salt_file, &salt).io_context(IoOp::Write, salt_file)?;
+            salt
+        }
+        Err(e) => return Err(e).io_context(IoOp::Read, salt_file),
+    };
+    provider.session_key(&salt)
+}
+
+/// `plain` encrypted with `key`: the magic bytes, the nonce, and the cipher
+/// text with the tag authenticating it and the magic bytes
+pub(crate) fn seal(key: &SessionKey, plain: &[u8]) -> io::Result<Vec<u8>> {
+    let nonce = random_bytes::<NONCE_LEN>()?;
+    let payload = Payload {
+        msg: plain,
+        aad: MAGIC,
+    };
+    // Fails only past the 256 GiB one nonce can encrypt
+    let cipher_text = ChaCha20Poly1305::new(Key::from_slice(&key.cipher))
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too long to encrypt"))?;
+    let mut sealed = Vec::with_capacity(MAGIC.len() + NONCE_LEN + cipher_text.len());
+    sealed.extend_from_slice(MAGIC);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&cipher_text);
+    Ok(sealed)
+}
+
+/// Whether `data` was written by [`seal`]
+pub(crate) fn is_sealed(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// The text [`seal`] encrypted into `data`, or `data` itself when it isn't
+/// encrypted, as files written before encryption was enabled are not
+pub(crate) fn open(key: Option<&SessionKey>, data: Vec<u8>) -> io::Result<Vec<u8>> {
+    if !is_sealed(&data) {
+        return Ok(data);
+    }
+    let invalid = |reason: &str| io::Error::new(io::ErrorKind::InvalidData, reason.to_string());
+    let Some(key) = key else {
+        return Err(invalid("encrypted, and no key was given"));
+    };
+    if data.len() < MAGIC.len() + NONCE_LEN + TAG_LEN {
+        return Err(invalid("encrypted file cut short"));
+    }
+    let (nonce, cipher_text) = data[MAGIC.len()..].split_at(NONCE_LEN);
+    let payload = Payload {
+        msg: cipher_text,
+        aad: MAGIC,
+    };
+    ChaCha20Poly1305::new(Key::from_slice(&key.cipher))
+        .decrypt(Nonce::from_slice(nonce), payload)
+        .map_err(|_| invalid("encrypted with another key, or damaged"))
+}
+
+/// Random bytes from the generator of the OS
+fn random_bytes<const N: usize>() -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_text_opens_only_with_its_key() {
+        let key = PassphraseKey::new("secret")
+            .with_iterations(2)
+            .session_key(b"salt")
+            .unwrap();
+        let sealed = seal(&key, b"unsaved text").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.windows(5).any(|window| window == b"text"));
+        assert_ne!(sealed, seal(&key, b"unsaved text").unwrap());
+        assert_eq!(open(Some(&key), sealed.clone()).unwrap(), b"unsaved text");
+
+        let other = PassphraseKey::new("guess")
+            .with_iterations(2)
+            .session_key(b"salt")
+            .unwrap();
+        assert!(open(Some(&other), sealed.clone()).is_err());
+        assert!(open(None, sealed.clone()).is_err());
+        let mut damaged = sealed;
+        damaged[MAGIC.len() + NONCE_LEN] ^= 1;
+        assert!(open(Some(&key), damaged).is_err());
+        // Files written before encryption was enabled read as they are
+        assert_eq!(open(Some(&key), b"plain".to_vec()).unwrap(), b"plain");
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 
 pub mod latin;
+pub mod single_byte;
 pub mod utf16;
 pub mod utf8;
 pub mod windows;
@@ -77,7 +78,7 @@ impl Default for DetectionConfig {
 pub fn detect_encoding_heuristic(
     bytes: &[u8],
     config: DetectionConfig,
-) -> Result<Encoding, crate::EncodingError> {
+) -> crate::FsResult<Encoding> {
     let sample = if bytes.len() > config.sample_size {
         &bytes[..config.sample_size]
     } else {
@@ -98,8 +99,17 @@ pub fn detect_encoding_heuristic(
     }
     let null_ratio = null_count as f64 / sample.len() as f64;
     let control_ratio = control_count as f64 / sample.len() as f64;
-    if null_ratio > config.max_null_ratio || control_ratio > config.max_control_ratio {
-        return Err(crate::EncodingError::BinaryFile);
+    if null_ratio > config.max_null_ratio {
+        return Err(crate::FsError::binary(format!(
+            "{:.0}% null bytes",
+            null_ratio * 100.0
+        )));
+    }
+    if control_ratio > config.max_control_ratio {
+        return Err(crate::FsError::binary(format!(
+            "{:.0}% control characters",
+            control_ratio * 100.0
+        )));
     }
 
     if sample.len() >= 32 {
@@ -0,0 +1,155 @@
+//! Byte and character mappings of the Latin family: Latin-1, Windows-1252
+//! and Latin-9. All three agree with Latin-1, where byte N is U+00NN,
+//! except where their tables below say otherwise. Loading and saving both
+//! go through these tables so that every byte a file was read with is
+//! written back as it was.
+
+use super::Encoding;
+
+/// Characters of Windows-1252 bytes 0x80 to 0x9F. The five bytes Windows
+/// leaves unassigned (0x81, 0x8D, 0x8F, 0x90 and 0x9D) stand for the C1
+/// controls of the same value, as in Latin-1.
+const WINDOWS1252_C1: [char; 32] = [
+    '\u{20AC}', // 0x80 €
+    '\u{0081}', // 0x81 unassigned
+    '\u{201A}', // 0x82 ‚
+    '\u{0192}', // 0x83 ƒ
+    '\u{201E}', // 0x84 „
+    '\u{2026}', // 0x85 …
+    '\u{2020}', // 0x86 †
+    '\u{2021}', // 0x87 ‡
+    '\u{02C6}', // 0x88 ˆ
+    '\u{2030}', // 0x89 ‰
+    '\u{0160}', // 0x8A Š
+    '\u{2039}', // 0x8B ‹
+    '\u{0152}', // 0x8C Œ
+    '\u{008D}', // 0x8D unassigned
+    '\u{017D}', // 0x8E Ž
+    '\u{008F}', // 0x8F unassigned
+    '\u{0090}', // 0x90 unassigned
+    '\u{2018}', // 0x91 ‘
+    '\u{2019}', // 0x92 ’
+    '\u{201C}', // 0x93 “
+    '\u{201D}', // 0x94 ”
+    '\u{2022}', // 0x95 •
+    '\u{2013}', // 0x96 –
+    '\u{2014}', // 0x97 —
+    '\u{02DC}', // 0x98 ˜
+    '\u{2122}', // 0x99 ™
+    '\u{0161}', // 0x9A š
+    '\u{203A}', // 0x9B ›
+    '\u{0153}', // 0x9C œ
+    '\u{009D}', // 0x9D unassigned
+    '\u{017E}', // 0x9E ž
+    '\u{0178}', // 0x9F Ÿ
+];
+
+/// Latin-9 bytes whose characters differ from Latin-1's
+const LATIN9_CHANGES: [(u8, char); 8] = [
+    (0xA4, '\u{20AC}'), // € for ¤
+    (0xA6, '\u{0160}'), // Š for ¦
+    (0xA8, '\u{0161}'), // š for ¨
+    (0xB4, '\u{017D}'), // Ž for ´
+    (0xB8, '\u{017E}'), // ž for ¸
+    (0xBC, '\u{0152}'), // Œ for ¼
+    (0xBD, '\u{0153}'), // œ for ½
+    (0xBE, '\u{0178}'), // Ÿ for ¾
+];
+
+/// Character of `byte` in `encoding`, None for encodings that are not
+/// single-byte. Every byte has one.
+pub fn decode_byte(byte: u8, encoding: Encoding) -> Option<char> {
+    match encoding {
+        Encoding::Latin1 => Some(char::from(byte)),
+        Encoding::Windows1252 => Some(match byte {
+            0x80..=0x9F => WINDOWS1252_C1[usize::from(byte - 0x80)],
+            _ => char::from(byte),
+        }),
+        Encoding::Latin9 => Some(
+            LATIN9_CHANGES
+                .iter()
+                .find(|(changed, _)| *changed == byte)
+                .map_or(char::from(byte), |&(_, ch)| ch),
+        ),
+        _ => None,
+    }
+}
+
+/// Byte of `ch` in `encoding`, None when it has none or is not single-byte
+pub fn encode_char(ch: char, encoding: Encoding) -> Option<u8> {
+    match encoding {
+        Encoding::Latin1 => u8::try_from(ch).ok(),
+        Encoding::Windows1252 => match WINDOWS1252_C1.iter().position(|&c1| c1 == ch) {
+            Some(index) => Some(0x80 + index as u8),
+            // U+0080..=U+009F not in the table have no byte
+            None => u8::try_from(ch)
+                .ok()
+                .filter(|byte| !(0x80..=0x9F).contains(byte)),
+        },
+        Encoding::Latin9 => match LATIN9_CHANGES.iter().find(|&&(_, changed)| changed == ch) {
+            Some(&(byte, _)) => Some(byte),
+            // The Latin-1 characters Latin-9 replaced have no byte
+            None => u8::try_from(ch)
+                .ok()
+                .filter(|byte| LATIN9_CHANGES.iter().all(|(changed, _)| changed != byte)),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LATIN: [Encoding; 3] = [Encoding::Latin1, Encoding::Windows1252, Encoding::Latin9];
+
+    #[test]
+    fn test_every_byte_round_trips() {
+        for encoding in LATIN {
+            for byte in 0..=u8::MAX {
+                let ch = decode_byte(byte, encoding).unwrap();
+                assert_eq!(
+                    encode_char(ch, encoding),
+                    Some(byte),
+                    "{:#04x} ({:?}) in {}",
+                    byte,
+                    ch,
+                    encoding
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_chars_encode_to_the_byte_they_decode_from() {
+        // Every char with a byte in any of the three, and the ones around them
+        for encoding in LATIN {
+            let mut encodable = 0;
+            for ch in '\0'..='\u{2200}' {
+                if let Some(byte) = encode_char(ch, encoding) {
+                    assert_eq!(decode_byte(byte, encoding), Some(ch), "{:?}", ch);
+                    encodable += 1;
+                }
+            }
+            // Each byte for exactly one char
+            assert_eq!(encodable, 256, "{}", encoding);
+        }
+    }
+
+    #[test]
+    fn test_windows1252_quotes_and_ascii() {
+        let encoding = Encoding::Windows1252;
+        assert_eq!(encode_char('"', encoding), Some(b'"'));
+        assert_eq!(encode_char('\u{201C}', encoding), Some(0x93));
+        assert_eq!(encode_char('\u{201D}', encoding), Some(0x94));
+        assert_eq!(decode_byte(0x93, encoding), Some('\u{201C}'));
+        // C1 controls that Windows-1252 put characters in place of
+        assert_eq!(encode_char('\u{0080}', encoding), None);
+        assert_eq!(encode_char('\u{0081}', encoding), Some(0x81));
+
+        assert_eq!(encode_char('\u{00A4}', Encoding::Latin9), None);
+        assert_eq!(encode_char('\u{00A4}', Encoding::Latin1), Some(0xA4));
+        assert_eq!(encode_char('€', Encoding::Latin1), None);
+        assert_eq!(decode_byte(0x41, Encoding::Utf8), None);
+    }
+}
@@ -0,0 +1,340 @@
+use crate::encoding::Encoding;
+use crate::file::ConvertError;
+use crate::swap::SwapError;
+use crate::watcher::WatcherError;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What was being done to a file when an I/O error happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoOp {
+    Open,
+    Read,
+    Write,
+    Create,
+    CreateDir,
+    Rename,
+    Copy,
+    Remove,
+    Metadata,
+    SetPermissions,
+    SetTimes,
+    Seek,
+    Sync,
+    ReadDir,
+}
+
+impl fmt::Display for IoOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self {
+            IoOp::Open => "opening",
+            IoOp::Read => "reading",
+            IoOp::Write => "writing",
+            IoOp::Create => "creating",
+            IoOp::CreateDir => "creating directory",
+            IoOp::Rename => "renaming",
+            IoOp::Copy => "copying",
+            IoOp::Remove => "removing",
+            IoOp::Metadata => "reading metadata of",
+            IoOp::SetPermissions => "setting permissions of",
+            IoOp::SetTimes => "setting times of",
+            IoOp::Seek => "seeking in",
+            IoOp::Sync => "syncing",
+            IoOp::ReadDir => "listing",
+        };
+        f.write_str(verb)
+    }
+}
+
+/// Errors of the filesystem operations, with the path they happened on
+/// where known, so that callers can tell users what went wrong and where
+#[derive(Debug)]
+pub enum FsError {
+    /// Text that cannot be decoded from, or written in, `encoding`
+    Encoding {
+        path: Option<PathBuf>,
+        encoding: Encoding,
+        reason: String,
+    },
+    /// I/O error while doing `op` to the file at `path`. Errors converted
+    /// from a bare `io::Error` have neither, and `op` is `Read`.
+    Io {
+        op: IoOp,
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// Watching a file for external changes failed
+    Watch(WatcherError),
+    /// Reading or writing a swap file failed
+    Swap(SwapError),
+    /// The identity of a file (its metadata and content hash) could not be read
+    Identity { path: PathBuf, source: io::Error },
+    /// File is too large to process
+    TooLarge { path: Option<PathBuf>, size: u64 },
+    /// File does not look like text
+    Binary {
+        path: Option<PathBuf>,
+        reason: String,
+    },
+}
+
+impl FsError {
+    /// I/O error doing `op` to `path`. An error that already says what it
+    /// was doing to which file, as returned by the `io::Result` functions of
+    /// this crate, is kept as it is.
+    pub fn io(op: IoOp, path: &Path, source: io::Error) -> Self {
+        if has_context(&source) {
+            return FsError::from(source);
+        }
+        FsError::Io {
+            op,
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    /// Failure to read the identity of `path`
+    pub fn identity(path: &Path, source: io::Error) -> Self {
+        FsError::Identity {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    /// Text that cannot be decoded from, or written in, `encoding`, for a
+    /// path to be given by [`FsError::with_path`]
+    pub fn encoding(encoding: Encoding, reason: impl Into<String>) -> Self {
+        FsError::Encoding {
+            path: None,
+            encoding,
+            reason: reason.into(),
+        }
+    }
+
+    /// Content that is not text, for a path to be given by
+    /// [`FsError::with_path`]
+    pub fn binary(reason: impl Into<String>) -> Self {
+        FsError::Binary {
+            path: None,
+            reason: reason.into(),
+        }
+    }
+
+    /// Attach `path` to an error that has none yet
+    pub fn with_path(mut self, path: &Path) -> Self {
+        match &mut self {
+            FsError::Encoding { path: slot, .. }
+            | FsError::Io { path: slot, .. }
+            | FsError::TooLarge { path: slot, .. }
+            | FsError::Binary { path: slot, .. } => {
+                if slot.is_none() {
+                    *slot = Some(path.to_path_buf());
+                }
+            }
+            FsError::Watch(_) | FsError::Swap(_) | FsError::Identity { .. } => {}
+        }
+        self
+    }
+
+    /// Path the error happened on, if known
+    pub fn path(&self) -> Option<&Path> {
+        match self {
+            FsError::Encoding { path, .. }
+            | FsError::Io { path, .. }
+            | FsError::TooLarge { path, .. }
+            | FsError::Binary { path, .. } => path.as_deref(),
+            FsError::Identity { path, .. } => Some(path),
+            FsError::Watch(_) | FsError::Swap(_) => None,
+        }
+    }
+
+    /// Kind of the underlying I/O error, if this is one
+    pub fn io_kind(&self) -> Option<io::ErrorKind> {
+        match self {
+            FsError::Io { source, .. } | FsError::Identity { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // I/O errors read "Permission denied writing /etc/hosts"
+        match self {
+            FsError::Io {
+                op,
+                path: Some(path),
+                source,
+            } => return write!(f, "{} {} {}", message(source), op, path.display()),
+            FsError::Io { source, .. } => return write!(f, "I/O error: {}", message(source)),
+            FsError::Identity { source, .. } if has_context(source) => {
+                return write!(f, "{}", source);
+            }
+            FsError::Identity { path, source } => {
+                return write!(
+                    f,
+                    "{} checking {} for changes",
+                    message(source),
+                    path.display()
+                );
+            }
+            _ => {}
+        }
+        if let Some(path) = self.path() {
+            write!(f, "{}: ", path.display())?;
+        }
+        match self {
+            FsError::Encoding {
+                encoding, reason, ..
+            } => write!(f, "{} in {}", reason, encoding),
+            FsError::Watch(err) => write!(f, "{}", err),
+            FsError::Swap(err) => write!(f, "{}", err),
+            FsError::Io { .. } | FsError::Identity { .. } => Ok(()),
+            FsError::TooLarge { size, .. } => {
+                write!(f, "file is too large to process ({} bytes)", size)
+            }
+            FsError::Binary { reason, .. } => write!(f, "file appears to be binary ({})", reason),
+        }
+    }
+}
+
+impl std::error::Error for FsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FsError::Io { source, .. } | FsError::Identity { source, .. } => Some(source),
+            FsError::Watch(err) => Some(err),
+            FsError::Swap(err) => Some(err),
+            FsError::Encoding { .. } | FsError::TooLarge { .. } | FsError::Binary { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for FsError {
+    /// Takes back the path and operation of an error from [`IoContext`]
+    fn from(err: io::Error) -> Self {
+        match err.downcast::<FsError>() {
+            Ok(err) => err,
+            Err(err) => FsError::Io {
+                op: IoOp::Read,
+                path: None,
+                source: err,
+            },
+        }
+    }
+}
+
+/// Say what was being done to which file in the errors of `io::Result`s,
+/// keeping their `ErrorKind` for callers that match on it
+pub(crate) trait IoContext<T> {
+    fn io_context(self, op: IoOp, path: &Path) -> io::Result<T>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn io_context(self, op: IoOp, path: &Path) -> io::Result<T> {
+        self.map_err(|err| {
+            if has_context(&err) {
+                return err;
+            }
+            io::Error::new(err.kind(), FsError::io(op, path, err))
+        })
+    }
+}
+
+/// Whether `err` came from [`IoContext`], so already names its file
+fn has_context(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<FsError>())
+}
+
+/// Text of an I/O error without the OS error number
+fn message(err: &io::Error) -> String {
+    let text = err.to_string();
+    match text.rfind(" (os error ") {
+        Some(at) if text.ends_with(')') => text[..at].to_string(),
+        _ => text,
+    }
+}
+
+impl From<WatcherError> for FsError {
+    fn from(err: WatcherError) -> Self {
+        FsError::Watch(err)
+    }
+}
+
+impl From<SwapError> for FsError {
+    fn from(err: SwapError) -> Self {
+        FsError::Swap(err)
+    }
+}
+
+impl From<ConvertError> for FsError {
+    fn from(err: ConvertError) -> Self {
+        match err {
+            ConvertError::Decode { encoding, reason }
+            | ConvertError::Encode { encoding, reason } => FsError::encoding(encoding, reason),
+        }
+    }
+}
+
+/// Result type for filesystem operations
+pub type FsResult<T> = Result<T, FsError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_names_the_operation_and_path() {
+        let err = FsError::io(
+            IoOp::Write,
+            Path::new("/etc/hosts"),
+            io::Error::from_raw_os_error(13),
+        );
+        assert_eq!(err.to_string(), "Permission denied writing /etc/hosts");
+        assert_eq!(err.io_kind(), Some(io::ErrorKind::PermissionDenied));
+
+        let err = FsError::from(io::Error::other("disk full"));
+        assert_eq!(err.to_string(), "I/O error: disk full");
+    }
+
+    #[test]
+    fn test_io_context_survives_conversion() {
+        let path = Path::new("/tmp/niv_missing/notes.txt");
+        let result: io::Result<()> = Err(io::Error::from(io::ErrorKind::NotFound));
+        let err = result.io_context(IoOp::Open, path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            err.to_string(),
+            "entity not found opening /tmp/niv_missing/notes.txt"
+        );
+
+        // Outer context doesn't replace what the inner call already said
+        let err = Err::<(), _>(err).io_context(IoOp::Read, Path::new("other"));
+        let err = FsError::io(IoOp::Metadata, Path::new("other"), err.unwrap_err());
+        assert_eq!(err.path(), Some(path));
+        assert!(matches!(err, FsError::Io { op: IoOp::Open, .. }));
+    }
+
+    #[test]
+    fn test_with_path_keeps_an_existing_path() {
+        let err = FsError::encoding(Encoding::Latin1, "'€' cannot be written")
+            .with_path(Path::new("a.txt"))
+            .with_path(Path::new("b.txt"));
+        assert_eq!(err.path(), Some(Path::new("a.txt")));
+        assert_eq!(err.to_string(), "a.txt: '€' cannot be written in Latin1");
+    }
+
+    #[test]
+    fn test_source_is_the_underlying_error() {
+        use std::error::Error;
+
+        let err = FsError::identity(Path::new("a.txt"), io::Error::other("stale handle"));
+        assert_eq!(err.source().unwrap().to_string(), "stale handle");
+        assert_eq!(err.to_string(), "stale handle checking a.txt for changes");
+        assert!(
+            FsError::binary("no text encoding detected")
+                .source()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Converting text between encodings, with the transcoders files are
+//! loaded and saved with.
+
+use std::fmt;
+
+use super::load::decode;
+use super::save::{UnencodablePolicy, transcode_to_encoding};
+use crate::encoding::Encoding;
+use crate::error::FsError;
+
+/// Text converted by [`convert`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conversion {
+    /// The text in the target encoding
+    pub bytes: Vec<u8>,
+    /// Number of characters converted
+    pub chars: usize,
+    /// Number of characters the target encoding couldn't hold, written as
+    /// the policy says
+    pub lossy: usize,
+}
+
+/// Why a conversion failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConvertError {
+    /// The bytes are not text in the source encoding
+    Decode { encoding: Encoding, reason: String },
+    /// The text cannot be written in the target encoding
+    Encode { encoding: Encoding, reason: String },
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertError::Decode { encoding, reason }
+            | ConvertError::Encode { encoding, reason } => {
+                write!(f, "{} in {}", reason, encoding)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+/// Convert `bytes` from one encoding to another, writing `?` for the
+/// characters `to` has no bytes for and counting them in
+/// [`Conversion::lossy`]. A byte order mark is converted like any other
+/// character, and line endings are left as they are.
+pub fn convert(bytes: &[u8], from: Encoding, to: Encoding) -> Result<Conversion, ConvertError> {
+    convert_with_policy(bytes, from, to, UnencodablePolicy::Substitute)
+}
+
+/// [`convert`] with the characters `to` can't hold handled by `policy`
+pub fn convert_with_policy(
+    bytes: &[u8],
+    from: Encoding,
+    to: Encoding,
+    policy: UnencodablePolicy,
+) -> Result<Conversion, ConvertError> {
+    let text = decode(bytes, from).map_err(|err| ConvertError::Decode {
+        encoding: from,
+        reason: reason(err),
+    })?;
+    let mut lossy = Vec::new();
+    let converted =
+        transcode_to_encoding(text.as_bytes(), to, policy, &mut lossy).map_err(|err| {
+            ConvertError::Encode {
+                encoding: to,
+                reason: reason(err),
+            }
+        })?;
+    Ok(Conversion {
+        bytes: converted,
+        chars: text.chars().count(),
+        lossy: lossy.len(),
+    })
+}
+
+/// What went wrong, without the encoding the error is about
+fn reason(err: FsError) -> String {
+    match err {
+        FsError::Encoding { reason, .. } | FsError::Binary { reason, .. } => reason,
+        err => err.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_between_encodings() {
+        let text = "naïve café – 5€";
+        let utf16 = convert(text.as_bytes(), Encoding::Utf8, Encoding::Utf16Be).unwrap();
+        assert_eq!(utf16.chars, text.chars().count());
+        assert_eq!(utf16.lossy, 0);
+        assert_eq!(&utf16.bytes[..4], &[0, b'n', 0, b'a']);
+
+        let cp1252 = convert(&utf16.bytes, Encoding::Utf16Be, Encoding::Windows1252).unwrap();
+        assert_eq!(cp1252.bytes, b"na\xefve caf\xe9 \x96 5\x80");
+        let back = convert(&cp1252.bytes, Encoding::Windows1252, Encoding::Utf8).unwrap();
+        assert_eq!(back.bytes, text.as_bytes());
+
+        // Latin-1 has neither the dash nor the euro sign
+        let latin1 = convert(text.as_bytes(), Encoding::Utf8, Encoding::Latin1).unwrap();
+        assert_eq!(latin1.bytes, b"na\xefve caf\xe9 ? 5?");
+        assert_eq!(latin1.lossy, 2);
+        let latin1 = convert_with_policy(
+            text.as_bytes(),
+            Encoding::Utf8,
+            Encoding::Latin1,
+            UnencodablePolicy::CharRef,
+        )
+        .unwrap();
+        assert!(latin1.bytes.ends_with(b"&#8211; 5&#8364;"));
+    }
+
+    #[test]
+    fn test_convert_errors() {
+        let err = convert(b"ok \xff", Encoding::Utf8, Encoding::Latin1).unwrap_err();
+        assert_eq!(
+            err,
+            ConvertError::Decode {
+                encoding: Encoding::Utf8,
+                reason: "invalid byte sequence at byte 3".to_string(),
+            }
+        );
+
+        let err = convert_with_policy(
+            "5₤".as_bytes(),
+            Encoding::Utf8,
+            Encoding::Latin9,
+            UnencodablePolicy::Fail,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'₤' (U+20A4) at line 1, column 2 cannot be written in Latin9"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Directory listings for completion and file pickers.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{IoContext, IoOp};
+
+/// One entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntryInfo {
+    /// File name without the directory
+    pub name: String,
+    /// Full path of the entry
+    pub path: PathBuf,
+    /// Whether the entry is a directory (symlinks are followed)
+    pub is_dir: bool,
+    /// Whether the entry is hidden, as [`is_hidden`] tells
+    pub hidden: bool,
+}
+
+/// List the entries of `dir` sorted by name.
+///
+/// Entries that vanish while listing, or whose names are not valid UTF-8, are skipped.
+pub fn list_dir<P: AsRef<Path>>(dir: P) -> io::Result<Vec<DirEntryInfo>> {
+    let dir = dir.as_ref();
+    let mut entries: Vec<DirEntryInfo> = fs::read_dir(dir)
+        .io_context(IoOp::ReadDir, dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            let path = entry.path();
+            let hidden = name.starts_with('.')
+                || entry
+                    .metadata()
+                    .is_ok_and(|metadata| has_hidden_attribute(&metadata));
+            Some(DirEntryInfo {
+                name,
+                is_dir: path.is_dir(),
+                hidden,
+                path,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Check whether `path` is hidden: its name starts with `.`, or on Windows
+/// it has the hidden attribute. `.` and `..` are not hidden.
+pub fn is_hidden<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let dotfile = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    dotfile
+        || (cfg!(windows)
+            && fs::symlink_metadata(path).is_ok_and(|metadata| has_hidden_attribute(&metadata)))
+}
+
+/// Check whether a file has the Windows hidden attribute; files elsewhere
+/// never do
+pub fn has_hidden_attribute(metadata: &fs::Metadata) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        // FILE_ATTRIBUTE_HIDDEN
+        metadata.file_attributes() & 0x2 != 0
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = metadata;
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_list_dir() {
+        let dir = TempDir::new("list_dir");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.txt"), "").unwrap();
+        fs::write(dir.join("a.txt"), "").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+
+        let entries = list_dir(&dir).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, [".hidden", "a.txt", "b.txt", "sub"]);
+        assert!(entries[3].is_dir);
+        assert!(!entries[1].is_dir);
+        assert!(entries[0].hidden);
+        assert!(!entries[1].hidden);
+        assert!(is_hidden(dir.join(".hidden")));
+        assert!(!is_hidden(dir.join("a.txt")));
+        assert!(list_dir(dir.join("missing")).is_err());
+    }
+}
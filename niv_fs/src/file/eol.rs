@@ -27,46 +27,105 @@ impl fmt::Display for EolType {
     }
 }
 
-/// Detect the predominant end-of-line type in the given bytes.
-pub fn detect_eol(bytes: &[u8]) -> EolType {
-    let mut lf_count = 0u64;
-    let mut crlf_count = 0u64;
-    let mut cr_count = 0u64;
+/// Line breaks and line lengths of a text read in chunks, so that a file
+/// can be scanned as it is read. A CRLF split between two chunks is one
+/// break.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LineScan {
+    lf_count: u64,
+    crlf_count: u64,
+    cr_count: u64,
+    /// Whether the last chunk ended in a CR that a LF may still follow
+    pending_cr: bool,
+    /// Bytes since the last CR or LF
+    line_len: usize,
+    longest_line: usize,
+}
 
-    let mut i = 0;
-    while i < bytes.len() {
-        match bytes[i] {
-            b'\r' => {
-                if i + 1 < bytes.len() && bytes[i + 1] == b'\n' {
-                    crlf_count += 1;
-                    i += 2;
-                } else {
-                    cr_count += 1;
-                    i += 1;
+impl LineScan {
+    pub(crate) fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if self.pending_cr {
+                self.pending_cr = false;
+                if byte == b'\n' {
+                    self.crlf_count += 1;
+                    continue;
                 }
+                self.cr_count += 1;
             }
-            b'\n' => {
-                lf_count += 1;
-                i += 1;
+            match byte {
+                b'\r' | b'\n' => {
+                    self.longest_line = self.longest_line.max(self.line_len);
+                    self.line_len = 0;
+                    if byte == b'\r' {
+                        self.pending_cr = true;
+                    } else {
+                        self.lf_count += 1;
+                    }
+                }
+                _ => self.line_len += 1,
             }
-            _ => i += 1,
         }
     }
 
-    // Determine the predominant type
-    let total = lf_count + crlf_count + cr_count;
-    if total == 0 {
-        return EolType::Lf; // Default for empty or no line endings
+    /// Length in bytes of the longest line so far, line breaks left out
+    pub(crate) fn longest_line(&self) -> usize {
+        self.longest_line.max(self.line_len)
     }
 
-    // Find the maximum
-    if crlf_count >= lf_count && crlf_count >= cr_count {
-        EolType::Crlf
-    } else if lf_count >= cr_count {
-        EolType::Lf
-    } else {
-        EolType::Cr
+    /// Number of lines, as `str::lines` counts them: a last line without a
+    /// line break counts, an empty one after the last break doesn't
+    pub(crate) fn line_count(&self) -> usize {
+        let breaks = self.lf_count + self.crlf_count + self.cr_count + self.pending_cr as u64;
+        breaks as usize + usize::from(self.line_len > 0)
     }
+
+    /// The predominant end-of-line type so far
+    pub(crate) fn eol_type(&self) -> EolType {
+        let lf_count = self.lf_count;
+        let crlf_count = self.crlf_count;
+        let cr_count = self.cr_count + self.pending_cr as u64;
+
+        // Determine the predominant type
+        let total = lf_count + crlf_count + cr_count;
+        if total == 0 {
+            return EolType::Lf; // Default for empty or no line endings
+        }
+
+        // Find the maximum
+        if crlf_count >= lf_count && crlf_count >= cr_count {
+            EolType::Crlf
+        } else if lf_count >= cr_count {
+            EolType::Lf
+        } else {
+            EolType::Cr
+        }
+    }
+}
+
+/// Detect the predominant end-of-line type in the given bytes.
+pub fn detect_eol(bytes: &[u8]) -> EolType {
+    let mut scan = LineScan::default();
+    scan.feed(bytes);
+    scan.eol_type()
+}
+
+/// Normalize the end-of-lines of `text`, found to be `eol`, to LF; like
+/// [`normalize_eol`], text with mixed line endings is left as it is
+pub(crate) fn normalize_eol_text(text: String, eol: EolType) -> String {
+    if matches!(eol, EolType::Lf | EolType::Mixed) {
+        return text;
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(at) = rest.find('\r') {
+        result.push_str(&rest[..at]);
+        result.push('\n');
+        rest = &rest[at + 1..];
+        rest = rest.strip_prefix('\n').unwrap_or(rest);
+    }
+    result.push_str(rest);
+    result
 }
 
 /// Normalize end-of-lines to LF (\n) and return the original type.
@@ -192,6 +251,32 @@ mod tests {
         assert_eq!(original, EolType::Cr);
     }
 
+    #[test]
+    fn test_line_scan_across_chunks() {
+        let mut scan = LineScan::default();
+        for chunk in [&b"one\r"[..], b"\ntwo\r", b"\r\nthree, the longest"] {
+            scan.feed(chunk);
+        }
+        assert_eq!(scan.eol_type(), EolType::Crlf);
+        assert_eq!(scan.line_count(), 4);
+        assert_eq!(scan.longest_line(), "three, the longest".len());
+
+        let mut scan = LineScan::default();
+        scan.feed(b"a\rb\r");
+        assert_eq!(scan.eol_type(), EolType::Cr);
+        assert_eq!(scan.line_count(), 2);
+    }
+
+    #[test]
+    fn test_normalize_eol_text() {
+        let text = "line1\r\nline2\rline3\r".to_string();
+        assert_eq!(
+            normalize_eol_text(text.clone(), EolType::Crlf),
+            "line1\nline2\nline3\n"
+        );
+        assert_eq!(normalize_eol_text(text.clone(), EolType::Mixed), text);
+    }
+
     #[test]
     fn test_restore_eol_crlf() {
         let normalized = b"line1\nline2\nline3";
@@ -1,11 +1,17 @@
 //! File identity tracking for detecting renames and moves.
 //!
 //! Uses file system metadata to create stable identities that persist
-//! across file renames and moves within the same volume.
+//! across file renames and moves within the same volume. Paths are told
+//! apart as the file system holding them does, ignoring case where it does.
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::SystemTime;
 
+use crate::error::{IoContext, IoOp};
+
 /// File identity configuration
 #[derive(Debug, Clone)]
 pub struct FileIdentityConfig {
@@ -54,11 +60,12 @@ impl FileIdentity {
         config: &FileIdentityConfig,
         compute_hash: bool,
     ) -> std::io::Result<Self> {
-        let metadata = std::fs::metadata(&path)?;
-        let mtime = metadata.modified()?;
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path).io_context(IoOp::Metadata, path)?;
+        let mtime = metadata.modified().io_context(IoOp::Metadata, path)?;
 
         let content_hash = if compute_hash {
-            Self::compute_fast_hash(&path, config.hash_sample_size)?
+            Self::compute_fast_hash(path, config.hash_sample_size)?
         } else {
             None
         };
@@ -120,6 +127,25 @@ impl FileIdentity {
         }
     }
 
+    /// Check whether the file at `path`, now `current`, is still this file
+    /// with the same start, as after appending to it. The start is compared
+    /// by hash as far as the sample reaches; without a hash it is assumed kept.
+    pub fn is_prefix_of<P: AsRef<Path>>(
+        &self,
+        path: P,
+        current: &FileIdentity,
+        config: &FileIdentityConfig,
+    ) -> std::io::Result<bool> {
+        if !self.is_same_file(current) || current.size < self.size {
+            return Ok(false);
+        }
+        let Some(hash) = self.content_hash else {
+            return Ok(true);
+        };
+        let sample_size = config.hash_sample_size.min(self.size as usize);
+        Ok(Self::compute_fast_hash(path, sample_size)? == Some(hash))
+    }
+
     /// Compute a fast rolling hash of the file content.
     fn compute_fast_hash<P: AsRef<Path>>(
         path: P,
@@ -128,9 +154,10 @@ impl FileIdentity {
         use std::fs::File;
         use std::io::Read;
 
-        let mut file = File::open(path)?;
+        let path = path.as_ref();
+        let mut file = File::open(path).io_context(IoOp::Open, path)?;
         let mut buffer = vec![0u8; sample_size.min(8192)]; // Cap at 8KB
-        let bytes_read = file.read(&mut buffer)?;
+        let bytes_read = file.read(&mut buffer).io_context(IoOp::Read, path)?;
 
         if bytes_read == 0 {
             return Ok(None);
@@ -160,9 +187,80 @@ impl FileIdentity {
     }
 }
 
+/// Check whether the file system holding `path` ignores the case of file
+/// names, as it does by default on macOS and Windows.
+///
+/// Found by looking up the name of `path`, or of its nearest ancestor that
+/// exists, with the case of its ASCII letters flipped. The answer is kept
+/// per directory; the platform default is assumed when nothing can be
+/// looked up.
+pub fn is_case_insensitive(path: &Path) -> bool {
+    static KNOWN: OnceLock<Mutex<HashMap<PathBuf, bool>>> = OnceLock::new();
+    let known = KNOWN.get_or_init(Default::default);
+
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    for existing in path.ancestors() {
+        let (Some(dir), Some(name)) = (existing.parent(), existing.file_name()) else {
+            continue;
+        };
+        if let Some(&insensitive) = known.lock().unwrap().get(dir) {
+            return insensitive;
+        }
+        let Some(flipped) = name.to_str().and_then(flip_case) else {
+            continue;
+        };
+        let Ok(metadata) = fs::metadata(existing) else {
+            continue;
+        };
+        let insensitive =
+            fs::metadata(dir.join(flipped)).is_ok_and(|other| same_file(&metadata, &other));
+        known.lock().unwrap().insert(dir.to_path_buf(), insensitive);
+        return insensitive;
+    }
+    cfg!(any(windows, target_os = "macos"))
+}
+
+/// `path` in the form to compare and look up files by: lowercased where
+/// the file system ignores case, so that `Foo.txt` and `foo.txt` are the
+/// same file there
+pub fn path_key(path: &Path) -> PathBuf {
+    match path.to_str() {
+        Some(text) if is_case_insensitive(path) => PathBuf::from(text.to_lowercase()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// `name` with the case of its ASCII letters flipped; None if it has none
+fn flip_case(name: &str) -> Option<String> {
+    let flipped: String = name
+        .chars()
+        .map(|ch| match ch.is_ascii_lowercase() {
+            true => ch.to_ascii_uppercase(),
+            false => ch.to_ascii_lowercase(),
+        })
+        .collect();
+    (flipped != name).then_some(flipped)
+}
+
+/// Check whether two metadata are of the same file
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        a.dev() == b.dev() && a.ino() == b.ino()
+    }
+
+    #[cfg(not(unix))]
+    {
+        // Without file IDs, two names for one file at least agree on these
+        a.len() == b.len() && a.modified().ok() == b.modified().ok()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TempDir;
     use std::env;
 
     fn create_temp_file(content: &[u8]) -> std::path::PathBuf {
@@ -229,6 +327,34 @@ mod tests {
         cleanup_temp_file(&temp_file);
     }
 
+    #[test]
+    fn test_prefix_after_append() {
+        let temp_file = create_temp_file(b"Hello, world!\n");
+
+        let config = FileIdentityConfig::default();
+        let identity = FileIdentity::from_path(&temp_file, &config).unwrap();
+
+        let mut content = b"Hello, world!\n".to_vec();
+        content.extend_from_slice(b"Appended\n");
+        std::fs::write(&temp_file, &content).unwrap();
+        let current = FileIdentity::from_path(&temp_file, &config).unwrap();
+        assert!(
+            identity
+                .is_prefix_of(&temp_file, &current, &config)
+                .unwrap()
+        );
+
+        std::fs::write(&temp_file, b"Hello, there!\nAppended\n").unwrap();
+        let current = FileIdentity::from_path(&temp_file, &config).unwrap();
+        assert!(
+            !identity
+                .is_prefix_of(&temp_file, &current, &config)
+                .unwrap()
+        );
+
+        cleanup_temp_file(&temp_file);
+    }
+
     #[test]
     fn test_content_change_detection() {
         let temp_file = create_temp_file(b"Hello, world!");
@@ -254,4 +380,21 @@ mod tests {
 
         cleanup_temp_file(&temp_file);
     }
+
+    #[test]
+    fn test_case_insensitive_paths_share_a_key() {
+        let dir = TempDir::new("identity_case");
+        let file = dir.join("Notes.txt");
+        std::fs::write(&file, "notes").unwrap();
+
+        // Whatever the file system here, the probe agrees with it
+        let insensitive = dir.join("NOTES.TXT").exists();
+        assert_eq!(is_case_insensitive(&file), insensitive);
+        assert_eq!(
+            path_key(&file) == path_key(&dir.join("notes.txt")),
+            insensitive
+        );
+        // A file yet to be created is judged by its directory
+        assert_eq!(is_case_insensitive(&dir.join("New.txt")), insensitive);
+    }
 }
@@ -1,14 +1,14 @@
 //! File loading with encoding detection, streaming, and binary guards.
 
-use std::borrow::Cow;
 use std::fs::File;
-use std::io::{self, Read};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::Path;
 
-use super::eol::{EolType, normalize_eol};
+use super::eol::{EolType, LineScan, normalize_eol, normalize_eol_text};
 use super::identity::{FileIdentity, FileIdentityConfig};
 use crate::bom::detect_bom;
-use crate::encoding::{DetectionConfig, Encoding, detect_encoding_heuristic};
+use crate::encoding::{DetectionConfig, Encoding, detect_encoding_heuristic, single_byte};
+use crate::error::{FsError, FsResult, IoContext, IoOp};
 
 /// Configuration for file loading operations
 #[derive(Debug, Clone)]
@@ -23,6 +23,14 @@ pub struct FileLoadConfig {
     pub encoding_config: DetectionConfig,
     /// File identity configuration
     pub identity_config: FileIdentityConfig,
+    /// Checked between chunks; loading stops with an `Interrupted` I/O error
+    /// once it returns true
+    pub interrupt: Option<fn() -> bool>,
+    /// Whether the file may be written to while it is read, as an active log
+    /// is. The file is read up to the length it had when loading began, read
+    /// again if it shrinks meanwhile, and a character cut off at that length
+    /// is left out; a file that changed is opened read-only, with a warning.
+    pub tolerate_writes: bool,
 }
 
 impl Default for FileLoadConfig {
@@ -33,6 +41,8 @@ impl Default for FileLoadConfig {
             use_mmap: true,
             encoding_config: DetectionConfig::default(),
             identity_config: FileIdentityConfig::default(),
+            interrupt: None,
+            tolerate_writes: false,
         }
     }
 }
@@ -46,6 +56,8 @@ pub struct FileLoadResult {
     pub original_encoding: Encoding,
     /// Original end-of-line type
     pub original_eol: EolType,
+    /// Number of lines, counted while reading, as `str::lines` counts them
+    pub line_count: usize,
     /// File identity for tracking renames/moves
     pub identity: FileIdentity,
     /// Whether file was opened as read-only due to binary/huge content
@@ -62,20 +74,213 @@ pub struct FileLoadResult {
 /// 3. Loads content with streaming/chunked reading
 /// 4. Normalizes to UTF-8 + LF in memory
 /// 5. Captures file identity for external change detection
-pub fn load_file<P: AsRef<Path>>(path: P) -> Result<FileLoadResult, crate::EncodingError> {
+pub fn load_file<P: AsRef<Path>>(path: P) -> FsResult<FileLoadResult> {
     load_file_with_config(path, &FileLoadConfig::default())
 }
 
+/// Lines appended to a file since it was loaded, as read by [`load_appended`]
+#[derive(Debug)]
+pub struct FileAppendResult {
+    /// The appended lines (normalized to UTF-8, LF)
+    pub content: String,
+    /// Offset just past the last complete line read; the next read starts here
+    pub end_offset: u64,
+    /// File identity at the time of reading
+    pub identity: FileIdentity,
+}
+
+/// Read the lines added to a file past `offset`, decoding them with the
+/// file's `encoding` as found when it was loaded.
+///
+/// Only complete lines are read, so a line that is still being written, a
+/// CRLF pair or a multi-byte character is never split; the rest stays for the
+/// next read from `end_offset`. A file shorter than `offset` was truncated or
+/// replaced and fails with an `InvalidInput` I/O error, to be loaded again in
+/// full.
+pub fn load_appended<P: AsRef<Path>>(
+    path: P,
+    offset: u64,
+    encoding: Encoding,
+) -> FsResult<FileAppendResult> {
+    let path = path.as_ref();
+    let identity = FileIdentity::from_path(path, &FileIdentityConfig::default())?;
+    if identity.size < offset {
+        return Err(FsError::io(
+            IoOp::Read,
+            path,
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file is shorter than what was loaded",
+            ),
+        ));
+    }
+
+    let mut file = File::open(path).io_context(IoOp::Open, path)?;
+    file.seek(SeekFrom::Start(offset))
+        .io_context(IoOp::Seek, path)?;
+    let mut raw_content = Vec::new();
+    file.read_to_end(&mut raw_content)
+        .io_context(IoOp::Read, path)?;
+
+    let complete = complete_lines_len(&raw_content, encoding);
+    let decoded_content =
+        decode(&raw_content[..complete], encoding).map_err(|e| e.with_path(path))?;
+    let (normalized_content, _) = normalize_eol(decoded_content.as_bytes());
+
+    Ok(FileAppendResult {
+        content: String::from_utf8_lossy(&normalized_content).into_owned(),
+        end_offset: offset + complete as u64,
+        identity,
+    })
+}
+
+/// What [`reload_incremental`] found changed in a file since it was loaded
+#[derive(Debug)]
+pub enum FileReloadResult {
+    /// No complete line was added
+    Unchanged,
+    /// The file was only appended to; holds the new lines
+    Appended(FileAppendResult),
+    /// The file was rewritten, truncated or replaced, and loaded again in full
+    Replaced(FileLoadResult),
+}
+
+/// Reload a file that was loaded as `identity`, of which the first
+/// `prev_len` bytes were read, with default configuration.
+pub fn reload_incremental<P: AsRef<Path>>(
+    path: P,
+    identity: &FileIdentity,
+    prev_len: u64,
+    encoding: Encoding,
+) -> FsResult<FileReloadResult> {
+    reload_incremental_with_config(
+        path,
+        identity,
+        prev_len,
+        encoding,
+        &FileLoadConfig::default(),
+    )
+}
+
+/// Reload a file that was loaded as `identity`, of which the first
+/// `prev_len` bytes were read, decoded as `encoding`.
+///
+/// When the file is the same one, no shorter and starts the same (judged by
+/// the identity's content hash), only the lines appended past `prev_len` are
+/// read, so growing logs aren't read again in full on every change. Anything
+/// else loads the whole file again.
+pub fn reload_incremental_with_config<P: AsRef<Path>>(
+    path: P,
+    identity: &FileIdentity,
+    prev_len: u64,
+    encoding: Encoding,
+    config: &FileLoadConfig,
+) -> FsResult<FileReloadResult> {
+    let path = path.as_ref();
+    let current = FileIdentity::from_path(path, &config.identity_config)
+        .map_err(|e| FsError::identity(path, e))?;
+    let appended = current.size >= prev_len
+        && identity
+            .is_prefix_of(path, &current, &config.identity_config)
+            .map_err(|e| FsError::identity(path, e))?;
+    if !appended {
+        crate::log!(
+            Debug,
+            "fs",
+            "{} was rewritten, loading it again",
+            path.display()
+        );
+        return load_file_with_config(path, config).map(FileReloadResult::Replaced);
+    }
+
+    let result = load_appended(path, prev_len, encoding)?;
+    if result.content.is_empty() {
+        Ok(FileReloadResult::Unchanged)
+    } else {
+        Ok(FileReloadResult::Appended(result))
+    }
+}
+
+/// Length of `bytes` up to and including the last line feed, as encoded in
+/// `encoding`
+pub(super) fn complete_lines_len(bytes: &[u8], encoding: Encoding) -> usize {
+    let newline: &[u8] = match encoding {
+        Encoding::Utf16Le => &[0x0A, 0],
+        Encoding::Utf16Be => &[0, 0x0A],
+        Encoding::Utf32Le => &[0x0A, 0, 0, 0],
+        Encoding::Utf32Be => &[0, 0, 0, 0x0A],
+        _ => &[0x0A],
+    };
+    // Line feeds only count at code unit boundaries
+    bytes
+        .chunks_exact(newline.len())
+        .rposition(|unit| unit == newline)
+        .map_or(0, |unit| (unit + 1) * newline.len())
+}
+
+/// Length of `bytes` without a character cut off at its end, as encoded in
+/// `encoding`
+pub(super) fn complete_chars_len(bytes: &[u8], encoding: Encoding) -> usize {
+    match encoding {
+        Encoding::Utf8 => {
+            // Back to the lead byte of the last character, if it is short
+            let lead = bytes
+                .iter()
+                .rev()
+                .take(4)
+                .position(|byte| byte & 0xC0 != 0x80)
+                .map(|back| bytes.len() - 1 - back);
+            let Some(lead) = lead else {
+                return bytes.len();
+            };
+            let width = match bytes[lead] {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            if lead + width > bytes.len() {
+                lead
+            } else {
+                bytes.len()
+            }
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let len = bytes.len() - bytes.len() % 2;
+            // A high surrogate without the low one after it
+            let last = match encoding {
+                Encoding::Utf16Le => bytes.get(len.wrapping_sub(1)),
+                _ => bytes.get(len.wrapping_sub(2)),
+            };
+            match last {
+                Some(0xD8..=0xDB) => len - 2,
+                _ => len,
+            }
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => bytes.len() - bytes.len() % 4,
+        _ => bytes.len(),
+    }
+}
+
+/// How many more times a file that shrank while it was read is read, when
+/// writes are tolerated
+const SHRINK_RETRIES: u32 = 3;
+
 /// Load a file with custom configuration.
 pub fn load_file_with_config<P: AsRef<Path>>(
     path: P,
     config: &FileLoadConfig,
-) -> Result<FileLoadResult, crate::EncodingError> {
-    let path = path.as_ref();
+) -> FsResult<FileLoadResult> {
+    load_file_attempt(path.as_ref(), config, 0)
+}
 
+fn load_file_attempt(
+    path: &Path,
+    config: &FileLoadConfig,
+    attempt: u32,
+) -> FsResult<FileLoadResult> {
     // First, capture file identity
-    let identity =
-        FileIdentity::from_path(path, &config.identity_config).map_err(crate::EncodingError::Io)?;
+    let identity = FileIdentity::from_path(path, &config.identity_config)?;
 
     // Check if file is too large to load entirely
     if identity.size > 100 * 1024 * 1024 {
@@ -84,6 +289,7 @@ pub fn load_file_with_config<P: AsRef<Path>>(
             content: String::new(),
             original_encoding: Encoding::Unknown,
             original_eol: EolType::Lf,
+            line_count: 0,
             identity,
             read_only: true,
             warnings: vec!["File too large (>100MB), opened as read-only".to_string()],
@@ -91,15 +297,20 @@ pub fn load_file_with_config<P: AsRef<Path>>(
     }
 
     // Read initial sample for encoding detection
-    let mut file = File::open(path).map_err(crate::EncodingError::Io)?;
+    let mut file = File::open(path).io_context(IoOp::Open, path)?;
     let mut sample = vec![0u8; config.encoding_config.sample_size];
-    let sample_size = file.read(&mut sample).map_err(crate::EncodingError::Io)?;
+    let mut sample_size = file.read(&mut sample).io_context(IoOp::Read, path)?;
+    if config.tolerate_writes {
+        // Nothing written since the length was captured
+        sample_size = sample_size.min(usize::try_from(identity.size).unwrap_or(usize::MAX));
+    }
 
     if sample_size == 0 {
         return Ok(FileLoadResult {
             content: String::new(),
             original_encoding: Encoding::Utf8,
             original_eol: EolType::Lf,
+            line_count: 0,
             identity,
             read_only: false,
             warnings: vec![],
@@ -114,98 +325,180 @@ pub fn load_file_with_config<P: AsRef<Path>>(
             content: String::new(),
             original_encoding: Encoding::Unknown,
             original_eol: EolType::Lf,
+            line_count: 0,
             identity,
             read_only: true,
             warnings: vec!["Binary file detected, opened as read-only".to_string()],
         });
     }
 
+    // Line breaks and lengths are found while reading, starting with the
+    // sample, so the content isn't gone over again for them
+    let bom_result = detect_bom(sample);
+    let mut scan = LineScan::default();
+    scan.feed(&sample[bom_result.bom_length..]);
+
     // Check for extremely long lines
-    if has_extremely_long_lines(sample, config.max_line_length) {
-        return Ok(FileLoadResult {
+    let too_long = |scan: &LineScan| scan.longest_line() > config.max_line_length;
+    let long_lines = |identity| {
+        Ok(FileLoadResult {
             content: String::new(),
             original_encoding: Encoding::Unknown,
             original_eol: EolType::Lf,
+            line_count: 0,
             identity,
             read_only: true,
             warnings: vec![format!(
                 "Extremely long lines detected (>{} bytes), opened as read-only",
                 config.max_line_length
             )],
-        });
+        })
+    };
+    if too_long(&scan) {
+        return long_lines(identity);
     }
 
     // Detect encoding
-    let bom_result = detect_bom(sample);
     let encoding = if bom_result.encoding != Encoding::Unknown {
         bom_result.encoding
     } else {
         detect_encoding_heuristic(
             &sample[bom_result.bom_length..],
             config.encoding_config.clone(),
-        )?
+        )
+        .map_err(|e| e.with_path(path))?
     };
 
     // Load full content
-    let raw_content = load_content_streaming(path, config)?;
-    let raw_content = &raw_content[bom_result.bom_length..]; // Skip BOM
+    let mut raw_content = if config.tolerate_writes {
+        let rest = identity.size - sample.len() as u64;
+        let mut file = (&mut file).take(rest);
+        load_content_streaming(&mut file, sample, identity.size, path, config, &mut scan)?
+    } else {
+        load_content_streaming(&mut file, sample, identity.size, path, config, &mut scan)?
+    };
+    if too_long(&scan) {
+        return long_lines(identity);
+    }
 
-    // Decode content based on encoding
-    let decoded_content = match encoding {
-        Encoding::Utf8 => {
-            String::from_utf8(raw_content.to_vec()).map_err(|_| crate::EncodingError::BinaryFile)?
-        }
-        Encoding::Utf16Le => decode_utf16le(raw_content)?,
-        Encoding::Utf16Be => decode_utf16be(raw_content)?,
-        Encoding::Utf32Le => decode_utf32le(raw_content)?,
-        Encoding::Utf32Be => decode_utf32be(raw_content)?,
-        Encoding::Latin1 | Encoding::Windows1252 | Encoding::Latin9 => {
-            decode_latin(raw_content, encoding)
+    let mut read_only = false;
+    let mut warnings = Vec::new();
+    if config.tolerate_writes {
+        let read = raw_content.len() as u64;
+        if read < identity.size {
+            if attempt < SHRINK_RETRIES {
+                crate::log!(
+                    Debug,
+                    "fs",
+                    "{} shrank while it was read, reading it again",
+                    path.display()
+                );
+                return load_file_attempt(path, config, attempt + 1);
+            }
+            read_only = true;
+            warnings.push(format!(
+                "File shrank while being read, opened the {} bytes read as read-only",
+                read
+            ));
+        } else if file.metadata().is_ok_and(|metadata| metadata.len() > read) {
+            read_only = true;
+            warnings.push(format!(
+                "File grew while being read, opened its first {} bytes as read-only",
+                read
+            ));
         }
-        Encoding::Unknown => return Err(crate::EncodingError::BinaryFile),
-    };
 
-    // Normalize EOL
-    let (normalized_content, original_eol) = normalize_eol(decoded_content.as_bytes());
+        let body = &raw_content[bom_result.bom_length..];
+        let complete = bom_result.bom_length + complete_chars_len(body, encoding);
+        if complete < raw_content.len() {
+            raw_content.truncate(complete);
+            scan = LineScan::default();
+            scan.feed(&raw_content[bom_result.bom_length..]);
+            warnings.push("Character cut off at the end of the file left out".to_string());
+        }
+    }
 
-    // Convert to String, handling the Cow
-    let content = match normalized_content {
-        Cow::Borrowed(bytes) => String::from_utf8_lossy(bytes).into_owned(),
-        Cow::Owned(vec) => String::from_utf8_lossy(&vec).into_owned(),
-    };
+    // Decode content based on encoding, skipping the BOM
+    let decoded_content = decode_owned(raw_content, bom_result.bom_length, encoding)
+        .map_err(|e| e.with_path(path))?;
+
+    // Line breaks are the same bytes in the file as in the text in ASCII
+    // compatible encodings; in the others they are looked for in the text
+    if !matches!(
+        encoding,
+        Encoding::Utf8 | Encoding::Latin1 | Encoding::Windows1252 | Encoding::Latin9
+    ) {
+        scan = LineScan::default();
+        scan.feed(decoded_content.as_bytes());
+    }
 
+    // Normalize EOL
+    let original_eol = scan.eol_type();
+    let content = normalize_eol_text(decoded_content, original_eol);
+
+    crate::log!(
+        Debug,
+        "fs",
+        "loaded {} ({:?}, {:?} line endings)",
+        path.display(),
+        encoding,
+        original_eol
+    );
     Ok(FileLoadResult {
         content,
         original_encoding: encoding,
         original_eol,
+        line_count: scan.line_count(),
         identity,
-        read_only: false,
-        warnings: vec![],
+        read_only,
+        warnings,
     })
 }
 
-/// Load file content using streaming/chunked reading to avoid large allocations.
-fn load_content_streaming<P: AsRef<Path>>(
-    path: P,
+/// Read the rest of `file`, of which `sample` was read first, in chunks,
+/// feeding them to `scan` as they come. `size` is the expected length.
+/// Reading stops early at a line longer than `config.max_line_length`, as
+/// the file is then not loaded.
+fn load_content_streaming(
+    file: &mut impl Read,
+    sample: &[u8],
+    size: u64,
+    path: &Path,
     config: &FileLoadConfig,
-) -> Result<Vec<u8>, crate::EncodingError> {
-    let mut file = File::open(path).map_err(crate::EncodingError::Io)?;
-    let mut content = Vec::new();
-    let mut buffer = vec![0u8; config.chunk_size];
+    scan: &mut LineScan,
+) -> FsResult<Vec<u8>> {
+    let mut content = Vec::with_capacity(usize::try_from(size).unwrap_or(0).max(sample.len()));
+    content.extend_from_slice(sample);
 
     loop {
-        let bytes_read = file.read(&mut buffer).map_err(crate::EncodingError::Io)?;
+        if config.interrupt.is_some_and(|interrupted| interrupted()) {
+            return Err(FsError::io(
+                IoOp::Read,
+                path,
+                io::Error::new(io::ErrorKind::Interrupted, "loading interrupted"),
+            ));
+        }
+        // Read straight into the content, without a buffer in between
+        let start = content.len();
+        content.resize(start + config.chunk_size, 0);
+        let bytes_read = file
+            .read(&mut content[start..])
+            .io_context(IoOp::Read, path)?;
+        content.truncate(start + bytes_read);
         if bytes_read == 0 {
             break;
         }
-        content.extend_from_slice(&buffer[..bytes_read]);
+        scan.feed(&content[start..]);
+        if scan.longest_line() > config.max_line_length {
+            break;
+        }
     }
 
     Ok(content)
 }
 
 /// Check if content appears to be binary based on null bytes and control characters.
-fn is_binary_content(sample: &[u8]) -> bool {
+pub(super) fn is_binary_content(sample: &[u8]) -> bool {
     if sample.len() < 512 {
         return false; // Too small to determine
     }
@@ -228,179 +521,119 @@ fn is_binary_content(sample: &[u8]) -> bool {
     null_ratio > 0.1 || control_ratio > 0.3
 }
 
-/// Check if the file has extremely long lines that might indicate binary data.
-fn has_extremely_long_lines(sample: &[u8], max_line_length: usize) -> bool {
-    let mut current_line_length = 0;
-    let mut max_found_length = 0;
-
-    for &byte in sample {
-        if byte == b'\n' || byte == b'\r' {
-            max_found_length = max_found_length.max(current_line_length);
-            current_line_length = 0;
-        } else {
-            current_line_length += 1;
-            if current_line_length > max_line_length {
-                return true;
-            }
-        }
+/// Decode `bytes` past the first `skip` in `encoding` to a String, taking
+/// over the bytes of UTF-8 text instead of copying them
+fn decode_owned(mut bytes: Vec<u8>, skip: usize, encoding: Encoding) -> FsResult<String> {
+    if encoding != Encoding::Utf8 {
+        return decode(&bytes[skip..], encoding);
     }
-
-    max_found_length > max_line_length
+    bytes.drain(..skip);
+    String::from_utf8(bytes).map_err(|e| invalid_utf8(e.utf8_error()))
 }
 
-/// Decode UTF-16LE bytes to String.
-fn decode_utf16le(bytes: &[u8]) -> Result<String, crate::EncodingError> {
-    if bytes.len() % 2 != 0 {
-        return Err(crate::EncodingError::BinaryFile);
-    }
-
-    let u16_slice =
-        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) };
-
-    String::from_utf16(u16_slice).map_err(|_| crate::EncodingError::BinaryFile)
+fn invalid_utf8(err: std::str::Utf8Error) -> FsError {
+    let at = err.valid_up_to();
+    FsError::encoding(
+        Encoding::Utf8,
+        format!("invalid byte sequence at byte {}", at),
+    )
 }
 
-/// Decode UTF-16BE bytes to String.
-fn decode_utf16be(bytes: &[u8]) -> Result<String, crate::EncodingError> {
-    if bytes.len() % 2 != 0 {
-        return Err(crate::EncodingError::BinaryFile);
-    }
-
-    let mut u16_vec = Vec::with_capacity(bytes.len() / 2);
-    for chunk in bytes.chunks_exact(2) {
-        let be_bytes = [chunk[1], chunk[0]]; // Swap endianness
-        u16_vec.push(u16::from_be_bytes(be_bytes));
+/// Decode `bytes` in `encoding` to a String.
+pub(super) fn decode(bytes: &[u8], encoding: Encoding) -> FsResult<String> {
+    match encoding {
+        Encoding::Utf8 => {
+            String::from_utf8(bytes.to_vec()).map_err(|e| invalid_utf8(e.utf8_error()))
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => decode_utf16(bytes, encoding),
+        Encoding::Utf32Le | Encoding::Utf32Be => decode_utf32(bytes, encoding),
+        Encoding::Latin1 | Encoding::Windows1252 | Encoding::Latin9 => {
+            Ok(decode_latin(bytes, encoding))
+        }
+        Encoding::Unknown => Err(FsError::binary("no text encoding detected")),
     }
-
-    String::from_utf16(&u16_vec).map_err(|_| crate::EncodingError::BinaryFile)
 }
 
-/// Decode UTF-32LE bytes to String.
-fn decode_utf32le(bytes: &[u8]) -> Result<String, crate::EncodingError> {
-    if bytes.len() % 4 != 0 {
-        return Err(crate::EncodingError::BinaryFile);
+/// Decode UTF-16 bytes, little endian for `Utf16Le` and big endian
+/// otherwise, to String. Errors give the byte offset of the code unit that
+/// is wrong.
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> FsResult<String> {
+    if bytes.len() % 2 != 0 {
+        return Err(FsError::encoding(encoding, "odd number of bytes"));
     }
 
-    let u32_slice =
-        unsafe { std::slice::from_raw_parts(bytes.as_ptr() as *const u32, bytes.len() / 4) };
-
-    let mut result = String::new();
-    for &code in u32_slice {
-        if let Some(ch) = char::from_u32(code) {
-            result.push(ch);
-        } else {
-            return Err(crate::EncodingError::BinaryFile);
+    // Code units are put together from byte pairs, as the bytes needn't be
+    // aligned for u16
+    let units = bytes.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        match encoding {
+            Encoding::Utf16Le => u16::from_le_bytes(pair),
+            _ => u16::from_be_bytes(pair),
+        }
+    });
+    let mut result = String::with_capacity(bytes.len() / 2);
+    let mut offset = 0usize;
+    for decoded in char::decode_utf16(units) {
+        match decoded {
+            Ok(ch) => {
+                result.push(ch);
+                offset += ch.len_utf16() * 2;
+            }
+            Err(err) => {
+                return Err(FsError::encoding(
+                    encoding,
+                    format!(
+                        "unpaired surrogate {:#06x} at byte {}",
+                        err.unpaired_surrogate(),
+                        offset
+                    ),
+                ));
+            }
         }
     }
     Ok(result)
 }
 
-/// Decode UTF-32BE bytes to String.
-fn decode_utf32be(bytes: &[u8]) -> Result<String, crate::EncodingError> {
+/// Decode UTF-32 bytes, little endian for `Utf32Le` and big endian
+/// otherwise, to String. Errors give the byte offset of the code point that
+/// is wrong.
+fn decode_utf32(bytes: &[u8], encoding: Encoding) -> FsResult<String> {
     if bytes.len() % 4 != 0 {
-        return Err(crate::EncodingError::BinaryFile);
+        return Err(FsError::encoding(
+            encoding,
+            "length is not a multiple of 4 bytes",
+        ));
     }
 
-    let mut result = String::new();
-    for chunk in bytes.chunks_exact(4) {
-        let be_bytes = [chunk[3], chunk[2], chunk[1], chunk[0]]; // Swap endianness
-        let code = u32::from_be_bytes(be_bytes);
-        if let Some(ch) = char::from_u32(code) {
-            result.push(ch);
-        } else {
-            return Err(crate::EncodingError::BinaryFile);
-        }
+    let mut result = String::with_capacity(bytes.len() / 4);
+    for (index, quad) in bytes.chunks_exact(4).enumerate() {
+        let quad = [quad[0], quad[1], quad[2], quad[3]];
+        let code = match encoding {
+            Encoding::Utf32Le => u32::from_le_bytes(quad),
+            _ => u32::from_be_bytes(quad),
+        };
+        let Some(ch) = char::from_u32(code) else {
+            let what = if (0xD800..=0xDFFF).contains(&code) {
+                "surrogate"
+            } else {
+                "code point beyond U+10FFFF"
+            };
+            return Err(FsError::encoding(
+                encoding,
+                format!("{} {:#x} at byte {}", what, code, index * 4),
+            ));
+        };
+        result.push(ch);
     }
     Ok(result)
 }
 
 /// Decode Latin encodings (Latin-1, Windows-1252, Latin-9) to UTF-8.
 fn decode_latin(bytes: &[u8], encoding: Encoding) -> String {
-    let mut result = String::new();
-
-    for &byte in bytes {
-        let ch = match encoding {
-            Encoding::Latin1 => latin1_to_char(byte),
-            Encoding::Windows1252 => windows1252_to_char(byte),
-            Encoding::Latin9 => latin9_to_char(byte),
-            _ => unreachable!(),
-        };
-        result.push(ch);
-    }
-
-    result
-}
-
-/// Convert Latin-1 byte to Unicode character.
-fn latin1_to_char(byte: u8) -> char {
-    if byte < 0x80 {
-        byte as char
-    } else {
-        // Latin-1 supplementary characters
-        match byte {
-            0x80..=0x9F => char::from_u32(0x0080 + byte as u32 - 0x80).unwrap(),
-            _ => char::from_u32(0x00A0 + byte as u32 - 0xA0).unwrap(),
-        }
-    }
-}
-
-/// Convert Windows-1252 byte to Unicode character.
-fn windows1252_to_char(byte: u8) -> char {
-    if byte < 0x80 {
-        byte as char
-    } else {
-        // Windows-1252 specific mappings for 0x80-0x9F
-        match byte {
-            0x80 => '€',
-            0x81 => '\u{0081}', // Unassigned
-            0x82 => '‚',
-            0x83 => 'ƒ',
-            0x84 => '„',
-            0x85 => '…',
-            0x86 => '†',
-            0x87 => '‡',
-            0x88 => 'ˆ',
-            0x89 => '‰',
-            0x8A => 'Š',
-            0x8B => '‹',
-            0x8C => 'Œ',
-            0x8D => '\u{008D}', // Unassigned
-            0x8E => 'Ž',
-            0x8F => '\u{008F}', // Unassigned
-            0x90 => '\u{0090}', // Unassigned
-            0x91 => '‘',
-            0x92 => '’',
-            0x93 => '"',
-            0x94 => '"',
-            0x95 => '•',
-            0x96 => '–',
-            0x97 => '—',
-            0x98 => '˜',
-            0x99 => '™',
-            0x9A => 'š',
-            0x9B => '›',
-            0x9C => 'œ',
-            0x9D => '\u{009D}', // Unassigned
-            0x9E => 'ž',
-            0x9F => 'Ÿ',
-            _ => latin1_to_char(byte), // Fall back to Latin-1 for 0xA0+
-        }
-    }
-}
-
-/// Convert Latin-9 byte to Unicode character.
-fn latin9_to_char(byte: u8) -> char {
-    match byte {
-        0xA4 => '€', // Euro sign (different from Latin-1)
-        0xA6 => 'Š', // Latin capital letter S with caron
-        0xA8 => 'š', // Latin small letter s with caron
-        0xB4 => 'Ž', // Latin capital letter Z with caron
-        0xB8 => 'ž', // Latin small letter z with caron
-        0xBC => 'Œ', // Latin capital ligature OE
-        0xBD => 'œ', // Latin small ligature oe
-        0xBE => 'Ÿ', // Latin capital letter Y with diaeresis
-        _ => latin1_to_char(byte),
-    }
+    bytes
+        .iter()
+        .filter_map(|&byte| single_byte::decode_byte(byte, encoding))
+        .collect()
 }
 
 #[cfg(test)]
@@ -422,6 +655,12 @@ mod tests {
         temp_path
     }
 
+    fn append_bytes(path: &std::path::Path, bytes: &[u8]) {
+        use std::io::Write;
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        file.write_all(bytes).unwrap();
+    }
+
     fn cleanup_temp_file(path: &std::path::Path) {
         let _ = std::fs::remove_file(path);
     }
@@ -471,6 +710,190 @@ mod tests {
         let result = load_file(&temp_file).unwrap();
         assert_eq!(result.content, "Line1\nLine2\nLine3");
         assert_eq!(result.original_eol, EolType::Crlf);
+        assert_eq!(result.line_count, 3);
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_load_scans_lines_past_the_sample() {
+        // Read in chunks that split CRLF pairs, well past the sample
+        let config = FileLoadConfig {
+            chunk_size: 7,
+            max_line_length: 100,
+            ..FileLoadConfig::default()
+        };
+        let text = "short line\r\n".repeat(200);
+        let temp_file = create_temp_file(&text);
+        let result = load_file_with_config(&temp_file, &config).unwrap();
+        assert_eq!(result.content, "short line\n".repeat(200));
+        assert_eq!(result.original_eol, EolType::Crlf);
+        assert_eq!(result.line_count, 200);
+        cleanup_temp_file(&temp_file);
+
+        // A line too long for the editor, after a sample of short ones
+        let temp_file = create_temp_file(&(text + &"x".repeat(101)));
+        let result = load_file_with_config(&temp_file, &config).unwrap();
+        assert!(result.read_only);
+        assert!(result.content.is_empty());
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_load_interrupted() {
+        let temp_file = create_temp_file("Hello, UTF-8!\nSecond line");
+        let config = FileLoadConfig {
+            interrupt: Some(|| true),
+            ..FileLoadConfig::default()
+        };
+
+        match load_file_with_config(&temp_file, &config) {
+            Err(e) => assert_eq!(e.io_kind(), Some(io::ErrorKind::Interrupted)),
+            other => panic!(
+                "expected an interrupted load, got {:?}",
+                other.map(|r| r.content)
+            ),
+        }
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    // The file the hooks below write to while it is being loaded
+    static WRITTEN: std::sync::Mutex<Option<std::path::PathBuf>> = std::sync::Mutex::new(None);
+
+    fn write_once(write: fn(&std::path::Path)) {
+        if let Some(path) = WRITTEN.lock().unwrap().take() {
+            write(&path);
+        }
+    }
+
+    #[test]
+    fn test_load_tolerates_writes() {
+        let text = "log line\n".repeat(500);
+        let temp_file = create_temp_file(&text);
+        let config = FileLoadConfig {
+            chunk_size: 64,
+            tolerate_writes: true,
+            interrupt: Some(|| {
+                write_once(|path| append_bytes(path, b"appended\n"));
+                false
+            }),
+            ..FileLoadConfig::default()
+        };
+
+        // Lines appended during the read are left for the next one
+        *WRITTEN.lock().unwrap() = Some(temp_file.clone());
+        let result = load_file_with_config(&temp_file, &config).unwrap();
+        assert_eq!(result.content, text);
+        assert_eq!(result.line_count, 500);
+        assert!(result.read_only);
+        assert!(result.warnings[0].starts_with("File grew while being read"));
+
+        // A file truncated during the read is read again as it is now
+        let config = FileLoadConfig {
+            interrupt: Some(|| {
+                write_once(|path| std::fs::write(path, "rotated\n").unwrap());
+                false
+            }),
+            ..config
+        };
+        std::fs::write(&temp_file, &text).unwrap();
+        *WRITTEN.lock().unwrap() = Some(temp_file.clone());
+        let result = load_file_with_config(&temp_file, &config).unwrap();
+        assert_eq!(result.content, "rotated\n");
+        assert!(!result.read_only);
+        assert!(result.warnings.is_empty());
+
+        // A character still being written is left out
+        std::fs::write(&temp_file, b"\xEF\xBB\xBFcaf\xC3").unwrap();
+        let result = load_file_with_config(&temp_file, &config).unwrap();
+        assert_eq!(result.content, "caf");
+        assert_eq!(result.line_count, 1);
+        assert!(load_file(&temp_file).is_err());
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_load_errors_name_the_file() {
+        let missing = env::temp_dir().join("niv_fs_missing_file.txt");
+        let err = load_file(&missing).unwrap_err();
+        assert_eq!(err.path(), Some(missing.as_path()));
+        assert_eq!(err.io_kind(), Some(io::ErrorKind::NotFound));
+        let operation = format!("reading metadata of {}", missing.display());
+        assert!(err.to_string().ends_with(&operation), "{}", err);
+
+        let err = decode(&[0x61, 0, 0x62], Encoding::Utf16Le).unwrap_err();
+        assert!(matches!(err, FsError::Encoding { path: None, .. }));
+    }
+
+    #[test]
+    fn test_load_appended_reads_complete_lines() {
+        let temp_file = create_temp_file("first\n");
+        let offset = load_file(&temp_file).unwrap().identity.size;
+
+        append_bytes(&temp_file, b"second\r\nthird\npart");
+        let result = load_appended(&temp_file, offset, Encoding::Utf8).unwrap();
+        assert_eq!(result.content, "second\nthird\n");
+        assert_eq!(result.end_offset, offset + 14);
+
+        append_bytes(&temp_file, b"ial\n");
+        let result = load_appended(&temp_file, result.end_offset, Encoding::Utf8).unwrap();
+        assert_eq!(result.content, "partial\n");
+        assert_eq!(result.end_offset, result.identity.size);
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_load_appended_utf16() {
+        let temp_file = create_temp_file("");
+        // "a\n" and half of "b", in UTF-16LE
+        append_bytes(&temp_file, &[0x61, 0, 0x0A, 0, 0x62]);
+
+        let result = load_appended(&temp_file, 0, Encoding::Utf16Le).unwrap();
+        assert_eq!(result.content, "a\n");
+        assert_eq!(result.end_offset, 4);
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_load_appended_truncated() {
+        let temp_file = create_temp_file("short\n");
+
+        match load_appended(&temp_file, 100, Encoding::Utf8) {
+            Err(e) => assert_eq!(e.io_kind(), Some(io::ErrorKind::InvalidInput)),
+            other => panic!(
+                "expected a truncated file error, got {:?}",
+                other.map(|r| r.content)
+            ),
+        }
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_reload_incremental() {
+        let temp_file = create_temp_file("first\n");
+        let loaded = load_file(&temp_file).unwrap();
+        let (identity, encoding) = (loaded.identity, loaded.original_encoding);
+
+        append_bytes(&temp_file, b"part");
+        let result = reload_incremental(&temp_file, &identity, identity.size, encoding).unwrap();
+        assert!(matches!(result, FileReloadResult::Unchanged));
+
+        append_bytes(&temp_file, b"ial\n");
+        match reload_incremental(&temp_file, &identity, identity.size, encoding).unwrap() {
+            FileReloadResult::Appended(appended) => assert_eq!(appended.content, "partial\n"),
+            other => panic!("expected an append, got {:?}", other),
+        }
+
+        std::fs::write(&temp_file, "rewritten\nfirst\n").unwrap();
+        match reload_incremental(&temp_file, &identity, identity.size, encoding).unwrap() {
+            FileReloadResult::Replaced(loaded) => assert_eq!(loaded.content, "rewritten\nfirst\n"),
+            other => panic!("expected a full reload, got {:?}", other),
+        }
 
         cleanup_temp_file(&temp_file);
     }
@@ -489,11 +912,54 @@ mod tests {
 
     #[test]
     fn test_has_extremely_long_lines() {
+        let longest = |bytes: &[u8]| {
+            let mut scan = LineScan::default();
+            scan.feed(bytes);
+            scan.longest_line()
+        };
         let normal_lines = b"Short line\nAnother short line\n";
-        assert!(!has_extremely_long_lines(normal_lines, 1000));
+        assert!(longest(normal_lines) <= 1000);
 
         let long_line = vec![b'A'; 2000]; // Very long line
-        assert!(has_extremely_long_lines(&long_line, 1000));
+        assert!(longest(&long_line) > 1000);
+    }
+
+    #[test]
+    fn test_decode_utf16_and_utf32() {
+        let text = "a\u{e9}\u{1F600}\n";
+        let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let utf32le: Vec<u8> = text
+            .chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect();
+        let utf32be: Vec<u8> = text
+            .chars()
+            .flat_map(|c| (c as u32).to_be_bytes())
+            .collect();
+        for (bytes, encoding) in [
+            (utf16le, Encoding::Utf16Le),
+            (utf16be, Encoding::Utf16Be),
+            (utf32le, Encoding::Utf32Le),
+            (utf32be, Encoding::Utf32Be),
+        ] {
+            assert_eq!(decode(&bytes, encoding).unwrap(), text);
+            // Bytes that aren't aligned for u16 or u32
+            let mut shifted = vec![0u8];
+            shifted.extend_from_slice(&bytes);
+            assert_eq!(decode(&shifted[1..], encoding).unwrap(), text);
+        }
+
+        // A high surrogate followed by a letter instead of a low surrogate
+        let err = decode(&[0x61, 0, 0x3D, 0xD8, 0x61, 0], Encoding::Utf16Le).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("unpaired surrogate 0xd83d at byte 2")
+        );
+        let err = decode(&[0, 0, 0, 0x61, 0, 0, 0xDC, 0], Encoding::Utf32Be).unwrap_err();
+        assert!(err.to_string().contains("surrogate 0xdc00 at byte 4"));
+        let err = decode(&[0, 0, 0x11, 0], Encoding::Utf32Le).unwrap_err();
+        assert!(err.to_string().contains("beyond U+10FFFF"));
     }
 
     #[test]
@@ -5,15 +5,40 @@
 //! - EOL detection and normalization
 //! - Binary/huge file guards
 //! - Atomic saving with transcoding
+//! - Saves queued per path, retrying transient failures
+//! - Saving several files together, all of them or none
+//! - Converting text between encodings
 //! - File identity tracking for renames/moves
+//! - Renaming and moving files, across file systems if needed
+//! - Directory listings, telling hidden files apart
+//! - Previews of the start of files at a bounded cost
 //! - Cross-platform permission preservation
+//! - Windows read-only attributes, sharing violations and long paths
 
+pub mod convert;
+pub mod dir;
 pub mod eol;
 pub mod identity;
 pub mod load;
+pub mod ops;
+pub mod preview;
 pub mod save;
+pub mod scheduler;
+pub mod transaction;
 
+pub use convert::{Conversion, ConvertError, convert, convert_with_policy};
+pub use dir::{DirEntryInfo, has_hidden_attribute, is_hidden, list_dir};
 pub use eol::{EolType, normalize_eol, restore_eol};
-pub use identity::{FileIdentity, FileIdentityConfig};
-pub use load::{FileLoadConfig, FileLoadResult, load_file, load_file_with_config};
-pub use save::{FileSaveConfig, FileSaveResult, SaveContext, save_file, save_file_with_config};
+pub use identity::{FileIdentity, FileIdentityConfig, is_case_insensitive, path_key};
+pub use load::{
+    FileAppendResult, FileLoadConfig, FileLoadResult, FileReloadResult, load_appended, load_file,
+    load_file_with_config, reload_incremental, reload_incremental_with_config,
+};
+pub use ops::{rename_file, resolve_move_target};
+pub use preview::{FilePreview, preview_file};
+pub use save::{
+    FileSaveConfig, FileSaveResult, SaveContext, UnencodablePolicy, append_to_file, save_file,
+    save_file_with_config, save_line_range, slice_lines,
+};
+pub use scheduler::{SaveFailure, SaveScheduler, SaveSchedulerConfig};
+pub use transaction::{SaveTransaction, TransactionResult};
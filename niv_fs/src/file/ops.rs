@@ -0,0 +1,117 @@
+//! File system operations on files open in the editor (rename/move).
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::identity::{FileIdentity, FileIdentityConfig};
+use crate::error::{IoContext, IoOp};
+
+/// Resolve the destination of a move.
+///
+/// Moving onto an existing directory, or onto a path ending with a separator,
+/// keeps the file name: `src/a.rs` moved to `lib/` becomes `lib/a.rs`.
+pub fn resolve_move_target(from: &Path, to: &Path) -> PathBuf {
+    let names_directory = to.is_dir() || to.to_string_lossy().ends_with(['/', '\\']);
+    match from.file_name() {
+        Some(name) if names_directory => to.join(name),
+        _ => to.to_path_buf(),
+    }
+}
+
+/// Rename or move a file, returning the identity of the file at its new location.
+///
+/// Missing parent directories of `to` are created. An existing destination is
+/// only replaced when `overwrite` is set. Moves across file systems fall back
+/// to copy and delete.
+pub fn rename_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    from: P,
+    to: Q,
+    overwrite: bool,
+) -> io::Result<FileIdentity> {
+    let from = from.as_ref();
+    let to = to.as_ref();
+
+    if !overwrite && to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    if let Some(parent) = to.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).io_context(IoOp::CreateDir, parent)?;
+    }
+
+    if let Err(err) = fs::rename(from, to) {
+        if !is_cross_device(&err) {
+            return Err(err).io_context(IoOp::Rename, from);
+        }
+        // fs::copy also copies permission bits
+        fs::copy(from, to).io_context(IoOp::Copy, from)?;
+        fs::remove_file(from).io_context(IoOp::Remove, from)?;
+    }
+
+    FileIdentity::from_path(to, &FileIdentityConfig::default())
+}
+
+/// Check whether a rename failed because source and destination are on different devices
+pub(super) fn is_cross_device(err: &io::Error) -> bool {
+    // EXDEV on Unix, ERROR_NOT_SAME_DEVICE on Windows
+    #[cfg(unix)]
+    const CROSS_DEVICE: i32 = 18;
+    #[cfg(windows)]
+    const CROSS_DEVICE: i32 = 17;
+    #[cfg(not(any(unix, windows)))]
+    const CROSS_DEVICE: i32 = -1;
+
+    err.raw_os_error() == Some(CROSS_DEVICE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_rename_file() {
+        let dir = TempDir::new("ops_rename");
+        let from = dir.join("old.txt");
+        let to = dir.join("nested/new.txt");
+        fs::write(&from, "content").unwrap();
+
+        let identity = rename_file(&from, &to, false).unwrap();
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "content");
+        assert_eq!(identity.size, 7);
+    }
+
+    #[test]
+    fn test_rename_refuses_to_overwrite() {
+        let dir = TempDir::new("ops_overwrite");
+        let from = dir.join("a.txt");
+        let to = dir.join("b.txt");
+        fs::write(&from, "a").unwrap();
+        fs::write(&to, "b").unwrap();
+
+        let err = rename_file(&from, &to, false).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(rename_file(&from, &to, true).is_ok());
+        assert_eq!(fs::read_to_string(&to).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_resolve_move_target() {
+        let dir = TempDir::new("ops_target");
+        let from = Path::new("src/main.rs");
+
+        assert_eq!(resolve_move_target(from, dir.path()), dir.join("main.rs"));
+        assert_eq!(
+            resolve_move_target(from, Path::new("lib/")),
+            Path::new("lib/main.rs")
+        );
+        assert_eq!(
+            resolve_move_target(from, Path::new("lib/other.rs")),
+            Path::new("lib/other.rs")
+        );
+    }
+}
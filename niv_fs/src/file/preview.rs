@@ -0,0 +1,186 @@
+//! Previews of files for picker and explorer panes: the first lines of a
+//! file, read and decoded at a bounded cost however large the file is.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+use super::eol::{EolType, LineScan, normalize_eol_text};
+use super::load::{complete_chars_len, complete_lines_len, decode, is_binary_content};
+use crate::bom::detect_bom;
+use crate::encoding::{DetectionConfig, Encoding, detect_encoding_heuristic};
+use crate::error::{FsError, FsResult, IoContext, IoOp};
+
+/// The start of a file, as [`preview_file`] read it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePreview {
+    /// The first lines, in UTF-8 with LF line endings; empty for binary files
+    pub text: String,
+    /// Encoding the text was decoded from
+    pub encoding: Encoding,
+    /// Line endings of the lines read
+    pub eol: EolType,
+    /// Size of the whole file in bytes
+    pub size: u64,
+    /// Last modification time, where the platform has one
+    pub modified: Option<SystemTime>,
+    /// Whether the file goes on past `text`
+    pub truncated: bool,
+    /// Whether the file looks binary rather than text
+    pub binary: bool,
+}
+
+/// Preview a file: its first `max_lines` lines, read from at most its
+/// first `max_bytes` bytes, with its size and modification time.
+///
+/// Only the bytes to show are read, so previews of huge files cost no more
+/// than of small ones. A line cut off by `max_bytes` is left out, unless it
+/// is the only one, which is then cut at a character boundary. Binary files
+/// give an empty text with `binary` set.
+pub fn preview_file<P: AsRef<Path>>(
+    path: P,
+    max_bytes: usize,
+    max_lines: usize,
+) -> FsResult<FilePreview> {
+    let path = path.as_ref();
+    let metadata = fs::metadata(path).io_context(IoOp::Metadata, path)?;
+    let mut preview = FilePreview {
+        text: String::new(),
+        encoding: Encoding::Utf8,
+        eol: EolType::Lf,
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        truncated: false,
+        binary: false,
+    };
+
+    let file = File::open(path).io_context(IoOp::Open, path)?;
+    let mut bytes = Vec::with_capacity(max_bytes.min(preview.size as usize));
+    file.take(max_bytes as u64)
+        .read_to_end(&mut bytes)
+        .io_context(IoOp::Read, path)?;
+    preview.truncated = (bytes.len() as u64) < preview.size;
+
+    let bom = detect_bom(&bytes);
+    let encoding = if bom.encoding != Encoding::Unknown {
+        bom.encoding
+    } else if is_binary_content(&bytes) {
+        return Ok(FilePreview {
+            binary: true,
+            encoding: Encoding::Unknown,
+            ..preview
+        });
+    } else {
+        match detect_encoding_heuristic(&bytes, DetectionConfig::default()) {
+            Ok(encoding) => encoding,
+            Err(FsError::Binary { .. }) => {
+                return Ok(FilePreview {
+                    binary: true,
+                    encoding: Encoding::Unknown,
+                    ..preview
+                });
+            }
+            Err(e) => return Err(e.with_path(path)),
+        }
+    };
+    preview.encoding = encoding;
+
+    let mut body = &bytes[bom.bom_length..];
+    if preview.truncated {
+        body = match complete_lines_len(body, encoding) {
+            0 => &body[..complete_chars_len(body, encoding)],
+            complete => &body[..complete],
+        };
+    }
+    let text = match decode(body, encoding) {
+        Ok(text) => text,
+        // Text whose sample looked like UTF-8 may not be past it
+        Err(_) if encoding == Encoding::Utf8 => String::from_utf8_lossy(body).into_owned(),
+        Err(e) => return Err(e.with_path(path)),
+    };
+
+    let mut scan = LineScan::default();
+    scan.feed(text.as_bytes());
+    preview.eol = scan.eol_type();
+    let mut text = normalize_eol_text(text, preview.eol);
+    let kept = if max_lines == 0 {
+        Some(0)
+    } else {
+        text.match_indices('\n')
+            .nth(max_lines - 1)
+            .map(|(end, _)| end + 1)
+    };
+    if let Some(kept) = kept.filter(|&kept| kept < text.len()) {
+        text.truncate(kept);
+        preview.truncated = true;
+    }
+    preview.text = text;
+    Ok(preview)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn temp_file(dir: &TempDir, name: &str, content: &[u8]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_preview_reads_only_the_start() {
+        let content: String = (0..100_000).map(|i| format!("line {}\r\n", i)).collect();
+        let dir = TempDir::new("preview");
+        let path = temp_file(&dir, "big", content.as_bytes());
+
+        let preview = preview_file(&path, 64 * 1024, 3).unwrap();
+        assert_eq!(preview.text, "line 0\nline 1\nline 2\n");
+        assert_eq!(preview.eol, EolType::Crlf);
+        assert_eq!(preview.size, content.len() as u64);
+        assert!(preview.truncated);
+        assert!(!preview.binary);
+
+        // Cut by bytes, the partial line is left out
+        let preview = preview_file(&path, 20, 100).unwrap();
+        assert_eq!(preview.text, "line 0\nline 1\n");
+        assert!(preview.truncated);
+    }
+
+    #[test]
+    fn test_preview_cuts_long_lines_between_chars() {
+        let dir = TempDir::new("preview");
+        let path = temp_file(&dir, "long", "ééééé".as_bytes());
+        let preview = preview_file(&path, 5, 10).unwrap();
+        assert_eq!(preview.text, "éé");
+        assert!(preview.truncated);
+
+        let whole = preview_file(&path, 1024, 10).unwrap();
+        assert_eq!(whole.text, "ééééé");
+        assert!(!whole.truncated);
+
+        // UTF-16 with a BOM, cut inside a surrogate pair
+        let mut utf16 = vec![0xFF, 0xFE];
+        for unit in "a😀b".encode_utf16() {
+            utf16.extend_from_slice(&unit.to_le_bytes());
+        }
+        let path = temp_file(&dir, "utf16", &utf16);
+        let preview = preview_file(&path, 6, 10).unwrap();
+        assert_eq!(preview.encoding, Encoding::Utf16Le);
+        assert_eq!(preview.text, "a");
+    }
+
+    #[test]
+    fn test_preview_binary_file() {
+        let dir = TempDir::new("preview");
+        let path = temp_file(&dir, "binary", &[0u8; 4096]);
+        let preview = preview_file(&path, 1024, 10).unwrap();
+        assert!(preview.binary);
+        assert!(preview.text.is_empty());
+        let _ = fs::remove_file(&path);
+
+        assert!(preview_file(path, 1024, 10).is_err());
+    }
+}
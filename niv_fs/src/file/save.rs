@@ -1,13 +1,19 @@
 //! Atomic file saving with transcoding and permission preservation.
 
+use std::borrow::Cow;
+use std::ffi::OsString;
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use super::dir::has_hidden_attribute;
 use super::eol::{EolType, restore_eol};
 use super::identity::FileIdentity;
+use super::ops::is_cross_device;
 use crate::bom::BomDetectionResult;
-use crate::encoding::Encoding;
+use crate::encoding::{Encoding, single_byte};
+use crate::error::{FsError, FsResult, IoContext, IoOp};
 
 /// Configuration for file saving operations
 #[derive(Debug, Clone)]
@@ -18,8 +24,44 @@ pub struct FileSaveConfig {
     pub atomic_writes: bool,
     /// Custom temp file suffix
     pub temp_suffix: String,
+    /// Directory to write the temp file of an atomic write in; None for the
+    /// directory of the file itself, which keeps the rename over the file
+    /// on one file system. A temp file on another one is copied next to the
+    /// file and synced before the rename.
+    pub temp_dir: Option<PathBuf>,
     /// Buffer size for streaming writes
     pub buffer_size: usize,
+    /// Whether to overwrite a read-only file, like `:w!`. Its read-only
+    /// attribute is cleared for the write and set again afterwards.
+    pub force: bool,
+    /// Whether to keep the modification and access times of the file being
+    /// overwritten, and on Windows its creation time
+    pub preserve_times: bool,
+    /// How many more times to try renaming the temp file over the original
+    /// while another process has it open, as virus scanners and indexers
+    /// briefly do on Windows
+    pub rename_retries: u32,
+    /// Wait before the first rename retry, doubled for each next one
+    pub rename_retry_delay: Duration,
+    /// What to do with characters the file's encoding has no bytes for
+    pub unencodable: UnencodablePolicy,
+    /// Whether a file with the Windows hidden attribute keeps it; without
+    /// it, the file an atomic write puts in its place is not hidden
+    pub preserve_hidden: bool,
+}
+
+/// How a save writes characters the encoding of the file cannot hold, as
+/// '€' in Latin-1. Each character not written as it is gets a warning in
+/// [`FileSaveResult::warnings`] saying where it was.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnencodablePolicy {
+    /// Fail the save, leaving the file as it was
+    #[default]
+    Fail,
+    /// Write `?` in place of the character
+    Substitute,
+    /// Write a numeric character reference such as `&#8364;`
+    CharRef,
 }
 
 impl Default for FileSaveConfig {
@@ -28,7 +70,14 @@ impl Default for FileSaveConfig {
             preserve_permissions: true,
             atomic_writes: true,
             temp_suffix: ".tmp".to_string(),
+            temp_dir: None,
             buffer_size: 64 * 1024, // 64KB
+            force: false,
+            preserve_times: false,
+            rename_retries: 5,
+            rename_retry_delay: Duration::from_millis(10),
+            unencodable: UnencodablePolicy::Fail,
+            preserve_hidden: true,
         }
     }
 }
@@ -110,7 +159,7 @@ pub fn save_file<P: AsRef<Path>>(
     path: P,
     content: &str,
     context: &SaveContext,
-) -> Result<FileSaveResult, crate::EncodingError> {
+) -> FsResult<FileSaveResult> {
     save_file_with_config(path, content, context, &FileSaveConfig::default())
 }
 
@@ -120,30 +169,153 @@ pub fn save_file_with_config<P: AsRef<Path>>(
     content: &str,
     context: &SaveContext,
     config: &FileSaveConfig,
-) -> Result<FileSaveResult, crate::EncodingError> {
+) -> FsResult<FileSaveResult> {
     let path = path.as_ref();
 
     // Prepare content for saving
-    let prepared_content = prepare_content_for_save(content, context)?;
+    let mut warnings = Vec::new();
+    let prepared_content =
+        prepare_content_for_save(content, context, config.unencodable, &mut warnings)
+            .map_err(|e| e.with_path(path))?;
+
+    // Paths past MAX_PATH need the `\\?\` prefix on Windows; errors still
+    // name the path as given
+    let target = long_path(path);
+    let original = fs::metadata(&target).ok();
+
+    // A forced write makes a read-only file writable for the save
+    let readonly = original
+        .as_ref()
+        .map(fs::Metadata::permissions)
+        .filter(|permissions| config.force && permissions.readonly());
+    if let Some(permissions) = &readonly {
+        fs::set_permissions(&target, writable(permissions.clone()))
+            .io_context(IoOp::SetPermissions, path)?;
+    }
+
+    let times = original
+        .as_ref()
+        .filter(|_| config.preserve_times)
+        .and_then(file_times);
+    let hidden = config.preserve_hidden && original.as_ref().is_some_and(has_hidden_attribute);
 
     // Perform atomic write
-    if config.atomic_writes {
-        save_atomic(path, &prepared_content, context, config)
+    let mut result = if config.atomic_writes {
+        save_atomic(path, &target, &prepared_content, times, hidden, config)
     } else {
-        save_direct(path, &prepared_content, context, config)
+        save_direct(path, &target, &prepared_content, times, hidden, config)
+    };
+
+    // Set the read-only attribute again, whether or not the save worked
+    if let Some(permissions) = readonly {
+        let restored =
+            fs::set_permissions(&target, permissions).io_context(IoOp::SetPermissions, path);
+        if let (Ok(_), Err(e)) = (&result, restored) {
+            result = Err(e.into());
+        }
     }
+    if let Ok(saved) = &mut result {
+        warnings.append(&mut saved.warnings);
+        saved.warnings = warnings;
+    }
+    match &result {
+        Ok(_) => crate::log!(Debug, "fs", "saved {}", path.display()),
+        Err(e) => crate::log!(Warn, "fs", "saving {} failed: {}", path.display(), e),
+    }
+    result
+}
+
+/// Save a range of lines (0-based, inclusive) to a file.
+///
+/// The partial content goes through the same EOL restoration and transcoding
+/// as a full save. Lines past the end of the content are ignored.
+pub fn save_line_range<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    first_line: usize,
+    last_line: usize,
+    context: &SaveContext,
+) -> FsResult<FileSaveResult> {
+    save_file(path, slice_lines(content, first_line, last_line), context)
+}
+
+/// Append content to a file, creating it if it does not exist.
+///
+/// The content is transcoded like a normal save. A BOM is only written when
+/// the file is new or empty, so appending never inserts one mid-file.
+pub fn append_to_file<P: AsRef<Path>>(
+    path: P,
+    content: &str,
+    context: &SaveContext,
+) -> FsResult<FileSaveResult> {
+    let path = path.as_ref();
+    let existing_len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+
+    let policy = UnencodablePolicy::Fail;
+    let prepared_content = if existing_len > 0 {
+        let mut without_bom = context.clone();
+        without_bom.original_bom.bom_length = 0;
+        prepare_content_for_save(content, &without_bom, policy, &mut Vec::new())
+    } else {
+        prepare_content_for_save(content, context, policy, &mut Vec::new())
+    }
+    .map_err(|e| e.with_path(path))?;
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .io_context(IoOp::Open, path)?;
+    file.write_all(&prepared_content)
+        .io_context(IoOp::Write, path)?;
+    file.sync_all().io_context(IoOp::Sync, path)?;
+
+    Ok(FileSaveResult {
+        path: path.to_path_buf(),
+        bytes_written: prepared_content.len() as u64,
+        atomic_write: false,
+        warnings: vec![],
+    })
+}
+
+/// Slice lines `first..=last` (0-based) out of LF-normalized content, keeping
+/// the newline that ends the last line.
+pub fn slice_lines(content: &str, first: usize, last: usize) -> &str {
+    let line_start = |line: usize| -> usize {
+        if line == 0 {
+            return 0;
+        }
+        content
+            .match_indices('\n')
+            .nth(line - 1)
+            .map(|(idx, _)| idx + 1)
+            .unwrap_or(content.len())
+    };
+
+    let start = line_start(first);
+    let end = line_start(last.saturating_add(1)).max(start);
+    &content[start..end]
 }
 
-/// Prepare content for saving by transcoding and restoring format.
+/// Prepare content for saving by transcoding and restoring format. Chars
+/// the encoding can't hold are handled by `policy`, warnings about them
+/// added to `warnings`.
 fn prepare_content_for_save(
     content: &str,
     context: &SaveContext,
-) -> Result<Vec<u8>, crate::EncodingError> {
+    policy: UnencodablePolicy,
+    warnings: &mut Vec<String>,
+) -> FsResult<Vec<u8>> {
     // First, restore original EOL type
     let content_with_eol = restore_eol(content.as_bytes(), context.original_eol);
 
     // Transcode to original encoding
-    let transcoded = transcode_to_encoding(&content_with_eol, context.original_encoding)?;
+    let transcoded = transcode_to_encoding(
+        &content_with_eol,
+        context.original_encoding,
+        policy,
+        warnings,
+    )?;
 
     // Add BOM if original had one
     let final_content = if context.original_bom.bom_length > 0 {
@@ -158,10 +330,12 @@ fn prepare_content_for_save(
 }
 
 /// Transcode UTF-8 content to the specified encoding.
-fn transcode_to_encoding(
+pub(super) fn transcode_to_encoding(
     content: &[u8],
     encoding: Encoding,
-) -> Result<Vec<u8>, crate::EncodingError> {
+    policy: UnencodablePolicy,
+    warnings: &mut Vec<String>,
+) -> FsResult<Vec<u8>> {
     match encoding {
         Encoding::Utf8 => Ok(content.to_vec()),
         Encoding::Utf16Le => encode_utf16le(content),
@@ -169,9 +343,9 @@ fn transcode_to_encoding(
         Encoding::Utf32Le => encode_utf32le(content),
         Encoding::Utf32Be => encode_utf32be(content),
         Encoding::Latin1 | Encoding::Windows1252 | Encoding::Latin9 => {
-            encode_latin(content, encoding)
+            encode_latin(content, encoding, policy, warnings)
         }
-        Encoding::Unknown => Err(crate::EncodingError::BinaryFile),
+        Encoding::Unknown => Err(FsError::encoding(encoding, "text cannot be written")),
     }
 }
 
@@ -187,47 +361,154 @@ fn get_bom_bytes(encoding: Encoding) -> Vec<u8> {
     }
 }
 
-/// Perform atomic save using temp file.
+/// Perform atomic save using temp file. `target` is `path` in the form
+/// to give to the OS.
 fn save_atomic(
     path: &Path,
+    target: &Path,
     content: &[u8],
-    _context: &SaveContext,
+    times: Option<fs::FileTimes>,
+    hidden: bool,
     config: &FileSaveConfig,
-) -> Result<FileSaveResult, crate::EncodingError> {
+) -> FsResult<FileSaveResult> {
+    let (temp_path, bytes_written) = write_temp_file(path, target, content, times, hidden, config)?;
+    move_temp_file(path, &temp_path, target, times, config)?;
+
+    Ok(FileSaveResult {
+        path: path.to_path_buf(),
+        bytes_written,
+        atomic_write: true,
+        warnings: vec![],
+    })
+}
+
+/// Write the temp file of an atomic save of `target`, with the permissions
+/// of the file it is to replace. Returns its path and the bytes written.
+fn write_temp_file(
+    path: &Path,
+    target: &Path,
+    content: &[u8],
+    times: Option<fs::FileTimes>,
+    hidden: bool,
+    config: &FileSaveConfig,
+) -> FsResult<(PathBuf, u64)> {
     // Create temp file path
-    let temp_path = get_temp_path(path, &config.temp_suffix);
+    let temp_path = temp_path_for(target, config);
 
-    // Write to temp file first
-    let bytes_written = write_to_file(&temp_path, content, config)?;
+    // Write to temp file first, reporting failures as writing the file itself
+    let bytes_written = write_to_file(&temp_path, content, times, hidden, config)
+        .map_err(|e| as_write_of(path, e))?;
 
     // Preserve permissions from original file if it exists
-    if config.preserve_permissions && path.exists() {
-        preserve_permissions(path, &temp_path)?;
+    if config.preserve_permissions && target.exists() {
+        preserve_permissions(target, &temp_path)?;
     }
+    Ok((temp_path, bytes_written))
+}
 
-    // Atomically move temp file to final location
-    fs::rename(&temp_path, path).map_err(|e| {
+/// Move the temp file written by [`write_temp_file`] over `target`
+fn move_temp_file(
+    path: &Path,
+    temp_path: &Path,
+    target: &Path,
+    times: Option<fs::FileTimes>,
+    config: &FileSaveConfig,
+) -> FsResult<()> {
+    // Atomically move temp file to final location; one on another file
+    // system, known beforehand or found by the rename, is copied over first
+    let moved = if on_same_device(temp_path, target) {
+        match retry_while_shared(config, || fs::rename(temp_path, target)) {
+            Err(err) if is_cross_device(&err) => copy_and_rename(temp_path, target, times, config),
+            moved => moved,
+        }
+    } else {
+        copy_and_rename(temp_path, target, times, config)
+    };
+    moved.map_err(|e| {
         // If rename fails, try to clean up temp file
-        let _ = fs::remove_file(&temp_path);
-        crate::EncodingError::Io(e)
-    })?;
-
-    Ok(FileSaveResult {
-        path: path.to_path_buf(),
-        bytes_written,
-        atomic_write: true,
-        warnings: vec![],
+        let _ = fs::remove_file(temp_path);
+        FsError::io(IoOp::Rename, path, e)
     })
 }
 
+/// A save whose content is in its temp file, waiting to be moved over the
+/// file. Saves of several files are staged first so that one that cannot
+/// be written leaves all the files as they were.
+#[derive(Debug)]
+pub(super) struct StagedSave {
+    path: PathBuf,
+    temp_path: PathBuf,
+    times: Option<fs::FileTimes>,
+    bytes_written: u64,
+    warnings: Vec<String>,
+}
+
+impl StagedSave {
+    /// Transcode `content` for `path` and write it to the temp file. The
+    /// read-only attribute is left alone whatever `config.force` says.
+    pub(super) fn stage(
+        path: &Path,
+        content: &str,
+        context: &SaveContext,
+        config: &FileSaveConfig,
+    ) -> FsResult<Self> {
+        let mut warnings = Vec::new();
+        let prepared_content =
+            prepare_content_for_save(content, context, config.unencodable, &mut warnings)
+                .map_err(|e| e.with_path(path))?;
+        let target = long_path(path);
+        let original = fs::metadata(&target).ok();
+        let times = original
+            .as_ref()
+            .filter(|_| config.preserve_times)
+            .and_then(file_times);
+        let hidden = config.preserve_hidden && original.as_ref().is_some_and(has_hidden_attribute);
+        let (temp_path, bytes_written) =
+            write_temp_file(path, &target, &prepared_content, times, hidden, config)?;
+        Ok(StagedSave {
+            path: path.to_path_buf(),
+            temp_path,
+            times,
+            bytes_written,
+            warnings,
+        })
+    }
+
+    /// File the save is for
+    pub(super) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Move the temp file over the file
+    pub(super) fn commit(self, config: &FileSaveConfig) -> FsResult<FileSaveResult> {
+        let target = long_path(&self.path);
+        move_temp_file(&self.path, &self.temp_path, &target, self.times, config)?;
+        crate::log!(Debug, "fs", "saved {}", self.path.display());
+        Ok(FileSaveResult {
+            path: self.path,
+            bytes_written: self.bytes_written,
+            atomic_write: true,
+            warnings: self.warnings,
+        })
+    }
+
+    /// Remove the temp file, leaving the file as it was
+    pub(super) fn discard(self) {
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
 /// Perform direct save (non-atomic).
 fn save_direct(
     path: &Path,
+    target: &Path,
     content: &[u8],
-    _context: &SaveContext,
+    times: Option<fs::FileTimes>,
+    hidden: bool,
     config: &FileSaveConfig,
-) -> Result<FileSaveResult, crate::EncodingError> {
-    let bytes_written = write_to_file(path, content, config)?;
+) -> FsResult<FileSaveResult> {
+    let bytes_written =
+        write_to_file(target, content, times, hidden, config).map_err(|e| as_write_of(path, e))?;
 
     Ok(FileSaveResult {
         path: path.to_path_buf(),
@@ -237,13 +518,25 @@ fn save_direct(
     })
 }
 
-/// Write content to a file with buffering.
+/// Report an I/O error of a write to `path` through a temp file or a
+/// `\\?\` path as writing `path` itself
+fn as_write_of(path: &Path, err: FsError) -> FsError {
+    match err {
+        FsError::Io { source, .. } => FsError::io(IoOp::Write, path, source),
+        err => err,
+    }
+}
+
+/// Write content to a file with buffering, and then set its `times`. A
+/// `hidden` file is created with the Windows hidden attribute.
 fn write_to_file(
     path: &Path,
     content: &[u8],
+    times: Option<fs::FileTimes>,
+    hidden: bool,
     config: &FileSaveConfig,
-) -> Result<u64, crate::EncodingError> {
-    let file = File::create(path).map_err(crate::EncodingError::Io)?;
+) -> FsResult<u64> {
+    let file = create_file(path, hidden).io_context(IoOp::Create, path)?;
 
     // Use buffered writing for better performance
     let mut writer = io::BufWriter::with_capacity(config.buffer_size, file);
@@ -253,20 +546,112 @@ fn write_to_file(
     let chunk_size = config.buffer_size;
 
     for chunk in content.chunks(chunk_size) {
-        writer.write_all(chunk).map_err(crate::EncodingError::Io)?;
+        writer.write_all(chunk).io_context(IoOp::Write, path)?;
         bytes_written += chunk.len() as u64;
     }
 
     // Ensure all data is flushed to disk
-    writer.flush().map_err(crate::EncodingError::Io)?;
-    writer
-        .get_mut()
-        .sync_all()
-        .map_err(crate::EncodingError::Io)?;
+    writer.flush().io_context(IoOp::Write, path)?;
+    if let Some(times) = times {
+        writer
+            .get_mut()
+            .set_times(times)
+            .io_context(IoOp::SetTimes, path)?;
+    }
+    writer.get_mut().sync_all().io_context(IoOp::Sync, path)?;
 
     Ok(bytes_written)
 }
 
+/// Create or truncate `path`. Windows refuses to truncate a hidden file
+/// unless it is opened as one, which also hides a file it creates.
+fn create_file(path: &Path, hidden: bool) -> io::Result<File> {
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        if hidden {
+            // FILE_ATTRIBUTE_HIDDEN
+            options.attributes(0x2);
+        }
+    }
+    #[cfg(not(windows))]
+    let _ = hidden;
+    options.open(path)
+}
+
+/// Path of the temp file an atomic write of `target` goes through: next to
+/// it, or in `config.temp_dir` under a name that tells apart files of the
+/// same name in different directories
+fn temp_path_for(target: &Path, config: &FileSaveConfig) -> PathBuf {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let Some(dir) = &config.temp_dir else {
+        return get_temp_path(target, &config.temp_suffix);
+    };
+    let mut hasher = DefaultHasher::new();
+    target.hash(&mut hasher);
+    let name = target.file_name().unwrap_or_else(|| "file".as_ref());
+    let mut unique = OsString::from(format!("{:016x}-", hasher.finish()));
+    unique.push(name);
+    get_temp_path(&dir.join(unique), &config.temp_suffix)
+}
+
+/// Whether `temp` is on the file system of the directory `target` is in,
+/// as far as the platform tells; where it doesn't, the rename finds out
+fn on_same_device(temp: &Path, target: &Path) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = match target.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        match (fs::metadata(temp), fs::metadata(dir)) {
+            (Ok(temp), Ok(dir)) => temp.dev() == dir.dev(),
+            _ => true,
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (temp, target);
+        true
+    }
+}
+
+/// Move `temp` over `target` from another file system: copy it next to
+/// `target`, sync the copy and rename it over, so the file is still
+/// replaced in one step
+fn copy_and_rename(
+    temp: &Path,
+    target: &Path,
+    times: Option<fs::FileTimes>,
+    config: &FileSaveConfig,
+) -> io::Result<()> {
+    let local = get_temp_path(target, &config.temp_suffix);
+    let moved = (|| {
+        // fs::copy also copies the permissions set on the temp file
+        fs::copy(temp, &local)?;
+        let copy = File::options().write(true).open(&local)?;
+        if let Some(times) = times {
+            copy.set_times(times)?;
+        }
+        copy.sync_all()?;
+        retry_while_shared(config, || fs::rename(&local, target))
+    })();
+    match moved {
+        Ok(()) => {
+            let _ = fs::remove_file(temp);
+        }
+        Err(_) => {
+            let _ = fs::remove_file(&local);
+        }
+    }
+    moved
+}
+
 /// Generate temp file path.
 fn get_temp_path(original_path: &Path, suffix: &str) -> PathBuf {
     let mut temp_path = original_path.to_path_buf();
@@ -280,26 +665,144 @@ fn get_temp_path(original_path: &Path, suffix: &str) -> PathBuf {
     temp_path
 }
 
+/// Remove the temp file that a failed atomic save of `path` left behind,
+/// as one cut short by a full disk. Returns whether there was one.
+pub(super) fn remove_temp_file(path: &Path, config: &FileSaveConfig) -> bool {
+    fs::remove_file(temp_path_for(&long_path(path), config)).is_ok()
+}
+
 /// Preserve file permissions from source to target.
 #[cfg(unix)]
-fn preserve_permissions(source: &Path, target: &Path) -> Result<(), crate::EncodingError> {
+fn preserve_permissions(source: &Path, target: &Path) -> FsResult<()> {
     use std::os::unix::fs::PermissionsExt;
 
-    let metadata = fs::metadata(source).map_err(crate::EncodingError::Io)?;
+    let metadata = fs::metadata(source).io_context(IoOp::Metadata, source)?;
     let permissions = metadata.permissions();
 
-    fs::set_permissions(target, permissions).map_err(crate::EncodingError::Io)
+    fs::set_permissions(target, permissions).io_context(IoOp::SetPermissions, target)?;
+    Ok(())
 }
 
 #[cfg(not(unix))]
-fn preserve_permissions(_source: &Path, _target: &Path) -> Result<(), crate::EncodingError> {
+fn preserve_permissions(_source: &Path, _target: &Path) -> FsResult<()> {
     // Windows permission preservation would require additional dependencies
     Ok(())
 }
 
+/// `permissions` that let the owner write the file
+fn writable(mut permissions: fs::Permissions) -> fs::Permissions {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+    // Clears the read-only attribute; only on Unix would it make the file
+    // writable by everyone
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    {
+        permissions.set_readonly(false);
+    }
+    permissions
+}
+
+/// Times of a file to give the file that replaces it
+fn file_times(metadata: &fs::Metadata) -> Option<fs::FileTimes> {
+    let mut times = fs::FileTimes::new().set_modified(metadata.modified().ok()?);
+    if let Ok(accessed) = metadata.accessed() {
+        times = times.set_accessed(accessed);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTimesExt;
+        if let Ok(created) = metadata.created() {
+            times = times.set_created(created);
+        }
+    }
+    Some(times)
+}
+
+/// Run `op` again, with a doubling wait in between, for as long as it fails
+/// because another process has the file open without sharing it and
+/// `config.rename_retries` allows
+fn retry_while_shared<T>(
+    config: &FileSaveConfig,
+    mut op: impl FnMut() -> io::Result<T>,
+) -> io::Result<T> {
+    let mut delay = config.rename_retry_delay;
+    for _ in 0..config.rename_retries {
+        match op() {
+            Err(err) if is_sharing_violation(&err) => {
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+            }
+            result => return result,
+        }
+    }
+    op()
+}
+
+/// Check whether an error is a sharing or lock violation, which only Windows
+/// has: another process has the file open or locked
+pub(super) fn is_sharing_violation(err: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION
+    #[cfg(windows)]
+    const SHARING_VIOLATIONS: &[i32] = &[32, 33];
+    #[cfg(not(windows))]
+    const SHARING_VIOLATIONS: &[i32] = &[];
+
+    err.raw_os_error()
+        .is_some_and(|code| SHARING_VIOLATIONS.contains(&code))
+}
+
+/// `path` with the `\\?\` prefix when it is too long for the Windows API
+/// without one, MAX_PATH (260) characters. Relative paths and those with
+/// `..` are kept, as the prefix turns off their resolution.
+#[cfg(windows)]
+fn long_path(path: &Path) -> Cow<'_, Path> {
+    use std::path::{Component, Prefix};
+
+    const MAX_PATH: usize = 260;
+    if path.as_os_str().len() < MAX_PATH {
+        return Cow::Borrowed(path);
+    }
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return Cow::Borrowed(path);
+    };
+    let mut long = OsString::from(r"\\?\");
+    match prefix.kind() {
+        Prefix::Disk(_) => long.push(prefix.as_os_str()),
+        Prefix::UNC(server, share) => {
+            long.push(r"UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+        }
+        // Already verbatim, or a device
+        _ => return Cow::Borrowed(path),
+    }
+    for component in components {
+        match component {
+            Component::Normal(name) => {
+                long.push(r"\");
+                long.push(name);
+            }
+            Component::ParentDir => return Cow::Borrowed(path),
+            Component::Prefix(_) | Component::RootDir | Component::CurDir => {}
+        }
+    }
+    Cow::Owned(PathBuf::from(long))
+}
+
+#[cfg(not(windows))]
+fn long_path(path: &Path) -> Cow<'_, Path> {
+    Cow::Borrowed(path)
+}
+
 /// Encode UTF-8 content to UTF-16LE.
-fn encode_utf16le(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
-    let utf8_str = std::str::from_utf8(content).map_err(|_| crate::EncodingError::BinaryFile)?;
+fn encode_utf16le(content: &[u8]) -> FsResult<Vec<u8>> {
+    let utf8_str = std::str::from_utf8(content).map_err(|_| not_utf8())?;
 
     let mut result = Vec::new();
     for code_unit in utf8_str.encode_utf16() {
@@ -309,8 +812,8 @@ fn encode_utf16le(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
 }
 
 /// Encode UTF-8 content to UTF-16BE.
-fn encode_utf16be(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
-    let utf8_str = std::str::from_utf8(content).map_err(|_| crate::EncodingError::BinaryFile)?;
+fn encode_utf16be(content: &[u8]) -> FsResult<Vec<u8>> {
+    let utf8_str = std::str::from_utf8(content).map_err(|_| not_utf8())?;
 
     let mut result = Vec::new();
     for code_unit in utf8_str.encode_utf16() {
@@ -320,8 +823,8 @@ fn encode_utf16be(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
 }
 
 /// Encode UTF-8 content to UTF-32LE.
-fn encode_utf32le(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
-    let utf8_str = std::str::from_utf8(content).map_err(|_| crate::EncodingError::BinaryFile)?;
+fn encode_utf32le(content: &[u8]) -> FsResult<Vec<u8>> {
+    let utf8_str = std::str::from_utf8(content).map_err(|_| not_utf8())?;
 
     let mut result = Vec::new();
     for ch in utf8_str.chars() {
@@ -331,8 +834,8 @@ fn encode_utf32le(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
 }
 
 /// Encode UTF-8 content to UTF-32BE.
-fn encode_utf32be(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
-    let utf8_str = std::str::from_utf8(content).map_err(|_| crate::EncodingError::BinaryFile)?;
+fn encode_utf32be(content: &[u8]) -> FsResult<Vec<u8>> {
+    let utf8_str = std::str::from_utf8(content).map_err(|_| not_utf8())?;
 
     let mut result = Vec::new();
     for ch in utf8_str.chars() {
@@ -341,85 +844,71 @@ fn encode_utf32be(content: &[u8]) -> Result<Vec<u8>, crate::EncodingError> {
     Ok(result)
 }
 
-/// Encode UTF-8 content to Latin encoding.
-fn encode_latin(content: &[u8], encoding: Encoding) -> Result<Vec<u8>, crate::EncodingError> {
-    let utf8_str = std::str::from_utf8(content).map_err(|_| crate::EncodingError::BinaryFile)?;
+/// Encode UTF-8 content to Latin encoding. Chars it has no byte for are
+/// handled by `policy`, each one not written as it is warned about with its
+/// 1-based line and column, the column counted in chars.
+fn encode_latin(
+    content: &[u8],
+    encoding: Encoding,
+    policy: UnencodablePolicy,
+    warnings: &mut Vec<String>,
+) -> FsResult<Vec<u8>> {
+    let utf8_str = std::str::from_utf8(content).map_err(|_| not_utf8())?;
 
-    let mut result = Vec::new();
+    let mut result = Vec::with_capacity(utf8_str.len());
+    let (mut line, mut column) = (1, 0);
     for ch in utf8_str.chars() {
-        let byte = match encoding {
-            Encoding::Latin1 => char_to_latin1(ch)?,
-            Encoding::Windows1252 => char_to_windows1252(ch)?,
-            Encoding::Latin9 => char_to_latin9(ch)?,
-            _ => unreachable!(),
+        column += 1;
+        let byte = single_byte::encode_char(ch, encoding);
+        if ch == '\n' {
+            line += 1;
+            column = 0;
+        }
+        if let Some(byte) = byte {
+            result.push(byte);
+            continue;
+        }
+        let written = match policy {
+            UnencodablePolicy::Fail => {
+                return Err(FsError::encoding(
+                    encoding,
+                    format!(
+                        "{} at line {}, column {} cannot be written",
+                        describe_char(ch),
+                        line,
+                        column
+                    ),
+                ));
+            }
+            UnencodablePolicy::Substitute => "?".to_string(),
+            UnencodablePolicy::CharRef => format!("&#{};", ch as u32),
         };
-        result.push(byte);
+        warnings.push(format!(
+            "line {}, column {}: {} written as {}",
+            line,
+            column,
+            describe_char(ch),
+            written
+        ));
+        result.extend_from_slice(written.as_bytes());
     }
     Ok(result)
 }
 
-/// Convert Unicode character to Latin-1 byte.
-fn char_to_latin1(ch: char) -> Result<u8, crate::EncodingError> {
-    if ch as u32 <= 0xFF {
-        Ok(ch as u8)
-    } else {
-        Err(crate::EncodingError::BinaryFile) // Character cannot be represented
-    }
-}
-
-/// Convert Unicode character to Windows-1252 byte.
-fn char_to_windows1252(ch: char) -> Result<u8, crate::EncodingError> {
-    match ch {
-        '€' => Ok(0x80),
-        '‚' => Ok(0x82),
-        'ƒ' => Ok(0x83),
-        '„' => Ok(0x84),
-        '…' => Ok(0x85),
-        '†' => Ok(0x86),
-        '‡' => Ok(0x87),
-        'ˆ' => Ok(0x88),
-        '‰' => Ok(0x89),
-        'Š' => Ok(0x8A),
-        '‹' => Ok(0x8B),
-        'Œ' => Ok(0x8C),
-        'Ž' => Ok(0x8E),
-        '‘' => Ok(0x91),
-        '’' => Ok(0x92),
-        '"' => Ok(0x93), // Double quotation mark
-        '•' => Ok(0x95),
-        '–' => Ok(0x96),
-        '—' => Ok(0x97),
-        '˜' => Ok(0x98),
-        '™' => Ok(0x99),
-        'š' => Ok(0x9A),
-        '›' => Ok(0x9B),
-        'œ' => Ok(0x9C),
-        'ž' => Ok(0x9E),
-        'Ÿ' => Ok(0x9F),
-        ch if ch as u32 <= 0xFF => Ok(ch as u8),
-        _ => Err(crate::EncodingError::BinaryFile),
-    }
-}
-
-/// Convert Unicode character to Latin-9 byte.
-fn char_to_latin9(ch: char) -> Result<u8, crate::EncodingError> {
-    match ch {
-        '€' => Ok(0xA4), // Euro sign
-        'Š' => Ok(0xA6), // Latin capital S with caron
-        'š' => Ok(0xA8), // Latin small s with caron
-        'Ž' => Ok(0xB4), // Latin capital Z with caron
-        'ž' => Ok(0xB8), // Latin small z with caron
-        'Œ' => Ok(0xBC), // Latin capital OE ligature
-        'œ' => Ok(0xBD), // Latin small oe ligature
-        'Ÿ' => Ok(0xBE), // Latin capital Y with diaeresis
-        ch if ch as u32 <= 0xFF => Ok(ch as u8),
-        _ => Err(crate::EncodingError::BinaryFile),
-    }
+/// Error for content to transcode that is not UTF-8
+fn not_utf8() -> FsError {
+    FsError::encoding(Encoding::Utf8, "content is not valid UTF-8")
+}
+
+/// A character as it is named in messages, e.g. `'€' (U+20AC)`
+fn describe_char(ch: char) -> String {
+    format!("{:?} (U+{:04X})", ch, ch as u32)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TempDir;
     use std::env;
 
     fn create_temp_file(content: &str) -> std::path::PathBuf {
@@ -516,7 +1005,13 @@ mod tests {
     #[test]
     fn test_transcode_to_utf16le() {
         let content = "Hello! 🌍";
-        let transcoded = transcode_to_encoding(content.as_bytes(), Encoding::Utf16Le).unwrap();
+        let transcoded = transcode_to_encoding(
+            content.as_bytes(),
+            Encoding::Utf16Le,
+            UnencodablePolicy::Fail,
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         // Should be even number of bytes (UTF-16)
         assert_eq!(transcoded.len() % 2, 0);
@@ -528,13 +1023,270 @@ mod tests {
     #[test]
     fn test_encode_latin1() {
         let content = "Hello, ©®";
-        let encoded = encode_latin(content.as_bytes(), Encoding::Latin1).unwrap();
+        let encoded = encode_latin(
+            content.as_bytes(),
+            Encoding::Latin1,
+            UnencodablePolicy::Fail,
+            &mut Vec::new(),
+        )
+        .unwrap();
 
         assert_eq!(encoded.len(), content.chars().count());
         assert_eq!(encoded[7], 0xA9); // © in Latin-1
         assert_eq!(encoded[8], 0xAE); // ® in Latin-1
     }
 
+    #[test]
+    fn test_save_unencodable_character() {
+        let temp_file = create_temp_file("old");
+        let context = SaveContext {
+            original_encoding: Encoding::Latin1,
+            ..SaveContext::new()
+        };
+
+        let err = save_file(&temp_file, "price: 5€", &context).unwrap_err();
+        assert!(matches!(
+            err,
+            FsError::Encoding {
+                encoding: Encoding::Latin1,
+                ..
+            }
+        ));
+        assert_eq!(err.path(), Some(temp_file.as_path()));
+        assert!(
+            err.to_string()
+                .contains("'€' (U+20AC) at line 1, column 9 cannot be written")
+        );
+        // The file is left as it was
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "old");
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_save_lossy_characters_with_warnings() {
+        let temp_file = create_temp_file("old");
+        let context = SaveContext {
+            original_encoding: Encoding::Windows1252,
+            original_eol: EolType::Crlf,
+            ..SaveContext::new()
+        };
+        let content = "caf\u{e9} \u{2192} ok\n\u{2713}\u{1F600}\n";
+
+        let config = FileSaveConfig {
+            unencodable: UnencodablePolicy::Substitute,
+            ..FileSaveConfig::default()
+        };
+        let result = save_file_with_config(&temp_file, content, &context, &config).unwrap();
+        assert_eq!(
+            std::fs::read(&temp_file).unwrap(),
+            b"caf\xe9 ? ok\r\n??\r\n"
+        );
+        assert_eq!(
+            result.warnings,
+            [
+                "line 1, column 6: '→' (U+2192) written as ?",
+                "line 2, column 1: '✓' (U+2713) written as ?",
+                "line 2, column 2: '😀' (U+1F600) written as ?",
+            ]
+        );
+
+        let config = FileSaveConfig {
+            unencodable: UnencodablePolicy::CharRef,
+            ..FileSaveConfig::default()
+        };
+        let result = save_file_with_config(&temp_file, content, &context, &config).unwrap();
+        assert_eq!(
+            std::fs::read(&temp_file).unwrap(),
+            b"caf\xe9 &#8594; ok\r\n&#10003;&#128512;\r\n"
+        );
+        assert_eq!(result.warnings.len(), 3);
+        assert!(result.warnings[0].ends_with("written as &#8594;"));
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_save_line_range() {
+        let temp_file = create_temp_file("");
+        let content = "one\ntwo\nthree\nfour";
+
+        save_line_range(&temp_file, content, 1, 2, &SaveContext::new()).unwrap();
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "two\nthree\n");
+
+        save_line_range(&temp_file, content, 2, 10, &SaveContext::new()).unwrap();
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "three\nfour");
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_append_to_file_skips_bom_mid_file() {
+        let temp_file = create_temp_file("");
+        let mut context = SaveContext::new();
+        context.original_bom = BomDetectionResult {
+            encoding: Encoding::Utf8,
+            bom_length: 3,
+        };
+
+        append_to_file(&temp_file, "first\n", &context).unwrap();
+        append_to_file(&temp_file, "second\n", &context).unwrap();
+
+        let saved_bytes = std::fs::read(&temp_file).unwrap();
+        assert_eq!(&saved_bytes[0..3], &[0xEF, 0xBB, 0xBF]);
+        assert_eq!(&saved_bytes[3..], b"first\nsecond\n");
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[test]
+    fn test_forced_save_keeps_file_read_only() {
+        for atomic_writes in [true, false] {
+            let temp_file = create_temp_file("old");
+            let mut permissions = std::fs::metadata(&temp_file).unwrap().permissions();
+            permissions.set_readonly(true);
+            std::fs::set_permissions(&temp_file, permissions).unwrap();
+
+            let config = FileSaveConfig {
+                force: true,
+                atomic_writes,
+                ..FileSaveConfig::default()
+            };
+            save_file_with_config(&temp_file, "new", &SaveContext::new(), &config).unwrap();
+            assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "new");
+            let metadata = std::fs::metadata(&temp_file).unwrap();
+            assert!(metadata.permissions().readonly());
+
+            std::fs::set_permissions(&temp_file, writable(metadata.permissions())).unwrap();
+            cleanup_temp_file(&temp_file);
+        }
+    }
+
+    #[test]
+    fn test_save_preserves_times() {
+        let temp_file = create_temp_file("old");
+        let old = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options()
+            .write(true)
+            .open(&temp_file)
+            .unwrap()
+            .set_times(fs::FileTimes::new().set_modified(old).set_accessed(old))
+            .unwrap();
+
+        let config = FileSaveConfig {
+            preserve_times: true,
+            ..FileSaveConfig::default()
+        };
+        save_file_with_config(&temp_file, "new", &SaveContext::new(), &config).unwrap();
+        assert_eq!(std::fs::read_to_string(&temp_file).unwrap(), "new");
+        assert_eq!(
+            std::fs::metadata(&temp_file).unwrap().modified().unwrap(),
+            old
+        );
+
+        // By default the save is the file's last modification
+        save_file(&temp_file, "newer", &SaveContext::new()).unwrap();
+        assert!(std::fs::metadata(&temp_file).unwrap().modified().unwrap() > old);
+
+        cleanup_temp_file(&temp_file);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_long_path_prefix() {
+        let name = "a".repeat(200);
+        let long = format!(r"C:\Users\{}\{}.txt", name, name);
+        assert_eq!(
+            long_path(Path::new(&long)),
+            Path::new(&format!(r"\\?\{}", long))
+        );
+
+        let unc = format!(r"\\server\share\.\{}\{}.txt", name, name);
+        assert_eq!(
+            long_path(Path::new(&unc)),
+            Path::new(&format!(r"\\?\UNC\server\share\{}\{}.txt", name, name))
+        );
+
+        // Short, relative, already verbatim and `..` paths are kept
+        for path in [
+            r"C:\Users\notes.txt".to_string(),
+            format!(r"{}\{}.txt", name, name),
+            format!(r"\\?\C:\{}\{}.txt", name, name),
+            format!(r"C:\{}\..\{}.txt", name, name),
+        ] {
+            assert_eq!(long_path(Path::new(&path)), Path::new(&path));
+        }
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_rename_retries_sharing_violations() {
+        let config = FileSaveConfig {
+            rename_retries: 3,
+            rename_retry_delay: Duration::from_millis(1),
+            ..FileSaveConfig::default()
+        };
+
+        // Succeeds once the other process lets go of the file
+        let mut attempts = 0;
+        let result = retry_while_shared(&config, || {
+            attempts += 1;
+            match attempts {
+                1 | 2 => Err(io::Error::from_raw_os_error(32)),
+                _ => Ok(()),
+            }
+        });
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+
+        // Gives up after the retries
+        let mut attempts = 0;
+        let result: io::Result<()> = retry_while_shared(&config, || {
+            attempts += 1;
+            Err(io::Error::from_raw_os_error(33))
+        });
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(33));
+        assert_eq!(attempts, 4);
+
+        // Other errors aren't retried
+        let mut attempts = 0;
+        let result: io::Result<()> = retry_while_shared(&config, || {
+            attempts += 1;
+            Err(io::Error::from_raw_os_error(5))
+        });
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_save_through_temp_dir() {
+        let temp_file = create_temp_file("old");
+        let temp_dir = TempDir::new("save_tmp");
+        let config = FileSaveConfig {
+            temp_dir: Some(temp_dir.path().to_path_buf()),
+            ..FileSaveConfig::default()
+        };
+
+        // Files of the same name get temp files of their own
+        let other = Path::new("elsewhere").join(temp_file.file_name().unwrap());
+        let temp = temp_path_for(&temp_file, &config);
+        assert_eq!(temp.parent(), Some(temp_dir.path()));
+        assert_ne!(temp, temp_path_for(&other, &config));
+
+        save_file_with_config(&temp_file, "new", &SaveContext::new(), &config).unwrap();
+        assert_eq!(fs::read_to_string(&temp_file).unwrap(), "new");
+        assert!(!temp.exists());
+
+        // As from another file system: copied next to the file, then renamed
+        fs::write(&temp, "copied").unwrap();
+        copy_and_rename(&temp, &temp_file, None, &config).unwrap();
+        assert_eq!(fs::read_to_string(&temp_file).unwrap(), "copied");
+        assert!(!temp.exists());
+        assert!(!get_temp_path(&temp_file, &config.temp_suffix).exists());
+
+        cleanup_temp_file(&temp_file);
+    }
+
     #[test]
     fn test_get_temp_path() {
         let original = Path::new("/path/to/file.txt");
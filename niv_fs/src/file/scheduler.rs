@@ -0,0 +1,406 @@
+//! Saves queued per path, so that autosave, format-on-save and `:w` never
+//! write the same file at once, with the failures that pass on their own
+//! retried after a wait.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::identity::path_key;
+use super::save::{
+    FileSaveConfig, FileSaveResult, SaveContext, is_sharing_violation, remove_temp_file,
+    save_file_with_config,
+};
+use crate::error::{FsError, FsResult};
+
+/// Configuration of a [`SaveScheduler`]
+#[derive(Debug, Clone)]
+pub struct SaveSchedulerConfig {
+    /// How many more times to try a save that failed transiently: on a busy
+    /// file, a sharing violation or a full disk
+    pub retries: u32,
+    /// Wait before the first retry, doubled for each next one
+    pub retry_delay: Duration,
+    /// Longest wait between two tries
+    pub max_retry_delay: Duration,
+}
+
+impl Default for SaveSchedulerConfig {
+    fn default() -> Self {
+        SaveSchedulerConfig {
+            retries: 5,
+            retry_delay: Duration::from_millis(50),
+            max_retry_delay: Duration::from_secs(2),
+        }
+    }
+}
+
+/// A scheduled save that failed for good, as passed to the callback set
+/// with [`SaveScheduler::on_failure`]
+#[derive(Debug)]
+pub struct SaveFailure {
+    /// File that was being saved
+    pub path: PathBuf,
+    /// Error of the last try
+    pub error: FsError,
+    /// How many times the save was tried
+    pub attempts: u32,
+}
+
+type SaveFn = fn(&Path, &str, &SaveContext, &FileSaveConfig) -> FsResult<FileSaveResult>;
+type FailureCallback = Box<dyn Fn(SaveFailure) + Send + Sync>;
+
+struct SaveJob {
+    path: PathBuf,
+    content: String,
+    context: SaveContext,
+    config: FileSaveConfig,
+}
+
+/// Saves of one path: the one under way and the one to run after it
+#[derive(Default)]
+struct PathQueue {
+    busy: bool,
+    pending: Option<SaveJob>,
+}
+
+/// What the save threads share with their [`SaveScheduler`]
+struct SchedulerState {
+    config: SaveSchedulerConfig,
+    save: SaveFn,
+    /// Queues by [`path_key`], kept while a save of the path is under way
+    queues: Mutex<HashMap<PathBuf, PathQueue>>,
+    /// Notified whenever a path has no save under way any more
+    idle: Condvar,
+    on_failure: Mutex<Option<FailureCallback>>,
+}
+
+/// Runs saves one at a time per path, in the background or on the calling
+/// thread.
+///
+/// A save waits for the one of the same path under way to finish. A save
+/// scheduled while another is still waiting replaces it, as it has the
+/// newer text, so the last save asked for is the one that ends up on disk.
+pub struct SaveScheduler {
+    state: Arc<SchedulerState>,
+}
+
+impl SaveScheduler {
+    pub fn new(config: SaveSchedulerConfig) -> Self {
+        Self::with_save(config, |path, content, context, config| {
+            save_file_with_config(path, content, context, config)
+        })
+    }
+
+    fn with_save(config: SaveSchedulerConfig, save: SaveFn) -> Self {
+        SaveScheduler {
+            state: Arc::new(SchedulerState {
+                config,
+                save,
+                queues: Mutex::new(HashMap::new()),
+                idle: Condvar::new(),
+                on_failure: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Call `callback` from the save thread for each scheduled save that
+    /// fails for good, replacing the callback set before
+    pub fn on_failure<F>(&self, callback: F)
+    where
+        F: Fn(SaveFailure) + Send + Sync + 'static,
+    {
+        *self.state.on_failure.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    /// Save `content` to `path` in the background, once the save of the
+    /// path under way has finished. A save of the path still waiting is
+    /// dropped for this one.
+    pub fn schedule(
+        &self,
+        path: &Path,
+        content: String,
+        context: &SaveContext,
+        config: &FileSaveConfig,
+    ) {
+        let job = SaveJob {
+            path: path.to_path_buf(),
+            content,
+            context: context.clone(),
+            config: config.clone(),
+        };
+        let key = path_key(path);
+        let mut queues = self.state.queues.lock().unwrap();
+        let queue = queues.entry(key.clone()).or_default();
+        queue.pending = Some(job);
+        if !queue.busy {
+            queue.busy = true;
+            let job = queue.pending.take();
+            drop(queues);
+            self.spawn(key, job);
+        }
+    }
+
+    /// Save `content` to `path` on this thread, once the save of the path
+    /// under way has finished, retrying transient failures. A save of the
+    /// path still waiting is dropped, as this one has the newer text.
+    pub fn save(
+        &self,
+        path: &Path,
+        content: &str,
+        context: &SaveContext,
+        config: &FileSaveConfig,
+    ) -> FsResult<FileSaveResult> {
+        let key = path_key(path);
+        let mut queues = self.state.queues.lock().unwrap();
+        loop {
+            match queues.get_mut(&key) {
+                Some(queue) if queue.busy => {
+                    queue.pending = None;
+                    queues = self.state.idle.wait(queues).unwrap();
+                }
+                _ => break,
+            }
+        }
+        queues.entry(key.clone()).or_default().busy = true;
+        drop(queues);
+
+        let result = self.state.save_with_retries(path, content, context, config);
+        // Saves scheduled meanwhile run after this one
+        let next = self.state.next_job(&key);
+        if next.is_some() {
+            self.spawn(key, next);
+        }
+        result.map_err(|(error, _)| error)
+    }
+
+    /// Whether a save of `path` is under way or waiting
+    pub fn is_busy(&self, path: &Path) -> bool {
+        self.state
+            .queues
+            .lock()
+            .unwrap()
+            .contains_key(&path_key(path))
+    }
+
+    /// Wait until every save scheduled has finished, as before quitting
+    pub fn flush(&self) {
+        let queues = self.state.queues.lock().unwrap();
+        let _idle = self
+            .state
+            .idle
+            .wait_while(queues, |queues| !queues.is_empty())
+            .unwrap();
+    }
+
+    // Run `job` and the saves of the path scheduled after it on a thread
+    fn spawn(&self, key: PathBuf, mut job: Option<SaveJob>) {
+        let state = Arc::clone(&self.state);
+        thread::spawn(move || {
+            while let Some(save) = job {
+                if let Err((error, attempts)) =
+                    state.save_with_retries(&save.path, &save.content, &save.context, &save.config)
+                {
+                    state.report(SaveFailure {
+                        path: save.path,
+                        error,
+                        attempts,
+                    });
+                }
+                job = state.next_job(&key);
+            }
+        });
+    }
+}
+
+impl Default for SaveScheduler {
+    fn default() -> Self {
+        Self::new(SaveSchedulerConfig::default())
+    }
+}
+
+impl SchedulerState {
+    // The save of the path to run next; with none, the path is idle
+    fn next_job(&self, key: &Path) -> Option<SaveJob> {
+        let mut queues = self.queues.lock().unwrap();
+        let job = queues.get_mut(key).and_then(|queue| queue.pending.take());
+        if job.is_none() {
+            queues.remove(key);
+            self.idle.notify_all();
+        }
+        job
+    }
+
+    // Save, trying again after a doubling wait while the failure is
+    // transient; a full disk may have room again once the temp file of the
+    // failed try is gone. Fails with the last error and the number of tries.
+    fn save_with_retries(
+        &self,
+        path: &Path,
+        content: &str,
+        context: &SaveContext,
+        config: &FileSaveConfig,
+    ) -> Result<FileSaveResult, (FsError, u32)> {
+        let mut delay = self.config.retry_delay;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match (self.save)(path, content, context, config) {
+                Ok(saved) => return Ok(saved),
+                Err(error) => error,
+            };
+            if attempts > self.config.retries || !is_transient(&error) {
+                return Err((error, attempts));
+            }
+            if error.io_kind() == Some(io::ErrorKind::StorageFull) {
+                remove_temp_file(path, config);
+            }
+            crate::log!(
+                Debug,
+                "fs",
+                "saving {} failed ({}), trying again in {:?}",
+                path.display(),
+                error,
+                delay
+            );
+            thread::sleep(delay);
+            delay = delay.saturating_mul(2).min(self.config.max_retry_delay);
+        }
+    }
+
+    fn report(&self, failure: SaveFailure) {
+        crate::log!(
+            Warn,
+            "fs",
+            "gave up saving {} after {} tries: {}",
+            failure.path.display(),
+            failure.attempts,
+            failure.error
+        );
+        if let Some(callback) = self.on_failure.lock().unwrap().as_ref() {
+            callback(failure);
+        }
+    }
+}
+
+/// Whether a failed save may work when tried again: the file is busy or
+/// open in another process without sharing, or the disk is full
+fn is_transient(error: &FsError) -> bool {
+    match error {
+        FsError::Io { source, .. } => {
+            matches!(
+                source.kind(),
+                io::ErrorKind::ResourceBusy | io::ErrorKind::StorageFull
+            ) || is_sharing_violation(source)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::IoOp;
+    use crate::testing::TempDir;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn quick() -> SaveSchedulerConfig {
+        SaveSchedulerConfig {
+            retries: 3,
+            retry_delay: Duration::from_millis(1),
+            max_retry_delay: Duration::from_millis(2),
+        }
+    }
+
+    fn failing(kind: io::ErrorKind, path: &Path) -> FsResult<FileSaveResult> {
+        Err(FsError::io(IoOp::Write, path, io::Error::from(kind)))
+    }
+
+    #[test]
+    fn test_scheduler_retries_transient_failures() {
+        static TRIES: AtomicU32 = AtomicU32::new(0);
+        let scheduler = SaveScheduler::with_save(quick(), |path, content, context, config| {
+            match TRIES.fetch_add(1, Ordering::SeqCst) {
+                0 => failing(io::ErrorKind::ResourceBusy, path),
+                1 => failing(io::ErrorKind::StorageFull, path),
+                _ => save_file_with_config(path, content, context, config),
+            }
+        });
+        let dir = TempDir::new("scheduler");
+        let path = dir.join("retried");
+        let saved = scheduler
+            .save(
+                &path,
+                "saved",
+                &SaveContext::new(),
+                &FileSaveConfig::default(),
+            )
+            .unwrap();
+        assert_eq!(saved.bytes_written, 5);
+        assert_eq!(TRIES.load(Ordering::SeqCst), 3);
+        assert!(!scheduler.is_busy(&path));
+    }
+
+    #[test]
+    fn test_scheduler_reports_terminal_failures() {
+        static TRIES: AtomicU32 = AtomicU32::new(0);
+        let scheduler = SaveScheduler::with_save(quick(), |path, _, _, _| {
+            TRIES.fetch_add(1, Ordering::SeqCst);
+            match path.ends_with("busy") {
+                true => failing(io::ErrorKind::ResourceBusy, path),
+                false => failing(io::ErrorKind::PermissionDenied, path),
+            }
+        });
+        let failures = Arc::new(Mutex::new(Vec::new()));
+        let reported = Arc::clone(&failures);
+        scheduler.on_failure(move |failure| {
+            let kind = failure.error.io_kind().unwrap();
+            reported
+                .lock()
+                .unwrap()
+                .push((failure.path, kind, failure.attempts));
+        });
+
+        let context = SaveContext::new();
+        let config = FileSaveConfig::default();
+        scheduler.schedule(Path::new("denied"), String::new(), &context, &config);
+        scheduler.flush();
+        scheduler.schedule(Path::new("busy"), String::new(), &context, &config);
+        scheduler.flush();
+
+        assert_eq!(
+            *failures.lock().unwrap(),
+            [
+                (PathBuf::from("denied"), io::ErrorKind::PermissionDenied, 1),
+                (PathBuf::from("busy"), io::ErrorKind::ResourceBusy, 4),
+            ]
+        );
+        assert_eq!(TRIES.load(Ordering::SeqCst), 5);
+    }
+
+    #[test]
+    fn test_scheduler_saves_the_last_text() {
+        let scheduler = SaveScheduler::default();
+        let dir = TempDir::new("scheduler");
+        let path = dir.join("last");
+        let context = SaveContext::new();
+        let config = FileSaveConfig::default();
+
+        for i in 0..20 {
+            scheduler.schedule(&path, format!("autosave {}", i), &context, &config);
+        }
+        scheduler.flush();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "autosave 19");
+
+        // `:w` waits for the autosave under way and drops the one waiting
+        scheduler.schedule(&path, "autosave".to_string(), &context, &config);
+        scheduler.schedule(&path, "stale".to_string(), &context, &config);
+        scheduler.save(&path, "written", &context, &config).unwrap();
+        scheduler.flush();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "written");
+        assert!(!scheduler.is_busy(&path));
+    }
+}
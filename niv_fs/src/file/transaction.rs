@@ -0,0 +1,173 @@
+//! Saves of several files at once, as `:wall` does, that either replace all
+//! the files or, when one of them cannot be written, none of them.
+
+use std::path::{Path, PathBuf};
+
+use super::save::{FileSaveConfig, FileSaveResult, SaveContext, StagedSave};
+use crate::error::FsError;
+
+struct PendingSave {
+    path: PathBuf,
+    content: String,
+    context: SaveContext,
+    config: FileSaveConfig,
+}
+
+/// What came of a [`SaveTransaction`], file by file
+#[derive(Debug, Default)]
+pub struct TransactionResult {
+    /// Files written, in the order they were added
+    pub saved: Vec<FileSaveResult>,
+    /// Files that could not be written, with why
+    pub failed: Vec<(PathBuf, FsError)>,
+    /// Files left as they were because another one could not be written
+    pub rolled_back: Vec<PathBuf>,
+}
+
+impl TransactionResult {
+    /// Whether every file of the transaction was written
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.rolled_back.is_empty()
+    }
+}
+
+/// Saves of several files, written together by [`SaveTransaction::commit`].
+///
+/// Every file is first transcoded and written to its temp file, as an atomic
+/// save does. Only once all of them are written are the temp files moved
+/// over the files, so an encoding error, a full disk or a directory without
+/// write access leaves every file untouched. A move failing after others
+/// succeeded, which takes another process holding the file, can't be undone
+/// and is reported for that file alone.
+#[derive(Default)]
+pub struct SaveTransaction {
+    files: Vec<PendingSave>,
+}
+
+impl SaveTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a save of `content` to `path`. The file is always written
+    /// through a temp file, whatever `config.atomic_writes` says.
+    pub fn add<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &str,
+        context: &SaveContext,
+        config: &FileSaveConfig,
+    ) {
+        self.files.push(PendingSave {
+            path: path.as_ref().to_path_buf(),
+            content: content.to_string(),
+            context: context.clone(),
+            config: config.clone(),
+        });
+    }
+
+    /// Number of files added
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Write all the files, or none of them when one can't be written
+    pub fn commit(self) -> TransactionResult {
+        let mut result = TransactionResult::default();
+        let mut staged = Vec::new();
+        for file in &self.files {
+            match StagedSave::stage(&file.path, &file.content, &file.context, &file.config) {
+                Ok(save) => staged.push((save, &file.config)),
+                Err(e) => result.failed.push((file.path.clone(), e)),
+            }
+        }
+
+        if !result.failed.is_empty() {
+            staged.into_iter().for_each(|(save, _)| save.discard());
+            result.rolled_back = self
+                .files
+                .into_iter()
+                .map(|file| file.path)
+                .filter(|path| !result.failed.iter().any(|(failed, _)| failed == path))
+                .collect();
+            crate::log!(
+                Warn,
+                "fs",
+                "saving {} files failed, none written",
+                result.failed.len() + result.rolled_back.len()
+            );
+            return result;
+        }
+
+        for (save, config) in staged {
+            let path = save.path().to_path_buf();
+            match save.commit(config) {
+                Ok(saved) => result.saved.push(saved),
+                Err(e) => {
+                    crate::log!(Warn, "fs", "saving {} failed: {}", path.display(), e);
+                    result.failed.push((path, e));
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::Encoding;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_transaction_saves_all_files() {
+        let dir = TempDir::new("transaction_all");
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, "old a").unwrap();
+
+        let config = FileSaveConfig::default();
+        let mut transaction = SaveTransaction::new();
+        transaction.add(&a, "new a", &SaveContext::new(), &config);
+        transaction.add(&b, "new b", &SaveContext::new(), &config);
+        assert_eq!(transaction.len(), 2);
+        let result = transaction.commit();
+
+        assert!(result.is_complete());
+        assert_eq!(result.saved.len(), 2);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "new a");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "new b");
+    }
+
+    #[test]
+    fn test_transaction_writes_nothing_when_a_file_fails() {
+        let dir = TempDir::new("transaction_fail");
+        let (a, b) = (dir.join("a.txt"), dir.join("b.txt"));
+        std::fs::write(&a, "old a").unwrap();
+        std::fs::write(&b, "old b").unwrap();
+
+        // Latin-1 has no bytes for the snowman
+        let latin1 = SaveContext {
+            original_encoding: Encoding::Latin1,
+            ..SaveContext::new()
+        };
+        let config = FileSaveConfig::default();
+        let mut transaction = SaveTransaction::new();
+        transaction.add(&a, "new a", &SaveContext::new(), &config);
+        transaction.add(&b, "new \u{2603}", &latin1, &config);
+        let result = transaction.commit();
+
+        assert!(!result.is_complete());
+        assert!(result.saved.is_empty());
+        assert_eq!(result.failed.len(), 1);
+        assert_eq!(result.failed[0].0, b);
+        assert_eq!(result.rolled_back, vec![a.clone()]);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "old a");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "old b");
+        // No temp files are left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
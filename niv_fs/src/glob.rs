@@ -0,0 +1,380 @@
+//! Shell-style glob matching and expansion for file names and paths.
+//!
+//! Supported syntax:
+//! - `*` matches any run of characters except the path separator
+//! - `**` matches any run of characters including separators (`a/**/b` also matches `a/b`)
+//! - `?` matches exactly one character except the path separator
+//! - `[abc]`, `[a-z]` and `[!abc]` match character classes
+//! - `{a,b}` expands to each alternative before matching (see [`expand_braces`])
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::file::is_hidden;
+
+/// Check whether `text` matches the glob `pattern`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+/// Check whether a path matches a glob pattern.
+///
+/// Patterns without a separator are matched against the file name only, so
+/// `*.gpg` matches `/home/user/secrets.gpg`. Patterns containing a separator
+/// are matched against the whole path.
+pub fn path_matches_glob(pattern: &str, path: &Path) -> bool {
+    if pattern.contains('/') {
+        let full = path.to_string_lossy().replace('\\', "/");
+        return glob_match(pattern, &full);
+    }
+
+    path.file_name()
+        .map(|name| glob_match(pattern, &name.to_string_lossy()))
+        .unwrap_or(false)
+}
+
+/// Check whether a path matches any of the given glob patterns.
+pub fn path_matches_any<S: AsRef<str>>(patterns: &[S], path: &Path) -> bool {
+    patterns
+        .iter()
+        .any(|pattern| path_matches_glob(pattern.as_ref(), path))
+}
+
+/// Check whether a pattern contains glob syntax, i.e. needs expanding.
+pub fn has_glob_chars(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '[', '{'])
+}
+
+/// Expand `{a,b}` alternatives, including nested ones.
+///
+/// `src/{lib,main}.rs` becomes `src/lib.rs` and `src/main.rs`. Braces without
+/// a comma or without a closing brace are kept literally.
+pub fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some((open, close, alternatives)) = find_brace_group(pattern) else {
+        return vec![pattern.to_string()];
+    };
+
+    let prefix = &pattern[..open];
+    let suffix = &pattern[close + 1..];
+    alternatives
+        .iter()
+        .flat_map(|alt| expand_braces(&format!("{}{}{}", prefix, alt, suffix)))
+        .collect()
+}
+
+/// Find the first brace group with at least one top-level comma, returning its
+/// byte range and alternatives
+fn find_brace_group(pattern: &str) -> Option<(usize, usize, Vec<&str>)> {
+    let bytes = pattern.as_bytes();
+    for open in (0..bytes.len()).filter(|&i| bytes[i] == b'{') {
+        let mut depth = 0;
+        let mut splits = Vec::new();
+        for (i, &b) in bytes.iter().enumerate().skip(open) {
+            match b {
+                b'{' => depth += 1,
+                b'}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if splits.is_empty() {
+                            break;
+                        }
+                        let mut alternatives = Vec::new();
+                        let mut start = open + 1;
+                        for split in splits {
+                            alternatives.push(&pattern[start..split]);
+                            start = split + 1;
+                        }
+                        alternatives.push(&pattern[start..i]);
+                        return Some((open, i, alternatives));
+                    }
+                }
+                b',' if depth == 1 => splits.push(i),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Expand a glob pattern into the existing paths it matches, sorted.
+///
+/// Relative patterns are resolved against `base`. Hidden files and directories,
+/// dotfiles and on Windows those with the hidden attribute, are only matched by
+/// components that start with `.` explicitly, like in a shell.
+/// Patterns without glob syntax are returned as-is, even if the path does not exist.
+pub fn expand_glob(pattern: &str, base: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut results = Vec::new();
+    for pattern in expand_braces(pattern) {
+        let path = Path::new(&pattern);
+        let full = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            base.join(path)
+        };
+        if !has_glob_chars(&pattern) {
+            results.push(full);
+            continue;
+        }
+
+        let normalized = full.to_string_lossy().replace('\\', "/");
+        let (root, rest) = match normalized.strip_prefix('/') {
+            Some(rest) => (PathBuf::from("/"), rest.to_string()),
+            None => {
+                // Windows drive prefix such as `C:`
+                let (drive, rest) = normalized.split_once('/').unwrap_or((&normalized, ""));
+                (PathBuf::from(format!("{}/", drive)), rest.to_string())
+            }
+        };
+        let components: Vec<&str> = rest.split('/').filter(|c| !c.is_empty()).collect();
+        expand_components(&root, &components, &mut results)?;
+    }
+
+    results.sort();
+    results.dedup();
+    Ok(results)
+}
+
+/// Walk the directory tree matching one pattern component per level
+fn expand_components(
+    dir: &Path,
+    components: &[&str],
+    results: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let Some((&component, rest)) = components.split_first() else {
+        results.push(dir.to_path_buf());
+        return Ok(());
+    };
+
+    if component == "**" {
+        // Zero directories, then every subdirectory recursively
+        expand_components(dir, rest, results)?;
+        for entry in read_dir_sorted(dir) {
+            if entry.is_dir() && !is_hidden(&entry) {
+                expand_components(&entry, components, results)?;
+            }
+        }
+        return Ok(());
+    }
+
+    if !has_glob_chars(component) {
+        let next = dir.join(component);
+        if next.exists() {
+            expand_components(&next, rest, results)?;
+        }
+        return Ok(());
+    }
+
+    for entry in read_dir_sorted(dir) {
+        let Some(name) = entry.file_name().map(|n| n.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        if !component.starts_with('.') && is_hidden(&entry) {
+            continue;
+        }
+        if glob_match(component, &name) && (rest.is_empty() || entry.is_dir()) {
+            expand_components(&entry, rest, results)?;
+        }
+    }
+    Ok(())
+}
+
+/// Directory entries in name order; unreadable directories yield nothing
+fn read_dir_sorted(dir: &Path) -> Vec<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map(|entries| entries.filter_map(|e| e.ok().map(|e| e.path())).collect())
+        .unwrap_or_default();
+    entries.sort();
+    entries
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    let mut p = 0;
+    let mut t = 0;
+    // Position to resume from when a `*` has to swallow one more character
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() {
+            match pattern[p] {
+                '*' if pattern.get(p + 1) == Some(&'*') => {
+                    // `**/` may also match no directories at all
+                    if pattern.get(p + 2) == Some(&'/') && match_from(&pattern[p + 3..], &text[t..])
+                    {
+                        return true;
+                    }
+                    // Let `**` swallow any run of characters, separators included
+                    return (t..=text.len())
+                        .any(|skip| match_from(&pattern[p + 2..], &text[skip..]));
+                }
+                '*' => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                    continue;
+                }
+                '?' if text[t] != '/' => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                '[' => {
+                    if let Some((matched, next)) = match_class(pattern, p, text[t]) {
+                        if matched {
+                            p = next;
+                            t += 1;
+                            continue;
+                        }
+                    } else if text[t] == '[' {
+                        // Unterminated class: treat `[` literally
+                        p += 1;
+                        t += 1;
+                        continue;
+                    }
+                }
+                c if c == text[t] => {
+                    p += 1;
+                    t += 1;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        match backtrack {
+            Some((star_p, star_t)) if text[star_t] != '/' => {
+                backtrack = Some((star_p, star_t + 1));
+                p = star_p + 1;
+                t = star_t + 1;
+            }
+            _ => return false,
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match a character class starting at `pattern[start] == '['`.
+///
+/// Returns whether `ch` matched and the pattern index after the class, or
+/// `None` if the class is not terminated.
+fn match_class(pattern: &[char], start: usize, ch: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+    while i < pattern.len() {
+        let c = pattern[i];
+        if c == ']' && !first {
+            return Some((matched != negated && ch != '/', i + 1));
+        }
+        if i + 2 < pattern.len() && pattern[i + 1] == '-' && pattern[i + 2] != ']' {
+            if pattern[i] <= ch && ch <= pattern[i + 2] {
+                matched = true;
+            }
+            i += 3;
+        } else {
+            if c == ch {
+                matched = true;
+            }
+            i += 1;
+        }
+        first = false;
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_glob_match_basic() {
+        assert!(glob_match("*.gpg", "secrets.gpg"));
+        assert!(!glob_match("*.gpg", "secrets.gpg.txt"));
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+    }
+
+    #[test]
+    fn test_glob_match_classes() {
+        assert!(glob_match("[abc].rs", "b.rs"));
+        assert!(!glob_match("[!abc].rs", "b.rs"));
+        assert!(glob_match("[a-z]1", "q1"));
+        assert!(glob_match("[", "["));
+    }
+
+    #[test]
+    fn test_star_does_not_cross_separator() {
+        assert!(!glob_match("*.rs", "src/main.rs"));
+        assert!(glob_match("src/*.rs", "src/main.rs"));
+    }
+
+    #[test]
+    fn test_double_star_crosses_separators() {
+        assert!(glob_match("src/**/*.rs", "src/main.rs"));
+        assert!(glob_match("src/**/*.rs", "src/tui/editor/mod.rs"));
+        assert!(!glob_match("src/**/*.rs", "tests/main.rs"));
+        assert!(glob_match("**", "a/b/c"));
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(
+            expand_braces("src/{lib,main}.rs"),
+            ["src/lib.rs", "src/main.rs"]
+        );
+        assert_eq!(
+            expand_braces("{a,b{1,2}}.txt"),
+            ["a.txt", "b1.txt", "b2.txt"]
+        );
+        assert_eq!(expand_braces("{single}.txt"), ["{single}.txt"]);
+        assert_eq!(expand_braces("unclosed{a,b"), ["unclosed{a,b"]);
+    }
+
+    #[test]
+    fn test_expand_glob_walks_directories() {
+        let base = TempDir::new("glob_expand");
+        for file in ["a.rs", "b.md", "sub/c.rs", "sub/deep/d.rs", ".hidden/e.rs"] {
+            let path = base.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, "").unwrap();
+        }
+
+        let found = expand_glob("**/*.rs", base.path()).unwrap();
+        assert_eq!(
+            found,
+            [
+                base.join("a.rs"),
+                base.join("sub/c.rs"),
+                base.join("sub/deep/d.rs")
+            ]
+        );
+        assert_eq!(
+            expand_glob("*.{md,txt}", base.path()).unwrap(),
+            [base.join("b.md")]
+        );
+        assert_eq!(
+            expand_glob("new.txt", base.path()).unwrap(),
+            [base.join("new.txt")]
+        );
+    }
+
+    #[test]
+    fn test_path_matches_glob() {
+        let path = Path::new("/home/user/.password-store/mail.gpg");
+        assert!(path_matches_glob("*.gpg", path));
+        assert!(path_matches_glob("/home/*/.password-store/*", path));
+        assert!(!path_matches_glob("*.asc", path));
+        assert!(path_matches_any(&["*.asc", "*.gpg"], path));
+    }
+}
@@ -5,85 +5,79 @@
 //!
 //! Modules:
 //! - `bom` for BOM detection (UTF-8/16/32)
+//! - `crypt` for encrypting swap and draft files at rest
 //! - `encoding` for heuristic detection (UTF-8, UTF-16, Latin-1/9, Windows-1252)
-//! - `file` for file loading/saving operations
+//! - `error` for the error type shared by the file operations
+//! - `file` for file loading/saving operations and converting text between encodings
+//! - `glob` for shell-style file name pattern matching and expansion
+//! - `lock` for locking the state files that niv instances share
+//! - `log` for the logging facade and rotating log file
+//! - `paths` for the platform's directories to keep caches, state and configuration in
+//! - `shada` for the registers, marks, histories and cursor positions kept between runs
 //! - `swap` for crash recovery and periodic saves
+//! - `testing` for helpers of tests, such as temporary directories (with the `testing` feature)
 //! - `watcher` for external file change detection and conflict resolution
 
 mod bom;
+mod crypt;
 mod encoding;
+mod error;
 mod file;
+mod glob;
+mod lock;
+mod log;
+pub mod paths;
+mod shada;
 mod swap;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 mod watcher;
 
 pub use bom::{BomDetectionResult, detect_bom};
+pub use crypt::{KeyProvider, PBKDF2_ITERATIONS, PassphraseKey, SWAP_PASSPHRASE_ENV, SessionKey};
 pub use encoding::{
     DetectionConfidence, DetectionConfig, Encoding, EncodingDetectionResult,
     detect_encoding_heuristic, detect_encoding_heuristic_with_confidence,
 };
+pub use error::{FsError, FsResult, IoOp};
 pub use file::{
-    FileIdentity, FileIdentityConfig, FileLoadConfig, FileLoadResult, FileSaveConfig,
-    FileSaveResult, SaveContext,
+    Conversion, ConvertError, DirEntryInfo, FileAppendResult, FileIdentity, FileIdentityConfig,
+    FileLoadConfig, FileLoadResult, FilePreview, FileReloadResult, FileSaveConfig, FileSaveResult,
+    SaveContext, SaveFailure, SaveScheduler, SaveSchedulerConfig, SaveTransaction,
+    TransactionResult, UnencodablePolicy, append_to_file, convert, convert_with_policy,
     eol::{EolType, normalize_eol, restore_eol},
-    load_file, load_file_with_config, save_file, save_file_with_config,
+    has_hidden_attribute, is_case_insensitive, is_hidden, list_dir, load_appended, load_file,
+    load_file_with_config, path_key, preview_file, reload_incremental,
+    reload_incremental_with_config, rename_file, resolve_move_target, save_file,
+    save_file_with_config, save_line_range, slice_lines,
 };
+pub use glob::{
+    expand_braces, expand_glob, glob_match, has_glob_chars, path_matches_any, path_matches_glob,
+};
+pub use lock::{FileLock, LOCK_TIMEOUT, write_locked};
+pub use log::{
+    LOG_FILE_ENV, LOG_FILTER_ENV, LogConfig, LogFilter, LogLevel, format_timestamp, init_logging,
+    log_enabled, log_file, write_log,
+};
+pub use shada::{FilePosition, Register, SharedData};
 pub use swap::{
     CursorPosition, DraftError, DraftManager, DraftResult, SwapConfig, SwapError, SwapFile,
     SwapManager, SwapResult, SwapStatus, ViewportState,
 };
 pub use watcher::{
     ChangeType, ConflictResolution, FileChange, FileSnapshot, FileState, FileWatcher,
-    MergeConflict, WatchEvent, WatcherConfig, WatcherError, WatcherResult,
+    MergeConflict, WatchEvent, WatchPriority, WatcherConfig, WatcherError, WatcherResult,
 };
 
-use std::fmt;
+use error::IoContext;
 use std::io;
 
-/// Errors that can occur during encoding detection
-#[derive(Debug)]
-pub enum EncodingError {
-    /// I/O error while reading file
-    Io(io::Error),
-    /// File appears to be binary (contains many null/control bytes)
-    BinaryFile,
-    /// File is too large to process
-    FileTooLarge,
-}
-
-impl fmt::Display for EncodingError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            EncodingError::Io(err) => write!(f, "I/O error: {}", err),
-            EncodingError::BinaryFile => write!(f, "File appears to be binary"),
-            EncodingError::FileTooLarge => write!(f, "File is too large to process"),
-        }
-    }
-}
-
-impl std::error::Error for EncodingError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            EncodingError::Io(err) => Some(err),
-            _ => None,
-        }
-    }
-}
-
-impl From<io::Error> for EncodingError {
-    fn from(err: io::Error) -> Self {
-        EncodingError::Io(err)
-    }
-}
-
-/// Result type for encoding detection operations
-pub type EncodingResult<T> = Result<T, EncodingError>;
-
 /// Detect the encoding of a file from its byte content.
 ///
 /// Strategy:
 /// 1) BOM detection (high confidence)
 /// 2) Heuristic detection (UTF-16/UTF-8/Latin families)
-pub fn detect_encoding(bytes: &[u8], config: Option<DetectionConfig>) -> EncodingResult<Encoding> {
+pub fn detect_encoding(bytes: &[u8], config: Option<DetectionConfig>) -> FsResult<Encoding> {
     let cfg = config.unwrap_or_default();
 
     let bom = detect_bom(bytes);
@@ -98,7 +92,7 @@ pub fn detect_encoding(bytes: &[u8], config: Option<DetectionConfig>) -> Encodin
 pub fn detect_encoding_with_confidence(
     bytes: &[u8],
     config: Option<DetectionConfig>,
-) -> EncodingResult<EncodingDetectionResult> {
+) -> FsResult<EncodingDetectionResult> {
     let cfg = config.unwrap_or_default();
 
     let bom = detect_bom(bytes);
@@ -123,29 +117,31 @@ pub fn detect_encoding_with_confidence(
 pub fn detect_encoding_from_file<P: AsRef<std::path::Path>>(
     path: P,
     config: Option<DetectionConfig>,
-) -> EncodingResult<Encoding> {
+) -> FsResult<Encoding> {
     use std::fs;
     let cfg = config.unwrap_or_default();
 
+    let path = path.as_ref();
     let mut buffer = vec![0u8; cfg.sample_size];
-    let mut file = fs::File::open(path)?;
-    let bytes_read = io::Read::read(&mut file, &mut buffer)?;
+    let mut file = fs::File::open(path).io_context(IoOp::Open, path)?;
+    let bytes_read = io::Read::read(&mut file, &mut buffer).io_context(IoOp::Read, path)?;
     let content = &buffer[..bytes_read];
 
-    detect_encoding(content, Some(cfg))
+    detect_encoding(content, Some(cfg)).map_err(|e| e.with_path(path))
 }
 
 /// Detect encoding from a file path with confidence information.
 pub fn detect_encoding_from_file_with_confidence<P: AsRef<std::path::Path>>(
     path: P,
     config: Option<DetectionConfig>,
-) -> EncodingResult<EncodingDetectionResult> {
+) -> FsResult<EncodingDetectionResult> {
     use std::fs;
     let cfg = config.unwrap_or_default();
 
+    let path = path.as_ref();
     let mut buffer = vec![0u8; cfg.sample_size];
-    let mut file = fs::File::open(path)?;
-    let bytes_read = io::Read::read(&mut file, &mut buffer)?;
+    let mut file = fs::File::open(path).io_context(IoOp::Open, path)?;
+    let bytes_read = io::Read::read(&mut file, &mut buffer).io_context(IoOp::Read, path)?;
     let content = &buffer[..bytes_read];
 
     detect_encoding_with_confidence(content, Some(cfg))
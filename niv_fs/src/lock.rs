@@ -0,0 +1,263 @@
+//! Locks on the state files that niv instances share, such as the shada
+//! file and the sessions, so two instances never update one at once.
+//!
+//! The lock of `file` is `file.lock`, created only if it doesn't exist and
+//! holding the process id of its owner. A lock whose owner is no longer
+//! running, or that is older than any update takes, was left by an
+//! instance that crashed and is taken over. A lock with no id yet is only
+//! taken over by age, as its owner may still be writing it.
+
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::error::{FsError, FsResult, IoContext, IoOp};
+use crate::file::save::{SaveContext, save_file};
+
+/// How long to wait for another instance to release a lock
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Age past which a lock is stale whoever holds it; updates of the state
+/// files take milliseconds
+const STALE_LOCK_AGE: Duration = Duration::from_secs(30);
+
+/// Wait between two tries to take a lock
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Locks this process holds. One with this process's id that isn't among
+/// them was left by an earlier process that had the same id, as happens
+/// when instances in containers restart.
+static HELD: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The lock of a shared file, released when dropped
+#[derive(Debug)]
+pub struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Take the lock of `file`, waiting up to `timeout` for the instance
+    /// holding it to let go
+    pub fn acquire(file: &Path, timeout: Duration) -> FsResult<FileLock> {
+        let path = lock_path(file);
+        let started = Instant::now();
+        loop {
+            match File::options().write(true).create_new(true).open(&path) {
+                Ok(mut lock) => {
+                    // The id goes in after the create, so a lock without
+                    // one may still be getting it
+                    held().push(path.clone());
+                    let owned = writeln!(lock, "{}", std::process::id());
+                    let lock = FileLock { path };
+                    owned.io_context(IoOp::Write, &lock.path)?;
+                    return Ok(lock);
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(FsError::io(IoOp::Create, &path, e)),
+            }
+
+            let owner = lock_owner(&path);
+            let modified = lock_modified(&path);
+            if is_stale(&path, owner, modified) {
+                crate::log!(Info, "lock", "taking over stale lock {}", path.display());
+                // Whoever gets in first takes it; the others find a lock
+                // that was written again since
+                if lock_modified(&path) == modified && lock_owner(&path) == owner {
+                    let _ = fs::remove_file(&path);
+                }
+                continue;
+            }
+            if started.elapsed() >= timeout {
+                let held = match owner {
+                    Some(pid) => format!("locked by process {}", pid),
+                    None => "locked".to_string(),
+                };
+                let busy = io::Error::new(io::ErrorKind::WouldBlock, held);
+                return Err(FsError::io(IoOp::Create, &path, busy));
+            }
+            thread::sleep(RETRY_DELAY);
+        }
+    }
+
+    /// Path of the lock file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+        held().retain(|path| *path != self.path);
+    }
+}
+
+/// Replace `file` with `text` in one step while holding its lock, creating
+/// its directory if needed
+pub fn write_locked(file: &Path, text: &str) -> FsResult<()> {
+    if let Some(dir) = file.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).io_context(IoOp::CreateDir, dir)?;
+    }
+    let _lock = FileLock::acquire(file, LOCK_TIMEOUT)?;
+    save_file(file, text, &SaveContext::new())?;
+    Ok(())
+}
+
+/// Add `text` at the end of `file` while holding its lock, the way journal
+/// entries are added. The file must end with a newline, as `text` must.
+pub(crate) fn append_locked(file: &Path, text: &str, _lock: &FileLock) -> FsResult<()> {
+    let mut journal = File::options()
+        .append(true)
+        .open(file)
+        .io_context(IoOp::Open, file)?;
+    journal
+        .write_all(text.as_bytes())
+        .io_context(IoOp::Write, file)?;
+    journal.sync_all().io_context(IoOp::Sync, file)?;
+    Ok(())
+}
+
+fn lock_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_os_string();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+fn held() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    HELD.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Process id in the lock at `path`; None while it is still being written
+fn lock_owner(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// When the lock at `path` was last written
+fn lock_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Whether the lock at `path`, held by `owner` and written at `modified`,
+/// was left behind
+fn is_stale(path: &Path, owner: Option<u32>, modified: Option<SystemTime>) -> bool {
+    let age = modified.and_then(|modified| modified.elapsed().ok());
+    if age.is_some_and(|age| age > STALE_LOCK_AGE) {
+        return true;
+    }
+    match owner {
+        Some(pid) if pid == std::process::id() => !held().iter().any(|held| held == path),
+        Some(pid) => !process_alive(pid),
+        None => false,
+    }
+}
+
+/// Whether a process with id `pid` is running. Where that can't be told,
+/// it is taken to be, leaving the lock to go stale with age.
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_alive(pid: u32) -> bool {
+    // `kill -0` only checks that the process could be signalled
+    std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map_or(true, |status| status.success())
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map_or(true, |output| {
+            String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+        })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn test_lock_keeps_others_out_until_dropped() {
+        let dir = TempDir::new("lock");
+        let file = dir.join("held");
+        let lock = FileLock::acquire(&file, LOCK_TIMEOUT).unwrap();
+        assert!(lock.path().exists());
+
+        let busy = FileLock::acquire(&file, Duration::from_millis(30)).unwrap_err();
+        assert_eq!(busy.io_kind(), Some(io::ErrorKind::WouldBlock));
+        assert!(busy.to_string().contains(&std::process::id().to_string()));
+
+        drop(lock);
+        assert!(!lock_path(&file).exists());
+        drop(FileLock::acquire(&file, Duration::ZERO).unwrap());
+    }
+
+    #[test]
+    fn test_lock_of_a_dead_process_is_taken_over() {
+        let dir = TempDir::new("lock");
+        let file = dir.join("stale");
+        // Beyond the largest process id Linux hands out
+        fs::write(lock_path(&file), "4194305\n").unwrap();
+        let lock = FileLock::acquire(&file, Duration::ZERO).unwrap();
+        assert_eq!(lock_owner(lock.path()), Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_lock_with_this_process_id_is_taken_over_unless_held() {
+        let dir = TempDir::new("lock");
+        let file = dir.join("same_pid");
+        // Left by an earlier process that had the same id
+        fs::write(lock_path(&file), format!("{}\n", std::process::id())).unwrap();
+        let lock = FileLock::acquire(&file, Duration::ZERO).unwrap();
+
+        let busy = FileLock::acquire(&file, Duration::ZERO).unwrap_err();
+        assert_eq!(busy.io_kind(), Some(io::ErrorKind::WouldBlock));
+        drop(lock);
+    }
+
+    #[test]
+    fn test_lock_without_owner_is_taken_over_only_by_age() {
+        let dir = TempDir::new("lock");
+        let file = dir.join("no_owner");
+        let path = lock_path(&file);
+        fs::write(&path, "").unwrap();
+        assert!(FileLock::acquire(&file, Duration::from_millis(30)).is_err());
+
+        let old = SystemTime::now() - STALE_LOCK_AGE * 2;
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        let lock = FileLock::acquire(&file, Duration::ZERO).unwrap();
+        assert_eq!(lock_owner(lock.path()), Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_write_locked_replaces_the_file() {
+        let dir = TempDir::new("lock");
+        let file = dir.join("dir").join("state");
+        write_locked(&file, "one\n").unwrap();
+        write_locked(&file, "two\n").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "two\n");
+        assert!(!lock_path(&file).exists());
+    }
+}
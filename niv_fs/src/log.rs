@@ -0,0 +1,354 @@
+//! Logging facade shared by the niv crates.
+//!
+//! Records carry a level and a target naming the subsystem (`fs`, `watcher`,
+//! `render`, ...). Nothing is written until [`init_logging`] is called; after
+//! that, records passing the filter are appended to the log file, which is
+//! rotated once it grows past [`LogConfig::max_size`].
+//!
+//! Filters use the `RUST_LOG`-like syntax `info,fs=debug,render=trace`: a bare
+//! level sets the default, `target=level` overrides it for one subsystem.
+//!
+//! ```
+//! # let len = 42;
+//! niv_fs::log!(Debug, "fs", "loaded {} bytes", len);
+//! ```
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::error::{IoContext, IoOp};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Environment variable naming the log file when `--log-file` is not given
+pub const LOG_FILE_ENV: &str = "NIV_LOG_FILE";
+/// Environment variable holding the filter
+pub const LOG_FILTER_ENV: &str = "NIV_LOG";
+
+/// Severity of a log record, most severe first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warn" | "warning" => Some(Self::Warn),
+            "info" => Some(Self::Info),
+            "debug" => Some(Self::Debug),
+            "trace" => Some(Self::Trace),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Error => "ERROR",
+            Self::Warn => "WARN",
+            Self::Info => "INFO",
+            Self::Debug => "DEBUG",
+            Self::Trace => "TRACE",
+        }
+    }
+}
+
+/// Which records are written: a default level and per-target overrides
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    pub default: LogLevel,
+    pub targets: Vec<(String, LogLevel)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            default: LogLevel::Info,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl LogFilter {
+    /// Parse a filter such as `info,fs=debug`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            let (target, level) = match part.split_once('=') {
+                Some((target, level)) => (Some(target.trim()), level.trim()),
+                None => (None, part),
+            };
+            let level = LogLevel::from_name(level)
+                .ok_or_else(|| format!("unknown log level: {}", level))?;
+            match target {
+                Some(target) => filter.targets.push((target.to_string(), level)),
+                None => filter.default = level,
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Most verbose level written for `target`
+    pub fn level_for(&self, target: &str) -> LogLevel {
+        self.targets
+            .iter()
+            .rev()
+            .find(|(name, _)| name == target)
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    pub fn enabled(&self, level: LogLevel, target: &str) -> bool {
+        level <= self.level_for(target)
+    }
+}
+
+/// Where and what to log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogConfig {
+    pub path: PathBuf,
+    pub filter: LogFilter,
+    /// Size in bytes after which the file is rotated
+    pub max_size: u64,
+    /// Rotated files kept next to the log (`niv.log.1`, `niv.log.2`, ...)
+    pub max_files: usize,
+}
+
+impl LogConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            filter: LogFilter::default(),
+            max_size: 1024 * 1024,
+            max_files: 3,
+        }
+    }
+
+    /// Log file used when only a filter is given: `niv.log` in the state
+    /// directory
+    pub fn default_path() -> PathBuf {
+        crate::paths::state_dir().join("niv.log")
+    }
+
+    /// Configuration from `NIV_LOG_FILE` and `NIV_LOG`, logging to
+    /// [`LogConfig::default_path`] when only `NIV_LOG` is set; `None` when
+    /// neither is
+    pub fn from_env() -> Option<Self> {
+        let set = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+        let path = set(LOG_FILE_ENV)
+            .map(PathBuf::from)
+            .or_else(|| set(LOG_FILTER_ENV).map(|_| Self::default_path()))?;
+        let mut config = Self::new(path);
+        if let Some(filter) = std::env::var(LOG_FILTER_ENV)
+            .ok()
+            .and_then(|spec| LogFilter::parse(&spec).ok())
+        {
+            config.filter = filter;
+        }
+        Some(config)
+    }
+}
+
+struct Logger {
+    config: LogConfig,
+    file: File,
+    size: u64,
+}
+
+impl Logger {
+    fn open(config: LogConfig) -> io::Result<Self> {
+        if let Some(parent) = config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).io_context(IoOp::CreateDir, parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)
+            .io_context(IoOp::Open, &config.path)?;
+        let size = file
+            .metadata()
+            .io_context(IoOp::Metadata, &config.path)?
+            .len();
+        Ok(Self { config, file, size })
+    }
+
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_size {
+            self.rotate()?;
+        }
+        self.file
+            .write_all(line.as_bytes())
+            .io_context(IoOp::Write, &self.config.path)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shift `niv.log.N` to `niv.log.N+1`, dropping the oldest, and start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            self.file = File::create(path).io_context(IoOp::Create, path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(path, self.config.max_files));
+            for n in (1..self.config.max_files).rev() {
+                let _ = fs::rename(rotated_path(path, n), rotated_path(path, n + 1));
+            }
+            fs::rename(path, rotated_path(path, 1)).io_context(IoOp::Rename, path)?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .io_context(IoOp::Open, path)?;
+        }
+        self.size = 0;
+        Ok(())
+    }
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+/// Start writing log records to `config.path`, replacing any earlier log file
+pub fn init_logging(config: LogConfig) -> io::Result<()> {
+    let logger = Logger::open(config)?;
+    if let Ok(mut current) = LOGGER.lock() {
+        *current = Some(logger);
+    }
+    Ok(())
+}
+
+/// The current log file, if logging was started
+pub fn log_file() -> Option<PathBuf> {
+    let logger = LOGGER.lock().ok()?;
+    logger.as_ref().map(|logger| logger.config.path.clone())
+}
+
+/// Whether a record would be written; lets callers skip expensive formatting
+pub fn log_enabled(level: LogLevel, target: &str) -> bool {
+    LOGGER.lock().ok().is_some_and(|logger| {
+        logger
+            .as_ref()
+            .is_some_and(|logger| logger.config.filter.enabled(level, target))
+    })
+}
+
+/// Write a record; use the [`log!`](crate::log!) macro instead of calling this directly
+pub fn write_log(level: LogLevel, target: &str, message: fmt::Arguments<'_>) {
+    let Ok(mut logger) = LOGGER.lock() else {
+        return;
+    };
+    let Some(logger) = logger.as_mut() else {
+        return;
+    };
+    if !logger.config.filter.enabled(level, target) {
+        return;
+    }
+    let line = format!(
+        "{} {:<5} {}: {}\n",
+        format_timestamp(SystemTime::now()),
+        level.name(),
+        target,
+        message
+    );
+    // A failing log must never take the editor down
+    let _ = logger.write(&line);
+}
+
+/// Log a record: `log!(Level, "target", "format", args...)`
+#[macro_export]
+macro_rules! log {
+    ($level:ident, $target:expr, $($arg:tt)+) => {
+        $crate::write_log($crate::LogLevel::$level, $target, format_args!($($arg)+))
+    };
+}
+
+/// Path of the `n`th rotated log file
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// UTC time as `2024-05-01T12:34:56.789Z`
+pub fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use std::time::Duration;
+
+    #[test]
+    fn test_log_filter() {
+        let filter = LogFilter::parse("warn, fs=debug,render=trace").unwrap();
+        assert_eq!(filter.default, LogLevel::Warn);
+        assert!(filter.enabled(LogLevel::Debug, "fs"));
+        assert!(!filter.enabled(LogLevel::Trace, "fs"));
+        assert!(filter.enabled(LogLevel::Trace, "render"));
+        assert!(filter.enabled(LogLevel::Warn, "watcher"));
+        assert!(!filter.enabled(LogLevel::Info, "watcher"));
+        assert!(LogFilter::parse("fs=loud").is_err());
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let time = UNIX_EPOCH + Duration::from_millis(1_709_210_096_042);
+        assert_eq!(format_timestamp(time), "2024-02-29T12:34:56.042Z");
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = TempDir::new("log_test");
+        let path = dir.join("niv.log");
+        let mut config = LogConfig::new(&path);
+        config.max_size = 16;
+        config.max_files = 2;
+
+        let mut logger = Logger::open(config).unwrap();
+        for line in ["first line\n", "second line\n", "third line\n", "fourth\n"] {
+            logger.write(line).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "fourth\n");
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "third line\n"
+        );
+        assert_eq!(
+            fs::read_to_string(rotated_path(&path, 2)).unwrap(),
+            "second line\n"
+        );
+        assert!(!rotated_path(&path, 3).exists());
+    }
+}
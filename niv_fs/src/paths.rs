@@ -0,0 +1,161 @@
+//! Where niv keeps its files, following the conventions of each platform.
+//!
+//! - Linux and other Unixes: the XDG base directories, `$XDG_STATE_HOME`
+//!   and so on, falling back to `~/.local/state`, `~/.cache`, `~/.config`
+//!   and `~/.local/share`
+//! - macOS: `~/Library/Caches` for the cache and `~/Library/Application
+//!   Support` for the rest, unless the XDG variables are set
+//! - Windows: the Local and Roaming AppData known folders, as given by
+//!   `%LOCALAPPDATA%` and `%APPDATA%`
+//!
+//! Each directory is niv's own, e.g. `~/.local/state/niv`. When none can be
+//! found the temp directory is used. State is what niv keeps between runs
+//! that the user didn't write: swap files, undo files, sessions and logs.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// The kinds of directories niv keeps files in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DirKind {
+    Cache,
+    Config,
+    Data,
+    State,
+}
+
+/// Directory for files that can be deleted at any time, such as crash reports
+pub fn cache_dir() -> PathBuf {
+    resolve(DirKind::Cache)
+}
+
+/// Directory for configuration the user writes
+pub fn config_dir() -> PathBuf {
+    resolve(DirKind::Config)
+}
+
+/// Directory for data files the user adds, such as plugins
+pub fn data_dir() -> PathBuf {
+    resolve(DirKind::Data)
+}
+
+/// Directory for state kept between runs: swap files, sessions and logs
+pub fn state_dir() -> PathBuf {
+    resolve(DirKind::State)
+}
+
+fn resolve(kind: DirKind) -> PathBuf {
+    niv_dir(kind, |name| std::env::var_os(name)).unwrap_or_else(|| std::env::temp_dir().join("niv"))
+}
+
+/// niv's directory of `kind`, with environment variables read by `var`
+fn niv_dir(kind: DirKind, var: impl Fn(&str) -> Option<OsString>) -> Option<PathBuf> {
+    // Set but empty counts as unset, and the XDG spec ignores relative paths
+    let dir = |name: &str| var(name).map(PathBuf::from).filter(|dir| dir.is_absolute());
+
+    if cfg!(windows) {
+        return match kind {
+            DirKind::Cache => dir("LOCALAPPDATA").map(|local| local.join("niv").join("cache")),
+            DirKind::Config | DirKind::Data => dir("APPDATA").map(|roaming| roaming.join("niv")),
+            DirKind::State => dir("LOCALAPPDATA").map(|local| local.join("niv")),
+        };
+    }
+
+    let (xdg_var, home_default) = match kind {
+        DirKind::Cache => ("XDG_CACHE_HOME", ".cache"),
+        DirKind::Config => ("XDG_CONFIG_HOME", ".config"),
+        DirKind::Data => ("XDG_DATA_HOME", ".local/share"),
+        DirKind::State => ("XDG_STATE_HOME", ".local/state"),
+    };
+    if let Some(xdg) = dir(xdg_var) {
+        return Some(xdg.join("niv"));
+    }
+    let home = dir("HOME")?;
+    if cfg!(target_os = "macos") {
+        let library = match kind {
+            DirKind::Cache => "Library/Caches",
+            _ => "Library/Application Support",
+        };
+        return Some(home.join(library).join("niv"));
+    }
+    Some(home.join(home_default).join("niv"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<OsString> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| OsString::from(value))
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_xdg_dirs() {
+        let vars = [
+            ("HOME", "/home/ada"),
+            ("XDG_STATE_HOME", "/var/state"),
+            ("XDG_CACHE_HOME", ""),
+            ("XDG_CONFIG_HOME", "relative/config"),
+        ];
+        let dir = |kind| niv_dir(kind, env(&vars)).unwrap();
+        assert_eq!(dir(DirKind::State), PathBuf::from("/var/state/niv"));
+        // Empty and relative XDG variables are ignored
+        assert_eq!(dir(DirKind::Cache), PathBuf::from("/home/ada/.cache/niv"));
+        assert_eq!(dir(DirKind::Config), PathBuf::from("/home/ada/.config/niv"));
+        assert_eq!(
+            dir(DirKind::Data),
+            PathBuf::from("/home/ada/.local/share/niv")
+        );
+
+        assert_eq!(niv_dir(DirKind::State, env(&[])), None);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_library_dirs() {
+        let vars = [("HOME", "/Users/ada")];
+        let dir = |kind| niv_dir(kind, env(&vars)).unwrap();
+        assert_eq!(
+            dir(DirKind::Cache),
+            PathBuf::from("/Users/ada/Library/Caches/niv")
+        );
+        assert_eq!(
+            dir(DirKind::State),
+            PathBuf::from("/Users/ada/Library/Application Support/niv")
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_known_folders() {
+        let vars = [
+            ("LOCALAPPDATA", r"C:\Users\ada\AppData\Local"),
+            ("APPDATA", r"C:\Users\ada\AppData\Roaming"),
+        ];
+        let dir = |kind| niv_dir(kind, env(&vars)).unwrap();
+        assert_eq!(
+            dir(DirKind::Cache),
+            PathBuf::from(r"C:\Users\ada\AppData\Local\niv\cache")
+        );
+        assert_eq!(
+            dir(DirKind::State),
+            PathBuf::from(r"C:\Users\ada\AppData\Local\niv")
+        );
+        assert_eq!(
+            dir(DirKind::Config),
+            PathBuf::from(r"C:\Users\ada\AppData\Roaming\niv")
+        );
+    }
+
+    #[test]
+    fn test_dirs_are_nivs_own() {
+        for dir in [cache_dir(), config_dir(), data_dir(), state_dir()] {
+            assert!(dir.ends_with("niv") || dir.ends_with("niv/cache"));
+        }
+    }
+}
@@ -0,0 +1,707 @@
+//! Data kept between runs and shared by niv instances, like Vim's shada
+//! file: registers, global marks, command and search history and the last
+//! cursor position in each file.
+//!
+//! Every entry is stamped with the time it was set. Writing the file first
+//! merges in what is already there, keeping the newer of two entries for the
+//! same register, mark or file and the newest lines of both histories, so
+//! instances running side by side add to each other's data instead of
+//! overwriting it.
+//!
+//! The file is a journal: an instance holding its lock appends the entries
+//! that it lacks, and reading takes the last line written for each entry.
+//! Once the file is twice as long as its entries need, it is compacted by
+//! replacing it with just them in one step.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::hash::Hash;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{FsError, FsResult, IoContext, IoOp};
+use crate::file::identity::path_key;
+use crate::file::save::{SaveContext, save_file};
+use crate::lock::{FileLock, LOCK_TIMEOUT, append_locked};
+
+/// First line of a shada file
+const SHADA_HEADER: &str = "niv shada";
+
+/// Most lines kept of the command and of the search history
+const HISTORY_SIZE: usize = 100;
+
+/// Most files whose last cursor position is kept
+const POSITIONS_SIZE: usize = 100;
+
+/// How many times longer than its entries need the file grows before it is
+/// compacted
+const COMPACT_RATIO: usize = 2;
+
+/// Text of a register, and whether it holds whole lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Register {
+    pub text: String,
+    pub linewise: bool,
+}
+
+/// A position in a file: a global mark or where the cursor last was
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePosition {
+    pub path: PathBuf,
+    /// 0-based line
+    pub line: usize,
+    /// Byte column
+    pub col: usize,
+}
+
+/// An entry with the time it was set, in seconds since the Unix epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stamped<T> {
+    time: u64,
+    value: T,
+}
+
+impl<T> Stamped<T> {
+    fn now(value: T) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Stamped { time, value }
+    }
+}
+
+/// Registers, global marks, histories and file positions, as read from and
+/// written to a shada file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SharedData {
+    registers: BTreeMap<char, Stamped<Register>>,
+    marks: BTreeMap<char, Stamped<FilePosition>>,
+    /// Oldest first, as are the other lists
+    command_history: Vec<Stamped<String>>,
+    search_history: Vec<Stamped<String>>,
+    positions: Vec<Stamped<FilePosition>>,
+}
+
+impl SharedData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the shada file at `path`; one that doesn't exist yet reads as
+    /// empty
+    pub fn load<P: AsRef<Path>>(path: P) -> FsResult<Self> {
+        let path = path.as_ref();
+        match read_text(path)? {
+            Some(text) => parse_file(path, complete_lines(&text)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Write to the shada file at `path`, after merging in what other
+    /// instances wrote to it since it was read. The file is locked while it
+    /// is read and written, and only the entries it lacks are appended, or
+    /// it is compacted, replaced in one step so a reader never sees half of
+    /// it.
+    pub fn write<P: AsRef<Path>>(&mut self, path: P) -> FsResult<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).io_context(IoOp::CreateDir, dir)?;
+        }
+        let lock = FileLock::acquire(path, LOCK_TIMEOUT)?;
+        let Some(text) = read_text(path)? else {
+            save_file(path, &self.to_text(), &SaveContext::new())?;
+            return Ok(());
+        };
+        // A file that isn't one of ours is not to be overwritten
+        let on_disk = parse_file(path, complete_lines(&text))?;
+        self.merge(on_disk.clone());
+
+        let mut missing = String::new();
+        self.write_entries(&mut missing, &on_disk);
+        let compacted = self.to_text();
+        let journal_lines = text.lines().count() + missing.lines().count();
+        // A line cut short by an instance that crashed while appending is
+        // compacted away rather than appended to
+        if text.ends_with('\n') && journal_lines <= compacted.lines().count() * COMPACT_RATIO {
+            if !missing.is_empty() {
+                append_locked(path, &missing, &lock)?;
+            }
+        } else {
+            save_file(path, &compacted, &SaveContext::new())?;
+        }
+        Ok(())
+    }
+
+    /// Take in the entries of `other`: the newer entry of a register, mark
+    /// or file wins, the ones set here on a tie, and the histories are
+    /// combined in time order
+    pub fn merge(&mut self, other: SharedData) {
+        merge_newer(&mut self.registers, other.registers);
+        merge_newer(&mut self.marks, other.marks);
+        merge_list(
+            &mut self.command_history,
+            other.command_history,
+            HISTORY_SIZE,
+            Clone::clone,
+        );
+        merge_list(
+            &mut self.search_history,
+            other.search_history,
+            HISTORY_SIZE,
+            Clone::clone,
+        );
+        merge_list(
+            &mut self.positions,
+            other.positions,
+            POSITIONS_SIZE,
+            |position| path_key(&position.path),
+        );
+    }
+
+    pub fn register(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name).map(|register| &register.value)
+    }
+
+    pub fn set_register(&mut self, name: char, text: &str, linewise: bool) {
+        let register = Register {
+            text: text.to_string(),
+            linewise,
+        };
+        self.registers.insert(name, Stamped::now(register));
+    }
+
+    /// Registers by name
+    pub fn registers(&self) -> impl Iterator<Item = (char, &Register)> {
+        self.registers
+            .iter()
+            .map(|(name, register)| (*name, &register.value))
+    }
+
+    pub fn mark(&self, name: char) -> Option<&FilePosition> {
+        self.marks.get(&name).map(|mark| &mark.value)
+    }
+
+    pub fn set_mark(&mut self, name: char, path: &Path, line: usize, col: usize) {
+        let mark = FilePosition {
+            path: path.to_path_buf(),
+            line,
+            col,
+        };
+        self.marks.insert(name, Stamped::now(mark));
+    }
+
+    /// Marks by name
+    pub fn marks(&self) -> impl Iterator<Item = (char, &FilePosition)> {
+        self.marks.iter().map(|(name, mark)| (*name, &mark.value))
+    }
+
+    /// Add a command line to the history, moving it to the end if it is in
+    /// there already
+    pub fn push_command(&mut self, command: &str) {
+        push_history(&mut self.command_history, command);
+    }
+
+    /// Command lines, oldest first
+    pub fn command_history(&self) -> impl Iterator<Item = &str> {
+        self.command_history.iter().map(|line| line.value.as_str())
+    }
+
+    /// Add a search pattern to the history, moving it to the end if it is
+    /// in there already
+    pub fn push_search(&mut self, pattern: &str) {
+        push_history(&mut self.search_history, pattern);
+    }
+
+    /// Search patterns, oldest first
+    pub fn search_history(&self) -> impl Iterator<Item = &str> {
+        self.search_history.iter().map(|line| line.value.as_str())
+    }
+
+    /// Where the cursor was when `path` was last left
+    pub fn position(&self, path: &Path) -> Option<&FilePosition> {
+        let key = path_key(path);
+        self.positions
+            .iter()
+            .rev()
+            .find(|position| path_key(&position.value.path) == key)
+            .map(|position| &position.value)
+    }
+
+    /// Remember where the cursor is in `path`, forgetting the file left
+    /// longest ago once there are too many
+    pub fn set_position(&mut self, path: &Path, line: usize, col: usize) {
+        let key = path_key(path);
+        self.positions
+            .retain(|position| path_key(&position.value.path) != key);
+        self.positions.push(Stamped::now(FilePosition {
+            path: path.to_path_buf(),
+            line,
+            col,
+        }));
+        keep_newest(&mut self.positions, POSITIONS_SIZE);
+    }
+
+    /// Text of the shada file: a header, then a line per entry, each with
+    /// its time after the kind of entry. Register text and history lines
+    /// have backslashes and newlines escaped.
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", SHADA_HEADER);
+        self.write_entries(&mut text, &SharedData::default());
+        text
+    }
+
+    /// Add a line to `text` for each entry, leaving out the ones `written`
+    /// has already
+    fn write_entries(&self, text: &mut String, written: &SharedData) {
+        for (name, register) in &self.registers {
+            if written.registers.get(name) == Some(register) {
+                continue;
+            }
+            let kind = if register.value.linewise {
+                "line"
+            } else {
+                "char"
+            };
+            let _ = writeln!(
+                text,
+                "register {} {} {} {}",
+                name,
+                register.time,
+                kind,
+                escape(&register.value.text)
+            );
+        }
+        for (name, mark) in &self.marks {
+            if written.marks.get(name) == Some(mark) {
+                continue;
+            }
+            let Some(position) = position_text(&mark.value) else {
+                continue;
+            };
+            let _ = writeln!(text, "mark {} {} {}", name, mark.time, position);
+        }
+        for line in unwritten(&self.command_history, &written.command_history) {
+            let _ = writeln!(text, "command {} {}", line.time, escape(&line.value));
+        }
+        for line in unwritten(&self.search_history, &written.search_history) {
+            let _ = writeln!(text, "search {} {}", line.time, escape(&line.value));
+        }
+        for position in unwritten(&self.positions, &written.positions) {
+            let Some(value) = position_text(&position.value) else {
+                continue;
+            };
+            let _ = writeln!(text, "position {} {}", position.time, value);
+        }
+    }
+
+    /// Read a shada file's text; None when it isn't one. Lines that are not
+    /// understood are skipped, and of the lines for one entry the last one
+    /// with the newest time is kept.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()? != SHADA_HEADER {
+            return None;
+        }
+        let mut data = SharedData::default();
+        for line in lines {
+            let Some((kind, rest)) = line.split_once(' ') else {
+                continue;
+            };
+            match kind {
+                "register" => {
+                    let Some((name, time, rest)) = named_entry(rest) else {
+                        continue;
+                    };
+                    let Some((kind, text)) = rest.split_once(' ') else {
+                        continue;
+                    };
+                    let register = Register {
+                        text: unescape(text),
+                        linewise: kind == "line",
+                    };
+                    insert_newer(
+                        &mut data.registers,
+                        name,
+                        Stamped {
+                            time,
+                            value: register,
+                        },
+                    );
+                }
+                "mark" => {
+                    let Some((name, time, rest)) = named_entry(rest) else {
+                        continue;
+                    };
+                    if let Some(mark) = parse_position(rest) {
+                        insert_newer(&mut data.marks, name, Stamped { time, value: mark });
+                    }
+                }
+                "command" | "search" | "position" => {
+                    let Some((time, rest)) = rest.split_once(' ') else {
+                        continue;
+                    };
+                    let Ok(time) = time.parse() else {
+                        continue;
+                    };
+                    match kind {
+                        "command" => data.command_history.push(Stamped {
+                            time,
+                            value: unescape(rest),
+                        }),
+                        "search" => data.search_history.push(Stamped {
+                            time,
+                            value: unescape(rest),
+                        }),
+                        _ => {
+                            if let Some(position) = parse_position(rest) {
+                                data.positions.push(Stamped {
+                                    time,
+                                    value: position,
+                                });
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Appended lines repeat entries, so the lists are merged into
+        // themselves to drop the older copies
+        for history in [&mut data.command_history, &mut data.search_history] {
+            merge_list(history, Vec::new(), HISTORY_SIZE, Clone::clone);
+        }
+        merge_list(
+            &mut data.positions,
+            Vec::new(),
+            POSITIONS_SIZE,
+            |position| path_key(&position.path),
+        );
+        Some(data)
+    }
+}
+
+/// Text of the file at `path`; None when it doesn't exist yet
+fn read_text(path: &Path) -> FsResult<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(FsError::io(IoOp::Read, path, e)),
+    }
+}
+
+fn parse_file(path: &Path, text: &str) -> FsResult<SharedData> {
+    SharedData::parse(text).ok_or_else(|| {
+        let invalid = io::Error::new(io::ErrorKind::InvalidData, "not a shada file");
+        FsError::io(IoOp::Read, path, invalid)
+    })
+}
+
+/// `text` without a last line that is still being appended
+fn complete_lines(text: &str) -> &str {
+    match text.rfind('\n') {
+        Some(end) => &text[..=end],
+        None => text,
+    }
+}
+
+/// Entries of `list` that are not in `written`
+fn unwritten<'a, T: PartialEq>(
+    list: &'a [Stamped<T>],
+    written: &'a [Stamped<T>],
+) -> impl Iterator<Item = &'a Stamped<T>> {
+    list.iter().filter(|entry| !written.contains(entry))
+}
+
+/// Keep `entry` unless an entry at `key` is newer, for the later of two
+/// lines to win a tie
+fn insert_newer<K: Ord, T>(map: &mut BTreeMap<K, Stamped<T>>, key: K, entry: Stamped<T>) {
+    match map.get(&key) {
+        Some(own) if own.time > entry.time => {}
+        _ => {
+            map.insert(key, entry);
+        }
+    }
+}
+
+/// Keep the newer of each entry of `ours` and `theirs`, ours on a tie
+fn merge_newer<K: Ord, T>(ours: &mut BTreeMap<K, Stamped<T>>, theirs: BTreeMap<K, Stamped<T>>) {
+    for (key, entry) in theirs {
+        match ours.get(&key) {
+            Some(own) if own.time >= entry.time => {}
+            _ => {
+                ours.insert(key, entry);
+            }
+        }
+    }
+}
+
+/// Combine two lists in time order, keeping the last entry for each key and
+/// the `limit` newest entries
+fn merge_list<T, K: Hash + Eq>(
+    ours: &mut Vec<Stamped<T>>,
+    theirs: Vec<Stamped<T>>,
+    limit: usize,
+    key: impl Fn(&T) -> K,
+) {
+    // Ours go after theirs, so the stable sort puts them last on a tie
+    let mut combined = theirs;
+    combined.append(ours);
+    combined.sort_by_key(|entry| entry.time);
+
+    let mut seen = HashSet::new();
+    let mut merged: Vec<_> = combined
+        .into_iter()
+        .rev()
+        .filter(|entry| seen.insert(key(&entry.value)))
+        .collect();
+    merged.reverse();
+    keep_newest(&mut merged, limit);
+    *ours = merged;
+}
+
+fn push_history(history: &mut Vec<Stamped<String>>, line: &str) {
+    history.retain(|entry| entry.value != line);
+    history.push(Stamped::now(line.to_string()));
+    keep_newest(history, HISTORY_SIZE);
+}
+
+/// Drop the oldest entries of a list past its `limit`
+fn keep_newest<T>(list: &mut Vec<T>, limit: usize) {
+    if list.len() > limit {
+        list.drain(..list.len() - limit);
+    }
+}
+
+/// `{name} {time} {rest}` of a register or mark line
+fn named_entry(text: &str) -> Option<(char, u64, &str)> {
+    let mut chars = text.chars();
+    let name = chars.next()?;
+    let (time, rest) = chars.as_str().strip_prefix(' ')?.split_once(' ')?;
+    Some((name, time.parse().ok()?, rest))
+}
+
+/// `{line} {col} {path}`
+/// `line col path` of `position`; None when its path isn't UTF-8, which
+/// the shada file can't hold
+fn position_text(position: &FilePosition) -> Option<String> {
+    let path = position.path.to_str()?;
+    Some(format!(
+        "{} {} {}",
+        position.line,
+        position.col,
+        escape(path)
+    ))
+}
+
+fn parse_position(text: &str) -> Option<FilePosition> {
+    let mut fields = text.splitn(3, ' ');
+    let (line, col, path) = (fields.next()?, fields.next()?, fields.next()?);
+    Some(FilePosition {
+        path: PathBuf::from(unescape(path)),
+        line: line.parse().ok()?,
+        col: col.parse().ok()?,
+    })
+}
+
+/// Keep `text` on one line of the shada file
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            next => unescaped.extend(next),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+
+    #[test]
+    fn test_shada_text_round_trip() {
+        let mut data = SharedData::new();
+        data.set_register('a', "two\nlines\\", true);
+        data.set_mark('A', Path::new("/tmp/with space.txt"), 3, 7);
+        data.push_command("w");
+        data.push_command("e foo");
+        data.push_command("w");
+        data.push_search("needle");
+        data.set_position(Path::new("/tmp/a.txt"), 10, 2);
+
+        let parsed = SharedData::parse(&data.to_text()).unwrap();
+        assert_eq!(parsed, data);
+        assert_eq!(parsed.register('a').unwrap().text, "two\nlines\\");
+        assert_eq!(
+            parsed.mark('A').unwrap().path,
+            Path::new("/tmp/with space.txt")
+        );
+        assert_eq!(parsed.command_history().collect::<Vec<_>>(), ["e foo", "w"]);
+        assert_eq!(parsed.position(Path::new("/tmp/a.txt")).unwrap().line, 10);
+        assert!(SharedData::parse("not shada\n").is_none());
+    }
+
+    #[test]
+    fn test_shada_positions_keep_odd_paths() {
+        let mut data = SharedData::new();
+        data.set_mark('B', Path::new("/tmp/two\nlines\\.txt"), 1, 0);
+        data.set_position(Path::new("/tmp/cr\r.txt"), 4, 1);
+
+        let text = data.to_text();
+        assert_eq!(text.lines().count(), 3);
+        let parsed = SharedData::parse(&text).unwrap();
+        assert_eq!(parsed, data);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/\xff.txt"));
+            data.set_position(path, 2, 0);
+            let parsed = SharedData::parse(&data.to_text()).unwrap();
+            assert!(parsed.position(path).is_none());
+            assert!(parsed.position(Path::new("/tmp/cr\r.txt")).is_some());
+        }
+    }
+
+    #[test]
+    fn test_shada_merge_keeps_newer_entries() {
+        let mut ours = SharedData::parse(
+            "niv shada\n\
+             register a 20 char ours\n\
+             mark A 10 1 0 /ours\n\
+             command 10 first\n\
+             command 30 third\n\
+             position 5 1 1 /a\n",
+        )
+        .unwrap();
+        let theirs = SharedData::parse(
+            "niv shada\n\
+             register a 10 char theirs\n\
+             register b 10 line other\n\
+             mark A 20 2 0 /theirs\n\
+             command 20 second\n\
+             command 25 first\n\
+             position 6 2 2 /a\n\
+             position 7 3 3 /b\n",
+        )
+        .unwrap();
+        ours.merge(theirs);
+
+        assert_eq!(ours.register('a').unwrap().text, "ours");
+        assert_eq!(ours.register('b').unwrap().text, "other");
+        assert_eq!(ours.mark('A').unwrap().path, Path::new("/theirs"));
+        assert_eq!(
+            ours.command_history().collect::<Vec<_>>(),
+            ["second", "first", "third"]
+        );
+        assert_eq!(ours.position(Path::new("/a")).unwrap().line, 2);
+        assert_eq!(ours.position(Path::new("/b")).unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_shada_write_merges_other_instances() {
+        let dir = TempDir::new("shada");
+        let path = dir.join("write");
+
+        let mut first = SharedData::load(&path).unwrap();
+        let mut second = SharedData::load(&path).unwrap();
+        first.push_search("from first");
+        first.write(&path).unwrap();
+        second.push_search("from second");
+        second.write(&path).unwrap();
+
+        let merged = SharedData::load(&path).unwrap();
+        let history: Vec<_> = merged.search_history().collect();
+        assert!(history.contains(&"from first"));
+        assert!(history.contains(&"from second"));
+
+        // A file that isn't a shada file is left alone
+        fs::write(&path, "something else").unwrap();
+        assert!(SharedData::new().write(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "something else");
+    }
+
+    #[test]
+    fn test_shada_write_appends_then_compacts() {
+        let dir = TempDir::new("shada");
+        let path = dir.join("journal");
+        let lines = |path: &Path| fs::read_to_string(path).unwrap().lines().count();
+
+        let mut data = SharedData::new();
+        data.set_mark('A', Path::new("/a"), 1, 0);
+        data.write(&path).unwrap();
+        assert_eq!(lines(&path), 2);
+
+        // Only the new entry goes in, after what is there
+        let mut other = SharedData::new();
+        other.push_command("w");
+        other.write(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.lines().last().unwrap().starts_with("command "));
+        assert_eq!(lines(&path), 3);
+        other.write(&path).unwrap();
+        assert_eq!(lines(&path), 3);
+
+        // Setting the mark again repeats it, the last line winning within
+        // a second, until the file is compacted
+        for line in 2..6 {
+            data.set_mark('A', Path::new("/a"), line, 0);
+            data.write(&path).unwrap();
+        }
+        assert_eq!(lines(&path), 3);
+        let loaded = SharedData::load(&path).unwrap();
+        assert_eq!(loaded.mark('A').unwrap().line, 5);
+        assert_eq!(loaded.command_history().collect::<Vec<_>>(), ["w"]);
+
+        // A line cut short by a crash is left out, then compacted away
+        let mut text = fs::read_to_string(&path).unwrap();
+        text.push_str("command 1 cut sh");
+        fs::write(&path, &text).unwrap();
+        let mut loaded = SharedData::load(&path).unwrap();
+        assert_eq!(loaded.command_history().count(), 1);
+        loaded.write(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), loaded.to_text());
+    }
+
+    #[test]
+    fn test_shada_concurrent_writes_lose_nothing() {
+        let dir = TempDir::new("shada");
+        let path = dir.join("concurrent");
+
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let mut data = SharedData::new();
+                    for search in 0..5 {
+                        data.push_search(&format!("{} {}", writer, search));
+                        data.write(&path).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let merged = SharedData::load(&path).unwrap();
+        assert_eq!(merged.search_history().count(), 20);
+    }
+}
@@ -6,8 +6,10 @@
 //! - Cursor and viewport state preservation
 //! - Crash recovery detection and restoration prompts
 //! - Draft management for untitled buffers with UUIDs
+//! - Per-file swap exclusion for sensitive buffers (e.g. `*.gpg`)
+//! - Optional encryption of swap and draft files with a session key
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -16,6 +18,10 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use crate::crypt::{self, KeyProvider, SessionKey};
+use crate::error::{IoContext, IoOp};
+use crate::file::identity::path_key;
+
 /// Errors that can occur during swap operations
 #[derive(Debug)]
 pub enum SwapError {
@@ -78,7 +84,7 @@ pub type DraftResult<T> = Result<T, DraftError>;
 /// Configuration for swap file behavior
 #[derive(Debug, Clone)]
 pub struct SwapConfig {
-    /// Directory for swap files (defaults to system temp)
+    /// Directory for swap files (defaults to `swap` in the state directory)
     pub swap_dir: PathBuf,
     /// Directory for draft files
     pub draft_dir: PathBuf,
@@ -92,18 +98,29 @@ pub struct SwapConfig {
     pub save_viewport: bool,
     /// Maximum swap file age before cleanup (days)
     pub max_age_days: u64,
+    /// Glob patterns for files that must never get a swap file
+    pub exclude_patterns: Vec<String>,
 }
 
+/// File in the swap directory holding the salt of the session key
+const SALT_FILE: &str = ".salt";
+
 impl Default for SwapConfig {
     fn default() -> Self {
         Self {
-            swap_dir: std::env::temp_dir().join("niv_swap"),
-            draft_dir: std::env::temp_dir().join("niv_swap").join("drafts"),
+            swap_dir: crate::paths::state_dir().join("swap"),
+            draft_dir: crate::paths::state_dir().join("swap").join("drafts"),
             edits_threshold: 10,
             idle_timeout: Duration::from_secs(5),
             save_cursor: true,
             save_viewport: true,
             max_age_days: 7,
+            exclude_patterns: vec![
+                "*.gpg".to_string(),
+                "*.asc".to_string(),
+                "*.pgp".to_string(),
+                "*.age".to_string(),
+            ],
         }
     }
 }
@@ -162,27 +179,48 @@ pub struct SwapContent {
 /// Swap manager for handling swap files and crash recovery
 pub struct SwapManager {
     config: SwapConfig,
+    /// Swap state of files by [`path_key`], so that one file has one swap
+    /// however the case of its name is written
     active_swaps: HashMap<PathBuf, SwapContent>,
     last_save: HashMap<PathBuf, Instant>,
     edit_counts: HashMap<PathBuf, usize>,
+    /// Files whose swap has been disabled explicitly (e.g. `:setlocal noswapfile`)
+    disabled: HashSet<PathBuf>,
+    /// Key swap files are encrypted with, once encryption is enabled
+    key: Option<SessionKey>,
     is_running: Arc<AtomicBool>,
 }
 
 impl SwapManager {
     pub fn new(config: SwapConfig) -> SwapResult<Self> {
         // Create swap directories
-        fs::create_dir_all(&config.swap_dir)?;
-        fs::create_dir_all(&config.draft_dir)?;
+        fs::create_dir_all(&config.swap_dir).io_context(IoOp::CreateDir, &config.swap_dir)?;
+        fs::create_dir_all(&config.draft_dir).io_context(IoOp::CreateDir, &config.draft_dir)?;
 
         Ok(Self {
             config,
             active_swaps: HashMap::new(),
             last_save: HashMap::new(),
             edit_counts: HashMap::new(),
+            disabled: HashSet::new(),
+            key: None,
             is_running: Arc::new(AtomicBool::new(true)),
         })
     }
 
+    /// Encrypt the swap files written from now on with the session key of
+    /// `provider`, and decrypt the ones read with it. Swap files written
+    /// before encryption was enabled can still be read.
+    pub fn enable_encryption(&mut self, provider: &dyn KeyProvider) -> SwapResult<()> {
+        self.key = Some(session_key(&self.config, provider)?);
+        Ok(())
+    }
+
+    /// Key swap files are encrypted with, for drafts to share
+    pub fn session_key(&self) -> Option<&SessionKey> {
+        self.key.as_ref()
+    }
+
     /// Start the periodic swap writer
     pub fn start_periodic_save(&self) -> SwapResult<()> {
         let config = self.config.clone();
@@ -207,8 +245,35 @@ impl SwapManager {
         self.is_running.store(false, Ordering::Relaxed);
     }
 
+    /// Check whether swap files are disabled for the given path.
+    ///
+    /// A file is excluded when it was disabled explicitly or matches one of
+    /// the configured `exclude_patterns`.
+    pub fn is_swap_disabled(&self, file_path: &Path) -> bool {
+        self.disabled.contains(&path_key(file_path))
+            || crate::glob::path_matches_any(&self.config.exclude_patterns, file_path)
+    }
+
+    /// Enable or disable swap files for a single file.
+    ///
+    /// Disabling drops the in-memory copy of the content and removes any swap
+    /// file already written, so no plaintext is left behind in the swap directory.
+    pub fn set_swap_disabled(&mut self, file_path: &Path, disabled: bool) -> SwapResult<()> {
+        if disabled {
+            self.delete_swap(file_path)?;
+            self.disabled.insert(path_key(file_path));
+        } else {
+            self.disabled.remove(&path_key(file_path));
+        }
+        Ok(())
+    }
+
     /// Register a file for swap management
     pub fn register_file(&mut self, file_path: &Path, initial_content: &str) -> SwapResult<()> {
+        if self.is_swap_disabled(file_path) {
+            return Ok(());
+        }
+
         let swap_content = SwapContent {
             content: initial_content.to_string(),
             original_path: Some(file_path.to_path_buf()),
@@ -221,11 +286,10 @@ impl SwapManager {
                 .as_secs(),
         };
 
-        self.active_swaps
-            .insert(file_path.to_path_buf(), swap_content);
-        self.edit_counts.insert(file_path.to_path_buf(), 0);
-        self.last_save
-            .insert(file_path.to_path_buf(), Instant::now());
+        let key = path_key(file_path);
+        self.active_swaps.insert(key.clone(), swap_content);
+        self.edit_counts.insert(key.clone(), 0);
+        self.last_save.insert(key, Instant::now());
 
         Ok(())
     }
@@ -238,10 +302,15 @@ impl SwapManager {
         cursor: Option<CursorPosition>,
         viewport: Option<ViewportState>,
     ) -> SwapResult<bool> {
-        let edit_count = self.edit_counts.entry(file_path.to_path_buf()).or_insert(0);
+        if self.is_swap_disabled(file_path) {
+            return Ok(false);
+        }
+
+        let key = path_key(file_path);
+        let edit_count = self.edit_counts.entry(key.clone()).or_insert(0);
         *edit_count += 1;
 
-        if let Some(swap_content) = self.active_swaps.get_mut(file_path) {
+        if let Some(swap_content) = self.active_swaps.get_mut(&key) {
             swap_content.content = new_content.to_string();
             swap_content.edit_count = *edit_count;
 
@@ -263,8 +332,7 @@ impl SwapManager {
         let should_save = self.should_save_swap(file_path);
         if should_save {
             self.save_swap(file_path)?;
-            self.last_save
-                .insert(file_path.to_path_buf(), Instant::now());
+            self.last_save.insert(key, Instant::now());
         }
 
         Ok(should_save)
@@ -272,8 +340,9 @@ impl SwapManager {
 
     /// Check if swap should be saved based on edits or idle time
     fn should_save_swap(&self, file_path: &Path) -> bool {
-        let edit_count = self.edit_counts.get(file_path).unwrap_or(&0);
-        let last_save = self.last_save.get(file_path);
+        let key = path_key(file_path);
+        let edit_count = self.edit_counts.get(&key).unwrap_or(&0);
+        let last_save = self.last_save.get(&key);
 
         // Save if edit threshold reached
         if *edit_count >= self.config.edits_threshold {
@@ -292,17 +361,24 @@ impl SwapManager {
 
     /// Save swap file for the given path
     pub fn save_swap(&mut self, file_path: &Path) -> SwapResult<()> {
-        if let Some(swap_content) = self.active_swaps.get(file_path) {
+        if self.is_swap_disabled(file_path) {
+            return Ok(());
+        }
+
+        let key = path_key(file_path);
+        if let Some(swap_content) = self.active_swaps.get(&key) {
             let swap_path = self.get_swap_path(file_path)?;
             let serialized = self.serialize_swap_content(swap_content)?;
+            let data =
+                encrypt(self.key.as_ref(), serialized).io_context(IoOp::Write, &swap_path)?;
 
             // Write to temporary file first, then rename for atomicity
             let temp_path = swap_path.with_extension("tmp");
-            fs::write(&temp_path, serialized)?;
-            fs::rename(&temp_path, &swap_path)?;
+            fs::write(&temp_path, data).io_context(IoOp::Write, &temp_path)?;
+            fs::rename(&temp_path, &swap_path).io_context(IoOp::Rename, &swap_path)?;
 
             // Reset edit count after successful save
-            if let Some(edit_count) = self.edit_counts.get_mut(file_path) {
+            if let Some(edit_count) = self.edit_counts.get_mut(&key) {
                 *edit_count = 0;
             }
         }
@@ -324,7 +400,7 @@ impl SwapManager {
             return Ok(None);
         }
 
-        let metadata = fs::metadata(&swap_path)?;
+        let metadata = fs::metadata(&swap_path).io_context(IoOp::Metadata, &swap_path)?;
         let created = metadata.created().unwrap_or(SystemTime::UNIX_EPOCH);
         let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
 
@@ -334,7 +410,11 @@ impl SwapManager {
             Err(_) => SwapStatus::Corrupted,
         };
 
-        let edit_count = self.edit_counts.get(file_path).copied().unwrap_or(0);
+        let edit_count = self
+            .edit_counts
+            .get(&path_key(file_path))
+            .copied()
+            .unwrap_or(0);
 
         Ok(Some(SwapFile {
             original_path: file_path.to_path_buf(),
@@ -348,7 +428,7 @@ impl SwapManager {
 
     /// Read swap content from file
     pub fn read_swap(&self, swap_path: &Path) -> SwapResult<SwapContent> {
-        let content = fs::read_to_string(swap_path)?;
+        let content = read_text(swap_path, self.key.as_ref())?;
         let swap_content = self.deserialize_swap_content(&content)?;
         Ok(swap_content)
     }
@@ -363,23 +443,75 @@ impl SwapManager {
     pub fn delete_swap(&mut self, file_path: &Path) -> SwapResult<()> {
         let swap_path = self.get_swap_path(file_path)?;
         if swap_path.exists() {
-            fs::remove_file(&swap_path)?;
+            fs::remove_file(&swap_path).io_context(IoOp::Remove, &swap_path)?;
+        }
+        let key = path_key(file_path);
+        self.active_swaps.remove(&key);
+        self.edit_counts.remove(&key);
+        self.last_save.remove(&key);
+        Ok(())
+    }
+
+    /// Move swap tracking of a file to its new path after a rename.
+    ///
+    /// An existing swap file is rewritten under the new name and the old one removed.
+    pub fn rename_file(&mut self, old_path: &Path, new_path: &Path) -> SwapResult<()> {
+        let (old_key, new_key) = (path_key(old_path), path_key(new_path));
+        if self.disabled.remove(&old_key) {
+            self.disabled.insert(new_key.clone());
+        }
+        if let Some(count) = self.edit_counts.remove(&old_key) {
+            self.edit_counts.insert(new_key.clone(), count);
+        }
+        if let Some(saved) = self.last_save.remove(&old_key) {
+            self.last_save.insert(new_key.clone(), saved);
+        }
+
+        let had_swap = self.has_swap(old_path)?;
+        if had_swap {
+            let old_swap = self.get_swap_path(old_path)?;
+            fs::remove_file(&old_swap).io_context(IoOp::Remove, &old_swap)?;
+        }
+        if let Some(mut swap_content) = self.active_swaps.remove(&old_key) {
+            swap_content.original_path = Some(new_path.to_path_buf());
+            self.active_swaps.insert(new_key, swap_content);
+            if had_swap {
+                self.save_swap(new_path)?;
+            }
         }
-        self.active_swaps.remove(file_path);
-        self.edit_counts.remove(file_path);
-        self.last_save.remove(file_path);
         Ok(())
     }
 
-    /// Get the swap file path for a given file
+    /// Get the swap file path for a given file: `.~{name}.{hash}.swp`, with
+    /// the hash of the file's full path so that files of the same name in
+    /// different directories get swaps of their own. A swap of the file in
+    /// the old `.~{name}` form is moved there first.
     fn get_swap_path(&self, file_path: &Path) -> SwapResult<PathBuf> {
-        let file_name = file_path
+        let key = swap_key(file_path);
+        let file_name = key
             .file_name()
             .and_then(|n| n.to_str())
             .ok_or_else(|| SwapError::PathError("Invalid file name".to_string()))?;
 
-        let swap_name = format!(".~{}", file_name);
-        Ok(self.config.swap_dir.join(swap_name))
+        let swap_name = format!(".~{}.{:016x}.swp", file_name, path_hash(&key));
+        let swap_path = self.config.swap_dir.join(swap_name);
+        let legacy_path = self.config.swap_dir.join(format!(".~{}", file_name));
+        if !swap_path.exists() && self.is_legacy_swap_of(&legacy_path, &key) {
+            fs::rename(&legacy_path, &swap_path).io_context(IoOp::Rename, &legacy_path)?;
+        }
+        Ok(swap_path)
+    }
+
+    /// Check whether `legacy_path` is an old-format swap whose header names
+    /// the file with swap key `key`. The name alone could be any file's.
+    fn is_legacy_swap_of(&self, legacy_path: &Path, key: &Path) -> bool {
+        if !legacy_path.is_file() {
+            return false;
+        }
+        self.read_swap(legacy_path)
+            .ok()
+            .and_then(|swap| swap.original_path)
+            .is_some_and(|original| swap_key(&original) == key)
     }
 
     /// Serialize swap content to a simple text format
@@ -498,9 +630,13 @@ impl SwapManager {
         let max_age = Duration::from_secs(config.max_age_days * 24 * 60 * 60);
         let now = SystemTime::now();
 
-        for entry in fs::read_dir(&config.swap_dir)? {
-            let entry = entry?;
+        for entry in fs::read_dir(&config.swap_dir).io_context(IoOp::ReadDir, &config.swap_dir)? {
+            let entry = entry.io_context(IoOp::ReadDir, &config.swap_dir)?;
             let path = entry.path();
+            // The salt lasts as long as the files encrypted with its key
+            if entry.file_name() == SALT_FILE {
+                continue;
+            }
 
             if let Ok(metadata) = entry.metadata() {
                 if let Ok(modified) = metadata.modified() {
@@ -517,14 +653,66 @@ impl SwapManager {
     }
 }
 
+/// Session key of `provider` for the swap and draft files of `config`
+fn session_key(config: &SwapConfig, provider: &dyn KeyProvider) -> io::Result<SessionKey> {
+    fs::create_dir_all(&config.swap_dir).io_context(IoOp::CreateDir, &config.swap_dir)?;
+    crypt::load_session_key(&config.swap_dir.join(SALT_FILE), provider)
+}
+
+/// Bytes of a swap or draft file, encrypted when there is a key
+fn encrypt(key: Option<&SessionKey>, text: String) -> io::Result<Vec<u8>> {
+    match key {
+        Some(key) => crypt::seal(key, text.as_bytes()),
+        None => Ok(text.into_bytes()),
+    }
+}
+
+/// Text of a swap or draft file, decrypted with `key` if it is encrypted
+fn read_text(path: &Path, key: Option<&SessionKey>) -> io::Result<String> {
+    let data = fs::read(path).io_context(IoOp::Read, path)?;
+    let text = crypt::open(key, data).io_context(IoOp::Read, path)?;
+    String::from_utf8(text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        .io_context(IoOp::Read, path)
+}
+
+/// Full path of a file as swaps are named by: absolute, and with the case
+/// folded on file systems that ignore it
+fn swap_key(file_path: &Path) -> PathBuf {
+    path_key(&std::path::absolute(file_path).unwrap_or_else(|_| file_path.to_path_buf()))
+}
+
+/// Hash of a path, the same across builds (FNV-1a)
+fn path_hash(path: &Path) -> u64 {
+    path.to_string_lossy()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
 /// Manager for untitled buffer drafts
 pub struct DraftManager {
     config: SwapConfig,
+    /// Key drafts are encrypted with, once encryption is enabled
+    key: Option<SessionKey>,
 }
 
 impl DraftManager {
     pub fn new(config: SwapConfig) -> Self {
-        Self { config }
+        Self { config, key: None }
+    }
+
+    /// Encrypt the drafts saved from now on with the session key of
+    /// `provider`, and decrypt the ones read with it
+    pub fn enable_encryption(&mut self, provider: &dyn KeyProvider) -> DraftResult<()> {
+        self.key = Some(session_key(&self.config, provider)?);
+        Ok(())
+    }
+
+    /// Encrypt drafts with a key made already, such as the swap files' one
+    pub fn set_session_key(&mut self, key: SessionKey) {
+        self.key = Some(key);
     }
 
     /// Save untitled buffer as draft
@@ -556,7 +744,8 @@ impl DraftManager {
         };
 
         let serialized = self.serialize_swap_content(&draft_content)?;
-        fs::write(&draft_path, serialized)?;
+        let data = encrypt(self.key.as_ref(), serialized).io_context(IoOp::Write, &draft_path)?;
+        fs::write(&draft_path, data).io_context(IoOp::Write, &draft_path)?;
         Ok(draft_path)
     }
 
@@ -568,12 +757,13 @@ impl DraftManager {
 
         let mut drafts = Vec::new();
 
-        for entry in fs::read_dir(&self.config.draft_dir)? {
-            let entry = entry?;
+        let draft_dir = &self.config.draft_dir;
+        for entry in fs::read_dir(draft_dir).io_context(IoOp::ReadDir, draft_dir)? {
+            let entry = entry.io_context(IoOp::ReadDir, draft_dir)?;
             let path = entry.path();
 
             if path.extension().and_then(|e| e.to_str()) == Some("txt") {
-                if let Ok(content) = fs::read_to_string(&path) {
+                if let Ok(content) = read_text(&path, self.key.as_ref()) {
                     if let Ok(draft_content) = self.deserialize_swap_content(&content) {
                         drafts.push((path, draft_content));
                     }
@@ -589,7 +779,7 @@ impl DraftManager {
 
     /// Load draft content
     pub fn load_draft(&self, draft_path: &Path) -> DraftResult<SwapContent> {
-        let content = fs::read_to_string(draft_path)?;
+        let content = read_text(draft_path, self.key.as_ref())?;
         let draft_content = self.deserialize_swap_content(&content)?;
         Ok(draft_content)
     }
@@ -704,7 +894,7 @@ impl DraftManager {
     /// Delete draft
     pub fn delete_draft(&self, draft_path: &Path) -> DraftResult<()> {
         if draft_path.exists() {
-            fs::remove_file(draft_path)?;
+            fs::remove_file(draft_path).io_context(IoOp::Remove, draft_path)?;
         }
         Ok(())
     }
@@ -718,8 +908,9 @@ impl DraftManager {
         let max_age = Duration::from_secs(self.config.max_age_days * 24 * 60 * 60);
         let now = SystemTime::now();
 
-        for entry in fs::read_dir(&self.config.draft_dir)? {
-            let entry = entry?;
+        let draft_dir = &self.config.draft_dir;
+        for entry in fs::read_dir(draft_dir).io_context(IoOp::ReadDir, draft_dir)? {
+            let entry = entry.io_context(IoOp::ReadDir, draft_dir)?;
             let path = entry.path();
 
             if let Ok(metadata) = entry.metadata() {
@@ -740,31 +931,164 @@ impl DraftManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::env;
+    use crate::testing::TempDir;
 
-    fn test_config() -> SwapConfig {
-        let temp_dir = env::temp_dir().join("niv_swap_test");
+    fn test_config(dir: &TempDir) -> SwapConfig {
         SwapConfig {
-            swap_dir: temp_dir.clone(),
-            draft_dir: temp_dir.join("drafts"),
+            swap_dir: dir.path().to_path_buf(),
+            draft_dir: dir.join("drafts"),
             edits_threshold: 5,
             idle_timeout: Duration::from_millis(100),
             save_cursor: true,
             save_viewport: true,
             max_age_days: 1,
+            exclude_patterns: vec!["*.gpg".to_string()],
         }
     }
 
     #[test]
     fn test_swap_manager_creation() {
-        let config = test_config();
+        let dir = TempDir::new("swap_test");
+        let config = test_config(&dir);
         let manager = SwapManager::new(config);
         assert!(manager.is_ok());
     }
 
+    #[test]
+    fn test_sensitive_files_never_get_swap() {
+        let dir = TempDir::new("swap_test_sensitive");
+        let mut config = test_config(&dir);
+        config.edits_threshold = 1;
+        let mut manager = SwapManager::new(config).unwrap();
+
+        let secret = Path::new("/tmp/niv_swap_sensitive/secret.gpg");
+        manager.register_file(secret, "top secret").unwrap();
+        assert!(!manager.update_content(secret, "top secret!", None, None).unwrap());
+        assert!(!manager.has_swap(secret).unwrap());
+
+        let plain = Path::new("/tmp/niv_swap_sensitive/notes.txt");
+        manager.register_file(plain, "hello").unwrap();
+        assert!(manager.update_content(plain, "hello!", None, None).unwrap());
+        assert!(manager.has_swap(plain).unwrap());
+
+        // Disabling afterwards removes the swap that was already written
+        manager.set_swap_disabled(plain, true).unwrap();
+        assert!(manager.is_swap_disabled(plain));
+        assert!(!manager.has_swap(plain).unwrap());
+    }
+
+    #[test]
+    fn test_rename_moves_swap() {
+        let dir = TempDir::new("swap_test_rename");
+        let mut config = test_config(&dir);
+        config.edits_threshold = 1;
+        let mut manager = SwapManager::new(config).unwrap();
+
+        let old_path = Path::new("/tmp/niv_swap_rename/before.txt");
+        let new_path = Path::new("/tmp/niv_swap_rename/after.txt");
+        manager.register_file(old_path, "hello").unwrap();
+        manager.update_content(old_path, "hello!", None, None).unwrap();
+        assert!(manager.has_swap(old_path).unwrap());
+
+        manager.rename_file(old_path, new_path).unwrap();
+        assert!(!manager.has_swap(old_path).unwrap());
+        assert!(manager.has_swap(new_path).unwrap());
+        let recovered = manager.recover_swap(new_path).unwrap();
+        assert_eq!(recovered.original_path.as_deref(), Some(new_path));
+
+        manager.delete_swap(new_path).unwrap();
+    }
+
+    #[test]
+    fn test_same_name_files_get_their_own_swaps() {
+        let dir = TempDir::new("swap_test_same_name");
+        let mut config = test_config(&dir);
+        config.edits_threshold = 1;
+        let mut manager = SwapManager::new(config).unwrap();
+
+        let a = Path::new("/tmp/niv_swap_same_name/a/mod.rs");
+        let b = Path::new("/tmp/niv_swap_same_name/b/mod.rs");
+        manager.register_file(a, "mod a").unwrap();
+        manager.register_file(b, "mod b").unwrap();
+        manager.update_content(a, "mod a;", None, None).unwrap();
+        manager.update_content(b, "mod b;", None, None).unwrap();
+
+        assert_ne!(
+            manager.get_swap_path(a).unwrap(),
+            manager.get_swap_path(b).unwrap()
+        );
+        assert_eq!(manager.recover_swap(a).unwrap().content, "mod a;");
+        assert_eq!(manager.recover_swap(b).unwrap().content, "mod b;");
+
+        manager.delete_swap(a).unwrap();
+        manager.delete_swap(b).unwrap();
+    }
+
+    #[test]
+    fn test_old_format_swap_is_migrated() {
+        let dir = TempDir::new("swap_test_migrate");
+        let config = test_config(&dir);
+        let legacy_path = config.swap_dir.join(".~notes.txt");
+        let mut manager = SwapManager::new(config).unwrap();
+
+        let file = Path::new("/tmp/niv_swap_migrate/notes.txt");
+        let other = Path::new("/tmp/niv_swap_migrate/other/notes.txt");
+        let legacy = SwapContent {
+            content: "unsaved".to_string(),
+            original_path: Some(file.to_path_buf()),
+            edit_count: 3,
+            cursor_position: None,
+            viewport_state: None,
+            timestamp: 0,
+        };
+        let serialized = manager.serialize_swap_content(&legacy).unwrap();
+        fs::write(&legacy_path, serialized).unwrap();
+
+        // Only the file named in its header takes the old swap over
+        assert!(!manager.has_swap(other).unwrap());
+        assert!(legacy_path.exists());
+        assert!(manager.has_swap(file).unwrap());
+        assert!(!legacy_path.exists());
+        assert_eq!(manager.recover_swap(file).unwrap().content, "unsaved");
+
+        manager.delete_swap(file).unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_swap_needs_its_key() {
+        let dir = TempDir::new("swap_test_encrypt");
+        let mut config = test_config(&dir);
+        config.edits_threshold = 1;
+        let mut manager = SwapManager::new(config.clone()).unwrap();
+        let passphrase = crypt::PassphraseKey::new("hunter2").with_iterations(10);
+        manager.enable_encryption(&passphrase).unwrap();
+
+        let file = Path::new("/tmp/niv_swap_encrypt/diary.txt");
+        manager.register_file(file, "dear diary").unwrap();
+        manager.update_content(file, "dear diary,", None, None).unwrap();
+        let swap_path = manager.get_swap_path(file).unwrap();
+        let on_disk = fs::read(&swap_path).unwrap();
+        assert!(!String::from_utf8_lossy(&on_disk).contains("dear diary"));
+        assert_eq!(manager.recover_swap(file).unwrap().content, "dear diary,");
+
+        // Another instance with the same passphrase reads it; others can't
+        let mut other = SwapManager::new(config.clone()).unwrap();
+        other.enable_encryption(&passphrase).unwrap();
+        assert_eq!(other.recover_swap(file).unwrap().content, "dear diary,");
+        let mut wrong = SwapManager::new(config.clone()).unwrap();
+        let guess = crypt::PassphraseKey::new("hunter3").with_iterations(10);
+        wrong.enable_encryption(&guess).unwrap();
+        assert!(wrong.recover_swap(file).is_err());
+        let plain = SwapManager::new(config).unwrap();
+        assert!(plain.recover_swap(file).is_err());
+
+        manager.delete_swap(file).unwrap();
+    }
+
     #[test]
     fn test_draft_manager_creation() {
-        let config = test_config();
+        let dir = TempDir::new("swap_test");
+        let config = test_config(&dir);
         let _manager = DraftManager::new(config);
         // DraftManager has no fallible operations in constructor
     }
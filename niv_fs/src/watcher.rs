@@ -3,21 +3,23 @@
 //! This module provides:
 //! - Cross-platform file system watching (polling-based for no external deps)
 //! - Debounced change detection
+//! - Adaptive polling, backing off for files that stay unchanged
 //! - Three-way merge conflict resolution
 //! - Auto-reload for clean buffers
 //! - Rename/move following using file identity
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant, SystemTime};
 
-use crate::file::identity::{FileIdentity, FileIdentityConfig};
+use crate::error::{IoContext, IoOp};
+use crate::file::identity::{FileIdentity, FileIdentityConfig, path_key};
 
 /// Errors that can occur during file watching operations
 #[derive(Debug)]
@@ -58,6 +60,16 @@ pub type WatcherResult<T> = Result<T, WatcherError>;
 pub struct WatcherConfig {
     /// How often to poll for file changes
     pub poll_interval: Duration,
+    /// Longest time between polls of a file that stays unchanged; files
+    /// unchanged for `idle_after` are polled half as often after every poll,
+    /// up to this
+    pub max_poll_interval: Duration,
+    /// How long a file must stay unchanged before its polling backs off
+    pub idle_after: Duration,
+    /// Number of files of one directory polled at once from which the
+    /// directory is listed in one pass instead of reading the metadata of
+    /// each file; 0 never lists directories
+    pub dir_scan_threshold: usize,
     /// Delay before considering a change stable (debouncing)
     pub debounce_delay: Duration,
     /// Whether to auto-reload when buffer is clean
@@ -70,6 +82,9 @@ impl Default for WatcherConfig {
     fn default() -> Self {
         Self {
             poll_interval: Duration::from_millis(500),
+            max_poll_interval: Duration::from_secs(8),
+            idle_after: Duration::from_secs(30),
+            dir_scan_threshold: 8,
             debounce_delay: Duration::from_millis(100),
             auto_reload: true,
             max_snapshots: 10,
@@ -77,6 +92,70 @@ impl Default for WatcherConfig {
     }
 }
 
+/// How closely the watcher thread follows a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchPriority {
+    /// Polled every `poll_interval`, e.g. the file being edited
+    High,
+    /// Polled every `poll_interval` until unchanged for `idle_after`, then
+    /// less and less often
+    #[default]
+    Normal,
+    /// Polled every `max_poll_interval`, e.g. files in hidden buffers
+    Low,
+}
+
+/// When the watcher thread polls a file next
+#[derive(Debug, Clone)]
+struct PollSchedule {
+    interval: Duration,
+    next_poll: Instant,
+    last_change: Instant,
+}
+
+impl PollSchedule {
+    fn new(now: Instant, config: &WatcherConfig) -> Self {
+        Self {
+            interval: config.poll_interval,
+            next_poll: now,
+            last_change: now,
+        }
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        now >= self.next_poll
+    }
+
+    /// Poll on the next tick again, as after a change
+    fn wake(&mut self, now: Instant, config: &WatcherConfig) {
+        self.interval = config.poll_interval;
+        self.next_poll = now;
+        self.last_change = now;
+    }
+
+    /// Schedule the next poll after one at `now` that found the file
+    /// `changed` or not
+    fn polled(
+        &mut self,
+        now: Instant,
+        changed: bool,
+        priority: WatchPriority,
+        config: &WatcherConfig,
+    ) {
+        if changed {
+            self.last_change = now;
+        }
+        let idle = now.duration_since(self.last_change) >= config.idle_after;
+        self.interval = match priority {
+            WatchPriority::High => config.poll_interval,
+            WatchPriority::Low => config.max_poll_interval,
+            WatchPriority::Normal if changed || !idle => config.poll_interval,
+            WatchPriority::Normal => (self.interval * 2).min(config.max_poll_interval),
+        };
+        self.next_poll = now + self.interval;
+    }
+}
+
 /// Types of file changes
 #[derive(Debug, Clone, PartialEq)]
 pub enum ChangeType {
@@ -165,14 +244,32 @@ pub enum WatchEvent {
 /// File watcher for external change detection and conflict resolution
 pub struct FileWatcher {
     config: WatcherConfig,
+    /// Watched files by [`path_key`], so that a file is watched once however
+    /// the case of its name is written
     watched_files: Arc<Mutex<HashMap<PathBuf, FileState>>>,
     event_callbacks: Arc<Mutex<Vec<Box<dyn Fn(WatchEvent) + Send + Sync>>>>,
     event_sender: Option<Sender<WatchEvent>>,
     event_receiver: Option<Receiver<WatchEvent>>,
     is_running: Arc<AtomicBool>,
+    /// Files whose change detection is paused (e.g. while the editor saves them)
+    suspended: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Priorities other than [`WatchPriority::Normal`]
+    priorities: Arc<Mutex<HashMap<PathBuf, WatchPriority>>>,
+    /// Files with editor activity since the watcher thread last polled
+    active: Arc<Mutex<HashSet<PathBuf>>>,
+    /// Wakes the watcher thread before its next tick
+    wake: Mutex<Option<Sender<()>>>,
     identity_config: FileIdentityConfig,
 }
 
+/// What the watcher thread shares with its [`FileWatcher`]
+struct WatchedState {
+    watched_files: Arc<Mutex<HashMap<PathBuf, FileState>>>,
+    suspended: Arc<Mutex<HashSet<PathBuf>>>,
+    priorities: Arc<Mutex<HashMap<PathBuf, WatchPriority>>>,
+    active: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
 impl FileWatcher {
     pub fn new(config: WatcherConfig) -> Self {
         let (tx, rx) = mpsc::channel();
@@ -184,6 +281,10 @@ impl FileWatcher {
             event_sender: Some(tx),
             event_receiver: Some(rx),
             is_running: Arc::new(AtomicBool::new(false)),
+            suspended: Arc::new(Mutex::new(HashSet::new())),
+            priorities: Arc::new(Mutex::new(HashMap::new())),
+            active: Arc::new(Mutex::new(HashSet::new())),
+            wake: Mutex::new(None),
             identity_config: FileIdentityConfig::default(),
         }
     }
@@ -200,9 +301,20 @@ impl FileWatcher {
         let event_sender = self.event_sender.as_ref().unwrap().clone();
         let config = self.config.clone();
         let is_running = Arc::clone(&self.is_running);
+        let suspended = Arc::clone(&self.suspended);
+        let priorities = Arc::clone(&self.priorities);
+        let active = Arc::clone(&self.active);
+        let (wake, woken) = mpsc::channel();
+        *self.wake.lock().unwrap() = Some(wake);
 
         thread::spawn(move || {
-            Self::watcher_thread(watched_files, event_sender, config, is_running);
+            let shared = WatchedState {
+                watched_files,
+                suspended,
+                priorities,
+                active,
+            };
+            Self::watcher_thread(shared, woken, event_sender, config, is_running);
         });
 
         Ok(())
@@ -211,6 +323,63 @@ impl FileWatcher {
     /// Stop the file watcher
     pub fn stop(&self) {
         self.is_running.store(false, Ordering::Relaxed);
+        // Dropping the sender ends the thread's wait for the next tick
+        self.wake.lock().unwrap().take();
+    }
+
+    /// Set how closely the watcher thread follows a file
+    pub fn set_priority(&self, path: &Path, priority: WatchPriority) {
+        let key = path_key(path);
+        let mut priorities = self.priorities.lock().unwrap();
+        if priority == WatchPriority::Normal {
+            priorities.remove(&key);
+        } else {
+            priorities.insert(key, priority);
+        }
+    }
+
+    /// How closely the watcher thread follows a file
+    pub fn priority(&self, path: &Path) -> WatchPriority {
+        let priorities = self.priorities.lock().unwrap();
+        priorities.get(&path_key(path)).copied().unwrap_or_default()
+    }
+
+    /// Tell the watcher the editor is working on a file, e.g. the user typed
+    /// in its buffer or switched to it: the file is polled right away, and
+    /// its polling no longer backs off
+    pub fn notify_activity(&self, path: &Path) {
+        self.active.lock().unwrap().insert(path_key(path));
+        if let Some(wake) = &*self.wake.lock().unwrap() {
+            let _ = wake.send(());
+        }
+    }
+
+    /// Pause change detection for a file.
+    ///
+    /// Used while the editor writes the file itself, so its own atomic rename is
+    /// not reported as an external modification.
+    pub fn suspend(&self, path: &Path) {
+        self.suspended.lock().unwrap().insert(path_key(path));
+    }
+
+    /// Resume change detection for a file, taking its current state on disk as
+    /// the new baseline.
+    pub fn resume(&self, path: &Path) -> WatcherResult<()> {
+        let key = path_key(path);
+        self.suspended.lock().unwrap().remove(&key);
+
+        let mut watched_files = self.watched_files.lock().unwrap();
+        if let Some(file_state) = watched_files.get_mut(&key) {
+            let identity = FileIdentity::from_path(path, &self.identity_config)?;
+            file_state.last_modified = identity.mtime.max(SystemTime::now());
+            file_state.identity = identity;
+        }
+        Ok(())
+    }
+
+    /// Check whether change detection is paused for a file
+    pub fn is_suspended(&self, path: &Path) -> bool {
+        self.suspended.lock().unwrap().contains(&path_key(path))
     }
 
     /// Add an event callback
@@ -257,15 +426,25 @@ impl FileWatcher {
             snapshots: Vec::new(),
         };
 
-        watched_files.insert(path.to_path_buf(), file_state);
+        watched_files.insert(path_key(path), file_state);
         Ok(())
     }
 
+    /// Stop watching a file, e.g. when its buffer is closed.
+    ///
+    /// Returns whether the file was being watched.
+    pub fn unwatch_file(&self, path: &Path) -> bool {
+        let key = path_key(path);
+        self.suspended.lock().unwrap().remove(&key);
+        self.priorities.lock().unwrap().remove(&key);
+        self.watched_files.lock().unwrap().remove(&key).is_some()
+    }
+
     /// Update buffer content and mark as dirty
     pub fn update_buffer(&self, path: &Path, content: &str) -> WatcherResult<()> {
         let mut watched_files = self.watched_files.lock().unwrap();
 
-        if let Some(file_state) = watched_files.get_mut(path) {
+        if let Some(file_state) = watched_files.get_mut(&path_key(path)) {
             file_state.buffer_content = content.to_string();
             file_state.is_dirty = true;
 
@@ -285,16 +464,41 @@ impl FileWatcher {
         Ok(())
     }
 
+    /// Take text appended to a file into its state, e.g. once a buffer
+    /// following the file has loaded it, so the append isn't reported again
+    pub fn record_append(
+        &self,
+        path: &Path,
+        appended: &str,
+        identity: FileIdentity,
+    ) -> WatcherResult<()> {
+        let mut watched_files = self.watched_files.lock().unwrap();
+
+        if let Some(file_state) = watched_files.get_mut(&path_key(path)) {
+            file_state.buffer_content.push_str(appended);
+            file_state.disk_content.push_str(appended);
+            file_state.base_content.push_str(appended);
+            file_state.last_modified = identity.mtime.max(SystemTime::now());
+            file_state.identity = identity;
+        }
+        Ok(())
+    }
+
     /// Check for external changes to a file
     pub fn check_external_changes(&self, path: &Path) -> WatcherResult<Option<FileChange>> {
+        if self.is_suspended(path) {
+            return Ok(None);
+        }
+
         let watched_files = self.watched_files.lock().unwrap();
 
-        if let Some(file_state) = watched_files.get(path) {
+        if let Some(file_state) = watched_files.get(&path_key(path)) {
             // Check if file exists and get current identity
             let current_identity = match FileIdentity::from_path(path, &self.identity_config) {
                 Ok(identity) => identity,
                 Err(_) => {
                     // File was deleted
+                    crate::log!(Info, "watcher", "{} was deleted", path.display());
                     return Ok(Some(FileChange {
                         path: path.to_path_buf(),
                         change_type: ChangeType::Deleted,
@@ -307,6 +511,7 @@ impl FileWatcher {
 
             // Check if identity changed (file was modified or replaced)
             if current_identity != file_state.identity {
+                crate::log!(Info, "watcher", "{} changed on disk", path.display());
                 return Ok(Some(FileChange {
                     path: path.to_path_buf(),
                     change_type: ChangeType::Modified,
@@ -328,7 +533,7 @@ impl FileWatcher {
     ) -> WatcherResult<Option<MergeConflict>> {
         let watched_files = self.watched_files.lock().unwrap();
 
-        if let Some(file_state) = watched_files.get(path) {
+        if let Some(file_state) = watched_files.get(&path_key(path)) {
             if file_state.is_dirty {
                 // Read current disk content
                 let disk_content = match fs::read_to_string(path) {
@@ -383,7 +588,8 @@ impl FileWatcher {
         match resolution {
             ConflictResolution::UseBuffer => {
                 // Overwrite disk with buffer content
-                fs::write(&conflict.file_path, &conflict.buffer_content)?;
+                fs::write(&conflict.file_path, &conflict.buffer_content)
+                    .io_context(IoOp::Write, &conflict.file_path)?;
                 Ok(conflict.buffer_content.clone())
             }
             ConflictResolution::UseDisk => {
@@ -393,7 +599,8 @@ impl FileWatcher {
             ConflictResolution::KeepBoth => {
                 // Save buffer content as new file
                 let new_path = conflict.file_path.with_extension("buffer");
-                fs::write(&new_path, &conflict.buffer_content)?;
+                fs::write(&new_path, &conflict.buffer_content)
+                    .io_context(IoOp::Write, &new_path)?;
                 Ok(conflict.disk_content.clone())
             }
             ConflictResolution::Manual => Err(WatcherError::ConflictResolutionFailed(
@@ -406,7 +613,7 @@ impl FileWatcher {
     pub fn follow_rename(&self, old_path: &Path, new_path: &Path) -> WatcherResult<bool> {
         let mut watched_files = self.watched_files.lock().unwrap();
 
-        if let Some(file_state) = watched_files.remove(old_path) {
+        if let Some(file_state) = watched_files.remove(&path_key(old_path)) {
             // Check if the new path has the same identity
             let new_identity = match FileIdentity::from_path(new_path, &self.identity_config) {
                 Ok(identity) => identity,
@@ -415,7 +622,11 @@ impl FileWatcher {
 
             if new_identity == file_state.identity {
                 // Same file, just update path
-                watched_files.insert(new_path.to_path_buf(), file_state);
+                let mut priorities = self.priorities.lock().unwrap();
+                if let Some(priority) = priorities.remove(&path_key(old_path)) {
+                    priorities.insert(path_key(new_path), priority);
+                }
+                watched_files.insert(path_key(new_path), file_state);
                 return Ok(true);
             }
         }
@@ -425,24 +636,71 @@ impl FileWatcher {
 
     /// Watcher thread function
     fn watcher_thread(
-        watched_files: Arc<Mutex<HashMap<PathBuf, FileState>>>,
+        shared: WatchedState,
+        woken: Receiver<()>,
         event_sender: Sender<WatchEvent>,
         config: WatcherConfig,
         is_running: Arc<AtomicBool>,
     ) {
         let mut last_check = HashMap::new();
         let mut pending_changes = HashMap::new();
+        let mut schedules: HashMap<PathBuf, PollSchedule> = HashMap::new();
 
         while is_running.load(Ordering::Relaxed) {
-            thread::sleep(config.poll_interval);
-
-            let files_to_check: Vec<PathBuf> = {
-                let watched = watched_files.lock().unwrap();
-                watched.keys().cloned().collect()
+            // Sleep for a tick, or until there is editor activity
+            if let Err(RecvTimeoutError::Disconnected) = woken.recv_timeout(config.poll_interval) {
+                break;
+            }
+            let now = Instant::now();
+            let active = std::mem::take(&mut *shared.active.lock().unwrap());
+
+            let files_to_check: Vec<(PathBuf, SystemTime, WatchPriority)> = {
+                let watched = shared.watched_files.lock().unwrap();
+                let suspended = shared.suspended.lock().unwrap();
+                let priorities = shared.priorities.lock().unwrap();
+                schedules.retain(|path, _| watched.contains_key(path));
+                watched
+                    .iter()
+                    .filter(|(path, _)| !suspended.contains(*path))
+                    .map(|(path, state)| {
+                        let priority = priorities.get(path).copied().unwrap_or_default();
+                        (path.clone(), state.last_modified, priority)
+                    })
+                    .collect()
             };
 
-            for file_path in files_to_check {
-                let change = Self::check_file_change(&file_path, &last_check);
+            let due: Vec<(PathBuf, SystemTime, WatchPriority)> = files_to_check
+                .into_iter()
+                .filter(|(file_path, _, _)| {
+                    let schedule = schedules
+                        .entry(file_path.clone())
+                        .or_insert_with(|| PollSchedule::new(now, &config));
+                    if active.contains(file_path) {
+                        schedule.wake(now, &config);
+                    }
+                    schedule.is_due(now)
+                })
+                .collect();
+            let scanned = Self::scan_dirs(
+                due.iter().map(|(file_path, _, _)| file_path.as_path()),
+                config.dir_scan_threshold,
+            );
+
+            for (file_path, baseline, priority) in due {
+                // A resume moves the baseline past the editor's own write
+                let last_checked = last_check
+                    .get(&file_path)
+                    .map_or(baseline, |&checked: &SystemTime| checked.max(baseline));
+                let change = match scanned.get(&file_path) {
+                    Some(&modified) => {
+                        Ok(Self::change_since(&file_path, modified, Some(last_checked)))
+                    }
+                    None => Self::check_file_change(&file_path, Some(last_checked)),
+                };
+                let changed = !matches!(change, Ok(None));
+                if let Some(schedule) = schedules.get_mut(&file_path) {
+                    schedule.polled(now, changed, priority, &config);
+                }
 
                 match change {
                     Ok(Some(file_change)) => {
@@ -487,50 +745,118 @@ impl FileWatcher {
         }
     }
 
+    /// Modification times of `files` from one listing of each directory
+    /// holding at least `threshold` of them, `None` for those the listing
+    /// doesn't have. Files of other directories are left to
+    /// [`FileWatcher::check_file_change`].
+    ///
+    /// On Windows the listing carries the times, so a directory costs a few
+    /// calls instead of one per file; elsewhere each time still takes a
+    /// `stat`, but relative to the open directory.
+    fn scan_dirs<'a>(
+        files: impl Iterator<Item = &'a Path>,
+        threshold: usize,
+    ) -> HashMap<PathBuf, Option<SystemTime>> {
+        let mut by_dir: HashMap<&Path, HashSet<&Path>> = HashMap::new();
+        for file in files {
+            if let Some(dir) = file.parent() {
+                by_dir.entry(dir).or_default().insert(file);
+            }
+        }
+
+        let mut times = HashMap::new();
+        for (dir, files) in by_dir {
+            if threshold == 0 || files.len() < threshold {
+                continue;
+            }
+            // A directory that can't be listed is left to the checks of its
+            // files, which tell deleted files apart
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            let mut listed = HashMap::new();
+            for entry in entries.flatten() {
+                let key = path_key(&entry.path());
+                if !files.contains(key.as_path()) {
+                    continue;
+                }
+                // The listing has the times of a symlink, not of its target
+                let metadata = match entry.file_type() {
+                    Ok(file_type) if file_type.is_symlink() => fs::metadata(entry.path()),
+                    _ => entry.metadata(),
+                };
+                if let Ok(metadata) = metadata {
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    listed.insert(key, modified);
+                }
+            }
+            for file in files {
+                match listed.remove(file) {
+                    Some(modified) => times.insert(file.to_path_buf(), Some(modified)),
+                    // Gone, unless the listing only failed to read it
+                    None if !file.exists() => times.insert(file.to_path_buf(), None),
+                    None => None,
+                };
+            }
+        }
+        times
+    }
+
     /// Check if a file has changed
     fn check_file_change(
         file_path: &Path,
-        last_check: &HashMap<PathBuf, SystemTime>,
+        last_checked: Option<SystemTime>,
     ) -> WatcherResult<Option<FileChange>> {
-        let metadata = match fs::metadata(file_path) {
-            Ok(meta) => meta,
-            Err(_) => {
-                // File doesn't exist or can't be accessed
-                return Ok(Some(FileChange {
-                    path: file_path.to_path_buf(),
-                    change_type: ChangeType::Deleted,
-                    timestamp: SystemTime::now(),
-                    old_identity: None,
-                    new_identity: None,
-                }));
-            }
-        };
+        // A file that doesn't exist or can't be accessed counts as deleted
+        let modified = fs::metadata(file_path)
+            .ok()
+            .map(|meta| meta.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+        Ok(Self::change_since(file_path, modified, last_checked))
+    }
 
-        let current_modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    /// Change of a file modified at `modified`, `None` when it is gone
+    fn change_since(
+        file_path: &Path,
+        modified: Option<SystemTime>,
+        last_checked: Option<SystemTime>,
+    ) -> Option<FileChange> {
+        let Some(current_modified) = modified else {
+            return Some(FileChange {
+                path: file_path.to_path_buf(),
+                change_type: ChangeType::Deleted,
+                timestamp: SystemTime::now(),
+                old_identity: None,
+                new_identity: None,
+            });
+        };
 
-        if let Some(&last_modified) = last_check.get(file_path) {
+        if let Some(last_modified) = last_checked {
             if current_modified > last_modified {
-                return Ok(Some(FileChange {
+                return Some(FileChange {
                     path: file_path.to_path_buf(),
                     change_type: ChangeType::Modified,
                     timestamp: current_modified,
                     old_identity: None,
                     new_identity: None,
-                }));
+                });
             }
         }
 
-        Ok(None)
+        None
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::TempDir;
 
     fn test_config() -> WatcherConfig {
         WatcherConfig {
             poll_interval: Duration::from_millis(50),
+            max_poll_interval: Duration::from_millis(400),
+            idle_after: Duration::from_millis(200),
+            dir_scan_threshold: 8,
             debounce_delay: Duration::from_millis(10),
             auto_reload: true,
             max_snapshots: 5,
@@ -543,4 +869,130 @@ mod tests {
         let watcher = FileWatcher::new(config);
         assert!(!watcher.is_running.load(Ordering::Relaxed));
     }
+
+    #[test]
+    fn test_polling_backs_off_for_idle_files() {
+        let config = test_config();
+        let start = Instant::now();
+        let mut schedule = PollSchedule::new(start, &config);
+        assert!(schedule.is_due(start));
+
+        // Unchanged for less than `idle_after`: every tick
+        let at = |ms| start + Duration::from_millis(ms);
+        schedule.polled(at(100), false, WatchPriority::Normal, &config);
+        assert_eq!(schedule.interval, config.poll_interval);
+
+        // Then twice as long after every poll, up to the maximum
+        let mut intervals = Vec::new();
+        for ms in [200, 300, 500, 900, 1300] {
+            schedule.polled(at(ms), false, WatchPriority::Normal, &config);
+            intervals.push(schedule.interval.as_millis());
+        }
+        assert_eq!(intervals, [100, 200, 400, 400, 400]);
+        assert!(!schedule.is_due(at(1500)));
+
+        // Activity polls on the next tick and starts over
+        schedule.wake(at(1500), &config);
+        assert!(schedule.is_due(at(1500)));
+        schedule.polled(at(1500), false, WatchPriority::Normal, &config);
+        assert_eq!(schedule.interval, config.poll_interval);
+
+        schedule.polled(at(9000), false, WatchPriority::High, &config);
+        assert_eq!(schedule.interval, config.poll_interval);
+        schedule.polled(at(9050), true, WatchPriority::Low, &config);
+        assert_eq!(schedule.interval, config.max_poll_interval);
+    }
+
+    #[test]
+    fn test_dir_scan_reads_times_of_watched_files() {
+        let dir = TempDir::new("watcher_scan");
+        let files: Vec<PathBuf> = ["a.txt", "b.txt", "gone.txt"]
+            .iter()
+            .map(|name| path_key(&dir.join(name)))
+            .collect();
+        fs::write(&files[0], "a").unwrap();
+        fs::write(&files[1], "b").unwrap();
+        fs::write(dir.join("unwatched.txt"), "c").unwrap();
+
+        let times = FileWatcher::scan_dirs(files.iter().map(PathBuf::as_path), 3);
+        assert_eq!(times.len(), 3);
+        for file in &files[..2] {
+            let modified = fs::metadata(file).unwrap().modified().unwrap();
+            assert_eq!(times[file], Some(modified));
+        }
+        assert_eq!(times[&files[2]], None);
+
+        // Too few files of the directory to list it
+        let times = FileWatcher::scan_dirs(files.iter().map(PathBuf::as_path), 4);
+        assert!(times.is_empty());
+    }
+
+    #[test]
+    fn test_priority_follows_the_file() {
+        let watcher = FileWatcher::new(test_config());
+        let path = Path::new("/tmp/niv_watcher_priority.txt");
+        assert_eq!(watcher.priority(path), WatchPriority::Normal);
+        watcher.set_priority(path, WatchPriority::High);
+        assert_eq!(watcher.priority(path), WatchPriority::High);
+        watcher.unwatch_file(path);
+        assert_eq!(watcher.priority(path), WatchPriority::Normal);
+    }
+
+    #[test]
+    fn test_suspend_hides_own_writes() {
+        let dir = TempDir::new("watcher_suspend");
+        let path = dir.join("suspend.txt");
+        fs::write(&path, "original").unwrap();
+
+        let watcher = FileWatcher::new(test_config());
+        let identity = FileIdentity::from_path(&path, &FileIdentityConfig::default()).unwrap();
+        watcher.watch_file(&path, "original", identity).unwrap();
+
+        watcher.suspend(&path);
+        assert!(watcher.is_suspended(&path));
+        fs::write(&path, "saved by the editor").unwrap();
+        assert!(watcher.check_external_changes(&path).unwrap().is_none());
+
+        watcher.resume(&path).unwrap();
+        assert!(!watcher.is_suspended(&path));
+        assert!(watcher.check_external_changes(&path).unwrap().is_none());
+
+        fs::write(&path, "changed by someone else").unwrap();
+        let change = watcher.check_external_changes(&path).unwrap();
+        assert_eq!(change.map(|c| c.change_type), Some(ChangeType::Modified));
+    }
+
+    #[test]
+    fn test_record_append() {
+        let dir = TempDir::new("watcher_append");
+        let path = dir.join("append.txt");
+        fs::write(&path, "first\n").unwrap();
+
+        let watcher = FileWatcher::new(test_config());
+        let identity = FileIdentity::from_path(&path, &FileIdentityConfig::default()).unwrap();
+        watcher.watch_file(&path, "first\n", identity).unwrap();
+
+        fs::write(&path, "first\nsecond\n").unwrap();
+        let change = watcher.check_external_changes(&path).unwrap().unwrap();
+        watcher
+            .record_append(&path, "second\n", change.new_identity.unwrap())
+            .unwrap();
+        assert!(watcher.check_external_changes(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_unwatch_file() {
+        let dir = TempDir::new("watcher_unwatch");
+        let path = dir.join("unwatch.txt");
+        fs::write(&path, "original").unwrap();
+
+        let watcher = FileWatcher::new(test_config());
+        let identity = FileIdentity::from_path(&path, &FileIdentityConfig::default()).unwrap();
+        watcher.watch_file(&path, "original", identity).unwrap();
+        assert!(watcher.unwatch_file(&path));
+        assert!(!watcher.unwatch_file(&path));
+
+        fs::write(&path, "changed").unwrap();
+        assert!(watcher.check_external_changes(&path).unwrap().is_none());
+    }
 }
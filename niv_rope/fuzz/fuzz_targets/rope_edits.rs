@@ -0,0 +1,83 @@
+//! Apply edits decoded from the fuzzer input to a `Rope` and to a plain byte
+//! string, checking after every step that both hold the same bytes and line
+//! count and that the tree invariants still hold.
+//!
+//! Input layout: two bytes giving the length of the initial text, the text
+//! itself, then edits of `[kind, at (2 bytes), needle length, text length,
+//! text...]`. Inserts and deletes go through `replace_first`, the rope's only
+//! edit so far.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use niv_rope::Rope;
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if data.len() < n {
+        return None;
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Some(head)
+}
+
+fn replace_first(model: &mut Vec<u8>, needle: &[u8], replacement: &[u8]) -> bool {
+    let Some(at) = model.windows(needle.len()).position(|w| w == needle) else {
+        return false;
+    };
+    model.splice(at..at + needle.len(), replacement.iter().copied());
+    true
+}
+
+fn check(rope: &Rope, model: &[u8]) {
+    if let Err(e) = rope.validate() {
+        panic!("invalid tree: {}", e);
+    }
+    assert_eq!(rope.len(), model.len());
+    assert_eq!(
+        rope.total_lines(),
+        model.iter().filter(|&&b| b == b'\n').count()
+    );
+    let mut all = vec![0u8; rope.len()];
+    let read = rope.read_bytes_global(0, &mut all).expect("read all");
+    assert_eq!(&all[..read], model);
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut data = data;
+    let Some(header) = take(&mut data, 2) else {
+        return;
+    };
+    let initial_len = usize::from(u16::from_le_bytes([header[0], header[1]])).min(data.len());
+    let mut model = take(&mut data, initial_len).unwrap_or_default().to_vec();
+    let mut rope = Rope::new();
+    rope.build_from_bytes(&model).expect("build");
+    check(&rope, &model);
+
+    while let Some(edit) = take(&mut data, 5) {
+        let Some(text) = take(&mut data, usize::from(edit[4] % 32)) else {
+            break;
+        };
+        if model.is_empty() {
+            model = text.to_vec();
+            rope.build_from_bytes(&model).expect("build");
+            check(&rope, &model);
+            continue;
+        }
+        let at = usize::from(u16::from_le_bytes([edit[1], edit[2]])) % model.len();
+        let len = 1 + usize::from(edit[3]) % 8.min(model.len() - at);
+        let needle = model[at..at + len].to_vec();
+        let replacement = match edit[0] % 3 {
+            // insert after the needle
+            0 => [needle.as_slice(), text].concat(),
+            // delete the needle
+            1 => Vec::new(),
+            _ => text.to_vec(),
+        };
+        // A refused edit must leave the rope unchanged
+        if rope.replace_first(&needle, &replacement).is_ok() {
+            assert!(replace_first(&mut model, &needle, &replacement));
+        }
+        check(&rope, &model);
+    }
+});
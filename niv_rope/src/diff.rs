@@ -0,0 +1,470 @@
+// Differences between two Ropes.
+//
+// A clone or snapshot of a rope shares its leaves with it until either edits
+// them, and a shared leaf holds the same text in both, so shared leaves are
+// matched by address without reading them. Only the text between them is
+// compared: each stretch, widened to whole lines within the shared leaves
+// around it, is split into lines and diffed with Myers' algorithm. A shared
+// leaf without a newline can't end a stretch, which then goes on past it.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::rope::Rope;
+
+/// Lines of one rope replaced by lines of another, as [`Rope::diff`]
+/// finds them. Either side may be empty, for lines added or removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffHunk {
+    /// Bytes of the old text, whole lines
+    pub old: Range<usize>,
+    /// Bytes of the new text taking their place, whole lines
+    pub new: Range<usize>,
+    /// The 0-based lines of the old text the bytes are
+    pub old_lines: Range<usize>,
+    /// The 0-based lines of the new text the bytes are
+    pub new_lines: Range<usize>,
+}
+
+impl Rope {
+    /// Changes turning this rope's text into the text of `other`, as hunks
+    /// of whole lines in order. Leaves the ropes share, as a rope does with
+    /// its clones and snapshots until it edits them, are skipped without
+    /// reading them, so diffing a rope against a snapshot of itself costs
+    /// about as much as the leaves edited since.
+    pub fn diff(&self, other: &Rope) -> Vec<DiffHunk> {
+        let mut hunks = Vec::new();
+        for (old, new) in changed_regions(self, other) {
+            diff_region(self, other, old, new, &mut hunks);
+        }
+        hunks
+    }
+}
+
+// Ranges of whole lines of the two texts holding all their differences,
+// found between the leaves they share
+fn changed_regions(old: &Rope, new: &Rope) -> Vec<(Range<usize>, Range<usize>)> {
+    let old_leaves = old.leaf_spans();
+    let by_address: HashMap<usize, usize> = old_leaves
+        .iter()
+        .enumerate()
+        .map(|(i, &(_, _, address))| (address, i))
+        .collect();
+
+    let mut regions = Vec::new();
+    // Start of the region being gathered, a line start in both texts, and
+    // the ends of the last shared leaf
+    let mut open = (0usize, 0usize);
+    let mut shared_end = (0usize, 0usize);
+    let mut changed = false;
+    let mut next_old = 0;
+    for (new_start, len, address) in new.leaf_spans() {
+        let old_start = match by_address.get(&address) {
+            Some(&i) if i >= next_old => {
+                next_old = i + 1;
+                old_leaves[i].0
+            }
+            _ => continue,
+        };
+        changed |= old_start != shared_end.0 || new_start != shared_end.1;
+        shared_end = (old_start + len, new_start + len);
+
+        // The leaf's first and last line starts, the same in both texts
+        let Some((first, last)) = line_starts_within(old, old_start, len) else {
+            continue;
+        };
+        if changed {
+            regions.push((open.0..old_start + first, open.1..new_start + first));
+        }
+        open = (old_start + last, new_start + last);
+        changed = false;
+    }
+    changed |= old.len() != shared_end.0 || new.len() != shared_end.1;
+    if changed {
+        regions.push((open.0..old.len(), open.1..new.len()));
+    }
+    regions
+}
+
+// Offsets from `start` of the first and last line starts after a newline
+// within the `len` bytes there, or None when they hold no newline
+fn line_starts_within(rope: &Rope, start: usize, len: usize) -> Option<(usize, usize)> {
+    let end = start + len;
+    let line = rope.byte_to_line(start).ok()?;
+    let first = rope.line_to_byte(line + 1).ok().filter(|&at| at <= end)?;
+    let last = rope
+        .byte_to_line(end)
+        .and_then(|line| rope.line_to_byte(line))
+        .ok()?;
+    Some((first - start, last - start))
+}
+
+// Diff the lines of `old` in the old text with those of `new` in the new
+// one, adding the hunks found to `hunks`
+fn diff_region(
+    old_rope: &Rope,
+    new_rope: &Rope,
+    old: Range<usize>,
+    new: Range<usize>,
+    hunks: &mut Vec<DiffHunk>,
+) {
+    let old_text = read_range(old_rope, &old);
+    let new_text = read_range(new_rope, &new);
+    let old_lines: Vec<&[u8]> = old_text.split_inclusive(|&b| b == b'\n').collect();
+    let new_lines: Vec<&[u8]> = new_text.split_inclusive(|&b| b == b'\n').collect();
+    let first_old_line = old_rope.byte_to_line(old.start).unwrap_or(0);
+    let first_new_line = new_rope.byte_to_line(new.start).unwrap_or(0);
+
+    // Byte offsets and line numbers in both texts, as a hunk is opened at
+    // the first step that isn't Same and closed by the next Same one
+    let hunk = |from: [usize; 4], to: [usize; 4]| DiffHunk {
+        old: from[0]..to[0],
+        new: from[1]..to[1],
+        old_lines: first_old_line + from[2]..first_old_line + to[2],
+        new_lines: first_new_line + from[3]..first_new_line + to[3],
+    };
+    let (mut old_at, mut new_at) = (old.start, new.start);
+    let (mut old_line, mut new_line) = (0usize, 0usize);
+    let mut open = None;
+    for step in diff_lines(&old_lines, &new_lines) {
+        let here = [old_at, new_at, old_line, new_line];
+        match step {
+            LineDiff::Same { .. } => {
+                if let Some(from) = open.take() {
+                    hunks.push(hunk(from, here));
+                }
+                old_at += old_lines[old_line].len();
+                new_at += new_lines[new_line].len();
+                old_line += 1;
+                new_line += 1;
+            }
+            LineDiff::Removed(_) => {
+                open.get_or_insert(here);
+                old_at += old_lines[old_line].len();
+                old_line += 1;
+            }
+            LineDiff::Added(_) => {
+                open.get_or_insert(here);
+                new_at += new_lines[new_line].len();
+                new_line += 1;
+            }
+        }
+    }
+    if let Some(from) = open {
+        hunks.push(hunk(from, [old_at, new_at, old_line, new_line]));
+    }
+}
+
+fn read_range(rope: &Rope, range: &Range<usize>) -> Vec<u8> {
+    let mut text = Vec::with_capacity(range.len());
+    for (_, chunk) in rope.slice(range.start, range.end).chunks() {
+        text.extend_from_slice(chunk);
+    }
+    text
+}
+
+/// One line of a line diff, as [`diff_lines`] gives them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineDiff {
+    /// Line `old` of the old text is line `new` of the new one
+    Same { old: usize, new: usize },
+    /// Line of the old text that isn't in the new one
+    Removed(usize),
+    /// Line of the new text that isn't in the old one
+    Added(usize),
+}
+
+/// Shortest line diff turning `old` into `new`, in order.
+///
+/// This is Myers' O((N+M)D) algorithm in its linear space form: the middle
+/// snake of the shortest path is found searching from both ends, and the
+/// halves before and after it are diffed the same way, so memory stays
+/// O(N+M) however different the texts are.
+pub fn diff_lines<T: PartialEq>(old: &[T], new: &[T]) -> Vec<LineDiff> {
+    let mut diff = Vec::with_capacity(old.len().max(new.len()));
+    diff_between(old, new, 0..old.len(), 0..new.len(), &mut diff);
+    diff
+}
+
+// Diff lines `xs` of `old` with lines `ys` of `new`, adding to `diff`
+fn diff_between<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    mut xs: Range<usize>,
+    mut ys: Range<usize>,
+    diff: &mut Vec<LineDiff>,
+) {
+    // Lines the two start and end with alike are set aside first
+    while !xs.is_empty() && !ys.is_empty() && old[xs.start] == new[ys.start] {
+        diff.push(LineDiff::Same {
+            old: xs.start,
+            new: ys.start,
+        });
+        xs.start += 1;
+        ys.start += 1;
+    }
+    let mut suffix = 0;
+    while !xs.is_empty() && !ys.is_empty() && old[xs.end - 1] == new[ys.end - 1] {
+        xs.end -= 1;
+        ys.end -= 1;
+        suffix += 1;
+    }
+
+    if xs.is_empty() {
+        diff.extend(ys.clone().map(LineDiff::Added));
+    } else if ys.is_empty() {
+        diff.extend(xs.clone().map(LineDiff::Removed));
+    } else {
+        // Both start and end differently, so the path takes two steps or
+        // more and both halves around its middle snake are shorter
+        let (start, end) = middle_snake(&old[xs.clone()], &new[ys.clone()]);
+        let (x0, y0) = (xs.start + start.0, ys.start + start.1);
+        let (x1, y1) = (xs.start + end.0, ys.start + end.1);
+        diff_between(old, new, xs.start..x0, ys.start..y0, diff);
+        diff.extend(
+            (x0..x1)
+                .zip(y0..y1)
+                .map(|(old, new)| LineDiff::Same { old, new }),
+        );
+        diff_between(old, new, x1..xs.end, y1..ys.end, diff);
+    }
+
+    diff.extend(
+        (xs.end..xs.end + suffix)
+            .zip(ys.end..)
+            .map(|(old, new)| LineDiff::Same { old, new }),
+    );
+}
+
+// Start and end of the snake in the middle of a shortest path from the
+// start of `old` and `new` to their ends, searching forwards from the start
+// and backwards from the end until the two searches meet
+fn middle_snake<T: PartialEq>(old: &[T], new: &[T]) -> ((usize, usize), (usize, usize)) {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let delta = n - m;
+    let max = (n + m + 1) / 2;
+    let offset = max + 1;
+    // Furthest x on each diagonal k = x - y going forwards, and furthest x
+    // counted from the end on each diagonal of the reversed texts
+    let mut forward = vec![0isize; 2 * offset as usize + 1];
+    let mut backward = vec![0isize; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let start = (x, x - k);
+            while x < n && x - k < m && old[x as usize] == new[(x - k) as usize] {
+                x += 1;
+            }
+            forward[at(k)] = x;
+            // The backward search has taken d - 1 steps
+            let reverse = delta - k;
+            if delta % 2 != 0 && (1 - d..d).contains(&reverse) && x + backward[at(reverse)] >= n {
+                return (snake_point(start), snake_point((x, x - k)));
+            }
+        }
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let end = (n - x, m - (x - k));
+            while x < n && x - k < m && old[(n - x - 1) as usize] == new[(m - (x - k) - 1) as usize]
+            {
+                x += 1;
+            }
+            backward[at(k)] = x;
+            let forward_k = delta - k;
+            if delta % 2 == 0 && (-d..=d).contains(&forward_k) && x + forward[at(forward_k)] >= n {
+                return (snake_point((n - x, m - (x - k))), snake_point(end));
+            }
+        }
+    }
+    unreachable!("the searches meet by the middle of the longest path")
+}
+
+fn snake_point((x, y): (isize, isize)) -> (usize, usize) {
+    (x as usize, y as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(rope: &Rope) -> Vec<u8> {
+        let mut out = Vec::new();
+        rope.write_to(&mut out).expect("write");
+        out
+    }
+
+    // The old text with each hunk's lines swapped for the new ones
+    fn apply(old: &Rope, new: &Rope, hunks: &[DiffHunk]) -> Vec<u8> {
+        let (old, new) = (text(old), text(new));
+        let mut out = Vec::new();
+        let mut at = 0;
+        for hunk in hunks {
+            out.extend_from_slice(&old[at..hunk.old.start]);
+            out.extend_from_slice(&new[hunk.new.clone()]);
+            at = hunk.old.end;
+        }
+        out.extend_from_slice(&old[at..]);
+        out
+    }
+
+    #[test]
+    fn diff_finds_changed_lines() {
+        let mut old = Rope::new();
+        old.build_from_bytes(b"a\nb\nc\nd\n").expect("build");
+        let mut new = Rope::new();
+        new.build_from_bytes(b"a\nB\nc\nd\ne\n").expect("build");
+
+        let hunks = old.diff(&new);
+        assert_eq!(
+            hunks,
+            [
+                DiffHunk {
+                    old: 2..4,
+                    new: 2..4,
+                    old_lines: 1..2,
+                    new_lines: 1..2,
+                },
+                DiffHunk {
+                    old: 8..8,
+                    new: 8..10,
+                    old_lines: 4..4,
+                    new_lines: 4..5,
+                },
+            ]
+        );
+        assert!(old.diff(&old.clone()).is_empty());
+        assert_eq!(new.diff(&Rope::new()).len(), 1);
+    }
+
+    // Apply `diff` to `old`, checking it against `new` along the way
+    fn apply_lines<'a>(old: &[&'a str], new: &[&'a str], diff: &[LineDiff]) -> Vec<&'a str> {
+        let mut result = Vec::new();
+        let (mut next_old, mut next_new) = (0, 0);
+        for line in diff {
+            match *line {
+                LineDiff::Same { old: o, new: n } => {
+                    assert_eq!((o, n), (next_old, next_new));
+                    assert_eq!(old[o], new[n]);
+                    result.push(old[o]);
+                    next_old += 1;
+                    next_new += 1;
+                }
+                LineDiff::Removed(o) => {
+                    assert_eq!(o, next_old);
+                    next_old += 1;
+                }
+                LineDiff::Added(n) => {
+                    assert_eq!(n, next_new);
+                    result.push(new[n]);
+                    next_new += 1;
+                }
+            }
+        }
+        assert_eq!((next_old, next_new), (old.len(), new.len()));
+        result
+    }
+
+    fn edits(diff: &[LineDiff]) -> usize {
+        diff.iter()
+            .filter(|line| !matches!(line, LineDiff::Same { .. }))
+            .count()
+    }
+
+    #[test]
+    fn diff_lines_is_shortest_edit() {
+        let cases: [(&[&str], &[&str], usize); 8] = [
+            (&[], &[], 0),
+            (&["a"], &[], 1),
+            (&[], &["a", "b"], 2),
+            (&["a", "b", "c"], &["a", "x", "c"], 2),
+            (
+                &["a", "b", "c", "a", "b", "b", "a"],
+                &["c", "b", "a", "b", "a", "c"],
+                5,
+            ),
+            (&["x", "a", "y", "b"], &["a", "z", "b", "w"], 4),
+            (&["a", "b"], &["b", "a"], 2),
+            (&["a", "b", "c", "d"], &["x", "b", "c", "y", "z"], 5),
+        ];
+        for (old, new, count) in cases {
+            let diff = diff_lines(old, new);
+            assert_eq!(apply_lines(old, new, &diff), new);
+            assert_eq!(edits(&diff), count, "{:?} -> {:?}", old, new);
+        }
+    }
+
+    #[test]
+    fn diff_lines_of_unrelated_texts() {
+        // Every line replaced, the case a quadratic trace ran out of memory on
+        let old: Vec<String> = (0..2000).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..1500).map(|i| format!("new {}", i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let diff = diff_lines(&old, &new);
+        assert_eq!(apply_lines(&old, &new, &diff), new);
+        assert_eq!(edits(&diff), 3500);
+
+        // And with lines kept here and there, in order
+        let mixed: Vec<&str> = new
+            .iter()
+            .enumerate()
+            .map(|(i, &line)| if i % 7 == 0 { old[i] } else { line })
+            .collect();
+        let diff = diff_lines(&old, &mixed);
+        assert_eq!(apply_lines(&old, &mixed, &diff), mixed);
+        assert_eq!(edits(&diff), 3500 - 2 * 215);
+    }
+
+    #[test]
+    fn diff_against_a_snapshot_skips_shared_leaves() {
+        let lines: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(lines.as_bytes()).expect("build");
+        let snapshot = rope.snapshot();
+        assert!(snapshot.diff(&rope).is_empty());
+
+        let second = rope.line_to_byte(2000).unwrap();
+        rope.insert_at(second, b"new line\n").expect("insert");
+        let third = rope.line_to_byte(4000).unwrap();
+        let fourth = rope.line_to_byte(4001).unwrap();
+        rope.delete_range(third, fourth).expect("delete");
+        rope.insert_at(5, b"ONE").expect("insert");
+
+        let hunks = snapshot.diff(&rope);
+        assert_eq!(hunks.len(), 3);
+        assert_eq!(hunks[0].old_lines, 0..1);
+        assert_eq!(hunks[1].old_lines, 2000..2000);
+        assert_eq!(hunks[1].new_lines, 2000..2001);
+        assert_eq!(hunks[2].old_lines, 3999..4000);
+        assert_eq!(hunks[2].new_lines, 4000..4000);
+        assert_eq!(apply(&snapshot, &rope, &hunks), text(&rope));
+    }
+
+    #[test]
+    fn diff_lines_longer_than_leaves() {
+        // Shared leaves without newlines can't end a stretch to diff
+        let long: Vec<u8> = (0..20_000).map(|i| b'a' + (i % 26) as u8).collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&long).expect("build");
+        rope.insert_at(10_000, b"\nshort\n").expect("insert");
+        let snapshot = rope.snapshot();
+        rope.delete_range(3, 5).expect("delete");
+        rope.insert_at(15_000, b"x").expect("insert");
+
+        let hunks = snapshot.diff(&rope);
+        assert_eq!(apply(&snapshot, &rope, &hunks), text(&rope));
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].old_lines, 0..1);
+        assert_eq!(hunks[1].old_lines, 2..3);
+    }
+}
@@ -0,0 +1,345 @@
+// Extended grapheme cluster boundaries in a Rope.
+//
+// A grapheme cluster is what a user sees as one character: a letter with
+// the combining marks after it, a CRLF pair, an emoji with its modifiers or
+// joined by zero width joiners, a pair of regional indicators making a flag,
+// a Hangul syllable spelled in jamo. Boundaries follow the rules of Unicode
+// Standard Annex #29, with the character properties approximated by the
+// ranges below, which cover the scripts and emoji an editor meets most; a
+// mark outside them is taken as a character of its own.
+//
+// Only the chars around an offset are read to tell whether it is a boundary,
+// more of them only back over a run of regional indicators or of the marks
+// between an emoji and a joiner.
+
+use crate::rope::Rope;
+
+// Grapheme cluster break property of a char
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Break {
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    SpacingMark,
+    RegionalIndicator,
+    Pictographic,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+    Other,
+}
+
+fn break_property(ch: char) -> Break {
+    match ch as u32 {
+        0x0D => Break::Cr,
+        0x0A => Break::Lf,
+        0x00..=0x1F | 0x7F..=0x9F | 0x200B | 0x2028 | 0x2029 | 0xFEFF => Break::Control,
+        0x200D => Break::Zwj,
+        0x0300..=0x036F
+        | 0x0483..=0x0489
+        | 0x0591..=0x05BD
+        | 0x05BF
+        | 0x05C1..=0x05C2
+        | 0x05C4..=0x05C5
+        | 0x05C7
+        | 0x0610..=0x061A
+        | 0x064B..=0x065F
+        | 0x0670
+        | 0x06D6..=0x06DC
+        | 0x06DF..=0x06E4
+        | 0x06E7..=0x06E8
+        | 0x06EA..=0x06ED
+        | 0x0711
+        | 0x0730..=0x074A
+        | 0x0900..=0x0902
+        | 0x093A
+        | 0x093C
+        | 0x0941..=0x0948
+        | 0x094D
+        | 0x0951..=0x0957
+        | 0x0962..=0x0963
+        | 0x0981
+        | 0x09BC
+        | 0x09C1..=0x09C4
+        | 0x09CD
+        | 0x0E31
+        | 0x0E34..=0x0E3A
+        | 0x0E47..=0x0E4E
+        | 0x0EB1
+        | 0x0EB4..=0x0EBC
+        | 0x0EC8..=0x0ECE
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C
+        | 0x20D0..=0x20FF
+        | 0x302A..=0x302F
+        | 0x3099..=0x309A
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0xFF9E..=0xFF9F
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0020..=0xE007F
+        | 0xE0100..=0xE01EF => Break::Extend,
+        0x0903
+        | 0x093B
+        | 0x093E..=0x0940
+        | 0x0949..=0x094C
+        | 0x094E..=0x094F
+        | 0x0982..=0x0983
+        | 0x09BF..=0x09C0
+        | 0x0E33
+        | 0x0EB3 => Break::SpacingMark,
+        0x1F1E6..=0x1F1FF => Break::RegionalIndicator,
+        0x00A9
+        | 0x00AE
+        | 0x203C
+        | 0x2049
+        | 0x2122
+        | 0x2139
+        | 0x2194..=0x2199
+        | 0x21A9..=0x21AA
+        | 0x231A..=0x231B
+        | 0x2328
+        | 0x23CF
+        | 0x23E9..=0x23F3
+        | 0x23F8..=0x23FA
+        | 0x24C2
+        | 0x25AA..=0x25AB
+        | 0x25B6
+        | 0x25C0
+        | 0x25FB..=0x25FE
+        | 0x2600..=0x27BF
+        | 0x2934..=0x2935
+        | 0x2B05..=0x2B07
+        | 0x2B1B..=0x2B1C
+        | 0x2B50
+        | 0x2B55
+        | 0x3030
+        | 0x303D
+        | 0x3297
+        | 0x3299
+        | 0x1F000..=0x1FAFF
+        | 0x1FC00..=0x1FFFD => Break::Pictographic,
+        0x1100..=0x115F | 0xA960..=0xA97C => Break::L,
+        0x1160..=0x11A7 | 0xD7B0..=0xD7C6 => Break::V,
+        0x11A8..=0x11FF | 0xD7CB..=0xD7FB => Break::T,
+        code @ 0xAC00..=0xD7A3 if (code - 0xAC00) % 28 == 0 => Break::Lv,
+        0xAC00..=0xD7A3 => Break::Lvt,
+        _ => Break::Other,
+    }
+}
+
+// First char of `bytes` and its length, a byte that doesn't start one being
+// read as U+FFFD
+fn first_char(bytes: &[u8]) -> Option<(char, usize)> {
+    let width = match *bytes.first()? {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => return Some(('\u{FFFD}', 1)),
+    };
+    match bytes.get(..width).map(std::str::from_utf8) {
+        Some(Ok(text)) => text.chars().next().map(|ch| (ch, width)),
+        _ => Some(('\u{FFFD}', 1)),
+    }
+}
+
+impl Rope {
+    // Char starting at `offset` and its length
+    fn char_after(&self, offset: usize) -> Option<(char, usize)> {
+        let mut bytes = [0u8; 4];
+        let read = self.read_bytes_global(offset, &mut bytes).ok()?;
+        first_char(&bytes[..read])
+    }
+
+    // Char ending at `offset` and its length
+    fn char_ending_at(&self, offset: usize) -> Option<(char, usize)> {
+        let from = offset.saturating_sub(4);
+        let mut bytes = [0u8; 4];
+        let read = self
+            .read_bytes_global(from, &mut bytes[..offset - from])
+            .ok()?;
+        let bytes = &bytes[..read];
+        let start = bytes.iter().rposition(|&b| b & 0xC0 != 0x80)?;
+        match first_char(&bytes[start..])? {
+            (ch, width) if start + width == bytes.len() => Some((ch, width)),
+            _ => Some(('\u{FFFD}', 1)),
+        }
+    }
+
+    fn is_char_start(&self, offset: usize) -> bool {
+        let mut byte = [0u8; 1];
+        match self.read_bytes_global(offset, &mut byte) {
+            Ok(1) => byte[0] & 0xC0 != 0x80,
+            _ => offset == self.len(),
+        }
+    }
+
+    /// Whether a grapheme cluster starts or ends at byte `offset`: the start
+    /// and end of the text are boundaries, offsets inside a char or past the
+    /// end are not
+    pub fn is_grapheme_boundary(&self, offset: usize) -> bool {
+        if offset == 0 || offset == self.len() {
+            return true;
+        }
+        if offset > self.len() || !self.is_char_start(offset) {
+            return false;
+        }
+        let (Some((before, width)), Some((after, _))) =
+            (self.char_ending_at(offset), self.char_after(offset))
+        else {
+            return true;
+        };
+        match (break_property(before), break_property(after)) {
+            (Break::Cr, Break::Lf) => false,
+            (Break::Cr | Break::Lf | Break::Control, _) => true,
+            (_, Break::Cr | Break::Lf | Break::Control) => true,
+            (Break::L, Break::L | Break::V | Break::Lv | Break::Lvt) => false,
+            (Break::Lv | Break::V, Break::V | Break::T) => false,
+            (Break::Lvt | Break::T, Break::T) => false,
+            (_, Break::Extend | Break::Zwj | Break::SpacingMark) => false,
+            (Break::Zwj, Break::Pictographic) => !self.pictographic_ends_at(offset - width),
+            // Regional indicators pair up from the first of a run
+            (Break::RegionalIndicator, Break::RegionalIndicator) => {
+                self.regional_indicators_ending_at(offset).is_multiple_of(2)
+            }
+            _ => true,
+        }
+    }
+
+    // Whether a pictographic char with only extending marks after it ends
+    // at `offset`
+    fn pictographic_ends_at(&self, mut offset: usize) -> bool {
+        while let Some((ch, width)) = self.char_ending_at(offset) {
+            match break_property(ch) {
+                Break::Extend => offset -= width,
+                property => return property == Break::Pictographic,
+            }
+        }
+        false
+    }
+
+    // Regional indicators in the run ending at `offset`
+    fn regional_indicators_ending_at(&self, mut offset: usize) -> usize {
+        let mut count = 0;
+        while let Some((ch, width)) = self.char_ending_at(offset) {
+            if break_property(ch) != Break::RegionalIndicator {
+                break;
+            }
+            count += 1;
+            offset -= width;
+        }
+        count
+    }
+
+    /// Byte offset of the first grapheme cluster boundary after `offset`,
+    /// to move the cursor right by one character as the user sees it; the
+    /// length of the text at its end
+    pub fn next_grapheme_boundary(&self, offset: usize) -> usize {
+        let mut at = offset;
+        while at < self.len() {
+            at += self.char_after(at).map_or(1, |(_, width)| width);
+            if self.is_grapheme_boundary(at) {
+                return at;
+            }
+        }
+        self.len()
+    }
+
+    /// Byte offset of the last grapheme cluster boundary before `offset`,
+    /// to move the cursor left by one character as the user sees it; 0 at
+    /// the start of the text
+    pub fn prev_grapheme_boundary(&self, offset: usize) -> usize {
+        let mut at = offset.min(self.len());
+        while at > 0 {
+            at -= self.char_ending_at(at).map_or(1, |(_, width)| width);
+            if self.is_grapheme_boundary(at) {
+                return at;
+            }
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rope::LEAF_USABLE;
+
+    fn rope(text: &str) -> Rope {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(text.as_bytes()).expect("build");
+        rope
+    }
+
+    // The clusters of `text`, found walking forwards and checked walking
+    // backwards
+    fn clusters(text: &str) -> Vec<&str> {
+        let rope = rope(text);
+        let mut bounds = vec![0];
+        while *bounds.last().unwrap() < text.len() {
+            bounds.push(rope.next_grapheme_boundary(*bounds.last().unwrap()));
+        }
+        let mut back = vec![text.len()];
+        while *back.last().unwrap() > 0 {
+            back.push(rope.prev_grapheme_boundary(*back.last().unwrap()));
+        }
+        back.reverse();
+        assert_eq!(bounds, back, "{:?}", text);
+        bounds.windows(2).map(|w| &text[w[0]..w[1]]).collect()
+    }
+
+    #[test]
+    fn grapheme_marks_and_line_breaks() {
+        assert_eq!(clusters("abc"), ["a", "b", "c"]);
+        assert_eq!(clusters("e\u{301}\u{308}x"), ["e\u{301}\u{308}", "x"]);
+        assert_eq!(clusters("a\r\nb\n\r"), ["a", "\r\n", "b", "\n", "\r"]);
+        // A mark after a line break starts a cluster of its own
+        assert_eq!(clusters("\n\u{301}"), ["\n", "\u{301}"]);
+        assert_eq!(clusters("किक"), ["कि", "क"]);
+        assert_eq!(
+            clusters("\u{1100}\u{1161}\u{11A8}한"),
+            ["\u{1100}\u{1161}\u{11A8}", "한"]
+        );
+        assert_eq!(clusters(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn grapheme_emoji_sequences() {
+        let family = "👩\u{200D}👩\u{200D}👧";
+        assert_eq!(clusters(&format!("{}!", family)), [family, "!"]);
+        assert_eq!(clusters("👍🏽👍"), ["👍🏽", "👍"]);
+        assert_eq!(clusters("❤\u{FE0F}"), ["❤\u{FE0F}"]);
+        // Flags are pairs of regional indicators, however many follow
+        assert_eq!(clusters("🇰🇷🇯🇵🇫"), ["🇰🇷", "🇯🇵", "🇫"]);
+        // A joiner only joins pictographs
+        assert_eq!(clusters("a\u{200D}b"), ["a\u{200D}", "b"]);
+    }
+
+    #[test]
+    fn grapheme_offsets_inside_chars_and_leaves() {
+        let rope = rope("e\u{301}x");
+        assert!(!rope.is_grapheme_boundary(2));
+        assert!(!rope.is_grapheme_boundary(1));
+        assert_eq!(rope.next_grapheme_boundary(2), 3);
+        assert_eq!(rope.prev_grapheme_boundary(2), 0);
+        assert_eq!(rope.next_grapheme_boundary(10), 4);
+        assert_eq!(rope.prev_grapheme_boundary(10), 3);
+
+        // A cluster across the boundary of two leaves
+        let text = format!(
+            "{}e\u{301}\u{302}👩\u{200D}👧z",
+            "a".repeat(LEAF_USABLE - 2)
+        );
+        let rope = self::rope(&text);
+        let start = LEAF_USABLE - 2;
+        assert_eq!(rope.next_grapheme_boundary(start), start + 5);
+        assert_eq!(rope.prev_grapheme_boundary(start + 5), start);
+        assert_eq!(rope.next_grapheme_boundary(start + 5), text.len() - 1);
+    }
+}
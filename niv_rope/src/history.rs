@@ -0,0 +1,176 @@
+// Undo history of a Rope.
+//
+// Every edit is recorded as the bytes it inserted or deleted at an offset,
+// which is all it takes to apply its inverse. Edits are grouped into steps,
+// the unit of undo and redo: the edits between `begin_transaction` and
+// `end_transaction` are one step, as are the deletion and insertion of a
+// replacement. Outside a transaction, an insertion that goes on where the
+// last step's insertion ended joins that step, so typed text is undone as a
+// whole rather than a byte at a time. Any other edit, and any undo or redo,
+// starts a new step.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Edit {
+    Insert { at: usize, text: Vec<u8> },
+    Delete { at: usize, text: Vec<u8> },
+}
+
+impl Edit {
+    pub(crate) fn at(&self) -> usize {
+        match self {
+            Edit::Insert { at, .. } | Edit::Delete { at, .. } => *at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct History {
+    /// Steps to undo, the last one first
+    undo: Vec<Vec<Edit>>,
+    /// Steps undone, the last one undone first to redo
+    redo: Vec<Vec<Edit>>,
+    /// How many transactions are open
+    depth: usize,
+    /// Whether the next edit may join the last step: inside a transaction
+    /// once it has a step, outside one after an insertion
+    open: bool,
+}
+
+impl History {
+    pub(crate) fn begin_transaction(&mut self) {
+        if self.depth == 0 {
+            self.open = false;
+        }
+        self.depth += 1;
+    }
+
+    pub(crate) fn end_transaction(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            self.open = false;
+        }
+    }
+
+    pub(crate) fn record(&mut self, edit: Edit) {
+        self.redo.clear();
+        if self.depth > 0 {
+            match self.undo.last_mut() {
+                Some(step) if self.open => step.push(edit),
+                _ => self.undo.push(vec![edit]),
+            }
+            self.open = true;
+            return;
+        }
+        let Edit::Insert { at, text } = edit else {
+            self.undo.push(vec![edit]);
+            self.open = false;
+            return;
+        };
+        // Typing on where the last insertion ended
+        let last = self.undo.last_mut().and_then(|step| step.last_mut());
+        match last.filter(|_| self.open) {
+            Some(Edit::Insert {
+                at: last_at,
+                text: last_text,
+            }) if *last_at + last_text.len() == at => last_text.extend_from_slice(&text),
+            _ => {
+                self.undo.push(vec![Edit::Insert { at, text }]);
+                self.open = true;
+            }
+        }
+    }
+
+    /// The step to undo, moved over to the redo list
+    pub(crate) fn undo_step(&mut self) -> Option<&[Edit]> {
+        self.open = false;
+        let step = self.undo.pop()?;
+        self.redo.push(step);
+        self.redo.last().map(Vec::as_slice)
+    }
+
+    /// The step to redo, moved back to the undo list
+    pub(crate) fn redo_step(&mut self) -> Option<&[Edit]> {
+        self.open = false;
+        let step = self.redo.pop()?;
+        self.undo.push(step);
+        self.undo.last().map(Vec::as_slice)
+    }
+
+    pub(crate) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(crate) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(at: usize, text: &str) -> Edit {
+        Edit::Insert {
+            at,
+            text: text.as_bytes().to_vec(),
+        }
+    }
+
+    fn delete(at: usize, text: &str) -> Edit {
+        Edit::Delete {
+            at,
+            text: text.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn history_coalesces_typing() {
+        let mut history = History::default();
+        history.record(insert(0, "h"));
+        history.record(insert(1, "i"));
+        history.record(insert(2, "!"));
+        // Elsewhere, then after a deletion
+        history.record(insert(0, ">"));
+        history.record(delete(0, ">"));
+        history.record(insert(0, "<"));
+        assert_eq!(history.undo_step(), Some(&[insert(0, "<")][..]));
+        assert_eq!(history.undo_step(), Some(&[delete(0, ">")][..]));
+        assert_eq!(history.undo_step(), Some(&[insert(0, ">")][..]));
+        assert_eq!(history.undo_step(), Some(&[insert(0, "hi!")][..]));
+        assert_eq!(history.undo_step(), None);
+    }
+
+    #[test]
+    fn history_transactions_are_one_step() {
+        let mut history = History::default();
+        history.record(insert(0, "ab"));
+        history.begin_transaction();
+        history.record(insert(2, "c"));
+        history.begin_transaction();
+        history.record(delete(0, "a"));
+        history.end_transaction();
+        history.record(insert(0, "x"));
+        history.end_transaction();
+        // The transaction ended, so this is a step of its own
+        history.record(insert(3, "d"));
+
+        assert_eq!(history.undo_step(), Some(&[insert(3, "d")][..]));
+        assert_eq!(
+            history.undo_step(),
+            Some(&[insert(2, "c"), delete(0, "a"), insert(0, "x")][..])
+        );
+        assert!(history.can_redo());
+        assert_eq!(history.redo_step().map(<[Edit]>::len), Some(3));
+
+        // A new edit drops what there was to redo
+        history.record(insert(0, "y"));
+        assert!(!history.can_redo());
+        assert!(history.can_undo());
+    }
+}
@@ -1,9 +1,19 @@
+mod diff;
+mod grapheme;
+mod history;
+mod mark;
 mod rbt;
 mod rbt_chunk;
+mod regex;
 mod rope;
+mod search;
 // tinyvec removed
 
+pub use diff::{DiffHunk, LineDiff, diff_lines};
+pub use mark::{Bias, MarkId};
 pub use rbt::RBTree;
 pub use rbt_chunk::RBError;
 pub use rbt_chunk::RBRope;
-pub use rope::Rope;
+pub use regex::{Regex, RegexError};
+pub use rope::{Chunks, Lines, Rope, RopeBuilder, RopeSlice, RopeSnapshot};
+pub use search::SearchIndex;
@@ -0,0 +1,127 @@
+// Marks of a Rope.
+//
+// A mark is a byte offset registered with the rope, which every edit after
+// it shifts along with the text: insertions before the mark move it right,
+// deletions before it move it left, and a deletion around it puts it where
+// the deleted text was. Text inserted right at the mark goes after or before
+// it as its bias says. Undo and redo are edits like any other, so a mark a
+// deletion collapsed stays at the start of the text the undo brings back.
+
+/// Handle of a mark, given out by [`crate::Rope::add_mark`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MarkId(u64);
+
+/// Which side of text inserted right at a mark the mark ends up on
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Bias {
+    /// Stay before the inserted text, as a mark on a cursor position does
+    #[default]
+    Before,
+    /// Move past the inserted text, as the end of a range does
+    After,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mark {
+    id: MarkId,
+    offset: usize,
+    bias: Bias,
+}
+
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Marks {
+    /// Marks in the order they were added, so by id
+    marks: Vec<Mark>,
+    next_id: u64,
+}
+
+impl Marks {
+    pub(crate) fn add(&mut self, offset: usize, bias: Bias) -> MarkId {
+        let id = MarkId(self.next_id);
+        self.next_id += 1;
+        self.marks.push(Mark { id, offset, bias });
+        id
+    }
+
+    pub(crate) fn get(&self, id: MarkId) -> Option<usize> {
+        self.find(id).map(|i| self.marks[i].offset)
+    }
+
+    pub(crate) fn set(&mut self, id: MarkId, offset: usize) -> bool {
+        match self.find(id) {
+            Some(i) => {
+                self.marks[i].offset = offset;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn remove(&mut self, id: MarkId) -> Option<usize> {
+        self.find(id).map(|i| self.marks.remove(i).offset)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (MarkId, usize)> + '_ {
+        self.marks.iter().map(|mark| (mark.id, mark.offset))
+    }
+
+    /// Shift the marks for `len` bytes inserted at `at`
+    pub(crate) fn inserted(&mut self, at: usize, len: usize) {
+        for mark in &mut self.marks {
+            if mark.offset > at || (mark.offset == at && mark.bias == Bias::After) {
+                mark.offset += len;
+            }
+        }
+    }
+
+    /// Shift the marks for bytes `start..end` deleted
+    pub(crate) fn deleted(&mut self, start: usize, end: usize) {
+        for mark in &mut self.marks {
+            if mark.offset >= end {
+                mark.offset -= end - start;
+            } else if mark.offset > start {
+                mark.offset = start;
+            }
+        }
+    }
+
+    /// Keep the marks within a text of `len` bytes that replaced the old one
+    pub(crate) fn clamp(&mut self, len: usize) {
+        for mark in &mut self.marks {
+            mark.offset = mark.offset.min(len);
+        }
+    }
+
+    fn find(&self, id: MarkId) -> Option<usize> {
+        self.marks.binary_search_by_key(&id, |mark| mark.id).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_shift_with_edits() {
+        let mut marks = Marks::default();
+        let before = marks.add(5, Bias::Before);
+        let after = marks.add(5, Bias::After);
+        let end = marks.add(10, Bias::Before);
+
+        marks.inserted(5, 3);
+        assert_eq!(marks.get(before), Some(5));
+        assert_eq!(marks.get(after), Some(8));
+        assert_eq!(marks.get(end), Some(13));
+
+        // Deleting around a mark collapses it to the start
+        marks.deleted(6, 12);
+        assert_eq!(marks.get(after), Some(6));
+        assert_eq!(marks.get(end), Some(7));
+
+        assert_eq!(marks.remove(after), Some(6));
+        assert_eq!(marks.get(after), None);
+        assert!(!marks.set(after, 0));
+        marks.clamp(4);
+        assert_eq!(marks.iter().collect::<Vec<_>>(), [(before, 4), (end, 4)]);
+    }
+}
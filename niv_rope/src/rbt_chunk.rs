@@ -14,6 +14,7 @@ pub enum RBError {
     TreeFull,
     InvalidOffset,
     InsufficientSpace,
+    NotCharBoundary,
 }
 impl std::fmt::Display for RBError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -21,6 +22,7 @@ impl std::fmt::Display for RBError {
             RBError::TreeFull => write!(f, "Tree is full"),
             RBError::InvalidOffset => write!(f, "Invalid offset"),
             RBError::InsufficientSpace => write!(f, "Insufficient space"),
+            RBError::NotCharBoundary => write!(f, "Offset is not on a char boundary"),
         }
     }
 }
@@ -112,11 +114,8 @@ impl Leaf {
                 }
             }
         }
-        if new_positions.is_empty() {
-            return;
-        }
         let insert_at = self.partition_point_nl(at);
-        // shift existing >= at by added count
+        // shift existing >= at by added count, even if no newline was inserted
         let added = data.len();
         for p in &mut self.nl_idx[insert_at..] {
             *p = (*p as usize + added) as u16;
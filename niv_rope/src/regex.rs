@@ -0,0 +1,847 @@
+// Regular expression search over a Rope.
+//
+// Patterns are compiled to a program for a Pike VM, which runs all the ways
+// a pattern can match side by side, one char of the text at a time. That
+// lets a search read the rope chunk by chunk, borrowing the leaves, instead
+// of copying the text into one buffer, and keeps it linear in the length of
+// the text whatever the pattern.
+//
+// Supported syntax:
+//   - literal chars, with `\` escaping the special ones; `\n`, `\r`, `\t`
+//   - `.` for any char but a newline
+//   - `[abc]`, `[a-z]`, `[^abc]` classes
+//   - `\d`, `\w`, `\s` and their negations `\D`, `\W`, `\S`, also in classes
+//   - `^` and `$` for the start and end of a line, `\b` and `\B` for word
+//     boundaries
+//   - `*`, `+`, `?`, `{n}`, `{n,}`, `{n,m}`, each lazy with a `?` after it
+//   - `|` alternation and `(...)` / `(?:...)` groups
+//   - a leading `(?i)` to ignore case
+//
+// Matches are leftmost-first as in Perl: of the matches starting at the
+// leftmost offset, the one the pattern prefers (greedy or lazy, earlier
+// alternatives first) is taken. Text that isn't valid UTF-8 is read as
+// U+FFFD, one per bad byte sequence.
+
+use crate::rope::{Chunks, Rope};
+use std::fmt;
+use std::ops::Range;
+
+// Most chars a `{n,m}` repetition may expand to, to keep programs small
+const MAX_REPEAT: u32 = 1000;
+
+/// A pattern that doesn't parse, with the char offset in it of the problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RegexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at char {} of the pattern",
+            self.message, self.position
+        )
+    }
+}
+
+impl std::error::Error for RegexError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Look {
+    LineStart,
+    LineEnd,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Look {
+    // Whether the assertion holds between `prev` and `next`, None at the
+    // start and the end of the text
+    fn holds(self, prev: Option<char>, next: Option<char>) -> bool {
+        let is_word = |ch: Option<char>| ch.is_some_and(is_word_char);
+        match self {
+            Look::LineStart => prev.is_none_or(|ch| ch == '\n'),
+            Look::LineEnd => next.is_none_or(|ch| ch == '\n'),
+            Look::WordBoundary => is_word(prev) != is_word(next),
+            Look::NotWordBoundary => is_word(prev) == is_word(next),
+        }
+    }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ClassItem {
+    Range(char, char),
+    /// `\d`, or `\D` when negated
+    Digit(bool),
+    /// `\w`, or `\W` when negated
+    Word(bool),
+    /// `\s`, or `\S` when negated
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, ch: char) -> bool {
+        match *self {
+            ClassItem::Range(lo, hi) => lo <= ch && ch <= hi,
+            ClassItem::Digit(negated) => ch.is_ascii_digit() != negated,
+            ClassItem::Word(negated) => is_word_char(ch) != negated,
+            ClassItem::Space(negated) => ch.is_whitespace() != negated,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+    /// Whether a char also matches by its other case
+    fold: bool,
+}
+
+impl Class {
+    fn matches(&self, ch: char) -> bool {
+        let any = |ch: char| self.items.iter().any(|item| item.matches(ch));
+        let found = any(ch)
+            || (self.fold
+                && (ch.to_lowercase().any(|lower| lower != ch && any(lower))
+                    || ch.to_uppercase().any(|upper| upper != ch && any(upper))));
+        found != self.negated
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Empty,
+    Char(char),
+    Any,
+    Class(Class),
+    Look(Look),
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+        greedy: bool,
+    },
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    fold: bool,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> RegexError {
+        RegexError {
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, ch: char) -> bool {
+        let found = self.peek() == Some(ch);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn parse_alt(&mut self) -> Result<Node, RegexError> {
+        let mut alternatives = vec![self.parse_concat()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_concat()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap_or(Node::Empty)
+        } else {
+            Node::Alt(alternatives)
+        })
+    }
+
+    fn parse_concat(&mut self) -> Result<Node, RegexError> {
+        let mut nodes = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            nodes.push(self.parse_quantifiers(atom)?);
+        }
+        Ok(Node::Concat(nodes))
+    }
+
+    fn parse_quantifiers(&mut self, mut node: Node) -> Result<Node, RegexError> {
+        loop {
+            let at = self.pos;
+            let (min, max) = match self.peek() {
+                Some('*') => (0, None),
+                Some('+') => (1, None),
+                Some('?') => (0, Some(1)),
+                Some('{') => match self.parse_counts() {
+                    Some(counts) => counts,
+                    // Not a repetition, so a literal brace
+                    None => return Ok(node),
+                },
+                _ => return Ok(node),
+            };
+            if self.pos == at {
+                self.pos += 1;
+            }
+            if matches!(node, Node::Look(_) | Node::Empty) {
+                self.pos = at;
+                return Err(self.error("nothing to repeat"));
+            }
+            if min > MAX_REPEAT || max.is_some_and(|max| max > MAX_REPEAT) {
+                self.pos = at;
+                return Err(self.error("repetition count too large"));
+            }
+            if max.is_some_and(|max| max < min) {
+                self.pos = at;
+                return Err(self.error("repetition range is reversed"));
+            }
+            let greedy = !self.eat('?');
+            node = Node::Repeat {
+                node: Box::new(node),
+                min,
+                max,
+                greedy,
+            };
+        }
+    }
+
+    // `{n}`, `{n,}` or `{n,m}`, consumed only when it is one
+    fn parse_counts(&mut self) -> Option<(u32, Option<u32>)> {
+        let start = self.pos;
+        self.pos += 1;
+        let number = |parser: &mut Parser| {
+            let from = parser.pos;
+            while parser.peek().is_some_and(|ch| ch.is_ascii_digit()) {
+                parser.pos += 1;
+            }
+            let digits: String = parser.chars[from..parser.pos].iter().collect();
+            digits.parse::<u32>().ok()
+        };
+        let counts = number(self).and_then(|min| {
+            if self.eat('}') {
+                return Some((min, Some(min)));
+            }
+            if !self.eat(',') {
+                return None;
+            }
+            if self.eat('}') {
+                return Some((min, None));
+            }
+            let max = number(self)?;
+            self.eat('}').then_some((min, Some(max)))
+        });
+        if counts.is_none() {
+            self.pos = start;
+        }
+        counts
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, RegexError> {
+        let Some(ch) = self.peek() else {
+            return Err(self.error("pattern ends early"));
+        };
+        self.pos += 1;
+        match ch {
+            '(' => {
+                // Groups only group: matches are reported as a whole
+                if self.eat('?') && !self.eat(':') {
+                    self.pos -= 1;
+                    return Err(self.error("unsupported group flag"));
+                }
+                let node = self.parse_alt()?;
+                if !self.eat(')') {
+                    return Err(self.error("missing )"));
+                }
+                Ok(node)
+            }
+            ')' => Err(self.error("unmatched )")),
+            '[' => self.parse_class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Look(Look::LineStart)),
+            '$' => Ok(Node::Look(Look::LineEnd)),
+            '*' | '+' | '?' => {
+                self.pos -= 1;
+                Err(self.error("nothing to repeat"))
+            }
+            '\\' => self.parse_escape(),
+            ch => Ok(self.literal(ch)),
+        }
+    }
+
+    fn literal(&self, ch: char) -> Node {
+        if self.fold && (ch.is_lowercase() || ch.is_uppercase()) {
+            Node::Class(Class {
+                items: vec![ClassItem::Range(ch, ch)],
+                negated: false,
+                fold: true,
+            })
+        } else {
+            Node::Char(ch)
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, RegexError> {
+        let Some(ch) = self.peek() else {
+            return Err(self.error("pattern ends in \\"));
+        };
+        self.pos += 1;
+        let class = |item| {
+            Node::Class(Class {
+                items: vec![item],
+                negated: false,
+                fold: false,
+            })
+        };
+        Ok(match ch {
+            'b' => Node::Look(Look::WordBoundary),
+            'B' => Node::Look(Look::NotWordBoundary),
+            'd' | 'D' | 'w' | 'W' | 's' | 'S' => class(shorthand(ch)),
+            _ => self.literal(escaped(ch)),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<Node, RegexError> {
+        let start = self.pos - 1;
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let Some(ch) = self.peek() else {
+                self.pos = start;
+                return Err(self.error("missing ]"));
+            };
+            self.pos += 1;
+            // A `]` first in the class is a literal one
+            if ch == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = match ch {
+                '\\' => {
+                    let Some(escape) = self.peek() else {
+                        return Err(self.error("pattern ends in \\"));
+                    };
+                    self.pos += 1;
+                    if "dDwWsS".contains(escape) {
+                        items.push(shorthand(escape));
+                        continue;
+                    }
+                    escaped(escape)
+                }
+                ch => ch,
+            };
+            // `a-z`, unless the `-` ends the class
+            let is_range = self.peek() == Some('-')
+                && self
+                    .chars
+                    .get(self.pos + 1)
+                    .is_some_and(|&next| next != ']');
+            if !is_range {
+                items.push(ClassItem::Range(lo, lo));
+                continue;
+            }
+            self.pos += 1;
+            let mut hi = self.chars[self.pos];
+            self.pos += 1;
+            if hi == '\\' {
+                let Some(escape) = self.peek() else {
+                    return Err(self.error("pattern ends in \\"));
+                };
+                self.pos += 1;
+                hi = escaped(escape);
+            }
+            if hi < lo {
+                return Err(self.error("class range is reversed"));
+            }
+            items.push(ClassItem::Range(lo, hi));
+        }
+        Ok(Node::Class(Class {
+            items,
+            negated,
+            fold: self.fold,
+        }))
+    }
+}
+
+fn shorthand(ch: char) -> ClassItem {
+    match ch {
+        'd' => ClassItem::Digit(false),
+        'D' => ClassItem::Digit(true),
+        'w' => ClassItem::Word(false),
+        'W' => ClassItem::Word(true),
+        's' => ClassItem::Space(false),
+        _ => ClassItem::Space(true),
+    }
+}
+
+// The char an escape other than a class or an assertion stands for
+fn escaped(ch: char) -> char {
+    match ch {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        ch => ch,
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Look(Look),
+    /// Go on at both, the first one preferred
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+/// A compiled regular expression, to search ropes with
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    prog: Vec<Inst>,
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+            fold: false,
+        };
+        if parser.chars.starts_with(&['(', '?', 'i', ')']) {
+            parser.pos = 4;
+            parser.fold = true;
+        }
+        let node = parser.parse_alt()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched )"));
+        }
+        let mut prog = Vec::new();
+        compile(&node, &mut prog);
+        prog.push(Inst::Match);
+        Ok(Self {
+            pattern: pattern.to_string(),
+            prog,
+        })
+    }
+
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// First match in `rope` starting at or after byte `start`, as a byte
+    /// range. The text before `start` still counts for `^` and `\b`.
+    pub fn find_at(&self, rope: &Rope, start: usize) -> Option<Range<usize>> {
+        let len = rope.len();
+        if start > len {
+            return None;
+        }
+        let prev = char_before(rope, start);
+        let mut chars = ChunkChars::new(rope.slice(start, len).chunks(), start);
+        let mut vm = Vm::new(self.prog.len());
+        vm.run(&self.prog, prev, &mut chars, start)
+    }
+
+    /// Byte ranges of the matches in `rope`, in order and not overlapping.
+    /// After an empty match the search goes on past the next char.
+    pub fn find_all(&self, rope: &Rope) -> Vec<Range<usize>> {
+        let mut found = Vec::new();
+        let mut start = 0;
+        while let Some(range) = self.find_at(rope, start) {
+            start = if range.is_empty() {
+                match ChunkChars::new(rope.slice(range.end, rope.len()).chunks(), range.end).next()
+                {
+                    Some((_, _, next)) => next,
+                    None => {
+                        found.push(range);
+                        break;
+                    }
+                }
+            } else {
+                range.end
+            };
+            found.push(range);
+        }
+        found
+    }
+}
+
+fn compile(node: &Node, prog: &mut Vec<Inst>) {
+    match node {
+        Node::Empty => {}
+        Node::Char(ch) => prog.push(Inst::Char(*ch)),
+        Node::Any => prog.push(Inst::Any),
+        Node::Class(class) => prog.push(Inst::Class(class.clone())),
+        Node::Look(look) => prog.push(Inst::Look(*look)),
+        Node::Concat(nodes) => nodes.iter().for_each(|node| compile(node, prog)),
+        Node::Alt(alternatives) => {
+            // Split to each alternative in turn, all jumping to the end
+            let mut jumps = Vec::new();
+            for (i, alternative) in alternatives.iter().enumerate() {
+                if i + 1 < alternatives.len() {
+                    let split = prog.len();
+                    prog.push(Inst::Split(split + 1, 0));
+                    compile(alternative, prog);
+                    jumps.push(prog.len());
+                    prog.push(Inst::Jmp(0));
+                    let next = prog.len();
+                    prog[split] = Inst::Split(split + 1, next);
+                } else {
+                    compile(alternative, prog);
+                }
+            }
+            let end = prog.len();
+            for jump in jumps {
+                prog[jump] = Inst::Jmp(end);
+            }
+        }
+        Node::Repeat {
+            node,
+            min,
+            max,
+            greedy,
+        } => {
+            for _ in 0..*min {
+                compile(node, prog);
+            }
+            let split = |body: usize, out: usize| {
+                if *greedy {
+                    Inst::Split(body, out)
+                } else {
+                    Inst::Split(out, body)
+                }
+            };
+            match max {
+                None => {
+                    let start = prog.len();
+                    prog.push(Inst::Jmp(0));
+                    compile(node, prog);
+                    prog.push(Inst::Jmp(start));
+                    let end = prog.len();
+                    prog[start] = split(start + 1, end);
+                }
+                Some(max) => {
+                    // Each optional copy may skip all the ones after it
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(prog.len());
+                        prog.push(Inst::Jmp(0));
+                        compile(node, prog);
+                    }
+                    let end = prog.len();
+                    for at in splits {
+                        prog[at] = split(at + 1, end);
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Char ending just before byte `offset`, if any
+fn char_before(rope: &Rope, offset: usize) -> Option<char> {
+    let from = offset.saturating_sub(4);
+    let mut bytes = [0u8; 4];
+    let read = rope
+        .read_bytes_global(from, &mut bytes[..offset - from])
+        .ok()?;
+    String::from_utf8_lossy(&bytes[..read]).chars().next_back()
+}
+
+/// Chars of rope chunks with their byte offsets and the offset past them,
+/// put together across chunk boundaries
+struct ChunkChars<'a> {
+    chunks: Chunks<'a>,
+    chunk: &'a [u8],
+    /// Offset of `chunk` in the rope
+    chunk_start: usize,
+    at: usize,
+}
+
+impl<'a> ChunkChars<'a> {
+    fn new(chunks: Chunks<'a>, start: usize) -> Self {
+        Self {
+            chunks,
+            chunk: &[],
+            chunk_start: start,
+            at: 0,
+        }
+    }
+
+    fn peek_byte(&mut self) -> Option<u8> {
+        while self.at == self.chunk.len() {
+            let (start, chunk) = self.chunks.next()?;
+            self.chunk = chunk;
+            self.chunk_start = start;
+            self.at = 0;
+        }
+        Some(self.chunk[self.at])
+    }
+}
+
+impl Iterator for ChunkChars<'_> {
+    /// Offset, char and offset past the char
+    type Item = (usize, char, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.peek_byte()?;
+        let start = self.chunk_start + self.at;
+        self.at += 1;
+        if first.is_ascii() {
+            return Some((start, first as char, start + 1));
+        }
+        let width = match first {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        let mut bytes = [first, 0, 0, 0];
+        let mut len = 1;
+        while len < width {
+            match self.peek_byte() {
+                Some(byte) if byte & 0xC0 == 0x80 => {
+                    bytes[len] = byte;
+                    len += 1;
+                    self.at += 1;
+                }
+                _ => break,
+            }
+        }
+        let ch = std::str::from_utf8(&bytes[..len])
+            .ok()
+            .and_then(|text| text.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        Some((start, ch, start + len))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Thread {
+    pc: usize,
+    /// Offset the match of this thread started at
+    start: usize,
+}
+
+struct Vm {
+    current: Vec<Thread>,
+    next: Vec<Thread>,
+    /// Generation each instruction was last added to a list in
+    seen: Vec<usize>,
+    generation: usize,
+    stack: Vec<usize>,
+}
+
+impl Vm {
+    fn new(len: usize) -> Self {
+        Self {
+            current: Vec::new(),
+            next: Vec::new(),
+            seen: vec![0; len],
+            generation: 0,
+            stack: Vec::new(),
+        }
+    }
+
+    // Add the thread at `pc` to the current list, or the next one with
+    // `to_next`, following jumps, splits and the assertions that hold
+    // `around` it, in priority order
+    fn add(&mut self, to_next: bool, prog: &[Inst], pc: usize, start: usize, around: Around) {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] == self.generation {
+                continue;
+            }
+            self.seen[pc] = self.generation;
+            match prog[pc] {
+                Inst::Jmp(target) => self.stack.push(target),
+                Inst::Split(first, second) => {
+                    self.stack.push(second);
+                    self.stack.push(first);
+                }
+                Inst::Look(look) => {
+                    if look.holds(around.0, around.1) {
+                        self.stack.push(pc + 1);
+                    }
+                }
+                _ => {
+                    let list = if to_next {
+                        &mut self.next
+                    } else {
+                        &mut self.current
+                    };
+                    list.push(Thread { pc, start });
+                }
+            }
+        }
+    }
+
+    fn run(
+        &mut self,
+        prog: &[Inst],
+        mut prev: Option<char>,
+        chars: &mut ChunkChars,
+        mut pos: usize,
+    ) -> Option<Range<usize>> {
+        let mut matched = None;
+        let mut cur = chars.next();
+        self.generation += 1;
+        loop {
+            // A new match may start here, unless one started further left
+            if matched.is_none() {
+                let around = (prev, cur.map(|(_, ch, _)| ch));
+                self.add(false, prog, 0, pos, around);
+            }
+            if self.current.is_empty() && (matched.is_some() || cur.is_none()) {
+                break;
+            }
+            let ahead = match cur {
+                Some(_) => chars.next(),
+                None => None,
+            };
+            self.generation += 1;
+            let current = std::mem::take(&mut self.current);
+            for thread in &current {
+                let step = match (&prog[thread.pc], cur) {
+                    (Inst::Match, _) => {
+                        // Threads after this one are less preferred
+                        matched = Some(thread.start..pos);
+                        break;
+                    }
+                    (Inst::Char(want), Some((_, ch, _))) => *want == ch,
+                    (Inst::Any, Some((_, ch, _))) => ch != '\n',
+                    (Inst::Class(class), Some((_, ch, _))) => class.matches(ch),
+                    _ => false,
+                };
+                if let (true, Some((_, ch, _))) = (step, cur) {
+                    let around = (Some(ch), ahead.map(|(_, ch, _)| ch));
+                    self.add(true, prog, thread.pc + 1, thread.start, around);
+                }
+            }
+            self.current = current;
+            self.current.clear();
+            std::mem::swap(&mut self.current, &mut self.next);
+            let Some((_, ch, end)) = cur else {
+                break;
+            };
+            prev = Some(ch);
+            pos = end;
+            cur = ahead;
+        }
+        self.current.clear();
+        matched
+    }
+}
+
+// The chars before and after a position
+type Around = (Option<char>, Option<char>);
+
+impl Rope {
+    /// Byte ranges of the matches of the regular expression `pattern`, in
+    /// order and not overlapping, found reading the text chunk by chunk.
+    /// See [`Regex`] for the syntax.
+    pub fn find_regex(&self, pattern: &str) -> Result<Vec<Range<usize>>, RegexError> {
+        Ok(Regex::new(pattern)?.find_all(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rope::LEAF_USABLE;
+
+    fn rope(text: &str) -> Rope {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(text.as_bytes()).expect("build");
+        rope
+    }
+
+    fn found<'a>(pattern: &str, text: &'a str) -> Vec<&'a str> {
+        let rope = rope(text);
+        let ranges = rope.find_regex(pattern).expect("valid pattern");
+        ranges.into_iter().map(|range| &text[range]).collect()
+    }
+
+    #[test]
+    fn regex_matches_like_perl() {
+        assert_eq!(found("a+", "caaat a"), ["aaa", "a"]);
+        assert_eq!(found("a+?", "aa"), ["a", "a"]);
+        assert_eq!(found("cat|category", "category"), ["cat"]);
+        assert_eq!(found("(?:ab)+c", "abababc abc"), ["abababc", "abc"]);
+        assert_eq!(found(r"\d{2,3}", "1 12 1234"), ["12", "123"]);
+        assert_eq!(found("x{2}", "xxxxx"), ["xx", "xx"]);
+        assert_eq!(found("[a-c]+", "abcdcba"), ["abc", "cba"]);
+        assert_eq!(found("[^a-c ]+", "abc def"), ["def"]);
+        assert_eq!(found(r"[\d_]+", "a_1b"), ["_1"]);
+        assert_eq!(found("a.c", "abc a\nc"), ["abc"]);
+        assert_eq!(found("é+", "café éé"), ["é", "éé"]);
+        assert_eq!(found("a{,2}", "a{,2}"), ["a{,2}"]);
+    }
+
+    #[test]
+    fn regex_assertions_and_case() {
+        assert_eq!(found("^a", "ab\nab"), ["a", "a"]);
+        assert_eq!(found("b$", "ab\nab"), ["b", "b"]);
+        assert_eq!(found(r"\bis\b", "this is it"), ["is"]);
+        assert_eq!(found(r"\Bis", "this is"), ["is"]);
+        assert_eq!(found("(?i)hello", "Hello HELLO"), ["Hello", "HELLO"]);
+        assert_eq!(found("(?i)[a-c]+", "ABC"), ["ABC"]);
+
+        // Empty matches, one between each char
+        assert_eq!(found("x*", "ab").len(), 3);
+        assert_eq!(found("^", "a\nb").len(), 2);
+
+        // The text before the start counts for `\b`
+        let text = rope("foobar bar");
+        let regex = Regex::new(r"\bbar").unwrap();
+        assert_eq!(regex.find_at(&text, 3), Some(7..10));
+    }
+
+    #[test]
+    fn regex_streams_across_leaves() {
+        let mut text = String::new();
+        while text.len() < LEAF_USABLE * 3 {
+            text.push_str("fn main() { println!(\"ünïcode\"); }\n");
+        }
+        let rope = rope(&text);
+        let matches = rope.find_regex(r#""[^"]*""#).unwrap();
+        assert_eq!(matches.len(), text.matches("\"ünïcode\"").count());
+        for range in matches {
+            assert_eq!(&text[range], "\"ünïcode\"");
+        }
+    }
+
+    #[test]
+    fn regex_reports_bad_patterns() {
+        let error = |pattern| Regex::new(pattern).unwrap_err();
+        assert_eq!(error("a(b").message, "missing )");
+        assert_eq!(error("a)").position, 1);
+        assert_eq!(error("*a").message, "nothing to repeat");
+        assert_eq!(error("[a").message, "missing ]");
+        assert_eq!(error("[z-a]").message, "class range is reversed");
+        assert_eq!(error("a{3,2}").message, "repetition range is reversed");
+        assert_eq!(error("a{2000}").message, "repetition count too large");
+        assert_eq!(
+            error("a\\").to_string(),
+            "pattern ends in \\ at char 2 of the pattern"
+        );
+    }
+}
@@ -7,32 +7,56 @@
 // ✅ IMPLEMENTED:
 //   - Basic leaf operations (insert, delete, read)
 //   - Red-Black tree structure and balancing
-//   - Simple find and replace operations
-//   - Subtree metadata maintenance (sub_bytes, sub_lines)
-//   - Zero-copy slice iterator (RopeSlice)
+//   - Find and replace operations (find_first, find_all, replace_first, replace_all)
+//   - Cached literal search following edits (SearchIndex)
+//   - Regex search streaming over the chunks (Regex, find_regex)
+//   - Undo/redo of edit steps, transactions and typing coalesced (undo, redo)
+//   - Marks that follow the edits, for marks, jump lists and diagnostics (add_mark)
+//   - Subtree metadata maintenance (sub_bytes, sub_lines, sub_chars)
+//   - Char offsets alongside byte offsets (char_len, byte_to_char, char_to_byte)
+//   - UTF-8 safe edits refusing offsets inside a char (insert_str_at, delete_str_range)
+//   - Leaves split on char boundaries, never between the bytes of one char
+//   - Grapheme cluster boundaries for cursor movement (next_grapheme_boundary)
+//   - Line/byte offset conversion (line_to_byte, byte_to_line)
+//   - Single line access (get_line, get_line_content)
+//   - Lazy line iteration over a rope or slice (lines)
+//   - Zero-copy slice iterator (RopeSlice) and chunks with offsets (chunks)
+//   - Copy-on-write snapshots sharing leaves with the rope (snapshot)
+//   - Line diffs between ropes, skipping the leaves they share (diff)
+//   - Equality with ropes and strs, and a hash cached per leaf (content_hash)
+//   - Conversion from strs and to Strings (From<&str>, FromIterator, Display)
+//   - Streaming construction from a reader, leaf by leaf (build_from_reader)
+//   - Appending text in pieces into a tree balanced once at the end (RopeBuilder)
+//   - Writing the text out leaf by leaf, without a copy (write_to)
+//   - Merging underfilled leaves and respacing keys when idle (optimize, merge_leaves)
+//   - Insertion at any offset, splitting leaves that overflow (insert_at)
+//   - Range deletion across leaves, merging underfull neighbours (delete_range)
+//   - Leaf splitting for replacements that overflow a leaf
+//   - Replacements of matches spanning leaves
 //   - 20% buffer space for moderate edits
 //
 // ❌ MISSING (Production Requirements):
-//   - Tree restructuring for leaf overflow
-//   - Cross-leaf content coordination
-//   - Advanced text operations (delete_range, insert_at)
 //   - Performance optimizations (binary search, lazy evaluation)
-//   - History management (undo/redo)
-//   - Tree optimization and leaf merging
-//   - Line-based operations and navigation
 //   - Bulk operations and batch processing
 //   - Memory management and garbage collection
-//
-// Current implementation: ~60% complete (core structure + basic operations)
-// Production ready: ~30% (needs tree restructuring and metadata maintenance)
 
+use crate::history::{Edit, History};
+use crate::mark::{Bias, MarkId, Marks};
 use crate::rbt_chunk::RBError;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::Hasher;
+use std::io::{self, Read};
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 // Basic types and constants
 pub type NodeId = u64;
 pub const NIL: NodeId = u64::MAX;
 pub const LEAF_CAPACITY: usize = 2048; // maximum bytes in a leaf buffer
 pub const LEAF_USABLE: usize = (LEAF_CAPACITY * 80) / 100; // 80% of capacity (1638 bytes) for actual content
+const KEY_STRIDE: u64 = 1 << 16; // spacing of leaf keys, leaving room for leaves split in between
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -47,6 +71,97 @@ struct Leaf {
     gap_lo: u16,
     gap_hi: u16,
     nl_idx: Vec<u16>,
+    chars: u16,
+    hash: LeafHash,
+}
+
+// Hash of a leaf's text, worked out when first asked for and forgotten when
+// the leaf is edited. Not part of the leaf's value: clones start from it and
+// it never makes leaves unequal.
+#[derive(Debug, Default)]
+struct LeafHash(AtomicU64);
+
+// A `LeafHash` not worked out yet; a hash that comes out as this is stored
+// as 1 instead
+const NO_HASH: u64 = 0;
+
+impl LeafHash {
+    fn get(&self) -> Option<u64> {
+        match self.0.load(Ordering::Relaxed) {
+            NO_HASH => None,
+            hash => Some(hash),
+        }
+    }
+
+    fn set(&self, hash: u64) {
+        self.0.store(hash.max(1), Ordering::Relaxed);
+    }
+
+    fn clear(&mut self) {
+        *self.0.get_mut() = NO_HASH;
+    }
+}
+
+impl Clone for LeafHash {
+    fn clone(&self) -> Self {
+        LeafHash(AtomicU64::new(self.0.load(Ordering::Relaxed)))
+    }
+}
+
+impl PartialEq for LeafHash {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+// Whether `byte` starts a char, that is isn't a UTF-8 continuation byte
+#[inline]
+fn is_char_start(byte: u8) -> bool {
+    byte & 0xC0 != 0x80
+}
+
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| is_char_start(b)).count()
+}
+
+// Length of `bytes` up to the end of its last whole char, leaving out a char
+// cut off at the end. Bytes that aren't UTF-8 count as whole chars.
+fn whole_chars_len(bytes: &[u8]) -> usize {
+    let len = bytes.len();
+    let Some(start) = (len.saturating_sub(4)..len)
+        .rev()
+        .find(|&i| is_char_start(bytes[i]))
+    else {
+        return len;
+    };
+    let width = match bytes[start] {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    };
+    if start + width > len { start } else { len }
+}
+
+// `text` in pieces of at most LEAF_USABLE bytes for leaves, each ending on a
+// char boundary so no char is split between two leaves
+fn leaf_chunks(text: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let mut take = rest.len().min(LEAF_USABLE);
+        if take < rest.len() {
+            take = match whole_chars_len(&rest[..take]) {
+                0 => take,
+                whole => whole,
+            };
+        }
+        let (chunk, tail) = rest.split_at(take);
+        rest = tail;
+        Some(chunk)
+    })
 }
 
 impl Leaf {
@@ -56,9 +171,25 @@ impl Leaf {
             gap_lo: 0,
             gap_hi: LEAF_CAPACITY as u16,
             nl_idx: Vec::new(),
+            chars: 0,
+            hash: LeafHash::default(),
         }
     }
 
+    // Hash of the text, from the cache when the leaf wasn't edited since
+    fn content_hash(&self) -> u64 {
+        if let Some(hash) = self.hash.get() {
+            return hash;
+        }
+        let (lo, hi) = self.halves();
+        let mut hasher = DefaultHasher::new();
+        hasher.write(lo);
+        hasher.write(hi);
+        let hash = hasher.finish();
+        self.hash.set(hash);
+        hash
+    }
+
     #[inline]
     fn gap_size(&self) -> usize {
         self.gap_hi as usize - self.gap_lo as usize
@@ -69,6 +200,13 @@ impl Leaf {
         self.gap_lo as usize + (LEAF_CAPACITY - self.gap_hi as usize)
     }
 
+    // Chars starting in this leaf; one split across leaves is counted in the
+    // leaf holding its first byte
+    #[inline]
+    fn char_len(&self) -> usize {
+        self.chars as usize
+    }
+
     #[inline]
     fn gap_lo(&self) -> usize {
         self.gap_lo as usize
@@ -79,11 +217,6 @@ impl Leaf {
         self.gap_hi as usize
     }
 
-    #[inline]
-    fn buf(&self) -> &[u8; LEAF_CAPACITY] {
-        &self.buf
-    }
-
     #[inline]
     fn move_gap_to(&mut self, off: usize) {
         let gl = self.gap_lo as usize;
@@ -103,6 +236,32 @@ impl Leaf {
         }
     }
 
+    // Text before and after the gap
+    #[inline]
+    fn halves(&self) -> (&[u8], &[u8]) {
+        (&self.buf[..self.gap_lo()], &self.buf[self.gap_hi()..])
+    }
+
+    // Chars starting in bytes `0..off`
+    fn chars_before(&self, off: usize) -> usize {
+        let (lo, hi) = self.halves();
+        let in_lo = off.min(lo.len());
+        let in_hi = (off - in_lo).min(hi.len());
+        count_chars(&lo[..in_lo]) + count_chars(&hi[..in_hi])
+    }
+
+    // Byte offset of the `n`th char starting in this leaf, or the leaf's
+    // length when fewer chars start in it
+    fn byte_of_char(&self, n: usize) -> usize {
+        let (lo, hi) = self.halves();
+        lo.iter()
+            .chain(hi)
+            .enumerate()
+            .filter(|&(_, &b)| is_char_start(b))
+            .nth(n)
+            .map_or(self.byte_len(), |(i, _)| i)
+    }
+
     #[inline]
     fn partition_point_nl(&self, at: usize) -> usize {
         self.nl_idx.partition_point(|&p| (p as usize) < at)
@@ -121,9 +280,7 @@ impl Leaf {
                 }
             }
         }
-        if new_positions.is_empty() {
-            return;
-        }
+        // Newlines after the insertion point move even if none were inserted
         let insert_at = self.partition_point_nl(at);
         let added = data.len();
         for p in &mut self.nl_idx[insert_at..] {
@@ -162,10 +319,47 @@ impl Leaf {
         let gl = self.gap_lo as usize;
         self.buf[gl..gl + to_copy].copy_from_slice(&data[..to_copy]);
         self.gap_lo = (gl + to_copy) as u16;
+        self.chars += count_chars(&data[..to_copy]) as u16;
         self.insert_newline_indices(off, &data[..to_copy]);
         Ok(to_copy)
     }
 
+    // Fill an empty leaf with `carry`, then with bytes of `reader` read
+    // straight into its buffer, up to LEAF_USABLE bytes in all; short only
+    // at the end of the input. A char cut off at the end of a full leaf goes
+    // back to `carry` to start the next one. Returns whether it filled up.
+    fn fill_from<R: Read>(&mut self, reader: &mut R, carry: &mut Vec<u8>) -> io::Result<bool> {
+        let mut filled = carry.len();
+        self.buf[..filled].copy_from_slice(carry);
+        carry.clear();
+        while filled < LEAF_USABLE {
+            match reader.read(&mut self.buf[filled..LEAF_USABLE]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let full = filled == LEAF_USABLE;
+        if full {
+            let whole = whole_chars_len(&self.buf[..filled]);
+            if whole > 0 {
+                carry.extend_from_slice(&self.buf[whole..filled]);
+                filled = whole;
+            }
+        }
+        let data = &self.buf[..filled];
+        self.chars = count_chars(data) as u16;
+        self.nl_idx = data
+            .iter()
+            .enumerate()
+            .filter(|&(_, &b)| b == b'\n')
+            .map(|(i, _)| i as u16)
+            .collect();
+        self.gap_lo = filled as u16;
+        Ok(full)
+    }
+
     fn delete(&mut self, off: usize, len: usize) -> Result<usize, RBError> {
         let cur_len = self.byte_len();
         if off > cur_len {
@@ -180,7 +374,9 @@ impl Leaf {
             return Ok(0);
         }
         self.move_gap_to(off);
-        self.gap_hi = (self.gap_hi as usize + actual) as u16;
+        let gh = self.gap_hi as usize;
+        self.chars -= count_chars(&self.buf[gh..gh + actual]) as u16;
+        self.gap_hi = (gh + actual) as u16;
         self.remove_newline_indices_in_range(off, off + actual);
         Ok(actual)
     }
@@ -222,12 +418,24 @@ struct Node {
     color: Color,
     sub_bytes: u64,
     sub_lines: u64,
+    sub_chars: u64,
     payload: Payload,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 enum Payload {
-    Leaf(Leaf),
+    /// Shared with the snapshots taken since it was last edited
+    Leaf(Arc<Leaf>),
+}
+
+impl Payload {
+    // The leaf to edit, copied first when a snapshot shares it
+    fn leaf_mut(&mut self) -> &mut Leaf {
+        let Payload::Leaf(leaf) = self;
+        let leaf = Arc::make_mut(leaf);
+        leaf.hash.clear();
+        leaf
+    }
 }
 
 impl Node {
@@ -240,7 +448,8 @@ impl Node {
             color: Color::Red,
             sub_bytes: 0,
             sub_lines: 0,
-            payload: Payload::Leaf(Leaf::new()),
+            sub_chars: 0,
+            payload: Payload::Leaf(Arc::new(Leaf::new())),
         }
     }
 }
@@ -249,15 +458,142 @@ impl Node {
 pub struct Rope {
     root: NodeId,
     nodes: Vec<Node>,
+    /// Bumped by every edit
+    revision: u64,
+    /// Edits to undo and redo
+    history: History,
+    /// Offsets that follow the edits
+    marks: Marks,
+}
+
+/// A read-only copy of a [`Rope`] as it was when [`Rope::snapshot`] took
+/// it, which the rope's later edits don't change. It shares the leaves with
+/// the rope, each copied only when the rope first edits it afterwards, and
+/// clones of it share everything, so it can be handed to a saving thread or
+/// kept to diff against. It reads like the rope, through `Deref`.
+#[derive(Debug, Clone)]
+pub struct RopeSnapshot {
+    rope: Arc<Rope>,
+}
+
+impl Deref for RopeSnapshot {
+    type Target = Rope;
+
+    fn deref(&self) -> &Rope {
+        &self.rope
+    }
 }
 
 #[derive(Debug)]
 pub struct RopeSlice<'a> {
     rope: &'a Rope,
-    start: usize,
     end: usize,
     current_offset: usize,
     current_node: NodeId,
+    /// Offset the text of `current_node` starts at
+    node_start: usize,
+}
+
+/// Chunks of the text of a [`Rope`] or [`RopeSlice`] with the byte offset
+/// each starts at, borrowed from the leaves without copying
+#[derive(Debug)]
+pub struct Chunks<'a> {
+    slice: RopeSlice<'a>,
+}
+
+/// Lines of a [`Rope`] or [`RopeSlice`], each a slice with its trailing
+/// newline, found one at a time on the newline counts
+#[derive(Debug)]
+pub struct Lines<'a> {
+    rope: &'a Rope,
+    start: usize,
+    end: usize,
+    /// Whether the text after the last newline is a line even when empty,
+    /// as it is for the whole rope
+    empty_last: bool,
+    done: bool,
+}
+
+/// Builds a [`Rope`] from text that arrives in pieces, as a file read block
+/// by block or text synced from a language server. Appended bytes fill
+/// leaves in order, with no tree to keep balanced until [`RopeBuilder::finish`]
+/// puts them all in one that is balanced from the start.
+#[derive(Debug, Clone)]
+pub struct RopeBuilder {
+    leaves: Vec<Leaf>,
+    /// Leaf being filled, after `leaves`
+    current: Leaf,
+    len: usize,
+}
+
+impl RopeBuilder {
+    pub fn new() -> Self {
+        Self {
+            leaves: Vec::new(),
+            current: Leaf::new(),
+            len: 0,
+        }
+    }
+
+    /// Add `data` at the end of the text. A char may be split between two
+    /// appends; its bytes still end up in one leaf.
+    pub fn append(&mut self, mut data: &[u8]) {
+        self.len += data.len();
+        while !data.is_empty() {
+            let end = self.current.byte_len();
+            if end == LEAF_USABLE {
+                self.start_leaf();
+                continue;
+            }
+            let take = data.len().min(LEAF_USABLE - end);
+            let _ = self.current.insert(end, &data[..take]);
+            data = &data[take..];
+        }
+    }
+
+    /// Bytes appended so far
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The rope of all the text appended
+    pub fn finish(self) -> Rope {
+        let mut rope = Rope::new();
+        rope.set_leaves(self.into_leaves());
+        rope
+    }
+
+    // Put the full current leaf after the others and start the next one,
+    // moving a char cut off at the end of the full one over to it
+    fn start_leaf(&mut self) {
+        let (text, _) = self.current.halves();
+        let whole = match whole_chars_len(text) {
+            0 => text.len(),
+            whole => whole,
+        };
+        let carry = text[whole..].to_vec();
+        let _ = self.current.delete(whole, carry.len());
+        let full = std::mem::replace(&mut self.current, Leaf::new());
+        self.leaves.push(full);
+        let _ = self.current.insert(0, &carry);
+    }
+
+    fn into_leaves(mut self) -> Vec<Leaf> {
+        if self.current.byte_len() > 0 {
+            self.leaves.push(self.current);
+        }
+        self.leaves
+    }
+}
+
+impl Default for RopeBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Rope {
@@ -265,19 +601,40 @@ impl Rope {
         Self {
             root: NIL,
             nodes: Vec::new(),
+            revision: 0,
+            history: History::default(),
+            marks: Marks::default(),
         }
     }
 
+    /// Length of the text in bytes, kept by the root's subtree count
     pub fn len(&self) -> usize {
-        let mut total = 0usize;
-        for n in &self.nodes {
-            match &n.payload {
-                Payload::Leaf(l) => {
-                    total += l.byte_len();
-                }
-            }
+        if self.root == NIL {
+            0
+        } else {
+            self.nodes[self.root as usize].sub_bytes as usize
+        }
+    }
+
+    /// Counter bumped by every edit, so caches over the text can tell that
+    /// the rope changed under them. Clones share the revision they were
+    /// taken at.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Read-only copy of the text as it is now. Taking it copies the tree
+    /// nodes but none of the text; the undo history and marks are left out.
+    pub fn snapshot(&self) -> RopeSnapshot {
+        RopeSnapshot {
+            rope: Arc::new(Rope {
+                root: self.root,
+                nodes: self.nodes.clone(),
+                revision: self.revision,
+                history: History::default(),
+                marks: Marks::default(),
+            }),
         }
-        total
     }
 
     pub fn total_lines(&self) -> usize {
@@ -288,21 +645,21 @@ impl Rope {
         }
     }
 
-    pub fn slice(&self, start: usize, end: usize) -> RopeSlice {
+    pub fn slice(&self, start: usize, end: usize) -> RopeSlice<'_> {
         let len = self.len();
         let start = start.min(len);
         let end = end.min(len);
-        let current_node = if start < len {
-            self.min_node(self.root)
+        let (current_node, node_start) = if start < end {
+            self.leaf_at(start)
         } else {
-            NIL
+            (NIL, start)
         };
         RopeSlice {
             rope: self,
-            start,
             end,
             current_offset: start,
             current_node,
+            node_start,
         }
     }
 
@@ -341,8 +698,16 @@ impl Rope {
         let own_lines = match &self.nodes[idx].payload {
             Payload::Leaf(l) => l.nl_idx.len(),
         };
+        let child_chars = [left, right]
+            .iter()
+            .filter(|&&child| child != NIL)
+            .map(|&child| self.nodes[child as usize].sub_chars)
+            .sum::<u64>();
+        let Payload::Leaf(l) = &self.nodes[idx].payload;
+        let own_chars = l.char_len() as u64;
         self.nodes[idx].sub_bytes = (left_bytes + own + right_bytes) as u64;
         self.nodes[idx].sub_lines = (left_lines + own_lines + right_lines) as u64;
+        self.nodes[idx].sub_chars = child_chars + own_chars;
     }
 
     // Update aggregates from this node up to the root
@@ -355,29 +720,103 @@ impl Rope {
         }
     }
 
+    /// Replace the text with `data`, returning the number of bytes written.
+    /// The undo history starts over, as for a newly loaded text.
     pub fn build_from_bytes(&mut self, data: &[u8]) -> Result<usize, RBError> {
-        self.root = NIL;
-        self.nodes.clear();
-        let mut inserted_total = 0usize;
-        let mut key: u64 = 0;
-        while inserted_total < data.len() {
-            let remaining = data.len() - inserted_total;
-            let take = if remaining > LEAF_USABLE {
-                LEAF_USABLE
-            } else {
-                remaining
-            };
-            let new_id = self.insert_with_id(key)?;
-            key = key.saturating_add(1);
-            let leaf = match &mut self.nodes[new_id as usize].payload {
-                Payload::Leaf(l) => l,
+        let mut builder = RopeBuilder::new();
+        builder.append(data);
+        self.set_leaves(builder.into_leaves());
+        Ok(data.len())
+    }
+
+    /// Build the rope from everything `reader` yields, reading each leaf's
+    /// bytes straight into it, so the whole input is never held in memory
+    /// at once. Returns the number of bytes read; a read error leaves the
+    /// rope with the bytes read before it.
+    pub fn build_from_reader<R: Read>(&mut self, mut reader: R) -> io::Result<usize> {
+        let mut leaves = Vec::new();
+        let mut total = 0usize;
+        let mut carry = Vec::new();
+        loop {
+            let mut leaf = Leaf::new();
+            let full = match leaf.fill_from(&mut reader, &mut carry) {
+                Ok(full) => full,
+                Err(e) => {
+                    self.set_leaves(leaves);
+                    return Err(e);
+                }
             };
-            let wrote = leaf.insert(0, &data[inserted_total..inserted_total + take])?;
-            inserted_total += wrote;
-            // Update aggregates for this new leaf up to root
-            self.update_ancestors(new_id);
+            let filled = leaf.byte_len();
+            if filled == 0 {
+                break;
+            }
+            leaves.push(leaf);
+            total += filled;
+            if !full {
+                break;
+            }
+        }
+        self.set_leaves(leaves);
+        Ok(total)
+    }
+
+    // Replace the text with `leaves`, in order, in a tree balanced from the
+    // start. The undo history starts over.
+    fn set_leaves(&mut self, leaves: Vec<Leaf>) {
+        let count = leaves.len();
+        self.nodes = leaves
+            .into_iter()
+            .enumerate()
+            .map(|(i, leaf)| Node {
+                key: i as u64 * KEY_STRIDE,
+                left: NIL,
+                right: NIL,
+                parent: NIL,
+                color: Color::Black,
+                sub_bytes: 0,
+                sub_lines: 0,
+                sub_chars: 0,
+                payload: Payload::Leaf(Arc::new(leaf)),
+            })
+            .collect();
+        // Only the deepest level can be short of nodes; making its nodes
+        // red gives every path as many black ones
+        let red_depth = match count.checked_ilog2() {
+            Some(depth) if depth > 0 => depth,
+            _ => u32::MAX,
+        };
+        self.root = self.link_balanced(0, count, NIL, 0, red_depth);
+        self.revision += 1;
+        self.history.clear();
+        self.marks.clamp(self.len());
+    }
+
+    // Link the nodes `lo..hi` into a subtree under `parent` with the middle
+    // one at its top, returning that node
+    fn link_balanced(
+        &mut self,
+        lo: usize,
+        hi: usize,
+        parent: NodeId,
+        depth: u32,
+        red_depth: u32,
+    ) -> NodeId {
+        if lo >= hi {
+            return NIL;
         }
-        Ok(inserted_total)
+        let mid = lo + (hi - lo) / 2;
+        let id = mid as NodeId;
+        let left = self.link_balanced(lo, mid, id, depth + 1, red_depth);
+        let right = self.link_balanced(mid + 1, hi, id, depth + 1, red_depth);
+        let node = &mut self.nodes[mid];
+        node.left = left;
+        node.right = right;
+        node.parent = parent;
+        if depth == red_depth {
+            node.color = Color::Red;
+        }
+        self.recompute_node_aggregates(id);
+        id
     }
 
     pub fn read_bytes_global(&self, off: usize, out: &mut [u8]) -> Result<usize, RBError> {
@@ -402,152 +841,954 @@ impl Rope {
         Ok(written)
     }
 
-    pub fn find_first(&self, needle: &[u8]) -> Option<usize> {
-        if needle.is_empty() {
-            return Some(0);
+    /// Write the whole text to `out`, straight from the leaves in order,
+    /// without gathering it into one buffer first. Returns the number of
+    /// bytes written.
+    pub fn write_to<W: io::Write>(&self, mut out: W) -> io::Result<usize> {
+        let mut written = 0usize;
+        let mut cur = self.min_node(self.root);
+        while cur != NIL {
+            let Payload::Leaf(leaf) = &self.nodes[cur as usize].payload;
+            let (before, after) = leaf.halves();
+            out.write_all(before)?;
+            out.write_all(after)?;
+            written += before.len() + after.len();
+            cur = self.successor(cur);
         }
-        // FEAT:TODO: Missing optimized search across leaves
-        // Current implementation reads all leaves into memory - inefficient for large ropes
-        // Should implement: binary search using sub_bytes metadata for O(log n) performance
-        let mut all: Vec<u8> = Vec::new();
+        Ok(written)
+    }
+
+    // Each leaf in text order as the byte offset it starts at, its length,
+    // and the address of its text, the same for a leaf a clone or snapshot
+    // still shares
+    pub(crate) fn leaf_spans(&self) -> Vec<(usize, usize, usize)> {
+        let mut spans = Vec::new();
+        let mut start = 0usize;
         let mut cur = self.min_node(self.root);
         while cur != NIL {
-            let l = match &self.nodes[cur as usize].payload {
-                Payload::Leaf(l) => l,
-            };
-            let mut tmp = vec![0u8; l.byte_len()];
-            if l.read_into(0, &mut tmp).ok()? == tmp.len() {
-                all.extend_from_slice(&tmp);
-            }
+            let Payload::Leaf(leaf) = &self.nodes[cur as usize].payload;
+            spans.push((start, leaf.byte_len(), Arc::as_ptr(leaf) as usize));
+            start += leaf.byte_len();
             cur = self.successor(cur);
         }
-        if all.len() < needle.len() {
-            return None;
+        spans
+    }
+
+    /// Offset of the first match of `needle`, found reading the text chunk
+    /// by chunk. An empty needle matches at 0.
+    pub fn find_first(&self, needle: &[u8]) -> Option<usize> {
+        if needle.is_empty() {
+            return Some(0);
+        }
+        let mut first = None;
+        self.scan_matches(needle, 0, self.len(), |at| {
+            first = Some(at);
+            false
+        });
+        first
+    }
+
+    /// Start offsets of the matches of `needle` that start in `start..end`,
+    /// in order, overlapping ones included.
+    pub(crate) fn match_offsets(&self, needle: &[u8], start: usize, end: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.scan_matches(needle, start, end, |at| {
+            found.push(at);
+            true
+        });
+        found
+    }
+
+    // Call `found` with the start of each match of `needle` starting in
+    // `start..end`, in order, until it returns false. Chunks are borrowed
+    // from the leaves and searched for the first byte of the needle; the
+    // last `needle.len() - 1` bytes read are kept for matches that run on
+    // into the chunks after them.
+    fn scan_matches(
+        &self,
+        needle: &[u8],
+        start: usize,
+        end: usize,
+        mut found: impl FnMut(usize) -> bool,
+    ) {
+        if needle.is_empty() || start >= end {
+            return;
+        }
+        // A match starting before `end` may run on past it
+        let scan_end = end.saturating_add(needle.len() - 1).min(self.len());
+        let keep = needle.len() - 1;
+        // Tail of the text read so far that could still begin a match
+        let mut carry: Vec<u8> = Vec::with_capacity(keep);
+        let mut carry_start = start;
+        for (chunk_start, chunk) in self.slice(start, scan_end).chunks() {
+            // Matches starting in the carry: once one is too short to tell,
+            // so are the ones after it, and they stay in the carry
+            for (i, at) in (carry_start..carry_start + carry.len()).enumerate() {
+                if at >= end {
+                    return;
+                }
+                let (head, tail) = needle.split_at(carry.len() - i);
+                if tail.len() > chunk.len() {
+                    break;
+                }
+                if head == &carry[i..] && chunk.starts_with(tail) && !found(at) {
+                    return;
+                }
+            }
+            // Matches within the chunk, jumping between first bytes
+            let first = needle[0];
+            let mut from = 0;
+            while from + needle.len() <= chunk.len() {
+                let last = chunk.len() - needle.len();
+                let Some(hit) = chunk[from..=last].iter().position(|&b| b == first) else {
+                    break;
+                };
+                let at = from + hit;
+                if chunk_start + at >= end {
+                    return;
+                }
+                if chunk[at..].starts_with(needle) && !found(chunk_start + at) {
+                    return;
+                }
+                from = at + 1;
+            }
+            if chunk.len() >= keep {
+                carry.clear();
+                carry.extend_from_slice(&chunk[chunk.len() - keep..]);
+                carry_start = chunk_start + chunk.len() - keep;
+            } else {
+                carry.extend_from_slice(chunk);
+                let dropped = carry.len().saturating_sub(keep);
+                carry.drain(..dropped);
+                carry_start += dropped;
+            }
         }
-        let last = all.len() - needle.len();
-        let mut i = 0usize;
-        while i <= last {
-            if &all[i..i + needle.len()] == needle {
-                return Some(i);
+    }
+
+    // Leaf holding byte `off` and the offset its text starts at, found by
+    // descending on the subtree byte counts
+    fn leaf_at(&self, off: usize) -> (NodeId, usize) {
+        let mut node = self.root;
+        let mut base = 0usize;
+        while node != NIL {
+            let n = &self.nodes[node as usize];
+            let left_bytes = if n.left == NIL {
+                0
+            } else {
+                self.nodes[n.left as usize].sub_bytes as usize
+            };
+            let Payload::Leaf(l) = &n.payload;
+            if off < base + left_bytes {
+                node = n.left;
+            } else if off < base + left_bytes + l.byte_len() || n.right == NIL {
+                return (node, base + left_bytes);
+            } else {
+                base += left_bytes + l.byte_len();
+                node = n.right;
             }
-            i += 1;
         }
-        None
+        (NIL, base)
     }
 
     pub fn replace_first(&mut self, needle: &[u8], replacement: &[u8]) -> Result<usize, RBError> {
         if needle.is_empty() {
             return Ok(0);
         }
-        let Some(mut global_off) = self.find_first(needle) else {
+        let Some(offset) = self.find_first(needle) else {
             return Ok(0);
         };
-        let mut cur = self.min_node(self.root);
-        while cur != NIL {
-            let idx = cur as usize;
-            let replaced = match &mut self.nodes[idx].payload {
-                Payload::Leaf(l) => {
-                    let ll = l.byte_len();
-                    if global_off >= ll {
-                        global_off -= ll;
-                        false
-                    } else {
-                        // Check if replacement fits in current leaf
-                        let available = LEAF_CAPACITY - ll;
-                        if replacement.len() <= available {
-                            // Simple replacement within leaf capacity
-                            let del = l.delete(global_off, needle.len())?;
-                            if del != needle.len() {
-                                return Ok(0);
-                            }
-                            let ins = l.insert(global_off, replacement)?;
-                            if ins != replacement.len() {
-                                return Ok(0);
-                            }
-                            // Update subtree aggregates from this node upward
-                            self.update_ancestors(cur);
-                            true
-                        } else {
-                            // Need to restructure tree - split leaf
-                            self.restructure_leaf_for_replacement(
-                                cur,
-                                global_off,
-                                needle,
-                                replacement,
-                            )?
-                        }
-                    }
+        self.history.begin_transaction();
+        let replaced = self.replace_at(offset, needle, replacement);
+        self.history.end_transaction();
+        if replaced? {
+            Ok(replacement.len())
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Start offsets of the matches of `needle`, in order and not
+    /// overlapping: `aa` matches `aaa` at 0 only, as `replace_all` replaces
+    pub fn find_all(&self, needle: &[u8]) -> Vec<usize> {
+        let mut found = self.match_offsets(needle, 0, self.len());
+        let mut next_free = 0usize;
+        found.retain(|&m| {
+            let keep = m >= next_free;
+            if keep {
+                next_free = m + needle.len();
+            }
+            keep
+        });
+        found
+    }
+
+    /// Replace every match of `needle` found by `find_all`, returning how
+    /// many were replaced. Matches are replaced from the last one back, each
+    /// in place in its leaf where it fits, so the offsets of the ones still
+    /// to do stay put. All of them are undone as one step.
+    pub fn replace_all(&mut self, needle: &[u8], replacement: &[u8]) -> Result<usize, RBError> {
+        self.history.begin_transaction();
+        let mut replaced = Ok(0usize);
+        for offset in self.find_all(needle).into_iter().rev() {
+            match self.replace_at(offset, needle, replacement) {
+                Ok(true) => replaced = replaced.map(|count| count + 1),
+                Ok(false) => {}
+                Err(e) => {
+                    replaced = Err(e);
+                    break;
                 }
-            };
-            if replaced {
-                return Ok(replacement.len());
             }
-            cur = self.successor(cur);
         }
-        Ok(0)
+        self.history.end_transaction();
+        replaced
     }
 
+    // Replace the `needle` starting at `offset` with `replacement`,
+    // recording it in the history
+    fn replace_at(
+        &mut self,
+        offset: usize,
+        needle: &[u8],
+        replacement: &[u8],
+    ) -> Result<bool, RBError> {
+        if !self.replace_bytes_at(offset, needle, replacement)? {
+            return Ok(false);
+        }
+        self.history.record(Edit::Delete {
+            at: offset,
+            text: needle.to_vec(),
+        });
+        if !replacement.is_empty() {
+            self.history.record(Edit::Insert {
+                at: offset,
+                text: replacement.to_vec(),
+            });
+        }
+        Ok(true)
+    }
+
+    fn replace_bytes_at(
+        &mut self,
+        offset: usize,
+        needle: &[u8],
+        replacement: &[u8],
+    ) -> Result<bool, RBError> {
+        let (cur, leaf_start) = self.leaf_at(offset);
+        if cur == NIL {
+            return Ok(false);
+        }
+        let global_off = offset - leaf_start;
+        let l = self.nodes[cur as usize].payload.leaf_mut();
+        let ll = l.byte_len();
+        if global_off + needle.len() > ll {
+            // A match spanning leaves is deleted across them
+            self.delete_bytes(offset, offset + needle.len())?;
+            self.insert_bytes(offset, replacement)?;
+            return Ok(true);
+        }
+        // Check if replacement fits in current leaf
+        let available = LEAF_CAPACITY - ll;
+        if replacement.len() > available {
+            // Need to restructure tree - split leaf
+            return self.restructure_leaf_for_replacement(cur, global_off, needle, replacement);
+        }
+        // Simple replacement within leaf capacity
+        if l.delete(global_off, needle.len())? != needle.len() {
+            return Ok(false);
+        }
+        if l.insert(global_off, replacement)? != replacement.len() {
+            return Ok(false);
+        }
+        // Update subtree aggregates from this node upward
+        self.update_ancestors(cur);
+        self.replaced_marks(offset, needle.len(), replacement.len());
+        self.revision += 1;
+        Ok(true)
+    }
+
+    // Replace `needle` at `offset` in a leaf that had no room for
+    // `replacement`: once the needle is out, the leaf's text with the
+    // replacement is spread over it and as many new leaves as it takes
     fn restructure_leaf_for_replacement(
         &mut self,
-        _leaf_id: NodeId,
-        _offset: usize,
-        _needle: &[u8],
-        _replacement: &[u8],
+        leaf_id: NodeId,
+        offset: usize,
+        needle: &[u8],
+        replacement: &[u8],
     ) -> Result<bool, RBError> {
-        // FEAT:TODO: Missing tree restructuring for leaf overflow
-        // This method should:
-        // 1. Split the overflowing leaf into multiple leaves
-        // 2. Redistribute content to fit within capacity limits
-        // 3. Update tree structure (parent/child relationships)
-        // 4. Maintain Red-Black tree properties (colors, balance)
-        // 5. Update metadata (sub_bytes, sub_lines counts)
-        // 6. Handle cross-leaf content coordination
-        Err(RBError::InsufficientSpace) // Placeholder until proper restructuring is implemented
+        let l = self.nodes[leaf_id as usize].payload.leaf_mut();
+        if l.delete(offset, needle.len())? != needle.len() {
+            return Ok(false);
+        }
+        if replacement.len() <= l.gap_size() {
+            l.insert(offset, replacement)?;
+            self.update_ancestors(leaf_id);
+        } else {
+            self.split_leaf_with(leaf_id, offset, replacement)?;
+        }
+        let at = self.leaf_start(leaf_id) + offset;
+        self.replaced_marks(at, needle.len(), replacement.len());
+        self.revision += 1;
+        Ok(true)
     }
 
-    // Tree operations (BST + RB insert/rotations)
-    pub fn insert(&mut self, key: u64) -> Result<(), RBError> {
-        let _ = self.insert_with_id(key)?;
-        Ok(())
+    // Shift the marks for `old_len` bytes at `at` replaced by `new_len`
+    fn replaced_marks(&mut self, at: usize, old_len: usize, new_len: usize) {
+        self.marks.deleted(at, at + old_len);
+        self.marks.inserted(at, new_len);
     }
 
-    fn insert_with_id(&mut self, key: u64) -> Result<NodeId, RBError> {
-        let new_node = Node::new(key);
-        let new_id = self.nodes.len() as NodeId;
-        if new_id == NIL {
-            return Err(RBError::TreeFull);
+    // Offset the text of leaf `node` starts at
+    fn leaf_start(&self, node: NodeId) -> usize {
+        self.count_before(node, |n| n.sub_bytes, Leaf::byte_len)
+    }
+
+    // Char offset the text of leaf `node` starts at
+    fn leaf_char_start(&self, node: NodeId) -> usize {
+        self.count_before(node, |n| n.sub_chars, Leaf::char_len)
+    }
+
+    // What `sub` counts of a subtree and `own` of a leaf, summed over the
+    // leaves before leaf `node`
+    fn count_before(&self, node: NodeId, sub: fn(&Node) -> u64, own: fn(&Leaf) -> usize) -> usize {
+        let left_count = |n: NodeId| match self.nodes[n as usize].left {
+            NIL => 0,
+            left => sub(&self.nodes[left as usize]) as usize,
+        };
+        let mut start = left_count(node);
+        let mut cur = node;
+        let mut parent = self.nodes[cur as usize].parent;
+        while parent != NIL {
+            if self.nodes[parent as usize].right == cur {
+                let Payload::Leaf(l) = &self.nodes[parent as usize].payload;
+                start += left_count(parent) + own(l);
+            }
+            cur = parent;
+            parent = self.nodes[cur as usize].parent;
         }
-        self.nodes.push(new_node);
-        if self.root == NIL {
-            self.root = new_id;
-            self.nodes[new_id as usize].color = Color::Black;
-            return Ok(new_id);
+        start
+    }
+
+    /// Byte offset 0-based line `line` starts at: 0 for the first line,
+    /// and just past the `line`th newline for the others. The line after
+    /// the last newline exists even when empty.
+    pub fn line_to_byte(&self, line: usize) -> Result<usize, RBError> {
+        if line > self.total_lines() {
+            return Err(RBError::InvalidOffset);
         }
-        let mut cur = self.root;
-        let mut parent = NIL;
-        let mut is_left = false;
-        while cur != NIL {
-            parent = cur;
-            if key < self.nodes[cur as usize].key {
-                cur = self.nodes[cur as usize].left;
-                is_left = true;
+        if line == 0 {
+            return Ok(0);
+        }
+        // Descend to the leaf holding newline number `line` (1-based)
+        let mut node = self.root;
+        let (mut bytes, mut lines) = (0usize, 0usize);
+        while node != NIL {
+            let n = &self.nodes[node as usize];
+            let (left_bytes, left_lines) = if n.left == NIL {
+                (0, 0)
             } else {
-                cur = self.nodes[cur as usize].right;
-                is_left = false;
+                let left = &self.nodes[n.left as usize];
+                (left.sub_bytes as usize, left.sub_lines as usize)
+            };
+            let Payload::Leaf(l) = &n.payload;
+            if line <= lines + left_lines {
+                node = n.left;
+            } else if line <= lines + left_lines + l.nl_idx.len() {
+                let newline = l.nl_idx[line - lines - left_lines - 1] as usize;
+                return Ok(bytes + left_bytes + newline + 1);
+            } else {
+                bytes += left_bytes + l.byte_len();
+                lines += left_lines + l.nl_idx.len();
+                node = n.right;
             }
         }
-        self.nodes[new_id as usize].parent = parent;
-        if is_left {
-            self.nodes[parent as usize].left = new_id;
-        } else {
-            self.nodes[parent as usize].right = new_id;
+        Err(RBError::InvalidOffset)
+    }
+
+    /// 0-based line holding byte `offset`, that is the number of newlines
+    /// before it; `offset` may also be the length of the text
+    pub fn byte_to_line(&self, offset: usize) -> Result<usize, RBError> {
+        if offset > self.len() {
+            return Err(RBError::InvalidOffset);
         }
-        self.insert_fixup(new_id);
-        Ok(new_id)
+        if self.root == NIL {
+            return Ok(0);
+        }
+        let (leaf_id, leaf_start) = self.leaf_at(offset);
+        let Payload::Leaf(l) = &self.nodes[leaf_id as usize].payload;
+        let before = self.count_before(leaf_id, |n| n.sub_lines, |l| l.nl_idx.len());
+        Ok(before + l.partition_point_nl(offset - leaf_start))
     }
 
-    fn insert_fixup(&mut self, mut n: NodeId) {
-        while n != self.root
+    /// Chunks of the whole text in order, e.g. to write it out without
+    /// copying it into one buffer first
+    pub fn chunks(&self) -> Chunks<'_> {
+        self.slice(0, self.len()).chunks()
+    }
+
+    /// Lines of the text, the same as [`Rope::get_line`] gives: the line
+    /// after the last newline is included even when empty
+    pub fn lines(&self) -> Lines<'_> {
+        Lines {
+            rope: self,
+            start: 0,
+            end: self.len(),
+            empty_last: true,
+            done: false,
+        }
+    }
+
+    /// Line `line` (0-based) with its trailing newline, if it has one; None
+    /// past the last line. The line after the last newline exists even when
+    /// empty.
+    pub fn get_line(&self, line: usize) -> Option<RopeSlice<'_>> {
+        let (start, end) = self.line_bounds(line)?;
+        Some(self.slice(start, end))
+    }
+
+    /// Line `line` (0-based) without its trailing `\n`; a `\r` before it is
+    /// kept, as it is part of the text
+    pub fn get_line_content(&self, line: usize) -> Option<RopeSlice<'_>> {
+        let (start, end) = self.line_bounds(line)?;
+        let end = if line < self.total_lines() {
+            end - 1
+        } else {
+            end
+        };
+        Some(self.slice(start, end))
+    }
+
+    // Byte range of line `line`, its newline included
+    fn line_bounds(&self, line: usize) -> Option<(usize, usize)> {
+        let start = self.line_to_byte(line).ok()?;
+        let end = if line < self.total_lines() {
+            self.line_to_byte(line + 1).ok()?
+        } else {
+            self.len()
+        };
+        Some((start, end))
+    }
+
+    /// Number of chars in the text. Chars are counted by the bytes that
+    /// start one, so in text that isn't valid UTF-8 a stray continuation
+    /// byte belongs to the char before it.
+    pub fn char_len(&self) -> usize {
+        if self.root == NIL {
+            0
+        } else {
+            self.nodes[self.root as usize].sub_chars as usize
+        }
+    }
+
+    /// Index of the char holding byte `byte`; `byte` may also be the length
+    /// of the text, giving its char length
+    pub fn byte_to_char(&self, byte: usize) -> Result<usize, RBError> {
+        let len = self.len();
+        if byte > len {
+            return Err(RBError::InvalidOffset);
+        }
+        if byte == len {
+            return Ok(self.char_len());
+        }
+        let (leaf_id, leaf_start) = self.leaf_at(byte);
+        let Payload::Leaf(l) = &self.nodes[leaf_id as usize].payload;
+        let started = self.leaf_char_start(leaf_id) + l.chars_before(byte - leaf_start + 1);
+        Ok(started.saturating_sub(1))
+    }
+
+    /// Byte offset char `ch` starts at; `ch` may also be the char length
+    /// of the text, giving its byte length
+    pub fn char_to_byte(&self, ch: usize) -> Result<usize, RBError> {
+        let char_len = self.char_len();
+        if ch > char_len {
+            return Err(RBError::InvalidOffset);
+        }
+        if ch == char_len {
+            return Ok(self.len());
+        }
+        let (leaf_id, byte_start, char_start) = self.leaf_at_char(ch);
+        let Payload::Leaf(l) = &self.nodes[leaf_id as usize].payload;
+        Ok(byte_start + l.byte_of_char(ch - char_start))
+    }
+
+    // Leaf holding the start of char `ch` and the byte and char offsets its
+    // text starts at, found by descending on the subtree char counts
+    fn leaf_at_char(&self, ch: usize) -> (NodeId, usize, usize) {
+        let mut node = self.root;
+        let (mut bytes, mut chars) = (0usize, 0usize);
+        while node != NIL {
+            let n = &self.nodes[node as usize];
+            let (left_bytes, left_chars) = if n.left == NIL {
+                (0, 0)
+            } else {
+                let left = &self.nodes[n.left as usize];
+                (left.sub_bytes as usize, left.sub_chars as usize)
+            };
+            let Payload::Leaf(l) = &n.payload;
+            if ch < chars + left_chars {
+                node = n.left;
+            } else if ch < chars + left_chars + l.char_len() || n.right == NIL {
+                return (node, bytes + left_bytes, chars + left_chars);
+            } else {
+                bytes += left_bytes + l.byte_len();
+                chars += left_chars + l.char_len();
+                node = n.right;
+            }
+        }
+        (NIL, bytes, chars)
+    }
+
+    /// Insert `text` before char `ch`, returning the number of chars
+    /// inserted. Like [`Rope::insert_at`] with the offset counted in chars.
+    pub fn insert_char_at(&mut self, ch: usize, text: &str) -> Result<usize, RBError> {
+        let at = self.char_to_byte(ch)?;
+        self.insert_at(at, text.as_bytes())?;
+        Ok(text.chars().count())
+    }
+
+    /// Delete chars `start..end`, returning the number of chars deleted.
+    /// Like [`Rope::delete_range`] with the offsets counted in chars.
+    pub fn delete_char_range(&mut self, start: usize, end: usize) -> Result<usize, RBError> {
+        if start > end {
+            return Err(RBError::InvalidOffset);
+        }
+        let (from, to) = (self.char_to_byte(start)?, self.char_to_byte(end)?);
+        self.delete_range(from, to)?;
+        Ok(end - start)
+    }
+
+    /// Whether byte `offset` is on a char boundary: the start or end of the
+    /// text, or a byte that starts a char
+    pub fn is_char_boundary(&self, offset: usize) -> bool {
+        offset <= self.len() && self.floor_char_boundary(offset) == offset
+    }
+
+    /// The closest char boundary at or before byte `offset`; offsets past
+    /// the end give the length of the text
+    pub fn floor_char_boundary(&self, offset: usize) -> usize {
+        match self.byte_to_char(offset) {
+            Ok(ch) => self.char_to_byte(ch).unwrap_or(offset),
+            Err(_) => self.len(),
+        }
+    }
+
+    /// The closest char boundary at or after byte `offset`; offsets past
+    /// the end give the length of the text
+    pub fn ceil_char_boundary(&self, offset: usize) -> usize {
+        match self.byte_to_char(offset) {
+            Ok(ch) if self.char_to_byte(ch).is_ok_and(|byte| byte == offset) => offset,
+            Ok(ch) => self.char_to_byte(ch + 1).unwrap_or(offset),
+            Err(_) => self.len(),
+        }
+    }
+
+    /// Insert `text` at byte `offset`, returning the number of bytes
+    /// inserted. Unlike [`Rope::insert_at`], an offset inside a char is
+    /// refused with [`RBError::NotCharBoundary`], so text that is valid
+    /// UTF-8 stays so.
+    pub fn insert_str_at(&mut self, offset: usize, text: &str) -> Result<usize, RBError> {
+        self.check_char_boundary(offset)?;
+        self.insert_at(offset, text.as_bytes())
+    }
+
+    /// Delete bytes `start..end`, returning the number of bytes deleted.
+    /// Unlike [`Rope::delete_range`], offsets inside a char are refused with
+    /// [`RBError::NotCharBoundary`], so text that is valid UTF-8 stays so.
+    pub fn delete_str_range(&mut self, start: usize, end: usize) -> Result<usize, RBError> {
+        self.check_char_boundary(start)?;
+        self.check_char_boundary(end)?;
+        self.delete_range(start, end)
+    }
+
+    // Fail for offsets past the end of the text or inside a char
+    fn check_char_boundary(&self, offset: usize) -> Result<(), RBError> {
+        if offset > self.len() {
+            Err(RBError::InvalidOffset)
+        } else if !self.is_char_boundary(offset) {
+            Err(RBError::NotCharBoundary)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Whether the text is valid UTF-8, checked chunk by chunk without a copy
+    pub fn is_utf8(&self) -> bool {
+        // A char cut off at the end of one chunk, finished by the next
+        let mut partial = [0u8; 4];
+        let mut partial_len = 0;
+        for (_, mut chunk) in self.chunks() {
+            while partial_len > 0 && !chunk.is_empty() {
+                partial[partial_len] = chunk[0];
+                partial_len += 1;
+                chunk = &chunk[1..];
+                match std::str::from_utf8(&partial[..partial_len]) {
+                    Ok(_) => partial_len = 0,
+                    Err(e) if e.error_len().is_some() => return false,
+                    Err(_) => {}
+                }
+            }
+            match std::str::from_utf8(chunk) {
+                Ok(_) => {}
+                Err(e) if e.error_len().is_some() => return false,
+                Err(e) => {
+                    let rest = &chunk[e.valid_up_to()..];
+                    partial[..rest.len()].copy_from_slice(rest);
+                    partial_len = rest.len();
+                }
+            }
+        }
+        partial_len == 0
+    }
+
+    /// Insert `data` at byte `offset`, returning the number of bytes
+    /// inserted. Text that doesn't fit in the gap of the leaf holding
+    /// `offset` splits that leaf into as many leaves as needed.
+    pub fn insert_at(&mut self, offset: usize, data: &[u8]) -> Result<usize, RBError> {
+        let inserted = self.insert_bytes(offset, data)?;
+        if inserted > 0 {
+            self.history.record(Edit::Insert {
+                at: offset,
+                text: data.to_vec(),
+            });
+        }
+        Ok(inserted)
+    }
+
+    // `insert_at` without recording the edit in the history
+    fn insert_bytes(&mut self, offset: usize, data: &[u8]) -> Result<usize, RBError> {
+        let len = self.len();
+        if offset > len {
+            return Err(RBError::InvalidOffset);
+        }
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let (mut leaf_id, leaf_start) = self.leaf_at(offset);
+        if leaf_id == NIL {
+            leaf_id = self.insert_with_id(0)?;
+        }
+        let local = offset - leaf_start;
+        let l = self.nodes[leaf_id as usize].payload.leaf_mut();
+        if data.len() <= l.gap_size() {
+            l.insert(local, data)?;
+            self.update_ancestors(leaf_id);
+        } else {
+            self.split_leaf_with(leaf_id, local, data)?;
+        }
+        self.marks.inserted(offset, data.len());
+        self.revision += 1;
+        Ok(data.len())
+    }
+
+    // Refill a leaf with its text with `data` inserted at `local`, continuing
+    // into new leaves after it once it holds LEAF_USABLE bytes
+    fn split_leaf_with(
+        &mut self,
+        leaf_id: NodeId,
+        local: usize,
+        data: &[u8],
+    ) -> Result<(), RBError> {
+        let l = self.nodes[leaf_id as usize].payload.leaf_mut();
+        let mut text = vec![0u8; l.byte_len()];
+        l.read_into(0, &mut text)?;
+        text.splice(local..local, data.iter().copied());
+        *l = Leaf::new();
+
+        let mut chunks = leaf_chunks(&text);
+        let mut cur = leaf_id;
+        if let Some(first) = chunks.next() {
+            let l = self.nodes[cur as usize].payload.leaf_mut();
+            l.insert(0, first)?;
+            self.update_ancestors(cur);
+        }
+        for chunk in chunks {
+            cur = self.insert_leaf_after(cur)?;
+            let l = self.nodes[cur as usize].payload.leaf_mut();
+            l.insert(0, chunk)?;
+            self.update_ancestors(cur);
+        }
+        Ok(())
+    }
+
+    // Add an empty leaf right after `node` in text order, with a key between
+    // the keys of `node` and its successor
+    fn insert_leaf_after(&mut self, node: NodeId) -> Result<NodeId, RBError> {
+        if self.key_gap_after(node) < 2 {
+            self.renumber_keys();
+        }
+        let gap = self.key_gap_after(node);
+        if gap < 2 {
+            return Err(RBError::TreeFull);
+        }
+        self.insert_with_id(self.nodes[node as usize].key + gap / 2)
+    }
+
+    // Room between the key of `node` and the next key in text order
+    fn key_gap_after(&self, node: NodeId) -> u64 {
+        let key = self.nodes[node as usize].key;
+        match self.successor(node) {
+            NIL => key.saturating_add(KEY_STRIDE) - key,
+            next => self.nodes[next as usize].key - key,
+        }
+    }
+
+    // Space the keys KEY_STRIDE apart again, in text order
+    fn renumber_keys(&mut self) {
+        let mut cur = self.min_node(self.root);
+        let mut key = 0u64;
+        while cur != NIL {
+            self.nodes[cur as usize].key = key;
+            key = key.saturating_add(KEY_STRIDE);
+            cur = self.successor(cur);
+        }
+    }
+
+    /// Delete bytes `start..end`, returning the number of bytes deleted.
+    /// Leaves left empty are taken out of the tree, and the leaves around the
+    /// deletion are merged when their text fits in one.
+    pub fn delete_range(&mut self, start: usize, end: usize) -> Result<usize, RBError> {
+        let text: Vec<u8> = self
+            .slice(start, end)
+            .chunks()
+            .flat_map(|(_, chunk)| chunk.iter().copied())
+            .collect();
+        let deleted = self.delete_bytes(start, end)?;
+        if deleted > 0 {
+            self.history.record(Edit::Delete { at: start, text });
+        }
+        Ok(deleted)
+    }
+
+    // `delete_range` without recording the edit in the history
+    fn delete_bytes(&mut self, start: usize, end: usize) -> Result<usize, RBError> {
+        let len = self.len();
+        if start > end || end > len {
+            return Err(RBError::InvalidOffset);
+        }
+        let mut remaining = end - start;
+        if remaining == 0 {
+            return Ok(0);
+        }
+        while remaining > 0 {
+            let (leaf_id, leaf_start) = self.leaf_at(start);
+            let l = self.nodes[leaf_id as usize].payload.leaf_mut();
+            let deleted = l.delete(start - leaf_start, remaining)?;
+            let emptied = l.byte_len() == 0;
+            self.update_ancestors(leaf_id);
+            if emptied {
+                self.remove_node(leaf_id);
+            }
+            if deleted == 0 {
+                break;
+            }
+            remaining -= deleted;
+        }
+        self.merge_around(start);
+        self.marks.deleted(start, end);
+        self.revision += 1;
+        Ok(end - start)
+    }
+
+    /// Make the edits until the matching [`Rope::end_transaction`] one step
+    /// to undo and redo. Transactions nest, the outermost making the step.
+    pub fn begin_transaction(&mut self) {
+        self.history.begin_transaction();
+    }
+
+    pub fn end_transaction(&mut self) {
+        self.history.end_transaction();
+    }
+
+    /// Undo the last step of edits, returning the offset of its first edit
+    /// to put the cursor at, or None when there is nothing to undo
+    pub fn undo(&mut self) -> Result<Option<usize>, RBError> {
+        let Some(step) = self.history.undo_step() else {
+            return Ok(None);
+        };
+        let step = step.to_vec();
+        for edit in step.iter().rev() {
+            match edit {
+                Edit::Insert { at, text } => self.delete_bytes(*at, at + text.len())?,
+                Edit::Delete { at, text } => self.insert_bytes(*at, text)?,
+            };
+        }
+        Ok(step.first().map(Edit::at))
+    }
+
+    /// Redo the last step undone, returning the offset of its first edit,
+    /// or None when there is nothing to redo. Any edit since the undo drops
+    /// the steps there were to redo.
+    pub fn redo(&mut self) -> Result<Option<usize>, RBError> {
+        let Some(step) = self.history.redo_step() else {
+            return Ok(None);
+        };
+        let step = step.to_vec();
+        for edit in &step {
+            match edit {
+                Edit::Insert { at, text } => self.insert_bytes(*at, text)?,
+                Edit::Delete { at, text } => self.delete_bytes(*at, at + text.len())?,
+            };
+        }
+        Ok(step.first().map(Edit::at))
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history.can_undo()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history.can_redo()
+    }
+
+    /// Register a mark at byte `offset`, which the edits from now on keep
+    /// on the same spot of the text: see [`Bias`] for text inserted right
+    /// at it. A deletion around the mark moves it to where the text was.
+    pub fn add_mark(&mut self, offset: usize, bias: Bias) -> Result<MarkId, RBError> {
+        if offset > self.len() {
+            return Err(RBError::InvalidOffset);
+        }
+        Ok(self.marks.add(offset, bias))
+    }
+
+    /// Byte offset of a mark, or None once it is removed
+    pub fn mark(&self, id: MarkId) -> Option<usize> {
+        self.marks.get(id)
+    }
+
+    /// Move a mark to byte `offset`
+    pub fn set_mark(&mut self, id: MarkId, offset: usize) -> Result<(), RBError> {
+        if offset > self.len() || !self.marks.set(id, offset) {
+            return Err(RBError::InvalidOffset);
+        }
+        Ok(())
+    }
+
+    /// Stop tracking a mark, returning where it was
+    pub fn remove_mark(&mut self, id: MarkId) -> Option<usize> {
+        self.marks.remove(id)
+    }
+
+    /// Every mark with its byte offset, in the order they were added
+    pub fn marks(&self) -> impl Iterator<Item = (MarkId, usize)> + '_ {
+        self.marks.iter()
+    }
+
+    // Merge the leaf holding the byte before `offset` with its neighbours
+    // when their text fits in one leaf
+    fn merge_around(&mut self, offset: usize) {
+        if self.root == NIL {
+            return;
+        }
+        // Node ids move when a node is freed, so look the leaf up again
+        let at = offset.saturating_sub(1);
+        let prev = self.predecessor(self.leaf_at(at).0);
+        if prev != NIL {
+            self.merge_with_next(prev);
+        }
+        self.merge_with_next(self.leaf_at(at).0);
+    }
+
+    /// Merge each leaf with the ones after it for as long as their text fits
+    /// in one leaf, undoing the fragmentation edits leave behind. Returns the
+    /// number of leaves merged away. The text is unchanged.
+    pub fn merge_leaves(&mut self) -> usize {
+        let mut merged = 0;
+        let mut cur = self.min_node(self.root);
+        while cur != NIL {
+            let next = self.successor(cur);
+            let last = (self.nodes.len() - 1) as NodeId;
+            if !self.merge_with_next(cur) {
+                cur = next;
+                continue;
+            }
+            merged += 1;
+            // The last node moves into the slot of the one removed
+            if cur == last {
+                cur = next;
+            }
+        }
+        merged
+    }
+
+    /// Tidy the rope up after many edits, as an idle hook may: merge
+    /// underfilled leaves, space the keys evenly again and give back the
+    /// memory of the nodes freed. Returns the number of leaves merged away.
+    pub fn optimize(&mut self) -> usize {
+        let merged = self.merge_leaves();
+        self.renumber_keys();
+        self.nodes.shrink_to_fit();
+        merged
+    }
+
+    // Move the text of the leaf after `node` into it if it fits within
+    // LEAF_USABLE, removing that leaf
+    fn merge_with_next(&mut self, node: NodeId) -> bool {
+        let next = self.successor(node);
+        if next == NIL {
+            return false;
+        }
+        let Payload::Leaf(next_leaf) = &self.nodes[next as usize].payload;
+        let Payload::Leaf(leaf) = &self.nodes[node as usize].payload;
+        if leaf.byte_len() + next_leaf.byte_len() > LEAF_USABLE {
+            return false;
+        }
+        let mut text = vec![0u8; next_leaf.byte_len()];
+        if next_leaf.read_into(0, &mut text).is_err() {
+            return false;
+        }
+        let at = leaf.byte_len();
+        let leaf = self.nodes[node as usize].payload.leaf_mut();
+        if leaf.insert(at, &text).is_err() {
+            return false;
+        }
+        self.update_ancestors(node);
+        self.remove_node(next);
+        true
+    }
+
+    // Tree operations (BST + RB insert/rotations)
+    pub fn insert(&mut self, key: u64) -> Result<(), RBError> {
+        let _ = self.insert_with_id(key)?;
+        Ok(())
+    }
+
+    fn insert_with_id(&mut self, key: u64) -> Result<NodeId, RBError> {
+        let new_node = Node::new(key);
+        let new_id = self.nodes.len() as NodeId;
+        if new_id == NIL {
+            return Err(RBError::TreeFull);
+        }
+        self.nodes.push(new_node);
+        if self.root == NIL {
+            self.root = new_id;
+            self.nodes[new_id as usize].color = Color::Black;
+            return Ok(new_id);
+        }
+        let mut cur = self.root;
+        let mut parent = NIL;
+        let mut is_left = false;
+        while cur != NIL {
+            parent = cur;
+            if key < self.nodes[cur as usize].key {
+                cur = self.nodes[cur as usize].left;
+                is_left = true;
+            } else {
+                cur = self.nodes[cur as usize].right;
+                is_left = false;
+            }
+        }
+        self.nodes[new_id as usize].parent = parent;
+        if is_left {
+            self.nodes[parent as usize].left = new_id;
+        } else {
+            self.nodes[parent as usize].right = new_id;
+        }
+        self.insert_fixup(new_id);
+        Ok(new_id)
+    }
+
+    fn insert_fixup(&mut self, mut n: NodeId) {
+        while n != self.root
             && self.nodes[self.nodes[n as usize].parent as usize].color == Color::Red
         {
             let p = self.nodes[n as usize].parent;
@@ -643,6 +1884,159 @@ impl Rope {
         self.update_ancestors(self.nodes[x as usize].parent);
     }
 
+    // Take node `z` out of the tree (RB delete with fixup) and free its slot
+    fn remove_node(&mut self, z: NodeId) {
+        let z_left = self.nodes[z as usize].left;
+        let z_right = self.nodes[z as usize].right;
+        let mut removed_color = self.nodes[z as usize].color;
+        let x;
+        let x_parent;
+        if z_left == NIL {
+            x = z_right;
+            x_parent = self.nodes[z as usize].parent;
+            self.transplant(z, z_right);
+        } else if z_right == NIL {
+            x = z_left;
+            x_parent = self.nodes[z as usize].parent;
+            self.transplant(z, z_left);
+        } else {
+            // z's successor takes its place
+            let y = self.min_node(z_right);
+            removed_color = self.nodes[y as usize].color;
+            x = self.nodes[y as usize].right;
+            if self.nodes[y as usize].parent == z {
+                x_parent = y;
+            } else {
+                x_parent = self.nodes[y as usize].parent;
+                self.transplant(y, x);
+                self.nodes[y as usize].right = z_right;
+                self.nodes[z_right as usize].parent = y;
+            }
+            self.transplant(z, y);
+            self.nodes[y as usize].left = z_left;
+            self.nodes[z_left as usize].parent = y;
+            self.nodes[y as usize].color = self.nodes[z as usize].color;
+        }
+        self.update_ancestors(x_parent);
+        if removed_color == Color::Black {
+            self.delete_fixup(x, x_parent);
+        }
+        self.free_node(z);
+    }
+
+    // Put subtree `v` where subtree `u` hangs
+    fn transplant(&mut self, u: NodeId, v: NodeId) {
+        let u_parent = self.nodes[u as usize].parent;
+        if u_parent == NIL {
+            self.root = v;
+        } else if self.nodes[u_parent as usize].left == u {
+            self.nodes[u_parent as usize].left = v;
+        } else {
+            self.nodes[u_parent as usize].right = v;
+        }
+        if v != NIL {
+            self.nodes[v as usize].parent = u_parent;
+        }
+    }
+
+    #[inline]
+    fn is_black(&self, n: NodeId) -> bool {
+        n == NIL || self.nodes[n as usize].color == Color::Black
+    }
+
+    // Restore the red-black properties after removing a black node; `x` took
+    // its place below `parent` and may be NIL
+    fn delete_fixup(&mut self, mut x: NodeId, mut parent: NodeId) {
+        while x != self.root && self.is_black(x) {
+            if x == self.nodes[parent as usize].left {
+                let mut w = self.nodes[parent as usize].right;
+                if !self.is_black(w) {
+                    self.nodes[w as usize].color = Color::Black;
+                    self.nodes[parent as usize].color = Color::Red;
+                    self.left_rotate(parent);
+                    w = self.nodes[parent as usize].right;
+                }
+                let (w_left, w_right) = (self.nodes[w as usize].left, self.nodes[w as usize].right);
+                if self.is_black(w_left) && self.is_black(w_right) {
+                    self.nodes[w as usize].color = Color::Red;
+                    x = parent;
+                    parent = self.nodes[x as usize].parent;
+                } else {
+                    if self.is_black(w_right) {
+                        self.nodes[w_left as usize].color = Color::Black;
+                        self.nodes[w as usize].color = Color::Red;
+                        self.right_rotate(w);
+                        w = self.nodes[parent as usize].right;
+                    }
+                    self.nodes[w as usize].color = self.nodes[parent as usize].color;
+                    self.nodes[parent as usize].color = Color::Black;
+                    let w_right = self.nodes[w as usize].right;
+                    if w_right != NIL {
+                        self.nodes[w_right as usize].color = Color::Black;
+                    }
+                    self.left_rotate(parent);
+                    x = self.root;
+                }
+            } else {
+                let mut w = self.nodes[parent as usize].left;
+                if !self.is_black(w) {
+                    self.nodes[w as usize].color = Color::Black;
+                    self.nodes[parent as usize].color = Color::Red;
+                    self.right_rotate(parent);
+                    w = self.nodes[parent as usize].left;
+                }
+                let (w_left, w_right) = (self.nodes[w as usize].left, self.nodes[w as usize].right);
+                if self.is_black(w_left) && self.is_black(w_right) {
+                    self.nodes[w as usize].color = Color::Red;
+                    x = parent;
+                    parent = self.nodes[x as usize].parent;
+                } else {
+                    if self.is_black(w_left) {
+                        self.nodes[w_right as usize].color = Color::Black;
+                        self.nodes[w as usize].color = Color::Red;
+                        self.left_rotate(w);
+                        w = self.nodes[parent as usize].left;
+                    }
+                    self.nodes[w as usize].color = self.nodes[parent as usize].color;
+                    self.nodes[parent as usize].color = Color::Black;
+                    let w_left = self.nodes[w as usize].left;
+                    if w_left != NIL {
+                        self.nodes[w_left as usize].color = Color::Black;
+                    }
+                    self.right_rotate(parent);
+                    x = self.root;
+                }
+            }
+        }
+        if x != NIL {
+            self.nodes[x as usize].color = Color::Black;
+        }
+    }
+
+    // Drop the slot of a node already out of the tree, moving the last node
+    // into it so `nodes` stays dense
+    fn free_node(&mut self, id: NodeId) {
+        let last = (self.nodes.len() - 1) as NodeId;
+        self.nodes.swap_remove(id as usize);
+        if id == last {
+            return;
+        }
+        let moved = &self.nodes[id as usize];
+        let (parent, left, right) = (moved.parent, moved.left, moved.right);
+        if parent == NIL {
+            self.root = id;
+        } else if self.nodes[parent as usize].left == last {
+            self.nodes[parent as usize].left = id;
+        } else {
+            self.nodes[parent as usize].right = id;
+        }
+        for child in [left, right] {
+            if child != NIL {
+                self.nodes[child as usize].parent = id;
+            }
+        }
+    }
+
     fn min_node(&self, mut n: NodeId) -> NodeId {
         if n == NIL {
             return NIL;
@@ -653,6 +2047,26 @@ impl Rope {
         n
     }
 
+    fn predecessor(&self, mut n: NodeId) -> NodeId {
+        if n == NIL {
+            return NIL;
+        }
+        let l = self.nodes[n as usize].left;
+        if l != NIL {
+            let mut m = l;
+            while self.nodes[m as usize].right != NIL {
+                m = self.nodes[m as usize].right;
+            }
+            return m;
+        }
+        let mut p = self.nodes[n as usize].parent;
+        while p != NIL && n == self.nodes[p as usize].left {
+            n = p;
+            p = self.nodes[p as usize].parent;
+        }
+        p
+    }
+
     fn successor(&self, mut n: NodeId) -> NodeId {
         if n == NIL {
             return NIL;
@@ -669,146 +2083,816 @@ impl Rope {
         p
     }
 
-    // Debug visualization (tests only)
-    #[cfg(test)]
-    pub fn visualize(&self) {
-        if self.root == NIL {
-            println!("<empty rope>");
-            return;
+    /// Check the tree invariants: red-black properties, parent links, key
+    /// order, per-leaf newline indices and char counts, and the subtree
+    /// byte/line/char aggregates.
+    /// Returns a description of the first violation found.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.root == NIL {
+            return Ok(());
+        }
+        let root = &self.nodes[self.root as usize];
+        if root.parent != NIL {
+            return Err(format!("root {} has parent {}", self.root, root.parent));
+        }
+        if root.color != Color::Black {
+            return Err(format!("root {} is red", self.root));
+        }
+        self.validate_node(self.root, None, None).map(|_| ())
+    }
+
+    // Validate a subtree whose keys must lie within [lo, hi]; returns its black height
+    fn validate_node(&self, id: NodeId, lo: Option<u64>, hi: Option<u64>) -> Result<u32, String> {
+        if id == NIL {
+            return Ok(1);
+        }
+        let node = self
+            .nodes
+            .get(id as usize)
+            .ok_or_else(|| format!("node {} out of bounds", id))?;
+        if lo.is_some_and(|lo| node.key < lo) || hi.is_some_and(|hi| node.key > hi) {
+            return Err(format!("node {} key {} out of order", id, node.key));
+        }
+        for child in [node.left, node.right] {
+            if child == NIL {
+                continue;
+            }
+            let child_node = self
+                .nodes
+                .get(child as usize)
+                .ok_or_else(|| format!("child {} of node {} out of bounds", child, id))?;
+            if child_node.parent != id {
+                return Err(format!(
+                    "child {} of node {} has parent {}",
+                    child, id, child_node.parent
+                ));
+            }
+            if node.color == Color::Red && child_node.color == Color::Red {
+                return Err(format!("red node {} has red child {}", id, child));
+            }
+        }
+
+        let Payload::Leaf(leaf) = &node.payload;
+        let mut bytes = vec![0u8; leaf.byte_len()];
+        leaf.read_into(0, &mut bytes)
+            .map_err(|e| format!("node {}: {}", id, e))?;
+        let newlines: Vec<u16> = (0..bytes.len())
+            .filter(|&i| bytes[i] == b'\n')
+            .map(|i| i as u16)
+            .collect();
+        if leaf.nl_idx != newlines {
+            return Err(format!("node {} newline index out of date", id));
+        }
+        if leaf.char_len() != count_chars(&bytes) {
+            return Err(format!("node {} char count out of date", id));
+        }
+
+        let left_height = self.validate_node(node.left, lo, Some(node.key))?;
+        let right_height = self.validate_node(node.right, Some(node.key), hi)?;
+        if left_height != right_height {
+            return Err(format!(
+                "node {} black heights differ ({} left, {} right)",
+                id, left_height, right_height
+            ));
+        }
+
+        let child_sum = |f: fn(&Node) -> u64| {
+            [node.left, node.right]
+                .iter()
+                .filter(|&&child| child != NIL)
+                .map(|&child| f(&self.nodes[child as usize]))
+                .sum::<u64>()
+        };
+        let sub_bytes = child_sum(|n| n.sub_bytes) + leaf.byte_len() as u64;
+        let sub_lines = child_sum(|n| n.sub_lines) + leaf.nl_idx.len() as u64;
+        let sub_chars = child_sum(|n| n.sub_chars) + leaf.char_len() as u64;
+        if node.sub_bytes != sub_bytes || node.sub_lines != sub_lines || node.sub_chars != sub_chars
+        {
+            return Err(format!(
+                "node {} aggregates ({} bytes, {} lines, {} chars) should be ({}, {}, {})",
+                id, node.sub_bytes, node.sub_lines, node.sub_chars, sub_bytes, sub_lines, sub_chars
+            ));
+        }
+        Ok(left_height + u32::from(node.color == Color::Black))
+    }
+
+    // Debug visualization (tests only)
+    #[cfg(test)]
+    pub fn visualize(&self) {
+        if self.root == NIL {
+            println!("<empty rope>");
+            return;
+        }
+        self.visualize_node(self.root, 0);
+    }
+
+    #[cfg(test)]
+    fn visualize_node(&self, node_id: NodeId, depth: usize) {
+        if node_id == NIL {
+            return;
+        }
+        let node = &self.nodes[node_id as usize];
+        let indent = "  ".repeat(depth);
+        let color = match node.color {
+            Color::Red => 'R',
+            Color::Black => 'B',
+        };
+        match &node.payload {
+            Payload::Leaf(l) => println!(
+                "{}[{}] key={} Leaf(bytes={}, lines={})",
+                indent,
+                color,
+                node.key,
+                l.byte_len(),
+                l.nl_idx.len()
+            ),
+        }
+        if node.left != NIL {
+            self.visualize_node(node.left, depth + 1);
+        }
+        if node.right != NIL {
+            self.visualize_node(node.right, depth + 1);
+        }
+    }
+
+    // FEAT:TODO: Missing advanced rope operations
+    // 1. split_leaf_at(offset) - Split leaf at specific position
+    // 2. get_line_info(offset) - Get line number and column for offset
+    // 3. copy_range(start, end) - Copy text range to new rope
+}
+
+impl Rope {
+    /// Hash of the text made of a hash of each leaf, which is kept until
+    /// the leaf is edited, so hashing again after an edit costs about as
+    /// much as the leaves edited. It tells whether the text changed since
+    /// it was last hashed; two ropes with the same text split into leaves
+    /// differently may hash differently, so compare those with `==`.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let mut cur = self.min_node(self.root);
+        while cur != NIL {
+            let Payload::Leaf(leaf) = &self.nodes[cur as usize].payload;
+            if leaf.byte_len() > 0 {
+                hasher.write_u64(leaf.content_hash());
+            }
+            cur = self.successor(cur);
+        }
+        hasher.write_usize(self.len());
+        hasher.finish()
+    }
+
+    // Whether the text is `other`, compared chunk by chunk
+    fn text_eq(&self, other: &[u8]) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        self.chunks()
+            .all(|(at, chunk)| chunk == &other[at..at + chunk.len()])
+    }
+}
+
+/// Ropes are equal when their texts are, however they are split into leaves
+impl PartialEq for Rope {
+    fn eq(&self, other: &Rope) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        let mut theirs = other.chunks().map(|(_, chunk)| chunk);
+        let mut rest: &[u8] = &[];
+        for (_, mut chunk) in self.chunks() {
+            while !chunk.is_empty() {
+                if rest.is_empty() {
+                    match theirs.next() {
+                        Some(next) => rest = next,
+                        None => return false,
+                    }
+                }
+                let n = chunk.len().min(rest.len());
+                if chunk[..n] != rest[..n] {
+                    return false;
+                }
+                chunk = &chunk[n..];
+                rest = &rest[n..];
+            }
+        }
+        true
+    }
+}
+
+impl Eq for Rope {}
+
+impl PartialEq<str> for Rope {
+    fn eq(&self, other: &str) -> bool {
+        self.text_eq(other.as_bytes())
+    }
+}
+
+impl PartialEq<&str> for Rope {
+    fn eq(&self, other: &&str) -> bool {
+        self.text_eq(other.as_bytes())
+    }
+}
+
+impl PartialEq<Rope> for str {
+    fn eq(&self, other: &Rope) -> bool {
+        other.text_eq(self.as_bytes())
+    }
+}
+
+impl From<&str> for Rope {
+    fn from(text: &str) -> Self {
+        let mut builder = RopeBuilder::new();
+        builder.append(text.as_bytes());
+        builder.finish()
+    }
+}
+
+/// A rope of the pieces one after another, as from a [`RopeBuilder`]
+impl<'a> FromIterator<&'a str> for Rope {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(pieces: I) -> Self {
+        let mut builder = RopeBuilder::new();
+        for piece in pieces {
+            builder.append(piece.as_bytes());
         }
-        self.visualize_node(self.root, 0);
+        builder.finish()
     }
+}
 
-    #[cfg(test)]
-    fn visualize_node(&self, node_id: NodeId, depth: usize) {
-        if node_id == NIL {
-            return;
-        }
-        let node = &self.nodes[node_id as usize];
-        let indent = "  ".repeat(depth);
-        let color = match node.color {
-            Color::Red => 'R',
-            Color::Black => 'B',
-        };
-        match &node.payload {
-            Payload::Leaf(l) => println!(
-                "{}[{}] key={} Leaf(bytes={}, lines={})",
-                indent,
-                color,
-                node.key,
-                l.byte_len(),
-                l.nl_idx.len()
-            ),
-        }
-        if node.left != NIL {
-            self.visualize_node(node.left, depth + 1);
+/// The text, written chunk by chunk. Bytes that aren't UTF-8 are written as
+/// U+FFFD, as `String::from_utf8_lossy` does.
+impl fmt::Display for Rope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The first bytes of a char cut between two chunks, waiting for the
+        // rest
+        let mut pending: Vec<u8> = Vec::with_capacity(4);
+        for (_, mut chunk) in self.chunks() {
+            if !pending.is_empty() {
+                while whole_chars_len(&pending) < pending.len()
+                    && chunk.first().is_some_and(|&byte| !is_char_start(byte))
+                {
+                    pending.push(chunk[0]);
+                    chunk = &chunk[1..];
+                }
+                if chunk.is_empty() && whole_chars_len(&pending) < pending.len() {
+                    continue;
+                }
+                write_lossy(f, &pending)?;
+                pending.clear();
+            }
+            let whole = whole_chars_len(chunk);
+            write_lossy(f, &chunk[..whole])?;
+            pending.extend_from_slice(&chunk[whole..]);
         }
-        if node.right != NIL {
-            self.visualize_node(node.right, depth + 1);
+        write_lossy(f, &pending)
+    }
+}
+
+fn write_lossy(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    for piece in bytes.utf8_chunks() {
+        f.write_str(piece.valid())?;
+        if !piece.invalid().is_empty() {
+            f.write_str("\u{FFFD}")?;
         }
     }
+    Ok(())
+}
 
-    // FEAT:TODO: Missing advanced rope operations
-    // 1. delete_range(start, end) - Remove text range with tree rebalancing
-    // 2. insert_at(offset, text) - Insert text at specific offset
-    // 3. undo() / redo() - History management for text operations
-    // 4. optimize() - Rebalance tree for better performance
-    // 5. merge_leaves() - Combine underutilized leaves
-    // 6. split_leaf_at(offset) - Split leaf at specific position
-    // 7. get_line_info(offset) - Get line number and column for offset
-    // 8. find_all(needle) - Find all occurrences of text
-    // 9. replace_all(needle, replacement) - Replace all occurrences
-    // 10. copy_range(start, end) - Copy text range to new rope
+impl<'a> RopeSlice<'a> {
+    /// Chunks of the text left in the slice, with their offsets in the rope
+    pub fn chunks(self) -> Chunks<'a> {
+        Chunks { slice: self }
+    }
+
+    /// Lines of the text left in the slice, the last one only when it
+    /// isn't empty. Iterating a viewport is
+    /// `rope.slice(rope.line_to_byte(top)?, rope.len()).lines().take(height)`.
+    pub fn lines(self) -> Lines<'a> {
+        Lines {
+            rope: self.rope,
+            start: self.current_offset,
+            end: self.end,
+            empty_last: false,
+            done: false,
+        }
+    }
 }
 
 impl<'a> Iterator for RopeSlice<'a> {
     type Item = &'a [u8];
 
+    // Chunks are borrowed from the leaf buffers, the text before and after
+    // a leaf's gap being separate chunks
+    fn next(&mut self) -> Option<Self::Item> {
+        let rope = self.rope;
+        while self.current_offset < self.end && self.current_node != NIL {
+            let Payload::Leaf(leaf) = &rope.nodes[self.current_node as usize].payload;
+            let (lo, hi) = leaf.halves();
+            let at = self.current_offset - self.node_start;
+            let end = (self.end - self.node_start).min(leaf.byte_len());
+            let chunk = if at < lo.len() {
+                &lo[at..end.min(lo.len())]
+            } else {
+                &hi[(at - lo.len()).min(hi.len())..end - lo.len()]
+            };
+            self.current_offset += chunk.len();
+            if self.current_offset - self.node_start >= leaf.byte_len() {
+                self.node_start += leaf.byte_len();
+                self.current_node = rope.successor(self.current_node);
+            }
+            if !chunk.is_empty() {
+                return Some(chunk);
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for Chunks<'a> {
+    type Item = (usize, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk = self.slice.next()?;
+        Some((self.slice.current_offset - chunk.len(), chunk))
+    }
+}
+
+impl<'a> Iterator for Lines<'a> {
+    type Item = RopeSlice<'a>;
+
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current_offset >= self.end {
+        if self.done || (self.start == self.end && !self.empty_last) {
             return None;
         }
+        let line = self.rope.byte_to_line(self.start).ok()?;
+        let next_start = if line < self.rope.total_lines() {
+            self.rope.line_to_byte(line + 1).ok()?
+        } else {
+            usize::MAX
+        };
+        // Without a newline before the end this is the last line
+        if next_start > self.end {
+            self.done = true;
+        }
+        let end = next_start.min(self.end);
+        let slice = self.rope.slice(self.start, end);
+        self.start = end;
+        Some(slice)
+    }
+}
 
-        // Find the leaf containing current_offset
-        let mut node = self.current_node;
-        let mut offset_in_rope = 0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use proptest::{collection, sample};
 
-        // Navigate to the correct leaf
-        while node != NIL {
-            let node_idx = node as usize;
-            let left_bytes = if self.rope.nodes[node_idx].left != NIL {
-                self.rope.nodes[self.rope.nodes[node_idx].left as usize].sub_bytes as usize
-            } else {
-                0
-            };
+    fn rope_bytes(rope: &Rope) -> Vec<u8> {
+        let mut all = vec![0u8; rope.len()];
+        let read = rope.read_bytes_global(0, &mut all).expect("read all");
+        all.truncate(read);
+        all
+    }
 
-            if offset_in_rope + left_bytes > self.current_offset {
-                // Go left
-                node = self.rope.nodes[node_idx].left;
-            } else {
-                offset_in_rope += left_bytes;
-                let own_bytes = match &self.rope.nodes[node_idx].payload {
-                    Payload::Leaf(l) => l.byte_len(),
-                };
+    fn replace_first_model(model: &mut Vec<u8>, needle: &[u8], replacement: &[u8]) -> bool {
+        let Some(at) = model.windows(needle.len()).position(|w| w == needle) else {
+            return false;
+        };
+        model.splice(at..at + needle.len(), replacement.iter().copied());
+        true
+    }
 
-                if offset_in_rope + own_bytes > self.current_offset {
-                    // This is the correct leaf
-                    break;
-                } else {
-                    // Go right
-                    offset_in_rope += own_bytes;
-                    node = self.rope.nodes[node_idx].right;
+    /// Text of up to `max_len` bytes, with newlines and a two-byte char
+    fn text(max_len: usize) -> impl Strategy<Value = Vec<u8>> {
+        collection::vec(sample::select(b"ab \n\n\xc3\xa9xyz".to_vec()), 0..=max_len)
+    }
+
+    /// Text of a few bytes, now and then more than a leaf holds
+    fn edit_text() -> impl Strategy<Value = Vec<u8>> {
+        prop_oneof![9 => text(16), 1 => text(LEAF_CAPACITY * 2)]
+    }
+
+    /// One step of the model test. Offsets are picked within whatever text
+    /// the step applies to, so steps stay valid as failing cases shrink.
+    #[derive(Clone, Debug)]
+    enum Edit {
+        Rebuild(Vec<u8>),
+        Insert(sample::Index, Vec<u8>),
+        Delete(sample::Index, usize),
+        /// Replace a piece of the text found by `replace_first`: keeping
+        /// it and adding the text, deleting it, or putting the text there
+        Replace(sample::Index, usize, Vec<u8>, u8),
+    }
+
+    fn edit() -> impl Strategy<Value = Edit> {
+        prop_oneof![
+            1 => text(LEAF_USABLE * 3).prop_map(Edit::Rebuild),
+            12 => (any::<sample::Index>(), edit_text())
+                .prop_map(|(at, text)| Edit::Insert(at, text)),
+            9 => (any::<sample::Index>(), edit_text().prop_map(|text| text.len()))
+                .prop_map(|(start, len)| Edit::Delete(start, len)),
+            27 => (any::<sample::Index>(), 1..=8usize, text(16), 0..3u8)
+                .prop_map(|(at, len, text, kind)| Edit::Replace(at, len, text, kind)),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 32,
+            max_shrink_iters: 10_000,
+            ..ProptestConfig::default()
+        })]
+
+        /// Apply random edits to a rope and to a plain byte string and
+        /// compare them after every step. Inserts go through `insert_at` or
+        /// `replace_first`, deletes through `delete_range` or
+        /// `replace_first`.
+        #[test]
+        fn rope_random_edits_match_model(
+            initial in text(LEAF_USABLE * 3),
+            edits in collection::vec(edit(), 1..300),
+        ) {
+            let mut model = initial;
+            let mut rope = Rope::new();
+            rope.build_from_bytes(&model).expect("build");
+
+            for edit in edits {
+                match edit {
+                    Edit::Rebuild(text) => {
+                        model = text;
+                        rope.build_from_bytes(&model).expect("build");
+                    }
+                    Edit::Insert(at, text) => {
+                        let at = at.index(model.len() + 1);
+                        prop_assert_eq!(rope.insert_at(at, &text).expect("insert"), text.len());
+                        model.splice(at..at, text);
+                    }
+                    Edit::Delete(start, len) => {
+                        let start = start.index(model.len() + 1);
+                        let end = (start + len).min(model.len());
+                        prop_assert_eq!(rope.delete_range(start, end).expect("delete"), end - start);
+                        model.drain(start..end);
+                    }
+                    Edit::Replace(_, _, _, _) if model.is_empty() => continue,
+                    Edit::Replace(at, len, text, kind) => {
+                        let at = at.index(model.len());
+                        let needle = model[at..at + len.min(model.len() - at)].to_vec();
+                        let replacement = match kind {
+                            0 => [needle.as_slice(), &text].concat(),
+                            1 => Vec::new(),
+                            _ => text,
+                        };
+                        let replaced = rope.replace_first(&needle, &replacement).expect("replace");
+                        prop_assert_eq!(replaced, replacement.len());
+                        prop_assert!(replace_first_model(&mut model, &needle, &replacement));
+                    }
+                }
+
+                if let Err(e) = rope.validate() {
+                    return Err(TestCaseError::fail(format!("invalid tree: {}", e)));
                 }
+                prop_assert_eq!(rope.len(), model.len());
+                prop_assert_eq!(
+                    rope.total_lines(),
+                    model.iter().filter(|&&b| b == b'\n').count()
+                );
+                prop_assert!(rope_bytes(&rope) == model, "content differs");
             }
         }
+    }
 
-        if node == NIL {
-            return None;
-        }
+    #[test]
+    fn rope_char_offsets_match_str() {
+        // Many leaves, with multi-byte chars at the ends of some
+        let mut model = "añb€c🌍\n".repeat(400);
+        let mut rope = Rope::new();
+        rope.build_from_bytes(model.as_bytes()).expect("build");
+        assert!(rope.nodes.len() > 1);
+
+        let check = |rope: &Rope, model: &str| {
+            rope.validate().expect("valid tree");
+            assert_eq!(rope.char_len(), model.chars().count());
+            for (ch, (byte, c)) in model.char_indices().enumerate() {
+                assert_eq!(rope.char_to_byte(ch).unwrap(), byte);
+                for inner in byte..byte + c.len_utf8() {
+                    assert_eq!(rope.byte_to_char(inner).unwrap(), ch);
+                }
+            }
+            assert_eq!(rope.char_to_byte(rope.char_len()).unwrap(), model.len());
+            assert_eq!(rope.byte_to_char(model.len()).unwrap(), rope.char_len());
+        };
+        check(&rope, &model);
+        assert!(matches!(
+            rope.char_to_byte(rope.char_len() + 1),
+            Err(RBError::InvalidOffset)
+        ));
+        assert!(matches!(
+            rope.byte_to_char(model.len() + 1),
+            Err(RBError::InvalidOffset)
+        ));
+
+        // Edits by char offset never split a char
+        let char_at =
+            |model: &str, ch: usize| model.char_indices().nth(ch).map_or(model.len(), |(i, _)| i);
+        assert_eq!(rope.insert_char_at(1000, "über").unwrap(), 4);
+        let at = char_at(&model, 1000);
+        model.insert_str(at, "über");
+        check(&rope, &model);
+
+        assert_eq!(rope.delete_char_range(5, 2000).unwrap(), 1995);
+        let (from, to) = (char_at(&model, 5), char_at(&model, 2000));
+        model.replace_range(from..to, "");
+        check(&rope, &model);
+        assert_eq!(rope_bytes(&rope), model.as_bytes());
+    }
 
-        // Extract slice from this leaf - zero-copy approach
-        let Payload::Leaf(leaf) = &self.rope.nodes[node as usize].payload;
-        let leaf_start = (self.current_offset - offset_in_rope).min(leaf.byte_len());
-        let leaf_end = (self.end - offset_in_rope).min(leaf.byte_len());
+    #[test]
+    fn rope_utf8_edits_keep_chars_whole() {
+        let leaves_whole = |rope: &Rope| {
+            rope.chunks()
+                .all(|(_, chunk)| std::str::from_utf8(chunk).is_ok())
+        };
+        let mut model = "añb€c🌍\n".repeat(400);
+        let mut rope = Rope::new();
+        rope.build_from_bytes(model.as_bytes()).expect("build");
+        assert!(rope.nodes.len() > 1);
+        assert!(leaves_whole(&rope));
+        let mut read = Rope::new();
+        read.build_from_reader(model.as_bytes()).expect("read");
+        assert!(leaves_whole(&read));
+        assert_eq!(rope_bytes(&read), model.as_bytes());
+
+        for offset in 0..=model.len() + 1 {
+            assert_eq!(
+                rope.is_char_boundary(offset),
+                model.is_char_boundary(offset)
+            );
+        }
+        let euro = model.find('€').expect("euro");
+        assert_eq!(rope.floor_char_boundary(euro + 2), euro);
+        assert_eq!(rope.ceil_char_boundary(euro + 1), euro + 3);
+        assert_eq!(rope.ceil_char_boundary(euro), euro);
+        assert_eq!(rope.floor_char_boundary(model.len() + 5), model.len());
+
+        // Offsets inside a char are refused and leave the text as it was
+        assert!(matches!(
+            rope.insert_str_at(euro + 1, "x"),
+            Err(RBError::NotCharBoundary)
+        ));
+        assert!(matches!(
+            rope.delete_str_range(euro, euro + 2),
+            Err(RBError::NotCharBoundary)
+        ));
+        assert!(matches!(
+            rope.insert_str_at(model.len() + 1, "x"),
+            Err(RBError::InvalidOffset)
+        ));
+        assert_eq!(rope_bytes(&rope), model.as_bytes());
+
+        // Text spilling over into new leaves is cut between chars too
+        let big = "ü€🌍".repeat(LEAF_USABLE);
+        let at = rope.floor_char_boundary(LEAF_USABLE - 1);
+        assert_eq!(rope.insert_str_at(at, &big).unwrap(), big.len());
+        model.insert_str(at, &big);
+        assert_eq!(rope.delete_str_range(euro, euro + 3).unwrap(), 3);
+        model.replace_range(euro..euro + 3, "");
+        rope.validate().expect("valid tree");
+        assert!(leaves_whole(&rope));
+        assert!(rope.is_utf8());
+        assert_eq!(rope_bytes(&rope), model.as_bytes());
+
+        // Raw bytes can still break the text, and mend it again
+        rope.insert_at(0, &[0xE2, 0x82]).expect("insert");
+        assert!(!rope.is_utf8());
+        rope.insert_at(2, &[0xAC]).expect("insert");
+        assert!(rope.is_utf8());
+        rope.insert_at(rope.len(), &[0xF0]).expect("insert");
+        assert!(!rope.is_utf8());
+    }
 
-        if leaf_start >= leaf_end {
-            // Move to next leaf
-            self.current_node = self.rope.successor(node);
-            return self.next();
+    #[test]
+    fn rope_line_offsets_match_text() {
+        let model: Vec<u8> = (0..3000)
+            .flat_map(|i| format!("line {}\n", i).into_bytes())
+            .chain(*b"no newline")
+            .collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        assert!(rope.nodes.len() > 1);
+
+        let starts: Vec<usize> = std::iter::once(0)
+            .chain(
+                (0..model.len())
+                    .filter(|&i| model[i] == b'\n')
+                    .map(|i| i + 1),
+            )
+            .collect();
+        assert_eq!(starts.len(), rope.total_lines() + 1);
+        for (line, &start) in starts.iter().enumerate() {
+            assert_eq!(rope.line_to_byte(line).unwrap(), start);
+            assert_eq!(rope.byte_to_line(start).unwrap(), line);
+            if start > 0 {
+                // The newline ending a line belongs to it
+                assert_eq!(rope.byte_to_line(start - 1).unwrap(), line - 1);
+            }
         }
+        assert_eq!(rope.byte_to_line(model.len()).unwrap(), 3000);
+        assert!(matches!(
+            rope.line_to_byte(3001),
+            Err(RBError::InvalidOffset)
+        ));
+        assert!(matches!(
+            rope.byte_to_line(model.len() + 1),
+            Err(RBError::InvalidOffset)
+        ));
+
+        let empty = Rope::new();
+        assert_eq!(empty.line_to_byte(0).unwrap(), 0);
+        assert_eq!(empty.byte_to_line(0).unwrap(), 0);
+    }
 
-        let slice_len = leaf_end - leaf_start;
-        let gl = leaf.gap_lo();
-        let gh = leaf.gap_hi();
-
-        // Calculate the actual slice from the buffer, accounting for the gap
-        let slice_start = if leaf_start < gl {
-            leaf_start
-        } else {
-            leaf_start + (gh - gl)
-        };
+    #[test]
+    fn rope_get_line_matches_text() {
+        let mut rope = Rope::new();
+        let mut model: Vec<u8> = (0..2000)
+            .flat_map(|i| format!("line {}\r\n", i).into_bytes())
+            .collect();
+        rope.build_from_bytes(&model).expect("build");
+        // Edits leave gaps in the middle of leaves and lines across leaves
+        for at in [5, LEAF_USABLE - 3, 9000] {
+            rope.insert_at(at, b"xy\nz").expect("insert");
+            model.splice(at..at, b"xy\nz".iter().copied());
+        }
+        model.extend_from_slice(b"no newline");
+        rope.insert_at(rope.len(), b"no newline").expect("append");
+
+        let collect = |slice: RopeSlice| slice.flatten().copied().collect::<Vec<u8>>();
+        let lines: Vec<&[u8]> = model.split_inclusive(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), rope.total_lines() + 1);
+        for (n, line) in lines.iter().enumerate() {
+            assert_eq!(collect(rope.get_line(n).unwrap()), *line);
+            let content = line.strip_suffix(b"\n").unwrap_or(line);
+            assert_eq!(collect(rope.get_line_content(n).unwrap()), content);
+        }
+        assert!(rope.get_line(lines.len()).is_none());
+
+        // The empty line after a final newline
+        let mut rope = Rope::new();
+        rope.build_from_bytes(b"one\n").expect("build");
+        assert_eq!(collect(rope.get_line(0).unwrap()), b"one\n");
+        assert_eq!(rope.get_line(1).unwrap().count(), 0);
+        assert!(Rope::new().get_line(0).is_some());
+    }
 
-        let slice_end = if leaf_end <= gl {
-            leaf_end
-        } else {
-            leaf_end + (gh - gl)
-        };
+    #[test]
+    fn rope_lines_match_get_line() {
+        let collect = |slice: RopeSlice| slice.flatten().copied().collect::<Vec<u8>>();
+        let text: Vec<u8> = (0..3000)
+            .flat_map(|i| format!("{}\n", "x".repeat(i % 7)).into_bytes())
+            .collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&text).expect("build");
+        assert_eq!(rope.lines().count(), rope.total_lines() + 1);
+        for (n, line) in rope.lines().enumerate() {
+            assert_eq!(collect(line), collect(rope.get_line(n).unwrap()));
+        }
+
+        // A slice's lines stop at its end, where a line may be cut short
+        let top = rope.line_to_byte(1500).unwrap();
+        let lines: Vec<Vec<u8>> = rope.slice(top, top + 10).lines().map(collect).collect();
+        let expected: Vec<&[u8]> = text[top..top + 10]
+            .split_inclusive(|&b| b == b'\n')
+            .collect();
+        assert_eq!(lines, expected);
+        let bottom = rope.line_to_byte(1510).unwrap();
+        assert_eq!(rope.slice(top, bottom).lines().count(), 10);
+        assert_eq!(rope.slice(top, top).lines().count(), 0);
 
-        self.current_offset += slice_len;
+        let mut rope = Rope::new();
+        assert_eq!(rope.lines().map(collect).collect::<Vec<_>>(), [b""]);
+        rope.build_from_bytes(b"a\nb").expect("build");
+        let lines: Vec<Vec<u8>> = rope.lines().map(collect).collect();
+        assert_eq!(lines, [&b"a\n"[..], b"b"]);
+    }
 
-        // Update current_node for next iteration
-        if self.current_offset >= leaf_end + offset_in_rope {
-            self.current_node = self.rope.successor(node);
+    #[test]
+    fn rope_insert_at_splits_full_leaves() {
+        let mut rope = Rope::new();
+        assert!(matches!(
+            rope.insert_at(1, b"x"),
+            Err(RBError::InvalidOffset)
+        ));
+        rope.insert_at(0, b"end\n").expect("insert into empty rope");
+
+        // Typing at the same spot fills the leaf, then splits it again and again
+        let mut model = b"end\n".to_vec();
+        for i in 0..LEAF_CAPACITY * 4 {
+            let byte = if i % 40 == 39 {
+                b'\n'
+            } else {
+                b'a' + (i % 26) as u8
+            };
+            rope.insert_at(2, &[byte]).expect("insert");
+            model.insert(2, byte);
         }
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert_eq!(
+            rope.total_lines(),
+            model.iter().filter(|&&b| b == b'\n').count()
+        );
 
-        // Return the slice directly from the buffer - this is zero-copy
-        Some(&leaf.buf()[slice_start..slice_end])
+        // One insert spanning several leaves, between two existing leaves
+        let big: Vec<u8> = (0..LEAF_CAPACITY * 3)
+            .map(|i| b'0' + (i % 10) as u8)
+            .collect();
+        let at = LEAF_USABLE;
+        assert_eq!(rope.insert_at(at, &big).expect("insert"), big.len());
+        model.splice(at..at, big);
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn rope_insert_at_renumbers_crowded_keys() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&[b'a'; LEAF_USABLE * 2])
+            .expect("build");
+        // Splitting the first leaf over and over halves the key gap after it
+        for _ in 0..40 {
+            rope.insert_at(1, &[b'b'; LEAF_CAPACITY]).expect("insert");
+        }
+        rope.validate().expect("valid tree");
+        assert_eq!(rope.len(), LEAF_USABLE * 2 + LEAF_CAPACITY * 40);
+    }
+
+    #[test]
+    fn rope_delete_range_across_leaves() {
+        let model: Vec<u8> = (0..LEAF_USABLE * 10)
+            .map(|i| {
+                if i % 50 == 49 {
+                    b'\n'
+                } else {
+                    b'a' + (i % 26) as u8
+                }
+            })
+            .collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        assert!(matches!(
+            rope.delete_range(5, 4),
+            Err(RBError::InvalidOffset)
+        ));
+        assert!(matches!(
+            rope.delete_range(0, model.len() + 1),
+            Err(RBError::InvalidOffset)
+        ));
+
+        // From the middle of the second leaf to the middle of the sixth
+        let (start, end) = (LEAF_USABLE + 100, LEAF_USABLE * 5 + 200);
+        assert_eq!(rope.delete_range(start, end).expect("delete"), end - start);
+        let mut expected = model.clone();
+        expected.drain(start..end);
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == expected);
+        assert_eq!(
+            rope.total_lines(),
+            expected.iter().filter(|&&b| b == b'\n').count()
+        );
+        // The leaves emptied were dropped and the two partial ones merged
+        assert_eq!(rope.nodes.len(), 6);
+
+        assert_eq!(
+            rope.delete_range(0, rope.len()).expect("delete all"),
+            expected.len()
+        );
+        rope.validate().expect("valid tree");
+        assert_eq!(rope.len(), 0);
+        assert_eq!(rope.total_lines(), 0);
+        rope.insert_at(0, b"again\n").expect("insert");
+        assert!(rope_bytes(&rope) == b"again\n");
+    }
+
+    #[test]
+    fn rope_delete_range_keeps_tree_balanced() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&[b'x'; LEAF_USABLE * 64])
+            .expect("build");
+        // Delete whole leaves from the front, the back and the middle
+        for round in 0..60 {
+            let len = rope.len();
+            let start = match round % 3 {
+                0 => 0,
+                1 => len - LEAF_USABLE,
+                _ => len / 2,
+            };
+            rope.delete_range(start, start + LEAF_USABLE)
+                .expect("delete");
+            if let Err(e) = rope.validate() {
+                panic!("round {}: {}", round, e);
+            }
+        }
+        assert_eq!(rope.len(), LEAF_USABLE * 4);
+    }
 
     #[test]
     fn rope_build_len_read() {
@@ -840,6 +2924,516 @@ mod tests {
         assert!(std::str::from_utf8(&all).unwrap_or("").contains("show("));
     }
 
+    #[test]
+    fn rope_find_all_replace_all() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(b"aaa").expect("build");
+        assert_eq!(rope.find_all(b"aa"), [0]);
+        assert_eq!(rope.find_all(b""), Vec::<usize>::new());
+
+        // Matches inside leaves, across their boundaries, and replacements
+        // too big for the leaves they are in
+        let mut model: Vec<u8> = (0..LEAF_CAPACITY * 4)
+            .map(|i| if i % 97 < 3 { b'x' } else { b'.' })
+            .collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        let expected: Vec<usize> = (0..model.len())
+            .filter(|&i| model[i..].starts_with(b"xx") && (i == 0 || model[i - 1] != b'x'))
+            .collect();
+        assert_eq!(rope.find_all(b"xx"), expected);
+
+        let replacement = vec![b'y'; 700];
+        let mut replaced = 0;
+        while replace_first_model(&mut model, b"xx", &replacement) {
+            replaced += 1;
+        }
+        assert_eq!(
+            rope.replace_all(b"xx", &replacement).expect("replace"),
+            replaced
+        );
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert_eq!(rope.char_len(), model.len());
+        assert!(rope.find_all(b"xx").is_empty());
+        assert_eq!(rope.replace_all(b"xx", b"").expect("replace"), 0);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(40))]
+
+        #[test]
+        fn rope_find_streams_over_chunks(
+            initial in collection::vec(sample::select(b"aab".to_vec()), LEAF_USABLE * 3),
+            // Edits leave gaps, splitting leaves into chunks of any size
+            inserts in collection::vec(
+                (any::<sample::Index>(), collection::vec(sample::select(b"ab".to_vec()), 0..3)),
+                30,
+            ),
+            needle_len in 1..=7usize,
+            start in any::<sample::Index>(),
+            len in any::<sample::Index>(),
+        ) {
+            let mut model = initial;
+            let mut rope = Rope::new();
+            rope.build_from_bytes(&model).expect("build");
+            for (at, text) in inserts {
+                let at = at.index(model.len() + 1);
+                rope.insert_at(at, &text).expect("insert");
+                model.splice(at..at, text);
+            }
+            let needle = &b"aabaaab"[..needle_len];
+            let start = start.index(model.len());
+            let end = start + len.index(model.len() - start + 1);
+            let expected: Vec<usize> = (start..end)
+                .filter(|&i| model[i..].starts_with(needle))
+                .collect();
+            prop_assert_eq!(rope.match_offsets(needle, start, end), expected);
+            prop_assert_eq!(
+                rope.find_first(needle),
+                model.windows(needle.len()).position(|w| w == needle)
+            );
+        }
+    }
+
+    /// One step of the undo test
+    #[derive(Clone, Debug)]
+    enum Step {
+        Undo,
+        Redo,
+        Delete(sample::Index, usize),
+        /// Several edits as one step
+        Transaction,
+        /// Type a char, on where the last insertion ended if `resume` and
+        /// the last step typed
+        Type {
+            resume: bool,
+            at: sample::Index,
+            ch: u8,
+        },
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            1 => Just(Step::Undo),
+            1 => Just(Step::Redo),
+            1 => (any::<sample::Index>(), 1..=LEAF_CAPACITY)
+                .prop_map(|(start, len)| Step::Delete(start, len)),
+            1 => Just(Step::Transaction),
+            2 => (any::<bool>(), any::<sample::Index>(), sample::select(b"a\nz".to_vec()))
+                .prop_map(|(resume, at, ch)| Step::Type { resume, at, ch }),
+        ]
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig {
+            cases: 32,
+            max_shrink_iters: 10_000,
+            ..ProptestConfig::default()
+        })]
+
+        #[test]
+        fn rope_undo_redo_restore_text(
+            initial in text(LEAF_USABLE * 2),
+            steps in collection::vec(step(), 1..400),
+        ) {
+            let mut rope = Rope::new();
+            rope.build_from_bytes(&initial).expect("build");
+            prop_assert!(!rope.can_undo());
+            // Text before each step still to undo, and after each one undone
+            let mut before: Vec<Vec<u8>> = Vec::new();
+            let mut undone: Vec<Vec<u8>> = Vec::new();
+            // Where the last edit inserted up to, when the next may join it
+            let mut typing_at = None;
+            for step in steps {
+                let text = rope_bytes(&rope);
+                let mut typed_to = None;
+                match step {
+                    Step::Undo if rope.can_undo() => {
+                        rope.undo().expect("undo").expect("a step to undo");
+                        undone.push(text);
+                        prop_assert!(Some(rope_bytes(&rope)) == before.pop());
+                    }
+                    Step::Redo if rope.can_redo() => {
+                        rope.redo().expect("redo").expect("a step to redo");
+                        before.push(text);
+                        prop_assert!(Some(rope_bytes(&rope)) == undone.pop());
+                    }
+                    Step::Delete(start, len) if !text.is_empty() => {
+                        let start = start.index(text.len());
+                        let end = (start + len).min(text.len());
+                        rope.delete_range(start, end).expect("delete");
+                        before.push(text);
+                        undone.clear();
+                    }
+                    Step::Transaction => {
+                        rope.begin_transaction();
+                        rope.insert_at(0, b"<").expect("insert");
+                        rope.replace_all(b"a", b"AA").expect("replace");
+                        rope.insert_at(rope.len(), b">").expect("insert");
+                        rope.end_transaction();
+                        before.push(text);
+                        undone.clear();
+                    }
+                    Step::Type { resume, at, ch } => {
+                        let at = match typing_at {
+                            Some(at) if resume => at,
+                            _ => at.index(text.len() + 1),
+                        };
+                        rope.insert_at(at, &[ch]).expect("insert");
+                        if typing_at != Some(at) {
+                            before.push(text);
+                        }
+                        typed_to = Some(at + 1);
+                        undone.clear();
+                    }
+                    // Nothing to undo, redo or delete leaves typing as it was
+                    _ => typed_to = typing_at,
+                }
+                typing_at = typed_to;
+                if let Err(e) = rope.validate() {
+                    return Err(TestCaseError::fail(format!("invalid tree: {}", e)));
+                }
+            }
+            while let Some(text) = before.pop() {
+                rope.undo().expect("undo").expect("a step to undo");
+                prop_assert!(rope_bytes(&rope) == text);
+            }
+            prop_assert!(!rope.can_undo());
+        }
+    }
+
+    #[test]
+    fn rope_undo_returns_the_edit_offset() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(b"one two").expect("build");
+        for (i, ch) in b"six ".iter().enumerate() {
+            rope.insert_at(4 + i, &[*ch]).expect("insert");
+        }
+        rope.replace_first(b"one", b"1").expect("replace");
+        assert_eq!(rope_bytes(&rope), b"1 six two");
+
+        assert_eq!(rope.undo().expect("undo"), Some(0));
+        assert_eq!(rope_bytes(&rope), b"one six two");
+        // The typed chars are one step
+        assert_eq!(rope.undo().expect("undo"), Some(4));
+        assert_eq!(rope_bytes(&rope), b"one two");
+        assert_eq!(rope.undo().expect("undo"), None);
+
+        assert_eq!(rope.redo().expect("redo"), Some(4));
+        rope.delete_range(0, 4).expect("delete");
+        assert!(!rope.can_redo());
+        assert_eq!(rope.redo().expect("redo"), None);
+
+        // Loading a text starts the history over
+        rope.build_from_bytes(b"new").expect("build");
+        assert!(!rope.can_undo());
+    }
+
+    #[test]
+    fn rope_marks_follow_edits() {
+        let text = "alpha beta gamma\n".repeat(500);
+        let mut rope = Rope::new();
+        rope.build_from_bytes(text.as_bytes()).expect("build");
+        let betas: Vec<MarkId> = text
+            .match_indices("beta")
+            .map(|(at, _)| rope.add_mark(at, Bias::Before).expect("mark"))
+            .collect();
+        let end = rope.add_mark(rope.len(), Bias::After).expect("mark");
+        let at_beta = |rope: &Rope, ids: &[MarkId]| {
+            let bytes = rope_bytes(rope);
+            ids.iter()
+                .all(|&id| bytes[rope.mark(id).expect("mark")..].starts_with(b"beta"))
+        };
+
+        // Replacements within leaves, growing past them and inserts
+        rope.replace_all(b"alpha", b"a").expect("replace");
+        rope.replace_all(b"gamma", &[b'g'; 60]).expect("replace");
+        rope.insert_at(0, b"head\n").expect("insert");
+        assert!(at_beta(&rope, &betas));
+        assert_eq!(rope.mark(end), Some(rope.len()));
+        rope.insert_at(rope.len(), b"tail").expect("insert");
+        assert_eq!(rope.mark(end), Some(rope.len()));
+
+        // Deleting the line of a mark puts it at the start of the next one
+        let (start, next) = (rope.line_to_byte(1).unwrap(), rope.line_to_byte(2).unwrap());
+        rope.delete_range(start, next).expect("delete");
+        assert_eq!(rope.mark(betas[0]), Some(start));
+        assert!(at_beta(&rope, &betas[1..]));
+        // Undo is an edit like any other
+        rope.undo().expect("undo");
+        assert_eq!(rope.mark(betas[0]), Some(start));
+        assert!(at_beta(&rope, &betas[1..]));
+
+        assert_eq!(rope.remove_mark(betas[0]), Some(start));
+        assert_eq!(rope.mark(betas[0]), None);
+        assert!(matches!(
+            rope.add_mark(rope.len() + 1, Bias::Before),
+            Err(RBError::InvalidOffset)
+        ));
+        rope.set_mark(end, 3).expect("set");
+        // A new text keeps the marks within it
+        rope.build_from_bytes(b"ab").expect("build");
+        assert_eq!(rope.mark(end), Some(2));
+        assert_eq!(rope.marks().count(), betas.len());
+    }
+
+    #[test]
+    fn rope_snapshot_keeps_the_old_text() {
+        let model: Vec<u8> = (0..LEAF_USABLE * 6)
+            .map(|i| b'a' + (i % 26) as u8)
+            .collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        let snapshot = rope.snapshot();
+        let leaves = |rope: &Rope| -> Vec<Arc<Leaf>> {
+            let mut leaves = Vec::new();
+            let mut cur = rope.min_node(rope.root);
+            while cur != NIL {
+                let Payload::Leaf(leaf) = &rope.nodes[cur as usize].payload;
+                leaves.push(Arc::clone(leaf));
+                cur = rope.successor(cur);
+            }
+            leaves
+        };
+
+        // An edit copies the leaf it is in and no other
+        rope.insert_at(10, b"edit").expect("insert");
+        let (old, new) = (leaves(&snapshot), leaves(&rope));
+        assert!(!Arc::ptr_eq(&old[0], &new[0]));
+        assert!(
+            old[1..]
+                .iter()
+                .zip(&new[1..])
+                .all(|(a, b)| Arc::ptr_eq(a, b))
+        );
+
+        rope.delete_range(100, LEAF_USABLE * 4).expect("delete");
+        rope.replace_all(b"xyz", b"-").expect("replace");
+        rope.undo().expect("undo");
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) != model);
+
+        // The snapshot still reads as the text was, from another thread too
+        let clone = snapshot.clone();
+        let text = std::thread::spawn(move || rope_bytes(&clone))
+            .join()
+            .unwrap();
+        assert!(text == model);
+        assert_eq!(snapshot.len(), model.len());
+        assert_eq!(snapshot.find_first(b"xyz"), Some(23));
+        assert!(snapshot.revision() < rope.revision());
+        assert!(!snapshot.can_undo());
+    }
+
+    #[test]
+    fn rope_build_from_reader_matches_build_from_bytes() {
+        // Reads come back a few bytes at a time, and one is interrupted
+        struct Trickle<'a> {
+            data: &'a [u8],
+            reads: usize,
+        }
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.reads += 1;
+                if self.reads == 3 {
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                let n = buf.len().min(self.data.len()).min(self.reads % 700 + 1);
+                buf[..n].copy_from_slice(&self.data[..n]);
+                self.data = &self.data[n..];
+                Ok(n)
+            }
+        }
+
+        let model: Vec<u8> = "héllo wörld\n".repeat(LEAF_USABLE / 4).into_bytes();
+        let mut expected = Rope::new();
+        expected.build_from_bytes(&model).expect("build");
+        let mut rope = Rope::new();
+        rope.insert_at(0, b"old text").expect("insert");
+        let read = rope.build_from_reader(Trickle {
+            data: &model,
+            reads: 0,
+        });
+        assert_eq!(read.expect("build"), model.len());
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert_eq!(rope.total_lines(), expected.total_lines());
+        assert_eq!(rope.char_len(), expected.char_len());
+        assert_eq!(rope.line_to_byte(500).ok(), expected.line_to_byte(500).ok());
+        assert!(!rope.can_undo());
+
+        assert_eq!(rope.build_from_reader(&b""[..]).expect("build"), 0);
+        assert_eq!(rope.len(), 0);
+        let exact = vec![b'x'; LEAF_USABLE * 2];
+        assert_eq!(
+            rope.build_from_reader(exact.as_slice()).expect("build"),
+            exact.len()
+        );
+        assert!(rope_bytes(&rope) == exact);
+    }
+
+    #[test]
+    fn rope_builder_appends_pieces_into_a_balanced_tree() {
+        let model: Vec<u8> = "naïve 日本語 text\n".repeat(LEAF_USABLE / 2).into_bytes();
+        let mut builder = RopeBuilder::new();
+        assert!(builder.is_empty());
+        // Pieces of odd sizes cut chars in two between appends
+        for piece in model.chunks(333) {
+            builder.append(piece);
+        }
+        assert_eq!(builder.len(), model.len());
+        let mut rope = builder.finish();
+
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        let text = std::str::from_utf8(&model).unwrap();
+        assert_eq!(rope.char_len(), text.chars().count());
+        assert_eq!(rope.total_lines(), LEAF_USABLE / 2);
+        // No char is split between two leaves
+        let spans = rope.leaf_spans();
+        assert!(spans.len() > 10);
+        assert!(
+            spans
+                .iter()
+                .all(|&(start, _, _)| text.is_char_boundary(start))
+        );
+
+        // The tree takes edits like one built leaf by leaf
+        rope.insert_at(rope.len() / 2, &[b'x'; LEAF_USABLE])
+            .expect("insert");
+        rope.validate().expect("valid tree");
+        assert_eq!(rope.len(), model.len() + LEAF_USABLE);
+        assert_eq!(RopeBuilder::new().finish().len(), 0);
+    }
+
+    #[test]
+    fn rope_from_str_and_to_string() {
+        let text = "naïve 日本語\n".repeat(LEAF_USABLE / 4);
+        let rope = Rope::from(text.as_str());
+        rope.validate().expect("valid tree");
+        assert_eq!(rope.to_string(), text);
+        assert_eq!(Rope::from_iter(text.split_inclusive('\n')), rope);
+        assert_eq!(Rope::from("").to_string(), "");
+        assert_eq!(format!("[{}]", Rope::from("x")), "[x]");
+
+        // A char cut in two by the gap, and bytes that aren't UTF-8
+        let mut rope = Rope::from("a日b");
+        rope.delete_range(2, 3).expect("delete");
+        rope.insert_at(2, b"\x97").expect("insert");
+        assert!(rope.chunks().count() > 1);
+        assert_eq!(rope.to_string(), "a日b");
+        rope.insert_at(0, b"\xff").expect("insert");
+        assert_eq!(rope.to_string(), "\u{FFFD}a日b");
+    }
+
+    #[test]
+    fn rope_equality_and_content_hash() {
+        let text = "line of text\n".repeat(LEAF_USABLE / 4);
+        let mut rope = Rope::new();
+        rope.build_from_bytes(text.as_bytes()).expect("build");
+        // The same text split into leaves differently
+        let mut edited = Rope::new();
+        edited
+            .build_from_bytes(&text.as_bytes()[100..])
+            .expect("build");
+        edited
+            .insert_at(0, text[..100].repeat(10).as_bytes())
+            .expect("insert");
+        edited.delete_range(100, 1000).expect("delete");
+        let starts = |rope: &Rope| -> Vec<usize> {
+            rope.leaf_spans()
+                .iter()
+                .map(|&(start, _, _)| start)
+                .collect()
+        };
+        assert_ne!(starts(&edited), starts(&rope));
+
+        assert_eq!(rope, edited);
+        assert_eq!(rope, *text);
+        assert_eq!(rope, text.as_str());
+        assert!(*text == rope);
+        edited
+            .delete_range(text.len() - 2, text.len() - 1)
+            .expect("delete");
+        assert_ne!(rope, edited);
+        assert_ne!(rope, "line of text\n");
+        assert_eq!(Rope::new(), "");
+
+        // The hash holds until an edit, and a clone has the same one
+        let hash = rope.content_hash();
+        assert_eq!(rope.content_hash(), hash);
+        assert_eq!(rope.clone().content_hash(), hash);
+        rope.insert_at(LEAF_USABLE + 5, b"x").expect("insert");
+        assert_ne!(rope.content_hash(), hash);
+        let snapshot = rope.snapshot();
+        rope.delete_range(LEAF_USABLE + 5, LEAF_USABLE + 6)
+            .expect("delete");
+        assert_ne!(snapshot.content_hash(), rope.content_hash());
+        assert_eq!(rope, *text);
+    }
+
+    #[test]
+    fn rope_write_to_writes_the_text_in_order() {
+        let mut model: Vec<u8> = (0..LEAF_USABLE * 5).map(|i| b"ab\ncd"[i % 5]).collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        // Gaps in the middle of leaves
+        for at in [3, LEAF_USABLE + 10, LEAF_USABLE * 3] {
+            rope.insert_at(at, b"<edit>").expect("insert");
+            model.splice(at..at, b"<edit>".iter().copied());
+        }
+
+        let mut out = Vec::new();
+        assert_eq!(rope.write_to(&mut out).expect("write"), model.len());
+        assert!(out == model);
+
+        let mut out = Vec::new();
+        assert_eq!(Rope::new().write_to(&mut out).expect("write"), 0);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn rope_optimize_merges_fragmented_leaves() {
+        let mut model: Vec<u8> = (0..LEAF_USABLE * 8).map(|i| b"ab\ncd"[i % 5]).collect();
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        // Replacements shrink the text of every leaf without merging any
+        rope.replace_all(b"b\nc", b"").expect("replace");
+        model = String::from_utf8(model)
+            .unwrap()
+            .replace("b\nc", "")
+            .into_bytes();
+        let leaves = rope.nodes.len();
+        assert_eq!(leaves, 8);
+        let leaves = rope.nodes.len();
+        let snapshot = rope.snapshot();
+
+        let merged = rope.optimize();
+        assert!(merged > 0);
+        assert_eq!(rope.nodes.len(), leaves - merged);
+        assert_eq!(rope.merge_leaves(), 0);
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert!(rope_bytes(&snapshot) == model);
+        assert_eq!(rope.total_lines(), snapshot.total_lines());
+        assert_eq!(rope.char_len(), model.len());
+
+        // Keys are spaced evenly again, in text order
+        let mut cur = rope.min_node(rope.root);
+        let mut key = 0;
+        while cur != NIL {
+            assert_eq!(rope.nodes[cur as usize].key, key);
+            key += KEY_STRIDE;
+            cur = rope.successor(cur);
+        }
+
+        // Edits go on as before
+        rope.insert_at(5, b"more").expect("insert");
+        model.splice(5..5, b"more".iter().copied());
+        assert!(rope_bytes(&rope) == model);
+    }
+
     #[test]
     fn rope_debug_visualize() {
         let mut rope = Rope::new();
@@ -905,6 +3499,38 @@ mod tests {
         assert!(s.contains("abc\nd\ne\nf\n"));
     }
 
+    #[test]
+    fn rope_replace_larger_than_leaf() {
+        let mut model = vec![b'a'; LEAF_USABLE * 2];
+        model[10..14].copy_from_slice(b"NEED");
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+
+        // More than the leaf's gap, and more than a whole leaf
+        let replacement = [b"x\n".as_slice(); LEAF_CAPACITY].concat();
+        let replaced = rope.replace_first(b"NEED", &replacement).expect("replace");
+        assert_eq!(replaced, replacement.len());
+        assert!(replace_first_model(&mut model, b"NEED", &replacement));
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert_eq!(rope.total_lines(), LEAF_CAPACITY);
+    }
+
+    #[test]
+    fn rope_replace_match_spanning_leaves() {
+        let mut model = vec![b'a'; LEAF_USABLE * 2];
+        model[LEAF_USABLE - 2..LEAF_USABLE + 2].copy_from_slice(b"SPAN");
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+
+        let replaced = rope.replace_first(b"SPAN", b"s\np").expect("replace");
+        assert_eq!(replaced, 3);
+        assert!(replace_first_model(&mut model, b"SPAN", b"s\np"));
+        rope.validate().expect("valid tree");
+        assert!(rope_bytes(&rope) == model);
+        assert_eq!(rope.total_lines(), 1);
+    }
+
     #[test]
     fn rope_metadata_consistency_large_build() {
         let mut rope = Rope::new();
@@ -973,6 +3599,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn rope_chunks_cover_the_text() {
+        let mut rope = Rope::new();
+        let mut model: Vec<u8> = (0..LEAF_CAPACITY * 3).map(|i| (i % 251) as u8).collect();
+        rope.build_from_bytes(&model).expect("build");
+        rope.insert_at(100, b"gap").expect("insert");
+        model.splice(100..100, b"gap".iter().copied());
+
+        let mut next = 0;
+        for (offset, chunk) in rope.chunks() {
+            assert_eq!(offset, next);
+            assert!(!chunk.is_empty());
+            assert_eq!(chunk, &model[offset..offset + chunk.len()]);
+            next += chunk.len();
+        }
+        assert_eq!(next, model.len());
+
+        let (offset, chunk) = rope.slice(50, 60).chunks().next().unwrap();
+        assert_eq!((offset, chunk), (50, &model[50..60]));
+        assert_eq!(Rope::new().chunks().count(), 0);
+    }
+
     #[test]
     fn rope_slice_iterator_bounds() {
         let mut rope = Rope::new();
@@ -0,0 +1,212 @@
+// Cached literal search over a Rope.
+//
+// A SearchIndex holds every match of one needle for the revision of the rope
+// it was computed at. Repeated "next match" / "previous match" queries and
+// match counts on an unchanged rope are binary searches over the cached
+// offsets instead of rescans. After an edit the caller reports the edited
+// range with `edited`, along with the revision the rope had before it, and
+// only the matches around it are looked for again; the rest are shifted. An
+// index that missed an edit notices the new rope revision and rescans the
+// whole text on its next query.
+
+use crate::rope::Rope;
+
+#[derive(Debug, Clone)]
+pub struct SearchIndex {
+    needle: Vec<u8>,
+    /// Start offsets of the matches, in order
+    matches: Vec<usize>,
+    /// Rope revision `matches` is up to date with; None before the first scan
+    revision: Option<u64>,
+}
+
+impl SearchIndex {
+    pub fn new(needle: &[u8]) -> Self {
+        Self {
+            needle: needle.to_vec(),
+            matches: Vec::new(),
+            revision: None,
+        }
+    }
+
+    pub fn needle(&self) -> &[u8] {
+        &self.needle
+    }
+
+    /// Start offsets of every match in `rope`, in order. Overlapping
+    /// matches all count: `aa` matches `aaa` at 0 and 1.
+    pub fn matches(&mut self, rope: &Rope) -> &[usize] {
+        self.refresh(rope);
+        &self.matches
+    }
+
+    pub fn count(&mut self, rope: &Rope) -> usize {
+        self.matches(rope).len()
+    }
+
+    /// First match starting after `offset`, wrapping around to the first match
+    /// of the text
+    pub fn next_match(&mut self, rope: &Rope, offset: usize) -> Option<usize> {
+        let matches = self.matches(rope);
+        let after = matches.partition_point(|&m| m <= offset);
+        matches.get(after).or(matches.first()).copied()
+    }
+
+    /// Last match starting before `offset`, wrapping around to the last match
+    /// of the text
+    pub fn prev_match(&mut self, rope: &Rope, offset: usize) -> Option<usize> {
+        let matches = self.matches(rope);
+        let before = matches.partition_point(|&m| m < offset);
+        match before {
+            0 => matches.last().copied(),
+            _ => Some(matches[before - 1]),
+        }
+    }
+
+    /// Index `rope` after `removed` bytes at `offset` were replaced with
+    /// `inserted` bytes, the rope having been at revision `before` until then.
+    /// An index that wasn't up to date with `before` missed an edit and
+    /// rescans everything on the next query instead.
+    pub fn edited(
+        &mut self,
+        rope: &Rope,
+        before: u64,
+        offset: usize,
+        removed: usize,
+        inserted: usize,
+    ) {
+        if self.revision != Some(before) {
+            self.revision = None;
+            return;
+        }
+        if self.needle.is_empty() {
+            return;
+        }
+        let len = self.needle.len();
+        // Matches overlapping the removed bytes, or the edit point when
+        // nothing was removed, are gone
+        let first = self.matches.partition_point(|&m| m + len <= offset);
+        let last = self.matches.partition_point(|&m| m < offset + removed);
+        let shifted: Vec<usize> = self.matches[last..]
+            .iter()
+            .map(|&m| m - removed + inserted)
+            .collect();
+        // New matches overlap the inserted bytes or span the edit point
+        let found = rope.match_offsets(
+            &self.needle,
+            offset.saturating_sub(len - 1),
+            offset + inserted,
+        );
+        self.matches.truncate(first);
+        self.matches.extend(found);
+        self.matches.extend(shifted);
+        self.revision = Some(rope.revision());
+    }
+
+    /// Scan the whole rope unless the matches are for its revision
+    fn refresh(&mut self, rope: &Rope) {
+        if self.revision == Some(rope.revision()) {
+            return;
+        }
+        self.matches = rope.match_offsets(&self.needle, 0, rope.len());
+        self.revision = Some(rope.revision());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rope::LEAF_USABLE;
+
+    fn model_matches(text: &[u8], needle: &[u8]) -> Vec<usize> {
+        text.windows(needle.len())
+            .enumerate()
+            .filter(|(_, w)| *w == needle)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn search_finds_matches_across_leaves() {
+        let mut text = Vec::new();
+        while text.len() < LEAF_USABLE * 4 {
+            text.extend_from_slice(b"needle in a haystack, ");
+        }
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&text).expect("build");
+        let mut index = SearchIndex::new(b"needle");
+        assert_eq!(index.matches(&rope), model_matches(&text, b"needle"));
+
+        let mut overlapping = SearchIndex::new(b"aa");
+        let mut small = Rope::new();
+        small.build_from_bytes(b"aaa").expect("build");
+        assert_eq!(overlapping.matches(&small), [0, 1]);
+    }
+
+    #[test]
+    fn search_next_and_prev_wrap_around() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(b"ab ab ab").expect("build");
+        let mut index = SearchIndex::new(b"ab");
+        assert_eq!(index.count(&rope), 3);
+        assert_eq!(index.next_match(&rope, 0), Some(3));
+        assert_eq!(index.next_match(&rope, 6), Some(0));
+        assert_eq!(index.prev_match(&rope, 3), Some(0));
+        assert_eq!(index.prev_match(&rope, 0), Some(6));
+        assert_eq!(SearchIndex::new(b"zz").next_match(&rope, 0), None);
+    }
+
+    #[test]
+    fn search_follows_edits() {
+        let mut model = Vec::new();
+        while model.len() < LEAF_USABLE * 2 {
+            model.extend_from_slice(b"one two three two one\n");
+        }
+        let mut rope = Rope::new();
+        rope.build_from_bytes(&model).expect("build");
+        let mut index = SearchIndex::new(b"two");
+        index.count(&rope);
+
+        // Break a match, make one across the edit point, and remove text
+        let edits: [(&[u8], &[u8]); 4] = [
+            (b"two", b"tw"),
+            (b"three", b"twothree"),
+            (b"e t", b""),
+            (b"one\none", b"one\ntwo"),
+        ];
+        for (needle, replacement) in edits {
+            let at = model
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .expect("needle in model");
+            let before = rope.revision();
+            rope.replace_first(needle, replacement).expect("replace");
+            model.splice(at..at + needle.len(), replacement.iter().copied());
+            index.edited(&rope, before, at, needle.len(), replacement.len());
+            assert_eq!(index.revision, Some(rope.revision()));
+            assert_eq!(index.matches(&rope), model_matches(&model, b"two"));
+        }
+
+        // An edit the index wasn't told about is picked up by a rescan
+        rope.replace_first(b"two", b"").expect("replace");
+        let at = model_matches(&model, b"two")[0];
+        model.drain(at..at + 3);
+        assert_eq!(index.matches(&rope), model_matches(&model, b"two"));
+    }
+    #[test]
+    fn search_rescans_after_a_missed_edit() {
+        let mut rope = Rope::new();
+        rope.build_from_bytes(b"ab xx ab").expect("build");
+        let mut index = SearchIndex::new(b"ab");
+        assert_eq!(index.matches(&rope), [0, 6]);
+
+        // The first insertion is never reported, so the second one can't
+        // be followed from the cached matches
+        rope.insert_at(0, b"zzz").expect("insert");
+        let before = rope.revision();
+        rope.insert_at(11, b"!").expect("insert");
+        index.edited(&rope, before, 11, 0, 1);
+        assert_eq!(index.revision, None);
+        assert_eq!(index.matches(&rope), [3, 9]);
+    }
+}
@@ -1,48 +1,181 @@
-use niv_frontend::Editor;
-use niv_fs::load_file;
+use niv_frontend::{
+    Editor, JsonValue, RpcClient, find_workspace_root, session_path, workspace_root,
+    workspace_socket_path,
+};
+use niv_fs::{LogConfig, LogFilter};
 use std::env;
+use std::io::{self, IsTerminal};
 use std::path::PathBuf;
 
+/// What the command line asked for
+#[derive(Default)]
+struct Args {
+    /// `--listen` or `--listen=socket`; `None` inside means the workspace
+    /// socket
+    listen: Option<Option<PathBuf>>,
+    /// `--remote`: open the files in the workspace's running instance
+    remote: bool,
+    /// `--remote-send keys`
+    remote_send: Option<String>,
+    /// `--log-file path`, overriding `NIV_LOG_FILE`
+    log_file: Option<PathBuf>,
+    /// `--log filter`, overriding `NIV_LOG`
+    log_filter: Option<String>,
+    /// `--profile`: time frames and commands, printing a report on exit
+    profile: bool,
+    /// `--inline`: draw below the shell prompt instead of on the alternate screen
+    inline: bool,
+    /// `--view`: page through the files or standard input read-only, like `less`
+    view: bool,
+    /// Files to open, with the line given by a preceding `+N`
+    files: Vec<(PathBuf, Option<usize>)>,
+}
+
+fn parse_args(args: &[String]) -> Args {
+    let mut parsed = Args::default();
+    let mut line = None;
+    let mut rest = args.iter().peekable();
+    while let Some(arg) = rest.next() {
+        if let Some(socket) = arg.strip_prefix("--listen=") {
+            parsed.listen = Some(Some(PathBuf::from(socket)));
+            continue;
+        }
+        match arg.as_str() {
+            "--listen" => parsed.listen = Some(None),
+            "--remote" => parsed.remote = true,
+            "--remote-send" => parsed.remote_send = rest.next().cloned(),
+            "--log-file" => parsed.log_file = rest.next().map(PathBuf::from),
+            "--log" => parsed.log_filter = rest.next().cloned(),
+            "--profile" => parsed.profile = true,
+            "--inline" => parsed.inline = true,
+            "--view" => parsed.view = true,
+            _ => match arg.strip_prefix('+').and_then(|n| n.parse().ok()) {
+                Some(n) => line = Some(n),
+                None => parsed.files.push((PathBuf::from(arg), line.take())),
+            },
+        }
+    }
+    parsed
+}
+
+/// Start logging if a log file or filter was given on the command line or in
+/// the environment
+fn start_logging(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut config = match (&args.log_file, LogConfig::from_env()) {
+        (Some(path), env) => {
+            let mut config = LogConfig::new(path);
+            if let Some(env) = env {
+                config.filter = env.filter;
+            }
+            config
+        }
+        (None, Some(env)) => env,
+        (None, None) if args.log_filter.is_some() => LogConfig::new(LogConfig::default_path()),
+        (None, None) => return Ok(()),
+    };
+    if let Some(filter) = &args.log_filter {
+        config.filter = LogFilter::parse(filter)?;
+    }
+    niv_fs::init_logging(config)?;
+    niv_fs::log!(Info, "editor", "niv {} starting", env!("CARGO_PKG_VERSION"));
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("NIV Editor starting...");
     let args: Vec<String> = env::args().collect();
-    println!("Args: {:?}", args);
+    let mut args = parse_args(&args[1..]);
+    start_logging(&args)?;
 
-    println!("Creating editor...");
-    let mut editor = Editor::new();
-    println!("Editor created successfully");
-
-    // Open file if provided as argument
-    if args.len() > 1 {
-        let file_path = PathBuf::from(&args[1]);
-
-        if file_path.exists() {
-            // Load file using niv_fs
-            match load_file(&file_path) {
-                Ok(load_result) => {
-                    let line_count = load_result.content.lines().count();
-                    // Pass the loaded content to the editor
-                    editor.open_buffer_from_content(file_path.clone(), load_result)?;
-                    println!(
-                        "Loaded file: {} ({} lines)",
-                        file_path.display(),
-                        line_count
-                    );
-                }
-                Err(e) => {
-                    eprintln!("Failed to load file '{}': {}", file_path.display(), e);
-                    return Err(e.into());
+    // Instances are found by workspace, so `--remote` reaches the editor
+    // started in the same project
+    let cwd = env::current_dir()?;
+    let workspace_socket = workspace_socket_path(&workspace_root(&cwd));
+
+    if let Some(keys) = &args.remote_send {
+        let mut client = RpcClient::connect(&workspace_socket)
+            .map_err(|e| format!("no niv server for this workspace: {}", e))?;
+        client.call("input", JsonValue::object([("keys", keys.as_str().into())]))?;
+        return Ok(());
+    }
+    if args.remote {
+        match RpcClient::connect(&workspace_socket) {
+            Ok(mut client) => {
+                for (file, line) in &args.files {
+                    let path = cwd.join(file).display().to_string();
+                    let params =
+                        JsonValue::object([("path", path.into()), ("line", (*line).into())]);
+                    client.call("open", params)?;
                 }
+                return Ok(());
             }
-        } else {
-            // For new files, create an empty buffer
-            editor.create_new_buffer(file_path.clone())?;
-            println!("Created new file: {}", file_path.display());
+            // No instance yet: become the one later `--remote` calls reach
+            Err(_) => args.listen = Some(None),
+        }
+    }
+
+    let mut editor = Editor::new();
+
+    if let Some(socket) = args.listen {
+        let socket = socket.unwrap_or(workspace_socket);
+        match editor.listen(&socket) {
+            Ok(()) => niv_fs::log!(Info, "rpc", "listening on {}", socket.display()),
+            Err(e) if args.remote => eprintln!("Can't listen on {}: {}", socket.display(), e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if args.view {
+        editor.start_pager();
+        // `-`, or no files with input piped in, pages standard input
+        let stdin_only = args.files.iter().all(|(file, _)| file.as_os_str() == "-");
+        if stdin_only && (!args.files.is_empty() || !io::stdin().is_terminal()) {
+            args.files.clear();
+            editor.view_stream("[stdin]", io::stdin());
+        }
+    }
+
+    // Before the files open, so they open where the cursor last was
+    if editor.config().editor.shada && !args.view {
+        editor.start_shada(niv_fs::paths::state_dir().join("shada"));
+    }
+
+    // Open the files given as arguments; they become the argument list
+    let opened_files = !args.files.is_empty();
+    if opened_files {
+        niv_fs::log!(Info, "editor", "opening {} file(s)", args.files.len());
+        let line = args.files.first().and_then(|(_, line)| *line);
+        let files: Vec<PathBuf> = args.files.into_iter().map(|(file, _)| file).collect();
+        editor.open_args(files.clone());
+        if line.is_some() {
+            editor.open_file_at(&files[0], line);
         }
     }
 
+    // Inside a project, save its session on exit; started without files,
+    // offer to restore the last one
+    let auto_session = editor.config().editor.auto_session && !args.view;
+    if let Some(root) = find_workspace_root(&cwd).filter(|_| auto_session) {
+        editor.start_auto_session(session_path(&root), !opened_files);
+    }
+
+    if args.profile {
+        editor.start_profiling();
+    }
+    if args.inline {
+        editor.set_inline(true);
+    }
+
     // Run the TUI editor
     editor.run()?;
 
+    if let Some(report) = editor.profile_report() {
+        // A pager writes nothing but the screen to standard output
+        if args.view {
+            eprint!("{}", report);
+        } else {
+            print!("{}", report);
+        }
+    }
+
     Ok(())
 }